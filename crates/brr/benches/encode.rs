@@ -0,0 +1,39 @@
+//! Compares the sequential and parallel BRR encoders
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use brr::{encode_brr, encode_brr_parallel, DEFAULT_EVALUATOR};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// 4000 BRR blocks of noisy sine waves
+fn test_samples() -> Vec<i16> {
+    (0..4000 * 16_u32)
+        .map(|i| {
+            let t = f64::from(i);
+            let s = f64::sin(t * 0.07) * 12000.0 + f64::sin(t * 0.91) * 6000.0;
+            (s + f64::from(i.wrapping_mul(2654435761) >> 22) * 8.0) as i16
+        })
+        .collect()
+}
+
+fn encode_benchmark(c: &mut Criterion) {
+    let samples = test_samples();
+
+    let mut group = c.benchmark_group("encode_brr");
+    group.sample_size(10);
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| encode_brr(black_box(&samples), DEFAULT_EVALUATOR, None, None, None))
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| encode_brr_parallel(black_box(&samples), DEFAULT_EVALUATOR, None, None, None))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, encode_benchmark);
+criterion_main!(benches);
@@ -4,7 +4,7 @@
 //
 // SPDX-License-Identifier: MIT
 
-use crate::{BRR_HEADER_END_FLAG, BYTES_PER_BRR_BLOCK, SAMPLES_PER_BLOCK};
+use crate::{BRR_HEADER_END_FLAG, BRR_HEADER_LOOP_FLAG, BYTES_PER_BRR_BLOCK, SAMPLES_PER_BLOCK};
 
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct I15Sample {
@@ -165,6 +165,43 @@ pub fn decode_brr_data(brr_data: &[u8]) -> Vec<i16> {
     out
 }
 
+/// Decodes `n_samples` samples of BRR data, following the block header flags like the S-DSP.
+///
+/// When a block with the end flag is decoded, decoding continues at the `loop_offset` byte
+/// offset if the loop flag is also set.  The filter state is not reset at the loop point.
+/// Samples after a non-looping end block (or after an invalid `loop_offset`) are silent.
+pub fn decode_brr(brr_data: &[u8], loop_offset: Option<u16>, n_samples: usize) -> Vec<i16> {
+    let mut out = Vec::with_capacity(n_samples);
+
+    let mut p1 = 0;
+    let mut p2 = 0;
+    let mut index = 0;
+
+    while out.len() < n_samples {
+        let block = match brr_data.get(index..index + BYTES_PER_BRR_BLOCK) {
+            Some(b) => b,
+            None => break,
+        };
+
+        let samples = decode_brr_block(block.try_into().unwrap(), p1, p2);
+        p1 = samples[SAMPLES_PER_BLOCK - 1];
+        p2 = samples[SAMPLES_PER_BLOCK - 2];
+        out.extend(samples);
+
+        index += BYTES_PER_BRR_BLOCK;
+
+        if block[0] & BRR_HEADER_END_FLAG != 0 {
+            match (block[0] & BRR_HEADER_LOOP_FLAG != 0, loop_offset) {
+                (true, Some(lo)) if usize::from(lo) % BYTES_PER_BRR_BLOCK == 0 => index = lo.into(),
+                _ => break,
+            }
+        }
+    }
+
+    out.resize(n_samples, 0);
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,4 +330,63 @@ mod tests {
             ]
         );
     }
+
+    #[rustfmt::skip]
+    #[allow(clippy::identity_op)]
+    const LOOPING_BRR_DATA: [u8; 3 * BYTES_PER_BRR_BLOCK] = [
+        (10 << 4) | (0 << 2), 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+        ( 9 << 4) | (1 << 2), 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+        ( 8 << 4) | (2 << 2) | 0x03, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+    ];
+
+    fn block(i: usize) -> &'static [u8; BYTES_PER_BRR_BLOCK] {
+        LOOPING_BRR_DATA[i * BYTES_PER_BRR_BLOCK..][..BYTES_PER_BRR_BLOCK]
+            .try_into()
+            .unwrap()
+    }
+
+    fn last2(s: &[i16]) -> (i16, i16) {
+        (s[s.len() - 1], s[s.len() - 2])
+    }
+
+    #[test]
+    fn decode_brr_loop() {
+        let b0 = decode_brr_block(block(0), 0, 0);
+        let (p1, p2) = last2(&b0);
+        let b1 = decode_brr_block(block(1), p1, p2);
+        let (p1, p2) = last2(&b1);
+        let b2 = decode_brr_block(block(2), p1, p2);
+        // Loops to block 1 without resetting the filter
+        let (p1, p2) = last2(&b2);
+        let b3 = decode_brr_block(block(1), p1, p2);
+        let (p1, p2) = last2(&b3);
+        let b4 = decode_brr_block(block(2), p1, p2);
+
+        let expected = [b0, b1, b2, b3, b4].concat();
+
+        let decoded = decode_brr(&LOOPING_BRR_DATA, Some(9), 75);
+        assert_eq!(decoded, expected[..75]);
+
+        assert_eq!(decode_brr(&LOOPING_BRR_DATA, Some(9), 0), []);
+    }
+
+    #[test]
+    fn decode_brr_no_loop() {
+        let mut expected = decode_brr_data(&LOOPING_BRR_DATA);
+        expected.resize(80, 0);
+
+        // No loop offset
+        assert_eq!(decode_brr(&LOOPING_BRR_DATA, None, 80), expected);
+        // Invalid loop offset
+        assert_eq!(decode_brr(&LOOPING_BRR_DATA, Some(10), 80), expected);
+
+        // No loop flag
+        let mut data = LOOPING_BRR_DATA;
+        data[2 * BYTES_PER_BRR_BLOCK] &= !BRR_HEADER_LOOP_FLAG;
+        assert_eq!(decode_brr(&data, Some(9), 80), expected);
+
+        // No end flag
+        data[2 * BYTES_PER_BRR_BLOCK] &= !BRR_HEADER_END_FLAG;
+        assert_eq!(decode_brr(&data, Some(9), 80), expected);
+    }
 }
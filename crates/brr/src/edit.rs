@@ -0,0 +1,319 @@
+//! BRR sample trim and concat
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::decoder::decode_brr_data;
+use crate::encoder::encode_filter0_block;
+use crate::{
+    BrrSample, BRR_HEADER_END_FLAG, BRR_HEADER_LOOP_FLAG, BYTES_PER_BRR_BLOCK, SAMPLES_PER_BLOCK,
+};
+
+const BRR_HEADER_FILTER_MASK: u8 = 0b1100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrimError {
+    InvalidRange(usize, usize, usize),
+    LoopOutOfRange(usize),
+}
+
+impl std::fmt::Display for TrimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrimError::InvalidRange(start, end, n_blocks) => write!(
+                f,
+                "invalid block range ({start}..{end}, sample has {n_blocks} blocks)"
+            ),
+            TrimError::LoopOutOfRange(block) => {
+                write!(f, "loop point (block {block}) is outside the trimmed range")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConcatError {
+    NoSamples,
+    TooLarge(usize),
+}
+
+impl std::fmt::Display for ConcatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConcatError::NoSamples => write!(f, "no samples"),
+            ConcatError::TooLarge(size) => write!(
+                f,
+                "concatenated sample is too large ({size} bytes, max {})",
+                u16::MAX
+            ),
+        }
+    }
+}
+
+/// Clears the end and loop flags of every block except the last.
+fn fix_end_flags(brr_data: &mut [u8], looping: bool) {
+    let n_blocks = brr_data.len() / BYTES_PER_BRR_BLOCK;
+
+    for (i, block) in brr_data.chunks_exact_mut(BYTES_PER_BRR_BLOCK).enumerate() {
+        let header = &mut block[0];
+        *header &= !(BRR_HEADER_END_FLAG | BRR_HEADER_LOOP_FLAG);
+
+        if i + 1 == n_blocks {
+            *header |= BRR_HEADER_END_FLAG;
+            if looping {
+                *header |= BRR_HEADER_LOOP_FLAG;
+            }
+        }
+    }
+}
+
+/// Re-encodes the first block of `brr_data` with filter 0.
+///
+/// Filters 1-3 use the last two samples of the previous block, which are different after
+/// the block is moved to the start of a sample (or after another sample).
+/// `decoded` is the block's samples when decoded in its original position.
+fn reset_first_block_filter(brr_data: &mut [u8], decoded: &[i16]) {
+    if brr_data.len() < BYTES_PER_BRR_BLOCK
+        || decoded.len() < SAMPLES_PER_BLOCK
+        || brr_data[0] & BRR_HEADER_FILTER_MASK == 0
+    {
+        return;
+    }
+
+    let samples = decoded[..SAMPLES_PER_BLOCK].try_into().unwrap();
+    brr_data[..BYTES_PER_BRR_BLOCK].copy_from_slice(&encode_filter0_block(samples));
+}
+
+impl BrrSample {
+    /// Extracts the blocks in `start_block..end_block`.
+    ///
+    /// A looping sample must have its loop point inside the trimmed range.
+    ///
+    /// The first block is re-encoded with filter 0 if it uses a different filter.
+    pub fn trim(&self, start_block: usize, end_block: usize) -> Result<BrrSample, TrimError> {
+        let n_blocks = self.n_brr_blocks();
+
+        if start_block >= end_block || end_block > n_blocks {
+            return Err(TrimError::InvalidRange(start_block, end_block, n_blocks));
+        }
+
+        let loop_offset = match self.loop_offset {
+            Some(lo) => {
+                let loop_block = usize::from(lo) / BYTES_PER_BRR_BLOCK;
+                if loop_block < start_block || loop_block >= end_block {
+                    return Err(TrimError::LoopOutOfRange(loop_block));
+                }
+                let lo = (loop_block - start_block) * BYTES_PER_BRR_BLOCK;
+                Some(lo.try_into().unwrap())
+            }
+            None => None,
+        };
+
+        let mut brr_data = self.brr_data
+            [start_block * BYTES_PER_BRR_BLOCK..end_block * BYTES_PER_BRR_BLOCK]
+            .to_vec();
+        if start_block > 0 {
+            let decoded = decode_brr_data(&self.brr_data);
+            reset_first_block_filter(&mut brr_data, &decoded[start_block * SAMPLES_PER_BLOCK..]);
+        }
+        fix_end_flags(&mut brr_data, loop_offset.is_some());
+
+        Ok(BrrSample {
+            loop_offset,
+            brr_data,
+        })
+    }
+
+    /// Joins the BRR data of multiple samples.
+    ///
+    /// The output loops at the first sample's loop point if every sample is looping,
+    /// otherwise the output does not loop.
+    ///
+    /// The first block of every joined sample is re-encoded with filter 0 if it uses a
+    /// different filter.
+    pub fn concat(samples: &[&BrrSample]) -> Result<BrrSample, ConcatError> {
+        let first = samples.first().ok_or(ConcatError::NoSamples)?;
+
+        let size = samples.iter().map(|s| s.brr_data.len()).sum();
+        if size > u16::MAX.into() {
+            return Err(ConcatError::TooLarge(size));
+        }
+
+        let loop_offset = match samples.iter().all(|s| s.is_looping()) {
+            true => first.loop_offset,
+            false => None,
+        };
+
+        let mut brr_data = Vec::with_capacity(size);
+        for s in samples {
+            let start = brr_data.len();
+            brr_data.extend_from_slice(&s.brr_data);
+
+            if start > 0 {
+                let decoded = decode_brr_data(&s.brr_data);
+                reset_first_block_filter(&mut brr_data[start..], &decoded);
+            }
+        }
+        fix_end_flags(&mut brr_data, loop_offset.is_some());
+
+        Ok(BrrSample {
+            loop_offset,
+            brr_data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{encode_brr, DEFAULT_EVALUATOR};
+
+    fn sine(n_samples: usize, period: f64) -> Vec<i16> {
+        (0..n_samples)
+            .map(|i| (f64::sin(i as f64 * std::f64::consts::TAU / period) * 12000.0) as i16)
+            .collect()
+    }
+
+    fn filters(s: &BrrSample) -> Vec<u8> {
+        s.brr_data()
+            .chunks_exact(BYTES_PER_BRR_BLOCK)
+            .map(|b| (b[0] & BRR_HEADER_FILTER_MASK) >> 2)
+            .collect()
+    }
+
+    fn max_error(a: &[i16], b: &[i16]) -> i32 {
+        assert_eq!(a.len(), b.len());
+        a.iter()
+            .zip(b)
+            .map(|(&a, &b)| (i32::from(a) - i32::from(b)).abs())
+            .max()
+            .unwrap()
+    }
+
+    fn headers(s: &BrrSample) -> Vec<u8> {
+        s.brr_data()
+            .chunks_exact(BYTES_PER_BRR_BLOCK)
+            .map(|b| b[0] & (BRR_HEADER_END_FLAG | BRR_HEADER_LOOP_FLAG))
+            .collect()
+    }
+
+    #[test]
+    fn trim_round_trip() {
+        let s = encode_brr(&sine(128, 32.0), DEFAULT_EVALUATOR, Some(32), None, None).unwrap();
+        assert_eq!(s.n_brr_blocks(), 8);
+
+        let t = s.trim(0, 4).unwrap();
+        assert_eq!(t.loop_offset(), Some(2 * BYTES_PER_BRR_BLOCK as u16));
+        assert_eq!(headers(&t), [0, 0, 0, 3]);
+
+        let decoded = decode_brr_data(s.brr_data());
+        assert_eq!(
+            decode_brr_data(t.brr_data()),
+            decoded[..4 * SAMPLES_PER_BLOCK]
+        );
+
+        let t = s.trim(2, 8).unwrap();
+        assert_eq!(t.loop_offset(), Some(0));
+        assert_eq!(t.n_brr_blocks(), 6);
+        assert_eq!(headers(&t), [0, 0, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn trim_resets_filter() {
+        let s = encode_brr(&sine(128, 32.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+        assert_ne!(filters(&s)[3], 0);
+
+        let t = s.trim(3, 8).unwrap();
+        assert_eq!(filters(&t)[0], 0);
+        assert_eq!(filters(&t)[1..], filters(&s)[4..]);
+
+        let decoded = decode_brr_data(s.brr_data());
+        let t_decoded = decode_brr_data(t.brr_data());
+        assert_eq!(t_decoded.len(), 5 * SAMPLES_PER_BLOCK);
+
+        let err = max_error(
+            &t_decoded[..SAMPLES_PER_BLOCK],
+            &decoded[3 * SAMPLES_PER_BLOCK..4 * SAMPLES_PER_BLOCK],
+        );
+        assert!(err < 1024, "{err}");
+    }
+
+    #[test]
+    fn trim_errors() {
+        let s = encode_brr(&sine(128, 32.0), DEFAULT_EVALUATOR, Some(32), None, None).unwrap();
+
+        assert_eq!(s.trim(3, 8).err(), Some(TrimError::LoopOutOfRange(2)));
+        assert_eq!(s.trim(0, 2).err(), Some(TrimError::LoopOutOfRange(2)));
+        assert_eq!(s.trim(4, 4).err(), Some(TrimError::InvalidRange(4, 4, 8)));
+        assert_eq!(s.trim(0, 9).err(), Some(TrimError::InvalidRange(0, 9, 8)));
+
+        let s = encode_brr(&sine(128, 32.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+        let t = s.trim(3, 8).unwrap();
+        assert_eq!(t.loop_offset(), None);
+        assert_eq!(headers(&t), [0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn concat_round_trip() {
+        let a = encode_brr(&sine(64, 32.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+        let b = encode_brr(&sine(96, 48.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+
+        let c = BrrSample::concat(&[&a, &b]).unwrap();
+        assert_eq!(c.loop_offset(), None);
+        assert_eq!(c.n_brr_blocks(), a.n_brr_blocks() + b.n_brr_blocks());
+        assert_eq!(headers(&c), [0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let decoded = decode_brr_data(c.brr_data());
+        assert_eq!(decoded.len(), 160);
+        assert_eq!(decoded[..64], decode_brr_data(a.brr_data()));
+
+        // Splitting the concatenated sample restores the first sample
+        assert!(c.trim(0, a.n_brr_blocks()).unwrap() == a);
+    }
+
+    #[test]
+    fn concat_resets_filter() {
+        let a = encode_brr(&sine(64, 32.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+        let s = encode_brr(&sine(128, 48.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+        assert_ne!(filters(&s)[2], 0);
+
+        // A sample that starts with a filter 1-3 block
+        let b = BrrSample {
+            loop_offset: None,
+            brr_data: s.brr_data()[2 * BYTES_PER_BRR_BLOCK..].to_vec(),
+        };
+        let b_decoded = decode_brr_data(b.brr_data());
+
+        let c = BrrSample::concat(&[&a, &b]).unwrap();
+        assert_eq!(filters(&c)[a.n_brr_blocks()], 0);
+
+        // Decode across the join
+        let decoded = decode_brr_data(c.brr_data());
+        assert_eq!(decoded[..64], decode_brr_data(a.brr_data()));
+        assert_eq!(decoded.len(), 64 + b_decoded.len());
+        let err = max_error(
+            &decoded[64..64 + SAMPLES_PER_BLOCK],
+            &b_decoded[..SAMPLES_PER_BLOCK],
+        );
+        assert!(err < 1024, "{err}");
+    }
+
+    #[test]
+    fn concat_loop_offset() {
+        let a = encode_brr(&sine(64, 32.0), DEFAULT_EVALUATOR, Some(16), None, None).unwrap();
+        let b = encode_brr(&sine(64, 32.0), DEFAULT_EVALUATOR, Some(0), None, None).unwrap();
+        let n = encode_brr(&sine(64, 32.0), DEFAULT_EVALUATOR, None, None, None).unwrap();
+
+        let c = BrrSample::concat(&[&a, &b]).unwrap();
+        assert_eq!(c.loop_offset(), a.loop_offset());
+        assert_eq!(headers(&c), [0, 0, 0, 0, 0, 0, 0, 3]);
+
+        let c = BrrSample::concat(&[&a, &n]).unwrap();
+        assert_eq!(c.loop_offset(), None);
+        assert_eq!(headers(&c), [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        assert_eq!(BrrSample::concat(&[]).err(), Some(ConcatError::NoSamples));
+    }
+}
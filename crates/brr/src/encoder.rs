@@ -25,6 +25,7 @@ pub enum EncodeError {
     DupeBlockHackNotAllowedWithLoopPoint,
     DupeBlockHackNotAllowedWithLoopResetsFilter,
     DupeBlockHackTooLarge,
+    ClippedSamples { count: usize },
 }
 
 impl std::fmt::Display for EncodeError {
@@ -55,6 +56,9 @@ impl std::fmt::Display for EncodeError {
                 )
             }
             EncodeError::DupeBlockHackTooLarge => write!(f, "dupe_block_hack value is too large"),
+            EncodeError::ClippedSamples { count } => {
+                write!(f, "{count} samples are outside the range -1.0 to 1.0")
+            }
         }
     }
 }
@@ -254,6 +258,131 @@ fn find_best_block_filter<S: Scorer>(
     }
 }
 
+/// Returns the best block for the block at index `i`.
+fn find_block<S: Scorer>(
+    i: usize,
+    samples: &[I15Sample; SAMPLES_PER_BLOCK],
+    loop_block: usize,
+    loop_filter: Option<BrrFilter>,
+    prev1: I15Sample,
+    prev2: I15Sample,
+) -> BrrBlock {
+    if i == 0 {
+        // The first block always uses filter 0
+        find_best_block_filter::<S>(samples, BrrFilter::Filter0, prev1, prev2)
+    } else if i == loop_block {
+        match loop_filter {
+            None => find_best_block::<S>(samples, prev1, prev2),
+            Some(loop_filter) => find_best_block_filter::<S>(samples, loop_filter, prev1, prev2),
+        }
+    } else {
+        find_best_block::<S>(samples, prev1, prev2)
+    }
+}
+
+type FindBlocksFn =
+    fn(&[[I15Sample; SAMPLES_PER_BLOCK]], usize, Option<BrrFilter>) -> Vec<BrrBlock>;
+
+fn find_blocks<S: Scorer>(
+    inputs: &[[I15Sample; SAMPLES_PER_BLOCK]],
+    loop_block: usize,
+    loop_filter: Option<BrrFilter>,
+) -> Vec<BrrBlock> {
+    let mut prev1 = I15Sample::default();
+    let mut prev2 = I15Sample::default();
+
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, samples)| {
+            let block = find_block::<S>(i, samples, loop_block, loop_filter, prev1, prev2);
+
+            prev1 = block.decoded_samples[SAMPLES_PER_BLOCK - 1];
+            prev2 = block.decoded_samples[SAMPLES_PER_BLOCK - 2];
+
+            block
+        })
+        .collect()
+}
+
+/// Number of blocks in each `find_blocks_parallel()` task
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 128;
+
+/// Encodes chunks of blocks on different threads.
+///
+/// Each block depends on the last two decoded samples of the previous block.
+/// The first block of each chunk is encoded using the source samples of the previous block,
+/// then a serial fixup pass re-encodes every block that was encoded with the wrong previous
+/// samples.  The fixup pass typically only re-encodes the first few blocks of each chunk.
+///
+/// Falls back to `find_blocks()` if there is only one thread.
+///
+/// Returns the same blocks as `find_blocks()`.
+#[cfg(feature = "parallel")]
+fn find_blocks_parallel<S: Scorer>(
+    inputs: &[[I15Sample; SAMPLES_PER_BLOCK]],
+    loop_block: usize,
+    loop_filter: Option<BrrFilter>,
+) -> Vec<BrrBlock> {
+    use rayon::prelude::*;
+
+    const LAST: usize = SAMPLES_PER_BLOCK - 1;
+
+    if rayon::current_num_threads() <= 1 {
+        return find_blocks::<S>(inputs, loop_block, loop_filter);
+    }
+
+    // Each block is stored with the previous samples it was encoded with
+    let speculative: Vec<Vec<(BrrBlock, I15Sample, I15Sample)>> = inputs
+        .par_chunks(PARALLEL_CHUNK_SIZE)
+        .enumerate()
+        .map(|(c, chunk)| {
+            let first = c * PARALLEL_CHUNK_SIZE;
+
+            let (mut prev1, mut prev2) = match first.checked_sub(1) {
+                Some(p) => (inputs[p][LAST], inputs[p][LAST - 1]),
+                None => (I15Sample::default(), I15Sample::default()),
+            };
+
+            chunk
+                .iter()
+                .enumerate()
+                .map(|(j, samples)| {
+                    let block =
+                        find_block::<S>(first + j, samples, loop_block, loop_filter, prev1, prev2);
+                    let out = (block, prev1, prev2);
+
+                    prev1 = out.0.decoded_samples[LAST];
+                    prev2 = out.0.decoded_samples[LAST - 1];
+
+                    out
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut prev1 = I15Sample::default();
+    let mut prev2 = I15Sample::default();
+
+    speculative
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, (block, p1, p2))| {
+            let block = match (p1, p2) == (prev1, prev2) {
+                true => block,
+                false => find_block::<S>(i, &inputs[i], loop_block, loop_filter, prev1, prev2),
+            };
+
+            prev1 = block.decoded_samples[LAST];
+            prev2 = block.decoded_samples[LAST - 1];
+
+            block
+        })
+        .collect()
+}
+
 // Loop flag only set if end_flag is set.
 fn encode_block(block: BrrBlock, end_flag: bool, loop_flag: bool) -> [u8; BYTES_PER_BRR_BLOCK] {
     assert!(block.shift <= MAX_SHIFT);
@@ -282,12 +411,88 @@ fn encode_block(block: BrrBlock, end_flag: bool, loop_flag: bool) -> [u8; BYTES_
     out
 }
 
+/// Encodes a single filter 0 BRR block (without the end or loop flags).
+///
+/// A filter 0 block does not depend on the previous block's samples.
+pub(crate) fn encode_filter0_block(
+    samples: &[i16; SAMPLES_PER_BLOCK],
+) -> [u8; BYTES_PER_BRR_BLOCK] {
+    let samples = samples.map(I15Sample::from_sample);
+    let block = find_best_block_filter::<SquaredErrorAvoidGaussianOverflow>(
+        &samples,
+        BrrFilter::Filter0,
+        I15Sample::default(),
+        I15Sample::default(),
+    );
+
+    encode_block(block, false, false)
+}
+
+/// A BRR sample and the quality of the encoding.
+///
+/// The error statistics compare the decoded BRR samples with the input samples.
+#[derive(Clone)]
+pub struct BrrEncodeResult {
+    pub sample: BrrSample,
+
+    pub mean_squared_error: f64,
+    pub peak_absolute_error: i32,
+    /// Signal-to-noise ratio in decibels (infinite if the encoding is lossless)
+    pub snr_db: f64,
+
+    /// The number of blocks encoded with each BRR filter
+    pub blocks_used_filter: [u32; 4],
+}
+
+#[derive(Default)]
+struct StatsAccumulator {
+    n_samples: u64,
+    signal_power: f64,
+    squared_error: f64,
+    peak_absolute_error: i32,
+    blocks_used_filter: [u32; 4],
+}
+
+impl StatsAccumulator {
+    fn add_block(&mut self, samples: &[i16; SAMPLES_PER_BLOCK], block: &BrrBlock) {
+        self.blocks_used_filter[usize::from(block.filter.as_u8())] += 1;
+
+        for (s, d) in samples.iter().zip(block.decoded_samples) {
+            let s = i32::from(*s);
+            let error = s - i32::from(d.to_sample());
+
+            self.n_samples += 1;
+            self.signal_power += f64::from(s * s);
+            self.squared_error += f64::from(error * error);
+            self.peak_absolute_error = self.peak_absolute_error.max(error.abs());
+        }
+    }
+
+    fn finish(self, sample: BrrSample) -> BrrEncodeResult {
+        let n_samples = self.n_samples as f64;
+
+        let snr_db = match self.squared_error > 0.0 {
+            true => 10.0 * f64::log10(self.signal_power / self.squared_error),
+            false => f64::INFINITY,
+        };
+
+        BrrEncodeResult {
+            sample,
+            mean_squared_error: self.squared_error / n_samples,
+            peak_absolute_error: self.peak_absolute_error,
+            snr_db,
+            blocks_used_filter: self.blocks_used_filter,
+        }
+    }
+}
+
 fn encode_brr_with_scorer<S: Scorer>(
     samples: &[i16],
     loop_offset: Option<usize>,
     dupe_block_hack: Option<usize>,
     loop_filter: Option<BrrFilter>,
-) -> Result<BrrSample, EncodeError> {
+    find_blocks_fn: FindBlocksFn,
+) -> Result<BrrEncodeResult, EncodeError> {
     if samples.is_empty() {
         return Err(EncodeError::NoSamples);
     }
@@ -341,34 +546,25 @@ fn encode_brr_with_scorer<S: Scorer>(
 
     let mut brr_data = Vec::with_capacity(n_blocks * BYTES_PER_BRR_BLOCK);
 
-    let mut prev1 = I15Sample::default();
-    let mut prev2 = I15Sample::default();
-
-    for (i, samples) in samples
+    let sources: Vec<&[i16; SAMPLES_PER_BLOCK]> = samples
         .chunks_exact(SAMPLES_PER_BLOCK)
         .cycle()
         .take(n_blocks)
-        .enumerate()
-    {
-        let samples: [i16; SAMPLES_PER_BLOCK] = samples.try_into().unwrap();
-        let samples = samples.map(I15Sample::from_sample);
-
-        let block = if i == 0 {
-            // The first block always uses filter 0
-            find_best_block_filter::<S>(&samples, BrrFilter::Filter0, prev1, prev2)
-        } else if i == loop_block {
-            match loop_filter {
-                None => find_best_block::<S>(&samples, prev1, prev2),
-                Some(loop_filter) => {
-                    find_best_block_filter::<S>(&samples, loop_filter, prev1, prev2)
-                }
-            }
-        } else {
-            find_best_block::<S>(&samples, prev1, prev2)
-        };
+        .map(|s| s.try_into().unwrap())
+        .collect();
 
-        prev1 = block.decoded_samples[SAMPLES_PER_BLOCK - 1];
-        prev2 = block.decoded_samples[SAMPLES_PER_BLOCK - 2];
+    let inputs: Vec<_> = sources
+        .iter()
+        .map(|s| s.map(I15Sample::from_sample))
+        .collect();
+
+    let blocks = find_blocks_fn(&inputs, loop_block, loop_filter);
+    assert_eq!(blocks.len(), n_blocks);
+
+    let mut stats = StatsAccumulator::default();
+
+    for (i, (source, block)) in sources.into_iter().zip(blocks).enumerate() {
+        stats.add_block(source, &block);
 
         brr_data.extend(encode_block(block, i == last_block_index, loop_flag));
     }
@@ -377,10 +573,10 @@ fn encode_brr_with_scorer<S: Scorer>(
         assert!(usize::from(lo) < brr_data.len());
     }
 
-    Ok(BrrSample {
+    Ok(stats.finish(BrrSample {
         loop_offset,
         brr_data,
-    })
+    }))
 }
 
 pub enum Evaluator {
@@ -397,12 +593,31 @@ pub fn encode_brr(
     dupe_block_hack: Option<usize>,
     loop_filter: Option<BrrFilter>,
 ) -> Result<BrrSample, EncodeError> {
+    encode_brr_with_stats(
+        samples,
+        evaluator,
+        loop_offset,
+        dupe_block_hack,
+        loop_filter,
+    )
+    .map(|r| r.sample)
+}
+
+/// Encodes a BRR sample and calculates the quality of the encoding.
+pub fn encode_brr_with_stats(
+    samples: &[i16],
+    evaluator: Evaluator,
+    loop_offset: Option<usize>,
+    dupe_block_hack: Option<usize>,
+    loop_filter: Option<BrrFilter>,
+) -> Result<BrrEncodeResult, EncodeError> {
     match evaluator {
         Evaluator::SquaredError => encode_brr_with_scorer::<SquaredError>(
             samples,
             loop_offset,
             dupe_block_hack,
             loop_filter,
+            find_blocks::<SquaredError>,
         ),
         Evaluator::SquaredErrorAvoidGaussianOverflow => {
             encode_brr_with_scorer::<SquaredErrorAvoidGaussianOverflow>(
@@ -410,11 +625,206 @@ pub fn encode_brr(
                 loop_offset,
                 dupe_block_hack,
                 loop_filter,
+                find_blocks::<SquaredErrorAvoidGaussianOverflow>,
             )
         }
     }
 }
 
+/// Multi-threaded version of `encode_brr()`.
+///
+/// The output is identical to `encode_brr()`.
+#[cfg(feature = "parallel")]
+pub fn encode_brr_parallel(
+    samples: &[i16],
+    evaluator: Evaluator,
+    loop_offset: Option<usize>,
+    dupe_block_hack: Option<usize>,
+    loop_filter: Option<BrrFilter>,
+) -> Result<BrrSample, EncodeError> {
+    let r = match evaluator {
+        Evaluator::SquaredError => encode_brr_with_scorer::<SquaredError>(
+            samples,
+            loop_offset,
+            dupe_block_hack,
+            loop_filter,
+            find_blocks_parallel::<SquaredError>,
+        ),
+        Evaluator::SquaredErrorAvoidGaussianOverflow => {
+            encode_brr_with_scorer::<SquaredErrorAvoidGaussianOverflow>(
+                samples,
+                loop_offset,
+                dupe_block_hack,
+                loop_filter,
+                find_blocks_parallel::<SquaredErrorAvoidGaussianOverflow>,
+            )
+        }
+    };
+    r.map(|r| r.sample)
+}
+
+/// Options for converting `f32` samples in `encode_brr_f32()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Subtract the mean of the samples before converting
+    pub remove_dc_offset: bool,
+    /// Scale the samples so the peak amplitude is 1.0
+    pub normalize: bool,
+    /// Return `EncodeError::ClippedSamples` instead of clamping samples outside `-1.0..=1.0`
+    pub warn_on_clip: bool,
+}
+
+fn mean(samples: &[f32]) -> f32 {
+    match samples.is_empty() {
+        true => 0.0,
+        false => samples.iter().sum::<f32>() / samples.len() as f32,
+    }
+}
+
+fn i16_to_f32_samples(samples: &[i16]) -> Vec<f32> {
+    samples
+        .iter()
+        .map(|&s| f32::from(s) / f32::from(i16::MAX))
+        .collect()
+}
+
+fn f32_to_i16_sample(s: f32) -> i16 {
+    // `as` saturates and converts NaN to 0
+    (s * f32::from(i16::MAX)).round() as i16
+}
+
+/// Returns the DC offset to subtract and the amount to scale the samples by
+fn dc_offset_and_scale(samples: &[f32], options: EncodeOptions) -> (f32, f32) {
+    let dc_offset = match options.remove_dc_offset {
+        true => mean(samples),
+        false => 0.0,
+    };
+
+    let scale = match options.normalize {
+        true => {
+            let peak = samples
+                .iter()
+                .map(|s| (s - dc_offset).abs())
+                .filter(|s| s.is_finite())
+                .fold(0.0, f32::max);
+            match peak > 0.0 {
+                true => 1.0 / peak,
+                false => 1.0,
+            }
+        }
+        false => 1.0,
+    };
+
+    (dc_offset, scale)
+}
+
+fn f32_to_i16_samples(samples: &[f32], options: EncodeOptions) -> Result<Vec<i16>, EncodeError> {
+    let (dc_offset, scale) = dc_offset_and_scale(samples, options);
+
+    let samples = samples.iter().map(|s| (s - dc_offset) * scale);
+
+    if options.warn_on_clip {
+        let count = samples
+            .clone()
+            .filter(|s| !(-1.0..=1.0).contains(s))
+            .count();
+        if count > 0 {
+            return Err(EncodeError::ClippedSamples { count });
+        }
+    }
+
+    Ok(samples.map(f32_to_i16_sample).collect())
+}
+
+/// Returns the DC offset (mean) of 16 bit samples
+pub fn dc_offset(samples: &[i16]) -> i16 {
+    f32_to_i16_sample(mean(&i16_to_f32_samples(samples)))
+}
+
+/// Removes the DC offset and/or normalizes 16 bit samples, using the same conversion as
+/// `encode_brr_f32()`.
+///
+/// `options.warn_on_clip` is ignored.
+pub fn preprocess_i16_samples(samples: &[i16], options: EncodeOptions) -> Vec<i16> {
+    let samples = i16_to_f32_samples(samples);
+    let (dc_offset, scale) = dc_offset_and_scale(&samples, options);
+
+    samples
+        .iter()
+        .map(|s| f32_to_i16_sample((s - dc_offset) * scale))
+        .collect()
+}
+
+/// Converts `f32` samples (`-1.0..=1.0`) to 16 bit samples and encodes them with the
+/// default evaluator.
+pub fn encode_brr_f32(
+    samples: &[f32],
+    options: EncodeOptions,
+    loop_offset: Option<usize>,
+    dupe_block_hack: Option<usize>,
+    loop_filter: Option<BrrFilter>,
+) -> Result<BrrSample, EncodeError> {
+    let samples = f32_to_i16_samples(samples, options)?;
+
+    encode_brr(
+        &samples,
+        DEFAULT_EVALUATOR,
+        loop_offset,
+        dupe_block_hack,
+        loop_filter,
+    )
+}
+
+/// Searches for the loop point that produces the cleanest BRR loop.
+///
+/// Every block-aligned loop point that leaves a loop of at least `min_loop_len_blocks` blocks
+/// is tried.  The whole sample is encoded (with the default evaluator) for each candidate and
+/// the loop is decoded a second time, continuing the filter state from the end of the sample.
+/// The candidate with the lowest mean squared error in the repeated loop is returned.
+///
+/// The mean is used instead of the total error so shorter loops are not favoured.
+///
+/// Returns `None` if `samples` cannot be encoded or there are no valid loop points.
+///
+/// CAUTION: Expensive, the sample is encoded once per candidate loop point.
+pub fn find_optimal_loop_point(samples: &[i16], min_loop_len_blocks: usize) -> Option<usize> {
+    if samples.is_empty() || samples.len() % SAMPLES_PER_BLOCK != 0 {
+        return None;
+    }
+
+    let min_loop_len = min_loop_len_blocks.max(1).checked_mul(SAMPLES_PER_BLOCK)?;
+    let last_loop_point = samples.len().checked_sub(min_loop_len)?;
+
+    let mut best: Option<(usize, f64)> = None;
+
+    for loop_point in (0..=last_loop_point).step_by(SAMPLES_PER_BLOCK) {
+        let brr = match encode_brr(samples, DEFAULT_EVALUATOR, Some(loop_point), None, None) {
+            Ok(brr) => brr,
+            Err(_) => continue,
+        };
+
+        let loop_len = samples.len() - loop_point;
+        let mut decoded = vec![0; samples.len() + loop_len];
+        brr.decode_into_buffer(&mut decoded, 0, 0, 0);
+
+        let error: f64 = decoded[samples.len()..]
+            .iter()
+            .zip(&samples[loop_point..])
+            .map(|(&d, &s)| {
+                let e = f64::from(d) - f64::from(s);
+                e * e
+            })
+            .sum();
+        let score = error / loop_len as f64;
+
+        if best.map_or(true, |(_, s)| score < s) {
+            best = Some((loop_point, score));
+        }
+    }
+
+    best.map(|(loop_point, _)| loop_point)
+}
+
 #[cfg(test)]
 #[allow(clippy::bool_assert_comparison)]
 mod test_avoid_gaussian_overflow {
@@ -560,3 +970,245 @@ mod test_decoded_samples {
         );
     }
 }
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+
+    use crate::decode_brr_data;
+
+    #[test]
+    fn silence_is_lossless() {
+        let r = encode_brr_with_stats(&[0; 64], DEFAULT_EVALUATOR, None, None, None).unwrap();
+
+        assert_eq!(r.mean_squared_error, 0.0);
+        assert_eq!(r.peak_absolute_error, 0);
+        assert_eq!(r.snr_db, f64::INFINITY);
+        assert_eq!(r.blocks_used_filter.iter().sum::<u32>(), 4);
+        assert_eq!(r.blocks_used_filter[0], 4);
+    }
+
+    #[test]
+    fn sine() {
+        let input: Vec<i16> = (0..256)
+            .map(|i| (f64::sin(i as f64 * std::f64::consts::TAU / 40.0) * 20000.0) as i16)
+            .collect();
+
+        let r = encode_brr_with_stats(&input, DEFAULT_EVALUATOR, Some(0), None, None).unwrap();
+
+        assert_eq!(r.blocks_used_filter.iter().sum::<u32>(), 16);
+        assert!(r.blocks_used_filter[0] >= 1);
+        assert!(r.snr_db > 30.0, "snr {}", r.snr_db);
+
+        // The statistics match the decoded sample
+        let decoded = decode_brr_data(r.sample.brr_data());
+        let errors: Vec<i32> = input
+            .iter()
+            .zip(&decoded)
+            .map(|(&s, &d)| i32::from(s) - i32::from(d))
+            .collect();
+
+        let mse = errors.iter().map(|e| f64::from(e * e)).sum::<f64>() / input.len() as f64;
+        assert!((r.mean_squared_error - mse).abs() < 1e-9);
+        assert_eq!(
+            r.peak_absolute_error,
+            errors.iter().map(|e| e.abs()).max().unwrap()
+        );
+
+        let plain = encode_brr(&input, DEFAULT_EVALUATOR, Some(0), None, None).unwrap();
+        assert!(plain == r.sample);
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod test_parallel {
+    use super::*;
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let input: Vec<i16> = (0..4096_u32)
+            .map(|i| {
+                let t = f64::from(i);
+                let s = f64::sin(t * 0.07) * 12000.0 + f64::sin(t * 0.91) * 6000.0;
+                (s + f64::from(i.wrapping_mul(2654435761) >> 22) * 8.0) as i16
+            })
+            .collect();
+
+        let evaluators = || {
+            [
+                Evaluator::SquaredError,
+                Evaluator::SquaredErrorAvoidGaussianOverflow,
+            ]
+        };
+
+        for (loop_offset, dupe_block_hack, loop_filter) in [
+            (None, None, None),
+            (Some(512), None, Some(BrrFilter::Filter0)),
+            (Some(2048), None, None),
+            (None, Some(3), Some(BrrFilter::Filter1)),
+        ] {
+            for (e1, e2) in evaluators().into_iter().zip(evaluators()) {
+                // Multiple threads are required to test the fixup pass
+                let p = rayon::ThreadPoolBuilder::new()
+                    .num_threads(4)
+                    .build()
+                    .unwrap()
+                    .install(|| {
+                        encode_brr_parallel(&input, e1, loop_offset, dupe_block_hack, loop_filter)
+                    })
+                    .unwrap();
+                let s = encode_brr(&input, e2, loop_offset, dupe_block_hack, loop_filter).unwrap();
+
+                assert!(p == s);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_f32 {
+    use super::*;
+
+    const OFF: EncodeOptions = EncodeOptions {
+        remove_dc_offset: false,
+        normalize: false,
+        warn_on_clip: false,
+    };
+
+    #[test]
+    fn conversion() {
+        let input = [0.0, 0.5, -0.5, 1.0, -1.0, 2.0, -2.0, f32::NAN];
+
+        assert_eq!(
+            f32_to_i16_samples(&input, OFF).unwrap(),
+            [0, 16384, -16384, 32767, -32767, 32767, -32768, 0]
+        );
+    }
+
+    #[test]
+    fn clipped_samples() {
+        let input = [0.0, 1.0, 1.5, -1.01, f32::NAN, 0.2];
+
+        let o = EncodeOptions {
+            warn_on_clip: true,
+            ..OFF
+        };
+        assert!(matches!(
+            f32_to_i16_samples(&input, o),
+            Err(EncodeError::ClippedSamples { count: 3 })
+        ));
+
+        let o = EncodeOptions {
+            warn_on_clip: true,
+            normalize: true,
+            ..OFF
+        };
+        assert_eq!(
+            f32_to_i16_samples(&input[..4], o).unwrap(),
+            [0, 21845, 32767, -22063]
+        );
+    }
+
+    #[test]
+    fn remove_dc_offset_and_normalize() {
+        let input = [0.5, 0.6, 0.4, 0.5];
+
+        let o = EncodeOptions {
+            remove_dc_offset: true,
+            normalize: true,
+            ..OFF
+        };
+        assert_eq!(
+            f32_to_i16_samples(&input, o).unwrap(),
+            [0, 32767, -32767, 0]
+        );
+    }
+
+    #[test]
+    fn encode_brr_f32_matches_encode_brr() {
+        let input: Vec<f32> = (0..64).map(|i| f32::sin(i as f32 * 0.3) * 0.75).collect();
+        let i16_input = f32_to_i16_samples(&input, OFF).unwrap();
+
+        let a = encode_brr_f32(&input, OFF, Some(16), None, None).unwrap();
+        let b = encode_brr(&i16_input, DEFAULT_EVALUATOR, Some(16), None, None).unwrap();
+        assert!(a == b);
+    }
+}
+
+#[cfg(test)]
+mod test_find_optimal_loop_point {
+    use super::*;
+
+    #[test]
+    fn finds_periodic_loop() {
+        const PERIOD: usize = 32;
+
+        // Noise followed by a waveform that repeats every `PERIOD` samples
+        let mut seed: u32 = 12345;
+        let mut samples: Vec<i16> = (0..64)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as i16 / 2
+            })
+            .collect();
+        samples.extend((0..PERIOD * 4).map(|i| {
+            let t = (i % PERIOD) as f64 / PERIOD as f64;
+            (f64::sin(t * std::f64::consts::TAU) * 12000.0) as i16
+        }));
+
+        let lp = find_optimal_loop_point(&samples, 2).unwrap();
+        assert!(lp >= 64, "loop point {lp} is inside the noise");
+        assert_eq!(lp % SAMPLES_PER_BLOCK, 0);
+        assert_eq!((samples.len() - lp) % PERIOD, 0);
+    }
+
+    #[test]
+    fn no_valid_loop_point() {
+        assert_eq!(find_optimal_loop_point(&[], 1), None);
+        assert_eq!(find_optimal_loop_point(&[0; 20], 1), None);
+        assert_eq!(find_optimal_loop_point(&[0; 32], 3), None);
+
+        assert_eq!(find_optimal_loop_point(&[0; 32], 2), Some(0));
+    }
+}
+
+#[cfg(test)]
+mod test_preprocess_i16_samples {
+    use super::*;
+
+    fn options(remove_dc_offset: bool, normalize: bool) -> EncodeOptions {
+        EncodeOptions {
+            remove_dc_offset,
+            normalize,
+            warn_on_clip: false,
+        }
+    }
+
+    #[test]
+    fn test_preprocess() {
+        let samples = [100, 600, -400, 100];
+
+        assert_eq!(
+            preprocess_i16_samples(&samples, options(false, false)),
+            samples
+        );
+        assert_eq!(
+            preprocess_i16_samples(&samples, options(true, false)),
+            [0, 500, -500, 0]
+        );
+        assert_eq!(
+            preprocess_i16_samples(&samples, options(false, true)),
+            [5461, 32767, -21845, 5461]
+        );
+        assert_eq!(
+            preprocess_i16_samples(&samples, options(true, true)),
+            [0, 32767, -32767, 0]
+        );
+    }
+
+    #[test]
+    fn test_dc_offset() {
+        assert_eq!(dc_offset(&[]), 0);
+        assert_eq!(dc_offset(&[100, 600, -400, 100]), 100);
+    }
+}
@@ -7,6 +7,7 @@
 #![forbid(unsafe_code)]
 
 mod decoder;
+mod edit;
 mod encoder;
 mod gaussian_overflow_test;
 mod mono_pcm_wav;
@@ -14,10 +15,22 @@ mod parse_brr_file;
 
 use std::str::FromStr;
 
-pub use decoder::decode_brr_data;
-pub use encoder::{encode_brr, EncodeError, Evaluator, DEFAULT_EVALUATOR};
+pub use decoder::{decode_brr, decode_brr_data};
+pub use edit::{ConcatError, TrimError};
+pub use encoder::{
+    dc_offset, encode_brr, encode_brr_f32, encode_brr_with_stats, find_optimal_loop_point,
+    preprocess_i16_samples, BrrEncodeResult, EncodeError, EncodeOptions, Evaluator,
+    DEFAULT_EVALUATOR,
+};
+
+#[cfg(feature = "parallel")]
+pub use encoder::encode_brr_parallel;
 use gaussian_overflow_test::test_for_gaussian_overflow_glitch;
-pub use mono_pcm_wav::{read_mono_pcm_wave_file, MonoPcm16WaveFile, WavError};
+#[cfg(feature = "test-utils")]
+pub use mono_pcm_wav::test_utils as wav_test_utils;
+pub use mono_pcm_wav::{
+    read_mono_pcm_wave_file, MonoPcm16WaveFile, WavError, WavLoopInfo, WavUnityNote,
+};
 pub use parse_brr_file::{parse_brr_file, ParseError, ValidBrrFile};
 
 pub const SAMPLES_PER_BLOCK: usize = 16;
@@ -52,6 +65,33 @@ impl BrrFilter {
     fn as_u8(&self) -> u8 {
         *self as u8
     }
+
+    /// The filter's feedback coefficients (`a1`, `a2`).
+    ///
+    /// `output[n] = input[n] + a1 * output[n - 1] + a2 * output[n - 2]`
+    pub fn coefficients(&self) -> (f64, f64) {
+        match self {
+            Self::Filter0 => (0.0, 0.0),
+            Self::Filter1 => (15.0 / 16.0, 0.0),
+            Self::Filter2 => (61.0 / 32.0, -15.0 / 16.0),
+            Self::Filter3 => (115.0 / 64.0, -13.0 / 16.0),
+        }
+    }
+
+    /// Returns the linear gain of the filter at `freq_hz`.
+    ///
+    /// Evaluates the magnitude of the filter's transfer function
+    /// `H(z) = 1 / (1 - a1 z^-1 - a2 z^-2)` on the unit circle.
+    pub fn frequency_response(&self, freq_hz: f64, sample_rate: f64) -> f64 {
+        let (a1, a2) = self.coefficients();
+
+        let w = std::f64::consts::TAU * freq_hz / sample_rate;
+
+        let re = 1.0 - a1 * w.cos() - a2 * (2.0 * w).cos();
+        let im = a1 * w.sin() + a2 * (2.0 * w).sin();
+
+        1.0 / f64::hypot(re, im)
+    }
 }
 
 #[derive(Clone, Hash, Eq, PartialEq)]
@@ -202,11 +242,157 @@ impl BrrSample {
     }
 }
 
+/// Calculates the signal-to-noise ratio (in decibels) of each block in a BRR sample,
+/// comparing the decoded `brr` sample against the `original` samples it was encoded from.
+///
+/// Returns one SNR value per BRR block.
+/// A block that decodes perfectly has an SNR of `f32::INFINITY`.
+///
+/// Samples past the end of `original` (ie, dupe-block-hack blocks) are wrapped back to the
+/// `brr` loop point.
+/// If `brr` does not loop, the samples past the end of `original` are compared against silence.
+pub fn analyze_brr_quality(original: &[i16], brr: &BrrSample) -> Vec<f32> {
+    if original.is_empty() {
+        return Vec::new();
+    }
+
+    let decoded = decode_brr_data(&brr.brr_data);
+
+    let loop_length = brr
+        .loop_point_samples()
+        .map(|lp| decoded.len().saturating_sub(lp))
+        .filter(|&l| l > 0);
+
+    let original_sample = |mut i: usize| -> i16 {
+        while i >= original.len() {
+            match loop_length.and_then(|l| i.checked_sub(l)) {
+                Some(j) => i = j,
+                None => return 0,
+            }
+        }
+        original[i]
+    };
+
+    decoded
+        .chunks_exact(SAMPLES_PER_BLOCK)
+        .enumerate()
+        .map(|(block, decoded)| {
+            let mut signal = 0.0;
+            let mut noise = 0.0;
+
+            for (i, &d) in decoded.iter().enumerate() {
+                let o = f64::from(original_sample(block * SAMPLES_PER_BLOCK + i));
+                let d = f64::from(d);
+
+                signal += o * o;
+                noise += (o - d) * (o - d);
+            }
+
+            if noise == 0.0 {
+                f32::INFINITY
+            } else {
+                (10.0 * (signal / noise).log10()) as f32
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 #[allow(clippy::bool_assert_comparison)]
 mod test {
     use super::*;
 
+    #[test]
+    fn brr_filter_frequency_response() {
+        const SR: f64 = 32000.0;
+
+        let assert_close = |a: f64, b: f64| assert!((a - b).abs() < 1e-9, "{a} != {b}");
+
+        for f in [0.0, 1000.0, 8000.0, 16000.0] {
+            assert_close(BrrFilter::Filter0.frequency_response(f, SR), 1.0);
+        }
+
+        // DC gain is `1 / (1 - a1 - a2)`
+        assert_close(BrrFilter::Filter1.frequency_response(0.0, SR), 16.0);
+        assert_close(BrrFilter::Filter2.frequency_response(0.0, SR), 32.0);
+        assert_close(BrrFilter::Filter3.frequency_response(0.0, SR), 64.0);
+
+        // Nyquist gain is `1 / (1 + a1 - a2)`
+        assert_close(
+            BrrFilter::Filter1.frequency_response(16000.0, SR),
+            16.0 / 31.0,
+        );
+        assert_close(
+            BrrFilter::Filter2.frequency_response(16000.0, SR),
+            32.0 / 123.0,
+        );
+        assert_close(
+            BrrFilter::Filter3.frequency_response(16000.0, SR),
+            64.0 / 231.0,
+        );
+
+        // All filters are low-pass filters
+        for filter in [BrrFilter::Filter1, BrrFilter::Filter2, BrrFilter::Filter3] {
+            assert!(filter.frequency_response(500.0, SR) > filter.frequency_response(4000.0, SR));
+        }
+    }
+
+    #[test]
+    fn analyze_brr_quality_sine() {
+        let original: Vec<i16> = (0..64)
+            .map(|i| (f64::sin(f64::from(i) * std::f64::consts::TAU / 32.0) * 16000.0) as i16)
+            .collect();
+
+        let brr = encode_brr(&original, DEFAULT_EVALUATOR, Some(0), None, None).unwrap();
+
+        let snr = analyze_brr_quality(&original, &brr);
+
+        assert_eq!(snr.len(), 4);
+        assert!(snr.iter().all(|&s| s > 20.0), "{:?}", snr);
+    }
+
+    #[test]
+    fn analyze_brr_quality_silence() {
+        let original = [0; 32];
+
+        let brr = encode_brr(&original, DEFAULT_EVALUATOR, None, None, None).unwrap();
+
+        assert_eq!(
+            analyze_brr_quality(&original, &brr),
+            [f32::INFINITY, f32::INFINITY]
+        );
+    }
+
+    #[test]
+    fn analyze_brr_quality_dupe_block_hack() {
+        let original: Vec<i16> = (0..64)
+            .map(|i| (f64::sin(f64::from(i) * std::f64::consts::TAU / 64.0) * 16000.0) as i16)
+            .collect();
+
+        let brr = encode_brr(&original, DEFAULT_EVALUATOR, None, Some(2), None).unwrap();
+        assert_eq!(brr.n_brr_blocks(), 6);
+
+        let snr = analyze_brr_quality(&original, &brr);
+
+        assert_eq!(snr.len(), 6);
+        assert!(snr.iter().all(|&s| s > 20.0), "{:?}", snr);
+    }
+
+    #[test]
+    fn analyze_brr_quality_past_end_of_non_looping_sample() {
+        let original = [0; 16];
+
+        let brr = BrrSample {
+            loop_offset: None,
+            brr_data: vec![0; BYTES_PER_BRR_BLOCK * 2],
+        };
+
+        assert_eq!(
+            analyze_brr_quality(&original, &brr),
+            [f32::INFINITY, f32::INFINITY]
+        );
+    }
+
     #[test]
     fn test_for_gaussian_overflow_bug_false() {
         // Sine wave sample created using wav2brr
@@ -11,6 +11,24 @@ use ::std::io;
 pub struct MonoPcm16WaveFile {
     pub sample_rate: u32,
     pub samples: Vec<i16>,
+    pub loop_info: Option<WavLoopInfo>,
+    pub unity_note: Option<WavUnityNote>,
+}
+
+/// The loop point stored in a wave file's `smpl` or `cue ` chunk.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WavLoopInfo {
+    pub loop_start: usize,
+    /// Exclusive end of the loop (`None` if the wave file only contains a cue point)
+    pub loop_end: Option<usize>,
+}
+
+/// The MIDI note of a wave file's `smpl` chunk (the note the sample plays at its sample rate).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WavUnityNote {
+    pub midi_note: u32,
+    /// Fraction of a semitone above `midi_note` (0x80000000 is half a semitone)
+    pub pitch_fraction: u32,
 }
 
 #[derive(Debug)]
@@ -75,12 +93,20 @@ struct FmtChunk {
 struct WaveFile {
     format: FmtChunk,
     data: Vec<u8>,
+    // Loop positions are in sample frames and have not been validated
+    loop_info: Option<WavLoopInfo>,
+    unity_note: Option<WavUnityNote>,
 }
 
 const WAVE_CHUNK_ID: [u8; 4] = [b'R', b'I', b'F', b'F'];
 const WAVE_ID: [u8; 4] = [b'W', b'A', b'V', b'E'];
 const FMT_CHUNK_ID: [u8; 4] = [b'f', b'm', b't', b' '];
 const DATA_CHUNK_ID: [u8; 4] = [b'd', b'a', b't', b'a'];
+const SMPL_CHUNK_ID: [u8; 4] = [b's', b'm', b'p', b'l'];
+const CUE_CHUNK_ID: [u8; 4] = [b'c', b'u', b'e', b' '];
+
+// Larger `smpl` and `cue ` chunks are ignored
+const MAX_LOOP_CHUNK_SIZE: u32 = 4096;
 
 fn parse_fmt_chunk(data: &[u8]) -> Result<FmtChunk, WavError> {
     // There are different versions of fmt chunk.
@@ -110,6 +136,79 @@ fn parse_fmt_chunk(data: &[u8]) -> Result<FmtChunk, WavError> {
     })
 }
 
+fn read_u32_at(data: &[u8], o: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(o..o + 4)?.try_into().unwrap()))
+}
+
+impl WavLoopInfo {
+    /// Returns the first loop of a `smpl` chunk (`data` excludes the chunk header)
+    pub fn from_smpl_chunk(data: &[u8]) -> Option<Self> {
+        const HEADER_SIZE: usize = 36;
+        const N_LOOPS_OFFSET: usize = 28;
+        const LOOP_START_OFFSET: usize = HEADER_SIZE + 8;
+        const LOOP_END_OFFSET: usize = HEADER_SIZE + 12;
+
+        if read_u32_at(data, N_LOOPS_OFFSET)? == 0 {
+            return None;
+        }
+
+        let loop_start = read_u32_at(data, LOOP_START_OFFSET)?;
+        // The smpl loop end is inclusive
+        let loop_end = read_u32_at(data, LOOP_END_OFFSET)?.checked_add(1)?;
+
+        Some(Self {
+            loop_start: loop_start.try_into().ok()?,
+            loop_end: Some(loop_end.try_into().ok()?),
+        })
+    }
+
+    /// Returns true if the loop is inside a sample of `n_samples` length
+    pub fn is_valid(&self, n_samples: usize) -> bool {
+        match self.loop_end {
+            Some(end) => self.loop_start < end && end <= n_samples,
+            None => self.loop_start < n_samples,
+        }
+    }
+}
+
+impl WavUnityNote {
+    /// Returns the unity note of a `smpl` chunk (`data` excludes the chunk header)
+    pub fn from_smpl_chunk(data: &[u8]) -> Option<Self> {
+        const MIDI_UNITY_NOTE_OFFSET: usize = 12;
+        const MIDI_PITCH_FRACTION_OFFSET: usize = 16;
+
+        Some(Self {
+            midi_note: read_u32_at(data, MIDI_UNITY_NOTE_OFFSET)?,
+            pitch_fraction: read_u32_at(data, MIDI_PITCH_FRACTION_OFFSET)?,
+        })
+    }
+
+    /// Returns the MIDI note number, including the pitch fraction
+    pub fn note(&self) -> f64 {
+        f64::from(self.midi_note) + f64::from(self.pitch_fraction) / (1u64 << 32) as f64
+    }
+}
+
+/// Returns the first cue point of a `cue ` chunk
+fn parse_cue_chunk(data: &[u8]) -> Option<WavLoopInfo> {
+    const SAMPLE_OFFSET_OFFSET: usize = 4 + 20;
+
+    if read_u32_at(data, 0)? == 0 {
+        return None;
+    }
+
+    Some(WavLoopInfo {
+        loop_start: read_u32_at(data, SAMPLE_OFFSET_OFFSET)?.try_into().ok()?,
+        loop_end: None,
+    })
+}
+
+fn read_chunk(reader: &mut impl io::Read, chunk_size: u32) -> io::Result<Vec<u8>> {
+    let mut v = vec![0; chunk_size.try_into().unwrap()];
+    reader.read_exact(&mut v)?;
+    Ok(v)
+}
+
 fn read_four_bytes(reader: &mut impl io::Read) -> io::Result<[u8; 4]> {
     let mut data = [0; 4];
     reader.read_exact(&mut data)?;
@@ -153,6 +252,9 @@ fn read_wave_file(
     };
 
     let mut data = Vec::new();
+    let mut smpl_loop = None;
+    let mut cue_loop = None;
+    let mut unity_note = None;
 
     loop {
         let mut chunk_id = [0; 4];
@@ -187,6 +289,17 @@ fn read_wave_file(
                 return Err(WavError::InvalidWaveFile);
             }
 
+            SMPL_CHUNK_ID if chunk_size <= MAX_LOOP_CHUNK_SIZE => {
+                let chunk = read_chunk(reader, chunk_size)?;
+                smpl_loop = smpl_loop.or(WavLoopInfo::from_smpl_chunk(&chunk));
+                unity_note = unity_note.or(WavUnityNote::from_smpl_chunk(&chunk));
+            }
+
+            CUE_CHUNK_ID if chunk_size <= MAX_LOOP_CHUNK_SIZE => {
+                let chunk = read_chunk(reader, chunk_size)?;
+                cue_loop = cue_loop.or(parse_cue_chunk(&chunk));
+            }
+
             _ => {
                 // Ignore unknown chunks
                 reader.seek(io::SeekFrom::Current(chunk_size.into()))?;
@@ -198,7 +311,13 @@ fn read_wave_file(
         return Err(WavError::NoSamples);
     }
 
-    Ok(WaveFile { format, data })
+    Ok(WaveFile {
+        format,
+        data,
+        // The smpl chunk takes priority as it contains the loop end
+        loop_info: smpl_loop.or(cue_loop),
+        unity_note,
+    })
 }
 
 fn decode_16_bit(wav: WaveFile) -> Result<MonoPcm16WaveFile, WavError> {
@@ -217,6 +336,9 @@ fn decode_16_bit(wav: WaveFile) -> Result<MonoPcm16WaveFile, WavError> {
 
     Ok(MonoPcm16WaveFile {
         sample_rate: wav.format.samples_per_second,
+        // Discard loop points outside the sample
+        loop_info: wav.loop_info.filter(|l| l.is_valid(samples.len())),
+        unity_note: wav.unity_note,
         samples,
     })
 }
@@ -224,7 +346,7 @@ fn decode_16_bit(wav: WaveFile) -> Result<MonoPcm16WaveFile, WavError> {
 fn decode_8_bit(wav: WaveFile) -> Result<MonoPcm16WaveFile, WavError> {
     // Convert data to i16 vector
 
-    let samples = wav
+    let samples: Vec<i16> = wav
         .data
         .iter()
         .map(|s| (i16::from(*s) - 128) << 8)
@@ -232,6 +354,9 @@ fn decode_8_bit(wav: WaveFile) -> Result<MonoPcm16WaveFile, WavError> {
 
     Ok(MonoPcm16WaveFile {
         sample_rate: wav.format.samples_per_second,
+        // Discard loop points outside the sample
+        loop_info: wav.loop_info.filter(|l| l.is_valid(samples.len())),
+        unity_note: wav.unity_note,
         samples,
     })
 }
@@ -259,8 +384,66 @@ pub fn read_mono_pcm_wave_file(
     }
 }
 
+/// Wave file builders for tests
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils {
+    use super::{DATA_CHUNK_ID, FMT_CHUNK_ID, SMPL_CHUNK_ID, WAVE_CHUNK_ID, WAVE_ID};
+
+    pub fn append_chunk(wav: &mut Vec<u8>, chunk_id: [u8; 4], words: &[u32]) {
+        wav.extend(chunk_id);
+        wav.extend((words.len() as u32 * 4).to_le_bytes());
+        wav.extend(words.iter().flat_map(|w| w.to_le_bytes()));
+    }
+
+    /// A `smpl` chunk with a unity note of 60 (c4) and one loop (`loop_end` is inclusive)
+    pub fn smpl_chunk(loop_start: u32, loop_end: u32) -> [u32; 15] {
+        // manufacturer, product, sample_period, midi_unity_note, midi_pitch_fraction,
+        // smpte_format, smpte_offset, n_loops, sampler_data
+        // cue_point_id, type, start, end, fraction, play_count
+        [
+            0, 0, 31250, 60, 0, 0, 0, 1, 0, 0, 0, loop_start, loop_end, 0, 0,
+        ]
+    }
+
+    /// A 16-bit mono PCM wave file
+    pub fn mono_16_bit_wav_file(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let mut wav = Vec::new();
+        wav.extend(WAVE_CHUNK_ID);
+        // file size is not read
+        wav.extend([0; 4]);
+        wav.extend(WAVE_ID);
+
+        wav.extend(FMT_CHUNK_ID);
+        // chunk_size, format_tag, n_channels, sample_rate, avg_bytes_per_second, block_align,
+        // bits_per_sample
+        wav.extend(16u32.to_le_bytes());
+        wav.extend([1, 0, 1, 0]);
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend((sample_rate * 2).to_le_bytes());
+        wav.extend([2, 0, 16, 0]);
+
+        wav.extend(DATA_CHUNK_ID);
+        wav.extend((std::mem::size_of_val(samples) as u32).to_le_bytes());
+        wav.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        wav
+    }
+
+    /// A 16-bit mono PCM wave file with a `smpl` chunk that loops to the end of the sample
+    pub fn mono_16_bit_wav_file_with_loop(
+        samples: &[i16],
+        sample_rate: u32,
+        loop_start: u32,
+    ) -> Vec<u8> {
+        let mut wav = mono_16_bit_wav_file(samples, sample_rate);
+        let loop_end = samples.len() as u32 - 1;
+        append_chunk(&mut wav, SMPL_CHUNK_ID, &smpl_chunk(loop_start, loop_end));
+        wav
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::test_utils::*;
     use super::*;
 
     // 96000Hz, 32-bit stereo PCM wave file (1 sample of silence)
@@ -317,7 +500,9 @@ mod tests {
                     block_align: 8,
                     bits_per_sample: 32,
                 },
-                data: vec![0; STEREO_96000_32_BIT_PCM_DATA_SIZE]
+                data: vec![0; STEREO_96000_32_BIT_PCM_DATA_SIZE],
+                loop_info: None,
+                unity_note: None,
             }
         )
     }
@@ -382,7 +567,9 @@ mod tests {
                     block_align: 2,
                     bits_per_sample: 16,
                 },
-                data: vec![0; 4]
+                data: vec![0; 4],
+                loop_info: None,
+                unity_note: None,
             }
         )
     }
@@ -403,6 +590,8 @@ mod tests {
             wav == MonoPcm16WaveFile {
                 sample_rate: 32000,
                 samples: MONO_32000_16_BIT_SAMPLES.to_vec(),
+                loop_info: None,
+                unity_note: None,
             }
         );
     }
@@ -432,10 +621,142 @@ mod tests {
             wav == MonoPcm16WaveFile {
                 sample_rate: 32000,
                 samples: MONO_32000_8_BIT_SAMPLES.to_vec(),
+                loop_info: None,
+                unity_note: None,
             }
         );
     }
 
+    fn cue_chunk(sample_offset: u32) -> [u32; 7] {
+        // n_cue_points
+        // id, position, data_chunk_id, chunk_start, block_start, sample_offset
+        [
+            1,
+            1,
+            0,
+            u32::from_le_bytes(DATA_CHUNK_ID),
+            0,
+            0,
+            sample_offset,
+        ]
+    }
+
+    fn read_loop_info(chunks: &[([u8; 4], &[u32])]) -> Option<WavLoopInfo> {
+        let mut wav = MONO_32000_16_BIT_PCM.to_vec();
+        for (id, words) in chunks {
+            append_chunk(&mut wav, *id, words);
+        }
+
+        let wav = read_mono_pcm_wave_file(&mut io::Cursor::new(wav), 100).unwrap();
+        assert_eq!(wav.samples, MONO_32000_16_BIT_SAMPLES);
+
+        wav.loop_info
+    }
+
+    #[test]
+    fn test_loop_info() {
+        assert_eq!(
+            read_loop_info(&[(SMPL_CHUNK_ID, &smpl_chunk(2, 9))]),
+            Some(WavLoopInfo {
+                loop_start: 2,
+                loop_end: Some(10)
+            })
+        );
+
+        assert_eq!(
+            read_loop_info(&[(CUE_CHUNK_ID, &cue_chunk(4))]),
+            Some(WavLoopInfo {
+                loop_start: 4,
+                loop_end: None
+            })
+        );
+
+        // smpl chunk has priority over the cue chunk
+        assert_eq!(
+            read_loop_info(&[
+                (CUE_CHUNK_ID, &cue_chunk(4)),
+                (SMPL_CHUNK_ID, &smpl_chunk(1, 5))
+            ]),
+            Some(WavLoopInfo {
+                loop_start: 1,
+                loop_end: Some(6)
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_loop_info() {
+        // Loop points outside the sample are ignored
+        assert_eq!(read_loop_info(&[(SMPL_CHUNK_ID, &smpl_chunk(2, 10))]), None);
+        assert_eq!(read_loop_info(&[(SMPL_CHUNK_ID, &smpl_chunk(5, 4))]), None);
+        assert_eq!(read_loop_info(&[(CUE_CHUNK_ID, &cue_chunk(10))]), None);
+
+        // No loops
+        let mut smpl = smpl_chunk(2, 9);
+        smpl[7] = 0;
+        assert_eq!(read_loop_info(&[(SMPL_CHUNK_ID, &smpl)]), None);
+        assert_eq!(read_loop_info(&[(CUE_CHUNK_ID, &[0])]), None);
+
+        // Truncated chunks
+        assert_eq!(
+            read_loop_info(&[(SMPL_CHUNK_ID, &smpl_chunk(2, 9)[..11])]),
+            None
+        );
+        assert_eq!(read_loop_info(&[(CUE_CHUNK_ID, &cue_chunk(2)[..6])]), None);
+    }
+
+    #[test]
+    fn test_mono_16_bit_wav_file() {
+        assert!(
+            mono_16_bit_wav_file(&MONO_32000_16_BIT_SAMPLES, 32000)[8..]
+                == MONO_32000_16_BIT_PCM[8..]
+        );
+
+        let wav = mono_16_bit_wav_file_with_loop(&MONO_32000_16_BIT_SAMPLES, 16000, 4);
+        let wav = read_mono_pcm_wave_file(&mut io::Cursor::new(wav), 100).unwrap();
+        assert_eq!(wav.sample_rate, 16000);
+        assert_eq!(wav.samples, MONO_32000_16_BIT_SAMPLES);
+        assert_eq!(
+            wav.loop_info,
+            Some(WavLoopInfo {
+                loop_start: 4,
+                loop_end: Some(10)
+            })
+        );
+    }
+
+    #[test]
+    fn test_unity_note() {
+        let read_unity_note = |smpl: &[u32]| {
+            let mut wav = MONO_32000_16_BIT_PCM.to_vec();
+            append_chunk(&mut wav, SMPL_CHUNK_ID, smpl);
+            read_mono_pcm_wave_file(&mut io::Cursor::new(wav), 100)
+                .unwrap()
+                .unity_note
+        };
+
+        let mut smpl = smpl_chunk(2, 9);
+        assert_eq!(
+            read_unity_note(&smpl),
+            Some(WavUnityNote {
+                midi_note: 60,
+                pitch_fraction: 0
+            })
+        );
+
+        // The unity note is read even if the smpl chunk has no loops
+        smpl[3] = 69;
+        smpl[4] = 0x8000_0000;
+        smpl[7] = 0;
+        let note = read_unity_note(&smpl).unwrap();
+        assert_eq!(note.note(), 69.5);
+
+        assert_eq!(read_unity_note(&smpl[..4]), None);
+
+        let wav = read_mono_pcm_wave_file(&mut io::Cursor::new(MONO_32000_16_BIT_PCM), 100);
+        assert_eq!(wav.unwrap().unity_note, None);
+    }
+
     #[test]
     fn test_stereo_16_bit_pcm() {
         // 16-bit PCM stereo 32000 Hz wav file
@@ -59,7 +59,10 @@ const AUDIO_DRIVER_SYMBOLS: &[(&str, &str)] = &[
     ("channelSoA.stackPointer", "CHANNEL_STACK_POINTER"),
     ("channelSoA.loopStackPointer", "CHANNEL_LOOP_STACK_POINTER"),
     ("channelSoA.nextEventIsKeyOff", "CHANNEL_NEXT_EVENT_IS_KEY_OFF"),
-    ("channelSoA.portamento_direction", "CHANNEL_DIRECTION"),
+    ("channelSoA.portamento_direction", "CHANNEL_PORTAMENTO_DIRECTION"),
+    ("channelSoA.portamento_speed", "CHANNEL_PORTAMENTO_SPEED"),
+    ("channelSoA.portamento_target_l", "CHANNEL_PORTAMENTO_TARGET_L"),
+    ("channelSoA.portamento_target_h", "CHANNEL_PORTAMENTO_TARGET_H"),
     ("channelSoA.vibrato_pitchOffsetPerTick", "CHANNEL_VIBRATO_PITCH_OFFSET_PER_TICK"),
     ("channelSoA.vibrato_direction", "CHANNEL_VIBRATO_DIRECTION"),
     ("channelSoA.vibrato_tickCounter", "CHANNEL_VIBRATO_TICK_COUNTER"),
@@ -229,6 +229,9 @@ pub mod opcodes {
     macro_rules! declare_opcodes {
         ($first:ident, $($tail:ident),+ $(,)?) => {
             declare_opcode_recursive!(0u8, $first, $($tail),+);
+
+            /// Opcode names, indexed by opcode (excludes `DISABLE_CHANNEL` and the play note opcodes)
+            pub(crate) const NAMES: &[&str] = &[stringify!($first), $(stringify!($tail)),+];
         };
     }
 
@@ -348,6 +351,14 @@ impl SubroutineId {
         self.state.max_stack_depth
     }
 
+    pub fn subroutine_calls(&self) -> &[usize] {
+        &self.state.subroutine_calls
+    }
+
+    pub fn subroutine_depth(&self) -> u8 {
+        self.state.subroutine_depth
+    }
+
     pub fn no_instrument_notes(&self) -> &RangeInclusive<Note> {
         &self.state.no_instrument_notes
     }
@@ -963,6 +974,10 @@ pub struct State {
     pub tick_counter: TickCounter,
     pub max_stack_depth: StackDepth,
     pub tempo_changes: Vec<(TickCounter, TickClock)>,
+    /// Subroutine indexes called by this bytecode (in call order, without duplicates)
+    pub subroutine_calls: Vec<usize>,
+    /// Maximum number of nested subroutine calls
+    pub subroutine_depth: u8,
 
     pub(crate) instrument: InstrumentState,
     pub(crate) envelope: IeState<Envelope>,
@@ -1051,6 +1066,13 @@ impl State {
         // No maybe prev_slurred_note state
     }
 
+    fn add_subroutine_call(&mut self, subroutine: &SubroutineId, depth: u8) {
+        if !self.subroutine_calls.contains(&subroutine.as_usize()) {
+            self.subroutine_calls.push(subroutine.as_usize());
+        }
+        self.subroutine_depth = max(self.subroutine_depth, depth);
+    }
+
     fn merge_subroutine(&mut self, subroutine: &SubroutineId) {
         let s: &Self = &subroutine.state;
 
@@ -1141,6 +1163,8 @@ impl<'a> Bytecode<'a> {
                 tick_counter: TickCounter::new(0),
                 max_stack_depth: StackDepth(0),
                 tempo_changes: Vec::new(),
+                subroutine_calls: Vec::new(),
+                subroutine_depth: 0,
                 instrument: InstrumentState::Unset,
                 envelope: IeState::Unset,
                 prev_temp_gain: IeState::Unset,
@@ -1267,6 +1291,8 @@ impl<'a> Bytecode<'a> {
             if let Err(e) = self._update_subtroutine_state_excluding_stack_depth(s) {
                 return Err((e, self.bytecode));
             }
+            // A tail call does not push a return address onto the stack
+            self.state.add_subroutine_call(s, s.subroutine_depth());
         }
 
         // ::TODO test song loop notes at the end of the channel::
@@ -2164,6 +2190,9 @@ impl<'a> Bytecode<'a> {
             ));
         }
 
+        self.state
+            .add_subroutine_call(subroutine, subroutine.subroutine_depth().saturating_add(1));
+
         let opcode = match disable_vibraro {
             true => opcodes::CALL_SUBROUTINE_AND_DISABLE_VIBRATO,
             false => opcodes::CALL_SUBROUTINE,
@@ -2212,7 +2241,7 @@ impl<'a> Bytecode<'a> {
     fn _find_subroutine(&self, name: &'a str) -> Result<&'a SubroutineId, BytecodeError> {
         match self.subroutines.find_subroutine(name) {
             FindSubroutineResult::Found(s) => Ok(s),
-            FindSubroutineResult::NotCompiled => {
+            FindSubroutineResult::NotCompiled | FindSubroutineResult::Unchecked => {
                 // Subroutine has been compiled, but it contains an error
                 Err(BytecodeError::SubroutineHasError(name.to_owned()))
             }
@@ -0,0 +1,387 @@
+//! Audio driver bytecode disassembler
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::bytecode::opcodes;
+use crate::driver_constants::{
+    N_MUSIC_CHANNELS, SONG_HEADER_CHANNELS_SIZE, SONG_HEADER_N_SUBROUTINES_OFFSET, SONG_HEADER_SIZE,
+};
+use crate::notes::{NOTE_NAMES, SEMITONES_PER_OCTAVE};
+
+use std::fmt::Write;
+
+// `SET_FIR_FILTER`
+const MAX_INSTRUCTION_SIZE: usize = 9;
+
+const DISABLED_CHANNEL_OFFSET: u16 = 0xffff;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Option<u8> {
+        let b = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn dec(&mut self) -> Option<String> {
+        Some(self.u8()?.to_string())
+    }
+
+    fn signed(&mut self) -> Option<String> {
+        Some(i8::from_le_bytes([self.u8()?]).to_string())
+    }
+
+    fn hex(&mut self) -> Option<String> {
+        Some(format!("${:02x}", self.u8()?))
+    }
+
+    fn u16_hex(&mut self) -> Option<String> {
+        let l = self.u8()?;
+        let h = self.u8()?;
+        Some(format!("${:04x}", u16::from_le_bytes([l, h])))
+    }
+
+    /// A play pitch or portamento pitch target argument (with the key-off bit)
+    fn pitch_and_key_off(&mut self) -> Option<[String; 2]> {
+        let l = self.u8()?;
+        let h = self.u8()?;
+        Some([
+            format!("${:04x}", u16::from_le_bytes([l, h >> 1])),
+            key_off(h).to_owned(),
+        ])
+    }
+}
+
+fn key_off(b: u8) -> &'static str {
+    match b & 1 {
+        0 => "no_keyoff",
+        _ => "keyoff",
+    }
+}
+
+/// The note of a play note opcode
+fn note(note_opcode: u8) -> String {
+    match note_opcode.checked_sub(opcodes::FIRST_PLAY_NOTE_INSTRUCTION) {
+        Some(n) => {
+            let note_id = n >> 1;
+            format!(
+                "{}{}",
+                NOTE_NAMES[usize::from(note_id % SEMITONES_PER_OCTAVE)],
+                note_id / SEMITONES_PER_OCTAVE
+            )
+        }
+        None => format!("invalid_note(${:02x})", note_opcode),
+    }
+}
+
+fn branch_target(song_addr: u16, offset: usize, relative: i32) -> String {
+    let offset = i32::try_from(offset).unwrap_or(i32::MAX);
+    let addr = i32::from(song_addr) + offset + relative;
+    format!("${:04x}", addr & 0xffff)
+}
+
+struct Instruction {
+    size: usize,
+    text: String,
+    /// The next instruction is not executed after this one
+    terminator: bool,
+}
+
+/// Returns None if the instruction is truncated
+fn decode_instruction(data: &[u8], pos: usize, song_addr: u16) -> Option<Instruction> {
+    let mut r = Reader { data, pos };
+
+    let opcode = r.u8()?;
+
+    let name = match opcode {
+        opcodes::FIRST_PLAY_NOTE_INSTRUCTION.. => "play_note",
+        opcodes::DISABLE_CHANNEL => "disable_channel",
+        o => opcodes::NAMES[usize::from(o)],
+    };
+
+    let operands: Vec<String> = match opcode {
+        opcodes::FIRST_PLAY_NOTE_INSTRUCTION.. => {
+            vec![note(opcode), key_off(opcode).to_owned(), r.dec()?]
+        }
+
+        opcodes::PORTAMENTO_DOWN | opcodes::PORTAMENTO_UP => {
+            let n = r.u8()?;
+            vec![note(n), key_off(n).to_owned(), r.dec()?, r.dec()?]
+        }
+        opcodes::PORTAMENTO_PITCH_DOWN | opcodes::PORTAMENTO_PITCH_UP => {
+            let [pitch, ko] = r.pitch_and_key_off()?;
+            vec![pitch, ko, r.dec()?, r.dec()?]
+        }
+        opcodes::SET_VIBRATO => vec![r.dec()?, r.dec()?],
+        opcodes::SET_VIBRATO_DEPTH_AND_PLAY_NOTE => {
+            let depth = r.dec()?;
+            let n = r.u8()?;
+            vec![depth, note(n), key_off(n).to_owned(), r.dec()?]
+        }
+        opcodes::PLAY_PITCH => {
+            let [pitch, ko] = r.pitch_and_key_off()?;
+            vec![pitch, ko, r.dec()?]
+        }
+        opcodes::PLAY_NOISE => {
+            let f = r.u8()?;
+            vec![(f >> 1).to_string(), key_off(f).to_owned(), r.dec()?]
+        }
+        opcodes::DISABLE_NOISE => vec![],
+        opcodes::WAIT | opcodes::REST => vec![r.dec()?],
+
+        opcodes::SET_INSTRUMENT => vec![r.dec()?],
+        opcodes::SET_INSTRUMENT_AND_ADSR_OR_GAIN => vec![r.dec()?, r.hex()?, r.hex()?],
+        opcodes::SET_ADSR => vec![r.hex()?, r.hex()?],
+        opcodes::SET_GAIN => vec![r.hex()?],
+        opcodes::SET_TEMP_GAIN => vec![r.hex()?],
+        opcodes::SET_TEMP_GAIN_AND_WAIT | opcodes::SET_TEMP_GAIN_AND_REST => {
+            vec![r.hex()?, r.dec()?]
+        }
+        opcodes::REUSE_TEMP_GAIN => vec![],
+        opcodes::REUSE_TEMP_GAIN_AND_WAIT | opcodes::REUSE_TEMP_GAIN_AND_REST => vec![r.dec()?],
+        opcodes::SET_EARLY_RELEASE => vec![r.dec()?, r.dec()?, r.hex()?],
+        opcodes::SET_EARLY_RELEASE_NO_MINIMUM => vec![r.dec()?, r.hex()?],
+
+        opcodes::SET_DETUNE_I16 => {
+            let l = r.u8()?;
+            let h = r.u8()?;
+            vec![i16::from_le_bytes([l, h]).to_string()]
+        }
+        opcodes::SET_DETUNE_P8 => vec![i16::from_le_bytes([r.u8()?, 0x00]).to_string()],
+        opcodes::SET_DETUNE_N8 => vec![i16::from_le_bytes([r.u8()?, 0xff]).to_string()],
+
+        opcodes::ADJUST_PAN => vec![r.signed()?],
+        opcodes::SET_PAN => vec![r.dec()?],
+        opcodes::SET_PAN_AND_VOLUME => vec![r.dec()?, r.dec()?],
+        opcodes::ADJUST_VOLUME => vec![r.signed()?],
+        opcodes::SET_VOLUME => vec![r.dec()?],
+        opcodes::SET_CHANNEL_INVERT => vec![r.hex()?],
+
+        opcodes::VOLUME_SLIDE_UP
+        | opcodes::VOLUME_SLIDE_DOWN
+        | opcodes::TREMOLO
+        | opcodes::PAN_SLIDE_UP
+        | opcodes::PAN_SLIDE_DOWN
+        | opcodes::PANBRELLO => vec![r.dec()?, r.u16_hex()?],
+
+        opcodes::SET_SONG_TICK_CLOCK => vec![r.dec()?],
+
+        opcodes::START_LOOP => vec![r.dec()?],
+        opcodes::SKIP_LAST_LOOP_U8 => {
+            let to_skip = r.u8()?;
+            vec![branch_target(song_addr, r.pos, to_skip.into())]
+        }
+        opcodes::SKIP_LAST_LOOP_U16BE => {
+            let h = r.u8()?;
+            let l = r.u8()?;
+            let to_skip = u16::from_be_bytes([h, l]);
+            vec![branch_target(song_addr, r.pos, to_skip.into())]
+        }
+        opcodes::END_LOOP => vec![],
+
+        opcodes::CALL_SUBROUTINE_AND_DISABLE_VIBRATO | opcodes::CALL_SUBROUTINE => vec![r.dec()?],
+        opcodes::RETURN_FROM_SUBROUTINE_AND_DISABLE_VIBRATO | opcodes::RETURN_FROM_SUBROUTINE => {
+            vec![]
+        }
+        opcodes::GOTO_RELATIVE => {
+            let l = r.u8()?;
+            let h = r.u8()?;
+            // The offset is relative to the address of the high byte
+            let offset = i16::from_le_bytes([l, h]);
+            vec![branch_target(song_addr, r.pos - 1, offset.into())]
+        }
+
+        opcodes::ENABLE_ECHO | opcodes::DISABLE_ECHO => vec![],
+        opcodes::ENABLE_PMOD | opcodes::DISABLE_PMOD => vec![],
+
+        opcodes::SET_ECHO_VOLUME => vec![r.dec()?],
+        opcodes::SET_STEREO_ECHO_VOLUME => vec![r.dec()?, r.dec()?],
+        opcodes::ADJUST_ECHO_VOLUME => vec![r.signed()?],
+        opcodes::ADJUST_STEREO_ECHO_VOLUME => vec![r.signed()?, r.signed()?],
+        opcodes::SET_FIR_FILTER => (0..8).map(|_| r.signed()).collect::<Option<_>>()?,
+        opcodes::SET_ECHO_I8 | opcodes::ADJUST_ECHO_I8 => vec![r.dec()?, r.signed()?],
+        opcodes::ADJUST_ECHO_I8_LIMIT => vec![r.dec()?, r.signed()?, r.signed()?],
+        opcodes::SET_ECHO_INVERT => vec![r.hex()?],
+        opcodes::SET_ECHO_DELAY => vec![r.dec()?],
+
+        opcodes::RESERVED_FOR_CUSTOM_USE => vec![],
+        opcodes::DISABLE_CHANNEL => vec![],
+    };
+
+    let terminator = matches!(
+        opcode,
+        opcodes::GOTO_RELATIVE
+            | opcodes::RETURN_FROM_SUBROUTINE_AND_DISABLE_VIBRATO
+            | opcodes::RETURN_FROM_SUBROUTINE
+            | opcodes::RESERVED_FOR_CUSTOM_USE
+            | opcodes::DISABLE_CHANNEL
+    );
+
+    let text = match operands.is_empty() {
+        true => name.to_lowercase(),
+        false => format!("{} {}", name.to_lowercase(), operands.join(", ")),
+    };
+
+    Some(Instruction {
+        size: r.pos - pos,
+        text,
+        terminator,
+    })
+}
+
+/// Disassembles the bytecode starting at `offset` until the first instruction that does not
+/// fall through to the next instruction.
+fn disassemble_block(out: &mut String, song_data: &[u8], offset: u16, song_addr: u16) {
+    let mut pos = usize::from(offset);
+
+    loop {
+        let addr = song_addr.wrapping_add(pos as u16);
+
+        match decode_instruction(song_data, pos, song_addr) {
+            Some(inst) => {
+                let mut bytes = String::with_capacity(MAX_INSTRUCTION_SIZE * 3);
+                for b in &song_data[pos..pos + inst.size] {
+                    write!(bytes, "{:02x} ", b).unwrap();
+                }
+                writeln!(
+                    out,
+                    "{:04x}:  {:w$} {}",
+                    addr,
+                    bytes,
+                    inst.text,
+                    w = MAX_INSTRUCTION_SIZE * 3
+                )
+                .unwrap();
+
+                if inst.terminator {
+                    break;
+                }
+                pos += inst.size;
+            }
+            None => {
+                writeln!(out, "{:04x}:  ; end of song data", addr).unwrap();
+                break;
+            }
+        }
+    }
+}
+
+/// Disassembles the channels and subroutines of a compiled song.
+///
+/// `song_addr` is the audio-RAM address the song is loaded into.
+/// Only the bytecode reachable without following a `goto_relative` is disassembled.
+pub fn disassemble_song(song_data: &[u8], song_addr: u16) -> String {
+    let mut out = String::new();
+
+    if song_data.len() < SONG_HEADER_SIZE {
+        out.push_str("; song header is truncated\n");
+        return out;
+    }
+
+    let read_offset = |l: usize, h: usize| {
+        u16::from_le_bytes([
+            song_data.get(l).copied().unwrap_or(0xff),
+            song_data.get(h).copied().unwrap_or(0xff),
+        ])
+    };
+
+    const _: () = assert!(SONG_HEADER_CHANNELS_SIZE == N_MUSIC_CHANNELS * 2);
+    for i in 0..N_MUSIC_CHANNELS {
+        let name = char::from(b'A' + i as u8);
+        let offset = read_offset(i * 2, i * 2 + 1);
+
+        match offset {
+            DISABLED_CHANNEL_OFFSET => writeln!(out, "; channel {}: disabled\n", name).unwrap(),
+            o => {
+                writeln!(out, "; channel {}", name).unwrap();
+                disassemble_block(&mut out, song_data, o, song_addr);
+                out.push('\n');
+            }
+        }
+    }
+
+    let n_subroutines = usize::from(song_data[SONG_HEADER_N_SUBROUTINES_OFFSET]);
+    for i in 0..n_subroutines {
+        let li = SONG_HEADER_SIZE + i;
+        let offset = read_offset(li, li + n_subroutines);
+
+        writeln!(out, "; subroutine {}", i).unwrap();
+        disassemble_block(&mut out, song_data, offset, song_addr);
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disassemble() {
+        const SONG_ADDR: u16 = 0x1000;
+        const S: u16 = SONG_HEADER_SIZE as u16 + 2;
+
+        let mut data = vec![0xff; SONG_HEADER_CHANNELS_SIZE];
+        data[0..2].copy_from_slice(&S.to_le_bytes());
+        data.resize(SONG_HEADER_SIZE, 0);
+        data[SONG_HEADER_N_SUBROUTINES_OFFSET] = 1;
+
+        // Subroutine table
+        data.extend([(S + 10) as u8, ((S + 10) >> 8) as u8]);
+
+        data.extend([
+            opcodes::SET_INSTRUMENT,
+            3,
+            opcodes::START_LOOP,
+            2,
+            opcodes::FIRST_PLAY_NOTE_INSTRUCTION + ((49 << 1) | 1),
+            24,
+            opcodes::END_LOOP,
+            opcodes::GOTO_RELATIVE,
+            0xf9,
+            0xff,
+            // subroutine 0
+            opcodes::PLAY_PITCH,
+            0x00,
+            0x20,
+            10,
+            opcodes::RETURN_FROM_SUBROUTINE,
+        ]);
+
+        let out = disassemble_song(&data, SONG_ADDR);
+        let lines: Vec<&str> = out.lines().map(str::trim_end).collect();
+
+        assert_eq!(lines[0], "; channel A");
+        assert_eq!(
+            lines[1],
+            "1021:  0b 03                       set_instrument 3"
+        );
+        assert_eq!(lines[2], "1023:  26 02                       start_loop 2");
+        assert_eq!(
+            lines[3],
+            "1025:  a3 18                       play_note c+4, keyoff, 24"
+        );
+        assert_eq!(lines[4], "1027:  36                          end_loop");
+        assert_eq!(
+            lines[5],
+            "1028:  2b f9 ff                    goto_relative $1023"
+        );
+        assert_eq!(lines[7], "; channel B: disabled");
+        assert_eq!(lines[lines.len() - 4], "; subroutine 0");
+        assert_eq!(
+            lines[lines.len() - 3],
+            "102b:  07 00 20 0a                 play_pitch $1000, no_keyoff, 10"
+        );
+        assert_eq!(
+            lines[lines.len() - 2],
+            "102f:  38                          return_from_subroutine"
+        );
+    }
+}
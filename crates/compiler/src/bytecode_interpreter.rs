@@ -19,13 +19,18 @@ use crate::echo::EchoVolume;
 use crate::envelope::Envelope;
 use crate::invert_flags::InvertFlags;
 use crate::mml::MmlPrefixData;
+use crate::notes::Note;
+use crate::smooth_value::{SmoothValue, SmoothValueDirection};
 use crate::songs::Channel as SongChannel;
 use crate::songs::SongData;
 use crate::subroutines::Subroutine;
 use crate::time::TickClock;
 use crate::time::TickCounter;
 
+use fxhash::FxHasher64;
+
 use std::cmp::min;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
 pub const UNINITIALISED: u8 = 0xaa;
@@ -34,6 +39,62 @@ const MAX_PAN: u8 = Pan::MAX.as_u8();
 const PITCH_MOD_MASK: u8 = 0b00111110;
 const ECHO_VOLUME_MASK: u8 = EchoVolume::MAX.as_u8();
 
+/// The envelope used by a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopeKind {
+    /// The instrument's envelope
+    Instrument,
+    /// Set by a `set_adsr`, `set_gain` or `set_instrument_and_adsr_or_gain` instruction
+    Override(Envelope),
+}
+
+/// The display-relevant state of a music channel (used by the GUI).
+///
+/// Small enough to be copied to the GUI thread every monitor update.
+///
+/// `tick` is not compared by `PartialEq`, the GUI only redraws a channel when its
+/// displayed state changes.
+#[derive(Debug, Clone, Copy, Default, Eq)]
+pub struct ChannelDisplayState {
+    /// True if the channel is playing a note
+    pub active: bool,
+    pub disabled: bool,
+    pub volume: u8,
+    pub pan: u8,
+    pub instrument: Option<u8>,
+    pub echo: bool,
+    pub tick: TickCounter,
+}
+
+impl PartialEq for ChannelDisplayState {
+    fn eq(&self, o: &Self) -> bool {
+        self.active == o.active
+            && self.disabled == o.disabled
+            && self.volume == o.volume
+            && self.pan == o.pan
+            && self.instrument == o.instrument
+            && self.echo == o.echo
+    }
+}
+
+/// A note-on, note-off or tempo change event of a music channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteEventKind {
+    NoteOn { note: Note, instrument: Option<u8> },
+    NoteOff,
+    TempoChange { timer_register: u8 },
+}
+
+/// A note event emitted by `SongInterpreter::process_ticks()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteEvent {
+    pub channel_index: usize,
+    pub kind: NoteEventKind,
+    pub tick: TickCounter,
+}
+
+type NoteEventCallback = Box<dyn FnMut(NoteEvent) + Send>;
+
 /// Error advancing subroutine to the end of the pointer
 #[derive(Debug)]
 pub struct SongSubroutineError;
@@ -84,7 +145,11 @@ struct ChannelSoA {
     pan: ChannelSoAPanVol,
     invert_flags: u8,
 
-    // Not emulating portamento
+    // Only emulating pitch portamento
+    portamento_direction: u8,
+    portamento_speed: u8,
+    portamento_target_l: u8,
+    portamento_target_h: u8,
 
     // Not accurate but since no notes are playing when the GUI starts playing this
     // InterpreterOutput it will not be audible at all.
@@ -163,6 +228,7 @@ struct InterpreterOutput {
     echo: EchoVariables,
 }
 
+#[derive(Clone)]
 struct GlobalState {
     timer_register: u8,
     echo: EchoVariables,
@@ -187,263 +253,21 @@ impl GlobalState {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum PanVolEffectDirection {
-    None = 0,
-    SlideUp = 0x80,
-    SlideDown = 0x81,
-    TriangleUp = 0x40,
-    TriangleDown = 0x41,
-}
-
-#[derive(Debug, Clone)]
-struct PanVolValue<const MAX: u8> {
-    tc: TickCounter,
-
-    value: u8,
-    sub_value: u8,
-    counter: u8,
-    direction: PanVolEffectDirection,
-    half_wavelength: u8,
-
-    offset: u32,
-
-    triangle_starting_value: u8,
-}
-
-impl<const M: u8> PanVolValue<M> {
-    const MAX: u8 = M;
-    const MAX_U32: u32 = ((Self::MAX as u32) << 8) | 0xff;
-
-    fn new(value: u8) -> Self {
-        Self {
-            tc: TickCounter::new(0),
-            value,
-            sub_value: UNINITIALISED,
-            counter: UNINITIALISED,
-            direction: PanVolEffectDirection::None,
-            offset: u32::from_le_bytes([UNINITIALISED, UNINITIALISED, 0, 0]),
-            half_wavelength: UNINITIALISED,
-            triangle_starting_value: UNINITIALISED,
-        }
-    }
-
-    fn as_soa(&self) -> ChannelSoAPanVol {
-        ChannelSoAPanVol {
-            value: self.value,
-            sub_value: self.sub_value,
-            direction: self.direction as u8,
-            offset_l: self.offset.to_le_bytes()[0],
-            offset_h: self.offset.to_le_bytes()[1],
-            counter: self.counter,
-            half_wavelength: self.half_wavelength,
-        }
-    }
-
-    pub(self) fn u8_0is256_to_tick_counter(t: u8) -> TickCounter {
-        match t {
-            0 => TickCounter::new(0x100),
-            t => TickCounter::new(u32::from(t)),
-        }
-    }
-
-    pub(self) fn slide_offset(&self, channel_ticks: TickCounter) -> (u8, u32) {
-        debug_assert!(self.tc < channel_ticks);
-
-        let slide_ticks = Self::u8_0is256_to_tick_counter(self.counter).value();
-
-        let elapsed = (channel_ticks.value() - self.tc.value()).min(slide_ticks);
-        let offset = self.offset * elapsed;
-
-        ((slide_ticks - elapsed).try_into().unwrap(), offset)
-    }
-
-    fn update(&mut self, channel_ticks: TickCounter) {
-        if self.tc == channel_ticks {
-            return;
-        }
-
-        match self.direction {
-            PanVolEffectDirection::None => (),
-
-            PanVolEffectDirection::SlideUp => {
-                let value = u32::from_le_bytes([self.sub_value, self.value, 0, 0]);
-
-                let (counter, offset) = self.slide_offset(channel_ticks);
-
-                let value = value.wrapping_add(offset);
-
-                if value <= Self::MAX_U32 {
-                    self.value = value.to_le_bytes()[1];
-                    self.sub_value = value.to_le_bytes()[0];
-                } else {
-                    self.value = Self::MAX;
-                    self.direction = PanVolEffectDirection::None;
-                }
-
-                self.counter = counter;
-                if counter == 0 {
-                    self.direction = PanVolEffectDirection::None;
-                }
-
-                self.tc = channel_ticks;
-            }
-
-            PanVolEffectDirection::SlideDown => {
-                let value = u32::from_le_bytes([self.sub_value, self.value, 0, 0]);
-
-                let (counter, offset) = self.slide_offset(channel_ticks);
-
-                let value = value.wrapping_sub(offset);
-
-                self.sub_value = value.to_le_bytes()[0];
-
-                if value <= Self::MAX_U32 {
-                    self.value = value.to_le_bytes()[1];
-                    self.sub_value = value.to_le_bytes()[0];
-                } else {
-                    self.value = 0;
-                    self.direction = PanVolEffectDirection::None;
-                }
-
-                self.counter = counter;
-                if counter == 0 {
-                    self.direction = PanVolEffectDirection::None;
-                }
-
-                self.tc = channel_ticks;
-            }
-
-            PanVolEffectDirection::TriangleUp | PanVolEffectDirection::TriangleDown => {
-                self.process_triangle(channel_ticks)
-            }
-        }
-    }
-
-    const TRIANGLE_SUB_START: u8 = u8::MAX / 2;
-
-    pub(self) fn process_triangle(&mut self, channel_ticks: TickCounter) {
-        let starting_value =
-            u32::from_le_bytes([Self::TRIANGLE_SUB_START, self.triangle_starting_value, 0, 0]);
-
-        let wavelength = u32::from(self.half_wavelength) * 2;
-        let quarter_wavelength = wavelength / 4;
-
-        let elapsed = channel_ticks.value() - self.tc.value();
-
-        let position = elapsed % wavelength;
-        let quadrant = position / quarter_wavelength;
-
-        if elapsed >= wavelength || quadrant > 0 {
-            // Test for overflow
-            if starting_value + (quarter_wavelength * self.offset) > Self::MAX_U32 {
-                self.value = Self::MAX;
-                self.direction = PanVolEffectDirection::None;
-                return;
-            }
-        }
-
-        if elapsed >= wavelength || quadrant > 2 {
-            // Test for underflow
-            if quarter_wavelength * self.offset > starting_value {
-                self.value = 0;
-                self.direction = PanVolEffectDirection::None;
-                return;
-            }
-        }
-
-        let (value, direction) = match quadrant {
-            0 => (
-                starting_value.wrapping_add(position * self.offset),
-                PanVolEffectDirection::TriangleUp,
-            ),
-            1 => {
-                let p = u32::from(self.half_wavelength) - position;
-                (
-                    starting_value.wrapping_add(p * self.offset),
-                    PanVolEffectDirection::TriangleDown,
-                )
-            }
-            2 => {
-                let p = position - u32::from(self.half_wavelength);
-                (
-                    starting_value.wrapping_sub(p * self.offset),
-                    PanVolEffectDirection::TriangleDown,
-                )
-            }
-            3 => {
-                let p = wavelength - position;
-                (
-                    starting_value.wrapping_sub(p * self.offset),
-                    PanVolEffectDirection::TriangleUp,
-                )
-            }
-            _ => panic!("Wrong quadrant"),
-        };
-
-        if value <= Self::MAX_U32 {
-            let half_wavelength = u32::from(self.half_wavelength);
-
-            self.value = value.to_le_bytes()[1];
-            self.sub_value = value.to_le_bytes()[0];
-            self.counter = (half_wavelength - (position + quarter_wavelength) % half_wavelength)
-                .to_le_bytes()[0];
-            self.direction = direction;
-        } else {
-            self.value = if quadrant < 2 { Self::MAX } else { 0 };
-            self.direction = PanVolEffectDirection::None;
-        }
-    }
-
-    fn set_value(&mut self, value: u8) {
-        self.direction = PanVolEffectDirection::None;
-        self.value = value;
-    }
-
-    fn adjust_value(&mut self, amount: i8, tc: TickCounter) {
-        self.update(tc);
-
-        self.direction = PanVolEffectDirection::None;
-        self.value = self.value.saturating_add_signed(amount).clamp(0, Self::MAX);
-    }
-
-    fn slide_up_instruction(&mut self, ticks: u8, o1: u8, o2: u8, tc: TickCounter) {
-        self.update(tc);
-
-        self.tc = tc;
-        self.counter = ticks;
-        self.half_wavelength = 0;
-        self.direction = PanVolEffectDirection::SlideUp;
-        self.offset = u32::from_le_bytes([o1, o2, 0, 0]);
-        self.sub_value = 0;
-    }
-
-    fn slide_down_instruction(&mut self, ticks: u8, o1: u8, o2: u8, tc: TickCounter) {
-        self.update(tc);
-
-        self.tc = tc;
-        self.counter = ticks;
-        self.half_wavelength = 0;
-        self.direction = PanVolEffectDirection::SlideDown;
-        self.offset = u32::from_le_bytes([o1, o2, 0, 0]);
-        self.sub_value = u8::MAX;
-    }
-
-    fn tremolo_panbrello_instruction(&mut self, qwt: u8, o1: u8, o2: u8, tc: TickCounter) {
-        self.update(tc);
-
-        self.tc = tc;
-        self.counter = qwt;
-        self.half_wavelength = qwt.wrapping_mul(2);
-        self.direction = PanVolEffectDirection::TriangleUp;
-        self.offset = u32::from_le_bytes([o1, o2, 0, 0]);
-        self.sub_value = Self::TRIANGLE_SUB_START;
-
-        self.triangle_starting_value = self.value;
+fn pan_vol_soa<const M: u8>(v: &SmoothValue<M>) -> ChannelSoAPanVol {
+    let offset = v.offset().to_le_bytes();
+
+    ChannelSoAPanVol {
+        value: v.value(),
+        sub_value: v.sub_value(),
+        direction: v.direction() as u8,
+        offset_l: offset[0],
+        offset_h: offset[1],
+        counter: v.counter(),
+        half_wavelength: v.half_wavelength(),
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
 enum ChannelNote {
     None,
     PlayNote {
@@ -460,12 +284,11 @@ enum ChannelNote {
         instrument: Option<u8>,
         detune: i16,
     },
-    PortamentoPitch {
-        target_pitch: u16,
-    },
+    /// Pitch is stored in `ChannelState::portamento_pitch`
+    PortamentoPitch,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ChannelState {
     ticks: TickCounter,
     disabled: bool,
@@ -505,16 +328,17 @@ pub struct ChannelState {
     note: ChannelNote,
     note_time: TickCounter,
 
-    volume: PanVolValue<0xff>,
-    pan: PanVolValue<MAX_PAN>,
+    volume: SmoothValue<0xff>,
+    pan: SmoothValue<MAX_PAN>,
 
     invert_flags: u8,
 
     echo: bool,
     pitch_mod: bool,
 
-    // Not emulating pitch
-    // Not emulating portamento
+    // Only emulating pitch portamento (the pitch table is not accessible here).
+    // Only valid if `note` is `PlayPitch` or `PortamentoPitch`.
+    portamento_pitch: SmoothValue<0xff>,
 
     // Partially emulating vibrato
     vibrato_pitch_offset_per_tick: u8,
@@ -522,6 +346,51 @@ pub struct ChannelState {
 }
 
 impl ChannelState {
+    /// Hashes the channel state (used by `SongInterpreter::checksum_at_tick()`).
+    fn hash_state<H: Hasher>(&self, state: &mut H) {
+        self.ticks.value().hash(state);
+        self.disabled.hash(state);
+        self.song_ptr.hash(state);
+        self.instruction_ptr.hash(state);
+        self.topmost_return_pos.hash(state);
+        self.call_stack_depth.hash(state);
+        // `usize` is hashed as a `u64` so the checksum does not depend on the target's pointer width
+        (self.stack_pointer as u64).hash(state);
+        (self.loop_stack_pointer as u64).hash(state);
+        self.bc_stack.hash(state);
+        self.instrument.hash(state);
+        self.adsr_or_gain_override.hash(state);
+        self.temp_gain.hash(state);
+        self.prev_temp_gain.hash(state);
+        self.early_release_cmp.hash(state);
+        self.early_release_min_ticks.hash(state);
+        self.early_release_gain.hash(state);
+        self.detune.hash(state);
+        self.next_event_is_key_off.hash(state);
+        self.note.hash(state);
+        self.note_time.value().hash(state);
+        self.volume.hash(state);
+        self.pan.hash(state);
+        self.invert_flags.hash(state);
+        self.echo.hash(state);
+        self.pitch_mod.hash(state);
+        self.portamento_pitch.hash(state);
+        self.vibrato_pitch_offset_per_tick.hash(state);
+        self.vibrato_quarter_wavelength_in_ticks.hash(state);
+    }
+
+    pub fn to_display_state(&self) -> ChannelDisplayState {
+        ChannelDisplayState {
+            active: !matches!(self.note, ChannelNote::None),
+            disabled: self.disabled,
+            volume: self.volume.value(),
+            pan: self.pan.value(),
+            instrument: self.instrument,
+            echo: self.echo,
+            tick: self.ticks,
+        }
+    }
+
     fn new(channel: Option<&SongChannel>, song_ptr: u16) -> Self {
         Self {
             ticks: TickCounter::new(0),
@@ -544,8 +413,9 @@ impl ChannelState {
             early_release_min_ticks: UNINITIALISED,
             early_release_gain: UNINITIALISED,
             detune: 0,
-            volume: PanVolValue::new(STARTING_VOLUME),
-            pan: PanVolValue::new(Pan::CENTER.as_u8()),
+            volume: SmoothValue::new(STARTING_VOLUME),
+            pan: SmoothValue::new(Pan::CENTER.as_u8()),
+            portamento_pitch: SmoothValue::new(0),
             invert_flags: 0,
             echo: false,
             pitch_mod: false,
@@ -591,6 +461,94 @@ impl ChannelState {
         self.increment_tick_count(length, key_off);
     }
 
+    /// Returns the note and instrument of a play-note or portamento instruction.
+    ///
+    /// `None` if the channel is not playing a note or is playing a pitch.
+    fn playing_note(&self) -> Option<(Note, Option<u8>)> {
+        match self.note {
+            ChannelNote::PlayNote {
+                note_opcode,
+                instrument,
+                ..
+            }
+            | ChannelNote::Portamento {
+                target_opcode: note_opcode,
+                instrument,
+                ..
+            } => {
+                let note_id = note_opcode.checked_sub(opcodes::FIRST_PLAY_NOTE_INSTRUCTION)? >> 1;
+                let note = Note::from_note_id_u32(note_id.into()).ok()?;
+                Some((note, instrument))
+            }
+            ChannelNote::None | ChannelNote::PlayPitch(_) | ChannelNote::PortamentoPitch => None,
+        }
+    }
+
+    /// Processes the next bytecode instruction and emits the note events.
+    ///
+    /// A note-off event is emitted when a note is keyed-off and before a slurred note is
+    /// replaced by a new note.
+    /// A tempo change event is emitted when the instruction changes the song tick clock.
+    /// Pitches (`P`, `PR`, `PF`) do not emit note events.
+    fn process_next_bytecode_with_note_events(
+        &mut self,
+        channel_index: usize,
+        global: &mut GlobalState,
+        song_data: &[u8],
+        callback: &mut NoteEventCallback,
+    ) {
+        let prev_note = self.playing_note();
+        let prev_instruction_ptr = self.instruction_ptr;
+        let key_off = self.next_event_is_key_off;
+        let prev_ticks = self.ticks;
+        let prev_timer_register = global.timer_register;
+
+        let mut emit = |kind, tick| {
+            callback(NoteEvent {
+                channel_index,
+                kind,
+                tick,
+            })
+        };
+
+        if key_off && prev_note.is_some() {
+            emit(NoteEventKind::NoteOff, self.ticks);
+        }
+
+        self.process_next_bytecode(global, song_data);
+
+        if global.timer_register != prev_timer_register {
+            emit(
+                NoteEventKind::TempoChange {
+                    timer_register: global.timer_register,
+                },
+                prev_ticks,
+            );
+        }
+
+        let is_play_note = match song_data.get(usize::from(prev_instruction_ptr)) {
+            Some(&opcode) => {
+                opcode >= opcodes::FIRST_PLAY_NOTE_INSTRUCTION
+                    || matches!(
+                        opcode,
+                        opcodes::SET_VIBRATO_DEPTH_AND_PLAY_NOTE
+                            | opcodes::PORTAMENTO_DOWN
+                            | opcodes::PORTAMENTO_UP
+                    )
+            }
+            None => false,
+        };
+
+        if is_play_note && !self.disabled {
+            if !key_off && prev_note.is_some() {
+                emit(NoteEventKind::NoteOff, self.note_time);
+            }
+            if let Some((note, instrument)) = self.playing_note() {
+                emit(NoteEventKind::NoteOn { note, instrument }, self.note_time);
+            }
+        }
+    }
+
     fn call_subroutine(&mut self, s_id: u8, song_data: &[u8]) {
         self.call_stack_depth += 1;
         if self.call_stack_depth == 1 {
@@ -700,15 +658,26 @@ impl ChannelState {
             opcodes::PORTAMENTO_PITCH_DOWN | opcodes::PORTAMENTO_PITCH_UP => {
                 let pitch_l = read_pc();
                 let pitch_h_and_keyoff = read_pc();
-                let _portamento_speed = read_pc();
+                let speed = read_pc();
                 let length = read_pc();
 
                 let key_off = (pitch_h_and_keyoff & 1) == 1;
-
-                // Ignoring portamento speed and direction
-                self.note = ChannelNote::PortamentoPitch {
-                    target_pitch: u16::from_le_bytes([pitch_l, pitch_h_and_keyoff >> 1]),
-                };
+                let target_pitch = u16::from_le_bytes([pitch_l, pitch_h_and_keyoff >> 1]);
+
+                match self.note {
+                    ChannelNote::PlayPitch(_) | ChannelNote::PortamentoPitch => {
+                        let p = &mut self.portamento_pitch;
+                        match opcode {
+                            opcodes::PORTAMENTO_PITCH_UP => {
+                                p.portamento_up_instruction(target_pitch, speed, self.ticks)
+                            }
+                            _ => p.portamento_down_instruction(target_pitch, speed, self.ticks),
+                        }
+                    }
+                    // Starting pitch is unknown, skip the portamento
+                    _ => self.portamento_pitch.set_u16_value(target_pitch),
+                }
+                self.note = ChannelNote::PortamentoPitch;
 
                 self.increment_tick_count(length, key_off);
             }
@@ -750,9 +719,10 @@ impl ChannelState {
                 let length = read_pc();
 
                 let key_off = (pitch_h_and_keyoff & 1) == 1;
+                let pitch = u16::from_le_bytes([pitch_l, pitch_h_and_keyoff >> 1]);
 
-                self.note =
-                    ChannelNote::PlayPitch(u16::from_le_bytes([pitch_l, pitch_h_and_keyoff >> 1]));
+                self.note = ChannelNote::PlayPitch(pitch);
+                self.portamento_pitch.set_u16_value(pitch);
 
                 self.increment_tick_count(length, key_off);
             }
@@ -907,53 +877,45 @@ impl ChannelState {
 
             opcodes::VOLUME_SLIDE_UP => {
                 let ticks = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
-                self.volume.slide_up_instruction(ticks, o1, o2, self.ticks);
+                self.volume.slide_up_instruction(ticks, offset, self.ticks);
             }
 
             opcodes::VOLUME_SLIDE_DOWN => {
                 let ticks = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
                 self.volume
-                    .slide_down_instruction(ticks, o1, o2, self.ticks);
+                    .slide_down_instruction(ticks, offset, self.ticks);
             }
 
             opcodes::TREMOLO => {
                 let qwt = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
-                self.volume
-                    .tremolo_panbrello_instruction(qwt, o1, o2, self.ticks);
+                self.volume.triangle_instruction(qwt, offset, self.ticks);
             }
 
             opcodes::PAN_SLIDE_UP => {
                 let ticks = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
-                self.pan.slide_up_instruction(ticks, o1, o2, self.ticks);
+                self.pan.slide_up_instruction(ticks, offset, self.ticks);
             }
 
             opcodes::PAN_SLIDE_DOWN => {
                 let ticks = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
-                self.pan.slide_down_instruction(ticks, o1, o2, self.ticks);
+                self.pan.slide_down_instruction(ticks, offset, self.ticks);
             }
 
             opcodes::PANBRELLO => {
                 let qwt = read_pc();
-                let o1 = read_pc();
-                let o2 = read_pc();
+                let offset = u16::from_le_bytes([read_pc(), read_pc()]);
 
-                self.pan
-                    .tremolo_panbrello_instruction(qwt, o1, o2, self.ticks);
+                self.pan.triangle_instruction(qwt, offset, self.ticks);
             }
 
             opcodes::SET_SONG_TICK_CLOCK => {
@@ -1180,6 +1142,7 @@ impl ChannelState {
     fn finalise(&mut self, target_ticks: TickCounter) {
         self.volume.update(target_ticks);
         self.pan.update(target_ticks);
+        self.portamento_pitch.update(target_ticks);
     }
 
     // Create a new song subroutine interpreter channel and process a mml-prefix.
@@ -1324,6 +1287,15 @@ where
     channels: [Option<ChannelState>; N_MUSIC_CHANNELS],
     tick_counter: TickCounter,
     stereo_flag: bool,
+
+    /// Tempo changes of the enabled channels, sorted by tick
+    tempo_changes: Vec<(TickCounter, TickClock)>,
+
+    /// State at tick 0, used by `reset()`
+    initial_global: GlobalState,
+    initial_channels: [Option<ChannelState>; N_MUSIC_CHANNELS],
+
+    note_event_callback: Option<NoteEventCallback>,
 }
 
 impl<CAD, SD> SongInterpreter<CAD, SD>
@@ -1332,15 +1304,22 @@ where
     SD: Deref<Target = SongData>,
 {
     pub fn new(common_audio_data: CAD, song_data: SD, stereo_flag: bool) -> Self {
+        let channels: [Option<ChannelState>; N_MUSIC_CHANNELS] = std::array::from_fn(|i| {
+            song_data.channels()[i]
+                .as_ref()
+                .map(|c| ChannelState::new(Some(c), common_audio_data.song_data_addr()))
+        });
+        let global = GlobalState::new(song_data.metadata().tick_clock, &song_data);
+
         Self {
-            channels: std::array::from_fn(|i| {
-                song_data.channels()[i]
-                    .as_ref()
-                    .map(|c| ChannelState::new(Some(c), common_audio_data.song_data_addr()))
-            }),
+            initial_channels: channels.clone(),
+            initial_global: global.clone(),
+            channels,
             tick_counter: TickCounter::default(),
-            global: GlobalState::new(song_data.metadata().tick_clock, &song_data),
+            global,
             stereo_flag,
+            tempo_changes: Self::song_tempo_changes(&song_data, u8::MAX),
+            note_event_callback: None,
             song_data,
             common_audio_data,
         }
@@ -1353,11 +1332,18 @@ where
         subroutine_index: u8,
         stereo_flag: bool,
     ) -> Result<Self, SongSubroutineError> {
+        let global = GlobalState::new(song_data.metadata().tick_clock, &song_data);
+
         let mut out = Self {
             channels: Default::default(),
             tick_counter: TickCounter::default(),
-            global: GlobalState::new(song_data.metadata().tick_clock, &song_data),
+            initial_global: global.clone(),
+            global,
             stereo_flag,
+            // Subroutine tick counters do not match the song's tick counters
+            tempo_changes: Vec::new(),
+            initial_channels: Default::default(),
+            note_event_callback: None,
             song_data,
             common_audio_data,
         };
@@ -1404,44 +1390,107 @@ where
             &mut out.global,
         );
 
+        out.initial_global = out.global.clone();
+        out.initial_channels = out.channels.clone();
+
         Ok(out)
     }
 
+    /// Disables the channels that are not in `mask`.
+    ///
+    /// Bit 0 of `mask` is channel A.  Disabled channels are not interpreted.
+    pub fn with_channel_mask(mut self, mask: u8) -> Self {
+        for (i, c) in self.channels.iter_mut().enumerate() {
+            if mask & (1 << i) == 0 {
+                *c = None;
+            }
+        }
+        self.initial_channels = self.channels.clone();
+        if !self.tempo_changes.is_empty() {
+            self.tempo_changes = Self::song_tempo_changes(&self.song_data, mask);
+        }
+        self
+    }
+
+    /// Sets a callback that is called by `process_ticks()` on every note-on, note-off and
+    /// tempo change event.
+    ///
+    /// Events are emitted in the order the bytecode is processed, which might not be in tick
+    /// order.
+    pub fn set_note_event_callback(&mut self, callback: impl FnMut(NoteEvent) + Send + 'static) {
+        self.note_event_callback = Some(Box::new(callback));
+    }
+
+    fn song_tempo_changes(song_data: &SongData, mask: u8) -> Vec<(TickCounter, TickClock)> {
+        let mut out: Vec<_> = song_data
+            .channels()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .filter_map(|(_, c)| c.as_ref())
+            .flat_map(|c| c.tempo_changes.iter().copied())
+            .collect();
+        out.sort_by_key(|(tc, _)| *tc);
+        out
+    }
+
+    /// Returns the next tempo change that has not been processed and the new tick clock.
+    ///
+    /// Tempo changes inside a loop are only returned once.
+    pub fn pending_tempo_change(&self) -> Option<(TickCounter, TickClock)> {
+        // Instructions at `self.tick_counter` are processed by the next `process_ticks()` call
+        let i = self
+            .tempo_changes
+            .partition_point(|(tc, _)| *tc < self.tick_counter);
+        self.tempo_changes.get(i).copied()
+    }
+
+    pub fn timer_register(&self) -> u8 {
+        self.global.timer_register
+    }
+
     pub fn channels(&self) -> &[Option<ChannelState>; N_MUSIC_CHANNELS] {
         &self.channels
     }
 
     /// Return the channel with the smallest tick-counter and the tick-counter to execute to
+    ///
+    /// Also returns the index of the channel.
     fn next_channel_to_process(
         channels: &mut [Option<ChannelState>; N_MUSIC_CHANNELS],
         target_ticks: TickCounter,
-    ) -> Option<(&mut ChannelState, TickCounter)> {
+    ) -> Option<(usize, &mut ChannelState, TickCounter)> {
         // ::TODO optimise (profile before and after)::
 
-        let mut iter = channels.iter_mut().flatten();
+        let mut iter = channels
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, c)| Some((i, c.as_mut()?)));
 
         let mut smallest = iter.next()?;
         let mut second_smallest_ticks = TickCounter::new(u32::MAX - 1);
 
         if let Some(c) = iter.next() {
-            second_smallest_ticks = c.ticks;
-            if c.ticks < smallest.ticks {
-                second_smallest_ticks = smallest.ticks;
+            second_smallest_ticks = c.1.ticks;
+            if c.1.ticks < smallest.1.ticks {
+                second_smallest_ticks = smallest.1.ticks;
                 smallest = c;
             }
         }
 
         for c in iter {
-            if c.ticks < smallest.ticks {
-                second_smallest_ticks = smallest.ticks;
+            if c.1.ticks < smallest.1.ticks {
+                second_smallest_ticks = smallest.1.ticks;
                 smallest = c;
-            } else if c.ticks < second_smallest_ticks {
-                second_smallest_ticks = c.ticks;
+            } else if c.1.ticks < second_smallest_ticks {
+                second_smallest_ticks = c.1.ticks;
             }
         }
 
+        let (i, smallest) = smallest;
+
         if smallest.ticks < target_ticks {
-            Some((smallest, min(second_smallest_ticks, target_ticks)))
+            Some((i, smallest, min(second_smallest_ticks, target_ticks)))
         } else {
             None
         }
@@ -1456,20 +1505,28 @@ where
 
         let target_ticks = self.tick_counter + ticks;
 
-        while let Some((c, next_channel_ticks)) =
+        let note_event_callback = &mut self.note_event_callback;
+
+        let mut process_next_bytecode =
+            |c: &mut ChannelState, i: usize, g: &mut GlobalState| match note_event_callback {
+                Some(cb) => c.process_next_bytecode_with_note_events(i, g, song_data, cb),
+                None => c.process_next_bytecode(g, song_data),
+            };
+
+        while let Some((i, c, next_channel_ticks)) =
             Self::next_channel_to_process(&mut self.channels, target_ticks)
         {
             debug_assert!(next_channel_ticks >= c.ticks);
             debug_assert!(next_channel_ticks <= target_ticks);
 
-            c.process_next_bytecode(&mut self.global, song_data);
+            process_next_bytecode(c, i, &mut self.global);
             watchdog_counter -= 1;
             if watchdog_counter == 0 {
                 return false;
             }
 
             while c.ticks < next_channel_ticks {
-                c.process_next_bytecode(&mut self.global, song_data);
+                process_next_bytecode(c, i, &mut self.global);
 
                 watchdog_counter -= 1;
                 if watchdog_counter == 0 {
@@ -1493,6 +1550,32 @@ where
         true
     }
 
+    /// Resets the interpreter to tick 0.
+    ///
+    /// The channel mask (if any) is preserved.
+    pub fn reset(&mut self) {
+        self.global = self.initial_global.clone();
+        self.channels = self.initial_channels.clone();
+        self.tick_counter = TickCounter::default();
+    }
+
+    /// Processes the song until `tick_counter()` is `target`.
+    ///
+    /// Resets the interpreter if `target` is before `tick_counter()`.
+    ///
+    /// Returns false if there was a timeout
+    pub fn seek_to_tick(&mut self, target: TickCounter) -> bool {
+        if target < self.tick_counter {
+            self.reset();
+        }
+
+        let ticks = TickCounter::new(target.value() - self.tick_counter.value());
+        if ticks.is_zero() {
+            return true;
+        }
+        self.process_ticks(ticks)
+    }
+
     #[must_use]
     pub fn process_song_skip_ticks(&mut self, ticks: TickCounter) -> bool {
         assert!(self.tick_counter.is_zero());
@@ -1510,6 +1593,74 @@ where
         self.tick_counter
     }
 
+    /// Processes the song to `target_tick` and returns a hash of the state of every music
+    /// channel.
+    ///
+    /// The checksum hashes the tick counter, the timer register, the echo settings and the
+    /// fields of every channel state field-by-field (see `ChannelState::hash_state()`).
+    /// Fields are hashed explicitly, instead of hashing the `Debug` output, so the checksum is
+    /// not changed by formatting changes and does not depend on the target's pointer width.
+    /// The checksum is identical every time the same song is processed to the same tick.
+    /// Used to detect bytecode interpreter regressions.
+    ///
+    /// The song is not processed if `target_tick` <= `tick_counter()`.
+    /// A watchdog timeout is not reported, the checksum is of the state when the timeout occurred.
+    pub fn checksum_at_tick(&mut self, target_tick: TickCounter) -> u64 {
+        if target_tick > self.tick_counter {
+            let ticks = TickCounter::new(target_tick.value() - self.tick_counter.value());
+            let _ = self.process_ticks(ticks);
+        }
+
+        let mut hasher = FxHasher64::default();
+
+        self.tick_counter.value().hash(&mut hasher);
+
+        self.global.timer_register.hash(&mut hasher);
+        self.global.echo.max_edl.hash(&mut hasher);
+        self.global.echo.to_driver_data().hash(&mut hasher);
+
+        for c in &self.channels {
+            match c {
+                Some(c) => {
+                    1_u8.hash(&mut hasher);
+                    c.hash_state(&mut hasher);
+                }
+                None => 0_u8.hash(&mut hasher),
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Returns the instrument and envelope of each music channel.
+    ///
+    /// `None` if the channel is disabled or has not set an instrument.
+    pub fn channel_instrument_state(&self) -> Vec<Option<(u8, EnvelopeKind)>> {
+        self.channels
+            .iter()
+            .map(|c| {
+                let c = c.as_ref()?;
+                let envelope = match c.adsr_or_gain_override {
+                    Some((adsr1, adsr2_or_gain)) => {
+                        EnvelopeKind::Override(Envelope::from_engine_value(adsr1, adsr2_or_gain))
+                    }
+                    None => EnvelopeKind::Instrument,
+                };
+                Some((c.instrument?, envelope))
+            })
+            .collect()
+    }
+
+    /// Returns the display state of each music channel.
+    ///
+    /// Cheaper than `write_to_emulator()`.
+    pub fn channel_activity(&self) -> [ChannelDisplayState; N_MUSIC_CHANNELS] {
+        std::array::from_fn(|i| match &self.channels[i] {
+            Some(c) => c.to_display_state(),
+            None => ChannelDisplayState::default(),
+        })
+    }
+
     pub fn song_header_edl(&self) -> u8 {
         self.global.echo.song_header_edl()
     }
@@ -1684,7 +1835,7 @@ fn build_channel(
             detune,
         } => common.pitch_table_entry(target_opcode, instrument, detune),
         ChannelNote::PlayPitch(p) => p.to_le_bytes().into(),
-        ChannelNote::PortamentoPitch { target_pitch } => target_pitch.to_le_bytes().into(),
+        ChannelNote::PortamentoPitch => c.portamento_pitch.u16_value().to_le_bytes().into(),
     };
 
     let key_on = if pmon_source {
@@ -1693,7 +1844,7 @@ fn build_channel(
             ChannelNote::PlayNote { .. }
             | ChannelNote::PlayPitch(..)
             | ChannelNote::Portamento { .. }
-            | ChannelNote::PortamentoPitch { .. } => {
+            | ChannelNote::PortamentoPitch => {
                 if c.next_event_is_key_off {
                     delay > 1
                 } else {
@@ -1722,8 +1873,30 @@ fn build_channel(
         c.temp_gain
     };
 
-    let volume_soa = c.volume.as_soa();
-    let pan_soa = c.pan.as_soa();
+    let (portamento_direction, portamento_speed, portamento_target) = match c.note {
+        ChannelNote::PortamentoPitch => {
+            let p = &c.portamento_pitch;
+            let direction = match p.direction() {
+                d @ (SmoothValueDirection::PortamentoUp | SmoothValueDirection::PortamentoDown) => {
+                    d as u8
+                }
+                _ => 0,
+            };
+            (
+                direction,
+                p.offset().to_le_bytes()[0],
+                p.portamento_target(),
+            )
+        }
+        _ => (
+            0,
+            UNINITIALISED,
+            u16::from_le_bytes([UNINITIALISED, UNINITIALISED]),
+        ),
+    };
+
+    let volume_soa = pan_vol_soa(&c.volume);
+    let pan_soa = pan_vol_soa(&c.pan);
 
     assert!(c.stack_pointer <= BC_CHANNEL_STACK_SIZE);
     assert!(c.loop_stack_pointer + BC_STACK_BYTES_PER_LOOP <= BC_CHANNEL_STACK_SIZE);
@@ -1762,6 +1935,10 @@ fn build_channel(
             volume: volume_soa,
             pan: pan_soa,
             invert_flags: c.invert_flags,
+            portamento_direction,
+            portamento_speed,
+            portamento_target_l: portamento_target.to_le_bytes()[0],
+            portamento_target_h: portamento_target.to_le_bytes()[1],
             vibrato_pitch_offset_per_tick: c.vibrato_pitch_offset_per_tick,
             vibrato_tick_counter: vibrato_tick_counter_start,
             vibrato_tick_counter_start,
@@ -1837,6 +2014,10 @@ fn unused_channel(channel_index: usize) -> Channel {
                 half_wavelength: UNINITIALISED,
             },
             invert_flags: 0,
+            portamento_direction: 0,
+            portamento_speed: UNINITIALISED,
+            portamento_target_l: UNINITIALISED,
+            portamento_target_h: UNINITIALISED,
             vibrato_pitch_offset_per_tick: 0,
             vibrato_tick_counter: UNINITIALISED,
             vibrato_tick_counter_start: UNINITIALISED,
@@ -1885,6 +2066,69 @@ pub trait Emulator {
     }
 }
 
+/// An `Emulator` that records the audio-RAM and register writes without emulating the S-SMP.
+///
+/// The program counter is always in the main loop (as if the audio driver is paused).
+pub struct RecordingEmulator {
+    apuram: Box<[u8; 0x10000]>,
+    dsp: [u8; 128],
+    smp: [u8; 256],
+}
+
+impl RecordingEmulator {
+    const DSP_ADDR_REGISTER: u8 = 0xf2;
+    const DSP_DATA_REGISTER: u8 = 0xf3;
+
+    pub fn new() -> Self {
+        Self {
+            apuram: Box::new([0; 0x10000]),
+            dsp: [0; 128],
+            smp: [0; 256],
+        }
+    }
+
+    pub fn apuram(&self) -> &[u8; 0x10000] {
+        &self.apuram
+    }
+
+    pub fn dsp_registers(&self) -> &[u8; 128] {
+        &self.dsp
+    }
+
+    pub fn smp_registers(&self) -> &[u8; 256] {
+        &self.smp
+    }
+}
+
+impl Default for RecordingEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator for RecordingEmulator {
+    fn apuram_mut(&mut self) -> &mut [u8; 0x10000] {
+        &mut self.apuram
+    }
+
+    fn write_smp_register(&mut self, addr: u8, value: u8) {
+        self.smp[usize::from(addr)] = value;
+
+        if addr == Self::DSP_DATA_REGISTER {
+            let dsp_addr = usize::from(self.smp[usize::from(Self::DSP_ADDR_REGISTER)]);
+
+            // DSP addresses $80-$FF are read-only
+            if let Some(r) = self.dsp.get_mut(dsp_addr) {
+                *r = value;
+            }
+        }
+    }
+
+    fn program_counter(&self) -> u16 {
+        addresses::MAIN_LOOP_CODE_RANGE.start
+    }
+}
+
 /// Writes `InterpreterOutput` to the emulator.
 ///
 /// REQUIRES:
@@ -2025,7 +2269,20 @@ impl InterpreterOutput {
 
                 soa_write_u8(addresses::CHANNEL_INVERT_FLAGS, c.invert_flags);
 
-                // Not interpreting portamento
+                // Only pitch portamento is interpreted
+                soa_write_u8(
+                    addresses::CHANNEL_PORTAMENTO_DIRECTION,
+                    c.portamento_direction,
+                );
+                soa_write_u8(addresses::CHANNEL_PORTAMENTO_SPEED, c.portamento_speed);
+                soa_write_u8(
+                    addresses::CHANNEL_PORTAMENTO_TARGET_L,
+                    c.portamento_target_l,
+                );
+                soa_write_u8(
+                    addresses::CHANNEL_PORTAMENTO_TARGET_H,
+                    c.portamento_target_h,
+                );
 
                 soa_write_u8(
                     addresses::CHANNEL_VIBRATO_PITCH_OFFSET_PER_TICK,
@@ -2094,9 +2351,23 @@ impl InterpreterOutput {
 
 #[cfg(test)]
 mod test {
-    use super::{ChannelState, EchoVariables, GlobalState};
-    use crate::driver_constants::SONG_HEADER_SIZE;
-    use crate::time::{TickCounter, MIN_TICK_TIMER};
+    use super::{
+        ChannelState, EchoVariables, Emulator, GlobalState, NoteEvent, NoteEventKind,
+        RecordingEmulator, SongInterpreter,
+    };
+    use crate::common_audio_data::{build_common_audio_data, CommonAudioData};
+    use crate::data::{validate_instrument_and_sample_names, Instrument, Name, TextFile};
+    use crate::driver_constants::{addresses, SONG_HEADER_SIZE, S_SMP_TIMER_0_REGISTER};
+    use crate::envelope::{Envelope, Gain};
+    use crate::mml::compile_mml;
+    use crate::notes::{Note, NoteRange, Octave};
+    use crate::pitch_table::build_pitch_table;
+    use crate::samples::{combine_samples, InstrumentSampleData, SampleSampleData};
+    use crate::songs::SongData;
+    use crate::sound_effects::{blank_compiled_sound_effects, CompiledSfxSubroutines};
+    use crate::time::{TickClock, TickCounter, MIN_TICK_TIMER};
+
+    use std::sync::{Arc, Mutex};
 
     fn blank_global_state() -> GlobalState {
         GlobalState {
@@ -2146,4 +2417,265 @@ mod test {
             assert_eq!(o, expected, "opocde: {opcode}");
         }
     }
+
+    fn compile_test_song(mml: &str) -> (CommonAudioData, SongData) {
+        let samples = combine_samples(
+            [].as_slice() as &[InstrumentSampleData],
+            [].as_slice() as &[SampleSampleData],
+        )
+        .unwrap();
+        let common_audio_data = build_common_audio_data(
+            &samples,
+            &CompiledSfxSubroutines::blank(),
+            &blank_compiled_sound_effects(),
+        )
+        .unwrap();
+
+        // compile_mml requires at least one instrument
+        let inst = Instrument {
+            name: Name::try_from("dummy_instrument".to_owned()).unwrap(),
+            source: Default::default(),
+            freq: 500.0,
+            loop_setting: crate::data::LoopSetting::None,
+            evaluator: Default::default(),
+            ignore_gaussian_overflow: false,
+            auto_detect_loop: false,
+            dc_offset_removal: false,
+            normalize: false,
+            note_range: NoteRange {
+                first: Octave::try_new(2).unwrap(),
+                last: Octave::try_new(6).unwrap(),
+            },
+            envelope: Envelope::Gain(Gain::new(0)),
+            comment: None,
+        };
+        let instruments = validate_instrument_and_sample_names([inst].iter(), [].iter()).unwrap();
+
+        let song_data = compile_mml(
+            &TextFile {
+                contents: mml.to_owned(),
+                path: None,
+                file_name: "".to_owned(),
+            },
+            None,
+            &instruments,
+            &build_pitch_table(&instruments).unwrap(),
+        )
+        .unwrap();
+
+        (common_audio_data, song_data)
+    }
+
+    #[test]
+    fn test_timer_register_changes_at_tempo_change_tick() {
+        let (common_audio_data, song_data) =
+            compile_test_song("#Timer 100\nA r%10 T150 r%20 T200 r%5\nB r%15 T120 r%15");
+
+        let tc = |t| TickClock::try_from(t).unwrap();
+        let expected_changes = [(10, 150), (15, 120), (30, 200)];
+
+        let mut interpreter = SongInterpreter::new(&common_audio_data, &song_data, false);
+        assert_eq!(interpreter.timer_register(), 100);
+
+        let mut expected_timer = 100;
+        for tick in 0..40 {
+            let pending = expected_changes.iter().find(|(t, _)| *t >= tick);
+            assert_eq!(
+                interpreter.pending_tempo_change(),
+                pending.map(|&(t, c)| (TickCounter::new(t), tc(c))),
+                "tick {tick}"
+            );
+
+            assert!(interpreter.process_ticks(TickCounter::new(1)));
+
+            if let Some(&(_, c)) = expected_changes.iter().find(|(t, _)| *t == tick) {
+                expected_timer = c;
+            }
+            assert_eq!(interpreter.timer_register(), expected_timer, "tick {tick}");
+        }
+
+        // Channel B tempo change is ignored when channel B is disabled
+        let interpreter =
+            SongInterpreter::new(&common_audio_data, &song_data, false).with_channel_mask(0b01);
+        assert_eq!(
+            interpreter.pending_tempo_change(),
+            Some((TickCounter::new(10), tc(150)))
+        );
+    }
+
+    #[test]
+    fn test_recording_emulator_dsp_registers() {
+        let mut emu = RecordingEmulator::new();
+
+        emu.write_smp_register(0xf2, 0x0c);
+        emu.write_smp_register(0xf3, 0x7f);
+        assert_eq!(emu.dsp_registers()[0x0c], 0x7f);
+
+        // Read-only
+        emu.write_smp_register(0xf2, 0x8c);
+        emu.write_smp_register(0xf3, 0x12);
+        assert_eq!(emu.dsp_registers()[0x0c], 0x7f);
+
+        assert_eq!(emu.smp_registers()[0xf2], 0x8c);
+        assert_eq!(emu.smp_registers()[0xf3], 0x12);
+    }
+
+    #[test]
+    fn test_write_to_recording_emulator() {
+        let (common_audio_data, song_data) = compile_test_song("#Timer 100\nA r%10 T150 r%20");
+
+        let mut interpreter = SongInterpreter::new(&common_audio_data, &song_data, false);
+        assert!(interpreter.process_ticks(TickCounter::new(15)));
+
+        let mut emu = RecordingEmulator::new();
+
+        // `write_to_emulator()` verifies the song pointer
+        let song_ptr = usize::from(addresses::SONG_PTR);
+        emu.apuram_mut()[song_ptr..song_ptr + 2]
+            .copy_from_slice(&common_audio_data.song_data_addr().to_le_bytes());
+
+        interpreter.write_to_emulator(&mut emu);
+
+        assert_eq!(
+            emu.smp_registers()[usize::from(S_SMP_TIMER_0_REGISTER)],
+            150
+        );
+
+        let stc = usize::from(addresses::SONG_TICK_COUNTER);
+        assert_eq!(emu.apuram()[stc..stc + 2], 15_u16.to_le_bytes());
+    }
+
+    #[test]
+    fn test_seek_to_tick() {
+        let (common_audio_data, song_data) = compile_test_song(
+            "#Timer 100\nA r%10 T150 v4 r%20 T200 [r%5 v+1]3\nB r%15 T120 p+20 r%15",
+        );
+
+        let state = |i: &SongInterpreter<&CommonAudioData, &SongData>| {
+            (i.timer_register(), format!("{:?}", i.channels()))
+        };
+
+        let mut interpreter = SongInterpreter::new(&common_audio_data, &song_data, false);
+        let initial_state = state(&interpreter);
+
+        for target in [25, 40, 12, 12, 0, 33, 50] {
+            let mut expected = SongInterpreter::new(&common_audio_data, &song_data, false);
+            if target > 0 {
+                assert!(expected.process_ticks(TickCounter::new(target)));
+            }
+
+            assert!(interpreter.seek_to_tick(TickCounter::new(target)));
+            assert_eq!(interpreter.tick_counter(), TickCounter::new(target));
+            assert_eq!(state(&interpreter), state(&expected), "tick {target}");
+        }
+
+        interpreter.reset();
+        assert_eq!(interpreter.tick_counter(), TickCounter::new(0));
+        assert_eq!(state(&interpreter), initial_state);
+
+        // reset() preserves the channel mask
+        let mut interpreter =
+            SongInterpreter::new(&common_audio_data, &song_data, false).with_channel_mask(0b10);
+        assert!(interpreter.seek_to_tick(TickCounter::new(20)));
+        interpreter.reset();
+        assert!(interpreter.channels()[0].is_none());
+        assert!(interpreter.channels()[1].is_some());
+    }
+
+    #[test]
+    fn test_note_events() {
+        let (common_audio_data, song_data) = compile_test_song(
+            "@0 dummy_instrument\nA @0 o4 c8 d8 & e8 r8 {fg}8 P1000,8 a8\nB @0 o3 c4",
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let mut interpreter = SongInterpreter::new(&common_audio_data, &song_data, false);
+        interpreter.set_note_event_callback({
+            let events = events.clone();
+            move |e| events.lock().unwrap().push(e)
+        });
+        assert!(interpreter.process_ticks(TickCounter::new(200)));
+
+        let note_id = |pitch: &str, octave: u32| {
+            let p = [
+                "c", "c+", "d", "d+", "e", "f", "f+", "g", "g+", "a", "a+", "b",
+            ];
+            let p = p.iter().position(|&p2| p2 == pitch).unwrap() as u32;
+            Note::from_note_id_u32(octave * 12 + p).unwrap()
+        };
+        let on = |c: usize, t: u32, pitch: &str, octave: u32| NoteEvent {
+            channel_index: c,
+            kind: NoteEventKind::NoteOn {
+                note: note_id(pitch, octave),
+                instrument: Some(0),
+            },
+            tick: TickCounter::new(t),
+        };
+        let off = |c: usize, t: u32| NoteEvent {
+            channel_index: c,
+            kind: NoteEventKind::NoteOff,
+            tick: TickCounter::new(t),
+        };
+
+        let mut events = events.lock().unwrap().clone();
+        events.sort_by_key(|e| (e.tick, e.channel_index));
+
+        assert_eq!(
+            events,
+            [
+                on(0, 0, "c", 4),
+                on(1, 0, "c", 3),
+                off(0, 12),
+                on(0, 12, "d", 4),
+                off(0, 24),
+                on(0, 24, "e", 4),
+                off(1, 24),
+                off(0, 36),
+                // Portamento (`f` is slurred into the portamento)
+                on(0, 48, "f", 4),
+                off(0, 49),
+                on(0, 49, "g", 4),
+                off(0, 60),
+                // The play pitch does not emit note events
+                on(0, 72, "a", 4),
+                off(0, 84),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tempo_change_events() {
+        let (common_audio_data, song_data) = compile_test_song(
+            "#Timer 100\n!s r%5 T180 r%5\nA r%10 T150 r%20 T200 r%5\nB r%15 !s T120 r%15",
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let mut interpreter = SongInterpreter::new(&common_audio_data, &song_data, false);
+        interpreter.set_note_event_callback({
+            let events = events.clone();
+            move |e| events.lock().unwrap().push(e)
+        });
+        assert!(interpreter.process_ticks(TickCounter::new(50)));
+
+        let tempo = |c: usize, t: u32, timer_register: u8| NoteEvent {
+            channel_index: c,
+            kind: NoteEventKind::TempoChange { timer_register },
+            tick: TickCounter::new(t),
+        };
+
+        let mut events = events.lock().unwrap().clone();
+        events.sort_by_key(|e| (e.tick, e.channel_index));
+
+        assert_eq!(
+            events,
+            [
+                tempo(0, 10, 150),
+                tempo(1, 20, 180),
+                tempo(1, 25, 120),
+                tempo(0, 30, 200),
+            ]
+        );
+    }
 }
@@ -23,10 +23,10 @@ use crate::invert_flags::InvertFlags;
 use crate::mml::IdentifierBuf;
 use crate::notes::Note;
 use crate::notes::SEMITONES_PER_OCTAVE;
-use crate::pitch_table::{PitchTable, PlayPitchFrequency, PITCH_REGISTER_MAX};
+use crate::pitch_table::{PitchTable, PlayPitchFrequency, TuningTable, PITCH_REGISTER_MAX};
 use crate::songs::LoopPoint;
 use crate::subroutines::{NoSubroutines, SubroutineStore};
-use crate::time::{Bpm, TickClock, TickCounter};
+use crate::time::{Bpm, MinRestTicks, TempoScale, TickClock, TickCounter};
 use crate::value_newtypes::{i16_value_newtype, u8_value_newtype, SignedValueNewType};
 use crate::FilePosRange;
 
@@ -431,6 +431,21 @@ enum AfterPlayNote {
     },
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ChannelCompileOptions<'a> {
+    /// Maximum number of bytecode bytes in the channel (excluding the terminator)
+    pub max_bytecode_bytes: Option<usize>,
+
+    /// Percentage applied to all `t` (set song tempo) commands
+    pub tempo_scale: TempoScale,
+
+    /// Minimum length of a rest after a note (applied by the MML parser)
+    pub min_rest_ticks: Option<MinRestTicks>,
+
+    /// Notes are played at the frequency in the song's `#TuningTable` (if set)
+    pub tuning_table: Option<&'a TuningTable>,
+}
+
 pub(crate) struct ChannelBcGenerator<'a> {
     pitch_table: &'a PitchTable,
     mml_file: &'a str,
@@ -444,9 +459,13 @@ pub(crate) struct ChannelBcGenerator<'a> {
     quantize: Quantize,
 
     loop_point: Option<LoopPoint>,
+
+    bc_start: usize,
+    options: ChannelCompileOptions<'a>,
 }
 
 impl<'a> ChannelBcGenerator<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         bc_data: Vec<u8>,
         pitch_table: &'a PitchTable,
@@ -455,8 +474,11 @@ impl<'a> ChannelBcGenerator<'a> {
         mml_instruments: &'a [MmlInstrument],
         subroutines: &'a dyn SubroutineStore,
         context: BytecodeContext,
+        options: ChannelCompileOptions<'a>,
     ) -> ChannelBcGenerator<'a> {
         ChannelBcGenerator {
+            bc_start: bc_data.len(),
+            options,
             pitch_table,
             mml_file,
             instruments: mml_instruments,
@@ -558,6 +580,18 @@ impl<'a> ChannelBcGenerator<'a> {
         }
     }
 
+    /// Converts `note` to a pitch if the song has a `#TuningTable`.
+    ///
+    /// Like `PF`, tuned notes are not detuned.
+    fn apply_tuning_table(&self, note: NoteOrPitch) -> Result<NoteOrPitch, ChannelError> {
+        match (note, self.options.tuning_table) {
+            (NoteOrPitch::Note(n), Some(tt)) => Ok(NoteOrPitch::Pitch(
+                tt.to_vxpitch(n, self.bc.get_instrument())?,
+            )),
+            (n, _) => Ok(n),
+        }
+    }
+
     fn play_note_or_pitch_with_detune(
         &mut self,
         note: NoteOrPitch,
@@ -1644,7 +1678,13 @@ impl<'a> ChannelBcGenerator<'a> {
                 let (pn_ticks, after) =
                     self.split_play_note_length(length, is_slur, rest_after_note)?;
 
-                self.play_note_with_mp_and_detune_cents(note, pn_ticks)?;
+                match self.options.tuning_table {
+                    Some(tt) => {
+                        let pitch = tt.to_vxpitch(note, self.bc.get_instrument())?;
+                        self.bc.play_pitch(pitch, pn_ticks);
+                    }
+                    None => self.play_note_with_mp_and_detune_cents(note, pn_ticks)?,
+                }
                 self.after_note(after)?;
             }
 
@@ -1699,6 +1739,12 @@ impl<'a> ChannelBcGenerator<'a> {
                 tie_length,
                 rest_after_note,
             } => {
+                let note1 = match note1 {
+                    Some(n) => Some(self.apply_tuning_table(n)?),
+                    None => None,
+                };
+                let note2 = self.apply_tuning_table(note2)?;
+
                 self.portamento(
                     note1,
                     note2,
@@ -1716,7 +1762,12 @@ impl<'a> ChannelBcGenerator<'a> {
                 total_length,
                 note_length,
             } => {
-                self.broken_chord(notes, *total_length, *note_length)?;
+                let notes = notes
+                    .iter()
+                    .map(|&n| self.apply_tuning_table(n))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.broken_chord(&notes, *total_length, *note_length)?;
             }
 
             Command::DisableNoise => {
@@ -1809,7 +1860,7 @@ impl<'a> ChannelBcGenerator<'a> {
             }
 
             &Command::SetSongTempo(bpm) => {
-                self.set_song_tick_clock(bpm.to_tick_clock()?)?;
+                self.set_song_tick_clock(bpm.to_scaled_tick_clock(self.options.tempo_scale)?)?;
             }
             &Command::SetSongTickClock(tick_clock) => {
                 self.set_song_tick_clock(tick_clock)?;
@@ -1852,6 +1903,19 @@ impl<'a> ChannelBcGenerator<'a> {
             }
         }
 
+        self.test_bytecode_size()
+    }
+
+    fn test_bytecode_size(&mut self) -> Result<(), ChannelError> {
+        if let Some(limit) = self.options.max_bytecode_bytes {
+            let current = self.bc.get_bytecode_len() - self.bc_start;
+            if current > limit {
+                // Only report the error once per channel
+                self.options.max_bytecode_bytes = None;
+
+                return Err(ChannelError::ChannelBytecodeTooBig { limit, current });
+            }
+        }
         Ok(())
     }
 
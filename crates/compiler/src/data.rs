@@ -11,7 +11,7 @@ use crate::errors::{
     DeserializeError, FileError, ProjectFileError, ProjectFileErrors, UniqueNameListError,
     ValueError,
 };
-use crate::notes::Octave;
+use crate::notes::NoteRange;
 use crate::path::{ParentPathBuf, SourcePathBuf};
 
 use std::collections::HashMap;
@@ -22,12 +22,15 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use serde::ser::SerializeStruct;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 
 pub const MAX_FILE_SIZE: u32 = 5 * 1024 * 1024;
 
 pub const PROJECT_FILE_EXTENSION: &str = "terrificaudio";
 
+const MAX_NAME_LENGTH: usize = 64;
+
 #[derive(Deserialize, Serialize, Clone, Hash, Eq, PartialEq, Debug)]
 #[serde(try_from = "String")]
 pub struct Name(String);
@@ -43,6 +46,10 @@ impl Name {
     }
 
     pub fn is_valid_name(s: &str) -> bool {
+        if s.len() > MAX_NAME_LENGTH {
+            return false;
+        }
+
         let mut iter = s.bytes();
 
         // first character
@@ -63,8 +70,14 @@ impl Name {
         true
     }
 
+    pub const fn max_len() -> usize {
+        MAX_NAME_LENGTH
+    }
+
     pub fn try_new(s: String) -> Result<Self, ValueError> {
-        if Self::is_valid_name(&s) {
+        if s.len() > MAX_NAME_LENGTH {
+            Err(ValueError::NameTooLong(s.len()))
+        } else if Self::is_valid_name(&s) {
             Ok(Self(s))
         } else {
             Err(ValueError::InvalidName(s))
@@ -89,6 +102,7 @@ impl Name {
                     s.replace_range(0..0, "_");
                 }
             }
+            s.truncate(MAX_NAME_LENGTH);
 
             Self(s)
         }
@@ -101,6 +115,11 @@ impl Name {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Returns the name, unquoted, for use in an identifier (ie, an exported enum).
+    pub fn as_identifier(&self) -> &str {
+        &self.0
+    }
 }
 
 impl TryFrom<String> for Name {
@@ -119,27 +138,30 @@ impl FromStr for Name {
     }
 }
 
+/// Displays the name as a quoted string (for error messages).
+///
+/// Use `Name::as_identifier()` when outputting the name as an identifier.
 impl Display for Name {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "\"{}\"", self.0)
     }
 }
 
 // I am including the filter as part of the enum item name for 3 reasons:
 //   1. DupeBlockHack cannot be used with loop_point_filter=BrrFilter::Filter0.
-//   2. Simpler JSON format (only 1 fields in `loop_setting`)
+//   2. Simpler JSON format (only 1 field in the serialized `loop` string)
 //   3. Backwards compatible with the v0.0.3 LoopSetting serde JSON
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
-#[serde(tag = "loop", content = "loop_setting")]
+//
+// LoopSetting is serialized as a single `"loop": "name:argument"` field
+// (ie, `"loop": "none"` or `"loop": "loop_with_filter:16"`).
+#[derive(Clone, PartialEq, Debug)]
 pub enum LoopSetting {
     /// This setting depends on the source file:
     ///     * wav files - The sample does not loop
     ///     * brr files - The sample loops if the brr file has a 2 byte loop header and the loop flag is set.
-    #[serde(rename = "none")]
     None,
 
     /// The sample is a looping BRR file
-    #[serde(rename = "override_brr_loop_point")]
     OverrideBrrLoopPoint(usize),
 
     /// Loop point in samples.
@@ -147,29 +169,24 @@ pub enum LoopSetting {
     /// This mode will not reset the BRR filter at the loop point.  It can create better sounding
     /// sample, however most samples will not loop perfectly, which can add low-frequency
     /// oscillation or glitches to the sample.
-    #[serde(rename = "loop_with_filter")]
     LoopWithFilter(usize),
 
     /// Resets the BRR filter at the loop point.
     ///
     /// The BRR block after the loop point will always use BRR filter 0, which ensures
     /// perfect looping at the cost of reduced quality for the BRR block after the loop point.
-    #[serde(rename = "loop_reset_filter")]
     LoopResetFilter(usize),
 
     /// Loop the sample and use BRR filter 1 at the loop-point.
     /// Argument is loop point in samples.
-    #[serde(rename = "loop_filter_1")]
     LoopFilter1(usize),
 
     /// Loop the sample and use BRR filter 2 at the loop-point.
     /// Argument is loop point in samples.
-    #[serde(rename = "loop_filter_2")]
     LoopFilter2(usize),
 
     /// Loop the sample and use BRR filter 3 at the loop-point.
     /// Argument is loop point in samples.
-    #[serde(rename = "loop_filter_3")]
     LoopFilter3(usize),
 
     /// Duplicates `N` blocks to the end of the sample in an attempt to improve the sample quality of the first-looping BRR block.
@@ -177,22 +194,18 @@ pub enum LoopSetting {
     ///  * This mode will not reset the filter at the loop point.
     ///  * Most samples created by this hack will not loop perfectly, which adds low-frequency oscillation to the sample.
     ///  * dupe_block_hack may create create a glitched sample, hence the name `dupe_block_hack`.
-    #[serde(rename = "dupe_block_hack")]
     DupeBlockHack(usize),
 
     // DupeBlockHack that uses loop-point BRR filter 1.
     // (See `DupeBlockHack`)
-    #[serde(rename = "dupe_block_hack_filter_1")]
     DupeBlockHackFilter1(usize),
 
     // DupeBlockHack that uses loop-point BRR filter 2.
     // (See `DupeBlockHack`)
-    #[serde(rename = "dupe_block_hack_filter_2")]
     DupeBlockHackFilter2(usize),
 
     // DupeBlockHack that uses loop-point BRR filter 3.
     // (See `DupeBlockHack`)
-    #[serde(rename = "dupe_block_hack_filter_3")]
     DupeBlockHackFilter3(usize),
 }
 
@@ -207,9 +220,120 @@ impl LoopSetting {
             Self::LoopFilter2(_) => "loop_filter_2",
             Self::LoopFilter3(_) => "loop_filter_3",
             Self::DupeBlockHack(_) => "dupe_block_hack",
-            Self::DupeBlockHackFilter1(_) => "dupe_block_hack_filter_1)",
-            Self::DupeBlockHackFilter2(_) => "dupe_block_hack_filter_2)",
-            Self::DupeBlockHackFilter3(_) => "dupe_block_hack_filter_3)",
+            Self::DupeBlockHackFilter1(_) => "dupe_block_hack_filter_1",
+            Self::DupeBlockHackFilter2(_) => "dupe_block_hack_filter_2",
+            Self::DupeBlockHackFilter3(_) => "dupe_block_hack_filter_3",
+        }
+    }
+
+    pub fn argument(&self) -> Option<usize> {
+        match self {
+            Self::None => None,
+            Self::OverrideBrrLoopPoint(a)
+            | Self::LoopWithFilter(a)
+            | Self::LoopResetFilter(a)
+            | Self::LoopFilter1(a)
+            | Self::LoopFilter2(a)
+            | Self::LoopFilter3(a)
+            | Self::DupeBlockHack(a)
+            | Self::DupeBlockHackFilter1(a)
+            | Self::DupeBlockHackFilter2(a)
+            | Self::DupeBlockHackFilter3(a) => Some(*a),
+        }
+    }
+
+    /// The BRR filter used by the first BRR block after the loop point (if it is forced)
+    pub fn loop_filter(&self) -> Option<brr::BrrFilter> {
+        match self {
+            Self::None
+            | Self::OverrideBrrLoopPoint(_)
+            | Self::LoopWithFilter(_)
+            | Self::DupeBlockHack(_) => None,
+            Self::LoopResetFilter(_) => Some(brr::BrrFilter::Filter0),
+            Self::LoopFilter1(_) | Self::DupeBlockHackFilter1(_) => Some(brr::BrrFilter::Filter1),
+            Self::LoopFilter2(_) | Self::DupeBlockHackFilter2(_) => Some(brr::BrrFilter::Filter2),
+            Self::LoopFilter3(_) | Self::DupeBlockHackFilter3(_) => Some(brr::BrrFilter::Filter3),
+        }
+    }
+
+    fn from_serializer_value(value: &str, argument: Option<usize>) -> Option<Self> {
+        match (value, argument) {
+            ("none", None) => Some(Self::None),
+            ("override_brr_loop_point", Some(a)) => Some(Self::OverrideBrrLoopPoint(a)),
+            ("loop_with_filter", Some(a)) => Some(Self::LoopWithFilter(a)),
+            ("loop_reset_filter", Some(a)) => Some(Self::LoopResetFilter(a)),
+            ("loop_filter_1", Some(a)) => Some(Self::LoopFilter1(a)),
+            ("loop_filter_2", Some(a)) => Some(Self::LoopFilter2(a)),
+            ("loop_filter_3", Some(a)) => Some(Self::LoopFilter3(a)),
+            ("dupe_block_hack", Some(a)) => Some(Self::DupeBlockHack(a)),
+            ("dupe_block_hack_filter_1", Some(a)) => Some(Self::DupeBlockHackFilter1(a)),
+            ("dupe_block_hack_filter_2", Some(a)) => Some(Self::DupeBlockHackFilter2(a)),
+            ("dupe_block_hack_filter_3", Some(a)) => Some(Self::DupeBlockHackFilter3(a)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LoopSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.argument() {
+            Some(a) => write!(f, "{}:{}", self.serialier_value(), a),
+            None => f.write_str(self.serialier_value()),
+        }
+    }
+}
+
+impl FromStr for LoopSetting {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ValueError::InvalidLoopSetting(s.to_owned());
+
+        let (value, argument) = match s.split_once(':') {
+            Some((v, a)) => (v, Some(a.parse().map_err(|_| err())?)),
+            None => (s, None),
+        };
+
+        Self::from_serializer_value(value, argument).ok_or_else(err)
+    }
+}
+
+impl Serialize for LoopSetting {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("LoopSetting", 1)?;
+        s.serialize_field("loop", &self.to_string())?;
+        s.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for LoopSetting {
+    fn deserialize<D>(deserializer: D) -> Result<LoopSetting, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct LoopFields {
+            #[serde(rename = "loop")]
+            value: String,
+
+            // Older project files stored the argument in a separate field
+            // (ie, `"loop": "loop_with_filter", "loop_setting": 16`)
+            #[serde(default)]
+            loop_setting: Option<usize>,
+        }
+
+        let fields = LoopFields::deserialize(deserializer)?;
+
+        match fields.loop_setting {
+            None => fields.value.parse().map_err(serde::de::Error::custom),
+            Some(a) => {
+                LoopSetting::from_serializer_value(&fields.value, Some(a)).ok_or_else(|| {
+                    serde::de::Error::custom(ValueError::InvalidLoopSetting(fields.value.clone()))
+                })
+            }
         }
     }
 }
@@ -306,14 +430,53 @@ pub struct Instrument {
     #[serde(default)]
     pub ignore_gaussian_overflow: bool,
 
-    pub first_octave: Octave,
-    pub last_octave: Octave,
+    /// If set and `loop_setting` is `None`, the loop point of a wav file's `smpl` or `cue `
+    /// chunk is used.
+    #[serde(default)]
+    pub auto_detect_loop: bool,
+
+    /// Subtract the mean of a wav file's samples before encoding
+    #[serde(default)]
+    pub dc_offset_removal: bool,
+
+    /// Scale a wav file's samples to the peak of the i16 range before encoding
+    #[serde(default)]
+    pub normalize: bool,
+
+    #[serde(flatten)]
+    pub note_range: NoteRange,
 
     pub envelope: Envelope,
 
     pub comment: Option<String>,
 }
 
+impl Instrument {
+    /// Returns the SHA-256 hash of the instrument's source file.
+    ///
+    /// Returns `None` on I/O error or if the file is larger than `MAX_FILE_SIZE`.
+    pub fn source_content_hash(&self, parent_path: &Path) -> Option<[u8; 32]> {
+        let parent_path = ParentPathBuf::new(parent_path.to_path_buf());
+
+        let file = File::open(self.source.to_path(&parent_path)).ok()?;
+
+        let mut data = Vec::new();
+        file.take(u64::from(MAX_FILE_SIZE) + 1)
+            .read_to_end(&mut data)
+            .ok()?;
+
+        if data.len() > MAX_FILE_SIZE as usize {
+            return None;
+        }
+        Some(content_hash(&data))
+    }
+}
+
+/// Returns the SHA-256 hash of a file's contents
+pub(crate) fn content_hash(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub struct Sample {
     pub name: Name,
@@ -355,6 +518,10 @@ impl Default for DefaultSfxFlags {
 pub struct Song {
     pub name: Name,
     pub source: SourcePathBuf,
+
+    /// Overrides the `.spc` file title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<Name>,
 }
 
 /// A small struct that documents (in the project file) what the project file is and the tad-gui
@@ -439,6 +606,10 @@ pub fn serialize_project(project: &Project) -> Result<Vec<u8>, serde_json::error
     serde_json::to_vec_pretty(project)
 }
 
+pub fn deserialize_project(json: &str) -> Result<Project, serde_json::error::Error> {
+    serde_json::from_str(json)
+}
+
 pub(crate) trait NameGetter {
     fn name(&self) -> &Name;
 }
@@ -797,3 +968,123 @@ pub fn _load_text_file_with_limit(path: &Path, file_name: String) -> Result<Text
         Err(_) => Err(FileError::Utf8Error(file_name)),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ALL_LOOP_SETTINGS: [(LoopSetting, &str); 11] = [
+        (LoopSetting::None, "none"),
+        (
+            LoopSetting::OverrideBrrLoopPoint(32),
+            "override_brr_loop_point:32",
+        ),
+        (LoopSetting::LoopWithFilter(16), "loop_with_filter:16"),
+        (LoopSetting::LoopResetFilter(0), "loop_reset_filter:0"),
+        (LoopSetting::LoopFilter1(48), "loop_filter_1:48"),
+        (LoopSetting::LoopFilter2(64), "loop_filter_2:64"),
+        (LoopSetting::LoopFilter3(80), "loop_filter_3:80"),
+        (LoopSetting::DupeBlockHack(4), "dupe_block_hack:4"),
+        (
+            LoopSetting::DupeBlockHackFilter1(1),
+            "dupe_block_hack_filter_1:1",
+        ),
+        (
+            LoopSetting::DupeBlockHackFilter2(2),
+            "dupe_block_hack_filter_2:2",
+        ),
+        (
+            LoopSetting::DupeBlockHackFilter3(3),
+            "dupe_block_hack_filter_3:3",
+        ),
+    ];
+
+    fn sample_json(loop_fields: &str) -> String {
+        format!(
+            r#"{{"name":"s","source":"s.brr",{loop_fields},"sample_rates":[32000],"envelope":"gain 127"}}"#
+        )
+    }
+
+    #[test]
+    fn loop_setting_round_trip() {
+        for (ls, s) in ALL_LOOP_SETTINGS {
+            assert_eq!(ls.to_string(), s);
+            assert_eq!(s.parse::<LoopSetting>(), Ok(ls.clone()));
+
+            let json = sample_json(&format!(r#""loop":"{s}""#));
+            let sample: Sample = serde_json::from_str(&json).unwrap();
+            assert_eq!(sample.loop_setting, ls);
+
+            let out = serde_json::to_string(&sample).unwrap();
+            assert!(out.contains(&format!(r#""loop":"{s}""#)), "{out}");
+            assert!(!out.contains("loop_setting"), "{out}");
+
+            let sample2: Sample = serde_json::from_str(&out).unwrap();
+            assert_eq!(sample2, sample);
+        }
+    }
+
+    #[test]
+    fn name_display_and_length() {
+        let name = Name::try_new("piano".to_owned()).unwrap();
+        assert_eq!(name.to_string(), "\"piano\"");
+        assert_eq!(name.as_identifier(), "piano");
+
+        let long = "a".repeat(Name::max_len() + 1);
+        assert_eq!(
+            Name::try_new(long.clone()),
+            Err(ValueError::NameTooLong(Name::max_len() + 1))
+        );
+        assert_eq!(Name::new_lossy(long).as_str().len(), Name::max_len());
+    }
+
+    #[test]
+    fn old_loop_setting_json() {
+        for (ls, _) in ALL_LOOP_SETTINGS {
+            let json = match ls.argument() {
+                Some(a) => sample_json(&format!(
+                    r#""loop":"{}","loop_setting":{a}"#,
+                    ls.serialier_value()
+                )),
+                None => sample_json(&format!(r#""loop":"{}""#, ls.serialier_value())),
+            };
+
+            let sample: Sample = serde_json::from_str(&json).unwrap();
+            assert_eq!(sample.loop_setting, ls);
+        }
+    }
+
+    #[test]
+    fn song_alias_round_trip() {
+        let json = r#"{"instruments":[],"sound_effect_file":null,"songs":[
+            {"name":"a","source":"a.mml"},
+            {"name":"b","source":"b.mml","alias":"title_b"}
+        ]}"#;
+
+        let project: Project = serde_json::from_str(json).unwrap();
+        assert_eq!(project.songs[0].alias, None);
+        assert_eq!(project.songs[1].alias, Some("title_b".parse().unwrap()));
+
+        let out = String::from_utf8(serialize_project(&project).unwrap()).unwrap();
+        assert_eq!(out.matches("\"alias\"").count(), 1, "{out}");
+
+        let project2: Project = serde_json::from_str(&out).unwrap();
+        assert_eq!(project2.songs, project.songs);
+    }
+
+    #[test]
+    fn invalid_loop_setting() {
+        for s in [
+            "",
+            "unknown",
+            "none:2",
+            "loop_with_filter",
+            "loop_with_filter:x",
+        ] {
+            assert_eq!(
+                s.parse::<LoopSetting>(),
+                Err(ValueError::InvalidLoopSetting(s.to_owned()))
+            );
+        }
+    }
+}
@@ -59,7 +59,10 @@ pub mod addresses {
         CHANNEL_STACK_POINTER,
         CHANNEL_LOOP_STACK_POINTER,
         CHANNEL_NEXT_EVENT_IS_KEY_OFF,
-        CHANNEL_DIRECTION,
+        CHANNEL_PORTAMENTO_DIRECTION,
+        CHANNEL_PORTAMENTO_SPEED,
+        CHANNEL_PORTAMENTO_TARGET_L,
+        CHANNEL_PORTAMENTO_TARGET_H,
         CHANNEL_VIBRATO_PITCH_OFFSET_PER_TICK,
         CHANNEL_VIBRATO_DIRECTION,
         CHANNEL_VIBRATO_TICK_COUNTER,
@@ -63,6 +63,57 @@ pub const IDENTITY_FILTER: [FirCoefficient; FIR_FILTER_SIZE] = [
     FirCoefficient(0),
 ];
 
+/// FIR filter presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirPreset {
+    FlatResponse,
+    LowPass,
+    HighPass,
+    WarmReverb,
+    CrispReverb,
+}
+
+impl FirPreset {
+    pub const ALL: [Self; 5] = [
+        Self::FlatResponse,
+        Self::LowPass,
+        Self::HighPass,
+        Self::WarmReverb,
+        Self::CrispReverb,
+    ];
+
+    /// The preset name used in MML
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FlatResponse => "flat",
+            Self::LowPass => "low_pass",
+            Self::HighPass => "high_pass",
+            Self::WarmReverb => "warm_reverb",
+            Self::CrispReverb => "crisp_reverb",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == s)
+    }
+}
+
+// All presets have an absolute sum <= `MAX_FIR_ABS_SUM`
+pub fn fir_preset_coefficients(preset: FirPreset) -> [i8; FIR_FILTER_SIZE] {
+    match preset {
+        // No filtering
+        FirPreset::FlatResponse => [127, 0, 0, 0, 0, 0, 0, 0],
+        // Symmetric triangle window, removes most of the high frequencies
+        FirPreset::LowPass => [8, 16, 24, 32, 24, 16, 8, 0],
+        // Zero DC gain, removes the low frequencies
+        FirPreset::HighPass => [64, -32, -16, -8, -4, -2, -1, -1],
+        // Gentle roll-off of the high frequencies
+        FirPreset::WarmReverb => [48, 24, 16, 12, 8, 6, 4, 2],
+        // Slight high frequency boost
+        FirPreset::CrispReverb => [100, -20, 4, 0, 0, 0, 0, 0],
+    }
+}
+
 impl EchoEdl {
     pub const ZERO: Self = Self(0);
 
@@ -75,7 +126,11 @@ impl EchoEdl {
     }
 
     pub fn to_duration(&self) -> Duration {
-        Duration::from_millis(u64::from(self.as_u8()) * u64::from(ECHO_BUFFER_EDL_MS))
+        Duration::from_millis(u64::from(self.to_milliseconds()))
+    }
+
+    pub fn to_milliseconds(&self) -> u32 {
+        u32::from(self.as_u8()) * ECHO_BUFFER_EDL_MS
     }
 
     pub fn to_length(&self) -> EchoLength {
@@ -157,9 +212,92 @@ impl EchoBuffer {
     }
 }
 
+// S-DSP echo register helpers.
+//
+// These functions use the raw S-DSP `ESA` and `EDL` register values.
+// Source: SnesLab https://sneslab.net/wiki/ESA_Register and https://sneslab.net/wiki/EDL_Register
+
+/// S-DSP `EDL` register bits
+const EDL_REGISTER_MASK: u8 = 0x0f;
+
+/// Size of the echo buffer when `EDL` is 0 (a single stereo sample)
+const EDL_ZERO_BUFFER_SIZE: usize = 4;
+
+/// Returns the start address of the echo buffer
+pub fn echo_buffer_address(esa: u8) -> u16 {
+    u16::from(esa) << 8
+}
+
+/// Returns the number of bytes the S-DSP will write to the echo buffer.
+///
+/// NOTE: The audio driver reserves `ECHO_BUFFER_MIN_SIZE` bytes if `EDL` is 0.
+pub fn echo_buffer_size_bytes(edl: u8) -> usize {
+    match edl & EDL_REGISTER_MASK {
+        0 => EDL_ZERO_BUFFER_SIZE,
+        edl => usize::from(edl) * ECHO_BUFFER_EDL_SIZE,
+    }
+}
+
+/// Returns the address of the last byte of the echo buffer.
+///
+/// The S-DSP wraps echo buffer writes around to the start of audio-RAM.
+pub fn echo_buffer_end_address(esa: u8, edl: u8) -> u16 {
+    let size = u16::try_from(echo_buffer_size_bytes(edl)).unwrap();
+
+    echo_buffer_address(esa).wrapping_add(size - 1)
+}
+
+/// Returns true if the echo buffer overlaps data that starts at address 0 and ends at `data_end`
+/// (exclusive).
+pub fn echo_overlaps_data(esa: u8, edl: u8, data_end: u16) -> bool {
+    let start = usize::from(echo_buffer_address(esa));
+    let end = start + echo_buffer_size_bytes(edl);
+
+    let wraps_around = end > 0x10000;
+
+    start < usize::from(data_end) || (wraps_around && data_end > 0)
+}
+
+/// Returns the largest `EDL` whose echo buffer, at the end of audio-RAM, does not overlap data
+/// that starts at address 0 and ends at `data_end` (exclusive).
+pub fn largest_edl_without_overlap(data_end: usize) -> Option<EchoEdl> {
+    let data_end = u16::try_from(data_end).ok()?;
+
+    (0..=ECHO_BUFFER_MAX_EDL)
+        .rev()
+        .map(|edl| EchoEdl::try_from(edl).unwrap())
+        .find(|edl| {
+            let esa = (0x10000 - usize::from(edl.buffer_size())) >> 8;
+            !echo_overlaps_data(esa as u8, edl.as_u8(), data_end)
+        })
+}
+
+/// Returns the `EDL` value with the echo delay closest to `delay_ms`.
+///
+/// Each `EDL` step is `ECHO_BUFFER_EDL_MS` milliseconds (512 samples at 32 kHz).
+pub fn edl_for_delay_ms(delay_ms: f64) -> u8 {
+    let edl = (delay_ms / f64::from(ECHO_BUFFER_EDL_MS)).round();
+
+    if edl.is_nan() {
+        0
+    } else {
+        edl.clamp(0.0, EDL_REGISTER_MASK.into()) as u8
+    }
+}
+
+/// Parses 8 FIR filter coefficients or a `FirPreset` name
 pub fn parse_fir_filter_string(s: &str) -> Result<[FirCoefficient; FIR_FILTER_SIZE], ValueError> {
     let input: Vec<&str> = s.split_whitespace().collect();
 
+    if let [name] = input.as_slice() {
+        if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return match FirPreset::from_name(name) {
+                Some(p) => Ok(fir_preset_coefficients(p).map(FirCoefficient)),
+                None => Err(ValueError::UnknownFirPreset(name.to_string())),
+            };
+        }
+    }
+
     if input.len() != FIR_FILTER_SIZE {
         return Err(ValueError::InvalidFirFilterSize);
     }
@@ -201,4 +339,109 @@ mod test {
             Ok([80, 50, 100, 127, 0, -1, -100, -128].map(FirCoefficient))
         );
     }
+
+    #[test]
+    fn test_fir_filter_preset() {
+        assert_eq!(parse_fir_filter_string("flat"), Ok(IDENTITY_FILTER));
+        assert_eq!(
+            parse_fir_filter_string(" low_pass "),
+            Ok([8, 16, 24, 32, 24, 16, 8, 0].map(FirCoefficient))
+        );
+        assert_eq!(
+            parse_fir_filter_string("unknown"),
+            Err(ValueError::UnknownFirPreset("unknown".to_owned()))
+        );
+        assert_eq!(
+            parse_fir_filter_string("low_pass 0"),
+            Err(ValueError::InvalidFirFilterSize)
+        );
+    }
+
+    #[test]
+    fn test_fir_preset_gain() {
+        for p in FirPreset::ALL {
+            assert_eq!(FirPreset::from_name(p.name()), Some(p));
+
+            let fir = fir_preset_coefficients(p).map(FirCoefficient);
+            assert_eq!(test_fir_filter_gain(&fir), Ok(()), "{p:?}");
+        }
+    }
+
+    #[test]
+    fn test_echo_buffer_address() {
+        assert_eq!(echo_buffer_address(0x00), 0x0000);
+        assert_eq!(echo_buffer_address(0x80), 0x8000);
+        assert_eq!(echo_buffer_address(0xff), 0xff00);
+    }
+
+    #[test]
+    fn test_echo_buffer_size_bytes() {
+        assert_eq!(echo_buffer_size_bytes(0), 4);
+        assert_eq!(echo_buffer_size_bytes(1), 2048);
+        assert_eq!(echo_buffer_size_bytes(4), 8192);
+        assert_eq!(echo_buffer_size_bytes(15), 30720);
+
+        // Only the lower 4 bits of EDL are used
+        assert_eq!(echo_buffer_size_bytes(0x12), 4096);
+    }
+
+    #[test]
+    fn test_echo_buffer_end_address() {
+        assert_eq!(echo_buffer_end_address(0xff, 0), 0xff03);
+        assert_eq!(echo_buffer_end_address(0xf8, 1), 0xffff);
+        assert_eq!(echo_buffer_end_address(0x88, 15), 0xffff);
+        assert_eq!(echo_buffer_end_address(0x40, 2), 0x4fff);
+
+        // Wraps around
+        assert_eq!(echo_buffer_end_address(0xfc, 1), 0x03ff);
+    }
+
+    #[test]
+    fn test_echo_overlaps_data() {
+        assert!(!echo_overlaps_data(0xf8, 1, 0xf800));
+        assert!(echo_overlaps_data(0xf8, 1, 0xf801));
+        assert!(!echo_overlaps_data(0x88, 15, 0x8800));
+        assert!(echo_overlaps_data(0x88, 15, 0x9000));
+        assert!(!echo_overlaps_data(0xff, 0, 0xff00));
+
+        // Echo buffer wraps around to the start of audio-RAM
+        assert!(echo_overlaps_data(0xfc, 1, 0x0200));
+        assert!(!echo_overlaps_data(0xfc, 1, 0));
+    }
+
+    #[test]
+    fn test_largest_edl_without_overlap() {
+        assert_eq!(largest_edl_without_overlap(0), EchoEdl::try_from(15u8).ok());
+        assert_eq!(
+            largest_edl_without_overlap(0x8800),
+            EchoEdl::try_from(15u8).ok()
+        );
+        assert_eq!(
+            largest_edl_without_overlap(0x8801),
+            EchoEdl::try_from(14u8).ok()
+        );
+        assert_eq!(
+            largest_edl_without_overlap(0xf800),
+            EchoEdl::try_from(1u8).ok()
+        );
+        assert_eq!(
+            largest_edl_without_overlap(0xff00),
+            EchoEdl::try_from(0u8).ok()
+        );
+        assert_eq!(largest_edl_without_overlap(0xff01), None);
+        assert_eq!(largest_edl_without_overlap(0x10000), None);
+    }
+
+    #[test]
+    fn test_edl_for_delay_ms() {
+        assert_eq!(edl_for_delay_ms(0.0), 0);
+        assert_eq!(edl_for_delay_ms(7.9), 0);
+        assert_eq!(edl_for_delay_ms(8.0), 1);
+        assert_eq!(edl_for_delay_ms(16.0), 1);
+        assert_eq!(edl_for_delay_ms(100.0), 6);
+        assert_eq!(edl_for_delay_ms(240.0), 15);
+        assert_eq!(edl_for_delay_ms(1000.0), 15);
+        assert_eq!(edl_for_delay_ms(-50.0), 0);
+        assert_eq!(edl_for_delay_ms(f64::NAN), 0);
+    }
 }
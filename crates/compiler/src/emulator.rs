@@ -0,0 +1,213 @@
+//! Audio driver emulator helpers
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::audio_driver;
+use crate::bytecode_interpreter::{Emulator, SongInterpreter};
+use crate::common_audio_data::CommonAudioData;
+use crate::driver_constants::{addresses, LoaderDataType, AUDIO_RAM_SIZE, SONG_HEADER_ECHO_EDL};
+use crate::songs::SongData;
+
+use shvc_sound_emu::ShvcSoundEmu;
+
+use std::fmt::Display;
+use std::ops::Deref;
+
+/// Maximum number of `emulate()` calls to wait for the audio driver to initialise
+const MAX_INIT_FRAMES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverInitTimeout;
+
+impl Display for DriverInitTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "audio driver did not finish initialisation")
+    }
+}
+
+impl Emulator for ShvcSoundEmu {
+    fn apuram_mut(&mut self) -> &mut [u8; AUDIO_RAM_SIZE] {
+        ShvcSoundEmu::apuram_mut(self)
+    }
+
+    fn write_smp_register(&mut self, addr: u8, value: u8) {
+        ShvcSoundEmu::write_smp_register(self, addr, value);
+    }
+
+    fn program_counter(&self) -> u16 {
+        ShvcSoundEmu::program_counter(self)
+    }
+}
+
+/// Writes the loader, audio driver, common audio data and song data into audio-RAM.
+///
+/// Panics if `song_data` does not fit in audio-RAM.
+pub fn write_driver_and_song(
+    apuram: &mut [u8; AUDIO_RAM_SIZE],
+    common_data: &[u8],
+    song_data: &[u8],
+    song_addr: u16,
+    loader_data_type: LoaderDataType,
+) {
+    const LOADER_DATA_TYPE_ADDR: usize = addresses::LOADER_DATA_TYPE as usize;
+
+    let mut write_spc_ram = |addr: u16, data: &[u8]| {
+        let addr = usize::from(addr);
+        apuram[addr..addr + data.len()].copy_from_slice(data);
+    };
+
+    write_spc_ram(addresses::LOADER, audio_driver::LOADER);
+    write_spc_ram(addresses::DRIVER_CODE, audio_driver::AUDIO_DRIVER);
+    write_spc_ram(addresses::COMMON_DATA, common_data);
+    write_spc_ram(addresses::SONG_PTR, &song_addr.to_le_bytes());
+    write_spc_ram(song_addr, song_data);
+
+    apuram[LOADER_DATA_TYPE_ADDR] = loader_data_type.driver_value();
+}
+
+/// Resets the emulator and emulates the audio driver until it has finished initialisation.
+///
+/// The echo buffer registers are set before the emulator processes any instructions.
+///
+/// REQUIRES: The audio driver and song have been written to audio-RAM.
+pub fn reset_and_wait_for_main_loop(
+    emu: &mut ShvcSoundEmu,
+    esa: u8,
+    edl: u8,
+) -> Result<(), DriverInitTimeout> {
+    emu.reset(shvc_sound_emu::ResetRegisters {
+        pc: addresses::DRIVER_CODE,
+        a: 0,
+        x: 0,
+        y: 0,
+        psw: 0,
+        sp: 0xff,
+        esa,
+        edl,
+    });
+
+    for _ in 0..MAX_INIT_FRAMES {
+        if emu.is_pc_in_mainloop() {
+            return Ok(());
+        }
+        emu.emulate();
+    }
+
+    match emu.is_pc_in_mainloop() {
+        true => Ok(()),
+        false => Err(DriverInitTimeout),
+    }
+}
+
+/// Loads the audio driver, common audio data and song into the emulator.
+///
+/// If `interpreter` is not `None`, the song's channels are set to the interpreter's state.
+///
+/// Returns after the audio driver has been initialised (the audio driver is paused).
+pub fn load_song_into_emulator<CAD, SD>(
+    emu: &mut ShvcSoundEmu,
+    common_audio_data: &CommonAudioData,
+    song_data: &SongData,
+    interpreter: Option<&SongInterpreter<CAD, SD>>,
+    stereo_flag: bool,
+) -> Result<(), DriverInitTimeout>
+where
+    CAD: Deref<Target = CommonAudioData>,
+    SD: Deref<Target = SongData>,
+{
+    let song_data_addr = common_audio_data.song_data_addr();
+    let echo_buffer = &song_data.metadata().echo_buffer;
+
+    let apuram = emu.apuram_mut();
+
+    write_driver_and_song(
+        apuram,
+        common_audio_data.data(),
+        song_data.data(),
+        song_data_addr,
+        LoaderDataType {
+            stereo_flag,
+            play_song: false,
+            skip_echo_buffer_reset: true,
+        },
+    );
+
+    // Reset echo buffer
+    let eb_start = usize::from(echo_buffer.buffer_addr());
+    let eb_end = eb_start + echo_buffer.buffer_size();
+    apuram[eb_start..eb_end].fill(0);
+
+    // The echo buffer registers must be setup BEFORE the emulator processes instructions.
+    // Otherwise the audio sounds weird.
+    let (esa, edl) = match interpreter {
+        Some(bci) => {
+            apuram[usize::from(song_data_addr) + SONG_HEADER_ECHO_EDL] = bci.song_header_edl();
+
+            (bci.esa_register(), bci.edl_register())
+        }
+        None => (echo_buffer.esa_register(), echo_buffer.edl_register()),
+    };
+
+    reset_and_wait_for_main_loop(emu, esa, edl)?;
+
+    if let Some(bci) = interpreter {
+        bci.write_to_emulator(emu);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOADER_DATA_TYPE: LoaderDataType = LoaderDataType {
+        stereo_flag: true,
+        play_song: true,
+        skip_echo_buffer_reset: false,
+    };
+
+    #[test]
+    fn test_write_driver_and_song() {
+        let mut apuram = [0; AUDIO_RAM_SIZE];
+
+        write_driver_and_song(&mut apuram, &[1, 2, 3], &[4, 5], 0x8000, LOADER_DATA_TYPE);
+
+        let driver_code = usize::from(addresses::DRIVER_CODE);
+        assert_eq!(
+            &apuram[driver_code..driver_code + audio_driver::AUDIO_DRIVER.len()],
+            audio_driver::AUDIO_DRIVER
+        );
+
+        let common_data = usize::from(addresses::COMMON_DATA);
+        assert_eq!(apuram[common_data..common_data + 3], [1, 2, 3]);
+
+        let song_ptr = usize::from(addresses::SONG_PTR);
+        assert_eq!(apuram[song_ptr..song_ptr + 2], [0x00, 0x80]);
+        assert_eq!(apuram[0x8000..0x8003], [4, 5, 0]);
+
+        assert_eq!(
+            apuram[usize::from(addresses::LOADER_DATA_TYPE)],
+            LOADER_DATA_TYPE.driver_value()
+        );
+    }
+
+    #[test]
+    fn test_reset_and_wait_for_main_loop_timeout() {
+        // `BRA -2` infinite loop
+        const INFINITE_LOOP: [u8; 2] = [0x2f, 0xfe];
+
+        let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+        let driver_code = usize::from(addresses::DRIVER_CODE);
+        emu.apuram_mut()[driver_code..driver_code + 2].copy_from_slice(&INFINITE_LOOP);
+
+        assert_eq!(
+            reset_and_wait_for_main_loop(&mut emu, 0, 0),
+            Err(DriverInitTimeout)
+        );
+        assert_eq!(emu.program_counter(), addresses::DRIVER_CODE);
+    }
+}
@@ -13,6 +13,29 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub const ADSR_STR: &str = "adsr";
 pub const GAIN_STR: &str = "gain";
 
+/// S-DSP sample rate (in Hz)
+pub const DSP_SAMPLE_RATE: u32 = 32000;
+
+/// Number of S-DSP samples between envelope updates for each envelope rate.
+/// A period of 0 means the envelope is never updated.
+const ENVELOPE_RATE_PERIODS: [u32; 32] = [
+    0, 2048, 1536, 1280, 1024, 768, 640, 512, 384, 320, 256, 192, 160, 128, 96, 80, 64, 48, 40, 32,
+    24, 20, 16, 12, 10, 8, 6, 5, 4, 3, 2, 1,
+];
+
+/// Maximum value of the 11-bit S-DSP envelope
+const ENVELOPE_MAX: u32 = 0x7ff;
+
+/// Number of envelope updates an exponential decrease takes to fall below `target`
+fn exponential_decay_steps(mut envelope: u32, target: u32) -> u32 {
+    let mut steps = 0;
+    while envelope > target {
+        envelope -= ((envelope - 1) >> 8) + 1;
+        steps += 1;
+    }
+    steps
+}
+
 const fn value_fits_in_bits(value: u8, bits: u8) -> bool {
     assert!(bits < 8);
 
@@ -111,6 +134,23 @@ impl Adsr {
     pub fn adsr2(&self) -> u8 {
         self.adsr2
     }
+
+    /// Estimates the time (in seconds) for the sustain rate's exponential decay to take the
+    /// envelope from its maximum value to inaudibility (less than 1/256 amplitude).
+    ///
+    /// Returns `f64::INFINITY` if the sustain rate is 0 (the envelope never decays).
+    pub fn estimated_sustain_decay_seconds(&self, sample_rate_hz: f64) -> f64 {
+        let sustain_rate = self.adsr2 & 0b11111;
+
+        let period = ENVELOPE_RATE_PERIODS[usize::from(sustain_rate)];
+        if period == 0 {
+            return f64::INFINITY;
+        }
+
+        let steps = exponential_decay_steps(ENVELOPE_MAX, ENVELOPE_MAX / 256);
+
+        f64::from(steps * period) / sample_rate_hz
+    }
 }
 
 impl FromStr for Adsr {
@@ -286,6 +326,35 @@ impl Gain {
         let (mode, value) = self.to_mode_and_value();
         format!("{}{}", mode.to_prefix_str(), value)
     }
+
+    /// Estimates the number of S-DSP envelope ticks it takes for a decreasing GAIN envelope to
+    /// reach 0.
+    ///
+    /// The envelope is clocked once per output sample, there are `DSP_SAMPLE_RATE` (32000)
+    /// ticks per second.
+    ///
+    /// `starting_value` is the 7-bit ENVX value at the start of the GAIN envelope.
+    ///
+    /// Returns `None` if the envelope will never reach 0 (ie, fixed or increasing GAIN).
+    pub fn estimated_ticks_to_silence(&self, starting_value: u8) -> Option<u32> {
+        let (mode, rate) = self.to_mode_and_value();
+
+        let envelope = u32::from(starting_value.min(Self::FIXED_GAIN_MASK)) << 4;
+
+        match mode {
+            GainMode::Raw | GainMode::Fixed => None,
+            GainMode::LinearIncrease | GainMode::BentIncrease => None,
+            _ if rate == 0 => None,
+            GainMode::LinearDecrease => {
+                let period = ENVELOPE_RATE_PERIODS[usize::from(rate)];
+                Some(envelope.div_ceil(32) * period)
+            }
+            GainMode::ExponentialDecrease => {
+                let period = ENVELOPE_RATE_PERIODS[usize::from(rate)];
+                Some(exponential_decay_steps(envelope, 0) * period)
+            }
+        }
+    }
 }
 
 impl From<u8> for Gain {
@@ -389,6 +458,17 @@ impl Envelope {
             Envelope::Gain(gain) => (0, gain.value()),
         }
     }
+
+    /// Inverse of `engine_value()`
+    pub fn from_engine_value(adsr1: u8, adsr2_or_gain: u8) -> Self {
+        match adsr1 & 0x80 != 0 {
+            true => Envelope::Adsr(Adsr {
+                adsr1,
+                adsr2: adsr2_or_gain,
+            }),
+            false => Envelope::Gain(Gain::new(adsr2_or_gain)),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Envelope {
@@ -411,6 +491,116 @@ impl Serialize for Envelope {
     }
 }
 
+fn exponential_decrease(envelope: u32) -> u32 {
+    envelope.saturating_sub((envelope.saturating_sub(1) >> 8) + 1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopeStage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Simulates the S-DSP envelope (ENVX) of a voice after key-on.
+///
+/// The S-DSP global counter offsets are not emulated, the envelope is updated at the start of
+/// every rate period.
+#[derive(Debug, Clone)]
+pub struct EnvelopeSimulator {
+    envelope: Envelope,
+    stage: EnvelopeStage,
+    value: u32,
+    sample: u32,
+}
+
+impl EnvelopeSimulator {
+    /// Maximum value of the 11-bit envelope
+    pub const MAX_VALUE: u16 = ENVELOPE_MAX as u16;
+
+    pub fn new(envelope: Envelope) -> Self {
+        Self {
+            envelope,
+            stage: EnvelopeStage::Attack,
+            value: 0,
+            sample: 0,
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.value as u16
+    }
+
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
+
+    pub fn key_off(&mut self) {
+        self.stage = EnvelopeStage::Release;
+    }
+
+    /// Returns the envelope rate and the envelope value after the next update
+    fn next_update(&self) -> (u8, u32) {
+        let env = self.value;
+
+        if self.stage == EnvelopeStage::Release {
+            // Release is not affected by the envelope rate
+            return (31, env.saturating_sub(8));
+        }
+
+        match self.envelope {
+            Envelope::Adsr(adsr) => match self.stage {
+                EnvelopeStage::Attack => match adsr.adsr1 & 0x0f {
+                    0x0f => (31, env + 1024),
+                    a => (a * 2 + 1, env + 32),
+                },
+                EnvelopeStage::Decay => (
+                    ((adsr.adsr1 >> 4) & 0x07) * 2 + 16,
+                    exponential_decrease(env),
+                ),
+                EnvelopeStage::Sustain | EnvelopeStage::Release => {
+                    (adsr.adsr2 & 0x1f, exponential_decrease(env))
+                }
+            },
+            Envelope::Gain(gain) => match gain.to_mode_and_value() {
+                (GainMode::Raw | GainMode::Fixed, v) => (31, u32::from(v) << 4),
+                (GainMode::LinearDecrease, rate) => (rate, env.saturating_sub(32)),
+                (GainMode::ExponentialDecrease, rate) => (rate, exponential_decrease(env)),
+                (GainMode::LinearIncrease, rate) => (rate, env + 32),
+                (GainMode::BentIncrease, rate) => match env < 0x600 {
+                    true => (rate, env + 32),
+                    false => (rate, env + 8),
+                },
+            },
+        }
+    }
+
+    /// Advances the simulation by one S-DSP sample
+    pub fn process_sample(&mut self) {
+        let (rate, new_value) = self.next_update();
+
+        let period = ENVELOPE_RATE_PERIODS[usize::from(rate)];
+        if period != 0 && self.sample % period == 0 {
+            self.value = new_value.min(ENVELOPE_MAX);
+
+            if let Envelope::Adsr(adsr) = self.envelope {
+                match self.stage {
+                    EnvelopeStage::Attack if self.value >= 0x7e0 => {
+                        self.stage = EnvelopeStage::Decay;
+                    }
+                    EnvelopeStage::Decay if self.value >> 8 == u32::from(adsr.adsr2 >> 5) => {
+                        self.stage = EnvelopeStage::Sustain;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        self.sample = self.sample.wrapping_add(1);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TempGain(u8);
 
@@ -490,3 +680,141 @@ impl From<OptionalGain> for TempGain {
         Self(value.0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn envelope_engine_value_round_trip() {
+        for s in [
+            "adsr 12 1 1 16",
+            "adsr 0 0 0 0",
+            "gain 127",
+            "gain 0",
+            "gain D5",
+        ] {
+            let e = Envelope::try_from_envelope_str(s).unwrap();
+            let (adsr1, adsr2_or_gain) = e.engine_value();
+            assert_eq!(Envelope::from_engine_value(adsr1, adsr2_or_gain), e, "{s}");
+        }
+    }
+
+    #[test]
+    fn gain_estimated_ticks_to_silence() {
+        let gain = |mode, value| Gain::from_mode_and_value(mode, value).unwrap();
+
+        assert_eq!(
+            gain(GainMode::Fixed, 127).estimated_ticks_to_silence(127),
+            None
+        );
+        assert_eq!(
+            gain(GainMode::LinearIncrease, 20).estimated_ticks_to_silence(127),
+            None
+        );
+        assert_eq!(
+            gain(GainMode::BentIncrease, 20).estimated_ticks_to_silence(127),
+            None
+        );
+        assert_eq!(
+            gain(GainMode::LinearDecrease, 0).estimated_ticks_to_silence(127),
+            None
+        );
+
+        // 0x7f0 / 32 = 63.5 steps
+        assert_eq!(
+            gain(GainMode::LinearDecrease, 31).estimated_ticks_to_silence(127),
+            Some(64)
+        );
+        assert_eq!(
+            gain(GainMode::LinearDecrease, 28).estimated_ticks_to_silence(127),
+            Some(64 * 4)
+        );
+        assert_eq!(
+            gain(GainMode::LinearDecrease, 28).estimated_ticks_to_silence(0),
+            Some(0)
+        );
+
+        let exp = gain(GainMode::ExponentialDecrease, 31)
+            .estimated_ticks_to_silence(127)
+            .unwrap();
+        let linear = gain(GainMode::LinearDecrease, 31)
+            .estimated_ticks_to_silence(127)
+            .unwrap();
+        assert!(exp > linear);
+    }
+
+    #[test]
+    fn envelope_simulator() {
+        let simulate = |envelope, n_samples| {
+            let mut sim = EnvelopeSimulator::new(envelope);
+            for _ in 0..n_samples {
+                sim.process_sample();
+            }
+            sim
+        };
+        let adsr = |a, d, sl, sr| Envelope::Adsr(Adsr::try_new(a, d, sl, sr).unwrap());
+        let gain = |mode, value| Envelope::Gain(Gain::from_mode_and_value(mode, value).unwrap());
+
+        // Attack 15 adds 1024 every sample
+        let sim = simulate(adsr(15, 7, 7, 0), 1);
+        assert_eq!((sim.value(), sim.stage()), (1024, EnvelopeStage::Attack));
+        let sim = simulate(adsr(15, 7, 7, 0), 2);
+        assert_eq!((sim.value(), sim.stage()), (2047, EnvelopeStage::Decay));
+
+        // Attack 14 (rate 29) adds 32 every 3 samples
+        let sim = simulate(adsr(14, 7, 7, 0), 3 * 10);
+        assert_eq!(sim.value(), 32 * 10);
+
+        // Decay stops at the sustain level
+        let sim = simulate(adsr(15, 7, 3, 0), 2000);
+        assert_eq!(sim.stage(), EnvelopeStage::Sustain);
+        assert_eq!(sim.value() >> 8, 3);
+
+        // Sustain rate 31 decays to silence
+        let sim = simulate(adsr(15, 7, 7, 31), 32000);
+        assert_eq!(sim.value(), 0);
+
+        // Release decreases the envelope by 8 every sample
+        let mut sim = simulate(gain(GainMode::Fixed, 127), 1);
+        assert_eq!(sim.value(), 127 << 4);
+        sim.key_off();
+        sim.process_sample();
+        assert_eq!(sim.value(), (127 << 4) - 8);
+        for _ in 0..254 {
+            sim.process_sample();
+        }
+        assert_eq!(sim.value(), 0);
+
+        let sim = simulate(gain(GainMode::LinearIncrease, 31), 64);
+        assert_eq!(sim.value(), EnvelopeSimulator::MAX_VALUE);
+
+        // Bent increase slows down at 0x600
+        let sim = simulate(gain(GainMode::BentIncrease, 31), 48);
+        assert_eq!(sim.value(), 0x600);
+        let sim = simulate(gain(GainMode::BentIncrease, 31), 49);
+        assert_eq!(sim.value(), 0x608);
+
+        // Rate 0 never updates the envelope
+        let sim = simulate(gain(GainMode::LinearIncrease, 0), 1000);
+        assert_eq!(sim.value(), 0);
+    }
+
+    #[test]
+    fn adsr_estimated_sustain_decay_seconds() {
+        let sr = |sr| Adsr::try_new(15, 7, 7, sr).unwrap();
+        let sample_rate = f64::from(DSP_SAMPLE_RATE);
+
+        assert_eq!(
+            sr(0).estimated_sustain_decay_seconds(sample_rate),
+            f64::INFINITY
+        );
+
+        // 688 envelope updates from 0x7ff to 7
+        assert_eq!(sr(31).estimated_sustain_decay_seconds(sample_rate), 0.0215);
+        assert_eq!(sr(16).estimated_sustain_decay_seconds(sample_rate), 1.376);
+        assert_eq!(sr(1).estimated_sustain_decay_seconds(sample_rate), 44.032);
+
+        assert_eq!(sr(16).estimated_sustain_decay_seconds(16000.0), 2.752);
+    }
+}
@@ -24,7 +24,8 @@ use crate::driver_constants::{
     MAX_SONG_DATA_SIZE, MAX_SOUND_EFFECTS, MAX_SUBROUTINES,
 };
 use crate::echo::{
-    EchoEdl, EchoFeedback, EchoLength, EchoVolume, FirCoefficient, FirTap, MAX_FIR_ABS_SUM,
+    largest_edl_without_overlap, EchoEdl, EchoFeedback, EchoLength, EchoVolume, FirCoefficient,
+    FirTap, MAX_FIR_ABS_SUM,
 };
 use crate::envelope::Gain;
 use crate::file_pos::{FilePosRange, MAX_MML_TEXT_LENGTH};
@@ -32,12 +33,14 @@ use crate::mml::command_parser::{
     Transpose, MAX_COARSE_TREMOLO_AMPLITUDE, MAX_COARSE_VOLUME, MIN_COARSE_TREMOLO_AMPLITUDE,
     PX_PAN_RANGE,
 };
-use crate::mml::{MAX_MML_PREFIX_STR_LENGTH, MAX_MML_PREFIX_TICKS};
+use crate::mml::{IncludeMap, MAX_MACRO_DEPTH, MAX_MML_PREFIX_STR_LENGTH, MAX_MML_PREFIX_TICKS};
 use crate::notes::{MidiNote, Note, Octave};
 use crate::path::PathString;
-use crate::pitch_table::{InstrumentHintFreq, PlayPitchFrequency, PlayPitchSampleRate};
+use crate::pitch_table::{
+    InstrumentHintFreq, PlayPitchFrequency, PlayPitchSampleRate, CONCERT_A_RANGE, TUNING_TABLE_SIZE,
+};
 use crate::sound_effects::MAX_SFX_TICKS;
-use crate::time::{Bpm, TickClock, TickCounter, ZenLen};
+use crate::time::{Bpm, MinRestTicks, TempoScale, TickClock, TickCounter, ZenLen};
 use crate::value_newtypes::{I8WithByteHexValueNewType, SignedValueNewType, UnsignedValueNewType};
 use crate::{export, mml, spc_file_export};
 
@@ -99,6 +102,9 @@ pub enum ValueError {
     CannotParseHex(String),
 
     InvalidName(String),
+    NameTooLong(usize),
+
+    InvalidLoopSetting(String),
 
     NoEnvelope,
     UnknownEnvelopeType(String),
@@ -134,6 +140,13 @@ pub enum ValueError {
     CannotConvertPitchFrequency(PlayPitchFrequency, u32),
     CannotConvertPitchFrequencySample,
     CannotConvertPitchFrequencyUnknownInstrument,
+    CannotConvertTuningTableFrequency(Note, u32),
+
+    InvalidConcertPitch(String),
+    InvalidTuningTableSize(usize),
+
+    MaxChannelBytesOutOfRange(u32),
+    InvalidTuningTableFrequency(u8, String),
 
     PxPanOutOfRange(i32),
     PanOutOfRange(u32),
@@ -191,6 +204,8 @@ pub enum ValueError {
     ZenLenOutOfRange(u32),
     TickClockOutOfRange(u32),
     BpmOutOfRange(u32),
+    TempoScaleOutOfRange(u32),
+    MinRestTicksOutOfRange(u32),
 
     MidiNoteNumberOutOfRange(u32),
     CannotConvertMidiNote,
@@ -228,6 +243,7 @@ pub enum ValueError {
 
     InvalidFirFilterSize,
     InvalidFirFilter,
+    UnknownFirPreset(String),
     InvalidFirFilterGain { abs_sum: i32 },
 
     InvalidMmlInvertFlags,
@@ -264,6 +280,8 @@ pub enum ValueError {
     NoFineQuantize,
     NoTickClock,
     NoBpm,
+    NoTempoScale,
+    NoMinRestTicks,
     NoMidiNote,
     NoPortamentoSpeed,
     NoPortamentoVelocity,
@@ -403,12 +421,21 @@ pub enum CombineSoundEffectsError {
     InvalidLowPriorityIndex,
 }
 
+#[derive(Debug, Clone)]
+pub enum FlacError {
+    InvalidFlacFile(&'static str),
+    Unsupported(&'static str),
+    NoSamples,
+    FlacFileTooLarge,
+}
+
 // BrrError is cloneable as the sample file cache caches errors.
 // `io::Error` is not cloneable, requiring to enclose `IoError` and `WaveFileError` inside an Arc.
 #[derive(Debug, Clone)]
 pub enum BrrError {
     IoError(std::sync::Arc<(PathString, io::Error)>),
     WaveFileError(std::sync::Arc<(PathString, brr::WavError)>),
+    FlacFileError(PathString, FlacError),
 
     UnknownFileType(PathString),
     BrrEncodeError(PathString, brr::EncodeError),
@@ -446,7 +473,15 @@ pub enum CommonAudioDataError {
     TooManySfxSubroutines(usize),
     TooManySoundEffects(usize),
     CommonAudioDataTooLarge(usize),
-    SoundEffectDataTooLarge { by: usize },
+    SoundEffectDataTooLarge {
+        by: usize,
+    },
+    EchoBufferOverlap {
+        echo_start: usize,
+        echo_end: usize,
+        data_start: usize,
+        data_end: usize,
+    },
 }
 
 #[derive(Debug)]
@@ -469,6 +504,7 @@ pub enum PitchError {
 
 #[derive(Debug)]
 pub enum PitchTableError {
+    InvalidConcertPitch(f64),
     TooManyInstruments,
     TooManyPitches(usize),
     InstrumentErrors(Vec<(usize, Name, PitchError)>),
@@ -496,6 +532,7 @@ pub enum MmlLineError {
     // String is a list of invalid channels in the line
     UnknownChannel(String),
     CannotParseLine,
+    InvalidSectionTickCount(String),
 
     // MML Header errors
     NoHeader,
@@ -504,10 +541,25 @@ pub enum MmlLineError {
     UnknownHeader(String),
     DuplicateHeader(String),
 
+    // Include errors
+    NoIncludePath,
+    IncludeInFileWithoutPath,
+    CannotLoadInclude(String),
+    CircularInclude(String),
+
+    // Macro definition errors
+    NoMacroName,
+    MacroNameIsCommand(String),
+    MissingMacroParametersEnd,
+    DuplicateMacroParameter(String),
+    DuplicateMacro(String),
+
     InvalidEchoFeedback,
     InvalidNumberOfEchoVolumeArguments,
     CannotSetTempo,
     CannotSetTimer,
+    CannotSetTuning,
+    CannotSetTuningTable,
     InvalidSpcSongLength,
     InvalidSpcFadeout,
 
@@ -537,6 +589,20 @@ pub enum ChannelError {
     NoBraceAfterAsm,
     MissingEndAsm,
 
+    MissingMacroArguments(String),
+    MissingMacroArgumentsEnd(String),
+    InvalidNumberOfMacroArguments(usize, usize),
+    RecursiveMacro(String),
+    MacroTooDeep(String),
+    MacroExpansionTooLarge(String),
+    AsmInMacro,
+    /// An error in a macro expansion, `definition` is the position of the macro's `#define` line
+    InMacro {
+        error: Box<ChannelError>,
+        name: String,
+        definition: FilePosRange,
+    },
+
     NoteIsTooShort,
     NoLengthAfterComma,
 
@@ -589,6 +655,10 @@ pub enum ChannelError {
     TooManyNotesInBrokenChord(usize),
     BrokenChordTotalLengthTooShort,
 
+    NoArpeggioSpeed,
+    ArpeggioSpeedZero,
+    NoPitchInArpeggio,
+
     NoBraceAfterFirFilter,
     MissingEndFirFilter,
     UnknownTokenInFirFilter,
@@ -598,6 +668,17 @@ pub enum ChannelError {
 
     NoTicksAfterLoopPoint,
 
+    SectionTickCountMismatch {
+        section: String,
+        expected: TickCounter,
+        actual: TickCounter,
+    },
+
+    ChannelBytecodeTooBig {
+        limit: usize,
+        current: usize,
+    },
+
     CannotCallSubroutineInAnMmlPrefix,
     CannotCallSubroutineInASoundEffect,
     CannotCallSubroutineRecursion(String),
@@ -633,11 +714,45 @@ pub struct MmlChannelError {
     pub errors: Vec<ErrorWithPos<ChannelError>>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnreachableReason {
+    PitchTooHigh,
+    PitchTooLow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrumentPitchWarning {
+    NoteUnreachable {
+        instrument: Name,
+        note: Note,
+        reason: UnreachableReason,
+    },
+}
+
+/// A diagnostic note about an instrument's sample (not an error or warning)
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrumentSampleNote {
+    DcOffsetRemoved { instrument: Name, dc_offset: i16 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MmlWarning {
+    UnusedInstrument(String),
+    UnusedSubroutine(String),
+    RestPaddedForTiming {
+        original: TickCounter,
+        padded: TickCounter,
+    },
+}
+
 #[derive(Debug)]
 pub struct MmlCompileErrors {
     pub song_name: Option<Name>,
     pub file_name: String,
 
+    /// Maps the error line numbers to the included files
+    pub include_map: IncludeMap,
+
     pub line_errors: Vec<ErrorWithPos<MmlLineError>>,
     pub subroutine_errors: Vec<MmlChannelError>,
     pub channel_errors: Vec<MmlChannelError>,
@@ -645,7 +760,7 @@ pub struct MmlCompileErrors {
 
 #[derive(Debug)]
 pub enum SongError {
-    MmlError(MmlCompileErrors),
+    MmlError(Box<MmlCompileErrors>),
 
     NoMusicChannels,
     InvalidMmlData,
@@ -674,6 +789,16 @@ pub enum ExportSpcFileError {
     },
 }
 
+#[derive(Debug)]
+pub enum MidiExportError {
+    SongError(SongError),
+    InterpreterTimeout,
+    EventOutOfOrder {
+        tick: TickCounter,
+        previous_tick: TickCounter,
+    },
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ExportSegmentType {
     Segment,
@@ -728,6 +853,172 @@ impl From<BytecodeError> for ChannelError {
     }
 }
 
+// Error codes
+// ===========
+//
+// The name of the error variant, used by `tad-compiler --json-errors`.
+
+impl MmlLineError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ValueError(..) => "ValueError",
+            Self::MmlTooLarge(..) => "MmlTooLarge",
+            Self::TooManySubroutines(..) => "TooManySubroutines",
+            Self::NoIdentifier(..) => "NoIdentifier",
+            Self::InvalidIdentifier(..) => "InvalidIdentifier",
+            Self::UnknownChannel(..) => "UnknownChannel",
+            Self::CannotParseLine => "CannotParseLine",
+            Self::InvalidSectionTickCount(..) => "InvalidSectionTickCount",
+            Self::NoHeader => "NoHeader",
+            Self::NoValue => "NoValue",
+            Self::UnexpectedHeaderValue => "UnexpectedHeaderValue",
+            Self::UnknownHeader(..) => "UnknownHeader",
+            Self::DuplicateHeader(..) => "DuplicateHeader",
+            Self::NoIncludePath => "NoIncludePath",
+            Self::IncludeInFileWithoutPath => "IncludeInFileWithoutPath",
+            Self::CannotLoadInclude(..) => "CannotLoadInclude",
+            Self::CircularInclude(..) => "CircularInclude",
+            Self::NoMacroName => "NoMacroName",
+            Self::MacroNameIsCommand(..) => "MacroNameIsCommand",
+            Self::MissingMacroParametersEnd => "MissingMacroParametersEnd",
+            Self::DuplicateMacroParameter(..) => "DuplicateMacroParameter",
+            Self::DuplicateMacro(..) => "DuplicateMacro",
+            Self::InvalidEchoFeedback => "InvalidEchoFeedback",
+            Self::InvalidNumberOfEchoVolumeArguments => "InvalidNumberOfEchoVolumeArguments",
+            Self::CannotSetTempo => "CannotSetTempo",
+            Self::CannotSetTimer => "CannotSetTimer",
+            Self::CannotSetTuning => "CannotSetTuning",
+            Self::CannotSetTuningTable => "CannotSetTuningTable",
+            Self::InvalidSpcSongLength => "InvalidSpcSongLength",
+            Self::InvalidSpcFadeout => "InvalidSpcFadeout",
+            Self::NoInstrument => "NoInstrument",
+            Self::CannotFindInstrument(..) => "CannotFindInstrument",
+            Self::DuplicateInstrumentName(..) => "DuplicateInstrumentName",
+            Self::HeaderInSoundEffect => "HeaderInSoundEffect",
+            Self::ChannelInSfxSubroutineHeader => "ChannelInSfxSubroutineHeader",
+            Self::SubroutineDefinitionInSoundEffect => "SubroutineDefinitionInSoundEffect",
+            Self::InvalidSoundEffectChannel => "InvalidSoundEffectChannel",
+        }
+    }
+}
+
+impl ChannelError {
+    /// Returns the error without the macro definition (if the error is in a macro expansion)
+    pub fn error_without_macro(&self) -> &ChannelError {
+        match self {
+            Self::InMacro { error, .. } => error,
+            e => e,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ValueError(..) => "ValueError",
+            Self::BytecodeError(..) => "BytecodeError",
+            Self::UnknownCharacters(..) => "UnknownCharacters",
+            Self::NoSlashCommand => "NoSlashCommand",
+            Self::InvalidSlashCommand(..) => "InvalidSlashCommand",
+            Self::NoBraceAfterAsm => "NoBraceAfterAsm",
+            Self::MissingEndAsm => "MissingEndAsm",
+            Self::MissingMacroArguments(..) => "MissingMacroArguments",
+            Self::MissingMacroArgumentsEnd(..) => "MissingMacroArgumentsEnd",
+            Self::InvalidNumberOfMacroArguments(..) => "InvalidNumberOfMacroArguments",
+            Self::RecursiveMacro(..) => "RecursiveMacro",
+            Self::MacroTooDeep(..) => "MacroTooDeep",
+            Self::MacroExpansionTooLarge(..) => "MacroExpansionTooLarge",
+            Self::AsmInMacro => "AsmInMacro",
+            Self::InMacro { error, .. } => error.code(),
+            Self::NoteIsTooShort => "NoteIsTooShort",
+            Self::NoLengthAfterComma => "NoLengthAfterComma",
+            Self::NoSubroutine => "NoSubroutine",
+            Self::NoInstrument => "NoInstrument",
+            Self::CannotFindSubroutine(..) => "CannotFindSubroutine",
+            Self::CannotFindInstrument(..) => "CannotFindInstrument",
+            Self::NoStartBrokenChord => "NoStartBrokenChord",
+            Self::NoStartPortamento => "NoStartPortamento",
+            Self::MissingEndBrokenChord => "MissingEndBrokenChord",
+            Self::MissingEndPortamento => "MissingEndPortamento",
+            Self::PortamentoRequiresTwoPitches => "PortamentoRequiresTwoPitches",
+            Self::InvalidPortamentoDelay => "InvalidPortamentoDelay",
+            Self::MissingNoteBeforeTie => "MissingNoteBeforeTie",
+            Self::MissingNoteBeforeSlur => "MissingNoteBeforeSlur",
+            Self::CannotParseComma => "CannotParseComma",
+            Self::CannotParseDot => "CannotParseDot",
+            Self::CannotParsePercentSign => "CannotParsePercentSign",
+            Self::CannotParseGainMode => "CannotParseGainMode",
+            Self::UnexpectedNumber => "UnexpectedNumber",
+            Self::InvalidPitchListSymbol => "InvalidPitchListSymbol",
+            Self::NoEvolValue => "NoEvolValue",
+            Self::SetAndRelativeStereoEchoVolume => "SetAndRelativeStereoEchoVolume",
+            Self::RelativeAndSetStereoEchoVolume => "RelativeAndSetStereoEchoVolume",
+            Self::LoopPointAlreadySet => "LoopPointAlreadySet",
+            Self::CannotSetLoopPoint => "CannotSetLoopPoint",
+            Self::CannotSetLoopPointInALoop => "CannotSetLoopPointInALoop",
+            Self::CannotUseMpWithoutInstrument => "CannotUseMpWithoutInstrument",
+            Self::MpPitchOffsetTooLarge(..) => "MpPitchOffsetTooLarge",
+            Self::MpDepthZero => "MpDepthZero",
+            Self::CannotUseDetuneCentsWithoutInstrument => "CannotUseDetuneCentsWithoutInstrument",
+            Self::DetuneCentsTooLargeForNote(..) => "DetuneCentsTooLargeForNote",
+            Self::PortamentoTooShort => "PortamentoTooShort",
+            Self::PortamentoTooLong => "PortamentoTooLong",
+            Self::PortamentoRequiresInstrument => "PortamentoRequiresInstrument",
+            Self::PortamentoNoteAndPitchWithoutInstrument => {
+                "PortamentoNoteAndPitchWithoutInstrument"
+            }
+            Self::OneNotePortamentoPreviousNoteIsNotSlurred => {
+                "OneNotePortamentoPreviousNoteIsNotSlurred"
+            }
+            Self::OneNotePortamentoNoPreviousNote => "OneNotePortamentoNoPreviousNote",
+            Self::OneNotePortamentoPreviousNoteIsUnknown => {
+                "OneNotePortamentoPreviousNoteIsUnknown"
+            }
+            Self::OneNotePortamentoPreviousNoteIsNoise => "OneNotePortamentoPreviousNoteIsNoise",
+            Self::NoNotesInBrokenChord => "NoNotesInBrokenChord",
+            Self::TooManyNotesInBrokenChord(..) => "TooManyNotesInBrokenChord",
+            Self::BrokenChordTotalLengthTooShort => "BrokenChordTotalLengthTooShort",
+            Self::NoArpeggioSpeed => "NoArpeggioSpeed",
+            Self::ArpeggioSpeedZero => "ArpeggioSpeedZero",
+            Self::NoPitchInArpeggio => "NoPitchInArpeggio",
+            Self::NoBraceAfterFirFilter => "NoBraceAfterFirFilter",
+            Self::MissingEndFirFilter => "MissingEndFirFilter",
+            Self::UnknownTokenInFirFilter => "UnknownTokenInFirFilter",
+            Self::InvalidNumberOfFirCoefficients(..) => "InvalidNumberOfFirCoefficients",
+            Self::BrokenChordTickCountMismatch(..) => "BrokenChordTickCountMismatch",
+            Self::NoTicksAfterLoopPoint => "NoTicksAfterLoopPoint",
+            Self::SectionTickCountMismatch { .. } => "SectionTickCountMismatch",
+            Self::ChannelBytecodeTooBig { .. } => "ChannelBytecodeTooBig",
+            Self::CannotCallSubroutineInAnMmlPrefix => "CannotCallSubroutineInAnMmlPrefix",
+            Self::CannotCallSubroutineInASoundEffect => "CannotCallSubroutineInASoundEffect",
+            Self::CannotCallSubroutineRecursion(..) => "CannotCallSubroutineRecursion",
+            Self::TooManySfxTicks(..) => "TooManySfxTicks",
+            Self::TooManyTicksInMmlPrefix(..) => "TooManyTicksInMmlPrefix",
+            Self::NoInstrumentHint => "NoInstrumentHint",
+            Self::InstrumentHintAlreadySet => "InstrumentHintAlreadySet",
+            Self::InstrumentHintOnlyAllowedInSubroutines => {
+                "InstrumentHintOnlyAllowedInSubroutines"
+            }
+            Self::InstrumentHintInstrumentAlreadySet => "InstrumentHintInstrumentAlreadySet",
+            Self::CannotSetInstrumentHintForSample => "CannotSetInstrumentHintForSample",
+            Self::CannotSetInstrumentHintForUnknown => "CannotSetInstrumentHintForUnknown",
+            Self::UnknownInstruction(..) => "UnknownInstruction",
+            Self::InvalidNumberOfArguments(..) => "InvalidNumberOfArguments",
+            Self::InvalidNumberOfArgumentsRange(..) => "InvalidNumberOfArgumentsRange",
+            Self::InvalidKeyoffArgument(..) => "InvalidKeyoffArgument",
+            Self::NoTicksInSoundEffect => "NoTicksInSoundEffect",
+        }
+    }
+}
+
+impl MmlWarning {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnusedInstrument(..) => "UnusedInstrument",
+            Self::UnusedSubroutine(..) => "UnusedSubroutine",
+            Self::RestPaddedForTiming { .. } => "RestPaddedForTiming",
+        }
+    }
+}
+
 // Display
 // =======
 
@@ -833,6 +1124,13 @@ impl Display for ValueError {
             Self::CannotParseHex(s) => write!(f, "cannot parse hexadecimal number: {}", s),
 
             Self::InvalidName(s) => write!(f, "invalid name: {}", s),
+            Self::NameTooLong(len) => write!(
+                f,
+                "name is too long ({len} characters, max {})",
+                Name::max_len()
+            ),
+
+            Self::InvalidLoopSetting(s) => write!(f, "invalid loop setting: {}", s),
 
             Self::NoEnvelope => write!(f, "no envelope"),
             Self::UnknownEnvelopeType(s) => write!(f, "unknown envelope type: {}", s),
@@ -924,6 +1222,40 @@ impl Display for ValueError {
             Self::CannotConvertPitchFrequencyUnknownInstrument => {
                 write!(f, "cannot convert frequency to VxPITCH: unknown instrument")
             }
+            Self::CannotConvertTuningTableFrequency(note, p) => {
+                write!(
+                    f,
+                    "cannot convert tuning table frequency for MIDI note {} to VxPITCH ({}, max is {})",
+                    note.note_id() + 12,
+                    p,
+                    PlayPitchPitch::MAX.value()
+                )
+            }
+
+            Self::InvalidConcertPitch(v) => write!(
+                f,
+                "invalid concert pitch: {} (expected {} - {} Hz)",
+                v,
+                CONCERT_A_RANGE.start(),
+                CONCERT_A_RANGE.end()
+            ),
+            Self::MaxChannelBytesOutOfRange(v) => write!(
+                f,
+                "max channel bytes out of bounds ({}, expected 1 - {})",
+                v, MAX_SONG_DATA_SIZE
+            ),
+            Self::InvalidTuningTableSize(n) => write!(
+                f,
+                "invalid tuning table size ({} frequencies, expected {})",
+                n, TUNING_TABLE_SIZE
+            ),
+            Self::InvalidTuningTableFrequency(midi_note, v) => write!(
+                f,
+                "invalid tuning table frequency for MIDI note {}: {} (expected > 0 and <= {} Hz)",
+                midi_note,
+                v,
+                PlayPitchFrequency::MAX.value()
+            ),
 
             Self::PxPanOutOfRange(v) => write!(
                 f,
@@ -1083,6 +1415,8 @@ impl Display for ValueError {
             Self::ZenLenOutOfRange(v) => out_of_range!("zenlen", v, ZenLen),
             Self::TickClockOutOfRange(v) => out_of_range!("tick clock", v, TickClock),
             Self::BpmOutOfRange(v) => out_of_range!("BPM", v, Bpm),
+            Self::TempoScaleOutOfRange(v) => out_of_range!("tempo scale", v, TempoScale),
+            Self::MinRestTicksOutOfRange(v) => out_of_range!("min rest ticks", v, MinRestTicks),
 
             Self::MidiNoteNumberOutOfRange(v) => out_of_range!("MIDI note number", v, MidiNote),
             Self::CannotConvertMidiNote => {
@@ -1162,6 +1496,7 @@ impl Display for ValueError {
             Self::InvalidFirFilterSize => {
                 write!(f, "expected {} FIR filter values", FIR_FILTER_SIZE)
             }
+            Self::UnknownFirPreset(s) => write!(f, "unknown FIR filter preset: {s}"),
             Self::InvalidFirFilter => write!(
                 f,
                 "invalid FIR filter (all {} values must be between {} - {})",
@@ -1221,6 +1556,8 @@ impl Display for ValueError {
             Self::NoFineQuantize => write!(f, "no fine quantization value"),
             Self::NoTickClock => write!(f, "no tick clock"),
             Self::NoBpm => write!(f, "no tempo bpm"),
+            Self::NoTempoScale => write!(f, "no tempo scale"),
+            Self::NoMinRestTicks => write!(f, "no min rest ticks"),
             Self::NoMidiNote => write!(f, "no MIDI note number"),
             Self::NoPortamentoSpeed => write!(f, "no portamento speed"),
             Self::NoPortamentoVelocity => write!(f, "no portamento velocity"),
@@ -1483,11 +1820,23 @@ impl Display for CombineSoundEffectsError {
     }
 }
 
+impl Display for FlacError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidFlacFile(s) => write!(f, "invalid flac file: {}", s),
+            Self::Unsupported(s) => write!(f, "unsupported flac file: {}", s),
+            Self::NoSamples => write!(f, "flac file is empty (no samples)"),
+            Self::FlacFileTooLarge => write!(f, "flac file is too large"),
+        }
+    }
+}
+
 impl Display for BrrError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::IoError(arc) => write!(f, "cannot read {}: {}", arc.0, arc.1),
             Self::WaveFileError(arc) => write!(f, "cannot read {}: {}", arc.0, arc.1),
+            Self::FlacFileError(p, e) => write!(f, "cannot read {}: {}", p, e),
 
             Self::UnknownFileType(p) => write!(f, "unknown file type: {}", p),
             Self::BrrEncodeError(p, e) => write!(f, "error encoding {}: {}", p, e),
@@ -1541,6 +1890,33 @@ impl Display for CommonAudioDataError {
             Self::SoundEffectDataTooLarge { by } => {
                 write!(f, "sound effect data is too large (by {} bytes)", by)
             }
+            Self::EchoBufferOverlap {
+                echo_start,
+                echo_end,
+                data_start,
+                data_end,
+            } => {
+                write!(
+                    f,
+                    "echo buffer (${:04x}-${:04x}) overlaps common audio data and song data (${:04x}-${:04x}), ",
+                    echo_start,
+                    echo_end - 1,
+                    data_start,
+                    data_end - 1
+                )?;
+                match largest_edl_without_overlap(*data_end) {
+                    Some(edl) => write!(
+                        f,
+                        "EDL 0 to {} fit (the largest echo length that fits is {}ms)",
+                        edl.as_u8(),
+                        edl.to_length().value()
+                    ),
+                    None => write!(
+                        f,
+                        "the data does not fit in audio-RAM without an echo buffer"
+                    ),
+                }
+            }
         }
     }
 }
@@ -1576,6 +1952,56 @@ impl Display for IdentifierError {
     }
 }
 
+impl Display for InstrumentPitchWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NoteUnreachable {
+                instrument,
+                note,
+                reason,
+            } => {
+                let reason = match reason {
+                    UnreachableReason::PitchTooHigh => "pitch too high",
+                    UnreachableReason::PitchTooLow => "pitch too low",
+                };
+                write!(f, "{}: cannot play note {} ({})", instrument, note, reason)
+            }
+        }
+    }
+}
+
+impl Display for InstrumentSampleNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::DcOffsetRemoved {
+                instrument,
+                dc_offset,
+            } => write!(
+                f,
+                "{}: removed a DC offset of {} ({:.1}% of full scale), the source file may need fixing",
+                instrument,
+                dc_offset,
+                f64::from(*dc_offset).abs() * 100.0 / 32768.0
+            ),
+        }
+    }
+}
+
+impl Display for MmlWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnusedInstrument(name) => write!(f, "unused instrument: {}", name),
+            Self::UnusedSubroutine(name) => write!(f, "unused subroutine: !{}", name),
+            Self::RestPaddedForTiming { original, padded } => write!(
+                f,
+                "rest after note padded from {} to {} ticks (#MinRestTicks)",
+                original.value(),
+                padded.value()
+            ),
+        }
+    }
+}
+
 impl Display for MmlLineError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -1596,6 +2022,7 @@ impl Display for MmlLineError {
             Self::InvalidIdentifier(e) => e.fmt(f),
             Self::UnknownChannel(name) => write!(f, "unknown channels {}", name),
             Self::CannotParseLine => write!(f, "cannot parse line"),
+            Self::InvalidSectionTickCount(s) => write!(f, "invalid section tick count: {}", s),
 
             Self::NoHeader => write!(f, "no header name"),
             Self::NoValue => write!(f, "no header value"),
@@ -1603,6 +2030,23 @@ impl Display for MmlLineError {
             Self::UnknownHeader(name) => write!(f, "unknown header: {}", name),
             Self::DuplicateHeader(name) => write!(f, "duplicate header: {}", name),
 
+            Self::NoIncludePath => write!(f, "expected #include \"path\""),
+            Self::IncludeInFileWithoutPath => {
+                write!(f, "cannot #include in a MML file that has not been saved")
+            }
+            Self::CannotLoadInclude(e) => write!(f, "cannot include {}", e),
+            Self::CircularInclude(name) => write!(f, "circular include: {}", name),
+
+            Self::NoMacroName => write!(f, "no macro name"),
+            Self::MacroNameIsCommand(name) => {
+                write!(f, "cannot use \\{} as a macro name", name)
+            }
+            Self::MissingMacroParametersEnd => write!(f, "missing `)` after macro parameters"),
+            Self::DuplicateMacroParameter(name) => {
+                write!(f, "duplicate macro parameter: {}", name)
+            }
+            Self::DuplicateMacro(name) => write!(f, "duplicate macro: {}", name),
+
             Self::InvalidEchoFeedback => write!(f, "invalid echo feedback"),
             Self::InvalidNumberOfEchoVolumeArguments => write!(
                 f,
@@ -1610,6 +2054,8 @@ impl Display for MmlLineError {
             ),
             Self::CannotSetTempo => write!(f, "tick clock already set by #Timer"),
             Self::CannotSetTimer => write!(f, "tick clock already set by #Tempo"),
+            Self::CannotSetTuning => write!(f, "tuning already set by #TuningTable"),
+            Self::CannotSetTuningTable => write!(f, "tuning already set by #Tuning"),
             Self::InvalidSpcSongLength => write!(
                 f,
                 "invalid spc export song length (expected 0 - {})",
@@ -1654,6 +2100,35 @@ impl Display for ChannelError {
             Self::NoBraceAfterAsm => write!(f, r"missing `{{` brace after \asm"),
             Self::MissingEndAsm => write!(f, r"cannot find \asm end (no `}}`)"),
 
+            Self::MissingMacroArguments(name) => write!(f, r"missing arguments for \{name}"),
+            Self::MissingMacroArgumentsEnd(name) => {
+                write!(f, r"missing `)` after \{name} arguments")
+            }
+            Self::InvalidNumberOfMacroArguments(expected, found) => write!(
+                f,
+                "invalid number of macro arguments (expected {}, found {})",
+                expected, found
+            ),
+            Self::RecursiveMacro(name) => write!(f, r"recursive macro: \{name}"),
+            Self::MacroTooDeep(name) => write!(
+                f,
+                r"macro expansion too deep: \{name} (max {} levels)",
+                MAX_MACRO_DEPTH
+            ),
+            Self::MacroExpansionTooLarge(name) => {
+                write!(f, r"\{name} macro expansion is too large")
+            }
+            Self::AsmInMacro => write!(f, r"\asm is not allowed in a macro"),
+            Self::InMacro {
+                error,
+                name,
+                definition,
+            } => write!(
+                f,
+                r"{error} (in \{name}, defined on line {})",
+                definition.line_number()
+            ),
+
             Self::NoteIsTooShort => write!(
                 f,
                 "note is too short (2 ticks are required for a key-off note)"
@@ -1768,6 +2243,9 @@ impl Display for ChannelError {
                     len, MAX_BROKEN_CHORD_NOTES
                 )
             }
+            Self::NoArpeggioSpeed => write!(f, "missing arpeggio speed"),
+            Self::ArpeggioSpeedZero => write!(f, "arpeggio speed cannot be 0"),
+            Self::NoPitchInArpeggio => write!(f, "missing arpeggio pitch after comma"),
             Self::BrokenChordTotalLengthTooShort => {
                 write!(
                     f,
@@ -1797,6 +2275,24 @@ impl Display for ChannelError {
 
             Self::NoTicksAfterLoopPoint => write!(f, "no notes or rests after loop point"),
 
+            Self::SectionTickCountMismatch {
+                section,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "section {} starts at tick {}, expected {}",
+                section,
+                actual.value(),
+                expected.value()
+            ),
+
+            Self::ChannelBytecodeTooBig { limit, current } => write!(
+                f,
+                "channel bytecode too large ({} bytes, max {})",
+                current, limit
+            ),
+
             Self::TooManySfxTicks(t) => write!(
                 f,
                 "sound effect too long ({} ticks, max {})",
@@ -1897,6 +2393,24 @@ impl Display for ExportSpcFileError {
     }
 }
 
+impl Display for MidiExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::SongError(e) => e.fmt(f),
+            Self::InterpreterTimeout => write!(f, "timeout interpreting song"),
+            Self::EventOutOfOrder {
+                tick,
+                previous_tick,
+            } => write!(
+                f,
+                "MIDI event at tick {} is before the previous event (tick {})",
+                tick.value(),
+                previous_tick.value()
+            ),
+        }
+    }
+}
+
 impl Display for ExportSegmentType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1950,8 +2464,17 @@ impl Display for ExportError {
 const SFX_MML_ERROR_LIMIT: usize = 30;
 
 fn plus_more_errors_line(f: &mut std::fmt::Formatter, prefix: &str, n: usize) -> std::fmt::Result {
-    if n > SFX_MML_ERROR_LIMIT {
-        let n = n - SFX_MML_ERROR_LIMIT;
+    plus_more_errors_line_limit(f, prefix, n, SFX_MML_ERROR_LIMIT)
+}
+
+fn plus_more_errors_line_limit(
+    f: &mut std::fmt::Formatter,
+    prefix: &str,
+    n: usize,
+    limit: usize,
+) -> std::fmt::Result {
+    if n > limit {
+        let n = n - limit;
         if n > 1 {
             writeln!(f, "{}+ {} more errors", prefix, n)
         } else {
@@ -2073,7 +2596,14 @@ fn fmt_indented_sfx_subroutine_errors(
         }
         SfxSubroutineErrors::SubroutineErrors(errors) => {
             for e in errors {
-                fmt_indented_channel_errors(f, e, file_name, true)?;
+                fmt_indented_channel_errors(
+                    f,
+                    e,
+                    file_name,
+                    &IncludeMap::default(),
+                    true,
+                    SFX_MML_ERROR_LIMIT,
+                )?;
             }
         }
         e => {
@@ -2197,6 +2727,13 @@ impl Display for PitchTableErrorIndentedDisplay<'_> {
         writeln!(f, "Cannot build pitch table")?;
 
         match self.0 {
+            PitchTableError::InvalidConcertPitch(hz) => writeln!(
+                f,
+                "  invalid concert pitch ({} Hz, expected {} - {} Hz)",
+                hz,
+                CONCERT_A_RANGE.start(),
+                CONCERT_A_RANGE.end()
+            ),
             PitchTableError::TooManyInstruments => writeln!(f, "  too many instruments"),
             PitchTableError::TooManyPitches(len) => {
                 writeln!(f, "  too many pitches ({}, max {})", len, MAX_N_PITCHES)
@@ -2242,33 +2779,93 @@ impl CommonAudioDataErrors {
     }
 }
 
-pub struct MmlCompileErrorsIndentedDisplay<'a>(&'a MmlCompileErrors);
+pub struct MmlCompileErrorsIndentedDisplay<'a>(&'a MmlCompileErrors, usize);
+
+/// Displays `file:line` or `file:line:char`.
+///
+/// If the error is in an included file, the location of the `#include` directive is appended.
+pub struct MmlErrorLocation<'a> {
+    file_name: &'a str,
+    include_map: &'a IncludeMap,
+    pos: &'a FilePosRange,
+    show_line_char: bool,
+}
+
+impl Display for MmlErrorLocation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let pos = self.pos;
+
+        let (file_name, line_number, include_site) =
+            match self.include_map.source_location(pos.line_number) {
+                Some(l) => (l.file_name, l.line_number, l.include_site),
+                None => (self.file_name, pos.line_number, None),
+            };
+
+        write!(f, "{}:{}", file_name, line_number)?;
+        if self.show_line_char {
+            write!(f, ":{}", pos.line_char)?;
+        }
+        if let Some((file_name, line_number)) = include_site {
+            write!(f, " (included from {}:{})", file_name, line_number)?;
+        }
+        Ok(())
+    }
+}
 
 impl MmlCompileErrors {
+    /// The file location of a `line_errors` error
+    pub fn line_error_location<'a>(&'a self, pos: &'a FilePosRange) -> MmlErrorLocation<'a> {
+        MmlErrorLocation {
+            file_name: &self.file_name,
+            include_map: &self.include_map,
+            pos,
+            show_line_char: false,
+        }
+    }
+
+    /// The file location of a channel or subroutine error (or a MML warning)
+    pub fn location<'a>(&'a self, pos: &'a FilePosRange) -> MmlErrorLocation<'a> {
+        MmlErrorLocation {
+            file_name: &self.file_name,
+            include_map: &self.include_map,
+            pos,
+            show_line_char: true,
+        }
+    }
+
     pub fn multiline_display(&self) -> MmlCompileErrorsIndentedDisplay {
-        MmlCompileErrorsIndentedDisplay(self)
+        MmlCompileErrorsIndentedDisplay(self, SFX_MML_ERROR_LIMIT)
+    }
+
+    /// Limits the number of errors displayed per channel, subroutine and header
+    pub fn multiline_display_with_limit(
+        &self,
+        max_errors: usize,
+    ) -> MmlCompileErrorsIndentedDisplay {
+        MmlCompileErrorsIndentedDisplay(self, max_errors)
     }
 }
 
 impl Display for MmlCompileErrorsIndentedDisplay<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let error = self.0;
+        let limit = self.1;
 
         match &error.song_name {
             Some(s) => writeln!(f, "Error compiling {}:", s)?,
             None => writeln!(f, "Error compiling {}:", error.file_name)?,
         }
 
-        for e in error.line_errors.iter().take(SFX_MML_ERROR_LIMIT) {
-            writeln!(f, "  {}:{} {}", error.file_name, e.0.line_number, e.1)?;
+        for e in error.line_errors.iter().take(limit) {
+            writeln!(f, "  {} {}", error.line_error_location(&e.0), e.1)?;
         }
-        plus_more_errors_line(f, "  ", error.line_errors.len())?;
+        plus_more_errors_line_limit(f, "  ", error.line_errors.len(), limit)?;
 
         for e in &error.subroutine_errors {
-            fmt_indented_channel_errors(f, e, &error.file_name, true)?;
+            fmt_indented_channel_errors(f, e, &error.file_name, &error.include_map, true, limit)?;
         }
         for e in &error.channel_errors {
-            fmt_indented_channel_errors(f, e, &error.file_name, false)?;
+            fmt_indented_channel_errors(f, e, &error.file_name, &error.include_map, false, limit)?;
         }
         Ok(())
     }
@@ -2279,7 +2876,9 @@ fn fmt_indented_channel_errors(
     f: &mut std::fmt::Formatter,
     error: &MmlChannelError,
     file_name: &str,
+    include_map: &IncludeMap,
     is_subroutine: bool,
+    limit: usize,
 ) -> std::fmt::Result {
     let n_errors = error.errors.len();
     if n_errors == 1 {
@@ -2294,10 +2893,16 @@ fn fmt_indented_channel_errors(
         writeln!(f, "channel {}", error.identifier.as_str())?;
     }
 
-    for e in error.errors.iter().take(SFX_MML_ERROR_LIMIT) {
-        writeln!(f, "    {}:{}:{} {}", file_name, e.0.line_number, e.0.line_char, e.1)?;
+    for e in error.errors.iter().take(limit) {
+        let location = MmlErrorLocation {
+            file_name,
+            include_map,
+            pos: &e.0,
+            show_line_char: true,
+        };
+        writeln!(f, "    {} {}", location, e.1)?;
     }
-    plus_more_errors_line(f, "    ", n_errors)?;
+    plus_more_errors_line_limit(f, "    ", n_errors, limit)?;
 
     Ok(())
 }
@@ -2320,18 +2925,23 @@ impl Display for SongTooLargeErrorIndentedDisplay<'_> {
     }
 }
 
-pub struct SongErrorIndentedDisplay<'a>(&'a SongError);
+pub struct SongErrorIndentedDisplay<'a>(&'a SongError, usize);
 
 impl SongError {
     pub fn multiline_display(&self) -> SongErrorIndentedDisplay {
-        SongErrorIndentedDisplay(self)
+        SongErrorIndentedDisplay(self, SFX_MML_ERROR_LIMIT)
+    }
+
+    /// Limits the number of MML errors displayed per channel, subroutine and header
+    pub fn multiline_display_with_limit(&self, max_errors: usize) -> SongErrorIndentedDisplay {
+        SongErrorIndentedDisplay(self, max_errors)
     }
 }
 
 impl Display for SongErrorIndentedDisplay<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self.0 {
-            SongError::MmlError(e) => MmlCompileErrorsIndentedDisplay(e).fmt(f),
+            SongError::MmlError(e) => MmlCompileErrorsIndentedDisplay(e, self.1).fmt(f),
             e => e.fmt(f),
         }
     }
@@ -84,7 +84,7 @@ impl Exporter for Ca65Exporter {
             writeln!(out, "  ; song_id 0 is blank (silent) song")?;
         }
         for (i, s) in pf.songs.list().iter().enumerate() {
-            writeln!(out, "  {} = {}", s.name, i + 1)?;
+            writeln!(out, "  {} = {}", s.name.as_identifier(), i + 1)?;
         }
         writeln!(out, ".endenum")?;
 
@@ -101,7 +101,7 @@ impl Exporter for Ca65Exporter {
             } else if i == 0 {
                 writeln!(out, "  ; high-priority sound effects")?;
             }
-            writeln!(out, "  {} = {}", s, i)?;
+            writeln!(out, "  {} = {}", s.as_identifier(), i)?;
         }
         writeln!(out, ".endenum")?;
 
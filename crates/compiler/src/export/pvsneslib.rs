@@ -76,7 +76,7 @@ impl Exporter for PvExporter {
             writeln!(out, "  // song_id 0 is blank (silent) song")?;
         }
         for (i, s) in pf.songs.list().iter().enumerate() {
-            writeln!(out, "  SONG_{} = {},", s.name, i + 1)?;
+            writeln!(out, "  SONG_{} = {},", s.name.as_identifier(), i + 1)?;
         }
         writeln!(out, "}};")?;
 
@@ -94,7 +94,7 @@ impl Exporter for PvExporter {
                 } else if i == 0 {
                     writeln!(out, "  // high-priority sound effects")?;
                 }
-                writeln!(out, "  SFX_{} = {},", s, i)?;
+                writeln!(out, "  SFX_{} = {},", s.as_identifier(), i)?;
             }
             writeln!(out, "}};")?;
         }
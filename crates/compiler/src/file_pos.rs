@@ -61,7 +61,7 @@ impl FilePos {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FilePosRange {
     pub(crate) line_number: u32,
     pub(crate) line_char: u32,
@@ -12,26 +12,37 @@ mod file_pos;
 mod value_newtypes;
 
 pub mod bytecode_assembler;
+pub mod bytecode_disassembler;
 pub mod bytecode_interpreter;
 pub mod common_audio_data;
 pub mod data;
 pub mod driver_constants;
 pub mod echo;
+#[cfg(feature = "emulator")]
+pub mod emulator;
 pub mod envelope;
 pub mod errors;
 pub mod export;
 pub mod invert_flags;
+pub mod midi_export;
 pub mod mml;
 pub mod notes;
 pub mod path;
 pub mod pitch_table;
+#[cfg(feature = "emulator")]
+pub mod render;
 pub mod samples;
 pub mod sfx_file;
+pub mod smooth_value;
 pub mod songs;
 pub mod sound_effects;
 pub mod spc_file_export;
 pub mod subroutines;
 pub mod time;
+pub mod wav_file;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 pub use bytecode::opcodes;
 
@@ -0,0 +1,309 @@
+//! Standard MIDI file export
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::bytecode::InstrumentId;
+use crate::bytecode_interpreter::{NoteEvent, NoteEventKind, SongInterpreter};
+use crate::common_audio_data::{build_common_audio_data, CommonAudioData};
+use crate::data::{InstrumentOrSample, TextFile, UniqueNamesList};
+use crate::driver_constants::N_MUSIC_CHANNELS;
+use crate::errors::MidiExportError;
+use crate::mml::compile_mml;
+use crate::notes::Note;
+use crate::pitch_table::PitchTable;
+use crate::samples::{combine_samples, InstrumentSampleData, SampleSampleData};
+use crate::songs::SongData;
+use crate::sound_effects::{blank_compiled_sound_effects, CompiledSfxSubroutines};
+use crate::time::{TickCounter, TIMER_HZ};
+
+use std::sync::{Arc, Mutex};
+
+const MIDI_C0: u8 = 12;
+const MIDI_A4: f64 = 69.0;
+const A4_FREQ: f64 = 440.0;
+const PITCH_REGISTER_FP_SCALE: f64 = 4096.0;
+
+const NOTE_VELOCITY: u8 = 100;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const PROGRAM_CHANGE: u8 = 0xc0;
+
+const META_EVENT: u8 = 0xff;
+const META_TRACK_NAME: u8 = 0x03;
+const META_END_OF_TRACK: u8 = 0x2f;
+const META_SET_TEMPO: u8 = 0x51;
+
+const MICROSECONDS_PER_TIMER_TICK: u32 = 1_000_000 / TIMER_HZ;
+
+/// Converts a note to a MIDI key number.
+///
+/// If the note is played by an instrument, the key is calculated from the instrument's pitch
+/// table entry, so the MIDI key matches the frequency played by the audio driver.
+fn midi_key(
+    note: Note,
+    instrument: Option<u8>,
+    data_instruments: &UniqueNamesList<InstrumentOrSample>,
+    pitch_table: &PitchTable,
+) -> u8 {
+    let inst = instrument.and_then(|i| {
+        let id = InstrumentId::try_from(u32::from(i)).ok()?;
+        match data_instruments.get_index(usize::from(i))? {
+            InstrumentOrSample::Instrument(inst) => Some((id, inst)),
+            InstrumentOrSample::Sample(_) => None,
+        }
+    });
+
+    match inst {
+        Some((id, inst)) => {
+            let pitch = f64::from(pitch_table.pitch_for_note(id, note));
+            let freq = inst.freq * pitch / PITCH_REGISTER_FP_SCALE;
+
+            let key = MIDI_A4 + 12.0 * f64::log2(freq / A4_FREQ);
+            key.round().clamp(0.0, 127.0) as u8
+        }
+        None => (note.note_id() + MIDI_C0).min(127),
+    }
+}
+
+fn write_variable_length(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = [0u8; 4];
+    let mut n = 0;
+    let mut v = value;
+
+    loop {
+        bytes[n] = (v & 0x7f) as u8;
+        n += 1;
+        v >>= 7;
+        if v == 0 {
+            break;
+        }
+    }
+
+    for i in (0..n).rev() {
+        let continue_bit = if i > 0 { 0x80 } else { 0 };
+        out.push(bytes[i] | continue_bit);
+    }
+}
+
+struct Track {
+    data: Vec<u8>,
+    last_tick: TickCounter,
+}
+
+impl Track {
+    fn new(name: &str) -> Self {
+        let mut t = Track {
+            data: Vec::new(),
+            last_tick: TickCounter::new(0),
+        };
+        t.write_meta_event(0, META_TRACK_NAME, name.as_bytes());
+        t
+    }
+
+    fn delta_time(&self, tick: TickCounter) -> Result<u32, MidiExportError> {
+        tick.value()
+            .checked_sub(self.last_tick.value())
+            .ok_or(MidiExportError::EventOutOfOrder {
+                tick,
+                previous_tick: self.last_tick,
+            })
+    }
+
+    fn event(&mut self, tick: TickCounter, bytes: &[u8]) -> Result<(), MidiExportError> {
+        let delta = self.delta_time(tick)?;
+
+        write_variable_length(&mut self.data, delta);
+        self.data.extend_from_slice(bytes);
+
+        self.last_tick = tick;
+        Ok(())
+    }
+
+    fn meta_event(
+        &mut self,
+        tick: TickCounter,
+        meta_type: u8,
+        data: &[u8],
+    ) -> Result<(), MidiExportError> {
+        let delta = self.delta_time(tick)?;
+        self.write_meta_event(delta, meta_type, data);
+
+        self.last_tick = tick;
+        Ok(())
+    }
+
+    fn write_meta_event(&mut self, delta: u32, meta_type: u8, data: &[u8]) {
+        write_variable_length(&mut self.data, delta);
+        self.data.extend_from_slice(&[META_EVENT, meta_type]);
+        write_variable_length(&mut self.data, data.len() as u32);
+        self.data.extend_from_slice(data);
+    }
+
+    fn write_chunk(mut self, out: &mut Vec<u8>) {
+        self.write_meta_event(0, META_END_OF_TRACK, &[]);
+
+        out.extend_from_slice(b"MTrk");
+        out.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.data);
+    }
+}
+
+fn blank_common_audio_data() -> CommonAudioData {
+    let samples = combine_samples(
+        [].as_slice() as &[InstrumentSampleData],
+        [].as_slice() as &[SampleSampleData],
+    )
+    .unwrap();
+
+    build_common_audio_data(
+        &samples,
+        &CompiledSfxSubroutines::blank(),
+        &blank_compiled_sound_effects(),
+    )
+    .unwrap()
+}
+
+struct InterpretedSong {
+    /// Tick and timer register value
+    tempo_changes: Vec<(TickCounter, u8)>,
+    events: Vec<NoteEvent>,
+}
+
+/// Plays the song in a `SongInterpreter` and returns the tempo changes and note events.
+fn interpret_song(song_data: &SongData) -> Result<InterpretedSong, MidiExportError> {
+    // The interpreter only uses the common audio data to calculate the song address
+    let common_audio_data = blank_common_audio_data();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let mut interpreter = SongInterpreter::new(&common_audio_data, song_data, false);
+    interpreter.set_note_event_callback({
+        let events = events.clone();
+        move |e| events.lock().unwrap().push(e)
+    });
+
+    let initial_timer = interpreter.timer_register();
+
+    if !interpreter.process_ticks(song_data.max_tick_count()) {
+        return Err(MidiExportError::InterpreterTimeout);
+    }
+
+    drop(interpreter);
+
+    let mut events = Arc::try_unwrap(events).unwrap().into_inner().unwrap();
+
+    // The interpreter emits events in bytecode order, not tick order.
+    // The sort is stable so the note-off and note-on events of a tick are not reordered.
+    events.sort_by_key(|e| e.tick);
+
+    let mut tempo_changes = vec![(TickCounter::new(0), initial_timer)];
+    for e in &events {
+        if let NoteEventKind::TempoChange { timer_register } = e.kind {
+            if tempo_changes.last().map(|(_, t)| *t) != Some(timer_register) {
+                tempo_changes.push((e.tick, timer_register));
+            }
+        }
+    }
+
+    Ok(InterpretedSong {
+        tempo_changes,
+        events,
+    })
+}
+
+/// Compiles a MML song and exports it to a Type-1 standard MIDI file.
+///
+/// The MIDI file contains a tempo track and a track for each music channel.
+/// One MIDI tick is one song tick.
+///
+/// Instruments are mapped to MIDI programs (instrument index modulo 128).
+/// Pitches (`P`, `PR`, `PF`) are not exported.
+pub fn export_mml_to_midi(
+    mml_file: &TextFile,
+    data_instruments: &UniqueNamesList<InstrumentOrSample>,
+    pitch_table: &PitchTable,
+) -> Result<Vec<u8>, MidiExportError> {
+    let song_data = compile_mml(mml_file, None, data_instruments, pitch_table)
+        .map_err(MidiExportError::SongError)?;
+
+    let InterpretedSong {
+        tempo_changes,
+        events,
+    } = interpret_song(&song_data)?;
+
+    let end_tick = song_data.max_tick_count();
+    let ticks_per_quarter_note = u16::from(song_data.metadata().zenlen.as_u8() / 4).max(1);
+
+    let mut tracks = Vec::with_capacity(N_MUSIC_CHANNELS + 1);
+
+    let mut tempo_track = Track::new(song_data.metadata().title.as_deref().unwrap_or(""));
+    for (tick, timer) in tempo_changes {
+        let timer = match timer {
+            0 => 256,
+            t => u32::from(t),
+        };
+        let us_per_quarter =
+            u32::from(ticks_per_quarter_note) * timer * MICROSECONDS_PER_TIMER_TICK;
+        tempo_track.meta_event(tick, META_SET_TEMPO, &us_per_quarter.to_be_bytes()[1..])?;
+    }
+    tracks.push(tempo_track);
+
+    for (channel_index, channel) in song_data.channels().iter().enumerate() {
+        let channel = match channel {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let midi_channel = channel_index as u8;
+
+        let mut track = Track::new(&channel.name.to_string());
+        let mut program = None;
+        let mut playing_key = None;
+
+        for e in events.iter().filter(|e| e.channel_index == channel_index) {
+            match e.kind {
+                NoteEventKind::NoteOn { note, instrument } => {
+                    let p = instrument.map(|i| i % 128).unwrap_or(0);
+                    if program != Some(p) {
+                        track.event(e.tick, &[PROGRAM_CHANGE | midi_channel, p])?;
+                        program = Some(p);
+                    }
+
+                    let key = midi_key(note, instrument, data_instruments, pitch_table);
+                    track.event(e.tick, &[NOTE_ON | midi_channel, key, NOTE_VELOCITY])?;
+                    playing_key = Some(key);
+                }
+                NoteEventKind::NoteOff => {
+                    if let Some(key) = playing_key.take() {
+                        track.event(e.tick, &[NOTE_OFF | midi_channel, key, 0])?;
+                    }
+                }
+                NoteEventKind::TempoChange { .. } => (),
+            }
+        }
+
+        if let Some(key) = playing_key {
+            let tick = end_tick.max(track.last_tick);
+            track.event(tick, &[NOTE_OFF | midi_channel, key, 0])?;
+        }
+
+        tracks.push(track);
+    }
+
+    let mut out = Vec::new();
+
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    out.extend_from_slice(&ticks_per_quarter_note.to_be_bytes());
+
+    for t in tracks {
+        t.write_chunk(&mut out);
+    }
+
+    Ok(out)
+}
@@ -8,13 +8,16 @@
 
 mod bc_generator;
 mod identifier;
+mod include;
 mod instruments;
 mod line_splitter;
+mod macros;
 mod metadata;
-mod song_duration;
+pub(crate) mod song_duration;
 mod subroutines;
 mod tick_count_table;
 mod tokenizer;
+mod validator;
 
 pub mod command_parser;
 
@@ -22,14 +25,17 @@ pub mod command_parser;
 pub(crate) mod note_tracking;
 
 use self::bc_generator::{parse_and_compile_sound_effect, MmlSongBytecodeGenerator};
+use self::include::expand_includes;
 use self::instruments::{build_instrument_map, parse_instruments};
 use self::line_splitter::{split_mml_song_lines, split_mml_sound_effect_lines};
+use self::macros::expand_macros;
 use self::metadata::parse_headers;
 use bc_generator::parse_and_compile_mml_prefix;
 pub(crate) use identifier::{IdentifierBuf, IdentifierStr};
 use line_splitter::split_mml_sfx_subroutines_header_lines;
 use tokenizer::MmlTokens;
 
+use crate::channel_bc_generator::ChannelCompileOptions;
 use crate::data::{self, TextFile, UniqueNamesList};
 use crate::driver_constants::{MAX_SFX_SUBROUTINES, N_MUSIC_CHANNELS};
 use crate::echo::EchoEdl;
@@ -45,6 +51,7 @@ use crate::time::{TickCounter, DEFAULT_ZENLEN};
 
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
+use std::time::Instant;
 
 pub const FIRST_MUSIC_CHANNEL: char = 'A';
 pub const LAST_MUSIC_CHANNEL: char = 'H';
@@ -63,10 +70,18 @@ pub const SECTION_PREFIX: &str = ";;";
 pub const MAX_MML_PREFIX_STR_LENGTH: usize = 16 * 1024;
 pub const MAX_MML_PREFIX_TICKS: TickCounter = TickCounter::new(16);
 
-pub use self::tick_count_table::MmlTickCountTable;
+pub use self::tick_count_table::{
+    build_section_tick_count_table, MmlTickCountTable, SectionTickCountTable, SectionTickCounts,
+    SectionTickRanges, TickRange,
+};
 
 pub use self::metadata::MetaData;
 
+pub use self::include::{included_files, IncludeMap, SourceLocation, INCLUDE_DIRECTIVE};
+pub use self::macros::{DEFINE_DIRECTIVE, MAX_MACRO_DEPTH};
+
+pub use self::validator::{validate_mml, MmlValidationResult};
+
 #[cfg(feature = "mml_tracking")]
 pub use self::note_tracking::CursorTracker;
 
@@ -82,6 +97,7 @@ pub enum ChannelId {
 pub struct Section {
     name: String,
     line_number: u32,
+    expected_tick_count: Option<TickCounter>,
 }
 
 impl Section {
@@ -91,6 +107,10 @@ impl Section {
     pub fn line_number(&self) -> u32 {
         self.line_number
     }
+    /// The tick count every channel must be at when the section starts (`;; name @ticks`)
+    pub fn expected_tick_count(&self) -> Option<TickCounter> {
+        self.expected_tick_count
+    }
 }
 
 #[derive(Debug)]
@@ -116,6 +136,18 @@ impl MmlSoundEffect {
     }
 }
 
+/// Statistics about a `compile_mml_with_metrics()` call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// Number of tokens in the channels and subroutines
+    pub total_tokens: usize,
+    /// Number of channels that are not empty
+    pub n_channels: usize,
+    pub n_subroutines: usize,
+    /// Wall time of the compilation (including line splitting and header parsing)
+    pub parse_time_micros: u64,
+}
+
 #[derive(Debug)]
 pub struct MmlPrefixData {
     bytecode: Vec<u8>,
@@ -133,22 +165,73 @@ pub fn compile_mml(
     data_instruments: &UniqueNamesList<data::InstrumentOrSample>,
     pitch_table: &PitchTable,
 ) -> Result<SongData, SongError> {
+    compile_mml_with_metrics(mml_file, song_name, data_instruments, pitch_table).0
+}
+
+/// Compiles a MML song and measures how long it took to compile.
+pub fn compile_mml_with_metrics(
+    mml_file: &TextFile,
+    song_name: Option<data::Name>,
+    data_instruments: &UniqueNamesList<data::InstrumentOrSample>,
+    pitch_table: &PitchTable,
+) -> (Result<SongData, SongError>, ParseMetrics) {
+    let start = Instant::now();
+
+    let mut metrics = ParseMetrics::default();
+
+    let r = compile_mml_song(
+        mml_file,
+        song_name,
+        data_instruments,
+        pitch_table,
+        &mut metrics,
+    );
+
+    metrics.parse_time_micros = start.elapsed().as_micros().try_into().unwrap_or(u64::MAX);
+
+    (r, metrics)
+}
+
+fn compile_mml_song(
+    mml_file: &TextFile,
+    song_name: Option<data::Name>,
+    data_instruments: &UniqueNamesList<data::InstrumentOrSample>,
+    pitch_table: &PitchTable,
+    metrics: &mut ParseMetrics,
+) -> Result<SongData, SongError> {
+    let (mml_text, include_map, include_errors) = expand_includes(mml_file);
+
     let mut errors = MmlCompileErrors {
         song_name,
         file_name: mml_file.file_name.clone(),
-        line_errors: Vec::new(),
+        include_map,
+        line_errors: include_errors,
         subroutine_errors: Vec::new(),
         channel_errors: Vec::new(),
     };
+    if !errors.line_errors.is_empty() {
+        return Err(SongError::MmlError(Box::new(errors)));
+    }
+
+    let (macro_calls, macro_errors) = expand_macros(&mml_text);
+    errors.line_errors.extend(macro_errors);
 
-    let lines = match split_mml_song_lines(&mml_file.contents) {
+    let lines = match split_mml_song_lines(&mml_text, &macro_calls) {
         Ok(l) => l,
         Err(e) => {
             errors.line_errors.extend(e);
-            return Err(SongError::MmlError(errors));
+            return Err(SongError::MmlError(Box::new(errors)));
         }
     };
 
+    let non_empty_channels = || lines.channels.iter().filter(|c| !c.is_empty());
+    metrics.n_channels = non_empty_channels().count();
+    metrics.n_subroutines = lines.subroutines.len();
+    metrics.total_tokens = non_empty_channels()
+        .chain(lines.subroutines.iter().map(|(_, t)| t))
+        .map(|t| t.token_iter().count())
+        .sum();
+
     let metadata = match parse_headers(lines.headers) {
         Ok(m) => Some(m),
         Err(e) => {
@@ -170,7 +253,7 @@ pub fn compile_mml(
     };
 
     if !errors.line_errors.is_empty() {
-        return Err(SongError::MmlError(errors));
+        return Err(SongError::MmlError(Box::new(errors)));
     }
     let metadata = metadata.unwrap();
 
@@ -178,24 +261,28 @@ pub fn compile_mml(
     let mut compiler = MmlSongBytecodeGenerator::new(
         metadata.zenlen,
         pitch_table,
-        &mml_file.contents,
+        &mml_text,
         data_instruments,
         &lines.sections,
         &instruments,
         instrument_map,
         &lines.subroutine_name_map,
         metadata.echo_buffer.max_edl,
+        ChannelCompileOptions {
+            max_bytecode_bytes: metadata.max_channel_bytes,
+            tempo_scale: metadata.tempo_scale,
+            min_rest_ticks: metadata.min_rest_ticks,
+            tuning_table: metadata.tuning_table.as_ref(),
+        },
         song_header_size(lines.subroutines.len()),
         true,
     );
 
+    // Channels are compiled even if a subroutine contains an error,
+    // calls to an invalid subroutine are ignored by the parser.
     errors.subroutine_errors =
         compile_subroutines(&mut compiler, lines.subroutines, &lines.subroutine_name_map);
 
-    if !errors.subroutine_errors.is_empty() {
-        return Err(SongError::MmlError(errors));
-    }
-
     let mut channels_iter = lines.channels.into_iter();
 
     let channels = std::array::from_fn(|c_index| {
@@ -214,13 +301,18 @@ pub fn compile_mml(
     });
     assert!(channels_iter.next().is_none());
 
-    if !errors.channel_errors.is_empty() {
-        return Err(SongError::MmlError(errors));
+    if !errors.subroutine_errors.is_empty() || !errors.channel_errors.is_empty() {
+        return Err(SongError::MmlError(Box::new(errors)));
     }
+    #[cfg(feature = "mml_tracking")]
+    let include_map = errors.include_map;
+    #[cfg(not(feature = "mml_tracking"))]
     drop(errors);
 
     #[cfg(feature = "mml_tracking")]
     let (song_data, subroutines, tracking) = compiler.take_data();
+    #[cfg(feature = "mml_tracking")]
+    let tracking = tracking.map_to_root_file(&include_map);
     #[cfg(not(feature = "mml_tracking"))]
     let (song_data, subroutines) = compiler.take_data();
 
@@ -282,6 +374,7 @@ pub(crate) fn compile_sfx_subroutines(
         instrument_map,
         &lines.subroutine_name_map,
         EchoEdl::MIN,
+        ChannelCompileOptions::default(),
         0,
         false,
     );
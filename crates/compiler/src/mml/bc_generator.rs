@@ -19,7 +19,7 @@ use crate::songs::{BytecodePos, SongBcTracking};
 
 use crate::bytecode::{BcTerminator, BytecodeContext, SubroutineId};
 use crate::channel_bc_generator::{
-    ChannelBcGenerator, Command, MmlInstrument, MpState, SubroutineCallType,
+    ChannelBcGenerator, ChannelCompileOptions, Command, MmlInstrument, MpState, SubroutineCallType,
 };
 use crate::errors::{ChannelError, ErrorWithPos, MmlChannelError};
 use crate::pitch_table::PitchTable;
@@ -73,6 +73,7 @@ pub struct MmlSongBytecodeGenerator<'a> {
     mml_instrument_map: HashMap<IdentifierStr<'a>, usize>,
 
     max_edl: EchoEdl,
+    channel_options: ChannelCompileOptions<'a>,
 
     subroutines: SongSubroutines<'a>,
 
@@ -98,6 +99,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
         instrument_map: HashMap<IdentifierStr<'a>, usize>,
         subroutine_name_map: &'a HashMap<IdentifierStr<'a>, usize>,
         max_edl: EchoEdl,
+        channel_options: ChannelCompileOptions<'a>,
         header_size: usize,
         is_song: bool,
     ) -> Self {
@@ -114,6 +116,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
             is_song,
 
             max_edl,
+            channel_options,
             subroutines: SongSubroutines {
                 vec: Vec::new(),
                 id_map: HashMap::new(),
@@ -250,6 +253,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
             &self.mml_instrument_map,
             &self.subroutines,
             self.default_zenlen,
+            self.channel_options.min_rest_ticks,
             None, // No sections in subroutines
             #[cfg(feature = "mml_tracking")]
             &mut self.cursor_tracker,
@@ -268,6 +272,12 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
                 },
                 false => BytecodeContext::SfxSubroutine,
             },
+            ChannelCompileOptions {
+                tempo_scale: self.channel_options.tempo_scale,
+                min_rest_ticks: self.channel_options.min_rest_ticks,
+                tuning_table: self.channel_options.tuning_table,
+                ..Default::default()
+            },
         );
 
         let tail_call = Self::parse_and_compile_tail_call(
@@ -397,6 +407,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
             &self.mml_instrument_map,
             &self.subroutines,
             self.default_zenlen,
+            self.channel_options.min_rest_ticks,
             Some(self.sections),
             #[cfg(feature = "mml_tracking")]
             &mut self.cursor_tracker,
@@ -413,6 +424,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
                 index: channel_index,
                 max_edl: self.max_edl,
             },
+            self.channel_options,
         );
 
         Self::parse_and_compile(
@@ -457,6 +469,7 @@ impl<'a> MmlSongBytecodeGenerator<'a> {
                 loop_point,
                 tick_counter: bc_state.tick_counter,
                 max_stack_depth: bc_state.max_stack_depth,
+                subroutine_depth: bc_state.subroutine_depth,
                 section_tick_counters,
                 tempo_changes: bc_state.tempo_changes,
             })
@@ -487,6 +500,7 @@ pub fn parse_and_compile_sound_effect(
         instruments_map,
         sfx_subroutines,
         DEFAULT_ZENLEN,
+        None,
         None, // No sections in sound effect
         #[cfg(feature = "mml_tracking")]
         &mut cursor_tracker,
@@ -500,6 +514,7 @@ pub fn parse_and_compile_sound_effect(
         mml_instruments,
         sfx_subroutines,
         BytecodeContext::SoundEffect,
+        ChannelCompileOptions::default(),
     );
 
     while let Some(c) = parser.next() {
@@ -564,6 +579,7 @@ pub fn parse_and_compile_mml_prefix(
         instruments_map,
         &NoSubroutines(),
         DEFAULT_ZENLEN,
+        None,
         None, // No sections in sound effect
         // ::TODO remove cursor tracker here::
         #[cfg(feature = "mml_tracking")]
@@ -578,6 +594,7 @@ pub fn parse_and_compile_mml_prefix(
         mml_instruments,
         &NoSubroutines(),
         BytecodeContext::MmlPrefix,
+        ChannelCompileOptions::default(),
     );
 
     while let Some(c) = parser.next() {
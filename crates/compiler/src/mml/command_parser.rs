@@ -25,13 +25,14 @@ use crate::channel_bc_generator::{
 use crate::driver_constants::FIR_FILTER_SIZE;
 use crate::echo::{EchoVolume, FirCoefficient, FirTap};
 use crate::envelope::{Gain, GainMode, OptionalGain, TempGain};
-use crate::errors::{ChannelError, ErrorWithPos, ValueError};
+use crate::errors::{ChannelError, ErrorWithPos, MmlWarning, ValueError};
 use crate::file_pos::{FilePos, FilePosRange};
 use crate::notes::{MidiNote, MmlPitch, Note, Octave, STARTING_OCTAVE};
 use crate::pitch_table::PlayPitchSampleRate;
 use crate::subroutines::{FindSubroutineResult, SubroutineStore};
 use crate::time::{
-    MmlDefaultLength, MmlLength, TickCounter, TickCounterWithLoopFlag, ZenLen, STARTING_MML_LENGTH,
+    MinRestTicks, MmlDefaultLength, MmlLength, TickCounter, TickCounterWithLoopFlag, ZenLen,
+    STARTING_MML_LENGTH,
 };
 use crate::value_newtypes::{
     i8_value_newtype, I8WithByteHexValueNewType, SignedValueNewType, UnsignedValueNewType,
@@ -91,9 +92,11 @@ mod parser {
 
         tokens: PeekableTokenIterator<'a>,
         errors: Vec<ErrorWithPos<ChannelError>>,
+        warnings: Vec<ErrorWithPos<MmlWarning>>,
         state: State,
 
         default_length: TickCounter,
+        min_rest_ticks: Option<MinRestTicks>,
 
         tick_counter: TickCounterWithLoopFlag,
         sections_tick_counters: Vec<TickCounterWithLoopFlag>,
@@ -103,6 +106,10 @@ mod parser {
         instruments_map: &'a HashMap<IdentifierStr<'a>, usize>,
         subroutines: &'a dyn SubroutineStore,
 
+        // Set when a subroutine that contains an error is called.
+        // The channel state is unknown after the call and all following errors are ignored.
+        called_invalid_subroutine: bool,
+
         #[cfg(feature = "mml_tracking")]
         cursor_tracker: &'a mut CursorTracker,
     }
@@ -114,6 +121,7 @@ mod parser {
             instruments_map: &'a HashMap<IdentifierStr, usize>,
             subroutines: &'a dyn SubroutineStore,
             zenlen: ZenLen,
+            min_rest_ticks: Option<MinRestTicks>,
             sections: Option<&'a [Section]>,
 
             #[cfg(feature = "mml_tracking")] cursor_tracking: &'a mut CursorTracker,
@@ -133,6 +141,7 @@ mod parser {
                 channel,
                 tokens: PeekableTokenIterator::new(tokens),
                 errors: Vec::new(),
+                warnings: Vec::new(),
                 state: State {
                     zenlen,
                     default_length: STARTING_MML_LENGTH,
@@ -142,6 +151,7 @@ mod parser {
                 },
 
                 default_length: zenlen.starting_length(),
+                min_rest_ticks,
 
                 tick_counter: TickCounterWithLoopFlag::default(),
                 sections_tick_counters: Vec::with_capacity(n_sections),
@@ -150,6 +160,8 @@ mod parser {
                 instruments_map,
                 subroutines,
 
+                called_invalid_subroutine: false,
+
                 #[cfg(feature = "mml_tracking")]
                 cursor_tracker: cursor_tracking,
             }
@@ -193,12 +205,64 @@ mod parser {
         }
 
         pub fn add_error(&mut self, pos: FilePos, e: ChannelError) {
-            self.errors
-                .push(ErrorWithPos(self.file_pos_range_from(pos), e))
+            self.add_error_range(self.file_pos_range_from(pos), e)
         }
 
         pub fn add_error_range(&mut self, pos: FilePosRange, e: ChannelError) {
-            self.errors.push(ErrorWithPos(pos, e))
+            if !self.called_invalid_subroutine {
+                let e = match self.tokens.macro_call_at(&pos) {
+                    Some(call) => call.in_macro_error(e),
+                    None => e,
+                };
+                self.errors.push(ErrorWithPos(pos, e))
+            }
+        }
+
+        /// Ignores all future errors.
+        ///
+        /// Prevents a subroutine error from emitting a cascade of errors in the channels that
+        /// call the subroutine.
+        pub(super) fn set_called_invalid_subroutine(&mut self) {
+            self.called_invalid_subroutine = true;
+        }
+
+        pub(super) fn add_warning(&mut self, pos: FilePos, w: MmlWarning) {
+            self.warnings
+                .push(ErrorWithPos(self.file_pos_range_from(pos), w))
+        }
+
+        pub fn take_warnings(&mut self) -> Vec<ErrorWithPos<MmlWarning>> {
+            std::mem::take(&mut self.warnings)
+        }
+
+        pub(super) fn min_rest_ticks(&self) -> Option<MinRestTicks> {
+            self.min_rest_ticks
+        }
+
+        /// Skips tokens until the next note, new line or end of input.
+        ///
+        /// Used to prevent a single invalid command from emitting a cascade of errors.
+        /// New lines are not skipped to ensure section and cursor tracking remains valid.
+        pub(super) fn recover_to_next_note(&mut self) {
+            while !matches!(
+                self.tokens.peek(),
+                Token::End
+                    | Token::NewLine(_)
+                    | Token::Pitch(_)
+                    | Token::PlayPitch
+                    | Token::PlayPitchSampleRate
+                    | Token::PlayPitchFrequency
+                    | Token::PlayNoise
+                    | Token::PlaySample
+                    | Token::PlayMidiNoteNumber
+                    | Token::Rest
+                    | Token::Wait
+                    | Token::StartPortamento
+                    | Token::StartBrokenChord
+                    | Token::Arpeggio
+            ) {
+                self.tokens.next();
+            }
         }
 
         pub fn instruments_map(&self) -> &HashMap<IdentifierStr<'a>, usize> {
@@ -247,13 +311,28 @@ mod parser {
         pub(super) fn process_new_line(&mut self, r: LineIndexRange) {
             let _ = r;
 
-            if let Some(pending_sections) = self.pending_sections.as_mut() {
-                while pending_sections
-                    .first()
-                    .is_some_and(|s| self.tokens.peek_pos().line_number >= s.line_number)
+            if let Some(mut pending_sections) = self.pending_sections {
+                let pos = self.tokens.peek_pos();
+
+                while let Some((s, remaining)) = pending_sections
+                    .split_first()
+                    .filter(|(s, _)| pos.line_number >= s.line_number)
                 {
-                    *pending_sections = &pending_sections[1..];
+                    pending_sections = remaining;
+                    self.pending_sections = Some(remaining);
                     self.sections_tick_counters.push(self.tick_counter);
+
+                    if let Some(expected) = s.expected_tick_count() {
+                        let actual = self.tick_counter.ticks;
+                        if actual != expected {
+                            let e = ChannelError::SectionTickCountMismatch {
+                                section: s.name().to_owned(),
+                                expected,
+                                actual,
+                            };
+                            self.add_error_range(pos.to_range(1), e);
+                        }
+                    }
                 }
             }
 
@@ -1426,15 +1505,39 @@ fn parse_rests_after_rest(p: &mut Parser) -> TickCounter {
 }
 
 fn parse_rest_ticks_after_note(is_slur: bool, p: &mut Parser) -> RestTicksAfterNote {
-    match is_slur {
-        false => RestTicksAfterNote(parse_rests_after_rest(p)),
+    let pos = p.peek_pos();
+
+    let ticks = match is_slur {
+        false => parse_rests_after_rest(p),
         true => {
             if next_token_matches!(p, Token::Rest) {
-                RestTicksAfterNote(parse_tracked_length(p) + parse_ties(p))
+                parse_tracked_length(p) + parse_ties(p)
             } else {
-                RestTicksAfterNote(TickCounter::new(0))
+                TickCounter::new(0)
             }
         }
+    };
+
+    RestTicksAfterNote(pad_rest_after_note(pos, ticks, p))
+}
+
+/// Lengthens a short rest after a note to `#MinRestTicks`
+fn pad_rest_after_note(pos: FilePos, ticks: TickCounter, p: &mut Parser) -> TickCounter {
+    match p.min_rest_ticks() {
+        Some(min) if ticks.value() > 0 && ticks.value() < min.as_u8().into() => {
+            let padded = TickCounter::new(min.as_u8().into());
+
+            p.increment_tick_counter(TickCounter::new(padded.value() - ticks.value()));
+            p.add_warning(
+                pos,
+                MmlWarning::RestPaddedForTiming {
+                    original: ticks,
+                    padded,
+                },
+            );
+            padded
+        }
+        _ => ticks,
     }
 }
 
@@ -1792,6 +1895,84 @@ fn parse_broken_chord(p: &mut Parser) -> Command {
     }
 }
 
+fn parse_arpeggio_pitch(p: &mut Parser) -> Option<NoteOrPitch> {
+    loop {
+        let (pos, token) = p.peek_and_next();
+
+        match token {
+            Token::Pitch(pitch) => {
+                return match Note::from_mml_pitch(
+                    pitch,
+                    p.state().octave,
+                    p.state().semitone_offset,
+                ) {
+                    Ok(note) => Some(NoteOrPitch::Note(note)),
+                    Err(e) => {
+                        p.add_error(pos, e.into());
+                        None
+                    }
+                };
+            }
+
+            Token::End | Token::Comma => {
+                p.add_error(pos, ChannelError::NoPitchInArpeggio);
+                return None;
+            }
+            Token::NewLine(r) => {
+                p.add_error(pos, ChannelError::NoPitchInArpeggio);
+                p.process_new_line(r);
+                return None;
+            }
+
+            _ => {
+                parse_pitch_list_state_change_token(token, pos, p);
+            }
+        }
+    }
+}
+
+// Arpeggio is a broken chord with comma separated pitches and a fixed note length
+fn parse_arpeggio(pos: FilePos, p: &mut Parser) -> Command {
+    let speed = match next_token_number(p) {
+        Some(0) => {
+            p.add_error(pos, ChannelError::ArpeggioSpeedZero);
+            None
+        }
+        Some(s) => match PlayNoteTicks::try_from_is_slur(s, true) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                p.add_error(pos, e.into());
+                None
+            }
+        },
+        None => {
+            p.add_error(pos, ChannelError::NoArpeggioSpeed);
+            None
+        }
+    };
+
+    let mut notes = Vec::new();
+    let mut valid = true;
+
+    while next_token_matches!(p, Token::Comma) {
+        match parse_arpeggio_pitch(p) {
+            Some(n) => notes.push(n),
+            None => valid = false,
+        }
+    }
+
+    let total_length = parse_tracked_length(p);
+
+    match (speed, valid) {
+        (Some(note_length), true) => Command::BrokenChord {
+            notes,
+            total_length,
+            note_length,
+        },
+        _ => Command::None,
+    }
+}
+
 fn parse_mp_vibrato(pos: FilePos, p: &mut Parser) -> Option<MpVibrato> {
     match_next_token!(
         p,
@@ -1975,8 +2156,10 @@ fn parse_call_subroutine(
         }
         FindSubroutineResult::NotCompiled => {
             // Subroutine has been compiled, but it contains an error
+            p.set_called_invalid_subroutine();
             Command::None
         }
+        FindSubroutineResult::Unchecked => Command::None,
         FindSubroutineResult::Recussion => invalid_token_error(
             p,
             pos,
@@ -2356,6 +2539,7 @@ fn parse_token(pos: FilePos, token: Token, p: &mut Parser) -> Command {
         Token::Wait => parse_wait(p),
         Token::StartPortamento => parse_portamento(pos, p),
         Token::StartBrokenChord => parse_broken_chord(p),
+        Token::Arpeggio => parse_arpeggio(pos, p),
 
         Token::DisableNoise => Command::DisableNoise,
 
@@ -2489,6 +2673,10 @@ fn parse_token(pos: FilePos, token: Token, p: &mut Parser) -> Command {
             invalid_token_error(p, pos, ChannelError::CannotParseGainMode)
         }
 
-        Token::Error(e) => invalid_token_error(p, pos, e),
+        Token::Error(e) => {
+            p.add_error(pos, e);
+            p.recover_to_next_note();
+            Command::None
+        }
     }
 }
@@ -0,0 +1,345 @@
+//! MML `#include` directive
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use super::COMMENT_CHAR;
+
+use crate::data::{_load_text_file_with_limit, TextFile};
+use crate::errors::{ErrorWithPos, MmlLineError};
+use crate::file_pos::{split_lines, FilePos, FilePosRange, MAX_MML_TEXT_LENGTH};
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+pub const INCLUDE_DIRECTIVE: &str = "#include";
+
+/// Returns true if the line (without comments) is an `#include` directive
+pub(crate) fn is_include_directive(line: &str) -> bool {
+    match line.strip_prefix(INCLUDE_DIRECTIVE) {
+        Some(s) => s.is_empty() || s.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.split_once(COMMENT_CHAR) {
+        Some((l, _comment)) => l,
+        None => line,
+    }
+}
+
+fn parse_include_path(line: &str) -> Result<&str, MmlLineError> {
+    let arg = line[INCLUDE_DIRECTIVE.len()..].trim();
+
+    match arg
+        .strip_prefix('"')
+        .and_then(|a| a.strip_suffix('"'))
+        .filter(|a| !a.is_empty() && !a.contains('"'))
+    {
+        Some(p) => Ok(p),
+        None => Err(MmlLineError::NoIncludePath),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IncludedFile {
+    file_name: String,
+    /// File index and line number of the `#include` directive
+    include_site: Option<(usize, u32)>,
+    /// Position of the `#include` directive in the root MML file
+    root_site: Option<FilePosRange>,
+}
+
+/// A run of consecutive lines from a single file
+#[derive(Debug, Clone)]
+struct Segment {
+    /// Line number of the first line in the expanded MML text
+    first_line: u32,
+    /// Character index of the first line in the expanded MML text
+    first_char: u32,
+    /// Line number of the first line in the source file
+    source_line: u32,
+    /// Character index of the first line in the source file
+    source_char: u32,
+    file: usize,
+}
+
+/// The location of a line in the original (unexpanded) MML files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation<'a> {
+    pub file_name: &'a str,
+    pub line_number: u32,
+    /// File name and line number of the `#include` directive that included this line
+    pub include_site: Option<(&'a str, u32)>,
+}
+
+#[derive(Debug, Clone)]
+struct IncludeMapData {
+    files: Vec<IncludedFile>,
+    segments: Vec<Segment>,
+}
+
+/// Maps the line numbers of a MML file with the `#include` directives expanded
+/// to the original files.
+///
+/// Empty if the MML file did not include any files.
+//
+// Boxed to keep `SongError` small.
+#[derive(Debug, Clone, Default)]
+pub struct IncludeMap(Option<Box<IncludeMapData>>);
+
+impl IncludeMap {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns `None` if the map is empty or `line_number` is not in the expanded MML text
+    pub fn source_location(&self, line_number: u32) -> Option<SourceLocation> {
+        let map = self.0.as_ref()?;
+
+        let i = map
+            .segments
+            .partition_point(|s| s.first_line <= line_number);
+        let s = map.segments.get(i.checked_sub(1)?)?;
+        let file = &map.files[s.file];
+
+        Some(SourceLocation {
+            file_name: &file.file_name,
+            line_number: s.source_line + (line_number - s.first_line),
+            include_site: file
+                .include_site
+                .map(|(f, line)| (map.files[f].file_name.as_str(), line)),
+        })
+    }
+
+    fn segment_at_char(map: &IncludeMapData, char_index: u32) -> Option<&Segment> {
+        let i = map.segments.partition_point(|s| s.first_char <= char_index);
+        map.segments.get(i.checked_sub(1)?)
+    }
+
+    /// Returns true if `char_index` (in the expanded MML text) is not inside an included file
+    pub fn is_in_root_file(&self, char_index: u32) -> bool {
+        match &self.0 {
+            Some(map) => match Self::segment_at_char(map, char_index) {
+                Some(s) => map.files[s.file].root_site.is_none(),
+                None => true,
+            },
+            None => true,
+        }
+    }
+
+    /// Maps a character index in the expanded MML text to the root MML file.
+    ///
+    /// Characters inside an included file are mapped to the root file's `#include` directive.
+    pub fn to_root_char_index(&self, char_index: u32) -> u32 {
+        let map = match &self.0 {
+            Some(m) => m,
+            None => return char_index,
+        };
+
+        match Self::segment_at_char(map, char_index) {
+            Some(s) => match &map.files[s.file].root_site {
+                None => s.source_char + (char_index - s.first_char),
+                Some(site) => site.index_start,
+            },
+            None => char_index,
+        }
+    }
+
+    /// Maps a position in the expanded MML text to the root MML file.
+    ///
+    /// Positions inside an included file are mapped to the root file's `#include` directive.
+    pub fn to_root_file_pos(&self, pos: &FilePosRange) -> FilePosRange {
+        let map = match &self.0 {
+            Some(m) => m,
+            None => return pos.clone(),
+        };
+
+        match Self::segment_at_char(map, pos.index_start) {
+            Some(s) => match &map.files[s.file].root_site {
+                None => FilePosRange {
+                    line_number: s.source_line + pos.line_number.saturating_sub(s.first_line),
+                    line_char: pos.line_char,
+                    index_start: s.source_char + (pos.index_start - s.first_char),
+                    index_end: s.source_char + pos.index_end.saturating_sub(s.first_char),
+                },
+                Some(site) => site.clone(),
+            },
+            None => pos.clone(),
+        }
+    }
+}
+
+struct Expander {
+    out: String,
+    n_lines: u32,
+    map: IncludeMapData,
+    /// Canonical paths of the files currently being expanded
+    stack: Vec<PathBuf>,
+    /// Paths of every `#include` directive (including the files that cannot be loaded)
+    include_paths: Vec<PathBuf>,
+    errors: Vec<ErrorWithPos<MmlLineError>>,
+}
+
+impl Expander {
+    fn expand_file(&mut self, file: usize, contents: &str, path: Option<&Path>) {
+        let mut new_segment = true;
+
+        for line in split_lines(contents) {
+            // Prevents exponential growth when a file is included multiple times.
+            // `split_mml_song_lines()` will emit a `MmlTooLarge` error.
+            if self.out.len() > MAX_MML_TEXT_LENGTH {
+                return;
+            }
+
+            if new_segment {
+                self.map.segments.push(Segment {
+                    first_line: self.n_lines + 1,
+                    first_char: u32::try_from(self.out.len()).unwrap(),
+                    source_line: line.position.line_number,
+                    source_char: line.position.char_index,
+                    file,
+                });
+                new_segment = false;
+            }
+
+            let char_index = u32::try_from(self.out.len()).unwrap();
+            self.out.push_str(line.text);
+            self.out.push('\n');
+            self.n_lines += 1;
+
+            let text = strip_comment(line.text).trim_end();
+            if is_include_directive(text) {
+                let site = line.position.to_range_str_len(text);
+
+                match self.include(file, site, text, path) {
+                    Ok(()) => new_segment = true,
+                    Err(e) => {
+                        let pos = FilePos {
+                            line_number: self.n_lines,
+                            line_char: 0,
+                            char_index,
+                        };
+                        self.errors
+                            .push(ErrorWithPos(pos.to_range_str_len(text), e));
+                    }
+                }
+            }
+        }
+    }
+
+    fn include(
+        &mut self,
+        parent: usize,
+        site: FilePosRange,
+        directive: &str,
+        parent_path: Option<&Path>,
+    ) -> Result<(), MmlLineError> {
+        let file_name = parse_include_path(directive)?;
+
+        let parent_path = parent_path.ok_or(MmlLineError::IncludeInFileWithoutPath)?;
+        let path = match parent_path.parent() {
+            Some(dir) => dir.join(file_name),
+            None => PathBuf::from(file_name),
+        };
+        self.include_paths.push(path.clone());
+
+        let canonical = std::fs::canonicalize(&path)
+            .map_err(|e| MmlLineError::CannotLoadInclude(format!("{}: {}", file_name, e)))?;
+        if self.stack.contains(&canonical) {
+            return Err(MmlLineError::CircularInclude(file_name.to_owned()));
+        }
+
+        let text_file = _load_text_file_with_limit(&path, file_name.to_owned())
+            .map_err(|e| MmlLineError::CannotLoadInclude(e.to_string()))?;
+
+        let root_site = match &self.map.files[parent].root_site {
+            Some(s) => s.clone(),
+            None => site.clone(),
+        };
+
+        let file = self.map.files.len();
+        self.map.files.push(IncludedFile {
+            file_name: file_name.to_owned(),
+            include_site: Some((parent, site.line_number)),
+            root_site: Some(root_site),
+        });
+
+        self.stack.push(canonical);
+        self.expand_file(file, &text_file.contents, Some(&path));
+        self.stack.pop();
+
+        Ok(())
+    }
+}
+
+/// Expands the `#include` directives in a MML song.
+///
+/// The `#include` lines are kept in the expanded text (and ignored by the line splitter)
+/// so errors in the directive can be reported.
+/// Include paths are relative to the directory of the file containing the directive.
+///
+/// Returns the MML file's contents and an empty `IncludeMap` if the file has no `#include`
+/// directives.
+pub(crate) fn expand_includes(
+    mml_file: &TextFile,
+) -> (Cow<str>, IncludeMap, Vec<ErrorWithPos<MmlLineError>>) {
+    match expand(mml_file) {
+        Some(e) => (
+            Cow::Owned(e.out),
+            IncludeMap(Some(Box::new(e.map))),
+            e.errors,
+        ),
+        None => (
+            Cow::Borrowed(&mml_file.contents),
+            IncludeMap::default(),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Returns the paths of the files included by a MML song (including nested includes).
+///
+/// Paths that cannot be loaded are also returned.
+pub fn included_files(mml_file: &TextFile) -> Vec<PathBuf> {
+    match expand(mml_file) {
+        Some(e) => e.include_paths,
+        None => Vec::new(),
+    }
+}
+
+/// Returns `None` if the file has no `#include` directives
+fn expand(mml_file: &TextFile) -> Option<Expander> {
+    let contents = &mml_file.contents;
+
+    if !split_lines(contents).any(|l| is_include_directive(strip_comment(l.text))) {
+        return None;
+    }
+
+    let mut expander = Expander {
+        out: String::with_capacity(contents.len()),
+        n_lines: 0,
+        map: IncludeMapData {
+            files: vec![IncludedFile {
+                file_name: mml_file.file_name.clone(),
+                include_site: None,
+                root_site: None,
+            }],
+            segments: Vec::new(),
+        },
+        stack: Vec::new(),
+        include_paths: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let path = mml_file.path.as_deref();
+    if let Some(c) = path.and_then(|p| std::fs::canonicalize(p).ok()) {
+        expander.stack.push(c);
+    }
+
+    expander.expand_file(0, contents, path);
+
+    Some(expander)
+}
@@ -5,6 +5,8 @@
 // SPDX-License-Identifier: MIT
 
 use super::identifier::IdentifierStr;
+use super::include::is_include_directive;
+use super::macros::{is_define_directive, MacroCalls};
 use super::tokenizer::MmlTokens;
 use super::{Section, FIRST_MUSIC_CHANNEL};
 use super::{COMMENT_CHAR, MUSIC_CHANNEL_RANGE, SECTION_PREFIX};
@@ -12,6 +14,7 @@ use super::{COMMENT_CHAR, MUSIC_CHANNEL_RANGE, SECTION_PREFIX};
 use crate::driver_constants::{MAX_SUBROUTINES, N_MUSIC_CHANNELS};
 use crate::errors::{ErrorWithPos, MmlLineError};
 use crate::file_pos::{blank_file_range, split_lines, FilePos, Line, MAX_MML_TEXT_LENGTH};
+use crate::time::TickCounter;
 
 use std::collections::HashMap;
 
@@ -126,9 +129,23 @@ fn validate_music_channels<'a>(
     }
 }
 
-pub(super) fn split_mml_song_lines(
-    mml_text: &str,
-) -> Result<MmlLines, Vec<ErrorWithPos<MmlLineError>>> {
+/// Splits a section line into the section name and the optional `@ticks` tick count assertion
+fn parse_section_line(s: &str) -> Result<(&str, Option<TickCounter>), MmlLineError> {
+    let s = s.trim();
+
+    match s.rsplit_once(char::is_whitespace) {
+        Some((name, ticks)) if ticks.starts_with('@') => match ticks[1..].parse() {
+            Ok(t) => Ok((name.trim_end(), Some(TickCounter::new(t)))),
+            Err(_) => Err(MmlLineError::InvalidSectionTickCount(ticks.to_owned())),
+        },
+        _ => Ok((s, None)),
+    }
+}
+
+pub(super) fn split_mml_song_lines<'a>(
+    mml_text: &'a str,
+    macro_calls: &'a MacroCalls,
+) -> Result<MmlLines<'a>, Vec<ErrorWithPos<MmlLineError>>> {
     let mut errors = Vec::new();
 
     if mml_text.len() > MAX_MML_TEXT_LENGTH {
@@ -154,11 +171,18 @@ pub(super) fn split_mml_song_lines(
 
         if let Some(section_name) = entire_line.text.strip_prefix(SECTION_PREFIX) {
             if section_name.starts_with(char::is_whitespace) {
-                let section_name = section_name.trim();
+                let (section_name, expected_tick_count) = match parse_section_line(section_name) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        errors.push(ErrorWithPos(entire_line.range(), e));
+                        (section_name.trim(), None)
+                    }
+                };
                 if !section_name.is_empty() {
                     sections.push(Section {
                         name: section_name.to_owned(),
                         line_number: entire_line.position.line_number,
+                        expected_tick_count,
                     });
                 }
             }
@@ -175,6 +199,10 @@ pub(super) fn split_mml_song_lines(
         };
 
         match line.text.chars().next() {
+            // Expanded by `expand_includes()`
+            Some('#') if is_include_directive(line.text.trim_end()) => (),
+            // Parsed by `expand_macros()`
+            Some('#') if is_define_directive(line.text.trim_end()) => (),
             Some('#') => headers.push(line),
             Some('@') => {
                 // instruments
@@ -191,6 +219,7 @@ pub(super) fn split_mml_song_lines(
                             line,
                             entire_line.index_range(),
                             &mut line_splitter,
+                            macro_calls,
                         ),
                         None => {
                             subroutine_name_map.insert(id, subroutines.len());
@@ -200,6 +229,7 @@ pub(super) fn split_mml_song_lines(
                                     line,
                                     entire_line.index_range(),
                                     &mut line_splitter,
+                                    macro_calls,
                                 ),
                             ));
                         }
@@ -222,12 +252,14 @@ pub(super) fn split_mml_song_lines(
                                 line.clone(),
                                 entire_line.index_range(),
                                 &mut line_splitter,
+                                macro_calls,
                             );
                         } else {
                             let tokens = MmlTokens::new_with_line(
                                 line,
                                 entire_line.index_range(),
                                 &mut line_splitter,
+                                macro_calls,
                             );
 
                             // Each channel will only use the line once
@@ -319,6 +351,7 @@ pub(super) fn split_mml_sfx_subroutines_header_lines(
                             line,
                             entire_line.index_range(),
                             &mut line_splitter,
+                            MacroCalls::none(),
                         ),
                         None => {
                             subroutine_name_map.insert(id, subroutines.len());
@@ -328,6 +361,7 @@ pub(super) fn split_mml_sfx_subroutines_header_lines(
                                     line,
                                     entire_line.index_range(),
                                     &mut line_splitter,
+                                    MacroCalls::none(),
                                 ),
                             ));
                         }
@@ -414,7 +448,12 @@ pub(super) fn split_mml_sound_effect_lines(
                 // MML channel
                 let (id, line) = split_idstr_and_line(line);
                 if id == "A" {
-                    mml.parse_line(line, entire_line.index_range(), &mut line_splitter);
+                    mml.parse_line(
+                        line,
+                        entire_line.index_range(),
+                        &mut line_splitter,
+                        MacroCalls::none(),
+                    );
                 } else {
                     errors.push(ErrorWithPos(
                         start_pos.to_range(1),
@@ -0,0 +1,411 @@
+//! MML `#define` text-substitution macros
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use super::identifier::IdentifierStr;
+use super::tokenizer::SLASH_COMMANDS;
+use super::COMMENT_CHAR;
+
+use crate::errors::{ChannelError, ErrorWithPos, MmlLineError};
+use crate::file_pos::{split_lines, FilePosRange, Line, MAX_MML_TEXT_LENGTH};
+
+use std::collections::HashMap;
+
+pub const DEFINE_DIRECTIVE: &str = "#define";
+
+/// Maximum number of nested macro expansions
+pub const MAX_MACRO_DEPTH: usize = 10;
+
+/// Returns true if the line (without comments) is a `#define` directive
+pub(crate) fn is_define_directive(line: &str) -> bool {
+    match line.strip_prefix(DEFINE_DIRECTIVE) {
+        Some(s) => s.is_empty() || s.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+struct MacroDefinition<'a> {
+    parameters: Vec<&'a str>,
+    body: &'a str,
+    /// Position of the `#define` line
+    pos: FilePosRange,
+}
+
+/// A macro call in the MML text
+pub(crate) struct MacroCall {
+    /// Number of bytes in the macro call (including the `\` and arguments)
+    pub len: usize,
+    /// The macro with every parameter and macro call substituted
+    pub expansion: Result<String, ChannelError>,
+    /// Name of the called macro
+    pub name: String,
+    /// Position of the called macro's `#define` line
+    pub definition: FilePosRange,
+}
+
+impl MacroCall {
+    /// Adds the macro definition to an error in the macro expansion
+    pub fn in_macro_error(&self, error: ChannelError) -> ChannelError {
+        ChannelError::InMacro {
+            error: Box::new(error),
+            name: self.name.clone(),
+            definition: self.definition.clone(),
+        }
+    }
+}
+
+/// The macro calls in a MML file, sorted by the `char_index` of the call's `\`
+pub(crate) struct MacroCalls(Vec<(u32, MacroCall)>);
+
+static NO_MACRO_CALLS: MacroCalls = MacroCalls(Vec::new());
+
+impl MacroCalls {
+    pub fn none() -> &'static Self {
+        &NO_MACRO_CALLS
+    }
+
+    pub fn get(&self, char_index: u32) -> Option<&MacroCall> {
+        match self.0.binary_search_by_key(&char_index, |(i, _)| *i) {
+            Ok(i) => Some(&self.0[i].1),
+            Err(_) => None,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns the macro name after the `\`
+fn call_name(s: &str) -> &str {
+    let s = s.strip_prefix('\\').unwrap_or("");
+    let len = s.find(|c| !is_word_char(c)).unwrap_or(s.len());
+    &s[..len]
+}
+
+/// Replaces every parameter in `body` with its argument.
+///
+/// A parameter is only substituted if it is an entire word
+/// (ie, `x` in `c%x` is a parameter, `x` in `c4x` is not).
+fn substitute(body: &str, parameters: &[&str], args: &[String]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut word_start = None;
+
+    for (i, c) in body
+        .char_indices()
+        .chain(std::iter::once((body.len(), ' ')))
+    {
+        if is_word_char(c) {
+            word_start.get_or_insert(i);
+            continue;
+        }
+
+        if let Some(s) = word_start.take() {
+            let word = &body[s..i];
+            match parameters.iter().position(|&p| p == word) {
+                Some(p) => out.push_str(&args[p]),
+                None => out.push_str(word),
+            }
+        }
+        if i < body.len() {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Splits the arguments of a macro call.
+///
+/// `s` starts at the `(`.
+/// Returns the arguments and the length of the argument list (in bytes).
+fn split_arguments(s: &str) -> Option<(Vec<&str>, usize)> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut arg_start = 1;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ',' if depth == 1 => {
+                args.push(&s[arg_start..i]);
+                arg_start = i + 1;
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    args.push(&s[arg_start..i]);
+                    return Some((args, i + 1));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+struct Expander<'a, 'm> {
+    macros: &'m HashMap<&'a str, MacroDefinition<'a>>,
+    /// The macros that are currently being expanded
+    stack: Vec<&'a str>,
+}
+
+impl<'a> Expander<'a, '_> {
+    /// Expands the macro call at the start of `s`.
+    ///
+    /// Returns the length of the macro call and the expansion.
+    /// Assumes `s` starts with a `\` and a macro name.
+    fn expand_call(&mut self, s: &str) -> (usize, Result<String, ChannelError>) {
+        let macros = self.macros;
+        let (&name, definition) = macros.get_key_value(call_name(s)).unwrap();
+
+        let name_len = name.len() + 1;
+
+        if definition.parameters.is_empty() {
+            return (name_len, self.expand_body(name, definition, &[]));
+        }
+
+        let after_name = &s[name_len..];
+        if !after_name.starts_with('(') {
+            return (
+                name_len,
+                Err(ChannelError::MissingMacroArguments(name.to_owned())),
+            );
+        }
+
+        let (args, args_len) = match split_arguments(after_name) {
+            Some(a) => a,
+            None => {
+                return (
+                    s.len(),
+                    Err(ChannelError::MissingMacroArgumentsEnd(name.to_owned())),
+                )
+            }
+        };
+        let len = name_len + args_len;
+
+        if args.len() != definition.parameters.len() {
+            return (
+                len,
+                Err(ChannelError::InvalidNumberOfMacroArguments(
+                    definition.parameters.len(),
+                    args.len(),
+                )),
+            );
+        }
+
+        // Arguments are trimmed and expanded before they are substituted
+        let args: Result<Vec<String>, ChannelError> =
+            args.iter().map(|a| self.expand_text(a.trim())).collect();
+
+        match args {
+            Ok(args) => (len, self.expand_body(name, definition, &args)),
+            Err(e) => (len, Err(e)),
+        }
+    }
+
+    fn expand_body(
+        &mut self,
+        name: &'a str,
+        definition: &MacroDefinition,
+        args: &[String],
+    ) -> Result<String, ChannelError> {
+        if self.stack.contains(&name) {
+            return Err(ChannelError::RecursiveMacro(name.to_owned()));
+        }
+        if self.stack.len() >= MAX_MACRO_DEPTH {
+            return Err(ChannelError::MacroTooDeep(name.to_owned()));
+        }
+
+        let body = substitute(definition.body, &definition.parameters, args);
+
+        self.stack.push(name);
+        let out = self.expand_text(&body);
+        self.stack.pop();
+
+        out
+    }
+
+    /// Expands every macro call in `text`
+    fn expand_text(&mut self, text: &str) -> Result<String, ChannelError> {
+        let mut out = String::with_capacity(text.len());
+        let mut remaining = text;
+
+        while let Some(i) = remaining.find('\\') {
+            let (before, s) = remaining.split_at(i);
+            out.push_str(before);
+
+            let name = call_name(s);
+            if self.macros.contains_key(name) {
+                let (len, expansion) = self.expand_call(s);
+                out.push_str(&expansion?);
+                remaining = &s[len..];
+
+                // Prevents exponential growth
+                if out.len() > MAX_MML_TEXT_LENGTH {
+                    return Err(ChannelError::MacroExpansionTooLarge(name.to_owned()));
+                }
+            } else {
+                out.push('\\');
+                remaining = &s[1..];
+            }
+        }
+        out.push_str(remaining);
+
+        Ok(out)
+    }
+}
+
+fn parse_parameters<'a>(
+    line: &Line,
+    params: &'a str,
+) -> Result<Vec<&'a str>, ErrorWithPos<MmlLineError>> {
+    if params.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut out: Vec<&str> = Vec::new();
+
+    for p in params.split(',') {
+        let p = p.trim();
+
+        if let Err(e) = IdentifierStr::try_from_name(p) {
+            return Err(ErrorWithPos(
+                line.range(),
+                MmlLineError::InvalidIdentifier(e),
+            ));
+        }
+        if out.contains(&p) {
+            return Err(ErrorWithPos(
+                line.range(),
+                MmlLineError::DuplicateMacroParameter(p.to_owned()),
+            ));
+        }
+        out.push(p);
+    }
+
+    Ok(out)
+}
+
+fn parse_define(line: Line) -> Result<(&str, MacroDefinition), ErrorWithPos<MmlLineError>> {
+    let text = line.text[DEFINE_DIRECTIVE.len()..].trim();
+
+    let name_len = text
+        .find(|c: char| c.is_whitespace() || c == '(')
+        .unwrap_or(text.len());
+    let (name, after_name) = text.split_at(name_len);
+
+    if name.is_empty() {
+        return Err(ErrorWithPos(line.range(), MmlLineError::NoMacroName));
+    }
+    if let Err(e) = IdentifierStr::try_from_name(name) {
+        return Err(ErrorWithPos(
+            line.range(),
+            MmlLineError::InvalidIdentifier(e),
+        ));
+    }
+    if SLASH_COMMANDS.contains(&name) {
+        return Err(ErrorWithPos(
+            line.range(),
+            MmlLineError::MacroNameIsCommand(name.to_owned()),
+        ));
+    }
+
+    let (parameters, body) = match after_name.strip_prefix('(') {
+        Some(s) => match s.split_once(')') {
+            Some((params, body)) => (parse_parameters(&line, params)?, body),
+            None => {
+                return Err(ErrorWithPos(
+                    line.range(),
+                    MmlLineError::MissingMacroParametersEnd,
+                ))
+            }
+        },
+        None => (Vec::new(), after_name),
+    };
+
+    Ok((
+        name,
+        MacroDefinition {
+            parameters,
+            body: body.trim(),
+            pos: line.range(),
+        },
+    ))
+}
+
+/// Parses the `#define` directives and expands every macro call in the MML text.
+///
+/// The expansions are stored in a `MacroCalls` as the tokens borrow the text they were
+/// tokenized from.
+/// Macros can be used before they are defined.
+pub(crate) fn expand_macros(mml_text: &str) -> (MacroCalls, Vec<ErrorWithPos<MmlLineError>>) {
+    let mut errors = Vec::new();
+    let mut macros = HashMap::new();
+
+    for line in split_lines(mml_text) {
+        let line = line.trim_comments(COMMENT_CHAR);
+        if !is_define_directive(line.text) {
+            continue;
+        }
+
+        match parse_define(line.clone()) {
+            Ok((name, definition)) => {
+                if macros.insert(name, definition).is_some() {
+                    errors.push(ErrorWithPos(
+                        line.range(),
+                        MmlLineError::DuplicateMacro(name.to_owned()),
+                    ));
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let mut calls = Vec::new();
+
+    if !macros.is_empty() {
+        let mut expander = Expander {
+            macros: &macros,
+            stack: Vec::new(),
+        };
+
+        for line in split_lines(mml_text) {
+            let line = line.trim_comments(COMMENT_CHAR);
+
+            // Skip headers and instruments
+            if line.text.starts_with(['#', '@']) {
+                continue;
+            }
+
+            let mut i = 0;
+            while let Some(p) = line.text[i..].find('\\') {
+                let s = &line.text[i + p..];
+
+                if let Some((&name, definition)) = macros.get_key_value(call_name(s)) {
+                    let (len, expansion) = expander.expand_call(s);
+
+                    let char_index = line.position.char_index + u32::try_from(i + p).unwrap();
+                    calls.push((
+                        char_index,
+                        MacroCall {
+                            len,
+                            expansion,
+                            name: name.to_owned(),
+                            definition: definition.pos.clone(),
+                        },
+                    ));
+
+                    i += p + len;
+                } else {
+                    i += p + 1;
+                }
+            }
+        }
+    }
+
+    (MacroCalls(calls), errors)
+}
@@ -6,7 +6,7 @@
 
 use std::collections::HashMap;
 
-use crate::driver_constants::SFX_TICK_CLOCK;
+use crate::driver_constants::{MAX_SONG_DATA_SIZE, SFX_TICK_CLOCK};
 use crate::echo::{
     parse_fir_filter_string, EchoBuffer, EchoEdl, EchoFeedback, EchoLength, EchoVolume,
     IDENTITY_FILTER,
@@ -14,7 +14,11 @@ use crate::echo::{
 use crate::errors::{ErrorWithPos, MmlLineError, ValueError};
 use crate::file_pos::{blank_file_range, Line};
 use crate::invert_flags::{parse_invert_flag_arguments, InvertFlags};
-use crate::time::{Bpm, TickClock, ZenLen, DEFAULT_BPM, DEFAULT_ZENLEN};
+use crate::pitch_table::{parse_concert_pitch, parse_tuning_table, TuningTable};
+use crate::time::{
+    Bpm, MinRestTicks, TempoScale, TickClock, ZenLen, DEFAULT_BPM, DEFAULT_TEMPO_SCALE,
+    DEFAULT_ZENLEN,
+};
 use crate::value_newtypes::{parse_i8wh, I8WithByteHexValueNewType};
 use crate::{spc_file_export, FilePosRange};
 
@@ -32,14 +36,27 @@ pub struct MetaData {
 
     pub tick_clock: TickClock,
 
+    /// Percentage applied to the `#Tempo` header and all `t` commands
+    pub tempo_scale: TempoScale,
+
     pub zenlen: ZenLen,
 
+    /// Rests after a note shorter than this value are lengthened
+    pub min_rest_ticks: Option<MinRestTicks>,
+
     /// SPC export song length in seconds before fading out
     /// (override calculated song duration)
     pub spc_song_length: Option<u32>,
 
     /// SPC export fadeout length in milliseconds
     pub spc_fadeout_millis: Option<u32>,
+
+    /// Maximum bytecode size of each song channel
+    pub max_channel_bytes: Option<usize>,
+
+    /// Frequency of each MIDI note, set by `#Tuning` or `#TuningTable`
+    /// (replaces the pitch table when playing notes)
+    pub tuning_table: Option<TuningTable>,
 }
 
 //
@@ -107,9 +124,13 @@ impl MetaData {
                 invert: InvertFlags::default(),
             },
             tick_clock: DEFAULT_BPM.to_tick_clock().unwrap(),
+            tempo_scale: DEFAULT_TEMPO_SCALE,
             zenlen: DEFAULT_ZENLEN,
+            min_rest_ticks: None,
             spc_song_length: None,
             spc_fadeout_millis: None,
+            max_channel_bytes: None,
+            tuning_table: None,
         }
     }
 
@@ -124,20 +145,26 @@ impl MetaData {
 struct HeaderState {
     metadata: MetaData,
     tempo_set: bool,
+    bpm: Option<Bpm>,
+    tempo_scale_pos: FilePosRange,
     disable_fir_filter_limit: bool,
     fir_pos: FilePosRange,
     max_edl_set: bool,
     edl_pos: FilePosRange,
+    tuning_set: bool,
 }
 
 impl HeaderState {
     fn new() -> Self {
         Self {
             tempo_set: false,
+            bpm: None,
+            tempo_scale_pos: blank_file_range(),
             disable_fir_filter_limit: false,
             fir_pos: blank_file_range(),
             max_edl_set: false,
             edl_pos: blank_file_range(),
+            tuning_set: false,
             metadata: MetaData::new(),
         }
     }
@@ -165,6 +192,7 @@ impl HeaderState {
             "#License" => self.metadata.license = to_option_string()?,
 
             "#ZenLen" => self.metadata.zenlen = parse_u32(value)?.try_into()?,
+            "#MinRestTicks" => self.metadata.min_rest_ticks = Some(parse_u32(value)?.try_into()?),
 
             "#MaxEchoLength" => {
                 let echo_length = EchoLength::try_from(parse_u32(value)?)?;
@@ -210,6 +238,11 @@ impl HeaderState {
 
                 let bpm = Bpm::try_from(parse_u32(value)?)?;
                 self.metadata.tick_clock = bpm.to_tick_clock()?;
+                self.bpm = Some(bpm);
+            }
+            "#TempoScale" => {
+                self.metadata.tempo_scale = parse_u32(value)?.try_into()?;
+                self.tempo_scale_pos = pos.clone();
             }
             "#Timer" => {
                 if self.tempo_set {
@@ -237,6 +270,32 @@ impl HeaderState {
                 Err(_) => return Err(MmlLineError::InvalidSpcSongLength),
             },
 
+            "#MaxChannelBytes" => {
+                let v = parse_u32(value)?;
+                match usize::try_from(v) {
+                    Ok(b @ 1..=MAX_SONG_DATA_SIZE) => self.metadata.max_channel_bytes = Some(b),
+                    _ => return Err(ValueError::MaxChannelBytesOutOfRange(v).into()),
+                }
+            }
+
+            "#Tuning" => {
+                if self.tuning_set {
+                    return Err(MmlLineError::CannotSetTuning);
+                }
+                self.tuning_set = true;
+
+                let concert_a_hz = parse_concert_pitch(value)?;
+                self.metadata.tuning_table = Some(TuningTable::equal_temperament(concert_a_hz));
+            }
+            "#TuningTable" => {
+                if self.tuning_set {
+                    return Err(MmlLineError::CannotSetTuningTable);
+                }
+                self.tuning_set = true;
+
+                self.metadata.tuning_table = Some(parse_tuning_table(value)?);
+            }
+
             h => return Err(MmlLineError::UnknownHeader(h.to_owned())),
         }
 
@@ -274,6 +333,16 @@ pub fn parse_headers(lines: Vec<Line>) -> Result<MetaData, Vec<ErrorWithPos<MmlL
         }
     }
 
+    // `#Timer` sets the tick clock directly and is not scaled
+    let timer_set = header_state.tempo_set && header_state.bpm.is_none();
+    if header_state.metadata.tempo_scale != DEFAULT_TEMPO_SCALE && !timer_set {
+        let bpm = header_state.bpm.unwrap_or(DEFAULT_BPM);
+        match bpm.to_scaled_tick_clock(header_state.metadata.tempo_scale) {
+            Ok(tc) => header_state.metadata.tick_clock = tc,
+            Err(e) => errors.push(ErrorWithPos(header_state.tempo_scale_pos.clone(), e.into())),
+        }
+    }
+
     let eb = &mut header_state.metadata.echo_buffer;
 
     if header_state.max_edl_set {
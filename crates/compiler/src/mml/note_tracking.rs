@@ -9,7 +9,7 @@
 const _: () = assert!(cfg!(feature = "mml_tracking"));
 
 use super::command_parser::State;
-use super::ChannelId;
+use super::{ChannelId, IncludeMap};
 
 use crate::file_pos::{FilePos, LineIndexRange};
 use crate::time::TickCounterWithLoopFlag;
@@ -81,6 +81,20 @@ impl CursorTracker {
         }
     }
 
+    /// Maps the character indexes to the root MML file and removes the lines in included files
+    pub(crate) fn map_to_root_file(&mut self, include_map: &IncludeMap) {
+        self.lines
+            .retain(|l| include_map.is_in_root_file(*l.char_indexes.start()));
+
+        for l in &mut self.lines {
+            l.char_indexes = include_map.to_root_char_index(*l.char_indexes.start())
+                ..=include_map.to_root_char_index(*l.char_indexes.end());
+        }
+        for c in &mut self.data {
+            c.char_index = include_map.to_root_char_index(c.char_index);
+        }
+    }
+
     pub fn find(&self, char_index: u32) -> Option<(ChannelId, &Cursor)> {
         let line = self
             .lines
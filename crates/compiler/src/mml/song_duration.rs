@@ -18,12 +18,6 @@ pub fn calc_song_duration(
     channels: &[Option<Channel>; N_MUSIC_CHANNELS],
     subroutines: &[Subroutine],
 ) -> Option<Duration> {
-    let set_song_tick_in_subroutine = subroutines.iter().any(|s| s.changes_song_tempo);
-
-    if set_song_tick_in_subroutine {
-        return None;
-    }
-
     let total_ticks: u32 = channels
         .iter()
         .filter_map(|c| c.as_ref())
@@ -31,6 +25,31 @@ pub fn calc_song_duration(
         .max()
         .unwrap_or(0);
 
+    calc_duration_at_tick(
+        metadata,
+        channels,
+        subroutines,
+        TickCounter::new(total_ticks),
+    )
+}
+
+/// Calculates the time taken to play the first `ticks` ticks of a song.
+///
+/// Returns `None` if a subroutine changes the song tempo.
+pub(crate) fn calc_duration_at_tick(
+    metadata: &MetaData,
+    channels: &[Option<Channel>; N_MUSIC_CHANNELS],
+    subroutines: &[Subroutine],
+    ticks: TickCounter,
+) -> Option<Duration> {
+    let set_song_tick_in_subroutine = subroutines.iter().any(|s| s.changes_song_tempo);
+
+    if set_song_tick_in_subroutine {
+        return None;
+    }
+
+    let total_ticks = ticks.value();
+
     let mut tempo_changes: Vec<(TickCounter, TickClock)> = channels
         .iter()
         .filter_map(|c| c.as_ref())
@@ -45,6 +64,9 @@ pub fn calc_song_duration(
 
     for (ticks, clock) in tempo_changes {
         let ticks = ticks.value();
+        if ticks > total_ticks {
+            break;
+        }
 
         let section_ticks = ticks - prev_ticks;
         out += u64::from(section_ticks) * u64::from(prev_clock.as_u8());
@@ -6,21 +6,112 @@
 
 use crate::driver_constants::N_MUSIC_CHANNELS;
 use crate::songs::{Channel, SongData};
+use crate::time::TickCounter;
+
+use std::collections::HashMap;
 
 const MIN_NAME_COLUMN_WIDTH: usize = 15;
 const MAX_NAME_COLUMN_WIDTH: usize = 100;
 
-pub struct MmlTickCountTable<'a>(pub &'a SongData);
+pub struct MmlTickCountTable<'a> {
+    song: &'a SongData,
+    section_ranges: SectionTickRanges,
+}
+
+/// A half-open `start..end` tick range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickRange {
+    pub start: TickCounter,
+    pub end: TickCounter,
+}
+
+impl TickRange {
+    pub fn contains(&self, tick: TickCounter) -> bool {
+        tick >= self.start && tick < self.end
+    }
+}
+
+/// The name and tick range of each section, in song order.
+///
+/// A section starts when every channel has reached the section
+/// and ends at the start of the next section (or the end of the song).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionTickRanges(Vec<(String, TickRange)>);
+
+impl SectionTickRanges {
+    pub fn new(song: &SongData) -> Self {
+        let starts: Vec<TickCounter> = build_section_tick_count_table(song)
+            .0
+            .iter()
+            .map(SectionTickCounts::max_ticks)
+            .collect();
+        let song_end = song.max_tick_count();
+
+        Self(
+            song.sections()
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    let start = starts[i];
+                    let end = starts.get(i + 1).copied().unwrap_or(song_end);
+                    (s.name().to_owned(), TickRange { start, end })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn to_hash_map(&self) -> HashMap<String, TickRange> {
+        self.0.iter().cloned().collect()
+    }
+
+    /// Returns the index of the section playing at `tick`.
+    ///
+    /// Returns `None` if the song has no sections or `tick` is past the end of the song.
+    pub fn position_at_tick(&self, tick: TickCounter) -> Option<usize> {
+        self.0.iter().position(|(_, r)| r.contains(tick))
+    }
+
+    /// Returns the name of the section playing at `tick`.
+    ///
+    /// Returns `None` if the song has no sections or `tick` is past the end of the song.
+    pub fn section_at_tick(&self, tick: TickCounter) -> Option<&str> {
+        self.position_at_tick(tick).map(|i| self.0[i].0.as_str())
+    }
+}
+
+impl<'a> MmlTickCountTable<'a> {
+    pub fn new(song: &'a SongData) -> Self {
+        Self {
+            song,
+            section_ranges: SectionTickRanges::new(song),
+        }
+    }
+
+    pub fn section_ranges(&self) -> HashMap<String, TickRange> {
+        self.section_ranges.to_hash_map()
+    }
+
+    /// Returns the name of the section playing at `tick`.
+    ///
+    /// Returns `None` if the song has no sections or `tick` is past the end of the song.
+    pub fn section_at_tick(&self, tick: TickCounter) -> Option<&str> {
+        self.section_ranges.section_at_tick(tick)
+    }
+
+    pub fn into_section_tick_ranges(self) -> SectionTickRanges {
+        self.section_ranges
+    }
+}
 
 impl std::fmt::Display for MmlTickCountTable<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let channels: Vec<&Channel> = self
-            .0
+            .song
             .channels()
             .iter()
             .filter_map(|c| c.as_ref())
             .collect();
-        let sections = self.0.sections();
+        let sections = self.song.sections();
 
         assert!(channels.len() <= N_MUSIC_CHANNELS);
 
@@ -84,3 +175,51 @@ impl std::fmt::Display for MmlTickCountTable<'_> {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionTickCounts {
+    pub name: String,
+    pub line_number: u32,
+
+    /// Tick offset of the section start, relative to the start of each channel.
+    /// `None` if the channel does not exist.
+    pub channels: [Option<TickCounter>; N_MUSIC_CHANNELS],
+}
+
+impl SectionTickCounts {
+    /// The largest tick offset of all channels
+    pub fn max_ticks(&self) -> TickCounter {
+        self.channels
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(TickCounter::new(0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionTickCountTable(pub Vec<SectionTickCounts>);
+
+pub fn build_section_tick_count_table(song: &SongData) -> SectionTickCountTable {
+    SectionTickCountTable(
+        song.sections()
+            .iter()
+            .enumerate()
+            .map(|(i, s)| SectionTickCounts {
+                name: s.name().to_owned(),
+                line_number: s.line_number(),
+                channels: std::array::from_fn(|c| {
+                    song.channels()[c].as_ref().map(|c| match i.checked_sub(1) {
+                        // `section_tick_counters` skips the first section
+                        Some(prev) => match c.section_tick_counters.get(prev) {
+                            Some(s) => s.ticks,
+                            None => c.tick_counter,
+                        },
+                        None => TickCounter::new(0),
+                    })
+                }),
+            })
+            .collect(),
+    )
+}
@@ -6,13 +6,16 @@
 
 use std::ops::Range;
 
+use super::macros::{MacroCall, MacroCalls};
 use super::{IdentifierStr, COMMENT_CHAR};
 
 use crate::bytecode_assembler;
 use crate::channel_bc_generator::SubroutineCallType;
 use crate::envelope::GainMode;
 use crate::errors::{ChannelError, ValueError};
-use crate::file_pos::{blank_line_splitter, FilePos, Line, LineIndexRange, LineSplitter};
+use crate::file_pos::{
+    blank_line_splitter, FilePos, FilePosRange, Line, LineIndexRange, LineSplitter,
+};
 use crate::invert_flags::{parse_mml_invert_flags, InvertFlags};
 use crate::notes::{parse_pitch_char, MmlPitch};
 
@@ -70,6 +73,7 @@ pub enum Token<'a> {
     RelativeTranspose,
     StartBrokenChord,
     EndBrokenChord,
+    Arpeggio,
     StartPortamento,
     EndPortamento,
     ManualVibrato,
@@ -120,6 +124,10 @@ pub struct TokenWithPosition<'a> {
     pub token: Token<'a>,
     // ::TODO optimise::
     pub end: FilePos,
+    /// The macro call that expanded to this token (if any).
+    ///
+    /// Every token in a macro expansion uses the position of the macro call.
+    pub(crate) macro_call: Option<&'a MacroCall>,
 }
 
 struct Scanner<'a> {
@@ -219,6 +227,15 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    // Assumes `n_bytes` is on a char boundary
+    fn advance_bytes(&mut self, n_bytes: usize) {
+        let (skipped, s) = self.to_process.split_at(n_bytes);
+
+        self.pos.line_char += u32::try_from(skipped.chars().count()).unwrap();
+        self.pos.char_index += u32::try_from(n_bytes).unwrap();
+        self.to_process = s;
+    }
+
     fn advance_one_ascii(&mut self) {
         self.pos.line_char += 1;
         self.pos.char_index += 1;
@@ -285,6 +302,10 @@ fn parse_unknown_chars<'a>(scanner: &mut Scanner<'a>) -> Token<'a> {
     Token::Error(ChannelError::UnknownCharacters(n_chars + 1))
 }
 
+/// The names of the `\` commands.
+/// This list must match `next_token()`.
+pub(super) const SLASH_COMMANDS: &[&str] = &["asm", "evol", "efb", "fir", "ftap", "edl", "ei"];
+
 fn next_token<'a>(scanner: &mut Scanner<'a>) -> Option<TokenWithPosition<'a>> {
     macro_rules! one_ascii_token {
         ($t:expr) => {{
@@ -425,7 +446,10 @@ fn next_token<'a>(scanner: &mut Scanner<'a>) -> Option<TokenWithPosition<'a>> {
         b'Q' => one_ascii_token!(Token::Quantize),
         b'q' => one_ascii_token!(Token::EarlyRelease),
         b'~' => one_ascii_token!(Token::ManualVibrato),
-        b'A' => one_ascii_token!(Token::SetAdsr),
+        b'A' => match scanner.second_byte() {
+            Some(b'R') => two_ascii_token!(Token::Arpeggio),
+            _ => one_ascii_token!(Token::SetAdsr),
+        },
         b'E' => one_ascii_token!(Token::Echo),
         b't' => one_ascii_token!(Token::SetSongTempo),
         b'T' => one_ascii_token!(Token::SetSongTickClock),
@@ -565,6 +589,7 @@ fn next_token<'a>(scanner: &mut Scanner<'a>) -> Option<TokenWithPosition<'a>> {
         pos,
         token,
         end: scanner.pos(),
+        macro_call: None,
     })
 }
 
@@ -580,6 +605,7 @@ fn parse_bytecode_asm<'a>(
             pos: scanner.pos(),
             token: Token::Error(ChannelError::NoBraceAfterAsm),
             end: scanner.pos(),
+            macro_call: None,
         });
 
         return;
@@ -590,6 +616,7 @@ fn parse_bytecode_asm<'a>(
         pos: scanner.pos(),
         token: Token::StartBytecodeAsm,
         end: scanner.pos(),
+        macro_call: None,
     });
 
     scanner.skip_whitespace();
@@ -607,6 +634,7 @@ fn parse_bytecode_asm<'a>(
                         pos: scanner.pos(),
                         token: Token::NewLine(line_range),
                         end: scanner.pos(),
+                        macro_call: None,
                     });
                     *scanner = Scanner::new(l.text, l.position);
                 }
@@ -615,6 +643,7 @@ fn parse_bytecode_asm<'a>(
                         pos: scanner.pos(),
                         token: Token::Error(ChannelError::MissingEndAsm),
                         end: scanner.pos(),
+                        macro_call: None,
                     });
                     return;
                 }
@@ -627,6 +656,7 @@ fn parse_bytecode_asm<'a>(
                     pos,
                     token: Token::EndBytecodeAsm,
                     end: scanner.pos(),
+                    macro_call: None,
                 });
                 return;
             }
@@ -645,6 +675,7 @@ fn parse_bytecode_asm<'a>(
                         pos,
                         token,
                         end: scanner.pos(),
+                        macro_call: None,
                     });
                 } else {
                     let start = usize::try_from(pos.char_index()).unwrap();
@@ -653,6 +684,7 @@ fn parse_bytecode_asm<'a>(
                         pos,
                         token: Token::BytecodeAsm(start..(start + asm.len())),
                         end: scanner.pos(),
+                        macro_call: None,
                     });
 
                     if scanner.first_byte() == Some(b'|') {
@@ -689,6 +721,80 @@ fn parse_call_subroutine_asm(asm: &str) -> Option<Token> {
     }
 }
 
+/// Tokenizes the text in `scanner`.
+///
+/// If `call_site` is `Some`, the scanner contains a macro expansion and every token will use
+/// the position of the macro call and reference the macro's definition.
+fn tokenize<'a>(
+    tokens: &mut Vec<TokenWithPosition<'a>>,
+    scanner: &mut Scanner<'a>,
+    remaining_lines: &mut LineSplitter<'a>,
+    macro_calls: &'a MacroCalls,
+    call_site: Option<(FilePos, FilePos, &'a MacroCall)>,
+) {
+    loop {
+        scanner.skip_whitespace();
+
+        let pos = scanner.pos();
+
+        // Macro expansions do not contain any macro calls
+        if call_site.is_none() && scanner.first_byte() == Some(b'\\') {
+            if let Some(call) = macro_calls.get(pos.char_index) {
+                scanner.advance_bytes(call.len);
+                let end = scanner.pos();
+
+                match &call.expansion {
+                    Ok(text) => tokenize(
+                        tokens,
+                        &mut Scanner::new(text, pos),
+                        &mut blank_line_splitter(),
+                        macro_calls,
+                        Some((pos, end, call)),
+                    ),
+                    Err(e) => tokens.push(TokenWithPosition {
+                        pos,
+                        token: Token::Error(e.clone()),
+                        end,
+                        macro_call: Some(call),
+                    }),
+                }
+                continue;
+            }
+        }
+
+        match next_token(scanner) {
+            Some(t) => match call_site {
+                None => match t.token {
+                    Token::StartBytecodeAsm => {
+                        parse_bytecode_asm(tokens, scanner, remaining_lines);
+                    }
+                    _ => tokens.push(t),
+                },
+                Some((pos, end, call)) => match t.token {
+                    // Bytecode assembly is not allowed in macros as `Token::BytecodeAsm`
+                    // is a range in the MML text.
+                    Token::StartBytecodeAsm => {
+                        tokens.push(TokenWithPosition {
+                            pos,
+                            token: Token::Error(ChannelError::AsmInMacro),
+                            end,
+                            macro_call: Some(call),
+                        });
+                        break;
+                    }
+                    token => tokens.push(TokenWithPosition {
+                        pos,
+                        token,
+                        end,
+                        macro_call: Some(call),
+                    }),
+                },
+            },
+            None => break,
+        }
+    }
+}
+
 pub struct MmlTokens<'a> {
     tokens: Vec<TokenWithPosition<'a>>,
     end_pos: FilePos,
@@ -715,9 +821,10 @@ impl<'a> MmlTokens<'a> {
         line: Line<'a>,
         entire_line_range: LineIndexRange,
         remaining_lines: &mut LineSplitter<'a>,
+        macro_calls: &'a MacroCalls,
     ) -> Self {
         let mut s = Self::new();
-        s.parse_line(line, entire_line_range, remaining_lines);
+        s.parse_line(line, entire_line_range, remaining_lines, macro_calls);
         s
     }
 
@@ -739,6 +846,7 @@ impl<'a> MmlTokens<'a> {
                 end: text.len().try_into().unwrap(),
             },
             &mut blank_line_splitter(),
+            MacroCalls::none(),
         )
     }
 
@@ -756,6 +864,7 @@ impl<'a> MmlTokens<'a> {
         line: Line<'a>,
         entire_line_range: LineIndexRange,
         remaining_lines: &mut LineSplitter<'a>,
+        macro_calls: &'a MacroCalls,
     ) {
         debug_assert!(entire_line_range.start <= line.position.char_index);
         debug_assert!(entire_line_range.end >= line.position.char_index);
@@ -768,18 +877,18 @@ impl<'a> MmlTokens<'a> {
             pos: self.end_pos,
             token: Token::NewLine(entire_line_range),
             end: self.end_pos,
+            macro_call: None,
         });
 
         let mut scanner = Scanner::new(line.text, line.position);
 
-        while let Some(t) = next_token(&mut scanner) {
-            match t.token {
-                Token::StartBytecodeAsm => {
-                    parse_bytecode_asm(&mut self.tokens, &mut scanner, remaining_lines);
-                }
-                _ => self.tokens.push(t),
-            }
-        }
+        tokenize(
+            &mut self.tokens,
+            &mut scanner,
+            remaining_lines,
+            macro_calls,
+            None,
+        );
 
         self.end_pos = scanner.pos();
     }
@@ -791,13 +900,23 @@ impl<'a> MmlTokens<'a> {
     pub fn first_token(&self) -> Option<&Token<'a>> {
         self.tokens.first().map(|t| &t.token)
     }
+
+    /// Returns the position of the first non-newline token (or the end position if there is none)
+    pub fn first_token_pos(&self) -> FilePos {
+        self.tokens
+            .iter()
+            .find(|t| !matches!(t.token, Token::NewLine(_)))
+            .map_or(self.end_pos, |t| t.pos)
+    }
 }
 
 // ::TODO find better name - not a rust iterator::
 pub(crate) struct PeekableTokenIterator<'a> {
     next: TokenWithPosition<'a>,
     remaining: std::vec::IntoIter<TokenWithPosition<'a>>,
+    prev_pos: FilePos,
     prev_end_pos: FilePos,
+    prev_macro_call: Option<&'a MacroCall>,
     end_pos: FilePos,
 }
 
@@ -810,7 +929,9 @@ impl<'a> PeekableTokenIterator<'a> {
                 .next()
                 .unwrap_or_else(|| (Self::end_token(tokens.end_pos))),
             remaining: iter,
+            prev_pos: blank_pos(),
             prev_end_pos: blank_pos(),
+            prev_macro_call: None,
             end_pos: tokens.end_pos,
         }
     }
@@ -820,6 +941,7 @@ impl<'a> PeekableTokenIterator<'a> {
             pos,
             token: Token::End,
             end: pos,
+            macro_call: None,
         }
     }
 
@@ -835,8 +957,24 @@ impl<'a> PeekableTokenIterator<'a> {
         &self.prev_end_pos
     }
 
+    /// Returns the macro call that expanded the previous or next token,
+    /// if that token starts at `pos`.
+    pub fn macro_call_at(&self, pos: &FilePosRange) -> Option<&'a MacroCall> {
+        [
+            (self.prev_pos, self.prev_macro_call),
+            (self.next.pos, self.next.macro_call),
+        ]
+        .into_iter()
+        .find_map(|(p, call)| match p.char_index == pos.index_start {
+            true => call,
+            false => None,
+        })
+    }
+
     pub fn next(&mut self) {
+        self.prev_pos = self.next.pos;
         self.prev_end_pos = self.next.end;
+        self.prev_macro_call = self.next.macro_call;
 
         self.next = self
             .remaining
@@ -852,7 +990,9 @@ impl<'a> PeekableTokenIterator<'a> {
 
         std::mem::swap(&mut n, &mut self.next);
 
+        self.prev_pos = n.pos;
         self.prev_end_pos = n.end;
+        self.prev_macro_call = n.macro_call;
         (n.pos, n.token)
     }
 }
@@ -0,0 +1,223 @@
+//! MML validator
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use super::command_parser::Parser;
+use super::include::expand_includes;
+use super::instruments::{build_instrument_map, parse_instruments};
+use super::line_splitter::split_mml_song_lines;
+use super::macros::expand_macros;
+use super::metadata::parse_headers;
+use super::tokenizer::{MmlTokens, Token};
+use super::{ChannelId, IdentifierStr, CHANNEL_NAMES};
+
+use crate::channel_bc_generator::Command;
+use crate::data::{self, TextFile, UniqueNamesList};
+use crate::errors::{ErrorWithPos, MmlChannelError, MmlCompileErrors, MmlWarning};
+use crate::subroutines::{FindSubroutineResult, Subroutine, SubroutineStore};
+use crate::time::DEFAULT_ZENLEN;
+
+use std::collections::HashMap;
+
+#[cfg(feature = "mml_tracking")]
+use super::note_tracking::CursorTracker;
+
+#[derive(Debug)]
+pub struct MmlValidationResult {
+    pub errors: MmlCompileErrors,
+    pub warnings: Vec<ErrorWithPos<MmlWarning>>,
+}
+
+impl MmlValidationResult {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.line_errors.is_empty()
+            || !self.errors.subroutine_errors.is_empty()
+            || !self.errors.channel_errors.is_empty()
+    }
+}
+
+/// Subroutine calls are validated by name only.
+///
+/// Returns `Unchecked` for every known subroutine so the parser does not emit a
+/// `CallSubroutine` command (or require the subroutine's tick count).
+struct NameOnlySubroutines<'a> {
+    name_map: &'a HashMap<IdentifierStr<'a>, usize>,
+}
+
+impl SubroutineStore for NameOnlySubroutines<'_> {
+    fn get(&self, _: usize) -> Option<&Subroutine> {
+        None
+    }
+
+    fn find_subroutine<'a, 'b>(&'a self, name: &'b str) -> FindSubroutineResult<'b>
+    where
+        'a: 'b,
+    {
+        match self.name_map.contains_key(&IdentifierStr::from_str(name)) {
+            true => FindSubroutineResult::Unchecked,
+            false => FindSubroutineResult::NotFound,
+        }
+    }
+}
+
+/// Validates a MML song without generating any bytecode.
+///
+/// Only the tokenizer and command parser are run.  Errors detected by the bytecode generator
+/// (ie, notes out of range or missing instruments) are not reported.
+pub fn validate_mml(
+    mml_file: &TextFile,
+    data_instruments: &UniqueNamesList<data::InstrumentOrSample>,
+) -> MmlValidationResult {
+    let (mml_text, include_map, include_errors) = expand_includes(mml_file);
+
+    let mut errors = MmlCompileErrors {
+        song_name: None,
+        file_name: mml_file.file_name.clone(),
+        include_map,
+        line_errors: include_errors,
+        subroutine_errors: Vec::new(),
+        channel_errors: Vec::new(),
+    };
+    let mut warnings = Vec::new();
+
+    if !errors.line_errors.is_empty() {
+        return MmlValidationResult { errors, warnings };
+    }
+
+    let (macro_calls, macro_errors) = expand_macros(&mml_text);
+    errors.line_errors.extend(macro_errors);
+
+    let lines = match split_mml_song_lines(&mml_text, &macro_calls) {
+        Ok(l) => l,
+        Err(e) => {
+            errors.line_errors.extend(e);
+            return MmlValidationResult { errors, warnings };
+        }
+    };
+
+    let (zenlen, min_rest_ticks) = match parse_headers(lines.headers) {
+        Ok(m) => (m.zenlen, m.min_rest_ticks),
+        Err(e) => {
+            errors.line_errors.extend(e);
+            (DEFAULT_ZENLEN, None)
+        }
+    };
+
+    let (instruments, inst_errors) = parse_instruments(lines.instruments, data_instruments);
+    errors.line_errors.extend(inst_errors);
+
+    let instrument_map = match build_instrument_map(&instruments) {
+        Ok(map) => map,
+        Err(e) => {
+            errors.line_errors.extend(e);
+            HashMap::new()
+        }
+    };
+
+    // Subroutine calls are not processed by the parser (see `NameOnlySubroutines`)
+    let mut called_subroutines = vec![false; lines.subroutines.len()];
+    let all_tokens = lines
+        .subroutines
+        .iter()
+        .map(|(_, t)| t)
+        .chain(lines.channels.iter());
+    for tokens in all_tokens {
+        for t in tokens.token_iter() {
+            if let Token::CallSubroutine(id, _) = t {
+                if let Some(&i) = lines.subroutine_name_map.get(id) {
+                    called_subroutines[i] = true;
+                }
+            }
+        }
+    }
+
+    let subroutines = NameOnlySubroutines {
+        name_map: &lines.subroutine_name_map,
+    };
+
+    #[cfg(feature = "mml_tracking")]
+    let mut cursor_tracker = CursorTracker::new();
+
+    let mut used_instruments = vec![false; instruments.len()];
+
+    let mut parse = |channel_id, tokens: MmlTokens<'_>| {
+        let mut parser = Parser::new(
+            channel_id,
+            tokens,
+            &instrument_map,
+            &subroutines,
+            zenlen,
+            min_rest_ticks,
+            None,
+            #[cfg(feature = "mml_tracking")]
+            &mut cursor_tracker,
+        );
+
+        for c in parser.by_ref() {
+            match c.command() {
+                Command::SetInstrument(i) | Command::SetSubroutineInstrumentHint(i) => {
+                    if let Some(u) = used_instruments.get_mut(*i) {
+                        *u = true;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let w = parser.take_warnings();
+
+        (parser.finalize().1, w)
+    };
+
+    for (i, ((identifier, tokens), called)) in lines
+        .subroutines
+        .into_iter()
+        .zip(called_subroutines)
+        .enumerate()
+    {
+        let pos = tokens.first_token_pos();
+        let (e, w) = parse(ChannelId::Subroutine(i.try_into().unwrap()), tokens);
+        warnings.extend(w);
+
+        if !called {
+            warnings.push(ErrorWithPos(
+                pos.to_range(1),
+                MmlWarning::UnusedSubroutine(identifier.as_str().to_owned()),
+            ));
+        }
+        if !e.is_empty() {
+            errors.subroutine_errors.push(MmlChannelError {
+                identifier: identifier.to_owned(),
+                errors: e,
+            });
+        }
+    }
+
+    for (tokens, name) in lines.channels.into_iter().zip(CHANNEL_NAMES) {
+        if !tokens.is_empty() {
+            let (e, w) = parse(ChannelId::Channel(name.chars().next().unwrap()), tokens);
+            warnings.extend(w);
+            if !e.is_empty() {
+                errors.channel_errors.push(MmlChannelError {
+                    identifier: IdentifierStr::from_str(name).to_owned(),
+                    errors: e,
+                });
+            }
+        }
+    }
+
+    for (inst, used) in instruments.iter().zip(used_instruments) {
+        if !used {
+            warnings.push(ErrorWithPos(
+                inst.file_range.clone(),
+                MmlWarning::UnusedInstrument(inst.identifier.as_str().to_owned()),
+            ));
+        }
+    }
+
+    warnings.sort_by_key(|w| w.0.index_start);
+
+    MmlValidationResult { errors, warnings }
+}
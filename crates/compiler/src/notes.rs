@@ -11,6 +11,8 @@ use crate::value_newtypes::{u8_value_newtype, UnsignedValueNewType};
 
 use serde::{Deserialize, Serialize, Serializer};
 
+use std::ops::RangeInclusive;
+
 u8_value_newtype!(MidiNote, MidiNoteNumberOutOfRange, NoMidiNote, 0, 127);
 
 pub const LAST_OCTAVE: u8 = 7;
@@ -19,6 +21,11 @@ pub const LAST_NOTE_ID: u8 = (LAST_OCTAVE + 1) * SEMITONES_PER_OCTAVE - 1;
 
 pub const N_NOTES: u8 = (LAST_OCTAVE + 1) * SEMITONES_PER_OCTAVE;
 
+/// MML note names (without the octave)
+pub const NOTE_NAMES: [&str; SEMITONES_PER_OCTAVE as usize] = [
+    "c", "c+", "d", "d+", "e", "f", "f+", "g", "g+", "a", "a+", "b",
+];
+
 #[derive(Debug, Clone, Copy)]
 pub struct PitchChar(u8);
 
@@ -272,6 +279,59 @@ impl TryFrom<u32> for Octave {
     }
 }
 
+/// An inclusive range of octaves
+#[derive(Deserialize, Serialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct NoteRange {
+    #[serde(rename = "first_octave")]
+    pub first: Octave,
+    #[serde(rename = "last_octave")]
+    pub last: Octave,
+}
+
+impl std::fmt::Display for Note {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            NOTE_NAMES[usize::from(self.note_id % SEMITONES_PER_OCTAVE)],
+            self.note_id / SEMITONES_PER_OCTAVE
+        )
+    }
+}
+
+impl NoteRange {
+    pub fn first_note(&self) -> Note {
+        Note::first_note_for_octave(self.first)
+    }
+
+    pub fn last_note(&self) -> Note {
+        Note::last_note_for_octave(self.last)
+    }
+
+    pub fn contains(&self, note: Note) -> bool {
+        note >= self.first_note() && note <= self.last_note()
+    }
+
+    pub fn intersection(&self, other: &NoteRange) -> Option<NoteRange> {
+        let first = self.first.max(other.first);
+        let last = self.last.min(other.last);
+
+        match first <= last {
+            true => Some(NoteRange { first, last }),
+            false => None,
+        }
+    }
+
+    /// Returns no notes if `first` > `last`
+    pub fn all_notes(&self) -> impl Iterator<Item = Note> {
+        (self.first_note().note_id..=self.last_note().note_id).map(|note_id| Note { note_id })
+    }
+
+    pub fn to_range(&self) -> RangeInclusive<Note> {
+        self.first_note()..=self.last_note()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MmlPitch {
     pitch: PitchChar,
@@ -286,3 +346,32 @@ impl MmlPitch {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn note_range(first: u32, last: u32) -> NoteRange {
+        NoteRange {
+            first: Octave::try_new(first).unwrap(),
+            last: Octave::try_new(last).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_note_range() {
+        let r = note_range(2, 4);
+
+        assert!(!r.contains(Note::last_note_for_octave(Octave::try_new(1).unwrap())));
+        assert!(r.contains(Note::first_note_for_octave(Octave::try_new(2).unwrap())));
+        assert!(r.contains(Note::last_note_for_octave(Octave::try_new(4).unwrap())));
+        assert!(!r.contains(Note::first_note_for_octave(Octave::try_new(5).unwrap())));
+
+        assert_eq!(r.all_notes().count(), 3 * usize::from(SEMITONES_PER_OCTAVE));
+        assert!(r.all_notes().all(|n| r.contains(n)));
+
+        assert_eq!(r.intersection(&note_range(3, 6)), Some(note_range(3, 4)));
+        assert_eq!(r.intersection(&note_range(0, 2)), Some(note_range(2, 2)));
+        assert_eq!(r.intersection(&note_range(5, 6)), None);
+    }
+}
@@ -10,7 +10,7 @@ use crate::bytecode::PlayPitchPitch;
 use crate::data::{Instrument, InstrumentOrSample, Sample, UniqueNamesList};
 use crate::driver_constants::{MAX_INSTRUMENTS_AND_SAMPLES, MAX_N_PITCHES};
 use crate::errors::ValueError;
-use crate::errors::{PitchError, PitchTableError};
+use crate::errors::{InstrumentPitchWarning, PitchError, PitchTableError, UnreachableReason};
 use crate::notes::{self, Note, Octave};
 use crate::value_newtypes::u16_value_newtype;
 use crate::value_newtypes::u32_value_newtype;
@@ -31,6 +31,9 @@ const F64_A4_C0_MST_OFFSET: f64 = A4_C0_MICROSEMITONE_OFFSET as f64;
 
 const SPC_SAMPLE_RATE: u32 = 32000;
 
+/// Concert pitch (A4) range accepted by `build_pitch_table_with_tuning()`
+pub const CONCERT_A_RANGE: std::ops::RangeInclusive<f64> = 400.0..=480.0;
+
 const MIN_SAMPLE_FREQ: f64 = 27.5; // a0
 const MAX_SAMPLE_FREQ: f64 = (SPC_SAMPLE_RATE / 2) as f64;
 
@@ -67,7 +70,41 @@ fn maximum_octave_increment(microsemitones_above_c: i32) -> i32 {
     }
 }
 
+/// Returns the octave and the micro-semitones above C of the sample's frequency.
+///
+/// Assumes `freq` is within `MIN_SAMPLE_FREQ..=MAX_SAMPLE_FREQ`.
+fn sample_pitch_above_c0(freq: f64, concert_a_hz: f64) -> (i32, i32) {
+    // Raising the concert pitch is equivalent to lowering the sample frequency
+    let mst_above_c0 = f64::log2(freq / concert_a_hz) * F64_MST_PER_OCTAVE + F64_A4_C0_MST_OFFSET;
+
+    assert!(
+        f64::log2(MAX_SAMPLE_FREQ / CONCERT_A_RANGE.start()) * F64_MST_PER_OCTAVE
+            + F64_A4_C0_MST_OFFSET
+            < (i32::MAX / 4) as f64,
+        "Cast overflows"
+    );
+    assert!(mst_above_c0 > 0.0);
+    let mst_above_c0 = mst_above_c0.round() as i32;
+
+    (
+        mst_above_c0 / MICROSEMITONES_PER_OCTAVE,
+        mst_above_c0 % MICROSEMITONES_PER_OCTAVE,
+    )
+}
+
 pub fn instrument_pitch(inst: &Instrument) -> Result<InstrumentPitch, PitchError> {
+    instrument_pitch_with_tuning(inst, F64_A4_FREQ)
+}
+
+/// Calculates the instrument pitch with an A4 frequency of `concert_a_hz`.
+///
+/// Assumes `concert_a_hz` is within `CONCERT_A_RANGE`.
+fn instrument_pitch_with_tuning(
+    inst: &Instrument,
+    concert_a_hz: f64,
+) -> Result<InstrumentPitch, PitchError> {
+    debug_assert!(CONCERT_A_RANGE.contains(&concert_a_hz));
+
     if inst.freq < MIN_SAMPLE_FREQ {
         return Err(PitchError::SampleRateTooLow);
     }
@@ -76,28 +113,16 @@ pub fn instrument_pitch(inst: &Instrument) -> Result<InstrumentPitch, PitchError
         return Err(PitchError::SampleRateTooHigh);
     }
 
-    if inst.first_octave > inst.last_octave {
+    if inst.note_range.first > inst.note_range.last {
         return Err(PitchError::FirstOctaveGreaterThanLastOctave);
     }
 
-    let mst_above_c0 =
-        f64::log2(inst.freq / F64_A4_FREQ) * F64_MST_PER_OCTAVE + F64_A4_C0_MST_OFFSET;
-
-    assert!(
-        f64::log2(MAX_SAMPLE_FREQ / F64_A4_FREQ) * F64_MST_PER_OCTAVE + F64_A4_C0_MST_OFFSET
-            < (i32::MAX / 4) as f64,
-        "Cast overflows"
-    );
-    assert!(mst_above_c0 > 0.0);
-    let mst_above_c0 = mst_above_c0.round() as i32;
-
-    let octaves_above_c0: i32 = mst_above_c0 / MICROSEMITONES_PER_OCTAVE;
-    let microsemitones_above_c: i32 = mst_above_c0 % MICROSEMITONES_PER_OCTAVE;
+    let (octaves_above_c0, microsemitones_above_c) = sample_pitch_above_c0(inst.freq, concert_a_hz);
 
     let maximum_octave_increment = maximum_octave_increment(microsemitones_above_c);
 
-    let min_octave_offset = inst.first_octave.as_i32() - octaves_above_c0;
-    let max_octave_offset = inst.last_octave.as_i32() - octaves_above_c0;
+    let min_octave_offset = inst.note_range.first.as_i32() - octaves_above_c0;
+    let max_octave_offset = inst.note_range.last.as_i32() - octaves_above_c0;
 
     let first_octave_valid = min_octave_offset >= MIN_MIN_OCTAVE_OFFSET;
     let last_octave_valid = max_octave_offset <= maximum_octave_increment;
@@ -211,6 +236,7 @@ pub fn sample_pitch(sample: &Sample) -> Result<SamplePitches, PitchError> {
 
 fn inst_pitch_vec(
     instruments_and_samples: &UniqueNamesList<InstrumentOrSample>,
+    concert_a_hz: f64,
 ) -> Result<SortedPitches, PitchTableError> {
     let mut instruments = Vec::with_capacity(instruments_and_samples.len());
     let mut samples = Vec::with_capacity(instruments_and_samples.len());
@@ -219,10 +245,12 @@ fn inst_pitch_vec(
 
     for (i, inst) in instruments_and_samples.list().iter().enumerate() {
         match inst {
-            InstrumentOrSample::Instrument(inst) => match instrument_pitch(inst) {
-                Ok(ip) => instruments.push((i, ip)),
-                Err(e) => errors.push((i, inst.name.clone(), e)),
-            },
+            InstrumentOrSample::Instrument(inst) => {
+                match instrument_pitch_with_tuning(inst, concert_a_hz) {
+                    Ok(ip) => instruments.push((i, ip)),
+                    Err(e) => errors.push((i, inst.name.clone(), e)),
+                }
+            }
             InstrumentOrSample::Sample(sample) => match sample_pitch(sample) {
                 Ok(sp) => samples.push((i, sp)),
                 Err(e) => errors.push((i, sample.name.clone(), e)),
@@ -392,11 +420,62 @@ pub(crate) fn merge_pitch_vec(
 pub fn build_pitch_table(
     instruments_and_samples: &UniqueNamesList<InstrumentOrSample>,
 ) -> Result<PitchTable, PitchTableError> {
-    let sorted_pitches = inst_pitch_vec(instruments_and_samples)?;
+    build_pitch_table_with_tuning(instruments_and_samples, F64_A4_FREQ)
+}
+
+/// Builds a pitch table where A4 is `concert_a_hz` Hz.
+///
+/// Only instrument pitches are tuned, sample pitches are unchanged.
+pub fn build_pitch_table_with_tuning(
+    instruments_and_samples: &UniqueNamesList<InstrumentOrSample>,
+    concert_a_hz: f64,
+) -> Result<PitchTable, PitchTableError> {
+    if !CONCERT_A_RANGE.contains(&concert_a_hz) {
+        return Err(PitchTableError::InvalidConcertPitch(concert_a_hz));
+    }
+
+    let sorted_pitches = inst_pitch_vec(instruments_and_samples, concert_a_hz)?;
 
     merge_pitch_vec(sorted_pitches, instruments_and_samples.len())
 }
 
+/// Lists the notes in the instrument's note range that cannot be played by the audio driver.
+///
+/// `instrument_pitch()` rejects the instrument if any octave is out of range.
+/// This function tells the user which notes caused the error.
+///
+/// Returns an empty `Vec` if the instrument frequency is invalid.
+pub fn validate_instrument_pitch_ranges(inst: &Instrument) -> Vec<InstrumentPitchWarning> {
+    if !(MIN_SAMPLE_FREQ..=MAX_SAMPLE_FREQ).contains(&inst.freq) {
+        return Vec::new();
+    }
+
+    let (octaves_above_c0, microsemitones_above_c) = sample_pitch_above_c0(inst.freq, F64_A4_FREQ);
+    let maximum_octave_increment = maximum_octave_increment(microsemitones_above_c);
+
+    inst.note_range
+        .all_notes()
+        .filter_map(|note| {
+            let octave_offset =
+                i32::from(note.note_id() / notes::SEMITONES_PER_OCTAVE) - octaves_above_c0;
+
+            let reason = if octave_offset < MIN_MIN_OCTAVE_OFFSET {
+                UnreachableReason::PitchTooLow
+            } else if octave_offset > maximum_octave_increment {
+                UnreachableReason::PitchTooHigh
+            } else {
+                return None;
+            };
+
+            Some(InstrumentPitchWarning::NoteUnreachable {
+                instrument: inst.name.clone(),
+                note,
+                reason,
+            })
+        })
+        .collect()
+}
+
 impl PitchTable {
     pub fn pitch_for_note(&self, inst_id: InstrumentId, note: Note) -> u16 {
         let offset: u8 = self.instruments_pitch_offset[usize::from(inst_id.as_u8())];
@@ -472,6 +551,85 @@ impl PlayPitchFrequency {
     }
 }
 
+/// Number of MIDI notes in a `#TuningTable`
+pub const TUNING_TABLE_SIZE: usize = 128;
+
+const MIDI_C0: u8 = 12;
+const MIDI_A4: f64 = 69.0;
+
+/// The frequency (in Hz) of every MIDI note number, used to play notes with a non-standard scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningTable(Box<[f64; TUNING_TABLE_SIZE]>);
+
+impl TuningTable {
+    /// Creates a twelve-tone equal temperament tuning table where A4 is `concert_a_hz` Hz.
+    pub fn equal_temperament(concert_a_hz: f64) -> Self {
+        Self(Box::new(std::array::from_fn(|midi_note| {
+            concert_a_hz * f64::powf(2.0, (midi_note as f64 - MIDI_A4) / 12.0)
+        })))
+    }
+
+    pub fn frequency(&self, note: Note) -> f64 {
+        self.0[usize::from(note.note_id() + MIDI_C0)]
+    }
+
+    pub fn to_vxpitch(
+        &self,
+        note: Note,
+        instrument: Option<&InstrumentOrSample>,
+    ) -> Result<PlayPitchPitch, ValueError> {
+        match instrument {
+            Some(InstrumentOrSample::Instrument(i)) => {
+                let pitch = self.frequency(note) / i.freq * f64::from(PITCH_REGISTER_FP_SCALE);
+                let pitch = pitch.round() as u32;
+
+                match PlayPitchPitch::try_from(pitch) {
+                    Ok(p) => Ok(p),
+                    Err(_) => Err(ValueError::CannotConvertTuningTableFrequency(note, pitch)),
+                }
+            }
+            Some(InstrumentOrSample::Sample(_)) => {
+                Err(ValueError::CannotConvertPitchFrequencySample)
+            }
+            None => Err(ValueError::CannotConvertPitchFrequencyUnknownInstrument),
+        }
+    }
+}
+
+/// Parses a whitespace separated list of `TUNING_TABLE_SIZE` frequencies (in Hz),
+/// one for each MIDI note number.
+pub fn parse_tuning_table(s: &str) -> Result<TuningTable, ValueError> {
+    let values: Vec<&str> = s.split_whitespace().collect();
+
+    if values.len() != TUNING_TABLE_SIZE {
+        return Err(ValueError::InvalidTuningTableSize(values.len()));
+    }
+
+    let mut table = Box::new([0.0; TUNING_TABLE_SIZE]);
+
+    for (midi_note, (t, v)) in table.iter_mut().zip(values).enumerate() {
+        match v.parse::<f64>() {
+            Ok(f) if f > 0.0 && f <= f64::from(PlayPitchFrequency::MAX.as_u16()) => *t = f,
+            _ => {
+                return Err(ValueError::InvalidTuningTableFrequency(
+                    midi_note.try_into().unwrap(),
+                    v.to_owned(),
+                ))
+            }
+        }
+    }
+
+    Ok(TuningTable(table))
+}
+
+/// Parses a concert pitch (A4 frequency in Hz) within `CONCERT_A_RANGE`.
+pub fn parse_concert_pitch(s: &str) -> Result<f64, ValueError> {
+    match s.parse::<f64>() {
+        Ok(f) if CONCERT_A_RANGE.contains(&f) => Ok(f),
+        _ => Err(ValueError::InvalidConcertPitch(s.to_owned())),
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct InstrumentHintFreq(u32);
 
@@ -501,3 +659,116 @@ impl std::fmt::Display for InstrumentHintFreq {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::data::{validate_instrument_and_sample_names, LoopSetting, Name};
+    use crate::envelope::{Envelope, Gain};
+    use crate::notes::NoteRange;
+
+    fn instrument(first_octave: u32, last_octave: u32) -> Instrument {
+        Instrument {
+            name: Name::try_from("inst".to_owned()).unwrap(),
+            source: Default::default(),
+            freq: 500.0,
+            loop_setting: LoopSetting::LoopWithFilter(0),
+            evaluator: Default::default(),
+            ignore_gaussian_overflow: false,
+            auto_detect_loop: false,
+            dc_offset_removal: false,
+            normalize: false,
+            note_range: NoteRange {
+                first: Octave::try_new(first_octave).unwrap(),
+                last: Octave::try_new(last_octave).unwrap(),
+            },
+            envelope: Envelope::Gain(Gain::new(0)),
+            comment: None,
+        }
+    }
+
+    fn instruments(first_octave: u32, last_octave: u32) -> UniqueNamesList<InstrumentOrSample> {
+        let inst = instrument(first_octave, last_octave);
+
+        validate_instrument_and_sample_names([inst].iter(), [].iter()).unwrap()
+    }
+
+    #[test]
+    fn test_validate_instrument_pitch_ranges() {
+        // 500 Hz is between b4 and c5, octaves 0 to 6 are valid
+        let inst = instrument(0, 6);
+        assert!(instrument_pitch(&inst).is_ok());
+        assert_eq!(validate_instrument_pitch_ranges(&inst), vec![]);
+
+        let inst = instrument(1, 7);
+        assert!(instrument_pitch(&inst).is_err());
+
+        let warnings = validate_instrument_pitch_ranges(&inst);
+        assert_eq!(warnings.len(), notes::SEMITONES_PER_OCTAVE as usize);
+        assert_eq!(
+            warnings[0],
+            InstrumentPitchWarning::NoteUnreachable {
+                instrument: inst.name.clone(),
+                note: Note::first_note_for_octave(inst.note_range.last),
+                reason: UnreachableReason::PitchTooHigh,
+            }
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "\"inst\": cannot play note c7 (pitch too high)"
+        );
+
+        let mut inst = instrument(0, 7);
+        inst.freq = 8000.0;
+        assert!(instrument_pitch(&inst).is_err());
+
+        let warnings = validate_instrument_pitch_ranges(&inst);
+        assert_eq!(warnings.len(), 2 * notes::SEMITONES_PER_OCTAVE as usize);
+        assert!(warnings.iter().all(|w| matches!(
+            w,
+            InstrumentPitchWarning::NoteUnreachable {
+                reason: UnreachableReason::PitchTooLow,
+                ..
+            }
+        )));
+        assert_eq!(
+            warnings.last().unwrap().to_string(),
+            "\"inst\": cannot play note b1 (pitch too low)"
+        );
+    }
+
+    #[test]
+    fn test_build_pitch_table_with_tuning() {
+        let a4 = Note::from_note_id_u32(4 * 12 + 9).unwrap();
+
+        let inst = instruments(2, 5);
+        let inst_id = InstrumentId::try_from(0u32).unwrap();
+
+        let pitch = |concert_a_hz| {
+            build_pitch_table_with_tuning(&inst, concert_a_hz)
+                .unwrap()
+                .pitch_for_note(inst_id, a4)
+        };
+
+        // freq = 500 Hz
+        assert_eq!(pitch(440.0), 0x0e14);
+        assert_eq!(
+            build_pitch_table(&inst)
+                .unwrap()
+                .pitch_for_note(inst_id, a4),
+            0x0e14
+        );
+        assert_eq!(pitch(415.0), 0x0d48);
+        assert_eq!(pitch(466.0), 0x0ee9);
+
+        assert!(matches!(
+            build_pitch_table_with_tuning(&inst, 100.0),
+            Err(PitchTableError::InvalidConcertPitch(_))
+        ));
+        assert!(matches!(
+            build_pitch_table_with_tuning(&inst, f64::NAN),
+            Err(PitchTableError::InvalidConcertPitch(_))
+        ));
+    }
+}
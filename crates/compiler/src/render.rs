@@ -0,0 +1,212 @@
+//! Renders songs to PCM audio with the emulator
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::bytecode_interpreter::SongInterpreter;
+use crate::common_audio_data::CommonAudioData;
+use crate::driver_constants::{
+    addresses, io_commands, LoaderDataType, AUDIO_RAM_SIZE, N_MUSIC_CHANNELS,
+};
+use crate::emulator::{
+    load_song_into_emulator, reset_and_wait_for_main_loop, write_driver_and_song, DriverInitTimeout,
+};
+use crate::envelope::DSP_SAMPLE_RATE;
+use crate::songs::SongData;
+use crate::time::{TickCounter, TIMER_HZ};
+
+use shvc_sound_emu::ShvcSoundEmu;
+
+/// Plays a song in the emulator and returns the emulated audio.
+///
+/// The emulator stops after the audio driver has processed `ticks` song ticks
+/// or when the song ends.
+///
+/// Returns interleaved stereo samples at `DSP_SAMPLE_RATE` Hz.
+pub fn render_song_to_pcm(
+    song_data: &SongData,
+    common_audio_data: &CommonAudioData,
+    stereo_flag: bool,
+    ticks: TickCounter,
+) -> Result<Vec<i16>, DriverInitTimeout> {
+    let interpreter = SongInterpreter::new(common_audio_data, song_data, stereo_flag);
+
+    // No IPL ROM
+    let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+    load_song_into_emulator(
+        &mut emu,
+        common_audio_data,
+        song_data,
+        Some(&interpreter),
+        stereo_flag,
+    )?;
+
+    emu.write_io_ports([io_commands::UNPAUSE, 0, 0, 0]);
+
+    Ok(render_emulator_to_pcm(&mut emu, ticks))
+}
+
+/// Plays compiled common audio data and song data in the emulator and returns the emulated audio.
+///
+/// The emulator stops after the audio driver has processed `ticks` song ticks
+/// or when the song ends.
+///
+/// Returns interleaved stereo samples at `DSP_SAMPLE_RATE` Hz.
+///
+/// Panics if `song_data` does not fit in audio-RAM.
+pub fn render_song_file_to_pcm(
+    common_data: &[u8],
+    song_data: &[u8],
+    song_addr: u16,
+    stereo_flag: bool,
+    ticks: TickCounter,
+) -> Result<Vec<i16>, DriverInitTimeout> {
+    // No IPL ROM
+    let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+    // The audio driver will setup the echo buffer
+    write_driver_and_song(
+        emu.apuram_mut(),
+        common_data,
+        song_data,
+        song_addr,
+        LoaderDataType {
+            stereo_flag,
+            play_song: true,
+            skip_echo_buffer_reset: false,
+        },
+    );
+
+    reset_and_wait_for_main_loop(&mut emu, 0, 0)?;
+
+    Ok(render_emulator_to_pcm(&mut emu, ticks))
+}
+
+/// Returns true if every music channel has been disabled
+fn all_music_channels_disabled(apuram: &[u8; AUDIO_RAM_SIZE]) -> bool {
+    let ptr_h = usize::from(addresses::CHANNEL_INSTRUCTION_PTR_H);
+
+    apuram[ptr_h..ptr_h + N_MUSIC_CHANNELS]
+        .iter()
+        .all(|&p| p == 0)
+}
+
+/// Emulates a playing song and returns the emulated audio.
+///
+/// The emulator stops after the audio driver has processed `ticks` song ticks
+/// or when all music channels have been disabled.
+pub fn render_emulator_to_pcm(emu: &mut ShvcSoundEmu, ticks: TickCounter) -> Vec<i16> {
+    const STC: usize = addresses::SONG_TICK_COUNTER as usize;
+
+    // A song tick is at most 256 timer ticks
+    const MAX_SAMPLES_PER_SONG_TICK: u64 = (256 * DSP_SAMPLE_RATE / TIMER_HZ) as u64 + 1;
+
+    let read_tick_counter =
+        |apuram: &[u8; AUDIO_RAM_SIZE]| u16::from_le_bytes([apuram[STC], apuram[STC + 1]]);
+
+    let max_frames = u64::from(ticks.value()) * MAX_SAMPLES_PER_SONG_TICK
+        / ShvcSoundEmu::AUDIO_BUFFER_SAMPLES as u64
+        + 1;
+
+    // The audio driver song tick counter is 16 bits
+    let mut prev_tick_counter = read_tick_counter(emu.apuram());
+    let mut tick_count: u64 = 0;
+
+    let mut out = Vec::new();
+
+    for _ in 0..max_frames {
+        let apuram = emu.apuram();
+
+        let tc = read_tick_counter(apuram);
+        tick_count += u64::from(tc.wrapping_sub(prev_tick_counter));
+        prev_tick_counter = tc;
+
+        if tick_count >= u64::from(ticks.value()) || all_music_channels_disabled(apuram) {
+            break;
+        }
+
+        out.extend_from_slice(emu.emulate());
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn emulator_with_program(program: &[u8]) -> ShvcSoundEmu {
+        let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+        let pc = usize::from(addresses::DRIVER_CODE);
+        emu.apuram_mut()[pc..pc + program.len()].copy_from_slice(program);
+
+        emu.reset(shvc_sound_emu::ResetRegisters {
+            pc: addresses::DRIVER_CODE,
+            a: 0,
+            x: 0,
+            y: 0,
+            psw: 0,
+            sp: 0xff,
+            esa: 0,
+            edl: 0,
+        });
+
+        emu
+    }
+
+    /// Enables the first music channel
+    fn enable_music_channel(emu: &mut ShvcSoundEmu) {
+        emu.apuram_mut()[usize::from(addresses::CHANNEL_INSTRUCTION_PTR_H)] = 0x80;
+    }
+
+    #[test]
+    fn render_stops_when_all_music_channels_are_disabled() {
+        // `BRA -2` infinite loop
+        let mut emu = emulator_with_program(&[0x2f, 0xfe]);
+
+        let pcm = render_emulator_to_pcm(&mut emu, TickCounter::new(1000));
+        assert!(pcm.is_empty());
+    }
+
+    #[test]
+    fn render_max_frames() {
+        // `BRA -2` infinite loop (the song tick counter never changes)
+        let mut emu = emulator_with_program(&[0x2f, 0xfe]);
+        enable_music_channel(&mut emu);
+
+        let pcm = render_emulator_to_pcm(&mut emu, TickCounter::new(10));
+        assert!(!pcm.is_empty());
+        assert_eq!(pcm.len() % ShvcSoundEmu::AUDIO_BUFFER_SIZE, 0);
+
+        let max_samples = 10 * (256 * DSP_SAMPLE_RATE / TIMER_HZ + 1) as usize
+            + ShvcSoundEmu::AUDIO_BUFFER_SAMPLES;
+        assert!(pcm.len() / 2 <= max_samples);
+    }
+
+    #[test]
+    fn render_stops_after_tick_counter_overflow() {
+        let [stc_l, stc_h] = addresses::SONG_TICK_COUNTER.to_le_bytes();
+        let [stc1_l, stc1_h] = (addresses::SONG_TICK_COUNTER + 1).to_le_bytes();
+
+        #[rustfmt::skip]
+        let program = [
+            0xac, stc_l, stc_h,   // INC !songTickCounter
+            0xd0, 0x03,           // BNE +3
+            0xac, stc1_l, stc1_h, // INC !songTickCounter+1
+            0x2f, 0xf6,           // BRA -10
+        ];
+
+        let mut emu = emulator_with_program(&program);
+        enable_music_channel(&mut emu);
+
+        let ticks = TickCounter::new(0x18000);
+        let pcm = render_emulator_to_pcm(&mut emu, ticks);
+        assert!(!pcm.is_empty());
+
+        // Stopped long before the maximum number of frames
+        assert!(pcm.len() < ShvcSoundEmu::AUDIO_BUFFER_SIZE * 1000);
+    }
+}
@@ -4,10 +4,16 @@
 //
 // SPDX-License-Identifier: MIT
 
+#[cfg(feature = "flac")]
+mod flac;
+
 use crate::data::{
-    BrrEvaluator, Instrument, InstrumentOrSample, LoopSetting, Sample, UniqueNamesProjectFile,
+    content_hash, BrrEvaluator, Instrument, InstrumentOrSample, LoopSetting, Sample,
+    UniqueNamesProjectFile, MAX_FILE_SIZE,
+};
+use crate::errors::{
+    BrrError, InstrumentSampleNote, SampleAndInstrumentDataError, SampleError, TaggedSampleError,
 };
-use crate::errors::{BrrError, SampleAndInstrumentDataError, SampleError, TaggedSampleError};
 use crate::notes::{Note, Octave, LAST_NOTE_ID};
 use crate::path::{ParentPathBuf, SourcePathBuf};
 use crate::pitch_table::{
@@ -16,13 +22,14 @@ use crate::pitch_table::{
 };
 
 use brr::{
-    encode_brr, parse_brr_file, read_mono_pcm_wave_file, BrrFilter, BrrSample, MonoPcm16WaveFile,
-    ValidBrrFile, BYTES_PER_BRR_BLOCK, SAMPLES_PER_BLOCK,
+    dc_offset, encode_brr, parse_brr_file, preprocess_i16_samples, read_mono_pcm_wave_file,
+    BrrFilter, BrrSample, EncodeOptions, MonoPcm16WaveFile, ValidBrrFile, BYTES_PER_BRR_BLOCK,
+    SAMPLES_PER_BLOCK,
 };
 
 use std::collections::HashMap;
 use std::fs;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::ops::RangeInclusive;
 use std::sync::Arc;
 
@@ -30,8 +37,27 @@ const MAX_BRR_SAMPLE_LOAD: u64 = 16 * 1024;
 const MAX_WAV_SAMPLES: usize =
     (MAX_BRR_SAMPLE_LOAD as usize) / BYTES_PER_BRR_BLOCK * SAMPLES_PER_BLOCK;
 
+#[cfg(feature = "flac")]
+const MAX_FLAC_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+// 0.5% of full scale
+const SIGNIFICANT_DC_OFFSET: u16 = 164;
+
 pub const WAV_EXTENSION: &str = "wav";
 pub const BRR_EXTENSION: &str = "brr";
+pub const FLAC_EXTENSION: &str = "flac";
+
+/// Returns true if the source file is decoded to PCM samples and encoded to BRR.
+///
+/// FLAC files are only decoded if the `flac` feature is enabled.
+pub fn is_pcm_source(source: &SourcePathBuf) -> bool {
+    match source.extension() {
+        Some(WAV_EXTENSION) => true,
+        #[cfg(feature = "flac")]
+        Some(FLAC_EXTENSION) => true,
+        _ => false,
+    }
+}
 
 fn read_file_limited(
     source: &SourcePathBuf,
@@ -57,10 +83,73 @@ fn read_file_limited(
     Ok(buffer)
 }
 
+/// The maximum size of a sample source file
+fn source_file_size_limit(source: &SourcePathBuf) -> u64 {
+    match source.extension() {
+        Some(BRR_EXTENSION) => MAX_BRR_SAMPLE_LOAD,
+        #[cfg(feature = "flac")]
+        Some(FLAC_EXTENSION) => MAX_FLAC_FILE_SIZE,
+        _ => MAX_FILE_SIZE.into(),
+    }
+}
+
+fn parse_brr_source(source: &SourcePathBuf, data: &[u8]) -> Result<ValidBrrFile, BrrError> {
+    match parse_brr_file(data) {
+        Ok(b) => Ok(b),
+        Err(e) => Err(BrrError::BrrParseError(source.to_path_string(), e)),
+    }
+}
+
+/// Decodes a wav file (or a flac file if the `flac` feature is enabled).
+fn parse_pcm_source(source: &SourcePathBuf, data: &[u8]) -> Result<MonoPcm16WaveFile, BrrError> {
+    #[cfg(feature = "flac")]
+    if source.extension() == Some(FLAC_EXTENSION) {
+        return match flac::decode_flac_file(data, MAX_WAV_SAMPLES) {
+            Ok(w) => Ok(w),
+            Err(e) => Err(BrrError::FlacFileError(source.to_path_string(), e)),
+        };
+    }
+
+    match read_mono_pcm_wave_file(&mut Cursor::new(data), MAX_WAV_SAMPLES) {
+        Ok(w) => Ok(w),
+        Err(e) => Err(BrrError::WaveFileError(Arc::from((
+            source.to_path_string(),
+            e,
+        )))),
+    }
+}
+
+/// Instrument settings that modify a wav or flac sample before it is encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PcmSampleOptions {
+    pub auto_detect_loop: bool,
+    pub dc_offset_removal: bool,
+    pub normalize: bool,
+}
+
+impl PcmSampleOptions {
+    pub fn from_instrument(inst: &Instrument) -> Self {
+        Self {
+            auto_detect_loop: inst.auto_detect_loop,
+            dc_offset_removal: inst.dc_offset_removal,
+            normalize: inst.normalize,
+        }
+    }
+}
+
+struct CachedInstrumentSample {
+    source_hash: [u8; 32],
+    loop_setting: LoopSetting,
+    pcm_options: PcmSampleOptions,
+    evaluator: BrrEvaluator,
+    brr_sample: Result<BrrSample, BrrError>,
+}
+
 pub struct SampleFileCache {
     parent_path: ParentPathBuf,
     brr_files: HashMap<SourcePathBuf, Result<ValidBrrFile, BrrError>>,
     wav_files: HashMap<SourcePathBuf, Result<MonoPcm16WaveFile, BrrError>>,
+    instrument_samples: HashMap<SourcePathBuf, CachedInstrumentSample>,
 }
 
 impl SampleFileCache {
@@ -69,46 +158,140 @@ impl SampleFileCache {
             parent_path,
             brr_files: HashMap::new(),
             wav_files: HashMap::new(),
+            instrument_samples: HashMap::new(),
         }
     }
 
     pub fn clear_cache(&mut self) {
         self.brr_files.clear();
         self.wav_files.clear();
+        self.instrument_samples.clear();
     }
 
     pub fn remove_path(&mut self, source: &SourcePathBuf) {
         self.brr_files.remove(source);
         self.wav_files.remove(source);
+        self.instrument_samples.remove(source);
+    }
+
+    /// Encodes (or loads) the instrument's BRR sample.
+    ///
+    /// The source file is read once and the sample is only re-encoded if the SHA-256 hash of
+    /// the source file or the instrument's loop setting, evaluator or pre-processing settings
+    /// have changed.
+    fn encode_instrument_sample(&mut self, inst: &Instrument) -> Result<BrrSample, BrrError> {
+        let source = &inst.source;
+
+        let data =
+            match read_file_limited(source, &self.parent_path, source_file_size_limit(source)) {
+                Ok(d) => d,
+                Err(e) => {
+                    self.remove_path(source);
+                    return Err(e);
+                }
+            };
+        let source_hash = content_hash(&data);
+
+        if let Some(c) = self.instrument_samples.get(&inst.source) {
+            if c.source_hash == source_hash {
+                if c.loop_setting == inst.loop_setting
+                    && c.pcm_options == PcmSampleOptions::from_instrument(inst)
+                    && c.evaluator == inst.evaluator
+                {
+                    return c.brr_sample.clone();
+                }
+            } else {
+                // Source file has changed
+                self.remove_path(&inst.source);
+            }
+        }
+
+        // Parse the data that was just read, instead of reading the file again
+        if source.extension() == Some(BRR_EXTENSION) {
+            let brr = parse_brr_source(source, &data);
+            self.brr_files.insert(source.clone(), brr);
+        } else if is_pcm_source(source) {
+            let wav = parse_pcm_source(source, &data);
+            self.wav_files.insert(source.clone(), wav);
+        }
+
+        let pcm_options = PcmSampleOptions::from_instrument(inst);
+        let brr_sample =
+            self.encode_sample_source(source, &inst.loop_setting, inst.evaluator, pcm_options);
+
+        self.instrument_samples.insert(
+            inst.source.clone(),
+            CachedInstrumentSample {
+                source_hash,
+                loop_setting: inst.loop_setting.clone(),
+                pcm_options,
+                evaluator: inst.evaluator,
+                brr_sample: brr_sample.clone(),
+            },
+        );
+
+        brr_sample
+    }
+
+    /// Encodes a sample source file, applying `pcm_options` if the source is a wav or flac file.
+    pub fn encode_sample_source(
+        &mut self,
+        source: &SourcePathBuf,
+        loop_setting: &LoopSetting,
+        evaluator: BrrEvaluator,
+        pcm_options: PcmSampleOptions,
+    ) -> Result<BrrSample, BrrError> {
+        if !is_pcm_source(source) {
+            return encode_or_load_brr_file(source, self, loop_setting, evaluator);
+        }
+
+        let wav = match self.load_wav_file(source) {
+            Ok(w) => w,
+            Err(e) => return Err(e.clone()),
+        };
+
+        let detected_loop = match pcm_options.auto_detect_loop && *loop_setting == LoopSetting::None
+        {
+            true => suggested_loop_setting(wav),
+            false => None,
+        };
+        let loop_setting = detected_loop.as_ref().unwrap_or(loop_setting);
+
+        if pcm_options.dc_offset_removal || pcm_options.normalize {
+            let samples = preprocess_i16_samples(
+                &wav.samples,
+                EncodeOptions {
+                    remove_dc_offset: pcm_options.dc_offset_removal,
+                    normalize: pcm_options.normalize,
+                    warn_on_clip: false,
+                },
+            );
+            encode_pcm_samples(source, &samples, loop_setting, evaluator)
+        } else {
+            encode_pcm_samples(source, &wav.samples, loop_setting, evaluator)
+        }
     }
 
     fn load_brr_file(&mut self, source: &SourcePathBuf) -> &Result<ValidBrrFile, BrrError> {
         self.brr_files.entry(source.to_owned()).or_insert_with(|| {
             match read_file_limited(source, &self.parent_path, MAX_BRR_SAMPLE_LOAD) {
-                Ok(data) => match parse_brr_file(&data) {
-                    Ok(b) => Ok(b),
-                    Err(e) => Err(BrrError::BrrParseError(source.to_path_string(), e)),
-                },
+                Ok(data) => parse_brr_source(source, &data),
                 Err(e) => Err(e),
             }
         })
     }
 
+    /// Loads a wav file (or a flac file if the `flac` feature is enabled).
     pub fn load_wav_file(
         &mut self,
         source: &SourcePathBuf,
     ) -> &Result<MonoPcm16WaveFile, BrrError> {
         self.wav_files.entry(source.to_owned()).or_insert_with(|| {
-            let p = &source.to_path(&self.parent_path);
-            match fs::File::open(p) {
-                Ok(mut file) => match read_mono_pcm_wave_file(&mut file, MAX_WAV_SAMPLES) {
-                    Ok(w) => Ok(w),
-                    Err(e) => Err(BrrError::WaveFileError(Arc::from((
-                        source.to_path_string(),
-                        e,
-                    )))),
-                },
-                Err(e) => Err(BrrError::IoError(Arc::from((source.to_path_string(), e)))),
+            let limit = source_file_size_limit(source);
+
+            match read_file_limited(source, &self.parent_path, limit) {
+                Ok(data) => parse_pcm_source(source, &data),
+                Err(e) => Err(e),
             }
         })
     }
@@ -125,6 +308,15 @@ fn encode_wave_file(
         Err(e) => return Err(e.clone()),
     };
 
+    encode_pcm_samples(source, &wav.samples, loop_setting, evaluator)
+}
+
+fn encode_pcm_samples(
+    source: &SourcePathBuf,
+    samples: &[i16],
+    loop_setting: &LoopSetting,
+    evaluator: BrrEvaluator,
+) -> Result<BrrSample, BrrError> {
     let (loop_point, dupe_block_hack, loop_filter) = match loop_setting {
         LoopSetting::None => (None, None, None),
         LoopSetting::OverrideBrrLoopPoint(_) => {
@@ -142,7 +334,7 @@ fn encode_wave_file(
     };
 
     match encode_brr(
-        &wav.samples,
+        samples,
         evaluator.to_evaluator(),
         loop_point,
         dupe_block_hack,
@@ -153,6 +345,76 @@ fn encode_wave_file(
     }
 }
 
+/// Returns the DC offset that will be removed from the instrument's sample if the offset is
+/// large enough to be worth reporting.
+///
+/// Returns `None` if `dc_offset_removal` is not set or the source is not a wav file.
+pub fn significant_dc_offset(inst: &Instrument, cache: &mut SampleFileCache) -> Option<i16> {
+    if !inst.dc_offset_removal || !is_pcm_source(&inst.source) {
+        return None;
+    }
+
+    let offset = dc_offset(&cache.load_wav_file(&inst.source).as_ref().ok()?.samples);
+
+    match offset.unsigned_abs() >= SIGNIFICANT_DC_OFFSET {
+        true => Some(offset),
+        false => None,
+    }
+}
+
+/// Returns the loop setting for the wav file's `smpl` or `cue ` chunk loop point.
+///
+/// Returns `None` if the wav file has no loop point, the loop does not end at the end of
+/// the sample or the loop start is not on a BRR block boundary.
+///
+/// (The loop start is not rounded to a block boundary as it would change the loop length.)
+pub fn suggested_loop_setting(wav: &MonoPcm16WaveFile) -> Option<LoopSetting> {
+    let l = wav.loop_info?;
+
+    if l.loop_start % SAMPLES_PER_BLOCK != 0 {
+        return None;
+    }
+
+    match l.loop_end {
+        Some(end) if end != wav.samples.len() => None,
+        _ => Some(LoopSetting::LoopResetFilter(l.loop_start)),
+    }
+}
+
+const SPC_SAMPLE_RATE: f64 = 32000.0;
+const MIDI_A4: f64 = 69.0;
+const A4_FREQ: f64 = 440.0;
+
+/// Returns the instrument frequency of a wav file (the frequency of the sample when played
+/// back at 32000 Hz).
+///
+/// The frequency is calculated from the MIDI unity note of the `smpl` chunk and the sample rate.
+/// Returns `None` if the wav file does not contain a `smpl` chunk.
+pub fn sample_frequency(wav: &MonoPcm16WaveFile) -> Option<f64> {
+    let unity_note = wav.unity_note?.note();
+    if wav.sample_rate == 0 || unity_note > 127.0 {
+        return None;
+    }
+
+    let note_freq = A4_FREQ * f64::powf(2.0, (unity_note - MIDI_A4) / 12.0);
+    let freq = note_freq * SPC_SAMPLE_RATE / f64::from(wav.sample_rate);
+
+    // Rounded to make the frequency easier to read in the GUI
+    Some((freq * 100.0).round() / 100.0)
+}
+
+/// Loads a wav file (or a flac file if the `flac` feature is enabled) and returns its
+/// instrument frequency.
+///
+/// Returns `None` if the file cannot be loaded or does not contain a `smpl` chunk.
+pub fn detect_sample_frequency(source: &SourcePathBuf, parent_path: &ParentPathBuf) -> Option<f64> {
+    let limit = source_file_size_limit(source);
+    let data = read_file_limited(source, parent_path, limit).ok()?;
+    let wav = parse_pcm_source(source, &data).ok()?;
+
+    sample_frequency(&wav)
+}
+
 fn load_brr_file(
     source: &SourcePathBuf,
     cache: &mut SampleFileCache,
@@ -183,8 +445,11 @@ pub fn encode_or_load_brr_file(
     loop_setting: &LoopSetting,
     evaluator: BrrEvaluator,
 ) -> Result<BrrSample, BrrError> {
+    if is_pcm_source(source) {
+        return encode_wave_file(source, cache, loop_setting, evaluator);
+    }
+
     match source.extension() {
-        Some(WAV_EXTENSION) => encode_wave_file(source, cache, loop_setting, evaluator),
         Some(BRR_EXTENSION) => load_brr_file(source, cache, loop_setting),
         _ => Err(BrrError::UnknownFileType(source.to_path_string())),
     }
@@ -202,6 +467,10 @@ impl<PitchT> SampleData<PitchT> {
     pub fn sample_size(&self) -> usize {
         self.brr_sample.brr_data().len()
     }
+
+    pub fn brr_sample(&self) -> &BrrSample {
+        &self.brr_sample
+    }
 }
 
 pub type InstrumentSampleData = SampleData<InstrumentPitch>;
@@ -229,8 +498,7 @@ pub fn load_sample_for_instrument(
     inst: &Instrument,
     cache: &mut SampleFileCache,
 ) -> Result<InstrumentSampleData, SampleError> {
-    let mut brr_sample =
-        encode_or_load_brr_file(&inst.source, cache, &inst.loop_setting, inst.evaluator);
+    let mut brr_sample = cache.encode_instrument_sample(inst);
 
     if !inst.ignore_gaussian_overflow
         && brr_sample
@@ -295,19 +563,33 @@ pub fn load_sample_for_sample(
     }
 }
 
+struct CompiledSamples {
+    instruments: Vec<InstrumentSampleData>,
+    samples: Vec<SampleSampleData>,
+    notes: Vec<InstrumentSampleNote>,
+}
+
 fn compile_samples(
     project: &UniqueNamesProjectFile,
-) -> Result<(Vec<InstrumentSampleData>, Vec<SampleSampleData>), Vec<TaggedSampleError>> {
+) -> Result<CompiledSamples, Vec<TaggedSampleError>> {
     let mut errors = Vec::new();
 
     let mut cache = SampleFileCache::new(project.parent_path.clone());
 
     let mut instruments = Vec::new();
+    let mut notes = Vec::new();
     for (i, inst) in project.instruments.list().iter().enumerate() {
         match load_sample_for_instrument(inst, &mut cache) {
             Ok(b) => instruments.push(b),
             Err(e) => errors.push(TaggedSampleError::Instrument(i, inst.name.clone(), e)),
         }
+
+        if let Some(dc_offset) = significant_dc_offset(inst, &mut cache) {
+            notes.push(InstrumentSampleNote::DcOffsetRemoved {
+                instrument: inst.name.clone(),
+                dc_offset,
+            });
+        }
     }
 
     let mut samples = Vec::new();
@@ -319,7 +601,11 @@ fn compile_samples(
     }
 
     if errors.is_empty() {
-        Ok((instruments, samples))
+        Ok(CompiledSamples {
+            instruments,
+            samples,
+            notes,
+        })
     } else {
         Err(errors)
     }
@@ -401,6 +687,8 @@ pub struct SampleAndInstrumentData {
 
     pub(crate) brr_data: Vec<u8>,
     pub(crate) brr_directory_offsets: Vec<BrrDirectoryOffset>,
+
+    pub(crate) notes: Vec<InstrumentSampleNote>,
 }
 
 impl SampleAndInstrumentData {
@@ -408,6 +696,10 @@ impl SampleAndInstrumentData {
         &self.pitch_table
     }
 
+    pub fn notes(&self) -> &[InstrumentSampleNote] {
+        &self.notes
+    }
+
     pub fn take_pitch_table(self) -> PitchTable {
         self.pitch_table
     }
@@ -477,6 +769,8 @@ pub fn combine_samples(
 
         brr_data: brr.brr_data,
         brr_directory_offsets: brr.brr_directory_offsets,
+
+        notes: Vec::new(),
     })
 }
 
@@ -499,12 +793,15 @@ pub fn build_sample_and_instrument_data(
     if !error.sample_errors.is_empty() {
         return Err(error);
     }
-    let (instruments, samples) = sample_data.unwrap();
-    combine_samples(instruments.as_slice(), samples.as_slice())
+    let c = sample_data.unwrap();
+
+    let mut data = combine_samples(c.instruments.as_slice(), c.samples.as_slice())?;
+    data.notes = c.notes;
+    Ok(data)
 }
 
 pub fn instrument_note_range(inst: &Instrument) -> RangeInclusive<Note> {
-    Note::first_note_for_octave(inst.first_octave)..=Note::last_note_for_octave(inst.last_octave)
+    inst.note_range.to_range()
 }
 
 pub fn sample_note_range(sample: &Sample) -> RangeInclusive<Note> {
@@ -523,3 +820,243 @@ pub fn note_range(s: &InstrumentOrSample) -> RangeInclusive<Note> {
         InstrumentOrSample::Sample(sample) => sample_note_range(sample),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::path::SourcePathResult;
+    use crate::test_utils::TempDir;
+
+    use brr::wav_test_utils::{mono_16_bit_wav_file, mono_16_bit_wav_file_with_loop};
+
+    use std::path::PathBuf;
+
+    /// A sample file inside a temporary project directory
+    struct TestSource {
+        dir: TempDir,
+        path: PathBuf,
+        parent_path: ParentPathBuf,
+        source: SourcePathBuf,
+    }
+
+    impl TestSource {
+        fn new(dir_name: &str, file_name: &str) -> Self {
+            let dir = TempDir::new(dir_name);
+            let path = dir.path().join(file_name);
+
+            let parent_path = ParentPathBuf::new(dir.path().to_owned());
+            let source = match parent_path.create_source_path(&path) {
+                SourcePathResult::InsideProject(s) => s,
+                _ => panic!("invalid source path"),
+            };
+
+            Self {
+                dir,
+                path,
+                parent_path,
+                source,
+            }
+        }
+    }
+
+    fn brr_file(sample: u8) -> Vec<u8> {
+        // Single BRR block with the end flag set
+        let mut brr = vec![0x01];
+        brr.extend([sample; 8]);
+        brr
+    }
+
+    fn test_instrument(source: SourcePathBuf) -> Instrument {
+        let mut inst: Instrument = serde_json::from_str(
+            r#"{"name":"inst","source":"","freq":500.0,"loop":"none","first_octave":2,"last_octave":5,"envelope":"gain 127","comment":null}"#,
+        )
+        .unwrap();
+        inst.source = source;
+        inst.ignore_gaussian_overflow = true;
+        inst
+    }
+
+    #[test]
+    fn instrument_sample_cache_uses_source_hash() {
+        let TestSource {
+            dir,
+            path: brr_path,
+            parent_path,
+            source,
+        } = TestSource::new("instrument_sample_cache", "inst.brr");
+        let inst = test_instrument(source.clone());
+
+        let mut cache = SampleFileCache::new(parent_path);
+
+        let load_brr_data = |cache: &mut SampleFileCache| {
+            load_sample_for_instrument(&inst, cache)
+                .unwrap()
+                .brr_sample
+                .brr_data()
+                .to_vec()
+        };
+
+        fs::write(&brr_path, brr_file(0x11)).unwrap();
+        assert_eq!(load_brr_data(&mut cache), brr_file(0x11));
+
+        let source_hash = cache.instrument_samples[&source].source_hash;
+        assert_eq!(inst.source_content_hash(dir.path()), Some(source_hash));
+
+        // Replace the cached sample to detect if the sample was re-encoded
+        let sentinel = parse_brr_file(&brr_file(0x22))
+            .unwrap()
+            .into_brr_sample(None)
+            .unwrap();
+        cache
+            .instrument_samples
+            .get_mut(&source)
+            .unwrap()
+            .brr_sample = Ok(sentinel);
+
+        // Unchanged file uses the cache
+        assert_eq!(load_brr_data(&mut cache), brr_file(0x22));
+
+        // Changing the file contents triggers a re-encode
+        fs::write(&brr_path, brr_file(0x33)).unwrap();
+        assert_eq!(load_brr_data(&mut cache), brr_file(0x33));
+
+        assert_ne!(cache.instrument_samples[&source].source_hash, source_hash);
+    }
+
+    fn ramp_samples(n_samples: u32) -> Vec<i16> {
+        (0..n_samples).map(|i| (i * 0x800) as i16).collect()
+    }
+
+    #[test]
+    fn detect_sample_frequency_from_smpl_chunk() {
+        let TestSource {
+            dir: _dir,
+            path: wav_path,
+            parent_path,
+            source,
+        } = TestSource::new("detect_sample_frequency", "inst.wav");
+
+        // midi_unity_note 60 (c4) at 32000 Hz
+        fs::write(
+            &wav_path,
+            mono_16_bit_wav_file_with_loop(&ramp_samples(64), 32000, 32),
+        )
+        .unwrap();
+        assert_eq!(detect_sample_frequency(&source, &parent_path), Some(261.63));
+
+        // 16000 Hz sample rate
+        let wav = mono_16_bit_wav_file_with_loop(&ramp_samples(64), 16000, 32);
+        fs::write(&wav_path, wav).unwrap();
+        assert_eq!(detect_sample_frequency(&source, &parent_path), Some(523.25));
+
+        // No smpl chunk
+        fs::write(&wav_path, mono_16_bit_wav_file(&[0; 16], 32000)).unwrap();
+        assert_eq!(detect_sample_frequency(&source, &parent_path), None);
+
+        // Not a wav file
+        fs::write(&wav_path, brr_file(0)).unwrap();
+        assert_eq!(detect_sample_frequency(&source, &parent_path), None);
+    }
+
+    #[test]
+    fn instrument_auto_detect_loop() {
+        let TestSource {
+            dir: _dir,
+            path: wav_path,
+            parent_path,
+            source,
+        } = TestSource::new("instrument_auto_detect_loop", "inst.wav");
+        let mut inst = test_instrument(source.clone());
+
+        fs::write(
+            &wav_path,
+            mono_16_bit_wav_file_with_loop(&ramp_samples(64), 32000, 32),
+        )
+        .unwrap();
+
+        let mut cache = SampleFileCache::new(parent_path);
+
+        assert_eq!(
+            suggested_loop_setting(cache.load_wav_file(&source).as_ref().unwrap()),
+            Some(LoopSetting::LoopResetFilter(32))
+        );
+
+        let loop_point = |inst: &Instrument, cache: &mut SampleFileCache| {
+            load_sample_for_instrument(inst, cache)
+                .unwrap()
+                .brr_sample
+                .loop_point_samples()
+        };
+
+        assert_eq!(loop_point(&inst, &mut cache), None);
+
+        inst.auto_detect_loop = true;
+        assert_eq!(loop_point(&inst, &mut cache), Some(32));
+
+        // An explicit loop setting overrides the wav file loop point
+        inst.loop_setting = LoopSetting::LoopWithFilter(16);
+        assert_eq!(loop_point(&inst, &mut cache), Some(16));
+    }
+
+    #[test]
+    fn instrument_auto_detect_unaligned_loop() {
+        let TestSource {
+            dir: _dir,
+            path: wav_path,
+            parent_path,
+            source,
+        } = TestSource::new("instrument_auto_detect_unaligned_loop", "inst.wav");
+        let mut inst = test_instrument(source.clone());
+        inst.auto_detect_loop = true;
+
+        fs::write(
+            &wav_path,
+            mono_16_bit_wav_file_with_loop(&ramp_samples(64), 32000, 20),
+        )
+        .unwrap();
+
+        let mut cache = SampleFileCache::new(parent_path);
+
+        assert_eq!(
+            suggested_loop_setting(cache.load_wav_file(&source).as_ref().unwrap()),
+            None
+        );
+
+        // Falls back to no loop instead of an encoding error
+        let sample = load_sample_for_instrument(&inst, &mut cache).unwrap();
+        assert_eq!(sample.brr_sample.loop_point_samples(), None);
+    }
+
+    #[test]
+    fn instrument_dc_offset_removal() {
+        let TestSource {
+            dir: _dir,
+            path: wav_path,
+            parent_path,
+            source,
+        } = TestSource::new("instrument_dc_offset_removal", "inst.wav");
+        let mut inst = test_instrument(source.clone());
+
+        // Square wave with a DC offset of 1000
+        let samples: Vec<i16> = (0..64)
+            .map(|i| if i % 8 < 4 { 3000 } else { -1000 })
+            .collect();
+        fs::write(&wav_path, mono_16_bit_wav_file(&samples, 32000)).unwrap();
+
+        let mut cache = SampleFileCache::new(parent_path);
+
+        let decoded_offset = |inst: &Instrument, cache: &mut SampleFileCache| {
+            let s = load_sample_for_instrument(inst, cache).unwrap();
+            let decoded = brr::decode_brr_data(s.brr_sample.brr_data());
+            dc_offset(&decoded)
+        };
+
+        assert!(decoded_offset(&inst, &mut cache) > 900);
+        assert_eq!(significant_dc_offset(&inst, &mut cache), None);
+
+        inst.dc_offset_removal = true;
+        assert!(decoded_offset(&inst, &mut cache).abs() < 20);
+        assert_eq!(significant_dc_offset(&inst, &mut cache), Some(1000));
+    }
+}
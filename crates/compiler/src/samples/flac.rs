@@ -0,0 +1,244 @@
+//! FLAC sample decoder
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::errors::FlacError;
+
+use brr::{MonoPcm16WaveFile, WavLoopInfo, WavUnityNote};
+use claxon::metadata::{MetadataBlock, MetadataBlockReader};
+use claxon::FlacReader;
+
+use std::io;
+
+const FLAC_MAGIC: &[u8; 4] = b"fLaC";
+
+// Application ID used by `flac --keep-foreign-metadata` to store RIFF chunks
+const RIFF_APPLICATION_ID: u32 = u32::from_be_bytes(*b"riff");
+const SMPL_CHUNK_ID: &[u8; 4] = b"smpl";
+const RIFF_CHUNK_HEADER_SIZE: usize = 8;
+
+impl From<claxon::Error> for FlacError {
+    fn from(e: claxon::Error) -> Self {
+        match e {
+            // The file has already been read into memory, an IO error is a truncated file
+            claxon::Error::IoError(_) => Self::InvalidFlacFile("unexpected end of file"),
+            claxon::Error::FormatError(s) => Self::InvalidFlacFile(s),
+            claxon::Error::Unsupported(s) => Self::Unsupported(s),
+        }
+    }
+}
+
+/// Returns the RIFF `smpl` chunk (excluding the chunk header) stored in an APPLICATION metadata block
+fn riff_smpl_chunk(data: &[u8]) -> Option<Vec<u8>> {
+    let data = data.strip_prefix(FLAC_MAGIC)?;
+
+    for block in MetadataBlockReader::new(io::Cursor::new(data)) {
+        if let MetadataBlock::Application { id, mut data } = block.ok()? {
+            if id == RIFF_APPLICATION_ID && data.starts_with(SMPL_CHUNK_ID) {
+                if data.len() < RIFF_CHUNK_HEADER_SIZE {
+                    return None;
+                }
+                data.drain(..RIFF_CHUNK_HEADER_SIZE);
+                return Some(data);
+            }
+        }
+    }
+    None
+}
+
+/// Returns the loop stored in the `LOOPSTART`, `LOOPLENGTH` or `LOOPEND` tags
+fn vorbis_comment_loop<R: io::Read>(reader: &FlacReader<R>) -> Option<WavLoopInfo> {
+    let tag = |name| -> Option<usize> { reader.get_tag(name).next()?.trim().parse().ok() };
+
+    let loop_start = tag("LOOPSTART")?;
+    let loop_end = match tag("LOOPLENGTH") {
+        Some(length) => Some(loop_start.checked_add(length)?),
+        None => tag("LOOPEND"),
+    };
+
+    Some(WavLoopInfo {
+        loop_start,
+        loop_end,
+    })
+}
+
+fn to_i16_sample(s: i64, bits_per_sample: u32) -> i16 {
+    let s = match bits_per_sample < 16 {
+        true => s << (16 - bits_per_sample),
+        false => s >> (bits_per_sample - 16),
+    };
+    s.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+/// Decodes a FLAC file, mixing multi-channel files down to mono.
+///
+/// The loop point is read from a RIFF `smpl` chunk (stored by `flac --keep-foreign-metadata`)
+/// or the `LOOPSTART` vorbis comment.
+pub(super) fn decode_flac_file(
+    data: &[u8],
+    max_samples: usize,
+) -> Result<MonoPcm16WaveFile, FlacError> {
+    let mut reader = FlacReader::new(io::Cursor::new(data))?;
+
+    let info = reader.streaminfo();
+    let n_channels = i64::from(info.channels);
+
+    if let Some(n) = info.samples {
+        if n > max_samples as u64 {
+            return Err(FlacError::FlacFileTooLarge);
+        }
+    }
+
+    let smpl_chunk = riff_smpl_chunk(data);
+    let loop_info = smpl_chunk
+        .as_deref()
+        .and_then(WavLoopInfo::from_smpl_chunk)
+        .or_else(|| vorbis_comment_loop(&reader));
+    let unity_note = smpl_chunk
+        .as_deref()
+        .and_then(WavUnityNote::from_smpl_chunk);
+
+    let mut samples = Vec::with_capacity(info.samples.unwrap_or(0) as usize);
+
+    let mut blocks = reader.blocks();
+    let mut buffer = Vec::new();
+
+    while let Some(block) = blocks.read_next_or_eof(buffer)? {
+        if samples.len() + block.duration() as usize > max_samples {
+            return Err(FlacError::FlacFileTooLarge);
+        }
+
+        samples.extend((0..block.duration()).map(|i| {
+            let sum: i64 = (0..block.channels())
+                .map(|c| i64::from(block.sample(c, i)))
+                .sum();
+            to_i16_sample(sum / n_channels, info.bits_per_sample)
+        }));
+
+        buffer = block.into_buffer();
+    }
+
+    if samples.is_empty() {
+        return Err(FlacError::NoSamples);
+    }
+
+    Ok(MonoPcm16WaveFile {
+        sample_rate: info.sample_rate,
+        // Discard loop points outside the sample
+        loop_info: loop_info.filter(|l| l.is_valid(samples.len())),
+        unity_note,
+        samples,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Created with a verbatim subframe encoder
+    //  Stereo, 24-bit, 32000 Hz, 16 samples, `LOOPSTART=8` vorbis comment
+    const STEREO_24_BIT_FLAC: [u8; 194] = [
+        0x66, 0x4c, 0x61, 0x43, 0x00, 0x00, 0x00, 0x22, 0x00, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x07, 0xd0, 0x03, 0x70, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x84, 0x00, 0x00,
+        0x29, 0x04, 0x00, 0x00, 0x00, 0x74, 0x65, 0x73, 0x74, 0x02, 0x00, 0x00, 0x00, 0x0a, 0x00,
+        0x00, 0x00, 0x54, 0x49, 0x54, 0x4c, 0x45, 0x3d, 0x74, 0x65, 0x73, 0x74, 0x0b, 0x00, 0x00,
+        0x00, 0x4c, 0x4f, 0x4f, 0x50, 0x53, 0x54, 0x41, 0x52, 0x54, 0x3d, 0x38, 0xff, 0xf8, 0x60,
+        0x1c, 0x00, 0x0f, 0x9f, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x03,
+        0x00, 0x00, 0x04, 0x00, 0x00, 0x05, 0x00, 0x00, 0x06, 0x00, 0x00, 0x07, 0x00, 0x00, 0x08,
+        0x00, 0x00, 0x09, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x0d,
+        0x00, 0x00, 0x0e, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0xff, 0x80, 0x00,
+        0xff, 0x00, 0x00, 0xfe, 0x80, 0x00, 0xfe, 0x00, 0x00, 0xfd, 0x80, 0x00, 0xfd, 0x00, 0x00,
+        0xfc, 0x80, 0x00, 0xfc, 0x00, 0x00, 0xfb, 0x80, 0x00, 0xfb, 0x00, 0x00, 0xfa, 0x80, 0x00,
+        0xfa, 0x00, 0x00, 0xf9, 0x80, 0x00, 0xf9, 0x00, 0x00, 0xf8, 0x80, 0x00, 0xe2, 0xfd,
+    ];
+
+    //  Mono, 16-bit, 16000 Hz, 16 samples, `riff` application block with a `smpl` chunk (loop 4-15)
+    const MONO_16_BIT_FLAC: [u8; 160] = [
+        0x66, 0x4c, 0x61, 0x43, 0x00, 0x00, 0x00, 0x22, 0x00, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x03, 0xe8, 0x00, 0xf0, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x00, 0x00,
+        0x48, 0x72, 0x69, 0x66, 0x66, 0x73, 0x6d, 0x70, 0x6c, 0x3c, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xf8,
+        0x60, 0x08, 0x00, 0x0f, 0x96, 0x02, 0xc0, 0x00, 0xc8, 0x00, 0xd0, 0x00, 0xd8, 0x00, 0xe0,
+        0x00, 0xe8, 0x00, 0xf0, 0x00, 0xf8, 0x00, 0x00, 0x00, 0x08, 0x00, 0x10, 0x00, 0x18, 0x00,
+        0x20, 0x00, 0x28, 0x00, 0x30, 0x00, 0x38, 0x00, 0xc6, 0x3d,
+    ];
+
+    #[test]
+    fn test_stereo_24_bit_flac() {
+        let wav = decode_flac_file(&STEREO_24_BIT_FLAC, 100).unwrap();
+
+        assert_eq!(wav.sample_rate, 32000);
+        assert_eq!(wav.samples, (0..16).map(|i| i * 64).collect::<Vec<i16>>());
+        assert_eq!(
+            wav.loop_info,
+            Some(WavLoopInfo {
+                loop_start: 8,
+                loop_end: None
+            })
+        );
+        assert_eq!(wav.unity_note, None);
+    }
+
+    #[test]
+    fn test_mono_16_bit_flac() {
+        let wav = decode_flac_file(&MONO_16_BIT_FLAC, 100).unwrap();
+
+        assert_eq!(wav.sample_rate, 16000);
+        assert_eq!(
+            wav.samples,
+            (0..16).map(|i| i * 0x800 - 0x4000).collect::<Vec<i16>>()
+        );
+        assert_eq!(
+            wav.loop_info,
+            Some(WavLoopInfo {
+                loop_start: 4,
+                loop_end: Some(16)
+            })
+        );
+        assert_eq!(
+            wav.unity_note,
+            Some(WavUnityNote {
+                midi_note: 60,
+                pitch_fraction: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_flac_too_large() {
+        assert!(decode_flac_file(&MONO_16_BIT_FLAC, 16).is_ok());
+        assert!(matches!(
+            decode_flac_file(&MONO_16_BIT_FLAC, 15),
+            Err(FlacError::FlacFileTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_flac() {
+        assert!(matches!(
+            decode_flac_file(b"RIFF", 100),
+            Err(FlacError::InvalidFlacFile(_))
+        ));
+
+        let truncated = &MONO_16_BIT_FLAC[..MONO_16_BIT_FLAC.len() - 10];
+        assert!(matches!(
+            decode_flac_file(truncated, 100),
+            Err(FlacError::InvalidFlacFile(_))
+        ));
+
+        // Corrupt a sample (CRC mismatch)
+        let mut corrupt = STEREO_24_BIT_FLAC;
+        corrupt[120] ^= 0x10;
+        assert!(matches!(
+            decode_flac_file(&corrupt, 100),
+            Err(FlacError::InvalidFlacFile(_))
+        ));
+    }
+}
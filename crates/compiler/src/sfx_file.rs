@@ -42,6 +42,34 @@ pub struct SoundEffectsFile {
     pub sound_effects: Vec<SoundEffectFileSfx>,
 }
 
+impl SoundEffectsFile {
+    pub fn len(&self) -> usize {
+        self.sound_effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sound_effects.is_empty()
+    }
+}
+
+impl IntoIterator for SoundEffectsFile {
+    type Item = SoundEffectFileSfx;
+    type IntoIter = std::vec::IntoIter<SoundEffectFileSfx>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sound_effects.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SoundEffectsFile {
+    type Item = &'a SoundEffectFileSfx;
+    type IntoIter = std::slice::Iter<'a, SoundEffectFileSfx>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sound_effects.iter()
+    }
+}
+
 pub fn convert_sfx_inputs_lossy(sound_effects: Vec<SoundEffectFileSfx>) -> Vec<SoundEffectInput> {
     sound_effects
         .into_iter()
@@ -574,6 +602,28 @@ c
         );
     }
 
+    #[test]
+    fn test_iterator() {
+        const INPUT: &str = "=== one ===\na\n=== two ===\nb\n=== three ===\n";
+
+        let tf = TextFile {
+            path: Default::default(),
+            file_name: Default::default(),
+            contents: INPUT.to_owned(),
+        };
+
+        let sfx_file = sfx_file_from_text_file(tf);
+
+        assert_eq!(sfx_file.len(), 3);
+        assert!(!sfx_file.is_empty());
+
+        let names: Vec<&str> = (&sfx_file).into_iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["one", "two", "three"]);
+
+        let names: Vec<String> = sfx_file.into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["one", "two", "three"]);
+    }
+
     #[test]
     fn test_unknown_attr() {
         const INPUT: &str = "=== name mml unknown ===\nTest";
@@ -0,0 +1,557 @@
+//! Smooth value effects
+
+// SPDX-FileCopyrightText: © 2023 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::bytecode_interpreter::UNINITIALISED;
+use crate::time::TickCounter;
+
+use std::hash::{Hash, Hasher};
+
+/// The effect currently applied to a [`SmoothValue`].
+///
+/// The discriminants of the slide and triangle effects match the audio driver's
+/// `volEffect_direction` and `panEffect_direction` values.
+/// The discriminants of the portamento effects match the audio driver's (signed)
+/// `portamento_direction` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmoothValueDirection {
+    None = 0,
+    SlideUp = 0x80,
+    SlideDown = 0x81,
+    TriangleUp = 0x40,
+    TriangleDown = 0x41,
+    PortamentoUp = 0x01,
+    PortamentoDown = 0xff,
+}
+
+/// A 16 bit fixed-point value (`value` + `sub_value`) that is smoothly changed by the audio driver.
+///
+/// Emulates the audio driver's per-tick effect processing using the number of ticks elapsed
+/// since the effect started, without processing every tick.
+///
+/// Used by the bytecode interpreter for:
+///  * Volume and pan slides (`Vs`, `ps`)
+///  * Tremolo and panbrello (`v~`, `p~`)
+///  * Portamento pitch tracking (`value` is the high byte of the pitch, `sub_value` is the low byte)
+///
+/// `value` is always <= `MAX`.
+/// The effect is disabled when it finishes or the value overflows.
+#[derive(Debug, Clone)]
+pub struct SmoothValue<const MAX: u8> {
+    tc: TickCounter,
+
+    value: u8,
+    sub_value: u8,
+    counter: u8,
+    direction: SmoothValueDirection,
+    half_wavelength: u8,
+
+    offset: u32,
+
+    triangle_starting_value: u8,
+    portamento_target: u16,
+}
+
+impl<const M: u8> Hash for SmoothValue<M> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tc.value().hash(state);
+        self.value.hash(state);
+        self.sub_value.hash(state);
+        self.counter.hash(state);
+        (self.direction as u8).hash(state);
+        self.half_wavelength.hash(state);
+        self.offset.hash(state);
+        self.triangle_starting_value.hash(state);
+        self.portamento_target.hash(state);
+    }
+}
+
+impl<const M: u8> SmoothValue<M> {
+    pub const MAX: u8 = M;
+    const MAX_U32: u32 = ((Self::MAX as u32) << 8) | 0xff;
+
+    const TRIANGLE_SUB_START: u8 = u8::MAX / 2;
+
+    pub fn new(value: u8) -> Self {
+        Self {
+            tc: TickCounter::new(0),
+            value,
+            sub_value: UNINITIALISED,
+            counter: UNINITIALISED,
+            direction: SmoothValueDirection::None,
+            offset: u32::from_le_bytes([UNINITIALISED, UNINITIALISED, 0, 0]),
+            half_wavelength: UNINITIALISED,
+            triangle_starting_value: UNINITIALISED,
+            portamento_target: 0,
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    pub fn sub_value(&self) -> u8 {
+        self.sub_value
+    }
+
+    /// Returns `value` and `sub_value` as a single 16 bit value.
+    pub fn u16_value(&self) -> u16 {
+        u16::from_le_bytes([self.sub_value, self.value])
+    }
+
+    pub fn direction(&self) -> SmoothValueDirection {
+        self.direction
+    }
+
+    /// The number of ticks remaining in the slide or the current half of a triangle wave.
+    pub fn counter(&self) -> u8 {
+        self.counter
+    }
+
+    /// The amount added to (or subtracted from) the 16 bit value every tick.
+    pub fn offset(&self) -> u16 {
+        self.offset.try_into().unwrap_or(u16::MAX)
+    }
+
+    pub fn half_wavelength(&self) -> u8 {
+        self.half_wavelength
+    }
+
+    pub fn portamento_target(&self) -> u16 {
+        self.portamento_target
+    }
+
+    fn u8_0is256_to_tick_counter(t: u8) -> TickCounter {
+        match t {
+            0 => TickCounter::new(0x100),
+            t => TickCounter::new(u32::from(t)),
+        }
+    }
+
+    fn slide_offset(&self, channel_ticks: TickCounter) -> (u8, u32) {
+        debug_assert!(self.tc < channel_ticks);
+
+        let slide_ticks = Self::u8_0is256_to_tick_counter(self.counter).value();
+
+        let elapsed = (channel_ticks.value() - self.tc.value()).min(slide_ticks);
+        let offset = self.offset * elapsed;
+
+        ((slide_ticks - elapsed).try_into().unwrap(), offset)
+    }
+
+    /// Advances the effect to `channel_ticks`.
+    ///
+    /// `channel_ticks` MUST be >= the tick counter of the last update or instruction.
+    pub fn update(&mut self, channel_ticks: TickCounter) {
+        if self.tc == channel_ticks {
+            return;
+        }
+
+        match self.direction {
+            SmoothValueDirection::None => (),
+
+            SmoothValueDirection::SlideUp => {
+                let value = u32::from_le_bytes([self.sub_value, self.value, 0, 0]);
+
+                let (counter, offset) = self.slide_offset(channel_ticks);
+
+                let value = value.wrapping_add(offset);
+
+                if value <= Self::MAX_U32 {
+                    self.value = value.to_le_bytes()[1];
+                    self.sub_value = value.to_le_bytes()[0];
+                } else {
+                    self.value = Self::MAX;
+                    self.direction = SmoothValueDirection::None;
+                }
+
+                self.counter = counter;
+                if counter == 0 {
+                    self.direction = SmoothValueDirection::None;
+                }
+
+                self.tc = channel_ticks;
+            }
+
+            SmoothValueDirection::SlideDown => {
+                let value = u32::from_le_bytes([self.sub_value, self.value, 0, 0]);
+
+                let (counter, offset) = self.slide_offset(channel_ticks);
+
+                let value = value.wrapping_sub(offset);
+
+                self.sub_value = value.to_le_bytes()[0];
+
+                if value <= Self::MAX_U32 {
+                    self.value = value.to_le_bytes()[1];
+                    self.sub_value = value.to_le_bytes()[0];
+                } else {
+                    self.value = 0;
+                    self.direction = SmoothValueDirection::None;
+                }
+
+                self.counter = counter;
+                if counter == 0 {
+                    self.direction = SmoothValueDirection::None;
+                }
+
+                self.tc = channel_ticks;
+            }
+
+            SmoothValueDirection::TriangleUp | SmoothValueDirection::TriangleDown => {
+                self.process_triangle(channel_ticks)
+            }
+
+            SmoothValueDirection::PortamentoUp | SmoothValueDirection::PortamentoDown => {
+                self.process_portamento(channel_ticks.value() - self.tc.value());
+                self.tc = channel_ticks;
+            }
+        }
+    }
+
+    fn process_triangle(&mut self, channel_ticks: TickCounter) {
+        let starting_value =
+            u32::from_le_bytes([Self::TRIANGLE_SUB_START, self.triangle_starting_value, 0, 0]);
+
+        let wavelength = u32::from(self.half_wavelength) * 2;
+        let quarter_wavelength = wavelength / 4;
+
+        let elapsed = channel_ticks.value() - self.tc.value();
+
+        let position = elapsed % wavelength;
+        let quadrant = position / quarter_wavelength;
+
+        if elapsed >= wavelength || quadrant > 0 {
+            // Test for overflow
+            if starting_value + (quarter_wavelength * self.offset) > Self::MAX_U32 {
+                self.value = Self::MAX;
+                self.direction = SmoothValueDirection::None;
+                return;
+            }
+        }
+
+        if elapsed >= wavelength || quadrant > 2 {
+            // Test for underflow
+            if quarter_wavelength * self.offset > starting_value {
+                self.value = 0;
+                self.direction = SmoothValueDirection::None;
+                return;
+            }
+        }
+
+        let (value, direction) = match quadrant {
+            0 => (
+                starting_value.wrapping_add(position * self.offset),
+                SmoothValueDirection::TriangleUp,
+            ),
+            1 => {
+                let p = u32::from(self.half_wavelength) - position;
+                (
+                    starting_value.wrapping_add(p * self.offset),
+                    SmoothValueDirection::TriangleDown,
+                )
+            }
+            2 => {
+                let p = position - u32::from(self.half_wavelength);
+                (
+                    starting_value.wrapping_sub(p * self.offset),
+                    SmoothValueDirection::TriangleDown,
+                )
+            }
+            3 => {
+                let p = wavelength - position;
+                (
+                    starting_value.wrapping_sub(p * self.offset),
+                    SmoothValueDirection::TriangleUp,
+                )
+            }
+            _ => panic!("Wrong quadrant"),
+        };
+
+        if value <= Self::MAX_U32 {
+            let half_wavelength = u32::from(self.half_wavelength);
+
+            self.value = value.to_le_bytes()[1];
+            self.sub_value = value.to_le_bytes()[0];
+            self.counter = (half_wavelength - (position + quarter_wavelength) % half_wavelength)
+                .to_le_bytes()[0];
+            self.direction = direction;
+        } else {
+            self.value = if quadrant < 2 { Self::MAX } else { 0 };
+            self.direction = SmoothValueDirection::None;
+        }
+    }
+
+    /// Emulates `_process_portamento__inline()` for `ticks` ticks.
+    fn process_portamento(&mut self, ticks: u32) {
+        let value = u32::from(self.u16_value());
+        let target = u32::from(self.portamento_target);
+        let offset = self.offset * ticks;
+
+        let new_value = match self.direction {
+            SmoothValueDirection::PortamentoUp => {
+                let v = value + offset;
+                match v < target {
+                    true => Some(v),
+                    false => None,
+                }
+            }
+            SmoothValueDirection::PortamentoDown => match value.checked_sub(offset) {
+                Some(v) if v >= target => Some(v),
+                _ => None,
+            },
+            _ => return,
+        };
+
+        match new_value {
+            Some(v) if v <= Self::MAX_U32 => {
+                self.value = v.to_le_bytes()[1];
+                self.sub_value = v.to_le_bytes()[0];
+            }
+            _ => {
+                // Pitch reached or exceeded target
+                self.set_u16_value(self.portamento_target);
+            }
+        }
+    }
+
+    /// Sets the value and disables the effect.
+    pub fn set_value(&mut self, value: u8) {
+        self.direction = SmoothValueDirection::None;
+        self.value = value;
+    }
+
+    /// Sets `value` and `sub_value` and disables the effect.
+    pub fn set_u16_value(&mut self, value: u16) {
+        self.direction = SmoothValueDirection::None;
+        self.value = value.to_le_bytes()[1].min(Self::MAX);
+        self.sub_value = value.to_le_bytes()[0];
+    }
+
+    /// Adds `amount` to the value (clamped to `0..=MAX`) and disables the effect.
+    pub fn adjust_value(&mut self, amount: i8, tc: TickCounter) {
+        self.update(tc);
+
+        self.direction = SmoothValueDirection::None;
+        self.value = self.value.saturating_add_signed(amount).clamp(0, Self::MAX);
+    }
+
+    /// Starts a slide up effect that adds `offset` to the value every tick for `ticks` ticks
+    /// (`0` is 256 ticks).
+    pub fn slide_up_instruction(&mut self, ticks: u8, offset: u16, tc: TickCounter) {
+        self.update(tc);
+
+        self.tc = tc;
+        self.counter = ticks;
+        self.half_wavelength = 0;
+        self.direction = SmoothValueDirection::SlideUp;
+        self.offset = offset.into();
+        self.sub_value = 0;
+    }
+
+    /// Starts a slide down effect that subtracts `offset` from the value every tick for `ticks`
+    /// ticks (`0` is 256 ticks).
+    pub fn slide_down_instruction(&mut self, ticks: u8, offset: u16, tc: TickCounter) {
+        self.update(tc);
+
+        self.tc = tc;
+        self.counter = ticks;
+        self.half_wavelength = 0;
+        self.direction = SmoothValueDirection::SlideDown;
+        self.offset = offset.into();
+        self.sub_value = u8::MAX;
+    }
+
+    /// Starts a triangle wave effect centred on the current value.
+    ///
+    /// `qwt` is the quarter-wavelength in ticks and `offset` is the amount the value changes
+    /// every tick.
+    pub fn triangle_instruction(&mut self, qwt: u8, offset: u16, tc: TickCounter) {
+        self.update(tc);
+
+        self.tc = tc;
+        self.counter = qwt;
+        self.half_wavelength = qwt.wrapping_mul(2);
+        self.direction = SmoothValueDirection::TriangleUp;
+        self.offset = offset.into();
+        self.sub_value = Self::TRIANGLE_SUB_START;
+
+        self.triangle_starting_value = self.value;
+    }
+
+    /// Starts a portamento that adds `speed` to the 16 bit value every tick until it
+    /// reaches `target`.
+    ///
+    /// The audio driver processes portamento after the bytecode, the first tick is applied
+    /// immediately.
+    pub fn portamento_up_instruction(&mut self, target: u16, speed: u8, tc: TickCounter) {
+        self.portamento_instruction(SmoothValueDirection::PortamentoUp, target, speed, tc);
+    }
+
+    /// Starts a portamento that subtracts `speed` from the 16 bit value every tick until it
+    /// reaches `target`.
+    ///
+    /// The audio driver processes portamento after the bytecode, the first tick is applied
+    /// immediately.
+    pub fn portamento_down_instruction(&mut self, target: u16, speed: u8, tc: TickCounter) {
+        self.portamento_instruction(SmoothValueDirection::PortamentoDown, target, speed, tc);
+    }
+
+    fn portamento_instruction(
+        &mut self,
+        direction: SmoothValueDirection,
+        target: u16,
+        speed: u8,
+        tc: TickCounter,
+    ) {
+        self.update(tc);
+
+        self.tc = tc;
+        self.direction = direction;
+        self.offset = speed.into();
+        self.portamento_target = target;
+
+        self.process_portamento(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SmoothValue, SmoothValueDirection};
+    use crate::time::TickCounter;
+
+    fn tc(t: u32) -> TickCounter {
+        TickCounter::new(t)
+    }
+
+    #[test]
+    fn slide_up() {
+        let mut v = SmoothValue::<0xff>::new(10);
+        v.slide_up_instruction(20, 0x0180, tc(5));
+
+        v.update(tc(15));
+        assert_eq!(v.value(), 10 + 15);
+        assert_eq!(v.sub_value(), 0);
+        assert_eq!(v.counter(), 10);
+        assert_eq!(v.direction(), SmoothValueDirection::SlideUp);
+
+        v.update(tc(100));
+        assert_eq!(v.value(), 10 + 30);
+        assert_eq!(v.counter(), 0);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+
+    #[test]
+    fn slide_down() {
+        let mut v = SmoothValue::<0xff>::new(100);
+        v.slide_down_instruction(0, 0x0100, tc(0));
+
+        v.update(tc(40));
+        assert_eq!(v.value(), 60);
+        assert_eq!(v.counter(), 0xff - 39);
+        assert_eq!(v.direction(), SmoothValueDirection::SlideDown);
+    }
+
+    #[test]
+    fn slide_clamps_to_max_and_zero() {
+        let mut v = SmoothValue::<64>::new(60);
+        v.slide_up_instruction(100, 0x0100, tc(0));
+        v.update(tc(50));
+        assert_eq!(v.value(), 64);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+
+        let mut v = SmoothValue::<64>::new(4);
+        v.slide_down_instruction(100, 0x0100, tc(0));
+        v.update(tc(50));
+        assert_eq!(v.value(), 0);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+
+    #[test]
+    fn adjust_value_clamps() {
+        let mut v = SmoothValue::<64>::new(60);
+        v.adjust_value(10, tc(0));
+        assert_eq!(v.value(), 64);
+
+        v.adjust_value(-100, tc(0));
+        assert_eq!(v.value(), 0);
+    }
+
+    #[test]
+    fn triangle() {
+        let mut v = SmoothValue::<0xff>::new(100);
+        v.triangle_instruction(8, 0x0200, tc(0));
+
+        // Peak
+        v.update(tc(8));
+        assert_eq!(v.value(), 116);
+        assert_eq!(v.direction(), SmoothValueDirection::TriangleDown);
+
+        // Trough (the tick counter is relative to the start of the effect)
+        v.update(tc(24));
+        assert_eq!(v.value(), 84);
+        assert_eq!(v.direction(), SmoothValueDirection::TriangleUp);
+
+        // Back to the start after a full wavelength
+        v.update(tc(32));
+        assert_eq!(v.value(), 100);
+        assert_eq!(v.direction(), SmoothValueDirection::TriangleUp);
+    }
+
+    #[test]
+    fn triangle_overflow_disables_effect() {
+        let mut v = SmoothValue::<64>::new(60);
+        v.triangle_instruction(8, 0x0100, tc(0));
+
+        v.update(tc(10));
+        assert_eq!(v.value(), 64);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+
+    #[test]
+    fn portamento_up() {
+        let mut v = SmoothValue::<0xff>::new(0);
+        v.set_u16_value(0x1000);
+        v.portamento_up_instruction(0x1100, 0x10, tc(10));
+
+        // First tick is processed by the instruction
+        assert_eq!(v.u16_value(), 0x1010);
+        assert_eq!(v.direction(), SmoothValueDirection::PortamentoUp);
+
+        v.update(tc(20));
+        assert_eq!(v.u16_value(), 0x10b0);
+        assert_eq!(v.direction(), SmoothValueDirection::PortamentoUp);
+
+        v.update(tc(25));
+        assert_eq!(v.u16_value(), 0x1100);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+
+    #[test]
+    fn portamento_down() {
+        let mut v = SmoothValue::<0xff>::new(0);
+        v.set_u16_value(0x0800);
+        v.portamento_down_instruction(0x0700, 0x20, tc(0));
+        assert_eq!(v.u16_value(), 0x07e0);
+
+        // Portamento down does not stop until the value is below the target
+        v.update(tc(7));
+        assert_eq!(v.u16_value(), 0x0700);
+        assert_eq!(v.direction(), SmoothValueDirection::PortamentoDown);
+
+        v.update(tc(8));
+        assert_eq!(v.u16_value(), 0x0700);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+
+    #[test]
+    fn portamento_down_underflow() {
+        let mut v = SmoothValue::<0xff>::new(0);
+        v.set_u16_value(0x0010);
+        v.portamento_down_instruction(0x0008, 0x20, tc(0));
+        assert_eq!(v.u16_value(), 0x0008);
+        assert_eq!(v.direction(), SmoothValueDirection::None);
+    }
+}
@@ -18,16 +18,17 @@ use crate::driver_constants::{
     SONG_HEADER_ECHO_EDL, SONG_HEADER_N_SUBROUTINES_OFFSET, SONG_HEADER_SIZE,
     SONG_HEADER_TICK_TIMER_OFFSET,
 };
-use crate::echo::EchoEdl;
+use crate::echo::{echo_buffer_address, echo_overlaps_data, EchoEdl};
 use crate::envelope::{Envelope, Gain};
-use crate::errors::{ChannelError, SongError, SongTooLargeError};
+use crate::errors::{ChannelError, CommonAudioDataError, SongError, SongTooLargeError};
 use crate::mml::{MetaData, Section};
-use crate::notes::{Note, Octave};
+use crate::notes::{Note, NoteRange, Octave};
 use crate::subroutines::{NoSubroutines, Subroutine};
 use crate::time::{TickClock, TickCounter, TickCounterWithLoopFlag};
 use crate::{audio_driver, mml};
 
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::Range;
 use std::sync::OnceLock;
@@ -72,6 +73,22 @@ pub struct SongBcTracking {
     pub first_channel_bc_offset: u16,
 }
 
+#[cfg(feature = "mml_tracking")]
+impl SongBcTracking {
+    /// Maps the character indexes to the root MML file (the file being edited).
+    ///
+    /// Notes in an included file are mapped to the `#include` directive.
+    pub(crate) fn map_to_root_file(mut self, include_map: &mml::IncludeMap) -> Self {
+        if !include_map.is_empty() {
+            for b in &mut self.bytecode {
+                b.char_index = include_map.to_root_char_index(b.char_index);
+            }
+            self.cursor_tracker.map_to_root_file(include_map);
+        }
+        self
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct LoopPoint {
     pub bytecode_offset: usize,
@@ -87,6 +104,7 @@ pub struct Channel {
     pub tick_counter: TickCounter,
 
     pub max_stack_depth: StackDepth,
+    pub subroutine_depth: u8,
 
     pub section_tick_counters: Vec<TickCounterWithLoopFlag>,
     pub tempo_changes: Vec<(TickCounter, TickClock)>,
@@ -124,6 +142,18 @@ impl SongData {
         self.duration
     }
 
+    /// Returns the time taken to play the song up to `ticks`.
+    ///
+    /// Returns `None` if a subroutine changes the song tempo.
+    pub fn duration_at_tick(&self, ticks: TickCounter) -> Option<Duration> {
+        mml::song_duration::calc_duration_at_tick(
+            &self.metadata,
+            &self.channels,
+            &self.subroutines,
+            ticks,
+        )
+    }
+
     pub fn max_tick_count(&self) -> TickCounter {
         self.channels
             .iter()
@@ -144,6 +174,10 @@ impl SongData {
         &self.sections
     }
 
+    pub fn section_tick_count_table(&self) -> mml::SectionTickCountTable {
+        mml::build_section_tick_count_table(self)
+    }
+
     pub fn instruments(&self) -> &[MmlInstrument] {
         &self.instruments
     }
@@ -156,6 +190,25 @@ impl SongData {
         &self.subroutines
     }
 
+    /// Maps each subroutine index to the subroutine indexes it calls.
+    pub fn subroutine_call_graph(&self) -> HashMap<usize, Vec<usize>> {
+        self.subroutines
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.subroutine_id.subroutine_calls().to_vec()))
+            .collect()
+    }
+
+    /// Returns the maximum number of nested subroutine calls from any channel.
+    pub fn subroutine_depth(&self) -> u8 {
+        self.channels
+            .iter()
+            .flatten()
+            .map(|c| c.subroutine_depth)
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn song_aram_size(&self) -> SongAramSize {
         let data_size = self.data().len();
         // Loader can only load a multiple of 2 bytes
@@ -193,8 +246,13 @@ fn sample_song_fake_instruments() -> &'static UniqueNamesList<InstrumentOrSample
             loop_setting: data::LoopSetting::None,
             evaluator: Default::default(),
             ignore_gaussian_overflow: false,
-            first_octave: Octave::try_new(Octave::MIN.as_u8().into()).unwrap(),
-            last_octave: Octave::try_new(Octave::MAX.as_u8().into()).unwrap(),
+            auto_detect_loop: false,
+            dc_offset_removal: false,
+            normalize: false,
+            note_range: NoteRange {
+                first: Octave::try_new(Octave::MIN.as_u8().into()).unwrap(),
+                last: Octave::try_new(Octave::MAX.as_u8().into()).unwrap(),
+            },
             envelope: Envelope::Gain(Gain::new(0)),
             comment: Default::default(),
         });
@@ -449,3 +507,38 @@ pub fn validate_song_size(
         })
     }
 }
+
+/// Tests the song's echo buffer does not overlap the common audio data or the song data.
+pub fn validate_echo_buffer(
+    song: &SongData,
+    common_data_size: usize,
+) -> Result<(), CommonAudioDataError> {
+    let echo_buffer = &song.metadata().echo_buffer;
+
+    // Loader can only transfer data that is a multiple of 2 bytes
+    let common_data_size = common_data_size + (common_data_size % 2);
+    let song_data_size = song.data().len() + (song.data().len() % 2);
+
+    let data_start = usize::from(addresses::COMMON_DATA);
+    let data_end = data_start + common_data_size + song_data_size;
+
+    let esa = echo_buffer.esa_register();
+    let edl = echo_buffer.max_edl.as_u8();
+
+    let overlaps = match u16::try_from(data_end) {
+        Ok(data_end) => echo_overlaps_data(esa, edl, data_end),
+        Err(_) => true,
+    };
+
+    if overlaps {
+        let echo_start = usize::from(echo_buffer_address(esa));
+        Err(CommonAudioDataError::EchoBufferOverlap {
+            echo_start,
+            echo_end: echo_start + echo_buffer.buffer_size(),
+            data_start,
+            data_end,
+        })
+    } else {
+        Ok(())
+    }
+}
@@ -286,11 +286,11 @@ pub fn compile_sound_effects_file(
         }
     };
 
-    let mut sound_effects = HashMap::with_capacity(sfx_file.sound_effects.len());
+    let mut sound_effects = HashMap::with_capacity(sfx_file.len());
 
     let mut errors = Vec::new();
 
-    for sfx in &sfx_file.sound_effects {
+    for sfx in sfx_file {
         let mut other_errors = Vec::new();
 
         let name = match sfx.name.parse::<Name>() {
@@ -593,3 +593,94 @@ pub fn compile_sound_effect_input(
         }),
     }
 }
+
+/// Lazily compiles sound effects, one sound effect at a time.
+///
+/// Each sound effect is compiled when the iterator is advanced,
+/// the compiled sound effects are not retained by this function.
+pub fn compile_sound_effects_stream<'a>(
+    inputs: impl Iterator<Item = &'a SoundEffectInput> + 'a,
+    inst_map: &'a UniqueNamesList<InstrumentOrSample>,
+    pitch_table: &'a PitchTable,
+    subroutines: &'a CompiledSfxSubroutines,
+) -> impl Iterator<Item = (Name, Result<CompiledSoundEffect, SoundEffectError>)> + 'a {
+    inputs.map(move |sfx| {
+        (
+            sfx.name.clone(),
+            compile_sound_effect_input(sfx, inst_map, pitch_table, subroutines),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::data::{self, Instrument, LoopSetting};
+    use crate::envelope::{Envelope, Gain};
+    use crate::notes::{NoteRange, Octave};
+    use crate::samples::combine_samples;
+
+    use std::cell::Cell;
+
+    #[test]
+    fn compile_sound_effects_stream_is_lazy() {
+        let samples = combine_samples([].as_slice(), [].as_slice()).unwrap();
+        let pitch_table = samples.pitch_table();
+
+        let inst_map = data::validate_instrument_and_sample_names(
+            [Instrument {
+                name: "dummy".parse().unwrap(),
+                source: Default::default(),
+                freq: 500.0,
+                loop_setting: LoopSetting::None,
+                evaluator: Default::default(),
+                ignore_gaussian_overflow: false,
+                auto_detect_loop: false,
+                dc_offset_removal: false,
+                normalize: false,
+                note_range: NoteRange {
+                    first: Octave::try_new(2).unwrap(),
+                    last: Octave::try_new(5).unwrap(),
+                },
+                envelope: Envelope::Gain(Gain::new(127)),
+                comment: None,
+            }]
+            .iter(),
+            std::iter::empty(),
+        )
+        .unwrap();
+
+        let subroutines =
+            compile_sfx_subroutines(&SfxSubroutinesMml(String::new()), &inst_map, pitch_table)
+                .unwrap();
+
+        let inputs: Vec<_> = (0..1000)
+            .map(|i| SoundEffectInput {
+                name: format!("sfx_{i}").parse().unwrap(),
+                flags: SfxFlags::default(),
+                sfx: SoundEffectText::BytecodeAssembly(format!("rest {}", i % 100 + 2)),
+            })
+            .collect();
+
+        let n_compiled = Cell::new(0);
+        let counted_inputs = inputs
+            .iter()
+            .inspect(|_| n_compiled.set(n_compiled.get() + 1));
+
+        let mut stream =
+            compile_sound_effects_stream(counted_inputs, &inst_map, pitch_table, &subroutines);
+
+        assert_eq!(n_compiled.get(), 0);
+
+        let (name, sfx) = stream.nth(2).unwrap();
+        assert_eq!(name.as_str(), "sfx_2");
+        assert!(sfx.is_ok());
+        assert_eq!(n_compiled.get(), 3);
+
+        let remaining: Vec<_> = stream.collect();
+        assert_eq!(remaining.len(), 997);
+        assert!(remaining.iter().all(|(_, r)| r.is_ok()));
+        assert_eq!(n_compiled.get(), 1000);
+    }
+}
@@ -78,6 +78,19 @@ const _: () = assert!(
 pub fn export_spc_file(
     common_audio_data: &CommonAudioData,
     song_data: &SongData,
+) -> Result<Vec<u8>, ExportSpcFileError> {
+    export_spc_file_with_title(
+        common_audio_data,
+        song_data,
+        song_data.metadata().title.as_deref(),
+    )
+}
+
+/// Export a `.spc` file, overriding the song's `#Title` metadata
+pub fn export_spc_file_with_title(
+    common_audio_data: &CommonAudioData,
+    song_data: &SongData,
+    title: Option<&str>,
 ) -> Result<Vec<u8>, ExportSpcFileError> {
     let song_data_addr = common_audio_data.song_data_addr();
 
@@ -127,7 +140,7 @@ pub fn export_spc_file(
         // ::TODO parse date from `metadata`::
 
         // Title of song
-        write_id666_tag(header, 0x2e, 0x20, metadata.title.as_deref());
+        write_id666_tag(header, 0x2e, 0x20, title);
         // Game title
         write_id666_tag(header, 0x4e, 0x20, metadata.game.as_deref());
         // Artist of song
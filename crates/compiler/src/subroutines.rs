@@ -19,6 +19,8 @@ pub struct Subroutine {
 pub enum FindSubroutineResult<'a> {
     Found(&'a SubroutineId),
     NotCompiled,
+    // The subroutine exists but was not compiled (used when validating MML)
+    Unchecked,
     Recussion,
     NotFound,
     NotAllowed,
@@ -0,0 +1,32 @@
+//! Test helpers shared with the tests of other crates
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A temporary directory that is deleted when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates a `tad-{name}-{process_id}` directory in the system's temporary directory.
+    ///
+    /// `name` must be unique within the test binary.
+    pub fn new(name: &str) -> Self {
+        let p = std::env::temp_dir().join(format!("tad-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&p).unwrap();
+        Self(p)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -39,11 +39,31 @@ u8_value_newtype!(Bpm, BpmOutOfRange, NoBpm, MIN_BPM, MAX_BPM);
 
 pub const DEFAULT_BPM: Bpm = Bpm(60);
 
+// Tempo scale percentage
+u8_value_newtype!(TempoScale, TempoScaleOutOfRange, NoTempoScale, 50, 200);
+
+pub const DEFAULT_TEMPO_SCALE: TempoScale = TempoScale(100);
+
+impl Default for TempoScale {
+    fn default() -> Self {
+        DEFAULT_TEMPO_SCALE
+    }
+}
+
+// Minimum number of ticks in a rest after a note
+u8_value_newtype!(MinRestTicks, MinRestTicksOutOfRange, NoMinRestTicks, 1, 8);
+
 impl Bpm {
     pub fn to_tick_clock(self) -> Result<TickClock, ValueError> {
-        let ticks_per_minute = CLOCK_CYCLES_PER_BPM * u32::from(self.as_u8());
+        self.to_scaled_tick_clock(DEFAULT_TEMPO_SCALE)
+    }
+
+    /// Converts the BPM to a tick clock after multiplying the BPM by `scale` percent
+    pub fn to_scaled_tick_clock(self, scale: TempoScale) -> Result<TickClock, ValueError> {
+        let ticks_per_minute =
+            CLOCK_CYCLES_PER_BPM * u32::from(self.as_u8()) * u32::from(scale.as_u8());
 
-        let tc = f64::from(TIMER_HZ * 60) / f64::from(ticks_per_minute);
+        let tc = f64::from(TIMER_HZ * 60 * 100) / f64::from(ticks_per_minute);
         let tc = tc.round();
 
         if tc >= 0.0 && tc < u32::MAX.into() {
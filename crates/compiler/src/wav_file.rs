@@ -0,0 +1,83 @@
+//! 16-bit PCM WAV file writer
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+const N_CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const BLOCK_ALIGN: u16 = N_CHANNELS * BITS_PER_SAMPLE / 8;
+
+const HEADER_SIZE: usize = 44;
+
+/// Creates a 16-bit stereo PCM WAV file.
+///
+/// `samples` are interleaved stereo samples.
+pub fn stereo_wav_file(samples: &[i16], sample_rate: u32) -> Result<Vec<u8>, String> {
+    let data_size = std::mem::size_of_val(samples);
+
+    let riff_size = u32::try_from(data_size + HEADER_SIZE - 8)
+        .map_err(|_| "too many samples for a WAV file".to_owned())?;
+    let data_size = riff_size - (HEADER_SIZE as u32 - 8);
+
+    let byte_rate = sample_rate * u32::from(BLOCK_ALIGN);
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + std::mem::size_of_val(samples));
+
+    out.extend(b"RIFF");
+    out.extend(riff_size.to_le_bytes());
+    out.extend(b"WAVE");
+
+    out.extend(b"fmt ");
+    out.extend(16u32.to_le_bytes());
+    out.extend(1u16.to_le_bytes()); // PCM
+    out.extend(N_CHANNELS.to_le_bytes());
+    out.extend(sample_rate.to_le_bytes());
+    out.extend(byte_rate.to_le_bytes());
+    out.extend(BLOCK_ALIGN.to_le_bytes());
+    out.extend(BITS_PER_SAMPLE.to_le_bytes());
+
+    out.extend(b"data");
+    out.extend(data_size.to_le_bytes());
+    for s in samples {
+        out.extend(s.to_le_bytes());
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stereo_wav_file() {
+        let wav = stereo_wav_file(&[1, -1, 0x1234, -0x1234], 32000).unwrap();
+
+        #[rustfmt::skip]
+        let expected: [u8; HEADER_SIZE + 8] = [
+            b'R', b'I', b'F', b'F', 44, 0, 0, 0,
+            b'W', b'A', b'V', b'E',
+            b'f', b'm', b't', b' ', 16, 0, 0, 0,
+            1, 0, // PCM
+            2, 0, // n_channels
+            0x00, 0x7d, 0x00, 0x00, // sample_rate
+            0x00, 0xf4, 0x01, 0x00, // byte_rate
+            4, 0, // block_align
+            16, 0, // bits_per_sample
+            b'd', b'a', b't', b'a', 8, 0, 0, 0,
+            0x01, 0x00, 0xff, 0xff, 0x34, 0x12, 0xcc, 0xed,
+        ];
+
+        assert_eq!(wav, expected);
+    }
+
+    #[test]
+    fn test_empty_stereo_wav_file() {
+        let wav = stereo_wav_file(&[], 32000).unwrap();
+
+        assert_eq!(wav.len(), HEADER_SIZE);
+        assert_eq!(wav[4..8], 36u32.to_le_bytes());
+        assert_eq!(wav[40..44], 0u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,168 @@
+//! Bytecode interpreter golden tests
+//!
+//! Processes the example project songs with the bytecode interpreter and compares the
+//! channel state checksums against known values.
+//!
+//! If a change to the bytecode interpreter intentionally changes the channel state,
+//! the expected checksums must be updated.
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use compiler::bytecode_interpreter::SongInterpreter;
+use compiler::common_audio_data::{build_common_audio_data, CommonAudioData};
+use compiler::data::{load_project_file, load_text_file_with_limit, validate_project_file_names};
+use compiler::mml::compile_mml;
+use compiler::samples::build_sample_and_instrument_data;
+use compiler::songs::SongData;
+use compiler::sound_effects::{blank_compiled_sound_effects, CompiledSfxSubroutines};
+use compiler::time::TickCounter;
+
+use std::path::Path;
+
+const STEREO_FLAG: bool = true;
+
+const EXAMPLE_PROJECT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../examples/example-project.terrificaudio"
+);
+
+/// Song name, checksum at the midpoint of the song, checksum at the end of the song
+#[rustfmt::skip]
+const EXPECTED_CHECKSUMS: &[(&str, u64, u64)] = &[
+    ("gimo_297", 0x7f45b2e268b57e74, 0xfe37aad7b8d8c74f),
+    ("ode_to_joy", 0x5f1e07509ff6cddd, 0x1fb1eb371fcd42e7),
+    ("chords", 0xd1eebf1a39c9bebd, 0x562b02c3d0cf0507),
+    ("chords_transpose", 0xd1eebf1a39c9bebd, 0x562b02c3d0cf0507),
+    ("test_broken_chord", 0xc78d03aef2e026e9, 0xabd5d0606f43d5bb),
+    ("quantize_with_temp_gain", 0xb8913be0563e17fb, 0xe8c18b7d7337d748),
+    ("early_release", 0xc7dc7784ae9241b2, 0x0723fc84a3f50d53),
+    ("invert", 0x505433cd74cbbf3d, 0x2e13e1358c2433cf),
+    ("test_echo", 0x8369bab03e94e5fa, 0xedaad4f9496f5c18),
+    ("test_set_echo_delay", 0xed2d785e9a2286f5, 0xbeb73ec5f45f2ad2),
+    ("test_fir", 0x5022d6de29577026, 0x463fd16e53a73fea),
+    ("test_envelope", 0x19e0e70fbdddc93f, 0x4c9219754d5916e9),
+    ("test_loop", 0x3dfd38cf60385c02, 0xf2e5276b7d0b3252),
+    ("test_loop_stack", 0x0caedbfef7e9bc33, 0xf9303db3e38922aa),
+    ("test_pan", 0xed36fd8d27ed8ab8, 0xda62e6214079e8f1),
+    ("test_portamento", 0xbe07455c2a797491, 0x79fe42cca979f416),
+    ("test_samples", 0x3956e7463f19c385, 0x4acd42a6b7e91501),
+    ("test_song_loop", 0xda552d0ce9308e56, 0x29cf4fb00772ae33),
+    ("test_subroutine_play_at_cursor", 0x592aeab3ed5de936, 0x8d2620e3ccf59ede),
+    ("tremolo", 0x40078201341865e6, 0xf444e587b812e21d),
+    ("test_vibrato", 0x0ec69d60c5cb4e75, 0x80b77017fa1c0070),
+    ("test_echo_invert", 0x57991be83c47fb61, 0x921608c4fc23860a),
+    ("test_bc_asm_in_mml", 0x200078cf95bd0f9d, 0x5b0618074505faf2),
+    ("pan_slide", 0x4b979dcf22e13fea, 0xeadcf5688931acad),
+    ("panbrello", 0x8792ff0efb9bd075, 0x86f2ba0ee1eb35d0),
+    ("pitch_modulation", 0x5ee4809723dc1b66, 0xaa2b0b358fc701e0),
+    ("play_noise", 0x4302773c721094d0, 0x1291d1d6889f5c3f),
+    ("play_pitch", 0x1fa6ff65cbc8dbbb, 0xaf96b5c8db31468b),
+    ("volume_slide", 0xea4c0f80dcc293fd, 0xe978cb98b350ae8d),
+    ("multi_channel_noise", 0x43d59c86646ba7cc, 0x6f1462488c99c1be),
+    ("detune", 0x572ddd1183e88964, 0x1a4fbdf5b21a9207),
+    ("volume_pop_bug", 0x1d5a325d7eb2ad8e, 0x1d3ea1b975f4bde0),
+    ("keyoff_scrn_bug", 0x396e9f2702d7b1a2, 0xb6821ae97f82a966),
+    ("envelope_race_condition", 0x13afe9786b2d1a99, 0x3657906b514829ef),
+    ("subroutine_vibrato_bug", 0x969fe0a9b014b131, 0x7057a50c279cd87a),
+    ("portamento_first_tick_bugtest", 0x8cdf4667a4d7d7b4, 0x50d89ed0dbde55d5),
+    ("tremolo_underflow_bug", 0x088c568b80a2d2fe, 0x059f15042d792a3c),
+    ("volume_slide_down_bug", 0x9bb292114ba497bf, 0x7a6e1afa65928b9c),
+    ("noise_and_sfx_test", 0xa0ab073ef9eb3dfc, 0x58df3bf325c50c7d),
+    ("two_channels_modify_timer", 0xd9abfdfd76893160, 0xac665f96c49957ce),
+    ("early_release_and_temp_gain", 0x4f13831564c89f90, 0x2c0326ddb2e45a06),
+    ("echo_stress_test", 0x1c77797e5749f772, 0x7cf2253f2dfcbd03),
+    ("adjust_echo_i8_limit", 0x1070f0d1da5bfba1, 0xccc87ebb7a349f5d),
+    ("large_skip_last_loop", 0x8df18037566368a5, 0x997a6d1c4ef51c2e),
+    ("unbalanced_vibrato_bug", 0x7f4b7fa4171f7a12, 0xbc519287c06bfb22),
+    ("vibrato_after_portamento", 0xf9a1ce36d895c25f, 0xcc831a01d3474ed8),
+];
+
+fn song_ticks(song: &SongData) -> TickCounter {
+    song.channels()
+        .iter()
+        .filter_map(|c| c.as_ref())
+        .map(|c| c.tick_counter)
+        .max()
+        .unwrap_or_default()
+}
+
+fn song_checksums(common_audio_data: &CommonAudioData, song: &SongData) -> (u64, u64) {
+    let end = song_ticks(song);
+    let midpoint = TickCounter::new(end.value() / 2);
+
+    let mut interpreter = SongInterpreter::new(common_audio_data, song, STEREO_FLAG);
+    let mid_checksum = interpreter.checksum_at_tick(midpoint);
+    let end_checksum = interpreter.checksum_at_tick(end);
+
+    (mid_checksum, end_checksum)
+}
+
+fn compile_example_project() -> (CommonAudioData, Vec<(String, SongData)>) {
+    let project = load_project_file(Path::new(EXAMPLE_PROJECT)).unwrap();
+    let project = validate_project_file_names(project).unwrap();
+
+    let samples = build_sample_and_instrument_data(&project).unwrap();
+
+    let common_audio_data = build_common_audio_data(
+        &samples,
+        &CompiledSfxSubroutines::blank(),
+        &blank_compiled_sound_effects(),
+    )
+    .unwrap();
+
+    let songs = project
+        .songs
+        .list()
+        .iter()
+        .map(|song| {
+            let mml_file = load_text_file_with_limit(&song.source, &project.parent_path).unwrap();
+            let song_data = compile_mml(
+                &mml_file,
+                Some(song.name.clone()),
+                &project.instruments_and_samples,
+                samples.pitch_table(),
+            )
+            .unwrap();
+
+            (song.name.as_str().to_owned(), song_data)
+        })
+        .collect();
+
+    (common_audio_data, songs)
+}
+
+#[test]
+fn checksums_are_deterministic() {
+    let (common_audio_data, songs) = compile_example_project();
+
+    for (name, song) in &songs {
+        assert_eq!(
+            song_checksums(&common_audio_data, song),
+            song_checksums(&common_audio_data, song),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn checksums_match_expected() {
+    let (common_audio_data, songs) = compile_example_project();
+
+    let checksums: Vec<(&str, u64, u64)> = songs
+        .iter()
+        .map(|(name, song)| {
+            let (mid, end) = song_checksums(&common_audio_data, song);
+            (name.as_str(), mid, end)
+        })
+        .collect();
+
+    // Printed on failure, to simplify updating `EXPECTED_CHECKSUMS`
+    println!();
+    for c in &checksums {
+        println!("    (\"{}\", {:#018x}, {:#018x}),", c.0, c.1, c.2);
+    }
+
+    assert_eq!(checksums, EXPECTED_CHECKSUMS);
+}
@@ -18,6 +18,17 @@ fn broken_chord() {
     assert_line_matches_line("{{c o3 e > g}}", "[c%1& : o3 e%1& > g%1&]8 o3 e%2");
     assert_line_matches_line("{{c _+2 e < g}}", "[c%1& : _+2 e%1& < g%1&]8 o4 e%2");
     assert_line_matches_line("{{c __-2 e g}}", "[c%1& : _-2 e%1& g%1&]8 e%2");
+
+    // Multi-octave broken chords
+    assert_line_matches_line("{{c > c g}}", "[o4 c%1& : o5 c%1& g%1&]8 o5 c%2");
+    assert_line_matches_line("{{c > c > c}}", "[o4 c%1& : o5 c%1& o6 c%1&]8 o5 c%2");
+    assert_line_matches_line("{{o5 c < g < c}}", "[o5 c%1& : o4 g%1& o3 c%1&]8 o4 g%2");
+
+    // Octave and transpose changes persist after the broken chord
+    assert_line_matches_line("{{c > c g}} c", "{{c > c g}} o5 c");
+    assert_line_matches_line("{{c > c < g}} c", "{{c > c < g}} o4 c");
+    assert_line_matches_line("{{c _+2 e}} c", "{{c _+2 e}} _+2 c");
+    assert_line_matches_line("{{c __-2 e > g}} c", "{{c __-2 e > g}} o5 _-2 c");
 }
 
 #[test]
@@ -76,3 +87,23 @@ fn pitch_list_errors() {
 }
 
 // ::TODO broken chord argument error tests::
+
+#[test]
+fn arpeggio() {
+    assert_line_matches_line("AR1,c,e,g", "{{ceg}}");
+    assert_line_matches_line("AR2,c,e,g 2", "{{ceg}}2,%2");
+    assert_line_matches_line("AR3,c,e-,g,>c 1", "{{ce-g>c}}1,%3");
+
+    // Octave and transpose
+    assert_line_matches_line("o3 AR1,c,e,g", "o3 {{ceg}}");
+    assert_line_matches_line("_+2 AR1,c,o5 e,g", "_+2 {{c o5 e g}}");
+    assert_line_matches_line("AR1,c,< g c", "{{c<g}} c");
+}
+
+#[test]
+fn arpeggio_errors() {
+    assert_one_error_in_mml_line("AR0,c,e", 1, ChannelError::ArpeggioSpeedZero);
+    assert_one_error_in_mml_line("AR,c,e", 1, ChannelError::NoArpeggioSpeed);
+    assert_one_error_in_mml_line("AR1,c,,e", 7, ChannelError::NoPitchInArpeggio);
+    assert_one_error_in_mml_line("AR1 c", 1, ChannelError::NoNotesInBrokenChord);
+}
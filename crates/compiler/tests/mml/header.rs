@@ -2,7 +2,13 @@
 //
 // SPDX-License-Identifier: MIT
 
+use compiler::driver_constants::addresses;
+use compiler::echo::{fir_preset_coefficients, largest_edl_without_overlap, FirPreset};
+use compiler::errors::CommonAudioDataError;
 use compiler::invert_flags::InvertFlags;
+use compiler::pitch_table::TUNING_TABLE_SIZE;
+use compiler::songs::{validate_echo_buffer, validate_song_size};
+use compiler::time::Bpm;
 use compiler::UnsignedValueNewType;
 
 use crate::*;
@@ -170,6 +176,47 @@ A r
     );
 }
 
+#[test]
+fn fir_filter_preset() {
+    let dummy_data = dummy_data();
+
+    let s = compile_mml(
+        r#"
+#FirFilter high_pass
+
+A r
+"#,
+        &dummy_data,
+    );
+    assert_eq!(
+        s.metadata().echo_buffer.fir.map(|c| c.as_i8()),
+        fir_preset_coefficients(FirPreset::HighPass)
+    );
+
+    let s = compile_mml(
+        r#"
+#FirFilter 64 -32 -16 -8 -4 -2 -1 -1
+
+A r
+"#,
+        &dummy_data,
+    );
+    assert_eq!(
+        s.metadata().echo_buffer.fir.map(|c| c.as_i8()),
+        fir_preset_coefficients(FirPreset::HighPass)
+    );
+
+    assert_one_header_error_in_mml(
+        r#"
+#FirFilter unknown
+
+A r
+"#,
+        2,
+        ValueError::UnknownFirPreset("unknown".to_owned()).into(),
+    );
+}
+
 #[test]
 fn max_edl() {
     let dummy_data = dummy_data();
@@ -210,3 +257,439 @@ A r
         .into(),
     );
 }
+
+#[test]
+fn echo_buffer_overlap() {
+    const RAM_SIZE: usize = compiler::driver_constants::AUDIO_RAM_SIZE;
+
+    let dummy_data = dummy_data();
+
+    let s = compile_mml(
+        r#"
+#EchoLength 240
+
+A r
+"#,
+        &dummy_data,
+    );
+
+    let (echo_start, echo_end, data_start, data_end) = match validate_echo_buffer(&s, 0x8000) {
+        Err(CommonAudioDataError::EchoBufferOverlap {
+            echo_start,
+            echo_end,
+            data_start,
+            data_end,
+        }) => (echo_start, echo_end, data_start, data_end),
+        r => panic!("expected EchoBufferOverlap error, got {r:?}"),
+    };
+    assert!(validate_song_size(&s, 0x8000).is_err());
+
+    assert_eq!(echo_start, RAM_SIZE - 15 * 2048);
+    assert_eq!(echo_end, RAM_SIZE);
+    assert_eq!(data_start, usize::from(addresses::COMMON_DATA));
+    assert!(data_end > echo_start);
+
+    let max_edl = largest_edl_without_overlap(data_end).unwrap();
+    let max_edl_size = usize::from(max_edl.buffer_size());
+    assert!(data_end + max_edl_size <= RAM_SIZE);
+    assert!(data_end + max_edl_size + 2048 > RAM_SIZE);
+
+    // The largest echo length fits
+    let s = compile_mml(
+        &format!("#EchoLength {}\n\nA r\n", max_edl.to_length().value()),
+        &dummy_data,
+    );
+    assert!(validate_echo_buffer(&s, 0x8000).is_ok());
+    assert!(validate_song_size(&s, 0x8000).is_ok());
+
+    // Song does not fit without an echo buffer
+    let e = validate_echo_buffer(&s, RAM_SIZE).unwrap_err();
+    assert!(e
+        .to_string()
+        .ends_with("the data does not fit in audio-RAM without an echo buffer"));
+}
+
+#[test]
+fn max_channel_bytes() {
+    let dummy_data = dummy_data();
+
+    let s = compile_mml(
+        r#"
+#MaxChannelBytes 6
+@1 dummy_instrument
+
+A @1 o4 c d
+"#,
+        &dummy_data,
+    );
+    assert_eq!(s.metadata().max_channel_bytes, Some(6));
+
+    assert_one_error_in_channel_a_mml(
+        r#"
+#MaxChannelBytes 4
+@1 dummy_instrument
+
+A @1 o4 c d e
+"#,
+        11,
+        ChannelError::ChannelBytecodeTooBig {
+            limit: 4,
+            current: 6,
+        },
+    );
+
+    assert_one_header_error_in_mml(
+        "#MaxChannelBytes 0\nA r",
+        1,
+        ValueError::MaxChannelBytesOutOfRange(0).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        "#MaxChannelBytes 53249\nA r",
+        1,
+        ValueError::MaxChannelBytesOutOfRange(53249).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        "#MaxChannelBytes 5000000000\nA r",
+        1,
+        ValueError::CannotParseUnsigned("5000000000".to_owned()).into(),
+    );
+}
+
+#[test]
+fn tempo_scale() {
+    let tc = |bpm: u32| {
+        let tc = f64::round(8000.0 * 60.0 / f64::from(48 * bpm)) as u32;
+        format!("set_song_tick_clock {tc}")
+    };
+
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#Tempo 100
+#TempoScale 50
+
+A t120
+"#,
+        &[&tc(60)],
+    );
+
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#TempoScale 200
+
+A t40
+"#,
+        &[&tc(80)],
+    );
+
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#TempoScale 150
+
+!s t60
+
+A !s
+"#,
+        &["call_subroutine s"],
+    );
+    assert_mml_subroutine_matches_bytecode(
+        r#"
+#TempoScale 150
+
+!s t60
+
+A !s
+"#,
+        0,
+        &[&tc(90)],
+    );
+
+    // `T` is not scaled
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#Tempo 100
+#TempoScale 50
+
+A T100
+"#,
+        &["set_song_tick_clock 100"],
+    );
+}
+
+#[test]
+fn tempo_scale_header_tempo() {
+    let dummy_data = dummy_data();
+
+    let tc = |bpm: u8| {
+        Bpm::try_from(u32::from(bpm))
+            .unwrap()
+            .to_tick_clock()
+            .unwrap()
+    };
+
+    let s = compile_mml(
+        r#"
+#Tempo 80
+#TempoScale 50
+
+A r
+"#,
+        &dummy_data,
+    );
+    assert_eq!(s.metadata().tick_clock, tc(40));
+
+    // Default tempo is scaled
+    let s = compile_mml(
+        r#"
+#TempoScale 200
+
+A r
+"#,
+        &dummy_data,
+    );
+    assert_eq!(s.metadata().tick_clock, tc(120));
+
+    // `#Timer` is not scaled
+    let s = compile_mml(
+        r#"
+#Timer 100
+#TempoScale 50
+
+A r
+"#,
+        &dummy_data,
+    );
+    assert_eq!(s.metadata().tick_clock, 100u32.try_into().unwrap());
+
+    assert_one_header_error_in_mml(
+        r#"
+#TempoScale 49
+
+A r
+"#,
+        2,
+        ValueError::TempoScaleOutOfRange(49).into(),
+    );
+    assert_one_header_error_in_mml(
+        r#"
+#TempoScale 201
+
+A r
+"#,
+        2,
+        ValueError::TempoScaleOutOfRange(201).into(),
+    );
+
+    // The default tempo (60 BPM) cannot be halved
+    assert_one_header_error_in_mml(
+        r#"
+#TempoScale 50
+
+A r
+"#,
+        2,
+        ValueError::TickClockOutOfRange(333).into(),
+    );
+}
+
+#[test]
+fn min_rest_ticks_header() {
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#MinRestTicks 4
+
+@1 dummy_instrument
+
+A @1 c r%2 d r%4 e r%6
+"#,
+        &[
+            "set_instrument dummy_instrument",
+            "play_note c4 24",
+            "rest 4",
+            "play_note d4 24",
+            "rest 4",
+            "play_note e4 24",
+            "rest 6",
+        ],
+    );
+
+    // Short rests not after a note are not padded
+    assert_mml_channel_a_matches_bytecode(
+        r#"
+#MinRestTicks 8
+
+@1 dummy_instrument
+
+A r%2 @1 c%2 & d r%1
+"#,
+        &[
+            "rest 2",
+            "set_instrument dummy_instrument",
+            "play_note c4 no_keyoff 2",
+            "play_note d4 24",
+            "rest 8",
+        ],
+    );
+
+    // Tick counter includes the padding
+    let s = compile_mml(
+        r#"
+#MinRestTicks 6
+
+@1 dummy_instrument
+
+A @1 c r%1 d r%1
+"#,
+        &dummy_data(),
+    );
+    assert_eq!(s.channels()[0].as_ref().unwrap().tick_counter.value(), 60);
+
+    assert_one_header_error_in_mml(
+        r#"
+#MinRestTicks 0
+
+A r
+"#,
+        2,
+        ValueError::MinRestTicksOutOfRange(0).into(),
+    );
+    assert_one_header_error_in_mml(
+        r#"
+#MinRestTicks 9
+
+A r
+"#,
+        2,
+        ValueError::MinRestTicksOutOfRange(9).into(),
+    );
+}
+
+#[test]
+fn tuning_table() {
+    let tuning_table = |f: &dyn Fn(usize) -> &'static str| -> String {
+        (0..TUNING_TABLE_SIZE).map(f).collect::<Vec<_>>().join(" ")
+    };
+
+    // c4 is MIDI note 60
+    let tt = tuning_table(&|i| match i {
+        60 => "1000",
+        62 => "250",
+        64 => "875",
+        _ => "500",
+    });
+
+    assert_mml_channel_a_matches_bytecode(
+        &format!(
+            r#"
+#TuningTable {tt}
+
+@1 dummy_instrument
+
+A @1 c d e
+"#
+        ),
+        &[
+            "set_instrument dummy_instrument",
+            "play_pitch $2000 24",
+            "play_pitch $0800 24",
+            "play_pitch $1c00 24",
+        ],
+    );
+
+    // Portamento and broken chord notes are also tuned
+    let dummy_data = dummy_data();
+    let s1 = compile_mml(
+        &format!("#TuningTable {tt}\n@1 dummy_instrument\nA @1 {{c g}} {{{{ce}}}}2,8"),
+        &dummy_data,
+    );
+    let s2 = compile_mml(
+        "@1 dummy_instrument\nA @1 {PF1000 PF500} {{PF1000 PF875}}2,8",
+        &dummy_data,
+    );
+    assert_eq!(mml_bytecode(&s1), mml_bytecode(&s2));
+
+    assert_one_error_in_channel_a_mml(
+        &format!(
+            "#TuningTable {}\n@1 dummy_instrument\nA @1 c",
+            tuning_table(&|_| "4000")
+        ),
+        6,
+        ValueError::CannotConvertTuningTableFrequency(note("c4"), 0x8000).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        "#TuningTable 440 440 440\n\nA r",
+        1,
+        ValueError::InvalidTuningTableSize(3).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        &format!(
+            "#TuningTable {}\n\nA r",
+            tuning_table(&|i| if i == 5 { "0" } else { "440" })
+        ),
+        1,
+        ValueError::InvalidTuningTableFrequency(5, "0".to_owned()).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        &format!(
+            "#TuningTable {}\n\nA r",
+            tuning_table(&|i| if i == 127 { "16001" } else { "440" })
+        ),
+        1,
+        ValueError::InvalidTuningTableFrequency(127, "16001".to_owned()).into(),
+    );
+}
+
+#[test]
+fn tuning() {
+    let dummy_data = dummy_data();
+
+    // Equal temperament with A4 at 415 Hz
+    let s1 = compile_mml(
+        "#Tuning 415\n@1 dummy_instrument\nA @1 o4 a > a {a <a} {{a >a}}2,8",
+        &dummy_data,
+    );
+    let s2 = compile_mml(
+        "@1 dummy_instrument\nA @1 PF415 PF830 {PF830 PF415} {{PF415 PF830}}2,8",
+        &dummy_data,
+    );
+    assert_eq!(mml_bytecode(&s1), mml_bytecode(&s2));
+
+    // `#Tuning` is the same as an equal temperament `#TuningTable`
+    let tt: Vec<String> = (0..TUNING_TABLE_SIZE)
+        .map(|i| format!("{}", 440.0 * f64::powf(2.0, (i as f64 - 69.0) / 12.0)))
+        .collect();
+    let s1 = compile_mml(
+        "#Tuning 440\n@1 dummy_instrument\nA @1 c d e f g a b > c",
+        &dummy_data,
+    );
+    let s2 = compile_mml(
+        &format!(
+            "#TuningTable {}\n@1 dummy_instrument\nA @1 c d e f g a b > c",
+            tt.join(" ")
+        ),
+        &dummy_data,
+    );
+    assert_eq!(mml_bytecode(&s1), mml_bytecode(&s2));
+
+    assert_one_header_error_in_mml(
+        "#Tuning 300\n\nA r",
+        1,
+        ValueError::InvalidConcertPitch("300".to_owned()).into(),
+    );
+
+    assert_one_header_error_in_mml(
+        &format!("#Tuning 440\n#TuningTable {}\n\nA r", tt.join(" ")),
+        2,
+        MmlLineError::CannotSetTuningTable,
+    );
+
+    assert_one_header_error_in_mml(
+        &format!("#TuningTable {}\n#Tuning 440\n\nA r", tt.join(" ")),
+        2,
+        MmlLineError::CannotSetTuning,
+    );
+}
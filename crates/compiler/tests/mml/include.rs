@@ -0,0 +1,231 @@
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::*;
+
+use std::path::PathBuf;
+
+/// Writes the files to a new temporary directory and returns the path of the first file
+fn write_files(test_name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "tad-include-test-{}-{}",
+        std::process::id(),
+        test_name
+    ));
+
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    dir.join(files[0].0)
+}
+
+fn compile_file(path: PathBuf) -> Result<SongData, SongError> {
+    let dummy_data = dummy_data();
+
+    let tf = data::load_text_file_with_limit_path(&path).unwrap();
+    let r = mml::compile_mml(
+        &tf,
+        None,
+        &dummy_data.instruments_and_samples,
+        &dummy_data.pitch_table,
+    );
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    r
+}
+
+fn mml_errors(r: Result<SongData, SongError>) -> Box<compiler::errors::MmlCompileErrors> {
+    match r {
+        Err(SongError::MmlError(e)) => e,
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn test_include() {
+    let path = write_files(
+        "include",
+        &[
+            (
+                "song.mml",
+                "#include \"parts/instruments.mml\"\nA @0 !s e\n",
+            ),
+            (
+                "parts/instruments.mml",
+                "@0 dummy_instrument\n#include \"s.mml\" ; comment\n",
+            ),
+            ("parts/s.mml", "!s c d\n"),
+        ],
+    );
+
+    let sd = compile_file(path).unwrap();
+
+    let dd = dummy_data();
+    let expected = compile_mml("@0 dummy_instrument\n!s c d\nA @0 !s e\n", &dd);
+
+    assert_eq!(sd.data(), expected.data());
+}
+
+#[test]
+fn test_error_in_included_file() {
+    let path = write_files(
+        "error_in_included_file",
+        &[
+            (
+                "song.mml",
+                "@0 dummy_instrument\n\n#include \"a.mml\"\nA @0 c\nA d\n",
+            ),
+            ("a.mml", "\nA @0 c\nA c `\n"),
+        ],
+    );
+
+    let e = mml_errors(compile_file(path));
+
+    assert_eq!(e.channel_errors.len(), 1);
+    let errors = &e.channel_errors[0].errors;
+    assert_eq!(errors.len(), 1);
+
+    assert_eq!(
+        e.location(&errors[0].0).to_string(),
+        "a.mml:3:5 (included from song.mml:3)"
+    );
+
+    let m = e.include_map.source_location(8).unwrap();
+    assert_eq!(m.file_name, "song.mml");
+    assert_eq!(m.line_number, 5);
+    assert_eq!(m.include_site, None);
+}
+
+#[test]
+fn test_circular_include() {
+    let path = write_files(
+        "circular_include",
+        &[
+            ("a.mml", "#include \"b.mml\"\nA @0 c\n"),
+            ("b.mml", "@0 dummy_instrument\n#include \"a.mml\"\n"),
+        ],
+    );
+
+    let e = mml_errors(compile_file(path));
+
+    assert_eq!(e.line_errors.len(), 1);
+    assert_eq!(
+        e.line_errors[0].1,
+        MmlLineError::CircularInclude("a.mml".to_owned())
+    );
+    assert_eq!(
+        e.line_error_location(&e.line_errors[0].0).to_string(),
+        "b.mml:2 (included from a.mml:1)"
+    );
+}
+
+#[test]
+fn test_include_errors() {
+    let path = write_files(
+        "include_errors",
+        &[(
+            "song.mml",
+            "@0 dummy_instrument\n#include missing.mml\n#include \"missing.mml\"\nA @0 c\n",
+        )],
+    );
+
+    let e = mml_errors(compile_file(path));
+
+    assert_eq!(e.line_errors.len(), 2);
+    assert_eq!(e.line_errors[0].1, MmlLineError::NoIncludePath);
+    assert_eq!(e.line_errors[0].0.line_number(), 2);
+    assert!(matches!(
+        e.line_errors[1].1,
+        MmlLineError::CannotLoadInclude(_)
+    ));
+    assert_eq!(e.line_errors[1].0.line_number(), 3);
+}
+
+#[test]
+fn test_include_without_path() {
+    let dummy_data = dummy_data();
+
+    let r = mml::compile_mml(
+        &TextFile {
+            contents: "#include \"a.mml\"\n@0 dummy_instrument\nA @0 c".to_owned(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        None,
+        &dummy_data.instruments_and_samples,
+        &dummy_data.pitch_table,
+    );
+
+    let e = mml_errors(r);
+    assert_eq!(e.line_errors.len(), 1);
+    assert_eq!(e.line_errors[0].1, MmlLineError::IncludeInFileWithoutPath);
+}
+
+#[test]
+fn test_root_file_pos() {
+    let path = write_files(
+        "root_file_pos",
+        &[
+            (
+                "song.mml",
+                "@0 dummy_instrument\n#include \"a.mml\"\nA @0 c `\n",
+            ),
+            ("a.mml", "A `\n"),
+        ],
+    );
+
+    let e = mml_errors(compile_file(path));
+
+    assert_eq!(e.channel_errors.len(), 1);
+    let errors = &e.channel_errors[0].errors;
+    assert_eq!(errors.len(), 2);
+
+    // Error in the included file is mapped to the `#include` directive
+    let p = e.include_map.to_root_file_pos(&errors[0].0);
+    assert_eq!(p.line_number(), 2);
+    assert_eq!(p.index_start(), 20);
+    assert_eq!(p.index_end(), 36);
+
+    let p = e.include_map.to_root_file_pos(&errors[1].0);
+    assert_eq!(errors[1].0.index_start(), 48);
+    assert_eq!(p.line_number(), 3);
+    assert_eq!(p.line_char(), errors[1].0.line_char());
+    assert_eq!(p.index_start(), 44);
+    assert_eq!(p.index_end(), 45);
+
+    assert!(!e.include_map.is_in_root_file(errors[0].0.index_start()));
+    assert!(e.include_map.is_in_root_file(errors[1].0.index_start()));
+}
+
+#[test]
+fn test_included_files() {
+    let path = write_files(
+        "included_files",
+        &[
+            ("song.mml", "#include \"a.mml\"\n#include \"missing.mml\"\n"),
+            ("a.mml", "#include \"parts/b.mml\"\n"),
+            ("parts/b.mml", "A c\n"),
+        ],
+    );
+
+    let tf = data::load_text_file_with_limit_path(&path).unwrap();
+    let files = mml::included_files(&tf);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let dir = path.parent().unwrap();
+    assert_eq!(
+        files,
+        [
+            dir.join("a.mml"),
+            dir.join("parts/b.mml"),
+            dir.join("missing.mml")
+        ]
+    );
+}
@@ -0,0 +1,352 @@
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::*;
+
+use compiler::errors::IdentifierError;
+use compiler::mml::MAX_MACRO_DEPTH;
+
+fn assert_mml_matches_mml(mml: &str, expected: &str) {
+    let dd = dummy_data();
+
+    let sd1 = compile_mml(mml, &dd);
+    let sd2 = compile_mml(expected, &dd);
+
+    assert_eq!(
+        sd1.data(),
+        sd2.data(),
+        "Testing {mml:?} against {expected:?}"
+    );
+}
+
+/// Asserts there is a single error in a macro expansion in channel A.
+///
+/// `definition_line` is the line number of the macro's `#define`.
+/// Returns the error message.
+fn assert_one_error_in_macro(
+    mml: &str,
+    line_char: u32,
+    expected_error: ChannelError,
+    macro_name: &str,
+    definition_line: u32,
+) -> String {
+    let dummy_data = dummy_data();
+
+    let r = mml::compile_mml(
+        &TextFile {
+            contents: mml.to_string(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        None,
+        &dummy_data.instruments_and_samples,
+        &dummy_data.pitch_table,
+    );
+
+    let errors = match &r {
+        Err(SongError::MmlError(e))
+            if e.channel_errors.len() == 1
+                && e.line_errors.is_empty()
+                && e.subroutine_errors.is_empty() =>
+        {
+            &e.channel_errors[0]
+        }
+        _ => panic!("expected a single channel error\nInput: {mml:?}\nResult: {r:?}"),
+    };
+    assert_eq!(errors.identifier.as_str(), "A");
+    assert_eq!(errors.errors.len(), 1, "Input: {mml:?}");
+
+    let pos = &errors.errors[0].0;
+    assert_eq!(pos.line_char(), line_char, "Input: {mml:?}");
+
+    let e = &errors.errors[0].1;
+    match e {
+        ChannelError::InMacro {
+            error,
+            name,
+            definition,
+        } => {
+            assert_eq!(**error, expected_error, "Input: {mml:?}");
+            assert_eq!(name, macro_name, "Input: {mml:?}");
+            assert_eq!(definition.line_number(), definition_line, "Input: {mml:?}");
+        }
+        e => panic!("expected an InMacro error, got {e:?}\nInput: {mml:?}"),
+    }
+
+    e.to_string()
+}
+
+#[test]
+fn test_macro() {
+    assert_mml_matches_mml(
+        r#"
+#define VIB ~20,3 v12
+@0 dummy_instrument
+A @0 \VIB c d \VIB
+"#,
+        r#"
+@0 dummy_instrument
+A @0 ~20,3 v12 c d ~20,3 v12
+"#,
+    );
+
+    // Macros can be used before they are defined
+    assert_mml_matches_mml(
+        r#"
+@0 dummy_instrument
+A @0 \N
+#define N c4 ; comment
+"#,
+        r#"
+@0 dummy_instrument
+A @0 c4
+"#,
+    );
+}
+
+#[test]
+fn test_macro_with_parameters() {
+    assert_mml_matches_mml(
+        r#"
+#define CHORD(a,b,c) [a%1& b%1& c%1&]8 a%2
+@0 dummy_instrument
+A @0 \CHORD(c,e,g) \CHORD(d, f+, a)
+"#,
+        r#"
+@0 dummy_instrument
+A @0 [c%1& e%1& g%1&]8 c%2 [d%1& f+%1& a%1&]8 d%2
+"#,
+    );
+
+    // Parameters are only substituted as whole words
+    assert_mml_matches_mml(
+        r#"
+#define N(e) e8 e
+@0 dummy_instrument
+A @0 \N(c) \N(d)
+"#,
+        r#"
+@0 dummy_instrument
+A @0 e8 c e8 d
+"#,
+    );
+}
+
+#[test]
+fn test_nested_macros() {
+    assert_mml_matches_mml(
+        r#"
+#define A(n) \B(n) \B(n)
+#define B(n) o n c d
+@0 dummy_instrument
+A @0 \A(3) \A(5)
+"#,
+        r#"
+@0 dummy_instrument
+A @0 o3 c d o3 c d o5 c d o5 c d
+"#,
+    );
+
+    // Macro call in an argument
+    assert_mml_matches_mml(
+        r#"
+#define T(x) x x x
+#define N c8
+@0 dummy_instrument
+A @0 \T(\N)
+"#,
+        r#"
+@0 dummy_instrument
+A @0 c8 c8 c8
+"#,
+    );
+
+    assert_mml_matches_mml(
+        r#"
+#define D(x) x x
+@0 dummy_instrument
+A @0 \D(\D(c8))
+"#,
+        r#"
+@0 dummy_instrument
+A @0 c8 c8 c8 c8
+"#,
+    );
+}
+
+#[test]
+fn test_macro_subroutine_and_instrument() {
+    assert_mml_matches_mml(
+        r#"
+#define S(i, s) @i !s
+@0 dummy_instrument
+!s1 c
+A \S(0, s1)
+"#,
+        r#"
+@0 dummy_instrument
+!s1 c
+A @0 !s1
+"#,
+    );
+}
+
+#[test]
+fn test_error_position_is_call_site() {
+    assert_one_error_in_macro(
+        "#define E c d `` e\n@0 dummy_instrument\nA @0 c \\E",
+        8,
+        ChannelError::UnknownCharacters(2),
+        "E",
+        1,
+    );
+
+    assert_one_error_in_macro(
+        "#define E(x) c x\n@0 dummy_instrument\nA @0 c \\E(d ``)",
+        8,
+        ChannelError::UnknownCharacters(2),
+        "E",
+        1,
+    );
+}
+
+#[test]
+fn test_error_shows_macro_definition() {
+    let e = assert_one_error_in_macro(
+        "@0 dummy_instrument\nA @0 c \\E d\n\n#define E c d `` e",
+        8,
+        ChannelError::UnknownCharacters(2),
+        "E",
+        4,
+    );
+    assert_eq!(e, r"2 unknown characters (in \E, defined on line 4)");
+}
+
+#[test]
+fn test_macro_call_errors() {
+    assert_one_error_in_macro(
+        "#define E(a,b) a b\n@0 dummy_instrument\nA @0 c \\E",
+        8,
+        ChannelError::MissingMacroArguments("E".to_owned()),
+        "E",
+        1,
+    );
+
+    assert_one_error_in_macro(
+        "#define E(a,b) a b\n@0 dummy_instrument\nA @0 c \\E(c,d",
+        8,
+        ChannelError::MissingMacroArgumentsEnd("E".to_owned()),
+        "E",
+        1,
+    );
+
+    assert_one_error_in_macro(
+        "#define E(a,b) a b\n@0 dummy_instrument\nA @0 c \\E(c,d,e)",
+        8,
+        ChannelError::InvalidNumberOfMacroArguments(2, 3),
+        "E",
+        1,
+    );
+
+    assert_one_error_in_macro(
+        "#define E(a,b) a b\n@0 dummy_instrument\nA @0 c \\E(c)",
+        8,
+        ChannelError::InvalidNumberOfMacroArguments(2, 1),
+        "E",
+        1,
+    );
+}
+
+#[test]
+fn test_asm_in_macro() {
+    assert_one_error_in_macro(
+        "#define E \\asm { enable_echo }\n@0 dummy_instrument\nA @0 c \\E",
+        8,
+        ChannelError::AsmInMacro,
+        "E",
+        1,
+    );
+}
+
+#[test]
+fn test_recursive_macro() {
+    assert_one_error_in_macro(
+        "#define R c \\R\n@0 dummy_instrument\nA @0 c \\R",
+        8,
+        ChannelError::RecursiveMacro("R".to_owned()),
+        "R",
+        1,
+    );
+
+    assert_one_error_in_macro(
+        "#define R1 c \\R2\n#define R2 d \\R1\n@0 dummy_instrument\nA @0 c \\R1",
+        8,
+        ChannelError::RecursiveMacro("R1".to_owned()),
+        "R1",
+        1,
+    );
+}
+
+#[test]
+fn test_max_macro_depth() {
+    let defines = |n_macros: usize| {
+        let mut mml: String = (1..n_macros)
+            .map(|i| format!("#define M{} \\M{}\n", i, i + 1))
+            .collect();
+        mml += &format!("#define M{n_macros} c\n");
+        mml
+    };
+
+    assert_mml_matches_mml(
+        &(defines(MAX_MACRO_DEPTH) + "@0 dummy_instrument\nA @0 \\M1"),
+        "@0 dummy_instrument\nA @0 c",
+    );
+
+    assert_one_error_in_macro(
+        &(defines(MAX_MACRO_DEPTH + 1) + "@0 dummy_instrument\nA @0 c \\M1"),
+        8,
+        ChannelError::MacroTooDeep(format!("M{}", MAX_MACRO_DEPTH + 1)),
+        "M1",
+        1,
+    );
+}
+
+#[test]
+fn test_macro_definition_errors() {
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define\nA @0 c",
+        2,
+        MmlLineError::NoMacroName,
+    );
+
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define 1a c\nA @0 c",
+        2,
+        MmlLineError::InvalidIdentifier(IdentifierError::InvalidName("1a".to_owned())),
+    );
+
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define evol c\nA @0 c",
+        2,
+        MmlLineError::MacroNameIsCommand("evol".to_owned()),
+    );
+
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define M(a, b c\nA @0 c",
+        2,
+        MmlLineError::MissingMacroParametersEnd,
+    );
+
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define M(a, a) c\nA @0 c",
+        2,
+        MmlLineError::DuplicateMacroParameter("a".to_owned()),
+    );
+
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n#define M c\n#define M d\nA @0 c",
+        3,
+        MmlLineError::DuplicateMacro("M".to_owned()),
+    );
+}
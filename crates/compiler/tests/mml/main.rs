@@ -12,8 +12,11 @@ mod detune;
 mod early_release;
 mod echo;
 mod header;
+mod include;
 mod instruments_and_envelope;
 mod loops;
+mod macros;
+mod midi_export;
 mod misc_instructions;
 mod notes;
 mod parsing;
@@ -21,9 +24,11 @@ mod pitch;
 mod portamento;
 mod quantize;
 mod rest_wait;
+mod sections;
 mod song_loop_point;
 mod subroutines;
 mod temp_gain;
+mod validate;
 mod vibrato;
 mod volume_pan;
 
@@ -37,7 +42,7 @@ use compiler::echo::EchoEdl;
 use compiler::envelope::{Adsr, Envelope, Gain};
 use compiler::errors::{BytecodeError, ChannelError, MmlLineError, SongError, ValueError};
 use compiler::mml;
-use compiler::notes::{Note, Octave};
+use compiler::notes::{Note, NoteRange, Octave};
 use compiler::pitch_table::{
     build_pitch_table, InstrumentHintFreq, PitchTable, PlayPitchFrequency,
 };
@@ -623,8 +628,13 @@ fn dummy_instrument(
         loop_setting: data::LoopSetting::LoopWithFilter(0),
         evaluator: Default::default(),
         ignore_gaussian_overflow: false,
-        first_octave: Octave::try_new(first_octave).unwrap(),
-        last_octave: Octave::try_new(last_octave).unwrap(),
+        auto_detect_loop: false,
+        dc_offset_removal: false,
+        normalize: false,
+        note_range: NoteRange {
+            first: Octave::try_new(first_octave).unwrap(),
+            last: Octave::try_new(last_octave).unwrap(),
+        },
         envelope,
         comment: None,
     }
@@ -0,0 +1,214 @@
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::*;
+
+use compiler::errors::MidiExportError;
+use compiler::midi_export::export_mml_to_midi;
+
+fn export_midi(mml: &str) -> Result<Vec<u8>, MidiExportError> {
+    let dd = dummy_data();
+
+    export_mml_to_midi(
+        &TextFile {
+            contents: mml.to_string(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        &dd.instruments_and_samples,
+        &dd.pitch_table,
+    )
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+    u16::from_be_bytes(data[..2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes(data[..4].try_into().unwrap())
+}
+
+fn read_variable_length(data: &[u8], pos: &mut usize) -> u32 {
+    let mut value = 0;
+    loop {
+        let b = data[*pos];
+        *pos += 1;
+        value = (value << 7) | u32::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+/// Returns the absolute tick and bytes of every event in every track
+fn midi_tracks(midi: &[u8]) -> Vec<Vec<(u32, Vec<u8>)>> {
+    let n_tracks = read_u16(&midi[10..]);
+
+    let mut tracks = Vec::new();
+    let mut pos = 14;
+
+    for _ in 0..n_tracks {
+        assert_eq!(&midi[pos..pos + 4], b"MTrk");
+        let len = read_u32(&midi[pos + 4..]) as usize;
+        let data = &midi[pos + 8..pos + 8 + len];
+        pos += 8 + len;
+
+        let mut events = Vec::new();
+        let mut tick = 0;
+        let mut p = 0;
+        while p < data.len() {
+            tick += read_variable_length(data, &mut p);
+
+            let start = p;
+            match data[p] {
+                0xff => {
+                    p += 2;
+                    let len = read_variable_length(data, &mut p) as usize;
+                    p += len;
+                }
+                0xc0..=0xcf => p += 2,
+                _ => p += 3,
+            }
+            events.push((tick, data[start..p].to_vec()));
+        }
+        tracks.push(events);
+    }
+
+    assert_eq!(pos, midi.len());
+
+    tracks
+}
+
+fn note_events(track: &[(u32, Vec<u8>)]) -> Vec<(u32, Vec<u8>)> {
+    track
+        .iter()
+        .filter(|(_, e)| matches!(e[0], 0x80..=0x9f | 0xc0..=0xcf))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn test_midi_header() {
+    let midi = export_midi("#ZenLen 96\n@0 dummy_instrument\nA @0 o4 c\nB @0 o4 e\n").unwrap();
+
+    assert_eq!(&midi[0..4], b"MThd");
+    assert_eq!(read_u32(&midi[4..]), 6);
+    // Type-1 MIDI file
+    assert_eq!(read_u16(&midi[8..]), 1);
+    // Tempo track + 2 channels
+    assert_eq!(read_u16(&midi[10..]), 3);
+    // Ticks per quarter note
+    assert_eq!(read_u16(&midi[12..]), 24);
+
+    let tracks = midi_tracks(&midi);
+    assert_eq!(tracks.len(), 3);
+
+    for t in &tracks {
+        assert_eq!(t.last().unwrap().1, [0xff, 0x2f, 0]);
+    }
+
+    assert!(tracks[0].iter().any(|(_, e)| e[..2] == [0xff, 0x51]));
+}
+
+/// The MIDI program is the instrument's index in the project
+#[test]
+fn test_midi_notes() {
+    let midi =
+        export_midi("@0 dummy_instrument\n@1 f1000_o4\nA @0 o4 c8 r8 d+8 @1 a4\nB @1 o4 c8\n")
+            .unwrap();
+
+    let tracks = midi_tracks(&midi);
+    assert_eq!(tracks.len(), 3);
+
+    assert_eq!(
+        note_events(&tracks[1]),
+        [
+            (0, vec![0xc0, 0]),
+            (0, vec![0x90, 60, 100]),
+            (12, vec![0x80, 60, 0]),
+            (24, vec![0x90, 63, 100]),
+            (36, vec![0x80, 63, 0]),
+            (36, vec![0xc0, 5]),
+            (36, vec![0x90, 69, 100]),
+            (60, vec![0x80, 69, 0]),
+        ]
+    );
+
+    assert_eq!(
+        note_events(&tracks[2]),
+        [
+            (0, vec![0xc1, 5]),
+            (0, vec![0x91, 60, 100]),
+            (12, vec![0x81, 60, 0]),
+        ]
+    );
+}
+
+#[test]
+fn test_midi_slurred_notes() {
+    let midi = export_midi("@0 dummy_instrument\nA @0 o4 c8 & d8\n").unwrap();
+    let tracks = midi_tracks(&midi);
+
+    assert_eq!(
+        note_events(&tracks[1]),
+        [
+            (0, vec![0xc0, 0]),
+            (0, vec![0x90, 60, 100]),
+            (12, vec![0x80, 60, 0]),
+            (12, vec![0x90, 62, 100]),
+            (24, vec![0x80, 62, 0]),
+        ]
+    );
+}
+
+#[test]
+fn test_midi_export_error() {
+    assert!(matches!(
+        export_midi("A @0 c"),
+        Err(MidiExportError::SongError(_))
+    ));
+}
+
+/// Tempo changes inside subroutines are exported at the tick they occur
+#[test]
+fn test_midi_tempo_changes() {
+    let midi = export_midi(
+        "#Timer 100\n!s r%5 T180 r%5\n@0 dummy_instrument\nA @0 c%10 T150 r%20 T200 r%5\nB r%15 !s T120 r%15\n",
+    )
+    .unwrap();
+    let tracks = midi_tracks(&midi);
+
+    let tempo = |tick: u32, timer: u32| {
+        let us_per_quarter = 24 * timer * 125;
+        let mut e = vec![0xff, 0x51, 3];
+        e.extend_from_slice(&us_per_quarter.to_be_bytes()[1..]);
+        (tick, e)
+    };
+
+    let tempo_events: Vec<_> = tracks[0]
+        .iter()
+        .filter(|(_, e)| e[..2] == [0xff, 0x51])
+        .cloned()
+        .collect();
+
+    assert_eq!(
+        tempo_events,
+        [
+            tempo(0, 100),
+            tempo(10, 150),
+            tempo(20, 180),
+            tempo(25, 120),
+            tempo(30, 200),
+        ]
+    );
+
+    assert_eq!(
+        note_events(&tracks[1]),
+        [
+            (0, vec![0xc0, 0]),
+            (0, vec![0x90, 60, 100]),
+            (10, vec![0x80, 60, 0]),
+        ]
+    );
+}
@@ -84,3 +84,41 @@ fn utf8_in_mml() {
     assert_eq!("𝅘𝅥𝅮".len(), 4);
     assert_one_error_in_mml_line("𝅘𝅥𝅮𝅘𝅥𝅮𝅘𝅥𝅮𝅗𝅥𝅗𝅥𝅘𝅥𝅮𝅘𝅥𝅮𝅘𝅥𝅮", 1, ChannelError::UnknownCharacters(8));
 }
+
+#[test]
+fn unknown_characters_error_recovery() {
+    // The numbers after the unknown characters must not emit an `UnexpectedNumber` error
+    assert_one_error_in_mml_line("a ´ 10,20 b", 3, ChannelError::UnknownCharacters(1));
+    assert_one_error_in_mml_line("a ´´ 10,20 . % b", 3, ChannelError::UnknownCharacters(2));
+}
+
+#[test]
+fn compile_mml_with_metrics() {
+    let dd = dummy_data();
+
+    let mml = r###"
+@0 dummy_instrument
+
+!s @0 c d
+
+A @0 a b
+B !s c
+"###;
+
+    let (r, metrics) = mml::compile_mml_with_metrics(
+        &TextFile {
+            contents: mml.to_string(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        None,
+        &dd.instruments_and_samples,
+        &dd.pitch_table,
+    );
+    assert!(r.is_ok());
+
+    assert_eq!(metrics.n_channels, 2);
+    assert_eq!(metrics.n_subroutines, 1);
+    // Includes a new-line token for each line
+    assert_eq!(metrics.total_tokens, 3 + 1 + 3 + 1 + 2 + 1);
+}
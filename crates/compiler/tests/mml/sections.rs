@@ -0,0 +1,165 @@
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::*;
+
+use compiler::driver_constants::N_MUSIC_CHANNELS;
+use compiler::mml::{MmlTickCountTable, SectionTickCounts, TickRange};
+use compiler::time::TickCounter;
+
+#[test]
+fn section_tick_count_table() {
+    let sd = compile_mml(
+        r##"
+@0 dummy_instrument
+
+;; Intro
+A @0 c
+B @0 c c
+
+;; Verse
+A d d
+B d
+
+;; Empty
+"##,
+        &dummy_data(),
+    );
+
+    let t = |a, b| {
+        let mut c = [None; N_MUSIC_CHANNELS];
+        c[0] = Some(TickCounter::new(a));
+        c[1] = Some(TickCounter::new(b));
+        c
+    };
+
+    let table = sd.section_tick_count_table();
+
+    assert_eq!(
+        table.0,
+        [
+            SectionTickCounts {
+                name: "Intro".to_owned(),
+                line_number: 4,
+                channels: t(0, 0),
+            },
+            SectionTickCounts {
+                name: "Verse".to_owned(),
+                line_number: 8,
+                channels: t(24, 48),
+            },
+            SectionTickCounts {
+                name: "Empty".to_owned(),
+                line_number: 12,
+                channels: t(72, 72),
+            },
+        ]
+    );
+
+    assert_eq!(table.0[1].max_ticks(), TickCounter::new(48));
+}
+
+#[test]
+fn section_at_tick() {
+    let sd = compile_mml(
+        r##"
+@0 dummy_instrument
+
+;; Intro
+A @0 c
+B @0 c c
+
+;; Verse
+A d d
+B d
+
+;; Empty
+
+;; Outro
+A e
+"##,
+        &dummy_data(),
+    );
+
+    let table = MmlTickCountTable::new(&sd);
+
+    let r = |start, end| TickRange {
+        start: TickCounter::new(start),
+        end: TickCounter::new(end),
+    };
+
+    let ranges = table.section_ranges();
+    assert_eq!(ranges.len(), 4);
+    assert_eq!(ranges["Intro"], r(0, 48));
+    assert_eq!(ranges["Verse"], r(48, 72));
+    assert_eq!(ranges["Empty"], r(72, 72));
+    assert_eq!(ranges["Outro"], r(72, 96));
+
+    let s = |t| table.section_at_tick(TickCounter::new(t));
+    assert_eq!(s(0), Some("Intro"));
+    assert_eq!(s(47), Some("Intro"));
+    assert_eq!(s(48), Some("Verse"));
+    assert_eq!(s(71), Some("Verse"));
+    assert_eq!(s(72), Some("Outro"));
+    assert_eq!(s(95), Some("Outro"));
+    assert_eq!(s(96), None);
+
+    let ranges = table.into_section_tick_ranges();
+    let p = |t| ranges.position_at_tick(TickCounter::new(t));
+    assert_eq!(p(0), Some(0));
+    assert_eq!(p(48), Some(1));
+    assert_eq!(p(72), Some(3));
+    assert_eq!(p(96), None);
+}
+
+#[test]
+fn section_tick_count_assertion() {
+    let sd = compile_mml(
+        r##"
+@0 dummy_instrument
+
+;; Intro @0
+A @0 c c
+B @0 c c
+
+;; Verse @48
+A d
+B d
+
+;; Outro @72
+A e
+"##,
+        &dummy_data(),
+    );
+
+    let table = sd.section_tick_count_table();
+    assert_eq!(table.0[1].name, "Verse");
+    assert_eq!(table.0[2].name, "Outro");
+
+    // Channel B ends before the Outro section
+    compile_mml(
+        "@0 dummy_instrument\n;; Intro\nA @0 c c c c\nB @0 c\n;; End @96\nA c",
+        &dummy_data(),
+    );
+
+    assert_one_channel_error_in_mml(
+        "@0 dummy_instrument\n;; Intro\nA @0 c\nB @0 c c\n;; Verse @48\nA d\nB d",
+        "A",
+        3,
+        ChannelError::SectionTickCountMismatch {
+            section: "Verse".to_owned(),
+            expected: TickCounter::new(48),
+            actual: TickCounter::new(24),
+        },
+    );
+}
+
+#[test]
+fn invalid_section_tick_count() {
+    assert_one_header_error_in_mml(
+        "@0 dummy_instrument\n;; Verse @4x\nA @0 c",
+        2,
+        MmlLineError::InvalidSectionTickCount("@4x".to_owned()),
+    );
+}
@@ -435,6 +435,54 @@ A @0 !s1 !s3
     );
 }
 
+#[test]
+fn channel_errors_with_subroutine_error() {
+    // Channels are compiled if a subroutine contains an error.
+    // Errors after a call to the invalid subroutine are ignored.
+    let mml = r##"
+@0 dummy_instrument
+
+!s @0 c ~
+
+A @0 d ~ !s e ~
+B @0 !s ~ f
+C @0 g ~
+"##;
+    let dummy_data = dummy_data();
+
+    let r = mml::compile_mml(
+        &TextFile {
+            contents: mml.to_string(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        None,
+        &dummy_data.instruments_and_samples,
+        &dummy_data.pitch_table,
+    );
+
+    let e = match &r {
+        Err(SongError::MmlError(e)) => e,
+        _ => panic!("expected an MmlError: {r:?}"),
+    };
+
+    let errors = |list: &[compiler::errors::MmlChannelError]| -> Vec<(String, Vec<u32>)> {
+        list.iter()
+            .map(|c| {
+                let chars = c.errors.iter().map(|e| e.0.line_char()).collect();
+                (c.identifier.as_str().to_owned(), chars)
+            })
+            .collect()
+    };
+
+    assert!(e.line_errors.is_empty(), "{e:?}");
+    assert_eq!(errors(&e.subroutine_errors), [("s".to_owned(), vec![9])]);
+    assert_eq!(
+        errors(&e.channel_errors),
+        [("A".to_owned(), vec![8]), ("C".to_owned(), vec![8])]
+    );
+}
+
 #[test]
 fn nested_subroutines_stack_depth_limit() {
     let channel_a_stack_depth =
@@ -1255,3 +1303,45 @@ A @d o6c !s o4c o6d o4e
         BytecodeError::NoteOutOfRange(note("d6"), note("c4")..=note("b4")).into(),
     );
 }
+
+#[test]
+fn subroutine_call_graph_and_depth() {
+    let mml = compile_mml(
+        r##"
+@0 dummy_instrument
+
+!s1 a
+!s2 !s1 b !s1
+!s3 [!s2 c]2 !s1
+!s4 d
+
+A @0 !s3
+B @0 !s1 !s4
+"##,
+        &dummy_data(),
+    );
+
+    let graph = mml.subroutine_call_graph();
+    assert_eq!(graph.len(), 4);
+    assert_eq!(graph[&0], Vec::<usize>::new());
+    assert_eq!(graph[&1], vec![0]);
+    assert_eq!(graph[&2], vec![1, 0]);
+    assert_eq!(graph[&3], Vec::<usize>::new());
+
+    assert_eq!(mml.subroutine_depth(), 3);
+}
+
+#[test]
+fn subroutine_depth_without_subroutines() {
+    let mml = compile_mml(
+        r##"
+@0 dummy_instrument
+
+A @0 a
+"##,
+        &dummy_data(),
+    );
+
+    assert!(mml.subroutine_call_graph().is_empty());
+    assert_eq!(mml.subroutine_depth(), 0);
+}
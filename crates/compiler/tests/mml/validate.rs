@@ -0,0 +1,138 @@
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::*;
+
+use compiler::errors::MmlWarning;
+use compiler::time::TickCounter;
+
+fn validate(mml: &str) -> mml::MmlValidationResult {
+    let dd = dummy_data();
+
+    let tf = TextFile {
+        contents: mml.to_owned(),
+        path: None,
+        file_name: "validate_test".to_owned(),
+    };
+
+    mml::validate_mml(&tf, &dd.instruments_and_samples)
+}
+
+#[test]
+fn validate_mml_valid() {
+    let r = validate(
+        r##"
+@0 dummy_instrument
+
+!s a b
+
+A @0 c d !s
+"##,
+    );
+
+    assert!(!r.has_errors(), "{}", r.errors.multiline_display());
+    assert!(r.warnings.is_empty(), "{:?}", r.warnings);
+}
+
+#[test]
+fn validate_mml_warnings() {
+    let r = validate(
+        r##"
+@0 dummy_instrument
+@1 dummy_instrument_2
+
+!unused a b
+!s @1 c
+
+A @0 c d
+"##,
+    );
+
+    assert!(!r.has_errors(), "{}", r.errors.multiline_display());
+
+    let warnings: Vec<_> = r
+        .warnings
+        .iter()
+        .map(|w| (w.0.line_number(), &w.1))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (5, &MmlWarning::UnusedSubroutine("unused".to_owned())),
+            (6, &MmlWarning::UnusedSubroutine("s".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn validate_mml_min_rest_ticks_warning() {
+    let r = validate(
+        r##"
+#MinRestTicks 4
+
+@0 dummy_instrument
+
+A @0 c r%2 d r%4
+A e r%1
+"##,
+    );
+
+    assert!(!r.has_errors(), "{}", r.errors.multiline_display());
+
+    let warnings: Vec<_> = r
+        .warnings
+        .iter()
+        .map(|w| (w.0.line_number(), w.0.line_char(), &w.1))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (
+                6,
+                8,
+                &MmlWarning::RestPaddedForTiming {
+                    original: TickCounter::new(2),
+                    padded: TickCounter::new(4),
+                }
+            ),
+            (
+                7,
+                5,
+                &MmlWarning::RestPaddedForTiming {
+                    original: TickCounter::new(1),
+                    padded: TickCounter::new(4),
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn validate_mml_errors() {
+    let r = validate(
+        r##"
+@0 dummy_instrument
+@1 unknown_instrument
+
+!s a b !missing
+
+A @0 c d !s
+B @0 c v20
+"##,
+    );
+
+    assert!(r.has_errors());
+    assert_eq!(r.errors.line_errors.len(), 1);
+    assert_eq!(r.errors.subroutine_errors.len(), 1);
+    assert_eq!(r.errors.channel_errors.len(), 1);
+
+    assert!(matches!(
+        r.errors.subroutine_errors[0].errors[0].1,
+        ChannelError::CannotFindSubroutine(_)
+    ));
+    assert!(matches!(
+        r.errors.channel_errors[0].errors[0].1,
+        ChannelError::ValueError(_)
+    ));
+}
@@ -0,0 +1,245 @@
+//! MML property-based tests
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use compiler::data::{self, Name, TextFile, UniqueNamesList};
+use compiler::envelope::{Envelope, Gain};
+use compiler::errors::SongError;
+use compiler::mml;
+use compiler::notes::{NoteRange, Octave};
+use compiler::opcodes;
+use compiler::pitch_table::{build_pitch_table, PitchTable};
+use compiler::songs::SongData;
+
+use proptest::prelude::*;
+
+const MML_HEADER: &str = "@1 dummy_instrument\nA @1 o4\nA ";
+
+/// Number of ticks in a quarter note (with the default zenlen)
+const QUARTER_NOTE_TICKS: u32 = 24;
+
+struct DummyData {
+    instruments_and_samples: UniqueNamesList<data::InstrumentOrSample>,
+    pitch_table: PitchTable,
+}
+
+fn dummy_data() -> DummyData {
+    let inst = data::Instrument {
+        name: Name::try_from("dummy_instrument".to_owned()).unwrap(),
+        source: Default::default(),
+        freq: 500.0,
+        loop_setting: data::LoopSetting::LoopWithFilter(0),
+        evaluator: Default::default(),
+        ignore_gaussian_overflow: false,
+        auto_detect_loop: false,
+        dc_offset_removal: false,
+        normalize: false,
+        note_range: NoteRange {
+            first: Octave::try_new(2).unwrap(),
+            last: Octave::try_new(6).unwrap(),
+        },
+        envelope: Envelope::Gain(Gain::new(0)),
+        comment: None,
+    };
+
+    let instruments_and_samples =
+        data::validate_instrument_and_sample_names([inst].iter(), [].iter()).unwrap();
+    let pitch_table = build_pitch_table(&instruments_and_samples).unwrap();
+
+    DummyData {
+        instruments_and_samples,
+        pitch_table,
+    }
+}
+
+fn compile_channel_a(mml_line: &str, dd: &DummyData) -> Result<SongData, SongError> {
+    mml::compile_mml(
+        &TextFile {
+            contents: [MML_HEADER, mml_line].concat(),
+            path: None,
+            file_name: "".to_owned(),
+        },
+        None,
+        &dd.instruments_and_samples,
+        &dd.pitch_table,
+    )
+}
+
+fn channel_a_bytecode(sd: &SongData) -> &[u8] {
+    let start: usize = sd.channels()[0].as_ref().unwrap().bytecode_offset.into();
+
+    &sd.data()[start..]
+}
+
+/// Returns the bytecode after the `set_instrument` instruction
+fn channel_a_bytecode_after_instrument(sd: &SongData) -> &[u8] {
+    let bc = channel_a_bytecode(sd);
+    assert_eq!(bc[0], opcodes::SET_INSTRUMENT);
+    &bc[2..]
+}
+
+fn channel_a_ticks(sd: &SongData) -> u32 {
+    sd.channels()[0].as_ref().unwrap().tick_counter.value()
+}
+
+#[derive(Debug, Clone)]
+struct TestNote {
+    octave: u32,
+    pitch: char,
+    accidental: &'static str,
+    length: u32,
+}
+
+fn note_strategy() -> impl Strategy<Value = TestNote> {
+    (
+        2u32..=6,
+        prop::sample::select(vec!['c', 'd', 'e', 'f', 'g', 'a', 'b']),
+        prop::sample::select(vec!["", "+", "-"]),
+        prop::sample::select(vec![1u32, 2, 4, 8, 16, 32]),
+    )
+        .prop_map(|(octave, pitch, accidental, length)| {
+            // Prevent notes outside of the instrument's octave range
+            let accidental = match (pitch, accidental) {
+                ('c', "-") | ('b', "+") => "",
+                _ => accidental,
+            };
+            TestNote {
+                octave,
+                pitch,
+                accidental,
+                length,
+            }
+        })
+}
+
+/// Formats the notes with an absolute octave and length before every note
+fn format_notes_absolute(notes: &[TestNote]) -> String {
+    notes
+        .iter()
+        .map(|n| format!("o{}{}{}{} ", n.octave, n.pitch, n.accidental, n.length))
+        .collect()
+}
+
+/// Formats the notes with relative octave changes and the `l` default length command
+fn format_notes_relative(notes: &[TestNote]) -> String {
+    let mut out = String::new();
+    let mut octave = 4;
+    let mut length = 4;
+
+    for n in notes {
+        while octave < n.octave {
+            out.push('>');
+            octave += 1;
+        }
+        while octave > n.octave {
+            out.push('<');
+            octave -= 1;
+        }
+        if length != n.length {
+            out += &format!("l{}", n.length);
+            length = n.length;
+        }
+        out.push(n.pitch);
+        out += n.accidental;
+    }
+    out
+}
+
+proptest! {
+    #[test]
+    fn formatted_notes_produce_the_same_bytecode(notes in prop::collection::vec(note_strategy(), 1..40)) {
+        let dd = dummy_data();
+
+        let sd1 = compile_channel_a(&format_notes_absolute(&notes), &dd).unwrap();
+        let sd2 = compile_channel_a(&format_notes_relative(&notes), &dd).unwrap();
+
+        prop_assert_eq!(channel_a_bytecode(&sd1), channel_a_bytecode(&sd2));
+        prop_assert_eq!(channel_a_ticks(&sd1), channel_a_ticks(&sd2));
+    }
+
+    #[test]
+    fn volume_slide_is_monotone_and_within_bounds(
+        amount in 1u32..=255,
+        ticks in 1u32..=256,
+        up in any::<bool>(),
+    ) {
+        let dd = dummy_data();
+
+        let sign = if up { '+' } else { '-' };
+        let sd = compile_channel_a(&format!("Vs{sign}{amount},{ticks}"), &dd).unwrap();
+        let bc = channel_a_bytecode_after_instrument(&sd);
+
+        let expected_opcode = if up { opcodes::VOLUME_SLIDE_UP } else { opcodes::VOLUME_SLIDE_DOWN };
+        prop_assert_eq!(bc[0], expected_opcode);
+        prop_assert_eq!(u32::from(bc[1]), ticks & 0xff);
+
+        let offset_per_tick = u32::from(u16::from_le_bytes([bc[2], bc[3]]));
+
+        // Simulates the audio driver's sub-volume
+        let slide_amount = |t: u32| -> u32 {
+            let offset = offset_per_tick * t;
+            if up {
+                offset >> 8
+            } else {
+                0xff - ((0xffff - offset) >> 8)
+            }
+        };
+
+        // The slide must never reverse direction and must never overshoot `amount`
+        let mut prev = 0;
+        for t in 0..=ticks {
+            let v = slide_amount(t);
+            prop_assert!(v >= prev);
+            prop_assert!(v <= amount);
+            prev = v;
+        }
+        prop_assert_eq!(slide_amount(ticks), amount);
+    }
+
+    #[test]
+    fn loop_body_is_repeated_loop_count_times(loop_count in 2u32..=256) {
+        let dd = dummy_data();
+
+        let sd = compile_channel_a(&format!("[c]{loop_count}"), &dd).unwrap();
+        prop_assert_eq!(channel_a_ticks(&sd), loop_count * QUARTER_NOTE_TICKS);
+
+        // The section after `:` is skipped on the last loop
+        let sd = compile_channel_a(&format!("[c : d8]{loop_count}"), &dd).unwrap();
+        prop_assert_eq!(
+            channel_a_ticks(&sd),
+            loop_count * QUARTER_NOTE_TICKS + (loop_count - 1) * QUARTER_NOTE_TICKS / 2
+        );
+    }
+
+    #[test]
+    fn invalid_loop_count_is_an_error(loop_count in prop_oneof![0u32..2, 257u32..1000]) {
+        let dd = dummy_data();
+
+        let r = compile_channel_a(&format!("[c]{loop_count}"), &dd);
+        prop_assert!(r.is_err());
+    }
+
+    #[test]
+    fn vibrato_depth_zero_disables_vibrato(
+        pitch_offset_per_tick in 1u32..=255,
+        quarter_wavelength_ticks in 1u32..=128,
+    ) {
+        let dd = dummy_data();
+
+        let vibrato = format!("~{pitch_offset_per_tick},{quarter_wavelength_ticks}");
+
+        let prefix_len = channel_a_bytecode(&compile_channel_a(&vibrato, &dd).unwrap()).len();
+
+        // Skip the vibrato bytecode (excluding the `disable_channel` terminator)
+        let sd = compile_channel_a(&format!("{vibrato} ~0 c"), &dd).unwrap();
+        let bc = &channel_a_bytecode(&sd)[prefix_len - 1..];
+        prop_assert_eq!(&bc[..3], &[opcodes::SET_VIBRATO, 0, 0]);
+
+        // `MP0` sets the vibrato depth to 0 (keeping the quarter wavelength)
+        let sd = compile_channel_a(&format!("{vibrato} MP0 c"), &dd).unwrap();
+        let bc = &channel_a_bytecode(&sd)[prefix_len - 1..];
+        prop_assert_eq!(&bc[..2], &[opcodes::SET_VIBRATO_DEPTH_AND_PLAY_NOTE, 0]);
+    }
+}
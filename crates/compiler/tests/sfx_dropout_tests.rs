@@ -18,7 +18,7 @@ use compiler::driver_constants::{
     N_SFX_CHANNELS,
 };
 use compiler::envelope::{Envelope, Gain};
-use compiler::notes::Octave;
+use compiler::notes::{NoteRange, Octave};
 use compiler::samples::combine_samples;
 use compiler::sound_effects::{
     combine_sound_effects, compile_sfx_subroutines, compile_sound_effect_input, SfxFlags,
@@ -564,8 +564,13 @@ fn _build_test_common_audio_data() -> CommonAudioData {
         loop_setting: data::LoopSetting::None,
         evaluator: Default::default(),
         ignore_gaussian_overflow: false,
-        first_octave: Octave::try_new(2).unwrap(),
-        last_octave: Octave::try_new(5).unwrap(),
+        auto_detect_loop: false,
+        dc_offset_removal: false,
+        normalize: false,
+        note_range: NoteRange {
+            first: Octave::try_new(2).unwrap(),
+            last: Octave::try_new(5).unwrap(),
+        },
         envelope: Envelope::Gain(Gain::new(127)),
         comment: None,
     };
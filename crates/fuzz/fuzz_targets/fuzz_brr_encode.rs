@@ -0,0 +1,71 @@
+//! BRR encoder fuzz target
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+#![no_main]
+
+use brr::{encode_brr, BrrFilter, Evaluator};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+enum FuzzEvaluator {
+    SquaredError,
+    SquaredErrorAvoidGaussianOverflow,
+}
+
+#[derive(Debug, Arbitrary)]
+enum FuzzLoopSetting {
+    None,
+    LoopPoint(u16),
+    LoopPointWithFilter(u16, u8),
+    DupeBlockHack(u8),
+    DupeBlockHackWithFilter(u8, u8),
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    samples: Vec<i16>,
+    evaluator: FuzzEvaluator,
+    loop_setting: FuzzLoopSetting,
+}
+
+fn filter(f: u8) -> BrrFilter {
+    match f % 4 {
+        0 => BrrFilter::Filter0,
+        1 => BrrFilter::Filter1,
+        2 => BrrFilter::Filter2,
+        _ => BrrFilter::Filter3,
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let evaluator = match input.evaluator {
+        FuzzEvaluator::SquaredError => Evaluator::SquaredError,
+        FuzzEvaluator::SquaredErrorAvoidGaussianOverflow => {
+            Evaluator::SquaredErrorAvoidGaussianOverflow
+        }
+    };
+
+    let (loop_offset, dupe_block_hack, loop_filter) = match input.loop_setting {
+        FuzzLoopSetting::None => (None, None, None),
+        FuzzLoopSetting::LoopPoint(lp) => (Some(lp.into()), None, None),
+        FuzzLoopSetting::LoopPointWithFilter(lp, f) => (Some(lp.into()), None, Some(filter(f))),
+        FuzzLoopSetting::DupeBlockHack(dbh) => (None, Some(dbh.into()), None),
+        FuzzLoopSetting::DupeBlockHackWithFilter(dbh, f) => {
+            (None, Some(dbh.into()), Some(filter(f)))
+        }
+    };
+
+    // Either result is fine, the encoder must not panic.
+    let _ = encode_brr(
+        &input.samples,
+        evaluator,
+        loop_offset,
+        dupe_block_hack,
+        loop_filter,
+    );
+});
@@ -0,0 +1,93 @@
+//! MML compiler fuzz target
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+#![no_main]
+
+use compiler::data::{self, Name, TextFile, UniqueNamesList};
+use compiler::envelope::{Envelope, Gain};
+use compiler::mml;
+use compiler::notes::{NoteRange, Octave};
+use compiler::pitch_table::{build_pitch_table, PitchTable};
+
+use libfuzzer_sys::fuzz_target;
+
+use std::sync::OnceLock;
+
+struct StubData {
+    instruments_and_samples: UniqueNamesList<data::InstrumentOrSample>,
+    pitch_table: PitchTable,
+}
+
+fn stub_instrument(name: &str, first_octave: u32, last_octave: u32) -> data::Instrument {
+    data::Instrument {
+        name: Name::try_from(name.to_owned()).unwrap(),
+        source: Default::default(),
+        freq: 500.0,
+        loop_setting: data::LoopSetting::LoopWithFilter(0),
+        evaluator: Default::default(),
+        ignore_gaussian_overflow: false,
+        auto_detect_loop: false,
+        dc_offset_removal: false,
+        normalize: false,
+        note_range: NoteRange {
+            first: Octave::try_new(first_octave).unwrap(),
+            last: Octave::try_new(last_octave).unwrap(),
+        },
+        envelope: Envelope::Gain(Gain::new(0)),
+        comment: None,
+    }
+}
+
+fn stub_data() -> &'static StubData {
+    static DATA: OnceLock<StubData> = OnceLock::new();
+
+    DATA.get_or_init(|| {
+        let instruments = [
+            stub_instrument("inst", 2, 6),
+            stub_instrument("inst_2", 1, 7),
+            stub_instrument("only_octave_four", 4, 4),
+        ];
+        let samples = [data::Sample {
+            name: "sample".parse().unwrap(),
+            source: Default::default(),
+            loop_setting: data::LoopSetting::None,
+            evaluator: Default::default(),
+            ignore_gaussian_overflow: false,
+            sample_rates: vec![32000, 16000, 18000],
+            envelope: Envelope::Gain(Gain::new(127)),
+            comment: None,
+        }];
+
+        let instruments_and_samples =
+            data::validate_instrument_and_sample_names(instruments.iter(), samples.iter()).unwrap();
+        let pitch_table = build_pitch_table(&instruments_and_samples).unwrap();
+
+        StubData {
+            instruments_and_samples,
+            pitch_table,
+        }
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(contents) = std::str::from_utf8(data) {
+        let stub = stub_data();
+
+        let mml_file = TextFile {
+            contents: contents.to_owned(),
+            path: None,
+            file_name: "fuzz.mml".to_owned(),
+        };
+
+        // Either result is fine, the compiler must not panic.
+        let _ = mml::compile_mml(
+            &mml_file,
+            None,
+            &stub.instruments_and_samples,
+            &stub.pitch_table,
+        );
+    }
+});
@@ -217,6 +217,7 @@ auto DSP::power(bool reset) -> void {
   }
 
   sampleBuffer.reset();
+  for(auto& b : voiceSampleBuffers) b.reset();
 
   timing = {};
 
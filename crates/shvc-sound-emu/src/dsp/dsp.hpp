@@ -8,6 +8,11 @@ struct DSP {
 
   SampleBuffer sampleBuffer;
 
+  //per-voice output (after main volume, excluding echo)
+  //only written if voiceCapture is set
+  std::array<SampleBuffer, 8> voiceSampleBuffers;
+  bool voiceCapture = false;
+
   auto mute() const -> bool { return mainvol.mute; }
 
   auto power(bool reset) -> void;
@@ -125,6 +130,7 @@ private:
     n1  _echo;
     n1  _end;
     n1  _looped;
+    s32 _output[2];      //last voiceOutput() amplitude (before main volume)
   } voice[8];
 
   //gaussian.cpp
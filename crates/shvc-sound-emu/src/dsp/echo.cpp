@@ -102,6 +102,18 @@ auto DSP::echo27() -> void {
 
   //output sample to DAC
   sample(outl, outr);
+
+  if(voiceCapture) {
+    for(auto& v : voice) {
+      i16 vl = 0;
+      i16 vr = 0;
+      if(!mainvol.mute) {
+        vl = sclamp<16>(v._output[0] * mainvol.volume[0] >> 7);
+        vr = sclamp<16>(v._output[1] * mainvol.volume[1] >> 7);
+      }
+      voiceSampleBuffers[v.index >> 4].write(vl, vr);
+    }
+  }
 }
 
 auto DSP::echo28() -> void {
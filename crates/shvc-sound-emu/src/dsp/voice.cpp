@@ -1,6 +1,7 @@
 inline auto DSP::voiceOutput(Voice& v, n1 channel) -> void {
   //apply left/right volume
   s32 amp = latch.output * v.volume[channel] >> 7;
+  v._output[channel] = amp;
 
   //add to output total
   mainvol.output[channel] += amp;
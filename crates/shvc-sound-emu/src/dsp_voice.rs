@@ -0,0 +1,152 @@
+//! S-DSP voice register accessors
+
+use crate::{ShvcSoundEmu, N_VOICES};
+
+/// The per-voice S-DSP registers (the low nibble of the register address).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DspVoiceReg {
+    VolL = 0x0,
+    VolR = 0x1,
+    PitchL = 0x2,
+    PitchH = 0x3,
+    Srcn = 0x4,
+    Adsr1 = 0x5,
+    Adsr2 = 0x6,
+    Gain = 0x7,
+    Envx = 0x8,
+    Outx = 0x9,
+}
+
+/// Returns the S-DSP register address of a voice register.
+///
+/// Panics if `voice` is >= `N_VOICES`.
+pub const fn voice_register_addr(voice: u8, reg: DspVoiceReg) -> u8 {
+    assert!((voice as usize) < N_VOICES);
+
+    (voice << 4) | reg as u8
+}
+
+/// The voice getters read the S-DSP register file.
+///
+/// The voice setters write through `write_dsp_register()`, so the S-DSP's internal state is
+/// also updated.
+///
+/// All methods panic if `voice` is >= `N_VOICES`.
+impl ShvcSoundEmu {
+    fn read_voice_register(&self, voice: u8, reg: DspVoiceReg) -> u8 {
+        self.dsp_registers()[usize::from(voice_register_addr(voice, reg))]
+    }
+
+    fn write_voice_register(&mut self, voice: u8, reg: DspVoiceReg, value: u8) {
+        self.write_dsp_register(voice_register_addr(voice, reg), value);
+    }
+
+    pub fn voice_vol_left(&self, voice: u8) -> i8 {
+        self.read_voice_register(voice, DspVoiceReg::VolL) as i8
+    }
+
+    pub fn voice_vol_right(&self, voice: u8) -> i8 {
+        self.read_voice_register(voice, DspVoiceReg::VolR) as i8
+    }
+
+    /// The 14 bit pitch of the voice
+    pub fn voice_pitch(&self, voice: u8) -> u16 {
+        let l = self.read_voice_register(voice, DspVoiceReg::PitchL);
+        let h = self.read_voice_register(voice, DspVoiceReg::PitchH);
+
+        u16::from_le_bytes([l, h]) & 0x3fff
+    }
+
+    pub fn voice_srcn(&self, voice: u8) -> u8 {
+        self.read_voice_register(voice, DspVoiceReg::Srcn)
+    }
+
+    /// Returns the `ADSR1` and `ADSR2` registers
+    pub fn voice_adsr(&self, voice: u8) -> [u8; 2] {
+        [
+            self.read_voice_register(voice, DspVoiceReg::Adsr1),
+            self.read_voice_register(voice, DspVoiceReg::Adsr2),
+        ]
+    }
+
+    pub fn voice_gain(&self, voice: u8) -> u8 {
+        self.read_voice_register(voice, DspVoiceReg::Gain)
+    }
+
+    /// The current envelope value (written by the S-DSP)
+    pub fn voice_envx(&self, voice: u8) -> u8 {
+        self.read_voice_register(voice, DspVoiceReg::Envx)
+    }
+
+    /// The current waveform value (written by the S-DSP)
+    pub fn voice_outx(&self, voice: u8) -> i8 {
+        self.read_voice_register(voice, DspVoiceReg::Outx) as i8
+    }
+
+    pub fn set_voice_vol_left(&mut self, voice: u8, value: i8) {
+        self.write_voice_register(voice, DspVoiceReg::VolL, value as u8);
+    }
+
+    pub fn set_voice_vol_right(&mut self, voice: u8, value: i8) {
+        self.write_voice_register(voice, DspVoiceReg::VolR, value as u8);
+    }
+
+    /// Only the low 14 bits of `pitch` are used
+    pub fn set_voice_pitch(&mut self, voice: u8, pitch: u16) {
+        let [l, h] = pitch.to_le_bytes();
+
+        self.write_voice_register(voice, DspVoiceReg::PitchL, l);
+        self.write_voice_register(voice, DspVoiceReg::PitchH, h & 0x3f);
+    }
+
+    pub fn set_voice_srcn(&mut self, voice: u8, value: u8) {
+        self.write_voice_register(voice, DspVoiceReg::Srcn, value);
+    }
+
+    /// Sets the `ADSR1` and `ADSR2` registers
+    pub fn set_voice_adsr(&mut self, voice: u8, adsr: [u8; 2]) {
+        self.write_voice_register(voice, DspVoiceReg::Adsr1, adsr[0]);
+        self.write_voice_register(voice, DspVoiceReg::Adsr2, adsr[1]);
+    }
+
+    pub fn set_voice_gain(&mut self, voice: u8, value: u8) {
+        self.write_voice_register(voice, DspVoiceReg::Gain, value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn voice_register_addr_layout() {
+        assert_eq!(voice_register_addr(0, DspVoiceReg::VolL), 0x00);
+        assert_eq!(voice_register_addr(3, DspVoiceReg::PitchH), 0x33);
+        assert_eq!(voice_register_addr(7, DspVoiceReg::Outx), 0x79);
+    }
+
+    #[test]
+    fn voice_setters_and_getters() {
+        let mut emu = ShvcSoundEmu::default();
+
+        emu.set_voice_vol_left(5, -20);
+        emu.set_voice_vol_right(5, 100);
+        emu.set_voice_pitch(5, 0xffff);
+        emu.set_voice_srcn(5, 42);
+        emu.set_voice_adsr(5, [0x8f, 0xe0]);
+        emu.set_voice_gain(5, 0x7f);
+
+        assert_eq!(emu.voice_vol_left(5), -20);
+        assert_eq!(emu.voice_vol_right(5), 100);
+        assert_eq!(emu.voice_pitch(5), 0x3fff);
+        assert_eq!(emu.voice_srcn(5), 42);
+        assert_eq!(emu.voice_adsr(5), [0x8f, 0xe0]);
+        assert_eq!(emu.voice_gain(5), 0x7f);
+
+        assert_eq!(
+            emu.dsp_registers()[0x50..0x58],
+            [236, 100, 0xff, 0x3f, 42, 0x8f, 0xe0, 0x7f]
+        );
+        assert_eq!(emu.voice_vol_left(4), 0);
+    }
+}
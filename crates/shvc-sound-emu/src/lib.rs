@@ -3,6 +3,14 @@
 extern crate cxx;
 use cxx::UniquePtr;
 
+mod dsp_voice;
+mod snapshot;
+mod spc_file;
+
+pub use dsp_voice::{voice_register_addr, DspVoiceReg};
+pub use snapshot::EmulatorSnapshot;
+pub use spc_file::{SpcLoadError, SpcMetadata};
+
 #[cxx::bridge(namespace = "shvc_sound_emu")]
 mod ffi {
     pub struct ResetRegisters {
@@ -34,6 +42,7 @@ mod ffi {
         fn apuram_mut(self: Pin<&mut ShvcSoundEmu>) -> &mut [u8; 65536];
 
         fn dsp_registers(self: &ShvcSoundEmu) -> &[u8; 128];
+        fn dsp_registers_mut(self: Pin<&mut ShvcSoundEmu>) -> &mut [u8; 128];
 
         fn write_dsp_register(self: Pin<&mut ShvcSoundEmu>, addr: u8, value: u8);
         fn write_smp_register(self: Pin<&mut ShvcSoundEmu>, addr: u8, value: u8);
@@ -42,33 +51,100 @@ mod ffi {
         fn write_io_ports(self: Pin<&mut ShvcSoundEmu>, ports: [u8; 4]);
 
         fn program_counter(self: &ShvcSoundEmu) -> u16;
+        fn cpu_registers(self: &ShvcSoundEmu) -> ResetRegisters;
 
         fn emulate(self: Pin<&mut ShvcSoundEmu>) -> &[i16; 512];
+
+        fn emulate_ticks(self: Pin<&mut ShvcSoundEmu>, ticks: u32) -> u32;
+        fn audio_buffer_len(self: &ShvcSoundEmu) -> u32;
+        fn flush_audio(self: Pin<&mut ShvcSoundEmu>) -> &[i16; 512];
+
+        fn emulate_per_voice(self: Pin<&mut ShvcSoundEmu>) -> &[i16; 512];
+        fn voice_output(self: &ShvcSoundEmu, voice: u8) -> &[i16; 512];
     }
 }
 
 pub use ffi::ResetRegisters;
 
+pub const N_VOICES: usize = 8;
+
+pub struct PerVoiceOutput {
+    /// The S-DSP output (identical to `ShvcSoundEmu::emulate()`)
+    pub mixed: [i16; ShvcSoundEmu::AUDIO_BUFFER_SIZE],
+
+    /// The output of each voice after the main volume is applied.
+    /// Does not include echo.
+    pub voices: [[i16; ShvcSoundEmu::AUDIO_BUFFER_SIZE]; N_VOICES],
+}
+
+/// Error returned by `ShvcSoundEmu::try_new()` when the emulator could not be created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulatorInitError;
+
+impl std::fmt::Display for EmulatorInitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot create SHVC-Sound emulator")
+    }
+}
+
+impl std::error::Error for EmulatorInitError {}
+
 pub struct ShvcSoundEmu {
     emu: UniquePtr<ffi::ShvcSoundEmu>,
+
+    /// Samples emulated by `emulate_n_samples()` that have not been output yet
+    pending: [i16; Self::AUDIO_BUFFER_SIZE],
+    pending_pos: usize,
+
+    /// Audio emulated by `emulate_ticks()` that did not fit in the emulator's audio buffer
+    ticks_audio: Vec<i16>,
+    flushed_audio: Vec<i16>,
+    /// The number of ticks the last `emulate_ticks()` call emulated past its target
+    tick_overshoot: u32,
+}
+
+/// Creates an emulator with a blank (all zero) IPL ROM.
+///
+/// Panics if the emulator could not be created.
+impl Default for ShvcSoundEmu {
+    fn default() -> Self {
+        Self::new(&[0; 64])
+    }
 }
 
 impl ShvcSoundEmu {
     pub const AUDIO_BUFFER_SAMPLES: usize = 256;
     pub const AUDIO_BUFFER_SIZE: usize = Self::AUDIO_BUFFER_SAMPLES * 2;
 
-    #[allow(clippy::new_without_default)]
+    /// The number of S-SMP clock ticks per stereo sample (2.048 MHz / 32 kHz)
+    pub const TICKS_PER_SAMPLE: u32 = 64;
+
+    /// Panics if the emulator could not be created.
     pub fn new(iplrom: &[u8; 64]) -> Self {
+        Self::try_new(iplrom).expect("new_emulator() returned null")
+    }
+
+    pub fn try_new(iplrom: &[u8; 64]) -> Result<Self, EmulatorInitError> {
         let emu = ffi::new_emulator(iplrom);
         if emu.is_null() {
-            panic!("new_emulator() returned null");
+            return Err(EmulatorInitError);
         }
-        Self { emu }
+        Ok(Self {
+            emu,
+            pending: [0; Self::AUDIO_BUFFER_SIZE],
+            pending_pos: Self::AUDIO_BUFFER_SIZE,
+            ticks_audio: Vec::new(),
+            flushed_audio: Vec::new(),
+            tick_overshoot: 0,
+        })
     }
 
     /// CAUTION: also resets S-DSP and S-SMP registers
     pub fn reset(&mut self, registers: ResetRegisters) {
         self.emu.pin_mut().reset(registers);
+        self.pending_pos = Self::AUDIO_BUFFER_SIZE;
+        self.ticks_audio.clear();
+        self.tick_overshoot = 0;
     }
 
     pub fn iplrom(&self) -> &[u8; 64] {
@@ -89,6 +165,15 @@ impl ShvcSoundEmu {
         self.emu.dsp_registers()
     }
 
+    /// Raw access to the S-DSP register file.
+    ///
+    /// CAUTION: Writing to this array does not update the S-DSP's internal state, it only
+    /// changes the value read back by the S-SMP (and `dsp_registers()`).
+    /// Use `write_dsp_register()` to change the S-DSP's behaviour.
+    pub fn dsp_registers_mut(&mut self) -> &mut [u8; 128] {
+        self.emu.pin_mut().dsp_registers_mut()
+    }
+
     /// This method is not reccomended for the `ESA` and `EDL` registers.
     pub fn write_dsp_register(self: &mut ShvcSoundEmu, addr: u8, value: u8) {
         self.emu.pin_mut().write_dsp_register(addr, value)
@@ -110,7 +195,280 @@ impl ShvcSoundEmu {
         self.emu.program_counter()
     }
 
+    /// Emulates `AUDIO_BUFFER_SAMPLES` stereo samples.
+    ///
+    /// CAUTION: discards any audio that has not been output by `flush_audio()`.
     pub fn emulate(&mut self) -> &[i16; Self::AUDIO_BUFFER_SIZE] {
+        self.ticks_audio.clear();
         self.emu.pin_mut().emulate()
     }
+
+    /// Advances the emulator by `n` S-SMP clock ticks without outputting any audio.
+    ///
+    /// The S-SMP cannot stop mid-instruction, any extra ticks emulated by this call are
+    /// subtracted from the next `emulate_ticks()` call.
+    ///
+    /// `apuram()` and `dsp_registers()` can be used to read the intermediate state and
+    /// `flush_audio()` returns the audio emulated by this method.
+    pub fn emulate_ticks(&mut self, n: u32) {
+        let skip = self.tick_overshoot.min(n);
+        self.tick_overshoot -= skip;
+
+        let mut remaining = n - skip;
+
+        while remaining > 0 {
+            let ticks = self.emu.pin_mut().emulate_ticks(remaining);
+
+            if ticks < remaining {
+                // Audio buffer is full
+                let buffer = self.emu.pin_mut().flush_audio();
+                self.ticks_audio.extend_from_slice(buffer);
+            }
+
+            self.tick_overshoot = ticks.saturating_sub(remaining);
+            remaining = remaining.saturating_sub(ticks);
+        }
+    }
+
+    /// Returns the audio emulated since the last `flush_audio()` or `emulate()` call.
+    ///
+    /// The output is interleaved stereo samples.
+    pub fn flush_audio(&mut self) -> &[i16] {
+        let len = self.emu.audio_buffer_len() as usize;
+        let buffer = self.emu.pin_mut().flush_audio();
+
+        self.flushed_audio.clear();
+        std::mem::swap(&mut self.flushed_audio, &mut self.ticks_audio);
+        self.flushed_audio.extend_from_slice(&buffer[..len]);
+
+        &self.flushed_audio
+    }
+
+    /// Fills `out` with interleaved stereo samples, returning the number of stereo pairs written.
+    ///
+    /// Samples left over from the last emulation step are output by the next call.
+    /// If `out` has an odd length, the last element is not written.
+    ///
+    /// CAUTION: `emulate()` and `emulate_per_voice()` skip any left over samples.
+    pub fn emulate_n_samples(&mut self, out: &mut [i16]) -> usize {
+        let n_pairs = out.len() / 2;
+        let mut out = &mut out[..n_pairs * 2];
+
+        while !out.is_empty() {
+            if self.pending_pos >= Self::AUDIO_BUFFER_SIZE {
+                self.pending = *self.emu.pin_mut().emulate();
+                self.pending_pos = 0;
+            }
+
+            let src = &self.pending[self.pending_pos..];
+            let n = src.len().min(out.len());
+
+            out[..n].copy_from_slice(&src[..n]);
+            out = &mut out[n..];
+            self.pending_pos += n;
+        }
+
+        n_pairs
+    }
+
+    /// Emulates the same number of samples as `emulate()` and also returns the output of each voice.
+    pub fn emulate_per_voice(&mut self) -> PerVoiceOutput {
+        self.ticks_audio.clear();
+        let mixed = *self.emu.pin_mut().emulate_per_voice();
+
+        PerVoiceOutput {
+            mixed,
+            voices: std::array::from_fn(|v| *self.emu.voice_output(v as u8)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DSP_FLG: u8 = 0x6c;
+    const DSP_KON: u8 = 0x4c;
+    const DSP_KOF: u8 = 0x5c;
+    const DSP_DIR: u8 = 0x5d;
+    const DSP_MVOL_L: u8 = 0x0c;
+    const DSP_MVOL_R: u8 = 0x1c;
+
+    const SPIN_LOOP_ADDR: u16 = 0x0200;
+    const DIR_ADDR: u16 = 0x0300;
+    const BRR_ADDR: u16 = 0x0400;
+
+    /// Plays a looping saw wave on every voice (with different volumes and pitches)
+    fn saw_wave_emu() -> ShvcSoundEmu {
+        let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+        let apuram = emu.apuram_mut();
+
+        // bra -2
+        apuram[usize::from(SPIN_LOOP_ADDR)..][..2].copy_from_slice(&[0x2f, 0xfe]);
+
+        let [brr_l, brr_h] = BRR_ADDR.to_le_bytes();
+        apuram[usize::from(DIR_ADDR)..][..4].copy_from_slice(&[brr_l, brr_h, brr_l, brr_h]);
+
+        // Single looping BRR block (shift 8, filter 0)
+        let brr = &mut apuram[usize::from(BRR_ADDR)..][..9];
+        brr[0] = 0x83;
+        for (i, b) in brr[1..].iter_mut().enumerate() {
+            let i = i as u8 * 2;
+            *b = ((i.wrapping_sub(8) & 0xf) << 4) | (i.wrapping_sub(7) & 0xf);
+        }
+
+        emu.reset(ResetRegisters {
+            pc: SPIN_LOOP_ADDR,
+            a: 0,
+            x: 0,
+            y: 0,
+            psw: 0,
+            sp: 0xff,
+            esa: 0xff,
+            edl: 0,
+        });
+
+        // Unmute, disable echo writes
+        emu.write_dsp_register(DSP_FLG, 0x20);
+        emu.write_dsp_register(DSP_DIR, (DIR_ADDR >> 8) as u8);
+        emu.write_dsp_register(DSP_MVOL_L, 0x50);
+        emu.write_dsp_register(DSP_MVOL_R, 0x30);
+
+        for v in 0..N_VOICES as u8 {
+            let i = v as i8;
+            emu.set_voice_vol_left(v, 0x10 + i * 4);
+            emu.set_voice_vol_right(v, 0x28 - i * 4);
+            emu.set_voice_pitch(v, u16::from(0x08 + v) << 8);
+            emu.set_voice_srcn(v, 0);
+            emu.set_voice_adsr(v, [0, 0]); // gain
+            emu.set_voice_gain(v, 0x7f);
+        }
+        emu.write_dsp_register(DSP_KOF, 0);
+        emu.write_dsp_register(DSP_KON, 0xff);
+
+        emu
+    }
+
+    #[test]
+    fn sum_of_voices_equals_mixed_output() {
+        let mut emu = saw_wave_emu();
+
+        let mut non_zero_voices = [false; N_VOICES];
+
+        for _ in 0..8 {
+            let o = emu.emulate_per_voice();
+
+            for (i, &m) in o.mixed.iter().enumerate() {
+                let sum: i32 = o.voices.iter().map(|v| i32::from(v[i])).sum();
+
+                // Each voice is rounded down when the main volume is applied
+                let diff = i32::from(m) - sum;
+                assert!(
+                    (0..=N_VOICES as i32).contains(&diff),
+                    "sample {i}: mixed {m}, sum {sum}"
+                );
+            }
+
+            for (nz, v) in non_zero_voices.iter_mut().zip(&o.voices) {
+                *nz |= v.iter().any(|&s| s != 0);
+            }
+        }
+
+        assert_eq!(non_zero_voices, [true; N_VOICES]);
+    }
+
+    #[test]
+    fn try_new_and_default() {
+        let iplrom = std::array::from_fn(|i| i as u8);
+
+        let emu = ShvcSoundEmu::try_new(&iplrom).unwrap();
+        assert_eq!(emu.iplrom(), &iplrom);
+
+        let emu = ShvcSoundEmu::default();
+        assert_eq!(emu.iplrom(), &[0; 64]);
+    }
+
+    #[test]
+    fn emulate_per_voice_matches_emulate() {
+        let mut emu1 = saw_wave_emu();
+        let mut emu2 = saw_wave_emu();
+
+        for _ in 0..4 {
+            let o = emu1.emulate_per_voice();
+            assert_eq!(&o.mixed, emu2.emulate());
+        }
+    }
+
+    #[test]
+    fn emulate_n_samples_matches_emulate() {
+        let mut emu1 = saw_wave_emu();
+        let mut emu2 = saw_wave_emu();
+
+        let mut expected = Vec::new();
+        for _ in 0..4 {
+            expected.extend_from_slice(emu2.emulate());
+        }
+
+        let mut output = Vec::new();
+        for size in [1, 2, 100, 512, 600, 3, 0, 832] {
+            let mut buffer = vec![0x7fff; size];
+            let n = emu1.emulate_n_samples(&mut buffer);
+            assert_eq!(n, size / 2);
+
+            if size % 2 == 1 {
+                assert_eq!(buffer.last(), Some(&0x7fff));
+            }
+            output.extend_from_slice(&buffer[..n * 2]);
+        }
+
+        assert_eq!(output.len(), expected.len());
+        assert!(output == expected);
+    }
+
+    #[test]
+    fn emulate_ticks_matches_emulate() {
+        let mut emu1 = saw_wave_emu();
+        let mut emu2 = saw_wave_emu();
+
+        let mut expected = Vec::new();
+        for _ in 0..4 {
+            expected.extend_from_slice(emu2.emulate());
+        }
+
+        let mut output = Vec::new();
+
+        emu1.emulate_ticks(ShvcSoundEmu::TICKS_PER_SAMPLE * 10);
+        output.extend_from_slice(emu1.flush_audio());
+        assert!((18..=22).contains(&output.len()), "{}", output.len());
+
+        // Emulate more than one audio buffer before flushing
+        for _ in 0..30 {
+            emu1.emulate_ticks(1000);
+        }
+        output.extend_from_slice(emu1.flush_audio());
+
+        for _ in 0..100 {
+            emu1.emulate_ticks(7);
+            output.extend_from_slice(emu1.flush_audio());
+        }
+
+        let expected_len = (10 * 64 + 30 * 1000 + 100 * 7) / 64 * 2;
+        assert!(output.len().abs_diff(expected_len) <= 2, "{}", output.len());
+        assert!(output[..] == expected[..output.len()]);
+    }
+
+    #[test]
+    fn dsp_registers_mut_does_not_change_output() {
+        let mut emu1 = saw_wave_emu();
+        let mut emu2 = saw_wave_emu();
+
+        emu1.dsp_registers_mut()[usize::from(DSP_MVOL_L)] = 0;
+        assert_eq!(emu1.dsp_registers()[usize::from(DSP_MVOL_L)], 0);
+        assert_eq!(emu2.dsp_registers()[usize::from(DSP_MVOL_L)], 0x50);
+
+        for _ in 0..4 {
+            assert_eq!(emu1.emulate(), emu2.emulate());
+        }
+    }
 }
@@ -23,6 +23,10 @@ struct SampleBuffer {
     return pos > MASK;
   }
 
+  auto size() const -> u32 {
+    return pos;
+  }
+
   auto samples() const -> const std::array<int16_t, N_SAMPLES * 2>& {
     return buffer;
   }
@@ -62,6 +62,10 @@ auto ShvcSoundEmu::dsp_registers() const -> const std::array<uint8_t, 128>& {
   return smp.dsp.registers;
 }
 
+auto ShvcSoundEmu::dsp_registers_mut () -> std::array<uint8_t, 128>& {
+  return smp.dsp.registers;
+}
+
 auto ShvcSoundEmu::write_dsp_register(uint8_t addr, uint8_t value) -> void {
   if(addr < smp.dsp.registers.size()) {
     smp.dsp.write(addr, value);
@@ -92,14 +96,69 @@ auto ShvcSoundEmu::program_counter() const -> uint16_t {
   return smp.r.pc.w;
 }
 
+auto ShvcSoundEmu::cpu_registers() const -> ResetRegisters {
+  constexpr uint8_t ESA_REG = 0x6d;
+  constexpr uint8_t EDL_REG = 0x7d;
+
+  ResetRegisters r;
+
+  r.pc = smp.r.pc.w;
+  r.a = smp.r.ya.byte.l;
+  r.x = smp.r.x;
+  r.y = smp.r.ya.byte.h;
+  r.psw = uint8_t(smp.r.p);
+  r.sp = smp.r.s;
+
+  r.esa = smp.dsp.registers[ESA_REG];
+  r.edl = smp.dsp.registers[EDL_REG];
+
+  return r;
+}
+
 auto ShvcSoundEmu::emulate() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>& {
-  smp.dsp.sampleBuffer.reset();
+  flush_audio();
+  emulate_ticks(UINT32_MAX);
+
+  return smp.dsp.sampleBuffer.samples();
+}
+
+// Emulates at least `ticks` S-SMP clocks (instructions are not split),
+// stopping early if the audio buffer is full.
+// Returns the number of clocks emulated.
+auto ShvcSoundEmu::emulate_ticks(uint32_t ticks) -> uint32_t {
+  const uint64_t start = smp.clocks;
 
-  while(!smp.dsp.sampleBuffer.isFull()) {
+  while(smp.clocks - start < ticks && !smp.dsp.sampleBuffer.isFull()) {
     smp.main();
   }
 
+  return smp.clocks - start;
+}
+
+auto ShvcSoundEmu::audio_buffer_len() const -> uint32_t {
+  return smp.dsp.sampleBuffer.size();
+}
+
+// Resets the audio buffers.
+// The returned samples are valid until the next emulate call.
+auto ShvcSoundEmu::flush_audio() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>& {
+  smp.dsp.sampleBuffer.reset();
+  for(auto& b : smp.dsp.voiceSampleBuffers) b.reset();
+
   return smp.dsp.sampleBuffer.samples();
 }
 
+// Same as emulate(), but also writes the output of each voice to the voice_output() buffers.
+auto ShvcSoundEmu::emulate_per_voice() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>& {
+  smp.dsp.voiceCapture = true;
+  emulate();
+  smp.dsp.voiceCapture = false;
+
+  return smp.dsp.sampleBuffer.samples();
+}
+
+auto ShvcSoundEmu::voice_output(uint8_t voice) const -> const std::array<int16_t, AUDIO_BUFFER_SIZE>& {
+  return smp.dsp.voiceSampleBuffers.at(voice & 7).samples();
+}
+
 }
@@ -41,6 +41,7 @@ struct ShvcSoundEmu {
   auto apuram_mut () -> std::array<uint8_t, 65536>&;
 
   auto dsp_registers() const -> const std::array<uint8_t, 128>&;
+  auto dsp_registers_mut () -> std::array<uint8_t, 128>&;
 
   auto write_dsp_register(uint8_t addr, uint8_t value) -> void;
   auto write_smp_register(uint8_t addr, uint8_t value) -> void;
@@ -49,9 +50,17 @@ struct ShvcSoundEmu {
   auto write_io_ports(std::array<uint8_t, 4> ports) -> void;
 
   auto program_counter() const -> uint16_t;
+  auto cpu_registers() const -> ResetRegisters;
 
   auto emulate() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>&;
 
+  auto emulate_ticks(uint32_t ticks) -> uint32_t;
+  auto audio_buffer_len() const -> uint32_t;
+  auto flush_audio() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>&;
+
+  auto emulate_per_voice() -> const std::array<int16_t, AUDIO_BUFFER_SIZE>&;
+  auto voice_output(uint8_t voice) const -> const std::array<int16_t, AUDIO_BUFFER_SIZE>&;
+
 private:
   SMP smp;
 };
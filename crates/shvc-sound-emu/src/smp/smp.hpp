@@ -19,6 +19,9 @@ struct SMP : SPC700 {
   DSP dsp;
   std::array<uint8_t, 64> iplrom;
 
+  //number of clocks stepped since the emulator was created
+  u64 clocks = 0;
+
 private:
   struct IO {
     //timing
@@ -20,6 +20,7 @@ inline auto SMP::wait(bool halve, maybe<n16> address) -> void {
 }
 
 inline auto SMP::step(u32 clocks) -> void {
+  this->clocks += clocks;
   dsp.smpStepped(clocks);
 }
 
@@ -0,0 +1,201 @@
+//! Emulator state snapshots
+
+use crate::{ResetRegisters, ShvcSoundEmu};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const DSP_KON: u8 = 0x4c;
+const DSP_ESA: u8 = 0x6d;
+const DSP_EDL: u8 = 0x7d;
+
+const SMP_CONTROL: usize = 0xf1;
+
+/// Writable S-SMP registers restored from audio-RAM (`CONTROL` is handled separately)
+const SMP_REGISTERS: [u8; 4] = [0xf2, 0xfa, 0xfb, 0xfc];
+
+/// Only the timer enable and IPL ROM enable bits are restored,
+/// the other bits would clear the IO ports.
+const SMP_CONTROL_MASK: u8 = 0x87;
+
+/// A copy of the emulator's memory and registers.
+///
+/// The S-DSP's internal voice state (envelopes, BRR decoder position) and the S-SMP timer
+/// counters are not included.  When the snapshot is restored, the voices in the `KON`
+/// register are keyed-on again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EmulatorSnapshot {
+    #[cfg_attr(feature = "serde", serde(with = "byte_array"))]
+    apuram: Box<[u8; 0x10000]>,
+    #[cfg_attr(feature = "serde", serde(with = "byte_array"))]
+    dsp_registers: Box<[u8; 128]>,
+    #[cfg_attr(feature = "serde", serde(with = "byte_array"))]
+    iplrom: Box<[u8; 64]>,
+
+    pc: u16,
+    a: u8,
+    x: u8,
+    y: u8,
+    psw: u8,
+    sp: u8,
+}
+
+#[cfg(feature = "serde")]
+mod byte_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        data: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Box<[u8; N]>, D::Error> {
+        let v = Vec::<u8>::deserialize(deserializer)?;
+        let len = v.len();
+
+        v.into_boxed_slice()
+            .try_into()
+            .map_err(|_| serde::de::Error::invalid_length(len, &"a fixed size byte array"))
+    }
+}
+
+impl ShvcSoundEmu {
+    pub fn snapshot(&self) -> EmulatorSnapshot {
+        let r = self.emu.cpu_registers();
+
+        EmulatorSnapshot {
+            apuram: Box::new(*self.apuram()),
+            dsp_registers: Box::new(*self.dsp_registers()),
+            iplrom: Box::new(*self.iplrom()),
+            pc: r.pc,
+            a: r.a,
+            x: r.x,
+            y: r.y,
+            psw: r.psw,
+            sp: r.sp,
+        }
+    }
+
+    /// CAUTION: resets the emulator.
+    pub fn restore(&mut self, snapshot: &EmulatorSnapshot) {
+        let dsp = &snapshot.dsp_registers;
+
+        self.load_state(
+            &snapshot.apuram,
+            dsp,
+            &snapshot.iplrom,
+            ResetRegisters {
+                pc: snapshot.pc,
+                a: snapshot.a,
+                x: snapshot.x,
+                y: snapshot.y,
+                psw: snapshot.psw,
+                sp: snapshot.sp,
+                esa: dsp[usize::from(DSP_ESA)],
+                edl: dsp[usize::from(DSP_EDL)],
+            },
+        );
+    }
+
+    /// Resets the emulator and loads memory and registers.
+    pub(crate) fn load_state(
+        &mut self,
+        apuram: &[u8; 0x10000],
+        dsp_registers: &[u8; 128],
+        iplrom: &[u8; 64],
+        registers: ResetRegisters,
+    ) {
+        self.iplrom_mut().copy_from_slice(iplrom);
+        self.apuram_mut().copy_from_slice(apuram);
+
+        self.reset(registers);
+
+        // KON is written last so the other voice registers are set before the key-on
+        for (addr, &value) in (0..).zip(dsp_registers) {
+            if !matches!(addr, DSP_KON | DSP_ESA | DSP_EDL) {
+                self.write_dsp_register(addr, value);
+            }
+        }
+        self.write_dsp_register(DSP_KON, dsp_registers[usize::from(DSP_KON)]);
+
+        self.write_smp_register(SMP_CONTROL as u8, apuram[SMP_CONTROL] & SMP_CONTROL_MASK);
+        for addr in SMP_REGISTERS {
+            self.write_smp_register(addr, apuram[usize::from(addr)]);
+        }
+        self.write_io_ports(apuram[0xf4..0xf8].try_into().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_emu() -> ShvcSoundEmu {
+        let mut emu = ShvcSoundEmu::default();
+
+        let apuram = emu.apuram_mut();
+        // mov a, #$12 ; mov x, #$34 ; inc $80 ; bra -4
+        apuram[0x200..][..8].copy_from_slice(&[0xe8, 0x12, 0xcd, 0x34, 0xab, 0x80, 0x2f, 0xfc]);
+
+        emu.reset(ResetRegisters {
+            pc: 0x200,
+            a: 0,
+            x: 0,
+            y: 0,
+            psw: 0,
+            sp: 0xff,
+            esa: 0xff,
+            edl: 0,
+        });
+        emu.set_voice_pitch(2, 0x1234);
+
+        emu
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut emu = test_emu();
+        emu.emulate_ticks(1000);
+
+        let snapshot = emu.snapshot();
+        assert_eq!(snapshot.a, 0x12);
+        assert_eq!(snapshot.x, 0x34);
+
+        emu.emulate();
+        assert_ne!(emu.snapshot(), snapshot);
+
+        emu.restore(&snapshot);
+        assert_eq!(emu.snapshot(), snapshot);
+        assert_eq!(emu.voice_pitch(2), 0x1234);
+
+        // Restoring the snapshot into a different emulator produces the same state
+        let mut emu2 = ShvcSoundEmu::default();
+        emu2.restore(&snapshot);
+        emu.emulate();
+        emu2.emulate();
+        assert_eq!(emu.snapshot(), emu2.snapshot());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_serde() {
+        let mut emu = test_emu();
+        emu.emulate_ticks(1000);
+
+        let snapshot = emu.snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EmulatorSnapshot>(&json).unwrap(),
+            snapshot
+        );
+
+        let json = json.replacen("[", "[1,", 1);
+        assert!(serde_json::from_str::<EmulatorSnapshot>(&json).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! .spc file loading
+
+use crate::{ResetRegisters, ShvcSoundEmu};
+
+const SPC_FILE_SIZE: usize = 0x10200;
+
+const MAGIC: &[u8] = b"SNES-SPC700 Sound File Data";
+
+const HEADER_HAS_ID666: u8 = 26;
+
+const APURAM_OFFSET: usize = 0x100;
+const DSP_REGISTERS_OFFSET: usize = 0x10100;
+const IPL_OFFSET: usize = 0x101c0;
+
+const DSP_ESA: u8 = 0x6d;
+const DSP_EDL: u8 = 0x7d;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpcLoadError {
+    Truncated(usize),
+    BadMagic,
+    RegisterOutOfRange { register: &'static str, value: u8 },
+}
+
+impl std::fmt::Display for SpcLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated(size) => write!(
+                f,
+                "spc file is truncated ({size} bytes, expected {SPC_FILE_SIZE})"
+            ),
+            Self::BadMagic => write!(f, "not a .spc file (bad magic bytes)"),
+            Self::RegisterOutOfRange { register, value } => {
+                write!(f, "{register} register out of range (0x{value:02x})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpcLoadError {}
+
+/// ID666 tag fields of a `.spc` file.
+///
+/// Empty fields are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpcMetadata {
+    pub title: Option<String>,
+    pub game: Option<String>,
+    pub dumper: Option<String>,
+    pub comments: Option<String>,
+    pub artist: Option<String>,
+    pub song_length_seconds: Option<u32>,
+    pub fadeout_millis: Option<u32>,
+}
+
+fn id666_string(s: &[u8]) -> Option<String> {
+    let s = match s.iter().position(|&b| b == 0) {
+        Some(p) => &s[..p],
+        None => s,
+    };
+    let s = String::from_utf8_lossy(s);
+    let s = s.trim();
+
+    match s.is_empty() {
+        true => None,
+        false => Some(s.to_owned()),
+    }
+}
+
+fn id666_text_number(s: &[u8]) -> Option<u32> {
+    id666_string(s)?.parse().ok().filter(|&n| n > 0)
+}
+
+fn id666_binary_number(s: &[u8]) -> Option<u32> {
+    let mut b = [0; 4];
+    b[..s.len()].copy_from_slice(s);
+    Some(u32::from_le_bytes(b)).filter(|&n| n > 0)
+}
+
+fn parse_id666(header: &[u8]) -> SpcMetadata {
+    if header[0x23] != HEADER_HAS_ID666 {
+        return SpcMetadata::default();
+    }
+
+    // The binary format stores the song length and fadeout as integers
+    // and the artist field starts one byte earlier.
+    let text_format = header[0xa9..0xb1]
+        .iter()
+        .all(|&b| b == 0 || b.is_ascii_digit());
+
+    let common = SpcMetadata {
+        title: id666_string(&header[0x2e..0x4e]),
+        game: id666_string(&header[0x4e..0x6e]),
+        dumper: id666_string(&header[0x6e..0x7e]),
+        comments: id666_string(&header[0x7e..0x9e]),
+        ..Default::default()
+    };
+
+    match text_format {
+        true => SpcMetadata {
+            artist: id666_string(&header[0xb1..0xd1]),
+            song_length_seconds: id666_text_number(&header[0xa9..0xac]),
+            fadeout_millis: id666_text_number(&header[0xac..0xb1]),
+            ..common
+        },
+        false => SpcMetadata {
+            artist: id666_string(&header[0xb0..0xd0]),
+            song_length_seconds: id666_binary_number(&header[0xa9..0xac]),
+            fadeout_millis: id666_binary_number(&header[0xac..0xb0]),
+            ..common
+        },
+    }
+}
+
+impl ShvcSoundEmu {
+    /// Loads the state of a `.spc` file into the emulator.
+    ///
+    /// CAUTION: resets the emulator.
+    pub fn load_spc_file(&mut self, data: &[u8]) -> Result<SpcMetadata, SpcLoadError> {
+        if !data.starts_with(MAGIC) {
+            return Err(SpcLoadError::BadMagic);
+        }
+        if data.len() < SPC_FILE_SIZE {
+            return Err(SpcLoadError::Truncated(data.len()));
+        }
+
+        let header = &data[..APURAM_OFFSET];
+        let apuram = data[APURAM_OFFSET..DSP_REGISTERS_OFFSET]
+            .try_into()
+            .unwrap();
+        let dsp_registers: &[u8; 128] = data[DSP_REGISTERS_OFFSET..][..128].try_into().unwrap();
+        let ipl = data[IPL_OFFSET..SPC_FILE_SIZE].try_into().unwrap();
+
+        let edl = dsp_registers[usize::from(DSP_EDL)];
+        if edl > 0x0f {
+            return Err(SpcLoadError::RegisterOutOfRange {
+                register: "EDL",
+                value: edl,
+            });
+        }
+
+        self.load_state(
+            apuram,
+            dsp_registers,
+            ipl,
+            ResetRegisters {
+                pc: u16::from_le_bytes([header[0x25], header[0x26]]),
+                a: header[0x27],
+                x: header[0x28],
+                y: header[0x29],
+                psw: header[0x2a],
+                sp: header[0x2b],
+                esa: dsp_registers[usize::from(DSP_ESA)],
+                edl,
+            },
+        );
+
+        Ok(parse_id666(header))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PC: u16 = 0x0234;
+
+    fn spc_file() -> Vec<u8> {
+        let mut out = vec![0; SPC_FILE_SIZE];
+
+        out[..33].copy_from_slice(b"SNES-SPC700 Sound File Data v0.30");
+        out[0x21] = 26;
+        out[0x22] = 26;
+        out[0x23] = HEADER_HAS_ID666;
+        out[0x24] = 30;
+
+        out[0x25..0x27].copy_from_slice(&PC.to_le_bytes());
+        out[0x2b] = 0xef;
+
+        out[0x2e..][..5].copy_from_slice(b"Title");
+        out[0x4e..][..4].copy_from_slice(b"Game");
+        out[0xa9..][..3].copy_from_slice(b"123");
+        out[0xac..][..4].copy_from_slice(b"5000");
+        out[0xb1..][..6].copy_from_slice(b"Artist");
+
+        // bra -2
+        out[APURAM_OFFSET + usize::from(PC)..][..2].copy_from_slice(&[0x2f, 0xfe]);
+        out[APURAM_OFFSET + 0xf4..][..4].copy_from_slice(&[1, 2, 3, 4]);
+
+        // DIR, FLG, ESA, EDL
+        out[DSP_REGISTERS_OFFSET + 0x5d] = 0x12;
+        out[DSP_REGISTERS_OFFSET + 0x6c] = 0x20;
+        out[DSP_REGISTERS_OFFSET + 0x6d] = 0xd0;
+        out[DSP_REGISTERS_OFFSET + 0x7d] = 0x02;
+
+        for (i, b) in out[IPL_OFFSET..].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        out
+    }
+
+    #[test]
+    fn load_spc_file() {
+        let spc = spc_file();
+
+        let mut emu = ShvcSoundEmu::default();
+        let metadata = emu.load_spc_file(&spc).unwrap();
+
+        assert_eq!(
+            metadata,
+            SpcMetadata {
+                title: Some("Title".to_owned()),
+                game: Some("Game".to_owned()),
+                dumper: None,
+                comments: None,
+                artist: Some("Artist".to_owned()),
+                song_length_seconds: Some(123),
+                fadeout_millis: Some(5000),
+            }
+        );
+
+        assert_eq!(emu.program_counter(), PC);
+        assert!(emu.apuram()[..] == spc[APURAM_OFFSET..DSP_REGISTERS_OFFSET]);
+        assert_eq!(emu.iplrom()[..], spc[IPL_OFFSET..]);
+        assert_eq!(emu.dsp_registers()[0x5d], 0x12);
+        assert_eq!(emu.dsp_registers()[0x7d], 0x02);
+
+        emu.emulate();
+        assert_eq!(emu.program_counter(), PC);
+    }
+
+    #[test]
+    fn load_spc_file_errors() {
+        let mut emu = ShvcSoundEmu::default();
+
+        let spc = spc_file();
+        assert_eq!(
+            emu.load_spc_file(&spc[..0x10180]),
+            Err(SpcLoadError::Truncated(0x10180))
+        );
+
+        let mut spc = spc_file();
+        spc[0] = b'N';
+        assert_eq!(emu.load_spc_file(&spc), Err(SpcLoadError::BadMagic));
+
+        let mut spc = spc_file();
+        spc[DSP_REGISTERS_OFFSET + 0x7d] = 0x10;
+        assert_eq!(
+            emu.load_spc_file(&spc),
+            Err(SpcLoadError::RegisterOutOfRange {
+                register: "EDL",
+                value: 0x10
+            })
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Machine readable error output (`--json-errors`)
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use compiler::errors::{
+    ChannelError, ErrorWithPos, MmlChannelError, MmlCompileErrors, MmlLineError, MmlWarning,
+};
+use compiler::mml::{IncludeMap, MmlValidationResult};
+use compiler::FilePosRange;
+
+use serde::Serialize;
+
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The location of the `#include` directive that included the file
+#[derive(Debug, Serialize)]
+struct IncludeSite {
+    file: String,
+    line: u32,
+}
+
+/// A single error or warning.
+///
+/// Line and column numbers are the same as the text output.
+/// The position fields are `None` if the error does not have a file position.
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    end_line: Option<u32>,
+    end_column: Option<u32>,
+    included_from: Option<IncludeSite>,
+    severity: Severity,
+    code: String,
+    message: String,
+}
+
+/// An error or warning with a machine readable code
+trait ErrorCode: Display {
+    /// The name of the error's enum variant (ie, `NoInstrument` for `ChannelError::NoInstrument`)
+    fn code(&self) -> &'static str;
+}
+
+impl ErrorCode for MmlLineError {
+    fn code(&self) -> &'static str {
+        MmlLineError::code(self)
+    }
+}
+
+impl ErrorCode for ChannelError {
+    fn code(&self) -> &'static str {
+        ChannelError::code(self)
+    }
+}
+
+impl ErrorCode for MmlWarning {
+    fn code(&self) -> &'static str {
+        MmlWarning::code(self)
+    }
+}
+
+/// Collects compiler errors and warnings and outputs them as a JSON array.
+#[derive(Debug)]
+pub struct JsonErrorReporter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl JsonErrorReporter {
+    pub const fn new() -> Self {
+        Self {
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Adds an error or warning without a file position
+    pub fn add_message(&mut self, severity: Severity, code: &str, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            file: None,
+            line: None,
+            column: None,
+            end_line: None,
+            end_column: None,
+            included_from: None,
+            severity,
+            code: code.to_owned(),
+            message: message.trim_end().to_owned(),
+        });
+    }
+
+    fn add_with_pos<T: ErrorCode>(
+        &mut self,
+        severity: Severity,
+        file_name: &str,
+        include_map: &IncludeMap,
+        e: &ErrorWithPos<T>,
+    ) {
+        let pos: &FilePosRange = &e.0;
+
+        let (file_name, line, included_from) = match include_map.source_location(pos.line_number())
+        {
+            Some(l) => (
+                l.file_name,
+                l.line_number,
+                l.include_site.map(|(file, line)| IncludeSite {
+                    file: file.to_owned(),
+                    line,
+                }),
+            ),
+            None => (file_name, pos.line_number(), None),
+        };
+
+        // The file position ranges never span multiple lines
+        let length = pos.index_end().saturating_sub(pos.index_start());
+
+        self.diagnostics.push(Diagnostic {
+            file: Some(file_name.to_owned()),
+            line: Some(line),
+            column: Some(pos.line_char()),
+            end_line: Some(line),
+            end_column: Some(pos.line_char() + length),
+            included_from,
+            severity,
+            code: e.1.code().to_owned(),
+            message: e.1.to_string(),
+        });
+    }
+
+    fn add_channel_errors(
+        &mut self,
+        errors: &MmlCompileErrors,
+        channel_errors: &[MmlChannelError],
+        limit: usize,
+    ) {
+        for c in channel_errors {
+            for e in c.errors.iter().take(limit) {
+                self.add_with_pos(Severity::Error, &errors.file_name, &errors.include_map, e);
+            }
+        }
+    }
+
+    /// Limits the number of errors added per channel, subroutine and header
+    pub fn add_mml_errors(&mut self, errors: &MmlCompileErrors, limit: usize) {
+        for e in errors.line_errors.iter().take(limit) {
+            self.add_with_pos(Severity::Error, &errors.file_name, &errors.include_map, e);
+        }
+        self.add_channel_errors(errors, &errors.subroutine_errors, limit);
+        self.add_channel_errors(errors, &errors.channel_errors, limit);
+    }
+
+    pub fn add_mml_warnings(&mut self, r: &MmlValidationResult) {
+        for w in &r.warnings {
+            self.add_with_pos(
+                Severity::Warning,
+                &r.errors.file_name,
+                &r.errors.include_map,
+                w,
+            );
+        }
+    }
+
+    /// Serializes the errors and warnings and clears the reporter
+    pub fn take_json(&mut self) -> String {
+        let json = match serde_json::to_string(&self.diagnostics) {
+            Ok(s) => s,
+            // This should not happen
+            Err(e) => panic!("Error serializing errors: {}", e),
+        };
+        self.diagnostics.clear();
+        json
+    }
+}
+
+/// Prints errors to stderr or, if `--json-errors` is active, collects them in a `JsonErrorReporter`.
+#[derive(Debug)]
+pub struct ErrorReporter {
+    json: Option<JsonErrorReporter>,
+    n_errors: usize,
+}
+
+impl ErrorReporter {
+    pub fn new(json_errors: bool) -> Self {
+        Self {
+            json: json_errors.then(JsonErrorReporter::new),
+            n_errors: 0,
+        }
+    }
+
+    /// The number of errors reported by `report_error()` and `report_errors()`
+    pub fn n_errors(&self) -> usize {
+        self.n_errors
+    }
+
+    pub fn json_errors_active(&self) -> bool {
+        self.json.is_some()
+    }
+
+    /// Returns `None` if `--json-errors` is not active
+    pub fn json(&mut self) -> Option<&mut JsonErrorReporter> {
+        self.json.as_mut()
+    }
+
+    /// Prints an error message to stderr or adds it to the JSON errors
+    pub fn report_error(&mut self, message: &str) {
+        self.report_errors(message, 1);
+    }
+
+    /// Prints a message containing `n_errors` errors to stderr or adds it to the JSON errors
+    pub fn report_errors(&mut self, message: &str, n_errors: usize) {
+        self.n_errors += n_errors;
+
+        match &mut self.json {
+            Some(j) => j.add_message(Severity::Error, "Error", message),
+            None => eprintln!("{}", message),
+        }
+    }
+
+    /// Prints the JSON errors to stderr (if `--json-errors` is active)
+    pub fn flush(&mut self) {
+        if let Some(j) = &mut self.json {
+            eprintln!("{}", j.take_json());
+        }
+    }
+}
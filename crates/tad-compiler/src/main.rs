@@ -6,37 +6,84 @@
 
 #![forbid(unsafe_code)]
 
+mod json_errors;
+
 use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
 
 use compiler::{
+    audio_driver,
+    bytecode_disassembler::disassemble_song,
+    bytecode_interpreter::SongInterpreter,
     common_audio_data::{build_common_audio_data, CommonAudioData},
     data::{
-        is_name_or_id, load_text_file_with_limit, load_text_file_with_limit_path, Name, Song,
-        TextFile, UniqueNamesProjectFile,
+        is_name_or_id, load_text_file_with_limit, load_text_file_with_limit_path,
+        InstrumentOrSample, Name, Song, TextFile, UniqueNamesProjectFile,
+    },
+    driver_constants::{addresses, AUDIO_RAM_SIZE, MAX_COMMON_DATA_SIZE},
+    emulator::load_song_into_emulator,
+    envelope::DSP_SAMPLE_RATE,
+    errors::{
+        CommonAudioDataErrors, MmlChannelError, PitchTableError, SampleAndInstrumentDataError,
+        SfxSubroutineErrors, SongError, SoundEffectErrorList, SoundEffectsFileError,
     },
     export::{
         bin_include_path, Ca65Exporter, Ca65MemoryMap, Exporter, MemoryMapMode, PvExporter,
         PvMemoryMap, SuffixType, Tass64Exporter, Tass64MemoryMap,
     },
-    mml::{compile_mml, MmlTickCountTable},
+    mml::{compile_mml, validate_mml, MmlTickCountTable},
     pitch_table::{build_pitch_table, PitchTable},
-    samples::build_sample_and_instrument_data,
+    render::{render_song_file_to_pcm, render_song_to_pcm},
+    samples::{build_sample_and_instrument_data, SampleAndInstrumentData},
     sfx_file,
-    songs::{song_duration_string, validate_song_size, SongData},
+    songs::{blank_song, song_duration_string, validate_echo_buffer, validate_song_size, SongData},
     sound_effects::{self, blank_compiled_sound_effects, CompiledSfxSubroutines, SfxExportOrder},
     spc_file_export::export_spc_file,
+    time::TickCounter,
+    wav_file,
 };
 
+use shvc_sound_emu::ShvcSoundEmu;
+
+use json_errors::ErrorReporter;
+
+use notify::{RecursiveMode, Watcher};
+
+use std::collections::BTreeSet;
 use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
 
+/// Reports the error to the `ErrorReporter`, prints the JSON errors and exits
 macro_rules! error {
-    ($($arg:tt)*) => {{
-        eprintln!($($arg)*);
+    ($errors:expr, $($arg:tt)*) => {{
+        let errors: &mut $crate::json_errors::ErrorReporter = $errors;
+        errors.report_error(&format!($($arg)*));
+        errors.flush();
         std::process::exit(1);
     }};
 }
 
+/// Returns the text output of a song error.
+///
+/// If `--json-errors` is active, the MML errors are added to the JSON errors and a short summary
+/// is returned instead.
+fn song_error_message(e: &SongError, max_errors: usize, errors: &mut ErrorReporter) -> String {
+    match (e, errors.json()) {
+        (SongError::MmlError(mml), Some(json)) => {
+            json.add_mml_errors(mml, max_errors);
+            match &mml.song_name {
+                Some(s) => format!("Error compiling {}", s),
+                None => format!("Error compiling {}", mml.file_name),
+            }
+        }
+        (e, _) => e.multiline_display_with_limit(max_errors).to_string(),
+    }
+}
+
 #[derive(Parser)]
 #[command(author, version)]
 #[command(about = "terrific audio driver compiler")]
@@ -44,6 +91,29 @@ macro_rules! error {
 struct ArgParser {
     #[command(subcommand)]
     command: Command,
+
+    #[arg(
+        long = "max-errors",
+        value_name = "N",
+        default_value = "30",
+        global = true,
+        help = "maximum number of MML errors to print per channel, subroutine or header"
+    )]
+    max_errors: usize,
+
+    #[arg(
+        long = "stats",
+        global = true,
+        help = "print compilation statistics (as JSON) to stderr"
+    )]
+    stats: bool,
+
+    #[arg(
+        long = "json-errors",
+        global = true,
+        help = "print errors and warnings as a JSON array to stderr"
+    )]
+    json_errors: bool,
 }
 
 #[derive(Subcommand)]
@@ -57,9 +127,33 @@ enum Command {
     /// Export a MML song as a .spc file
     Song2spc(Song2SpcArgs),
 
+    /// Load a song into the emulator and output an annotated hex dump of audio-RAM
+    DumpAram(DumpAramArgs),
+
+    /// Render a MML song to a 16-bit stereo .wav file
+    Song2wav(Song2WavArgs),
+
+    /// Render compiled common audio data and song data to a 16-bit stereo .wav file
+    Render(RenderArgs),
+
+    /// Disassemble the bytecode of a compiled song
+    Disassemble(DisassembleArgs),
+
     /// Check the project will compile successfully and all songs fit in audio-RAM
     Check(CheckProjectArgs),
 
+    /// Compile the project without writing any output, printing every error
+    Validate(ValidateProjectArgs),
+
+    /// Check a MML file for errors without compiling it
+    ValidateMml(ValidateMmlArgs),
+
+    /// List the instruments and samples in the project file
+    ListInstruments(ListArgs),
+
+    /// List the songs in the project file
+    ListSongs(ListArgs),
+
     /// Generate an ca65 include file containing songs and sound effect enums
     Ca65Enums(EnumArgs),
 
@@ -79,6 +173,9 @@ enum Command {
 
     /// Compile the project and output a PVSnesLib assembly file containing loadSongData() and .incbin statements
     PvExport(PvExportArgs),
+
+    /// Recompile common audio data or a MML song whenever an input file changes
+    Watch(WatchArgs),
 }
 
 #[derive(Args)]
@@ -123,12 +220,13 @@ fn blank_sfx() -> CompiledSfx {
 fn compile_sound_effects(
     pf: &UniqueNamesProjectFile,
     pitch_table: &PitchTable,
+    errors: &mut ErrorReporter,
 ) -> Result<CompiledSfx, ()> {
     match &pf.sound_effect_file {
         Some(sfx_file_source) => {
             match sfx_file::load_sound_effects_file(sfx_file_source, &pf.parent_path) {
                 Err(e) => {
-                    eprintln!("{}", e);
+                    errors.report_error(&e.to_string());
                     Err(())
                 }
                 Ok(sfx_file) => {
@@ -138,7 +236,10 @@ fn compile_sound_effects(
                         pitch_table,
                     ) {
                         Err(e) => {
-                            eprintln!("{}", e.multiline_display());
+                            errors.report_errors(
+                                &e.multiline_display().to_string(),
+                                n_sound_effects_file_errors(&e),
+                            );
                             Err(())
                         }
                         Ok((sub, sfx)) => {
@@ -149,7 +250,10 @@ fn compile_sound_effects(
                             ) {
                                 Ok(sfx) => Ok((sub, sfx)),
                                 Err(e) => {
-                                    eprintln!("Error compiling sound effects: {}", e);
+                                    errors.report_error(&format!(
+                                        "Error compiling sound effects: {}",
+                                        e
+                                    ));
                                     Err(())
                                 }
                             }
@@ -162,36 +266,58 @@ fn compile_sound_effects(
             if pf.sfx_export_order.n_sound_effects() == 0 {
                 Ok(blank_sfx())
             } else {
-                eprintln!("No sound effect file in {}", pf.file_name);
+                errors.report_error(&format!("No sound effect file in {}", pf.file_name));
                 Err(())
             }
         }
     }
 }
 
-fn compile_common_data(args: CompileCommonDataArgs) {
-    let output_arg = args.output.validate();
+/// Notes are not errors or warnings and are not printed if `--json-errors` is active
+fn print_sample_notes(samples: &SampleAndInstrumentData, errors: &ErrorReporter) {
+    if errors.json_errors_active() {
+        return;
+    }
 
-    let pf = load_project_file(&args.project_file);
+    for n in samples.notes() {
+        eprintln!("note: {}", n);
+    }
+}
 
-    let samples = match build_sample_and_instrument_data(&pf) {
+fn build_common_data(
+    pf: &UniqueNamesProjectFile,
+    errors: &mut ErrorReporter,
+) -> Result<CommonAudioData, String> {
+    let samples = match build_sample_and_instrument_data(pf) {
         Ok(samples) => samples,
-        Err(e) => {
-            error!("{}", e.multiline_display());
-        }
+        Err(e) => return Err(e.multiline_display().to_string()),
     };
+    print_sample_notes(&samples, errors);
 
-    let sfx = match compile_sound_effects(&pf, samples.pitch_table()) {
+    let sfx = match compile_sound_effects(pf, samples.pitch_table(), errors) {
         Ok(sfx) => sfx,
-        Err(()) => error!("Error compiling sound effects"),
+        Err(()) => return Err("Error compiling sound effects".to_owned()),
     };
 
-    let cad = match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
+    match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
+        Ok(data) => Ok(data),
+        Err(e) => Err(e.multiline_display().to_string()),
+    }
+}
+
+fn compile_common_data(args: CompileCommonDataArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let output_arg = args.output.validate(errors);
+
+    let pf = load_project_file(&args.project_file, errors);
+
+    let cad = match build_common_data(&pf, errors) {
         Ok(data) => data,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => error!(errors, "{}", e),
     };
 
-    write_data(output_arg, cad.data());
+    write_data(output_arg, cad.data(), errors);
+
+    CompileStats::new(&pf, cad.data().len()).with_sound_effects(&pf)
 }
 
 //
@@ -225,43 +351,52 @@ struct CompileSongDataArgs {
     options: SongOptions,
 }
 
-fn load_mml_file(
+/// Returns the path of the MML file and the song name (if the song is in the project file)
+fn find_mml_file(
     song_name_or_path: &OsStr,
     pf: &UniqueNamesProjectFile,
-) -> (TextFile, Option<Name>) {
+) -> Result<(PathBuf, Option<Name>), String> {
     // Safe, the `U+FFFD REPLACEMENT CHARACTER` is not a name character.
     let sn = song_name_or_path.to_string_lossy();
 
     if is_name_or_id(&sn) {
         if pf.songs.is_empty() {
-            error!("No songs in project file");
+            return Err("No songs in project file".to_owned());
         }
-        let (source, song_name) = match sn.parse::<usize>() {
+        let song = match sn.parse::<usize>() {
             Ok(song_id) => match pf.get_song_from_id(song_id) {
-                Some(s) => (s.source.clone(), Some(s.name.clone())),
-                None => error!(
-                    "Song number out of range ({} - {})",
-                    UniqueNamesProjectFile::FIRST_SONG_ID,
-                    pf.last_song_id()
-                ),
+                Some(s) => s,
+                None => {
+                    return Err(format!(
+                        "Song number out of range ({} - {})",
+                        UniqueNamesProjectFile::FIRST_SONG_ID,
+                        pf.last_song_id()
+                    ))
+                }
             },
             Err(_) => match pf.songs.get(&sn) {
-                Some(s) => (s.source.clone(), Some(s.name.clone())),
-                None => error!("Cannot find song: {}", sn),
+                Some(s) => s,
+                None => return Err(format!("Cannot find song: {}", sn)),
             },
         };
-        let text_file = match load_text_file_with_limit(&source, &pf.parent_path) {
-            Ok(tf) => tf,
-            Err(e) => error!("{}", e),
-        };
-        (text_file, song_name)
+        Ok((
+            song.source.to_path(&pf.parent_path),
+            Some(song.name.clone()),
+        ))
     } else {
-        let path = PathBuf::from(song_name_or_path);
-        let text_file = match load_text_file_with_limit_path(&path) {
-            Ok(tf) => tf,
-            Err(e) => error!("{}", e),
-        };
-        (text_file, None)
+        Ok((PathBuf::from(song_name_or_path), None))
+    }
+}
+
+fn load_mml_file(
+    song_name_or_path: &OsStr,
+    pf: &UniqueNamesProjectFile,
+) -> Result<(TextFile, Option<Name>), String> {
+    let (path, song_name) = find_mml_file(song_name_or_path, pf)?;
+
+    match load_text_file_with_limit_path(&path) {
+        Ok(tf) => Ok((tf, song_name)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
@@ -271,7 +406,9 @@ fn compile_song(
     options: &SongOptions,
     pf: &UniqueNamesProjectFile,
     pitch_table: &PitchTable,
-) -> SongData {
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> Result<SongData, String> {
     let song_data = match compile_mml(
         &mml_file,
         song_name,
@@ -279,30 +416,59 @@ fn compile_song(
         pitch_table,
     ) {
         Ok(mml) => mml,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => return Err(song_error_message(&e, max_errors, errors)),
     };
 
-    if options.print_tick_counts {
+    if options.print_tick_counts && !errors.json_errors_active() {
         println!("Duration: {}", song_duration_string(song_data.duration()));
-        println!("{}", MmlTickCountTable(&song_data));
+        println!("{}", MmlTickCountTable::new(&song_data));
     }
 
-    song_data
+    Ok(song_data)
 }
 
-fn compile_song_data(args: CompileSongDataArgs) {
-    let output_arg = args.output.validate();
-
-    let pf = load_project_file(&args.project_file);
-    let (mml_file, song_name) = load_mml_file(&args.song, &pf);
+fn build_song_data(
+    song: &OsStr,
+    options: &SongOptions,
+    pf: &UniqueNamesProjectFile,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> Result<SongData, String> {
+    let (mml_file, song_name) = load_mml_file(song, pf)?;
 
     let pitch_table = match build_pitch_table(&pf.instruments_and_samples) {
         Ok(pt) => pt,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => return Err(e.multiline_display().to_string()),
     };
-    let song_data = compile_song(mml_file, song_name, &args.options, &pf, &pitch_table);
 
-    write_data(output_arg, song_data.data());
+    compile_song(
+        mml_file,
+        song_name,
+        options,
+        pf,
+        &pitch_table,
+        max_errors,
+        errors,
+    )
+}
+
+fn compile_song_data(
+    args: CompileSongDataArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let output_arg = args.output.validate(errors);
+
+    let pf = load_project_file(&args.project_file, errors);
+
+    let song_data = match build_song_data(&args.song, &args.options, &pf, max_errors, errors) {
+        Ok(sd) => sd,
+        Err(e) => error!(errors, "{}", e),
+    };
+
+    write_data(output_arg, song_data.data(), errors);
+
+    CompileStats::new(&pf, song_data.data().len()).with_song(&song_data)
 }
 
 //
@@ -322,45 +488,483 @@ struct Song2SpcArgs {
     sound_effects: bool,
 }
 
-fn export_song_to_spc_file(args: Song2SpcArgs) {
-    let output_arg = args.song.output.validate();
-
-    let pf = load_project_file(&args.song.project_file);
-    let (mml_file, song_name) = load_mml_file(&args.song.song, &pf);
+fn export_song_to_spc_file(
+    args: Song2SpcArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let output_arg = args.song.output.validate(errors);
+
+    let pf = load_project_file(&args.song.project_file, errors);
+    let (mml_file, song_name) = match load_mml_file(&args.song.song, &pf) {
+        Ok(m) => m,
+        Err(e) => error!(errors, "{}", e),
+    };
 
     let samples = match build_sample_and_instrument_data(&pf) {
         Ok(s) => s,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => error!(errors, "{}", e.multiline_display()),
     };
+    print_sample_notes(&samples, errors);
 
     let sfx = if args.sound_effects {
-        match compile_sound_effects(&pf, samples.pitch_table()) {
+        match compile_sound_effects(&pf, samples.pitch_table(), errors) {
             Ok(sfx) => sfx,
-            Err(()) => error!("Error compiling sound effects"),
+            Err(()) => error!(errors, "Error compiling sound effects"),
         }
     } else {
         blank_sfx()
     };
 
-    let song_data = compile_song(
+    let song_data = match compile_song(
         mml_file,
         song_name,
         &args.song.options,
         &pf,
         samples.pitch_table(),
-    );
+        max_errors,
+        errors,
+    ) {
+        Ok(sd) => sd,
+        Err(e) => error!(errors, "{}", e),
+    };
 
     let common_audio_data = match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
         Ok(data) => data,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => error!(errors, "{}", e.multiline_display()),
     };
 
     let data = match export_spc_file(&common_audio_data, &song_data) {
         Ok(d) => d,
-        Err(e) => error!("{}", e),
+        Err(e) => error!(errors, "{}", e),
+    };
+
+    write_data(output_arg, &data, errors);
+
+    let stats = CompileStats::new(&pf, data.len()).with_song(&song_data);
+    match args.sound_effects {
+        true => stats.with_sound_effects(&pf),
+        false => stats,
+    }
+}
+
+//
+// Export song to .wav file
+// ========================
+
+#[derive(Args)]
+struct Song2WavArgs {
+    #[command(flatten)]
+    song: CompileSongDataArgs,
+
+    #[arg(
+        long = "ticks",
+        value_name = "TICKS",
+        help = "number of ticks to render (default: song length)"
+    )]
+    ticks: Option<u32>,
+
+    #[arg(long = "mono", help = "render the song in mono")]
+    mono: bool,
+}
+
+fn export_song_to_wav_file(
+    args: Song2WavArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let output_arg = args.song.output.validate(errors);
+
+    let pf = load_project_file(&args.song.project_file, errors);
+    let (mml_file, song_name) = match load_mml_file(&args.song.song, &pf) {
+        Ok(m) => m,
+        Err(e) => error!(errors, "{}", e),
+    };
+
+    let samples = match build_sample_and_instrument_data(&pf) {
+        Ok(s) => s,
+        Err(e) => error!(errors, "{}", e.multiline_display()),
+    };
+    print_sample_notes(&samples, errors);
+
+    let song_data = match compile_song(
+        mml_file,
+        song_name,
+        &args.song.options,
+        &pf,
+        samples.pitch_table(),
+        max_errors,
+        errors,
+    ) {
+        Ok(sd) => sd,
+        Err(e) => error!(errors, "{}", e),
+    };
+
+    let sfx = blank_sfx();
+    let common_audio_data = match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
+        Ok(data) => data,
+        Err(e) => error!(errors, "{}", e.multiline_display()),
+    };
+
+    if let Err(e) = validate_echo_buffer(&song_data, common_audio_data.data().len()) {
+        error!(errors, "{}", e);
+    }
+    if let Err(e) = validate_song_size(&song_data, common_audio_data.data().len()) {
+        error!(errors, "{}", e.multiline_display());
+    }
+
+    let ticks = match args.ticks {
+        Some(t) => TickCounter::new(t),
+        None => song_data.max_tick_count(),
+    };
+
+    let pcm = match render_song_to_pcm(&song_data, &common_audio_data, !args.mono, ticks) {
+        Ok(pcm) => pcm,
+        Err(e) => error!(errors, "Error: {}", e),
+    };
+
+    let data = match wav_file::stereo_wav_file(&pcm, DSP_SAMPLE_RATE) {
+        Ok(d) => d,
+        Err(e) => error!(errors, "Error creating wav file: {}", e),
+    };
+
+    write_data(output_arg, &data, errors);
+
+    CompileStats::new(&pf, data.len()).with_song(&song_data)
+}
+
+//
+// Render
+// ======
+
+#[derive(Args)]
+struct RenderArgs {
+    #[command(flatten)]
+    output: OutputArg,
+
+    #[arg(
+        long = "ticks",
+        value_name = "TICKS",
+        help = "maximum number of ticks to render"
+    )]
+    ticks: u32,
+
+    #[arg(long = "mono", help = "render the song in mono")]
+    mono: bool,
+
+    #[arg(value_name = "COMMON_FILE", help = "common audio data file")]
+    common_file: PathBuf,
+
+    #[arg(value_name = "SONG_FILE", help = "compiled song data file")]
+    song_file: PathBuf,
+}
+
+fn render_command(args: RenderArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let output_arg = args.output.validate(errors);
+
+    let common_data = read_binary_file(&args.common_file, errors);
+    let song_data = read_binary_file(&args.song_file, errors);
+
+    let song_addr = song_addr_after_common_data(&args.common_file, &common_data, errors);
+
+    if usize::from(song_addr) + song_data.len() > AUDIO_RAM_SIZE {
+        error!(
+            errors,
+            "Error: {} is too large to fit in audio-RAM",
+            args.song_file.display()
+        );
+    }
+
+    let pcm = match render_song_file_to_pcm(
+        &common_data,
+        &song_data,
+        song_addr,
+        !args.mono,
+        TickCounter::new(args.ticks),
+    ) {
+        Ok(pcm) => pcm,
+        Err(e) => error!(errors, "Error: {}", e),
+    };
+
+    let data = match wav_file::stereo_wav_file(&pcm, DSP_SAMPLE_RATE) {
+        Ok(d) => d,
+        Err(e) => error!(errors, "Error creating wav file: {}", e),
+    };
+
+    write_data(output_arg, &data, errors);
+
+    CompileStats::default()
+}
+
+//
+// Dump audio-RAM
+// ==============
+
+#[derive(Args)]
+struct DumpAramArgs {
+    #[command(flatten)]
+    output: OutputArg,
+
+    #[arg(value_name = "PROJECT_FILE", help = "project file")]
+    project_file: PathBuf,
+
+    #[arg(
+        long = "song",
+        value_name = "SONG",
+        help = "song name, song number, or MML file (default: blank song)"
+    )]
+    song: Option<OsString>,
+}
+
+/// Loads the audio driver, common audio data and song into a new emulator.
+///
+/// Returns the emulator after the audio driver has been initialised (the audio driver is paused).
+fn init_emulator_with_song(
+    common_audio_data: &CommonAudioData,
+    song_data: &SongData,
+    stereo_flag: bool,
+    errors: &mut ErrorReporter,
+) -> ShvcSoundEmu {
+    let interpreter = SongInterpreter::new(common_audio_data, song_data, stereo_flag);
+
+    // No IPL ROM
+    let mut emu = ShvcSoundEmu::new(&[0; 64]);
+
+    if let Err(e) = load_song_into_emulator(
+        &mut emu,
+        common_audio_data,
+        song_data,
+        Some(&interpreter),
+        stereo_flag,
+    ) {
+        error!(errors, "Error: {}", e);
+    }
+
+    emu
+}
+
+/// Returns the labelled audio-RAM regions, sorted by address.
+///
+/// Unlabelled gaps between the regions are not included.
+fn aram_regions(
+    common_audio_data: &CommonAudioData,
+    song_data: &SongData,
+) -> Vec<(Range<usize>, &'static str)> {
+    let r = |range: Range<u16>| usize::from(range.start)..usize::from(range.end);
+
+    let driver_code_end = usize::from(addresses::DRIVER_CODE) + audio_driver::AUDIO_DRIVER.len();
+    let song_data_addr = usize::from(common_audio_data.song_data_addr());
+    let echo_buffer = &song_data.metadata().echo_buffer;
+    let echo_buffer_addr = usize::from(echo_buffer.buffer_addr());
+
+    let mut regions = vec![
+        (
+            0..usize::from(addresses::LOADER),
+            "Driver variables and stack",
+        ),
+        (r(addresses::LOADER..addresses::DRIVER_CODE), "Loader"),
+        (
+            usize::from(addresses::DRIVER_CODE)..driver_code_end,
+            "Audio driver",
+        ),
+        (
+            driver_code_end..usize::from(addresses::COMMON_DATA),
+            "Driver variables",
+        ),
+        (
+            usize::from(addresses::COMMON_DATA)
+                ..usize::from(common_audio_data.dir_addr_range().start),
+            "Common audio data header",
+        ),
+        (r(common_audio_data.dir_addr_range()), "BRR directory"),
+        (
+            r(common_audio_data.sfx_data_and_tables_range()),
+            "Sound effects",
+        ),
+        (r(common_audio_data.pitch_table_addr_range()), "Pitch table"),
+        (
+            r(common_audio_data.instruments_soa_addr_range()),
+            "Instruments and samples",
+        ),
+        (r(common_audio_data.brr_addr_range()), "BRR samples"),
+        (
+            song_data_addr..song_data_addr + song_data.data().len(),
+            "Song data",
+        ),
+        (
+            echo_buffer_addr..echo_buffer_addr + echo_buffer.buffer_size(),
+            "Echo buffer",
+        ),
+    ];
+
+    regions.retain(|(r, _)| !r.is_empty());
+    regions.sort_by_key(|(r, _)| r.start);
+
+    regions
+}
+
+fn aram_hex_dump(aram: &[u8; AUDIO_RAM_SIZE], regions: &[(Range<usize>, &str)]) -> String {
+    const BYTES_PER_LINE: usize = 16;
+
+    let mut out = String::new();
+
+    let write_region = |out: &mut String, range: Range<usize>, label: &str| {
+        if range.is_empty() {
+            return;
+        }
+        writeln!(
+            out,
+            "; ${:04x} - ${:04x}  {} ({} bytes)",
+            range.start,
+            range.end - 1,
+            label,
+            range.len()
+        )
+        .unwrap();
+
+        for (i, line) in aram[range.clone()].chunks(BYTES_PER_LINE).enumerate() {
+            write!(out, "{:04x}:", range.start + i * BYTES_PER_LINE).unwrap();
+            for b in line {
+                write!(out, " {:02x}", b).unwrap();
+            }
+            out.push('\n');
+        }
+        out.push('\n');
     };
 
-    write_data(output_arg, &data);
+    let mut addr = 0;
+    for (range, label) in regions {
+        let start = range.start.max(addr);
+        write_region(&mut out, addr..start, "Unused");
+        write_region(&mut out, start..range.end.max(start), label);
+        addr = range.end.max(addr);
+    }
+    write_region(&mut out, addr..AUDIO_RAM_SIZE, "Unused");
+
+    out
+}
+
+fn dump_aram_command(
+    args: DumpAramArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let output_arg = args.output.validate(errors);
+
+    let pf = load_project_file(&args.project_file, errors);
+
+    let samples = match build_sample_and_instrument_data(&pf) {
+        Ok(s) => s,
+        Err(e) => error!(errors, "{}", e.multiline_display()),
+    };
+    print_sample_notes(&samples, errors);
+
+    let sfx = match compile_sound_effects(&pf, samples.pitch_table(), errors) {
+        Ok(sfx) => sfx,
+        Err(()) => error!(errors, "Error compiling sound effects"),
+    };
+
+    let common_audio_data = match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
+        Ok(data) => data,
+        Err(e) => error!(errors, "{}", e.multiline_display()),
+    };
+
+    let song_data = match &args.song {
+        Some(song) => {
+            let (mml_file, song_name) = match load_mml_file(song, &pf) {
+                Ok(m) => m,
+                Err(e) => error!(errors, "{}", e),
+            };
+            match compile_song(
+                mml_file,
+                song_name,
+                &SongOptions {
+                    print_tick_counts: false,
+                },
+                &pf,
+                samples.pitch_table(),
+                max_errors,
+                errors,
+            ) {
+                Ok(sd) => sd,
+                Err(e) => error!(errors, "{}", e),
+            }
+        }
+        None => blank_song(),
+    };
+
+    if let Err(e) = validate_echo_buffer(&song_data, common_audio_data.data().len()) {
+        error!(errors, "{}", e);
+    }
+    if let Err(e) = validate_song_size(&song_data, common_audio_data.data().len()) {
+        error!(errors, "{}", e.multiline_display());
+    }
+
+    const STEREO_FLAG: bool = true;
+
+    let emu = init_emulator_with_song(&common_audio_data, &song_data, STEREO_FLAG, errors);
+    let aram = emu.apuram();
+    let regions = aram_regions(&common_audio_data, &song_data);
+
+    let hex_dump = aram_hex_dump(aram, &regions);
+
+    write_data(output_arg, hex_dump.as_bytes(), errors);
+
+    let stats = CompileStats::new(&pf, common_audio_data.data().len()).with_sound_effects(&pf);
+    match args.song {
+        Some(_) => stats.with_song(&song_data),
+        None => stats,
+    }
+}
+
+//
+// Disassemble song
+// ================
+
+#[derive(Args)]
+struct DisassembleArgs {
+    #[arg(
+        value_name = "COMMON_FILE",
+        help = "common audio data file (used to determine the song address)"
+    )]
+    common_file: PathBuf,
+
+    #[arg(value_name = "SONG_FILE", help = "compiled song data file")]
+    song_file: PathBuf,
+}
+
+/// Returns the address the loader will place the song data at
+fn song_addr_after_common_data(
+    common_file: &Path,
+    common_data: &[u8],
+    errors: &mut ErrorReporter,
+) -> u16 {
+    if common_data.len() > MAX_COMMON_DATA_SIZE {
+        error!(
+            errors,
+            "Error: {} is too large to be common audio data",
+            common_file.display()
+        );
+    }
+
+    // Loader can only load a multiple of 2 bytes
+    let song_addr =
+        usize::from(addresses::COMMON_DATA) + common_data.len() + (common_data.len() % 2);
+    match u16::try_from(song_addr) {
+        Ok(a) => a,
+        Err(_) => error!(errors, "Error: invalid common audio data size"),
+    }
+}
+
+fn disassemble_command(args: DisassembleArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let common_data = read_binary_file(&args.common_file, errors);
+    let song_data = read_binary_file(&args.song_file, errors);
+
+    let song_addr = song_addr_after_common_data(&args.common_file, &common_data, errors);
+
+    print!("{}", disassemble_song(&song_data, song_addr));
+
+    CompileStats::default()
 }
 
 //
@@ -373,11 +977,313 @@ struct CheckProjectArgs {
     project_file: PathBuf,
 }
 
-fn check_project_command(args: CheckProjectArgs) {
-    let pf = load_project_file(&args.project_file);
-    compile_project(&pf);
+fn check_project_command(
+    args: CheckProjectArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let pf = load_project_file(&args.project_file, errors);
+    let (common_audio_data, songs) = compile_project(&pf, max_errors, errors);
+
+    if !errors.json_errors_active() {
+        println!("Project is valid and will fit in audio-RAM");
+    }
+
+    let output_size =
+        common_audio_data.data().len() + songs.iter().map(|s| s.data().len()).sum::<usize>();
 
-    println!("Project is valid and will fit in audio-RAM");
+    CompileStats::new(&pf, output_size)
+        .with_sound_effects(&pf)
+        .with_songs(&songs)
+}
+
+//
+// Validate project
+// ================
+
+#[derive(Args)]
+struct ValidateProjectArgs {
+    #[arg(value_name = "PROJECT_FILE", help = "project file")]
+    project_file: PathBuf,
+}
+
+fn n_mml_channel_errors(channel_errors: &[MmlChannelError]) -> usize {
+    channel_errors.iter().map(|c| c.errors.len()).sum()
+}
+
+fn n_sample_errors(e: &SampleAndInstrumentDataError) -> usize {
+    let n_pitch_table_errors = match &e.pitch_table_error {
+        Some(PitchTableError::InstrumentErrors(v)) => v.len(),
+        Some(_) => 1,
+        None => 0,
+    };
+    e.sample_errors.len() + n_pitch_table_errors
+}
+
+fn n_sound_effects_file_errors(e: &SoundEffectsFileError) -> usize {
+    let n_subroutine_errors = match &e.subroutine_errors {
+        Some(SfxSubroutineErrors::LineErrors(v)) => v.len(),
+        Some(SfxSubroutineErrors::SubroutineErrors(v)) => n_mml_channel_errors(v),
+        Some(SfxSubroutineErrors::TooManySfxSubroutines(_)) => 1,
+        None => 0,
+    };
+    let n_sfx_errors: usize = e
+        .errors
+        .iter()
+        .map(|s| {
+            let n = match &s.errors {
+                SoundEffectErrorList::BytecodeErrors(v) => v.len(),
+                SoundEffectErrorList::MmlLineErrors(v) => v.len(),
+                SoundEffectErrorList::MmlErrors(v) => v.len(),
+            };
+            s.other_errors.len() + n
+        })
+        .sum();
+
+    n_subroutine_errors + n_sfx_errors
+}
+
+fn n_common_audio_data_errors(e: &CommonAudioDataErrors) -> usize {
+    e.errors.len()
+}
+
+fn n_song_errors(e: &SongError) -> usize {
+    match e {
+        SongError::MmlError(e) => {
+            e.line_errors.len()
+                + n_mml_channel_errors(&e.subroutine_errors)
+                + n_mml_channel_errors(&e.channel_errors)
+        }
+        _ => 1,
+    }
+}
+
+/// The `validate` exit code is the number of errors, clamped to the 1 - 255 exit code range
+fn validate_exit_code(n_errors: usize) -> i32 {
+    n_errors.clamp(1, 255) as i32
+}
+
+/// Compiles everything in the project without writing any output.
+///
+/// Unlike `check`, compilation continues after an error so every error in the project is printed.
+/// Exits with the number of errors (see `validate_exit_code()`) if the project is invalid.
+fn validate_project_command(
+    args: ValidateProjectArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let pf = load_project_file(&args.project_file, errors);
+
+    let samples = match build_sample_and_instrument_data(&pf) {
+        Ok(s) => {
+            print_sample_notes(&s, errors);
+            Some(s)
+        }
+        Err(e) => {
+            errors.report_errors(&e.multiline_display().to_string(), n_sample_errors(&e));
+            None
+        }
+    };
+
+    // The sound effects and songs can still be compiled if a sample is invalid
+    let pitch_table_without_samples;
+    let pitch_table = match &samples {
+        Some(s) => Some(s.pitch_table()),
+        None => {
+            pitch_table_without_samples = build_pitch_table(&pf.instruments_and_samples).ok();
+            pitch_table_without_samples.as_ref()
+        }
+    };
+
+    let sfx = match pitch_table.map(|pt| compile_sound_effects(&pf, pt, errors)) {
+        Some(Ok(sfx)) => Some(sfx),
+        Some(Err(())) | None => None,
+    };
+
+    let common_audio_data = match (&samples, &sfx) {
+        (Some(samples), Some(sfx)) => match build_common_audio_data(samples, &sfx.0, &sfx.1) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                errors.report_errors(
+                    &e.multiline_display().to_string(),
+                    n_common_audio_data_errors(&e),
+                );
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let mut songs = Vec::with_capacity(pf.songs.len());
+    if let Some(pt) = pitch_table {
+        for r in compile_songs_in_parallel(&pf, pt, common_audio_data.as_ref()) {
+            match r {
+                Ok(sd) => songs.push(sd),
+                Err(e) => {
+                    let message = e.message(max_errors, errors);
+                    errors.report_errors(&format!("{}\n", message), e.n_errors());
+                }
+            }
+        }
+    }
+
+    let n_errors = errors.n_errors();
+
+    let common_audio_data = match common_audio_data {
+        Some(c) if n_errors == 0 => c,
+        _ => {
+            errors.report_error("Project is invalid");
+            errors.flush();
+            std::process::exit(validate_exit_code(n_errors));
+        }
+    };
+
+    if !errors.json_errors_active() {
+        println!("Project is valid");
+    }
+
+    let output_size =
+        common_audio_data.data().len() + songs.iter().map(|s| s.data().len()).sum::<usize>();
+
+    CompileStats::new(&pf, output_size)
+        .with_sound_effects(&pf)
+        .with_songs(&songs)
+}
+
+//
+// List instruments and songs
+// ==========================
+
+#[derive(Args)]
+struct ListArgs {
+    #[arg(value_name = "PROJECT_FILE", help = "project file")]
+    project_file: PathBuf,
+
+    #[arg(long, help = "Output a JSON array")]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct ListItem<'a> {
+    index: usize,
+    name: &'a str,
+    source: &'a str,
+}
+
+fn print_list(items: &[ListItem], json: bool, errors: &mut ErrorReporter) {
+    if json {
+        match serde_json::to_string(items) {
+            Ok(s) => println!("{}", s),
+            Err(e) => error!(errors, "Error serializing list: {}", e),
+        }
+    } else {
+        for i in items {
+            println!("{}\t{}\t{}", i.index, i.name, i.source);
+        }
+    }
+}
+
+/// The index is the instrument id (instruments are before samples)
+fn list_instruments_command(args: ListArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let pf = load_project_file(&args.project_file, errors);
+
+    let items: Vec<_> = pf
+        .instruments_and_samples
+        .list()
+        .iter()
+        .enumerate()
+        .map(|(index, i)| {
+            let (name, source) = match i {
+                InstrumentOrSample::Instrument(i) => (&i.name, &i.source),
+                InstrumentOrSample::Sample(s) => (&s.name, &s.source),
+            };
+            ListItem {
+                index,
+                name: name.as_str(),
+                source: source.as_str(),
+            }
+        })
+        .collect();
+
+    print_list(&items, args.json, errors);
+
+    CompileStats::new(&pf, 0)
+}
+
+/// The index is the song id
+fn list_songs_command(args: ListArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let pf = load_project_file(&args.project_file, errors);
+
+    let items: Vec<_> = pf
+        .songs
+        .list()
+        .iter()
+        .zip(UniqueNamesProjectFile::FIRST_SONG_ID..)
+        .map(|(s, index)| ListItem {
+            index,
+            name: s.name.as_str(),
+            source: s.source.as_str(),
+        })
+        .collect();
+
+    print_list(&items, args.json, errors);
+
+    CompileStats::new(&pf, 0)
+}
+
+//
+// Validate MML
+// ============
+
+#[derive(Args)]
+struct ValidateMmlArgs {
+    #[arg(value_name = "MML_FILE", help = "MML file")]
+    mml_file: PathBuf,
+
+    #[arg(value_name = "PROJECT_FILE", help = "project file")]
+    project_file: PathBuf,
+}
+
+fn validate_mml_command(
+    args: ValidateMmlArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
+    let pf = load_project_file(&args.project_file, errors);
+
+    let mml_file = match load_text_file_with_limit_path(&args.mml_file) {
+        Ok(tf) => tf,
+        Err(e) => error!(errors, "{}", e),
+    };
+
+    let r = validate_mml(&mml_file, &pf.instruments_and_samples);
+
+    match errors.json() {
+        Some(j) => {
+            j.add_mml_warnings(&r);
+            if r.has_errors() {
+                j.add_mml_errors(&r.errors, max_errors);
+            }
+        }
+        None => {
+            for w in &r.warnings {
+                eprintln!("{} warning: {}", r.errors.location(&w.0), w.1);
+            }
+        }
+    }
+
+    if r.has_errors() {
+        match errors.json_errors_active() {
+            true => error!(errors, "Error compiling {}", mml_file.file_name),
+            false => error!(
+                errors,
+                "{}",
+                r.errors.multiline_display_with_limit(max_errors)
+            ),
+        }
+    }
+
+    CompileStats::new(&pf, 0)
 }
 
 //
@@ -393,17 +1299,23 @@ struct EnumArgs {
     project_file: PathBuf,
 }
 
-fn generate_enums_command<E: Exporter>(args: EnumArgs) {
-    let output_arg = args.output.validate();
+fn generate_enums_command<E: Exporter>(args: EnumArgs, errors: &mut ErrorReporter) -> CompileStats {
+    let output_arg = args.output.validate(errors);
 
-    let pf = load_project_file(&args.project_file);
+    let pf = load_project_file(&args.project_file, errors);
+    let stats = CompileStats::new(&pf, 0).with_sound_effects(&pf);
 
     let inc_file = match E::generate_include_file(pf) {
         Ok(o) => o,
-        Err(e) => error!("Error creating enum file: {}", e),
+        Err(e) => error!(errors, "Error creating enum file: {}", e),
     };
 
-    write_data(output_arg, inc_file.as_bytes());
+    write_data(output_arg, inc_file.as_bytes(), errors);
+
+    CompileStats {
+        output_size_bytes: inc_file.len(),
+        ..stats
+    }
 }
 
 //
@@ -430,12 +1342,15 @@ struct MemoryMapModeArgument {
 }
 
 impl MemoryMapModeArgument {
-    fn mode(&self) -> MemoryMapMode {
+    fn mode(&self, errors: &mut ErrorReporter) -> MemoryMapMode {
         match (self.lorom, self.hirom) {
             (true, false) => MemoryMapMode::LoRom,
             (false, true) => MemoryMapMode::HiRom,
-            (false, false) => error!("Missing --lorom or --hirom argument"),
-            (true, true) => error!("Cannot use --lorom and --hirom arguments at the same time"),
+            (false, false) => error!(errors, "Missing --lorom or --hirom argument"),
+            (true, true) => error!(
+                errors,
+                "Cannot use --lorom and --hirom arguments at the same time"
+            ),
         }
     }
 }
@@ -470,61 +1385,108 @@ struct ExportWithAsmArgs {
     project_file: PathBuf,
 }
 
-fn export_with_asm_command<E: Exporter>(memory_map: &E::MemoryMap, args: ExportWithAsmArgs) {
+fn export_with_asm_command<E: Exporter>(
+    memory_map: &E::MemoryMap,
+    args: ExportWithAsmArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> CompileStats {
     let relative_bin_path = match bin_include_path(&args.output_asm, &args.output_bin) {
         Ok(p) => p,
-        Err(e) => error!("Error:  {}", e),
+        Err(e) => error!(errors, "Error:  {}", e),
     };
 
-    let (pf, common_audio_data, songs) = load_and_compile_project(&args);
+    let (pf, common_audio_data, songs) = load_and_compile_project(&args, max_errors, errors);
+
+    let stats = CompileStats::new(&pf, 0)
+        .with_sound_effects(&pf)
+        .with_songs(&songs);
 
     let bin_file = match E::export_bin_file(&common_audio_data, &songs, memory_map) {
         Ok(b) => b,
-        Err(e) => error!("Error: {}", e),
+        Err(e) => error!(errors, "Error: {}", e),
     };
 
     let asm_file = match E::generate_asm_file(&bin_file, memory_map, &relative_bin_path) {
         Ok(o) => o,
-        Err(e) => error!("Error creating assembly file: {}", e),
+        Err(e) => error!(errors, "Error creating assembly file: {}", e),
     };
 
     let inc_file = match &args.output_inc {
         Some(path) => {
             let inc_file = match E::generate_include_file(pf) {
                 Ok(o) => o,
-                Err(e) => error!("Error creating enum include file: {}", e),
+                Err(e) => error!(errors, "Error creating enum include file: {}", e),
             };
             Some((path, inc_file))
         }
         None => None,
     };
 
-    write_to_file(&args.output_bin, bin_file.data());
-    write_to_file(&args.output_asm, asm_file.as_bytes());
+    write_to_file(&args.output_bin, bin_file.data(), errors);
+    write_to_file(&args.output_asm, asm_file.as_bytes(), errors);
 
     if let Some((inc_path, inc_str)) = inc_file {
-        write_to_file(inc_path, inc_str.as_bytes());
+        write_to_file(inc_path, inc_str.as_bytes(), errors);
+    }
+
+    CompileStats {
+        output_size_bytes: bin_file.data().len(),
+        ..stats
     }
 }
 
 fn load_and_compile_project(
     args: &ExportWithAsmArgs,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
 ) -> (UniqueNamesProjectFile, CommonAudioData, Vec<SongData>) {
-    let pf = load_project_file(&args.project_file);
-    let (common_audio_data, songs) = compile_project(&pf);
+    let pf = load_project_file(&args.project_file, errors);
+    let (common_audio_data, songs) = compile_project(&pf, max_errors, errors);
 
     (pf, common_audio_data, songs)
 }
 
+/// An error from `compile_and_check_song()`.
+///
+/// The songs are compiled on multiple threads, song errors are converted into a message
+/// (and added to the JSON errors) on the main thread.
+enum ProjectSongError {
+    Song(SongError),
+    Message(String),
+}
+
+impl ProjectSongError {
+    fn message(&self, max_errors: usize, errors: &mut ErrorReporter) -> String {
+        match self {
+            Self::Song(e) => song_error_message(e, max_errors, errors),
+            Self::Message(s) => s.clone(),
+        }
+    }
+
+    fn n_errors(&self) -> usize {
+        match self {
+            Self::Song(e) => n_song_errors(e),
+            Self::Message(_) => 1,
+        }
+    }
+}
+
+/// The song size is not checked if `common_data` is `None`
 fn compile_and_check_song(
     song: &Song,
     pf: &UniqueNamesProjectFile,
     pitch_table: &PitchTable,
-    common_data: &CommonAudioData,
-) -> Result<SongData, String> {
+    common_data: Option<&CommonAudioData>,
+) -> Result<SongData, ProjectSongError> {
     let mml_file = match load_text_file_with_limit(&song.source, &pf.parent_path) {
         Ok(tf) => tf,
-        Err(e) => return Err(format!("Error compiling {}: {}", song.name, e)),
+        Err(e) => {
+            return Err(ProjectSongError::Message(format!(
+                "Error compiling {}: {}",
+                song.name, e
+            )))
+        }
     };
 
     let song_data = match compile_mml(
@@ -534,59 +1496,118 @@ fn compile_and_check_song(
         pitch_table,
     ) {
         Ok(mml) => mml,
-        Err(e) => return Err(e.multiline_display().to_string()),
+        Err(e) => return Err(ProjectSongError::Song(e)),
     };
 
+    let common_data = match common_data {
+        Some(c) => c,
+        None => return Ok(song_data),
+    };
+
+    if let Err(e) = validate_echo_buffer(&song_data, common_data.data().len()) {
+        return Err(ProjectSongError::Message(format!(
+            "Error compiling {}: {}",
+            song.name, e
+        )));
+    }
+
     match validate_song_size(&song_data, common_data.data().len()) {
         Ok(()) => Ok(song_data),
-        Err(e) => Err(format!(
+        Err(e) => Err(ProjectSongError::Message(format!(
             "Error compiling {}: {}",
             song.name,
             e.multiline_display()
-        )),
+        ))),
     }
 }
 
-fn compile_project(pf: &UniqueNamesProjectFile) -> (CommonAudioData, Vec<SongData>) {
+/// Compiles the project songs across multiple threads.
+///
+/// Returns the results in the same order as `pf.songs`.
+fn compile_songs_in_parallel(
+    pf: &UniqueNamesProjectFile,
+    pitch_table: &PitchTable,
+    common_data: Option<&CommonAudioData>,
+) -> Vec<Result<SongData, ProjectSongError>> {
+    let songs = pf.songs.list();
+    if songs.is_empty() {
+        return Vec::new();
+    }
+
+    let n_threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(songs.len());
+    let chunk_size = songs.len().div_ceil(n_threads);
+
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = songs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|song| compile_and_check_song(song, pf, pitch_table, common_data))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect()
+    })
+}
+
+fn compile_project(
+    pf: &UniqueNamesProjectFile,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> (CommonAudioData, Vec<SongData>) {
     let samples = build_sample_and_instrument_data(pf);
     if let Err(e) = samples {
-        error!("{}", e.multiline_display())
+        error!(errors, "{}", e.multiline_display())
     };
 
     let sfx = if let Ok(s) = &samples {
-        compile_sound_effects(pf, s.pitch_table())
+        compile_sound_effects(pf, s.pitch_table(), errors)
     } else {
         Err(())
     };
 
     let (samples, sfx) = match (samples, sfx) {
         (Ok(samples), Ok(sfx)) => (samples, sfx),
-        _ => error!("Error compiling common audio data"),
+        _ => error!(errors, "Error compiling common audio data"),
     };
+    print_sample_notes(&samples, errors);
 
     let common_audio_data = match build_common_audio_data(&samples, &sfx.0, &sfx.1) {
         Ok(data) => data,
-        Err(e) => error!("{}", e.multiline_display()),
+        Err(e) => error!(errors, "{}", e.multiline_display()),
     };
 
+    let results = compile_songs_in_parallel(pf, samples.pitch_table(), Some(&common_audio_data));
+
     let mut compiled_songs = Vec::with_capacity(pf.songs.len());
     let mut n_song_errors = 0;
 
-    for song in pf.songs.list().iter() {
-        match compile_and_check_song(song, pf, samples.pitch_table(), &common_audio_data) {
+    for r in results {
+        match r {
             Ok(sd) => compiled_songs.push(sd),
             Err(e) => {
                 n_song_errors += 1;
-                eprintln!("{}\n", e);
+                let message = e.message(max_errors, errors);
+                errors.report_error(&format!("{}\n", message));
             }
         }
     }
 
     if n_song_errors > 0 {
         if n_song_errors == 1 {
-            error!("{} song has an error", n_song_errors);
+            error!(errors, "{} song has an error", n_song_errors);
         } else {
-            error!("{} songs have errors", n_song_errors);
+            error!(errors, "{} songs have errors", n_song_errors);
         }
     }
 
@@ -632,21 +1653,25 @@ struct Ca65ExportArgs {
     upper_hex: bool,
 }
 
-fn parse_suffix_type(lower_hex: bool, upper_hex: bool) -> SuffixType {
+fn parse_suffix_type(lower_hex: bool, upper_hex: bool, errors: &mut ErrorReporter) -> SuffixType {
     match (lower_hex, upper_hex) {
         (false, false) => SuffixType::Integer,
         (true, false) => SuffixType::LowerHex,
         (false, true) => SuffixType::UpperHex,
-        (true, true) => error!("Invalid suffix type"),
+        (true, true) => error!(errors, "Invalid suffix type"),
     }
 }
 
-fn parse_ca65_memory_map(args: &Ca65ExportArgs) -> Ca65MemoryMap {
-    let suffix_type = parse_suffix_type(args.lower_hex, args.upper_hex);
+fn parse_ca65_memory_map(args: &Ca65ExportArgs, errors: &mut ErrorReporter) -> Ca65MemoryMap {
+    let suffix_type = parse_suffix_type(args.lower_hex, args.upper_hex, errors);
 
-    match Ca65MemoryMap::try_new(args.memory_map.mode(), &args.first_segment, suffix_type) {
+    match Ca65MemoryMap::try_new(
+        args.memory_map.mode(errors),
+        &args.first_segment,
+        suffix_type,
+    ) {
         Ok(mm) => mm,
-        Err(e) => error!("Invalid memory map: {}", e),
+        Err(e) => error!(errors, "Invalid memory map: {}", e),
     }
 }
 
@@ -687,12 +1712,16 @@ struct Tass64ExportArgs {
     upper_hex: bool,
 }
 
-fn parse_64tass_memory_map(args: &Tass64ExportArgs) -> Tass64MemoryMap {
-    let suffix_type = parse_suffix_type(args.lower_hex, args.upper_hex);
+fn parse_64tass_memory_map(args: &Tass64ExportArgs, errors: &mut ErrorReporter) -> Tass64MemoryMap {
+    let suffix_type = parse_suffix_type(args.lower_hex, args.upper_hex, errors);
 
-    match Tass64MemoryMap::try_new(args.memory_map.mode(), &args.first_section, suffix_type) {
+    match Tass64MemoryMap::try_new(
+        args.memory_map.mode(errors),
+        &args.first_section,
+        suffix_type,
+    ) {
         Ok(mm) => mm,
-        Err(e) => error!("Invalid memory map: {}", e),
+        Err(e) => error!(errors, "Invalid memory map: {}", e),
     }
 }
 
@@ -719,10 +1748,254 @@ struct PvExportArgs {
     first_bank: u8,
 }
 
-fn parse_pv_memory_map(args: &PvExportArgs) -> PvMemoryMap {
-    match PvMemoryMap::try_new(args.memory_map.mode(), args.first_bank) {
+fn parse_pv_memory_map(args: &PvExportArgs, errors: &mut ErrorReporter) -> PvMemoryMap {
+    match PvMemoryMap::try_new(args.memory_map.mode(errors), args.first_bank) {
         Ok(mm) => mm,
-        Err(e) => error!("Invalid memory map: {}", e),
+        Err(e) => error!(errors, "Invalid memory map: {}", e),
+    }
+}
+
+//
+// Watch
+// =====
+
+/// Minimum quiet period before recompiling
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Args)]
+struct WatchArgs {
+    #[command(subcommand)]
+    command: WatchCommand,
+}
+
+#[derive(Subcommand)]
+enum WatchCommand {
+    /// Compile common audio data
+    Common(CompileCommonDataArgs),
+
+    /// Compile MML song
+    Song(CompileSongDataArgs),
+}
+
+/// Returns the data to output and the files the data was compiled from.
+///
+/// The input files are returned even if compilation fails, so they can be watched.
+fn watch_build(
+    command: &WatchCommand,
+    max_errors: usize,
+    errors: &mut ErrorReporter,
+) -> (Result<Vec<u8>, String>, Vec<PathBuf>) {
+    let project_file = match command {
+        WatchCommand::Common(args) => &args.project_file,
+        WatchCommand::Song(args) => &args.project_file,
+    };
+    let mut inputs = vec![project_file.clone()];
+
+    let pf = match try_load_project_file(project_file) {
+        Ok(pf) => pf,
+        Err(e) => return (Err(e), inputs),
+    };
+
+    inputs.extend(
+        pf.instruments
+            .list()
+            .iter()
+            .map(|i| &i.source)
+            .chain(pf.samples.list().iter().map(|s| &s.source))
+            .map(|s| s.to_path(&pf.parent_path)),
+    );
+
+    let data = match command {
+        WatchCommand::Common(_) => {
+            inputs.extend(
+                pf.sound_effect_file
+                    .iter()
+                    .map(|s| s.to_path(&pf.parent_path)),
+            );
+            build_common_data(&pf, errors).map(|cad| cad.data().to_vec())
+        }
+        WatchCommand::Song(args) => {
+            if let Ok((mml_file, _)) = load_mml_file(&args.song, &pf) {
+                inputs.extend(mml_file.path.clone());
+                inputs.extend(compiler::mml::included_files(&mml_file));
+            }
+            build_song_data(&args.song, &args.options, &pf, max_errors, errors)
+                .map(|sd| sd.data().to_vec())
+        }
+    };
+
+    (data, inputs)
+}
+
+/// Returns the watched directory and the path of the file in the directory
+/// (in the same form as the paths in the `notify` events).
+fn watch_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = dir.canonicalize().ok()?;
+    let file = dir.join(path.file_name()?);
+
+    Some((dir, file))
+}
+
+/// The current UTC time of day (HH:MM:SS)
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    format!(
+        "{:02}:{:02}:{:02}",
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Blocks until an input file has changed and no events have been received for `WATCH_DEBOUNCE`.
+fn wait_for_changes(
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+    input_files: &BTreeSet<PathBuf>,
+    errors: &mut ErrorReporter,
+) {
+    loop {
+        match rx.recv() {
+            Ok(Ok(event)) => {
+                if !event.kind.is_access() && event.paths.iter().any(|p| input_files.contains(p)) {
+                    break;
+                }
+            }
+            Ok(Err(e)) => eprintln!("Watch error: {}", e),
+            Err(_) => error!(errors, "File watcher stopped"),
+        }
+    }
+
+    loop {
+        match rx.recv_timeout(WATCH_DEBOUNCE) {
+            Ok(_) => (),
+            Err(mpsc::RecvTimeoutError::Timeout) => break,
+            Err(mpsc::RecvTimeoutError::Disconnected) => error!(errors, "File watcher stopped"),
+        }
+    }
+}
+
+fn watch_command(args: WatchArgs, max_errors: usize, errors: &mut ErrorReporter) -> ! {
+    let output = match &args.command {
+        WatchCommand::Common(a) => &a.output,
+        WatchCommand::Song(a) => &a.output,
+    };
+    let output_path = match &output.path {
+        Some(p) => p,
+        None => error!(errors, "Error: watch cannot write to stdout"),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(w) => w,
+        Err(e) => error!(errors, "Cannot create file watcher: {}", e),
+    };
+    let mut watched_dirs = BTreeSet::new();
+
+    loop {
+        let (data, inputs) = watch_build(&args.command, max_errors, errors);
+
+        let result = data.and_then(|data| match std::fs::write(output_path, &data) {
+            Ok(()) => Ok(data.len()),
+            Err(e) => Err(format!("Error writing {}: {}", output_path.display(), e)),
+        });
+        match (result, errors.json_errors_active()) {
+            (Ok(size), false) => eprintln!(
+                "[{}] Compiled {} ({} bytes)",
+                timestamp(),
+                output_path.display(),
+                size
+            ),
+            (Err(e), false) => eprintln!("{}\n[{}] Compile failed", e, timestamp()),
+            (Ok(_), true) => errors.flush(),
+            (Err(e), true) => {
+                errors.report_error(&e);
+                errors.flush();
+            }
+        }
+
+        let (dirs, input_files): (BTreeSet<_>, BTreeSet<_>) =
+            inputs.iter().filter_map(|p| watch_path(p)).unzip();
+
+        for d in watched_dirs.difference(&dirs) {
+            let _ = watcher.unwatch(d);
+        }
+        for d in dirs.difference(&watched_dirs) {
+            if let Err(e) = watcher.watch(d, RecursiveMode::NonRecursive) {
+                eprintln!("Cannot watch {}: {}", d.display(), e);
+            }
+        }
+        watched_dirs = dirs;
+
+        wait_for_changes(&rx, &input_files, errors);
+    }
+}
+
+//
+// Compile statistics
+// ==================
+
+#[derive(Default, Serialize)]
+struct CompileStats {
+    compile_time_ms: f64,
+    output_size_bytes: usize,
+    n_instruments: usize,
+    n_sound_effects: usize,
+    /// Number of MML song subroutines
+    n_subroutines: usize,
+    /// Maximum number of nested subroutine calls in the compiled songs
+    max_subroutine_depth: u8,
+    /// `None` if the command did not compile a single song
+    song_duration_secs: Option<f64>,
+    /// Largest echo buffer of the compiled songs
+    echo_buffer_bytes: usize,
+}
+
+impl CompileStats {
+    fn new(pf: &UniqueNamesProjectFile, output_size_bytes: usize) -> Self {
+        Self {
+            output_size_bytes,
+            n_instruments: pf.instruments_and_samples.len(),
+            ..Default::default()
+        }
+    }
+
+    fn with_sound_effects(self, pf: &UniqueNamesProjectFile) -> Self {
+        Self {
+            n_sound_effects: pf.sfx_export_order.n_sound_effects(),
+            ..self
+        }
+    }
+
+    fn with_song(self, song: &SongData) -> Self {
+        Self {
+            song_duration_secs: song.duration().map(|d| d.as_secs_f64()),
+            ..self.with_songs(std::slice::from_ref(song))
+        }
+    }
+
+    fn with_songs(self, songs: &[SongData]) -> Self {
+        Self {
+            n_subroutines: songs.iter().map(|s| s.subroutines().len()).sum(),
+            max_subroutine_depth: songs
+                .iter()
+                .map(|s| s.subroutine_depth())
+                .max()
+                .unwrap_or(0),
+            echo_buffer_bytes: songs
+                .iter()
+                .map(|s| s.metadata().echo_buffer.buffer_size())
+                .max()
+                .unwrap_or(0),
+            ..self
+        }
     }
 }
 
@@ -732,23 +2005,59 @@ fn parse_pv_memory_map(args: &PvExportArgs) -> PvMemoryMap {
 
 fn main() {
     let args = ArgParser::parse();
+    let max_errors = args.max_errors;
+
+    let mut errors = ErrorReporter::new(args.json_errors);
+
+    let start_time = Instant::now();
+
+    let stats = match args.command {
+        Command::Common(args) => compile_common_data(args, &mut errors),
+        Command::Song(args) => compile_song_data(args, max_errors, &mut errors),
+        Command::Song2spc(args) => export_song_to_spc_file(args, max_errors, &mut errors),
+        Command::DumpAram(args) => dump_aram_command(args, max_errors, &mut errors),
+        Command::Song2wav(args) => export_song_to_wav_file(args, max_errors, &mut errors),
+        Command::Render(args) => render_command(args, &mut errors),
+        Command::Disassemble(args) => disassemble_command(args, &mut errors),
+        Command::Check(args) => check_project_command(args, max_errors, &mut errors),
+        Command::Validate(args) => validate_project_command(args, max_errors, &mut errors),
+        Command::ValidateMml(args) => validate_mml_command(args, max_errors, &mut errors),
+        Command::ListInstruments(args) => list_instruments_command(args, &mut errors),
+        Command::ListSongs(args) => list_songs_command(args, &mut errors),
+        Command::Ca65Enums(args) => generate_enums_command::<Ca65Exporter>(args, &mut errors),
+        Command::Ca65Export(args) => export_with_asm_command::<Ca65Exporter>(
+            &parse_ca65_memory_map(&args, &mut errors),
+            args.base,
+            max_errors,
+            &mut errors,
+        ),
+        Command::Tass64Enums(args) => generate_enums_command::<Tass64Exporter>(args, &mut errors),
+        Command::Tass64Export(args) => export_with_asm_command::<Tass64Exporter>(
+            &parse_64tass_memory_map(&args, &mut errors),
+            args.base,
+            max_errors,
+            &mut errors,
+        ),
+        Command::PvEnums(args) => generate_enums_command::<PvExporter>(args, &mut errors),
+        Command::PvExport(args) => export_with_asm_command::<PvExporter>(
+            &parse_pv_memory_map(&args, &mut errors),
+            args.base,
+            max_errors,
+            &mut errors,
+        ),
+        Command::Watch(args) => watch_command(args, max_errors, &mut errors),
+    };
 
-    match args.command {
-        Command::Common(args) => compile_common_data(args),
-        Command::Song(args) => compile_song_data(args),
-        Command::Song2spc(args) => export_song_to_spc_file(args),
-        Command::Check(args) => check_project_command(args),
-        Command::Ca65Enums(args) => generate_enums_command::<Ca65Exporter>(args),
-        Command::Ca65Export(args) => {
-            export_with_asm_command::<Ca65Exporter>(&parse_ca65_memory_map(&args), args.base)
-        }
-        Command::Tass64Enums(args) => generate_enums_command::<Tass64Exporter>(args),
-        Command::Tass64Export(args) => {
-            export_with_asm_command::<Tass64Exporter>(&parse_64tass_memory_map(&args), args.base)
-        }
-        Command::PvEnums(args) => generate_enums_command::<PvExporter>(args),
-        Command::PvExport(args) => {
-            export_with_asm_command::<PvExporter>(&parse_pv_memory_map(&args), args.base)
+    errors.flush();
+
+    if args.stats {
+        let stats = CompileStats {
+            compile_time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+            ..stats
+        };
+        match serde_json::to_string(&stats) {
+            Ok(s) => eprintln!("{}", s),
+            Err(e) => error!(&mut errors, "Error serializing stats: {}", e),
         }
     }
 }
@@ -757,68 +2066,85 @@ fn main() {
 // File functions
 // ==============
 
-fn load_project_file(path: &Path) -> UniqueNamesProjectFile {
+fn try_load_project_file(path: &Path) -> Result<UniqueNamesProjectFile, String> {
     match compiler::data::load_project_file(path) {
-        Err(e) => error!("Cannot load project file: {}", e),
+        Err(e) => Err(format!("Cannot load project file: {}", e)),
         Ok(m) => match compiler::data::validate_project_file_names(m) {
-            Ok(vm) => vm,
-            Err(e) => error!("{}", e.multiline_display()),
+            Ok(vm) => Ok(vm),
+            Err(e) => Err(e.multiline_display().to_string()),
         },
     }
 }
 
+fn read_binary_file(path: &Path, errors: &mut ErrorReporter) -> Vec<u8> {
+    match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => error!(errors, "Error reading {}: {}", path.display(), e),
+    }
+}
+
+fn load_project_file(path: &Path, errors: &mut ErrorReporter) -> UniqueNamesProjectFile {
+    match try_load_project_file(path) {
+        Ok(pf) => pf,
+        Err(e) => error!(errors, "{}", e),
+    }
+}
+
 // Output
 // ======
 
-fn write_to_file(path: &Path, contents: &[u8]) {
+fn write_to_file(path: &Path, contents: &[u8], errors: &mut ErrorReporter) {
     match std::fs::write(path, contents) {
         Ok(()) => (),
-        Err(e) => error!("Error writing {}: {}", path.display(), e),
+        Err(e) => error!(errors, "Error writing {}: {}", path.display(), e),
     }
 }
 
 mod output {
-    use super::{write_to_file, OutputArg};
+    use super::{write_to_file, ErrorReporter, OutputArg};
 
     use std::io::{IsTerminal, Write};
 
     pub struct ValidatedOutputArg(OutputArg);
 
-    pub fn validate_output_arg(out: OutputArg) -> ValidatedOutputArg {
+    pub fn validate_output_arg(out: OutputArg, errors: &mut ErrorReporter) -> ValidatedOutputArg {
         match (&out.path, out.stdout) {
             (Some(_), false) => ValidatedOutputArg(out),
             (None, true) => match std::io::stdout().is_terminal() {
                 false => ValidatedOutputArg(out),
-                true => error!("Error: --stdout will not write to a terminal."),
+                true => error!(errors, "Error: --stdout will not write to a terminal."),
             },
             // This should not happen
             _ => panic!("Invalid output arguments"),
         }
     }
 
-    pub fn write_data(out: ValidatedOutputArg, data: &[u8]) {
+    pub fn write_data(out: ValidatedOutputArg, data: &[u8], errors: &mut ErrorReporter) {
         if let Some(path) = &out.0.path {
-            write_to_file(path, data);
+            write_to_file(path, data, errors);
         } else if out.0.stdout {
             let mut stdout = std::io::stdout();
 
             if stdout.is_terminal() {
-                error!("Cannot write binary data to stdout, stdout is a terminal");
+                error!(
+                    errors,
+                    "Cannot write binary data to stdout, stdout is a terminal"
+                );
             }
 
             match stdout.write_all(data) {
                 Ok(()) => (),
-                Err(e) => error!("Error writing data: {}", e),
+                Err(e) => error!(errors, "Error writing data: {}", e),
             }
         } else {
-            error!("Error writing data: No output");
+            error!(errors, "Error writing data: No output");
         }
     }
 }
 use output::write_data;
 
 impl OutputArg {
-    fn validate(self) -> output::ValidatedOutputArg {
-        output::validate_output_arg(self)
+    fn validate(self, errors: &mut ErrorReporter) -> output::ValidatedOutputArg {
+        output::validate_output_arg(self, errors)
     }
 }
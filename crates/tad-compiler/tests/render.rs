@@ -0,0 +1,181 @@
+//! tad-compiler `song2wav` and `render` golden tests
+//!
+//! Renders a short song with the emulator and compares the .wav file against a known output.
+//!
+//! If a change to the audio driver or emulator intentionally changes the rendered audio,
+//! the golden files must be regenerated with:
+//! `TAD_UPDATE_GOLDEN_FILES=1 cargo test -p tad-compiler --test render`
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const UPDATE_GOLDEN_FILES_VAR: &str = "TAD_UPDATE_GOLDEN_FILES";
+
+const TICKS: &str = "96";
+
+const PROJECT_FILE: &str = r#"{
+  "instruments": [
+    {
+      "name": "sine",
+      "source": "sine.wav",
+      "freq": 500.0,
+      "loop": "dupe_block_hack_filter_1:2",
+      "evaluator": "default",
+      "first_octave": 1,
+      "last_octave": 6,
+      "envelope": "adsr 12 2 2 20"
+    },
+    {
+      "name": "square",
+      "source": "square.wav",
+      "freq": 500.0,
+      "loop": "loop_reset_filter:0",
+      "evaluator": "default",
+      "first_octave": 1,
+      "last_octave": 6,
+      "envelope": "gain F127"
+    }
+  ],
+  "samples": [],
+  "songs": [
+    { "name": "song", "source": "song.mml" }
+  ]
+}
+"#;
+
+const SONG: &str = r#"
+#Timer 100
+
+@1 sine
+@2 square
+
+A @1 o4 l16 v12 c e g > c
+B @2 o3 l8 v8 px-32 c g
+"#;
+
+struct TestProject(PathBuf);
+
+impl TestProject {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tad-render-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/samples");
+        for f in ["sine.wav", "square.wav"] {
+            fs::copy(samples.join(f), dir.join(f)).unwrap();
+        }
+
+        fs::write(dir.join("project.terrificaudio"), PROJECT_FILE).unwrap();
+        fs::write(dir.join("song.mml"), SONG).unwrap();
+
+        Self(dir)
+    }
+
+    fn path(&self, file_name: &str) -> String {
+        self.0.join(file_name).to_str().unwrap().to_owned()
+    }
+}
+
+impl Drop for TestProject {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(args: &[&str]) {
+    let out = Command::new(env!("CARGO_BIN_EXE_tad-compiler"))
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(
+        out.status.success(),
+        "tad-compiler failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+fn assert_golden_file(output: &str, golden_name: &str) {
+    let output = fs::read(output).unwrap();
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(golden_name);
+
+    if std::env::var_os(UPDATE_GOLDEN_FILES_VAR).is_some() {
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        fs::write(&golden_path, &output).unwrap();
+        return;
+    }
+
+    let golden = match fs::read(&golden_path) {
+        Ok(g) => g,
+        Err(e) => panic!(
+            "cannot read {}: {} (set {} to create it)",
+            golden_path.display(),
+            e,
+            UPDATE_GOLDEN_FILES_VAR
+        ),
+    };
+
+    // Not using `assert_eq!` as it would print every byte of the .wav files
+    assert_eq!(output.len(), golden.len(), "{} size", golden_name);
+    assert!(output == golden, "{} does not match", golden_name);
+}
+
+#[test]
+fn song2wav() {
+    let p = TestProject::new("song2wav");
+
+    let out = p.path("out.wav");
+    run(&[
+        "song2wav",
+        &p.path("project.terrificaudio"),
+        "song",
+        "--ticks",
+        TICKS,
+        "-o",
+        &out,
+    ]);
+
+    assert_golden_file(&out, "song2wav.wav");
+}
+
+#[test]
+fn song2wav_mono() {
+    let p = TestProject::new("song2wav_mono");
+
+    let out = p.path("out.wav");
+    run(&[
+        "song2wav",
+        &p.path("project.terrificaudio"),
+        "song",
+        "--ticks",
+        TICKS,
+        "--mono",
+        "-o",
+        &out,
+    ]);
+
+    assert_golden_file(&out, "song2wav-mono.wav");
+}
+
+#[test]
+fn render() {
+    let p = TestProject::new("render");
+
+    let project = p.path("project.terrificaudio");
+    let common = p.path("common.bin");
+    let song = p.path("song.bin");
+    let out = p.path("out.wav");
+
+    run(&["common", &project, "-o", &common]);
+    run(&["song", &project, "song", "-o", &song]);
+    run(&["render", "--ticks", TICKS, &common, &song, "-o", &out]);
+
+    assert_golden_file(&out, "render.wav");
+}
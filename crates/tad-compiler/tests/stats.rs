@@ -0,0 +1,242 @@
+//! tad-compiler `--stats` tests
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const N_INSTRUMENTS_AND_SAMPLES: u64 = 3;
+const N_SOUND_EFFECTS: u64 = 3;
+
+// EchoLength 48 (EDL 3) and EchoLength 16 (EDL 1)
+const SONG_1_ECHO_BUFFER_BYTES: u64 = 3 * 2048;
+const SONG_2_ECHO_BUFFER_BYTES: u64 = 2048;
+
+const PROJECT_FILE: &str = r#"{
+  "instruments": [
+    {
+      "name": "sine",
+      "source": "sine.wav",
+      "freq": 500.0,
+      "loop": "dupe_block_hack_filter_1:2",
+      "evaluator": "default",
+      "first_octave": 1,
+      "last_octave": 6,
+      "envelope": "gain F127"
+    },
+    {
+      "name": "square",
+      "source": "square.wav",
+      "freq": 500.0,
+      "loop": "loop_reset_filter:0",
+      "evaluator": "default",
+      "first_octave": 1,
+      "last_octave": 6,
+      "envelope": "gain F127"
+    }
+  ],
+  "samples": [
+    {
+      "name": "square_sample",
+      "source": "square.wav",
+      "loop": "none",
+      "evaluator": "default",
+      "sample_rates": [16000],
+      "envelope": "gain F127"
+    }
+  ],
+  "high_priority_sound_effects": ["sfx_high"],
+  "sound_effects": ["sfx_normal"],
+  "low_priority_sound_effects": ["sfx_low"],
+  "sound_effect_file": "sound-effects.txt",
+  "songs": [
+    { "name": "song_1", "source": "song-1.mml" },
+    { "name": "song_2", "source": "song-2.mml" }
+  ]
+}
+"#;
+
+const SOUND_EFFECTS_FILE: &str = r#"
+=== sfx_high ===
+    set_instrument sine
+    play_note c4 8
+
+=== sfx_normal ===
+    set_instrument square
+    play_note d4 8
+
+=== sfx_low ===
+    set_instrument sine
+    play_note e4 8
+"#;
+
+// 3 subroutines, nested 3 deep.
+// 144 ticks at 12.5ms per tick (1.8 seconds).
+const SONG_1: &str = r#"
+#Timer 200
+#EchoLength 48
+
+@1 sine
+
+!inner c
+!middle !inner d
+!outer !middle e
+
+A @1 l%48 !outer
+B @1 l%48 !inner r%48
+"#;
+
+// 1 subroutine
+const SONG_2: &str = r#"
+#EchoLength 16
+
+@1 square
+
+!s c
+
+A @1 !s
+"#;
+
+struct TestProject(PathBuf);
+
+impl TestProject {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tad-stats-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/samples");
+        for f in ["sine.wav", "square.wav"] {
+            fs::copy(samples.join(f), dir.join(f)).unwrap();
+        }
+
+        fs::write(dir.join("project.terrificaudio"), PROJECT_FILE).unwrap();
+        fs::write(dir.join("sound-effects.txt"), SOUND_EFFECTS_FILE).unwrap();
+        fs::write(dir.join("song-1.mml"), SONG_1).unwrap();
+        fs::write(dir.join("song-2.mml"), SONG_2).unwrap();
+
+        Self(dir)
+    }
+
+    fn project_file(&self) -> String {
+        self.0
+            .join("project.terrificaudio")
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    fn output(&self) -> String {
+        self.0.join("output.bin").to_str().unwrap().to_owned()
+    }
+}
+
+impl Drop for TestProject {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs tad-compiler and returns the stats JSON object
+fn run_with_stats(args: &[&str]) -> serde_json::Map<String, serde_json::Value> {
+    let out = Command::new(env!("CARGO_BIN_EXE_tad-compiler"))
+        .args(args)
+        .arg("--stats")
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(out.status.success(), "tad-compiler failed: {}", stderr);
+
+    let last_line = stderr.lines().last().expect("no stats output");
+    match serde_json::from_str(last_line).unwrap() {
+        serde_json::Value::Object(o) => o,
+        v => panic!("stats is not an object: {}", v),
+    }
+}
+
+fn u64_field(stats: &serde_json::Map<String, serde_json::Value>, field: &str) -> u64 {
+    stats[field].as_u64().unwrap()
+}
+
+fn assert_common_fields(stats: &serde_json::Map<String, serde_json::Value>) {
+    for field in [
+        "compile_time_ms",
+        "output_size_bytes",
+        "n_instruments",
+        "n_sound_effects",
+        "n_subroutines",
+        "max_subroutine_depth",
+        "song_duration_secs",
+        "echo_buffer_bytes",
+    ] {
+        assert!(stats.contains_key(field), "missing field: {}", field);
+    }
+    assert_eq!(stats.len(), 8);
+
+    let compile_time = stats["compile_time_ms"].as_f64().unwrap();
+    assert!(compile_time > 0.0 && compile_time < 60_000.0);
+
+    assert_eq!(u64_field(stats, "n_instruments"), N_INSTRUMENTS_AND_SAMPLES);
+}
+
+#[test]
+fn common_stats() {
+    let p = TestProject::new("common");
+
+    let stats = run_with_stats(&["common", &p.project_file(), "-o", &p.output()]);
+
+    let file_size = fs::metadata(p.output()).unwrap().len();
+
+    assert_common_fields(&stats);
+    assert_eq!(u64_field(&stats, "output_size_bytes"), file_size);
+    assert_eq!(u64_field(&stats, "n_sound_effects"), N_SOUND_EFFECTS);
+    assert_eq!(u64_field(&stats, "n_subroutines"), 0);
+    assert_eq!(u64_field(&stats, "max_subroutine_depth"), 0);
+    assert_eq!(u64_field(&stats, "echo_buffer_bytes"), 0);
+    assert!(stats["song_duration_secs"].is_null());
+}
+
+#[test]
+fn song_stats() {
+    let p = TestProject::new("song");
+
+    let stats = run_with_stats(&["song", &p.project_file(), "song_1", "-o", &p.output()]);
+
+    let file_size = fs::metadata(p.output()).unwrap().len();
+
+    assert_common_fields(&stats);
+    assert_eq!(u64_field(&stats, "output_size_bytes"), file_size);
+    assert_eq!(u64_field(&stats, "n_sound_effects"), 0);
+    assert_eq!(u64_field(&stats, "n_subroutines"), 3);
+    assert_eq!(u64_field(&stats, "max_subroutine_depth"), 3);
+    assert_eq!(
+        u64_field(&stats, "echo_buffer_bytes"),
+        SONG_1_ECHO_BUFFER_BYTES
+    );
+
+    let duration = stats["song_duration_secs"].as_f64().unwrap();
+    assert!((duration - 1.8).abs() < 0.01, "duration {}", duration);
+}
+
+#[test]
+fn check_stats() {
+    let p = TestProject::new("check");
+
+    let stats = run_with_stats(&["check", &p.project_file()]);
+
+    assert_common_fields(&stats);
+    assert!(u64_field(&stats, "output_size_bytes") > 0);
+    assert_eq!(u64_field(&stats, "n_sound_effects"), N_SOUND_EFFECTS);
+    // Sum of all songs
+    assert_eq!(u64_field(&stats, "n_subroutines"), 3 + 1);
+    // Maximum of all songs
+    assert_eq!(u64_field(&stats, "max_subroutine_depth"), 3);
+    assert_eq!(
+        u64_field(&stats, "echo_buffer_bytes"),
+        SONG_1_ECHO_BUFFER_BYTES.max(SONG_2_ECHO_BUFFER_BYTES)
+    );
+    assert!(stats["song_duration_secs"].is_null());
+}
@@ -0,0 +1,92 @@
+//! tad-compiler `validate` tests
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PROJECT_FILE: &str = r#"{
+  "instruments": [
+    {
+      "name": "sine",
+      "source": "sine.wav",
+      "freq": 500.0,
+      "loop": "none",
+      "evaluator": "default",
+      "first_octave": 1,
+      "last_octave": 6,
+      "envelope": "gain F127"
+    }
+  ],
+  "samples": [],
+  "high_priority_sound_effects": [],
+  "sound_effects": [],
+  "low_priority_sound_effects": [],
+  "songs": [
+    { "name": "song_1", "source": "song-1.mml" },
+    { "name": "song_2", "source": "song-2.mml" }
+  ]
+}
+"#;
+
+struct TestProject(PathBuf);
+
+impl TestProject {
+    fn new(name: &str, song_1: &str, song_2: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("tad-validate-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/samples");
+        fs::copy(samples.join("sine.wav"), dir.join("sine.wav")).unwrap();
+
+        fs::write(dir.join("project.terrificaudio"), PROJECT_FILE).unwrap();
+        fs::write(dir.join("song-1.mml"), song_1).unwrap();
+        fs::write(dir.join("song-2.mml"), song_2).unwrap();
+
+        Self(dir)
+    }
+
+    /// Returns the exit code of `tad-compiler validate`
+    fn validate(&self) -> i32 {
+        let project_file = self.0.join("project.terrificaudio");
+
+        let out = Command::new(env!("CARGO_BIN_EXE_tad-compiler"))
+            .arg("validate")
+            .arg(&project_file)
+            .output()
+            .unwrap();
+
+        out.status.code().unwrap()
+    }
+}
+
+impl Drop for TestProject {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn valid_project() {
+    let p = TestProject::new("valid", "A r", "A r r");
+    assert_eq!(p.validate(), 0);
+}
+
+/// The exit code is the total number of errors in every song
+#[test]
+fn exit_code_is_error_count() {
+    // 1 header error, 1 error in channel A and 1 error in channel B
+    let p = TestProject::new("error-count", "#Unknown\nA r\n", "@1 sine\nA c d\nB e\n");
+    assert_eq!(p.validate(), 3);
+}
+
+#[test]
+fn exit_code_is_clamped() {
+    let song = format!("@1 sine\nA @1 {}\n", "o9 ".repeat(300));
+    let p = TestProject::new("clamped", &song, "A r");
+    assert_eq!(p.validate(), 255);
+}
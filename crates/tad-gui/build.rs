@@ -1,6 +1,8 @@
 //! Audio Driver GUI uild script
 //!
 //! Compiles `.md` files into HTML 2.0 (ish) files for syntax help sidebar.
+//!
+//! Sets the `TAD_BUILD_DATE` and `TAD_GIT_HASH` environment variables for the about tab.
 
 // SPDX-FileCopyrightText: © 2023 Marcus Rowe <undisbeliever@gmail.com>
 //
@@ -10,6 +12,8 @@ use std::env;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 extern crate markdown;
 extern crate regex;
@@ -107,6 +111,46 @@ fn validate_no_links_in_html(html: &str, name: &str) {
     }
 }
 
+fn git_hash() -> String {
+    Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Returns the build date in `YYYY-MM-DD` format.
+///
+/// Uses `SOURCE_DATE_EPOCH` (if set) to allow for reproducible builds.
+fn build_date() -> String {
+    let secs = match env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+    {
+        Some(s) => s,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+
+    // Howard Hinnant's `civil_from_days` algorithm
+    let z = i64::try_from(secs / 86400).unwrap() + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
 fn main() {
     let out_dir: PathBuf = env::var_os("OUT_DIR").unwrap().into();
     if !out_dir.is_dir() {
@@ -125,8 +169,39 @@ fn main() {
         fs::write(&out_path, html).unwrap();
     }
 
+    println!("cargo:rustc-env=TAD_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=TAD_GIT_HASH={}", git_hash());
+
     println!("cargo:rerun-if-changed=build.rs");
     for f in MARKDOWN_FILES {
         println!("cargo:rerun-if-changed={}", f);
     }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    rerun_if_git_head_changed();
+}
+
+/// Updates the git hash on commit or checkout.
+///
+/// Watches `.git/HEAD` and the branch it points to.
+fn rerun_if_git_head_changed() {
+    const GIT_DIR: &str = "../../.git";
+
+    let head = Path::new(GIT_DIR).join("HEAD");
+    let head_contents = match fs::read_to_string(&head) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    println!("cargo:rerun-if-changed={}", head.display());
+
+    if let Some(r) = head_contents.trim().strip_prefix("ref: ") {
+        let ref_path = Path::new(GIT_DIR).join(r);
+        let packed_refs = Path::new(GIT_DIR).join("packed-refs");
+
+        if ref_path.is_file() {
+            println!("cargo:rerun-if-changed={}", ref_path.display());
+        } else if packed_refs.is_file() {
+            println!("cargo:rerun-if-changed={}", packed_refs.display());
+        }
+    }
 }
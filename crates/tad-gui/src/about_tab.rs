@@ -13,10 +13,23 @@ use fltk::button::Button;
 use fltk::enums::{CallbackReason, CallbackTrigger, Color, Font};
 use fltk::frame::Frame;
 use fltk::group::{Flex, Tabs};
-use fltk::prelude::{GroupExt, WidgetExt};
+use fltk::prelude::{DisplayExt, GroupExt, WidgetExt};
+use fltk::text::{TextBuffer, TextDisplay, WrapMode};
 
 const VERSION_STR: &str = concat!["Version ", env!("CARGO_PKG_VERSION")];
 
+const BUILD_INFO: &str = concat![
+    "terrific audio driver ",
+    env!("CARGO_PKG_VERSION"),
+    "\nBuild date: ",
+    env!("TAD_BUILD_DATE"),
+    "\nGit commit: ",
+    env!("TAD_GIT_HASH"),
+    "\n\n",
+];
+
+const CHANGELOG: &str = include_str!("../../../docs/changelog.md");
+
 const COPYRIGHT_TEXT: &str = "Copyright © 2023 Marcus Rowe";
 
 const WARNING_TEXT: &str = concat![
@@ -51,6 +64,7 @@ fn no_project_button_group(sender: app::Sender<GuiMessage>) -> Flex {
     };
 
     button("New Project", || GuiMessage::NewProject);
+    button("From Template", || GuiMessage::ShowProjectTemplateDialog);
     button("Open Project", || GuiMessage::OpenProject);
 
     Frame::default();
@@ -87,6 +101,17 @@ impl AboutTab {
         warning.set_label_font(Font::ScreenBold);
         warning.set_label_color(Color::Red);
         warning.set_label_size(group.label_size() + 4);
+        group.fixed(&warning, input_height(&warning) * 4);
+
+        let mut changelog = TextDisplay::default();
+        changelog.set_text_font(Font::Courier);
+        changelog.wrap_mode(WrapMode::AtBounds, 0);
+        {
+            let mut buffer = TextBuffer::default();
+            buffer.set_text(BUILD_INFO);
+            buffer.append(CHANGELOG);
+            changelog.set_buffer(buffer);
+        }
 
         group.end();
 
@@ -7,19 +7,17 @@
 #![allow(clippy::assertions_on_constants)]
 
 use brr::{BrrSample, SAMPLES_PER_BLOCK};
-use compiler::audio_driver;
-use compiler::bytecode_interpreter;
-use compiler::bytecode_interpreter::Emulator;
+use compiler::bytecode_interpreter::ChannelDisplayState;
+use compiler::bytecode_interpreter::EnvelopeKind;
 use compiler::bytecode_interpreter::SongInterpreter;
 use compiler::common_audio_data::CommonAudioData;
 use compiler::driver_constants::N_CHANNELS;
 use compiler::driver_constants::N_DSP_VOICES;
 use compiler::driver_constants::N_MUSIC_CHANNELS;
-use compiler::driver_constants::SONG_HEADER_ECHO_EDL;
 use compiler::driver_constants::{
-    addresses, io_commands, LoaderDataType, FIRST_SFX_CHANNEL, IO_COMMAND_I_MASK, IO_COMMAND_MASK,
-    N_SFX_CHANNELS,
+    addresses, io_commands, FIRST_SFX_CHANNEL, IO_COMMAND_I_MASK, IO_COMMAND_MASK, N_SFX_CHANNELS,
 };
+use compiler::emulator::load_song_into_emulator;
 use compiler::mml::MmlPrefixData;
 use compiler::songs::{blank_song, SongData};
 use compiler::sound_effects::CompiledSoundEffect;
@@ -84,9 +82,25 @@ impl MusicChannelsMask {
     }
 }
 
+/// Restarts the song at `start` when the song tick counter reaches `end`
+#[derive(Debug, Clone, Copy)]
+pub struct SongLoop {
+    pub start: TickCounter,
+    // Loop end is the end of the song if None
+    pub end: Option<TickCounter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveSongLoop {
+    start: TickCounter,
+    end: TickCounter,
+    interpreter_mask: Option<MusicChannelsMask>,
+}
+
 enum SongSkip {
     None,
-    Song(TickCounter),
+    // Channels disabled in the optional mask are not interpreted
+    Song(TickCounter, Option<MusicChannelsMask>),
     Subroutine(Option<MmlPrefixData>, u8, TickCounter),
 }
 
@@ -115,7 +129,15 @@ pub enum AudioMessage {
     CommonAudioDataSfxBufferChanged(Option<Arc<CommonAudioDataWithSfxBuffer>>),
     CommandAudioDataWithSfxChanged(Option<Arc<CommonAudioDataWithSfx>>),
 
-    PlaySong(ItemId, Arc<SongData>, TickCounter, MusicChannelsMask),
+    // Channels disabled in the optional interpreter mask are not interpreted
+    PlaySong(
+        ItemId,
+        Arc<SongData>,
+        TickCounter,
+        MusicChannelsMask,
+        Option<MusicChannelsMask>,
+        Option<SongLoop>,
+    ),
     PlaySongSubroutine(
         ItemId,
         Arc<SongData>,
@@ -155,6 +177,12 @@ pub struct AudioMonitorData {
     pub voice_instruction_ptrs: [Option<u16>; N_MUSIC_CHANNELS],
     /// May not be valid.
     pub voice_return_inst_ptrs: [Option<u16>; N_MUSIC_CHANNELS],
+    /// Empty if the bytecode interpreter is not active.
+    pub channel_instruments: Vec<Option<(u8, EnvelopeKind)>>,
+    /// Only the lower 16 bits are valid if the bytecode interpreter is not active.
+    pub song_tick_counter: Option<TickCounter>,
+    /// All channels are inactive if the bytecode interpreter is not active.
+    pub channel_activity: [ChannelDisplayState; N_MUSIC_CHANNELS],
 }
 
 impl AudioMonitorData {
@@ -163,6 +191,9 @@ impl AudioMonitorData {
             song_id,
             voice_instruction_ptrs: Default::default(),
             voice_return_inst_ptrs: Default::default(),
+            channel_instruments: Vec::new(),
+            song_tick_counter: None,
+            channel_activity: Default::default(),
         }
     }
 }
@@ -403,21 +434,6 @@ impl<'a> BrrSampleDecoder<'a> {
     }
 }
 
-struct EmulatorWrapper<'a>(&'a mut ShvcSoundEmu);
-impl bytecode_interpreter::Emulator for EmulatorWrapper<'_> {
-    fn apuram_mut(&mut self) -> &mut [u8; 0x10000] {
-        self.0.apuram_mut()
-    }
-
-    fn write_smp_register(&mut self, addr: u8, value: u8) {
-        self.0.write_smp_register(addr, value);
-    }
-
-    fn program_counter(&self) -> u16 {
-        self.0.program_counter()
-    }
-}
-
 enum AudioDataState {
     NotLoaded,
     CommonDataOutOfDate, // Audio is still platying
@@ -461,8 +477,11 @@ fn create_and_process_song_interpreter(
 
     match song_skip {
         SongSkip::None => Ok(Some(SongInterpreter::new(cad, sd, stereo_flag))),
-        SongSkip::Song(ticks) => {
+        SongSkip::Song(ticks, interpreter_mask) => {
             let mut si = SongInterpreter::new(cad, sd, stereo_flag);
+            if let Some(mask) = interpreter_mask {
+                si = si.with_channel_mask(mask.0);
+            }
             if si.process_song_skip_ticks(ticks) {
                 Ok(Some(si))
             } else {
@@ -503,6 +522,7 @@ struct TadEmu {
     data_state: AudioDataState,
     song_id: Option<ItemId>,
     bc_interpreter: Option<SongInterpreter<SiCad, Arc<SongData>>>,
+    song_loop: Option<ActiveSongLoop>,
 
     previous_command: u8,
     sfx_queue: SfxQueue,
@@ -522,6 +542,7 @@ impl TadEmu {
             cad_with_sfx: None,
             data_state: AudioDataState::NotLoaded,
             bc_interpreter: None,
+            song_loop: None,
             song_id: None,
             previous_command: 0,
             sfx_queue: SfxQueue::None,
@@ -580,7 +601,15 @@ impl TadEmu {
         song: Arc<SongData>,
         skip: TickCounter,
         music_channels_mask: MusicChannelsMask,
+        interpreter_mask: Option<MusicChannelsMask>,
+        song_loop: Option<SongLoop>,
     ) -> Result<(), ()> {
+        let song_loop = song_loop.map(|l| ActiveSongLoop {
+            start: l.start,
+            end: l.end.unwrap_or_else(|| song.max_tick_count()),
+            interpreter_mask,
+        });
+
         let data = match (
             &self.cad_with_sfx,
             &self.cad_with_sfx_buffer,
@@ -594,8 +623,53 @@ impl TadEmu {
         self._load_song_into_memory(
             Some(song_id),
             data,
-            SongSkip::Song(skip),
+            SongSkip::Song(skip, interpreter_mask),
             music_channels_mask,
+        )?;
+
+        // Prevents the song from restarting every time the ring buffer is filled
+        self.song_loop = song_loop.filter(|l| l.start < l.end);
+
+        Ok(())
+    }
+
+    /// Returns true if the song is looping and has reached the loop end
+    /// (or has finished playing).
+    fn song_loop_end_reached(&self, song_finished: bool) -> bool {
+        match (&self.song_loop, &self.bc_interpreter) {
+            (Some(_), _) if song_finished => true,
+            (Some(l), Some(bci)) => bci.tick_counter() >= l.end,
+            _ => false,
+        }
+    }
+
+    /// Restarts the song at the loop start.
+    ///
+    /// The song is reloaded (instead of seeking the bytecode interpreter) as the
+    /// emulator state must also be reset.
+    fn restart_song_loop(&mut self) -> Result<(), ()> {
+        let song_loop = self.song_loop.ok_or(())?;
+        let song_id = self.song_id.ok_or(())?;
+
+        let song = match &self.data_state {
+            AudioDataState::SongNoSfx(_, sd)
+            | AudioDataState::SongAndSfx(_, sd)
+            | AudioDataState::SongWithSfxBuffer(_, sd) => sd.clone(),
+            _ => return Err(()),
+        };
+
+        let mask = self.emu.apuram()[addresses::IO_MUSIC_CHANNELS_MASK as usize];
+
+        self.load_song(
+            song_id,
+            song,
+            song_loop.start,
+            MusicChannelsMask(mask),
+            song_loop.interpreter_mask,
+            Some(SongLoop {
+                start: song_loop.start,
+                end: Some(song_loop.end),
+            }),
         )
     }
 
@@ -643,7 +717,7 @@ impl TadEmu {
         self._load_song_into_memory(
             Some(song_id),
             data,
-            SongSkip::Song(skip),
+            SongSkip::Song(skip, None),
             MusicChannelsMask::ALL,
         )
     }
@@ -676,11 +750,10 @@ impl TadEmu {
         song_skip: SongSkip,
         music_channels_mask: MusicChannelsMask,
     ) -> Result<(), ()> {
-        const LOADER_DATA_TYPE_ADDR: usize = addresses::LOADER_DATA_TYPE as usize;
-
         self.data_state = AudioDataState::NotLoaded;
         self.song_id = None;
         self.bc_interpreter = None;
+        self.song_loop = None;
 
         self.sfx_queue = SfxQueue::None;
 
@@ -693,10 +766,6 @@ impl TadEmu {
             AudioDataState::SongWithSfxBuffer(cad, sd) => (&cad.0, sd.as_ref()),
         };
 
-        let song_data = song.data();
-        let common_data = common_audio_data.data();
-        let echo_buffer = &song.metadata().echo_buffer;
-
         let stereo_flag = match self.stereo_flag {
             StereoFlag::Stereo => true,
             StereoFlag::Mono => false,
@@ -705,66 +774,17 @@ impl TadEmu {
         self.bc_interpreter =
             create_and_process_song_interpreter(&data_state, song_skip, stereo_flag)?;
 
-        let song_data_addr = common_audio_data.song_data_addr();
-
-        let apuram = self.emu.apuram_mut();
-
-        let mut write_spc_ram = |addr: u16, data: &[u8]| {
-            let addr = usize::from(addr);
-            apuram[addr..addr + data.len()].copy_from_slice(data);
-        };
-
-        // Load driver
-        write_spc_ram(addresses::LOADER, audio_driver::LOADER);
-        write_spc_ram(addresses::DRIVER_CODE, audio_driver::AUDIO_DRIVER);
-
-        write_spc_ram(addresses::COMMON_DATA, common_data);
-        write_spc_ram(addresses::SONG_PTR, &song_data_addr.to_le_bytes());
-        write_spc_ram(song_data_addr, song_data);
-
-        // Reset echo buffer
-        let eb_start = usize::from(echo_buffer.buffer_addr());
-        let eb_end = eb_start + echo_buffer.buffer_size();
-        apuram[eb_start..eb_end].fill(0);
-
-        // Set loader flags
-        apuram[LOADER_DATA_TYPE_ADDR] = LoaderDataType {
+        if load_song_into_emulator(
+            &mut self.emu,
+            common_audio_data,
+            song,
+            self.bc_interpreter.as_ref(),
             stereo_flag,
-            play_song: false,
-            skip_echo_buffer_reset: true,
-        }
-        .driver_value();
-
-        // The echo buffer registers must be setup BEFORE the emulator processes instructions.
-        // Otherwise the audio sounds weird.
-        let (esa, edl) = match &self.bc_interpreter {
-            Some(bci) => {
-                apuram[usize::from(song_data_addr) + SONG_HEADER_ECHO_EDL] = bci.song_header_edl();
-
-                (bci.esa_register(), bci.edl_register())
-            }
-            None => (echo_buffer.esa_register(), echo_buffer.edl_register()),
-        };
-
-        self.emu.reset(shvc_sound_emu::ResetRegisters {
-            pc: addresses::DRIVER_CODE,
-            a: 0,
-            x: 0,
-            y: 0,
-            psw: 0,
-            sp: 0xff,
-            esa,
-            edl,
-        });
-
-        // Wait for the audio-driver to finish initialization
-        let mut emu_wrapper = EmulatorWrapper(&mut self.emu);
-        while !emu_wrapper.is_pc_in_mainloop() {
-            emu_wrapper.0.emulate();
-        }
-
-        if let Some(bci) = &self.bc_interpreter {
-            bci.write_to_emulator(&mut emu_wrapper);
+        )
+        .is_err()
+        {
+            self.bc_interpreter = None;
+            return Err(());
         }
 
         self.set_music_channels_mask(music_channels_mask);
@@ -904,6 +924,7 @@ impl TadEmu {
             end: addresses::CHANNEL_INSTRUCTION_PTR_H as usize + N_CHANNELS,
         };
         const COMMON_DATA_ADDR_H: u8 = (addresses::COMMON_DATA >> 8) as u8;
+        const STC: usize = addresses::SONG_TICK_COUNTER as usize;
 
         if !self.song_loaded() {
             return None;
@@ -939,10 +960,8 @@ impl TadEmu {
             addresses::CHANNEL_INSTRUCTION_PTR_H,
         );
 
-        let voice_return_inst_ptrs = match &mut self.bc_interpreter {
+        let (voice_return_inst_ptrs, channel_instruments) = match &mut self.bc_interpreter {
             Some(b) => {
-                const STC: usize = addresses::SONG_TICK_COUNTER as usize;
-
                 // Assumes number of ticks since the last read was < 256;
                 let bc_tick_counter_l = b.tick_counter().value().to_le_bytes()[0];
                 let emu_tick_counter_l = apuram[STC];
@@ -960,11 +979,24 @@ impl TadEmu {
 
                 let channels = b.channels();
 
-                std::array::from_fn(|i| channels[i].as_ref().and_then(|c| c.topmost_return_pos))
+                (
+                    std::array::from_fn(|i| {
+                        channels[i].as_ref().and_then(|c| c.topmost_return_pos)
+                    }),
+                    b.channel_instrument_state(),
+                )
             }
             None => Default::default(),
         };
 
+        let (song_tick_counter, channel_activity) = match &self.bc_interpreter {
+            Some(b) => (b.tick_counter(), b.channel_activity()),
+            None => (
+                TickCounter::new(u16::from_le_bytes([apuram[STC], apuram[STC + 1]]).into()),
+                Default::default(),
+            ),
+        };
+
         let any_channels_active = apuram[ALL_CHANNELS_INSTRUCTION_PTR_H_RANGE]
             .iter()
             .any(|&inst_ptr_h| inst_ptr_h > COMMON_DATA_ADDR_H);
@@ -974,6 +1006,9 @@ impl TadEmu {
                 song_id: self.song_id,
                 voice_instruction_ptrs,
                 voice_return_inst_ptrs,
+                channel_instruments,
+                song_tick_counter: Some(song_tick_counter),
+                channel_activity,
             })
         } else {
             None
@@ -1078,10 +1113,24 @@ impl AudioThread {
                 self.tad.stop_song();
             }
 
-            AudioMessage::PlaySong(song_id, song, song_skip, channels_mask) => {
+            AudioMessage::PlaySong(
+                song_id,
+                song,
+                song_skip,
+                channels_mask,
+                interpreter_mask,
+                song_loop,
+            ) => {
                 if self
                     .tad
-                    .load_song(song_id, song.clone(), song_skip, channels_mask)
+                    .load_song(
+                        song_id,
+                        song.clone(),
+                        song_skip,
+                        channels_mask,
+                        interpreter_mask,
+                        song_loop,
+                    )
                     .is_ok()
                 {
                     self.send_started_song_message(song_id, song);
@@ -1197,7 +1246,15 @@ impl AudioThread {
                             // Must test if the emulator is outputting audio as the echo buffer
                             // feedback can output sound long after the song has finished.
                             let voices = self.tad.read_voice_positions();
-                            if voices.is_some() {
+                            if self.tad.song_loop_end_reached(voices.is_none()) {
+                                // Prevents a buffer overrun when the loop start is large
+                                playback.pause();
+                                playback.lock().reset();
+                                match self.tad.restart_song_loop() {
+                                    Ok(()) => playback.resume(),
+                                    Err(()) => state = PlayState::PauseRequested,
+                                }
+                            } else if voices.is_some() {
                                 self.monitor.set(voices);
                             } else if sound {
                                 self.monitor
@@ -1235,14 +1292,25 @@ impl AudioThread {
                     self.tad.load_cad_with_sfx(data)
                 }
 
-                AudioMessage::PlaySong(id, song, song_skip, channels_mask) => {
+                AudioMessage::PlaySong(
+                    id,
+                    song,
+                    song_skip,
+                    channels_mask,
+                    interpreter_mask,
+                    song_loop,
+                ) => {
                     // Pause playback to prevent buffer overrun when tick_to_skip is large.
                     playback.pause();
                     playback.lock().reset();
-                    match self
-                        .tad
-                        .load_song(id, song.clone(), song_skip, channels_mask)
-                    {
+                    match self.tad.load_song(
+                        id,
+                        song.clone(),
+                        song_skip,
+                        channels_mask,
+                        interpreter_mask,
+                        song_loop,
+                    ) {
                         Ok(()) => {
                             self.send_started_song_message(id, song);
 
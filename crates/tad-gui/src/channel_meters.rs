@@ -0,0 +1,91 @@
+//! Channel activity meters
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::helpers::input_height;
+
+use compiler::bytecode_interpreter::ChannelDisplayState;
+use compiler::driver_constants::N_MUSIC_CHANNELS;
+use compiler::Pan;
+
+use fltk::draw;
+use fltk::enums::Color;
+use fltk::group::Flex;
+use fltk::prelude::*;
+use fltk::widget::Widget;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const VOLUME_COLOR: Color = Color::DarkGreen;
+const PAN_COLOR: Color = Color::Foreground;
+
+/// Displays the volume and pan of each music channel as a horizontal bar.
+///
+/// The meters are aligned with the columns of an equally spaced `Flex` row.
+pub struct ChannelMeters {
+    widget: Widget,
+    activity: Rc<RefCell<[ChannelDisplayState; N_MUSIC_CHANNELS]>>,
+}
+
+impl ChannelMeters {
+    pub fn new(parent: &mut Flex) -> Self {
+        let mut widget = Widget::default();
+        parent.fixed(&widget, input_height(&widget) / 2);
+        widget.set_tooltip("Channel volume and pan");
+
+        let activity = Rc::new(RefCell::new(Default::default()));
+
+        widget.draw({
+            let activity = activity.clone();
+            move |w| {
+                if let Ok(a) = activity.try_borrow() {
+                    Self::draw(w, &a);
+                }
+            }
+        });
+
+        Self { widget, activity }
+    }
+
+    pub fn set_activity(&mut self, activity: &[ChannelDisplayState; N_MUSIC_CHANNELS]) {
+        if let Ok(mut a) = self.activity.try_borrow_mut() {
+            if *a != *activity {
+                *a = *activity;
+                self.widget.redraw();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.set_activity(&Default::default());
+    }
+
+    fn draw(w: &Widget, activity: &[ChannelDisplayState; N_MUSIC_CHANNELS]) {
+        draw::draw_rect_fill(w.x(), w.y(), w.w(), w.h(), Color::Background);
+
+        let n_channels = N_MUSIC_CHANNELS as i32;
+
+        let y = w.y() + 1;
+        let h = w.h() - 2;
+
+        for (i, a) in (0..).zip(activity) {
+            let x1 = w.x() + w.w() * i / n_channels + 1;
+            let x2 = w.x() + w.w() * (i + 1) / n_channels - 1;
+            let bar_w = x2 - x1;
+
+            draw::draw_rect_fill(x1, y, bar_w, h, Color::Background2);
+
+            if a.active && !a.disabled {
+                let vol_w = bar_w * i32::from(a.volume) / i32::from(u8::MAX);
+                draw::draw_rect_fill(x1, y, vol_w, h, VOLUME_COLOR);
+
+                let pan_x = x1 + bar_w * i32::from(a.pan) / i32::from(Pan::MAX.as_u8());
+                draw::set_draw_color(PAN_COLOR);
+                draw::draw_line(pan_x, y, pan_x, y + h - 1);
+            }
+        }
+    }
+}
@@ -10,7 +10,7 @@ use crate::sfx_export_order::{GuiSfxExportOrder, SfxExportOrderAction};
 use crate::GuiMessage;
 
 use crate::audio_thread::{
-    AudioMessage, CommonAudioDataNoSfx, CommonAudioDataWithSfxBuffer, MusicChannelsMask,
+    AudioMessage, CommonAudioDataNoSfx, CommonAudioDataWithSfxBuffer, MusicChannelsMask, SongLoop,
     SFX_BUFFER_SIZE,
 };
 
@@ -27,16 +27,17 @@ use compiler::data::{load_text_file_with_limit, DefaultSfxFlags, LoopSetting, Te
 use compiler::driver_constants::COMMON_DATA_BYTES_PER_SOUND_EFFECT;
 use compiler::envelope::Envelope;
 use compiler::errors::{
-    self, BrrError, CommonAudioDataErrors, ExportSpcFileError, MmlPrefixError, ProjectFileErrors,
-    SongTooLargeError,
+    self, BrrError, CommonAudioDataError, CommonAudioDataErrors, ExportSpcFileError,
+    InstrumentPitchWarning, MmlPrefixError, ProjectFileErrors, SongTooLargeError,
 };
-use compiler::mml::compile_mml_prefix;
+use compiler::mml::{compile_mml_prefix, compile_mml_with_metrics, ParseMetrics};
 use compiler::notes::Note;
 use compiler::path::{ParentPathBuf, SourcePathBuf};
+use compiler::pitch_table::validate_instrument_pitch_ranges;
 use compiler::samples::{
-    combine_samples, create_test_instrument_data, encode_or_load_brr_file,
-    load_sample_for_instrument, load_sample_for_sample, CompiledDataList, InstrumentSampleData,
-    SampleAndInstrumentData, SampleFileCache, SampleSampleData, WAV_EXTENSION,
+    combine_samples, create_test_instrument_data, is_pcm_source, load_sample_for_instrument,
+    load_sample_for_sample, CompiledDataList, InstrumentSampleData, PcmSampleOptions,
+    SampleAndInstrumentData, SampleFileCache, SampleSampleData,
 };
 use compiler::songs::{test_sample_song, SongAramSize, SongData, BLANK_SONG_ARAM_SIZE};
 use compiler::sound_effects::{
@@ -44,8 +45,10 @@ use compiler::sound_effects::{
     CombinedSoundEffectsData, CompiledSfxMap, CompiledSfxSubroutines, SfxExportOrder,
     SfxSubroutinesMml,
 };
-use compiler::sound_effects::{compile_sound_effect_input, CompiledSoundEffect, SoundEffectInput};
-use compiler::spc_file_export::export_spc_file;
+use compiler::sound_effects::{
+    compile_sound_effect_input, compile_sound_effects_stream, CompiledSoundEffect, SoundEffectInput,
+};
+use compiler::spc_file_export::export_spc_file_with_title;
 use compiler::Pan;
 
 extern crate fltk;
@@ -129,7 +132,7 @@ pub enum ToCompiler {
     Instrument(ItemChanged<data::Instrument>),
     Sample(ItemChanged<data::Sample>),
 
-    AnalyseSample(SourcePathBuf, LoopSetting, BrrEvaluator),
+    AnalyseSample(SourcePathBuf, LoopSetting, BrrEvaluator, PcmSampleOptions),
 
     // Updates sfx_data_size and rechecks song sizes.
     // (sent when the user deselects the sound effects tab in the GUI)
@@ -141,8 +144,19 @@ pub enum ToCompiler {
     PlaySfxUsingSfxBuffer(ItemId, Pan),
 
     SongTabClosed(ItemId),
+    // Sets the path used to resolve `#include` directives.
+    // Must be sent before `SongChanged` when opening a MML file.
+    SongSourceChanged(ItemId, SourcePathBuf),
     SongChanged(ItemId, String),
-    CompileAndPlaySong(ItemId, String, TickCounter, MusicChannelsMask),
+    // Channels disabled in the optional interpreter mask are not interpreted
+    CompileAndPlaySong(
+        ItemId,
+        String,
+        TickCounter,
+        MusicChannelsMask,
+        Option<MusicChannelsMask>,
+        Option<SongLoop>,
+    ),
     CompileAndPlaySongSubroutine(ItemId, String, Option<String>, u8, TickCounter),
     PlayInstrument(ItemId, PlaySampleArgs),
     PlaySample(ItemId, PlaySampleArgs),
@@ -154,7 +168,13 @@ pub enum ToCompiler {
 }
 
 #[derive(Debug)]
-pub struct InstrumentSize(pub usize);
+pub struct InstrumentSize {
+    pub data_size: usize,
+
+    /// Encoded BRR data (used by the waveform preview)
+    pub brr_data: Vec<u8>,
+    pub loop_offset: Option<u16>,
+}
 
 #[derive(Debug)]
 pub struct SampleSize(pub usize);
@@ -173,11 +193,14 @@ pub enum CompilerOutput {
     Sample(ItemId, SampleOutput),
 
     CommonAudioData(CadOutput),
+    InstrumentPitchWarnings(Vec<InstrumentPitchWarning>),
 
     SfxSubroutines(SfxSubroutineOutput),
     SoundEffect(ItemId, SoundEffectOutput),
 
     Song(ItemId, SongOutput),
+    // Sent before the `Song` output
+    SongMetrics(ItemId, ParseMetrics),
     SongPrefix(ItemId, Result<(), MmlPrefixError>),
 
     NumberOfMissingSoundEffects(usize),
@@ -291,7 +314,7 @@ impl Display for SfxSubroutinesError {
 pub enum SongError {
     Dependency,
     Song(errors::SongError),
-    TooLarge(SongTooLargeError),
+    TooLarge(SongTooLargeError, Option<CommonAudioDataError>),
 }
 
 impl SongError {
@@ -299,7 +322,7 @@ impl SongError {
         match self {
             Self::Dependency => ShortSongError::Dependency,
             Self::Song(_) => ShortSongError::Song,
-            Self::TooLarge(_) => ShortSongError::TooLarge,
+            Self::TooLarge(..) => ShortSongError::TooLarge,
         }
     }
 }
@@ -355,6 +378,15 @@ fn decrement_index_from_map<T>(map: &mut HashMap<T, usize>, index: usize) {
     }
 }
 
+// Returns the map's `(ItemId, index)` pairs sorted by index.
+//
+// Used to process items in a deterministic order (HashMap iteration order is random).
+fn sorted_map_entries(map: &HashMap<ItemId, usize>) -> Vec<(ItemId, usize)> {
+    let mut v: Vec<_> = map.iter().map(|(id, i)| (*id, *i)).collect();
+    v.sort_unstable_by_key(|(_, i)| *i);
+    v
+}
+
 struct IList<ItemT> {
     items: Vec<ItemT>,
     map: HashMap<ItemId, usize>,
@@ -372,6 +404,16 @@ impl<ItemT> IList<ItemT> {
         self.map.get(id).and_then(|i| self.items.get(*i))
     }
 
+    /// Returns the items in list order.
+    ///
+    /// NOTE: Items added after the last `replace_all()` are at the end of the list.
+    fn items_ordered(&self) -> Vec<(ItemId, &ItemT)> {
+        sorted_map_entries(&self.map)
+            .into_iter()
+            .map(|(id, i)| (id, &self.items[i]))
+            .collect()
+    }
+
     fn replace_all(&mut self, data: ReplaceAllVec<ItemT>) {
         self.map = data
             .0
@@ -569,7 +611,7 @@ where
     }
 
     fn recompile_all(&mut self, mut compiler_fn: impl FnMut(ItemId, &ItemT) -> OutT) {
-        for (&id, &index) in &self.map {
+        for (id, index) in sorted_map_entries(&self.map) {
             let out = compiler_fn(id, &self.items[index]);
             self.output[index] = out;
         }
@@ -580,7 +622,7 @@ where
         mut compiler_fn: impl FnMut(ItemId, &ItemT) -> OutT,
         filter_fn: impl Fn(&ItemT) -> bool,
     ) {
-        for (&id, &index) in &self.map {
+        for (id, index) in sorted_map_entries(&self.map) {
             let item = &self.items[index];
             if filter_fn(item) {
                 let out = compiler_fn(id, item);
@@ -634,7 +676,11 @@ fn create_instrument_compiler<'a>(
         Ok(s) => {
             sender.send(CompilerOutput::Instrument(
                 id,
-                Ok(InstrumentSize(s.sample_size())),
+                Ok(InstrumentSize {
+                    data_size: s.sample_size(),
+                    brr_data: s.brr_sample().brr_data().to_vec(),
+                    loop_offset: s.brr_sample().loop_offset(),
+                }),
             ));
             Some(s)
         }
@@ -796,6 +842,48 @@ fn create_sfx_compiler<'a>(
     }
 }
 
+fn recompile_all_sfx(
+    dependencies: &Option<SongDependencies>,
+    sfx_subroutines: &Option<Arc<CompiledSfxSubroutines>>,
+    sound_effects: &mut CList<SoundEffectInput, Option<Arc<CompiledSoundEffect>>>,
+    sender: &Sender,
+) {
+    let (dep, subroutines) = match (dependencies, sfx_subroutines) {
+        (Some(d), Some(s)) => (d, s),
+        _ => {
+            // Sends a dependency error for each sound effect
+            let c = create_sfx_compiler(dependencies, sfx_subroutines, sender);
+            sound_effects.recompile_all(c);
+            return;
+        }
+    };
+
+    let entries = sorted_map_entries(&sound_effects.map);
+    let items = &sound_effects.items;
+    let output = &mut sound_effects.output;
+
+    let stream = compile_sound_effects_stream(
+        entries.iter().map(|(_, index)| &items[*index]),
+        &dep.inst_map,
+        dep.combined_samples.pitch_table(),
+        subroutines,
+    );
+
+    for ((id, index), (_name, r)) in entries.iter().zip(stream) {
+        output[*index] = match r {
+            Ok(sfx) => {
+                let sfx = Arc::from(sfx);
+                sender.send(CompilerOutput::SoundEffect(*id, Ok(sfx.clone())));
+                Some(sfx)
+            }
+            Err(e) => {
+                sender.send(CompilerOutput::SoundEffect(*id, Err(SfxError::Error(e))));
+                None
+            }
+        };
+    }
+}
+
 fn count_missing_sfx(
     sfx_export_order: &GuiSfxExportOrder,
     sound_effects: &CList<SoundEffectInput, Option<Arc<CompiledSoundEffect>>>,
@@ -965,6 +1053,13 @@ fn build_common_audio_data_with_sfx(
     (cad, sfx_data_size)
 }
 
+fn validate_song_size(song_data: &SongData, common_data_size: usize) -> Result<(), SongError> {
+    compiler::songs::validate_song_size(song_data, common_data_size).map_err(|e| {
+        let overlap = compiler::songs::validate_echo_buffer(song_data, common_data_size).err();
+        SongError::TooLarge(e, overlap)
+    })
+}
+
 struct SongState {
     file: TextFile,
     song_data: Option<Arc<SongData>>,
@@ -1007,12 +1102,11 @@ impl SongCompiler {
         };
 
         let name = name.cloned();
-        let song_data = match compiler::mml::compile_mml(
-            f,
-            name,
-            &dep.inst_map,
-            dep.combined_samples.pitch_table(),
-        ) {
+        let (song_data, metrics) =
+            compile_mml_with_metrics(f, name, &dep.inst_map, dep.combined_samples.pitch_table());
+        sender.send(CompilerOutput::SongMetrics(id, metrics));
+
+        let song_data = match song_data {
             Ok(sd) => Arc::from(sd),
             Err(e) => {
                 sender.send(CompilerOutput::Song(id, Err(SongError::Song(e))));
@@ -1020,12 +1114,12 @@ impl SongCompiler {
             }
         };
 
-        match compiler::songs::validate_song_size(&song_data, dep.common_data_size()) {
+        match validate_song_size(&song_data, dep.common_data_size()) {
             Ok(()) => {
                 sender.send(CompilerOutput::Song(id, Ok(song_data.clone())));
             }
             Err(e) => {
-                sender.send(CompilerOutput::Song(id, Err(SongError::TooLarge(e))));
+                sender.send(CompilerOutput::Song(id, Err(e)));
             }
         }
 
@@ -1135,36 +1229,106 @@ impl SongCompiler {
         self.output_largest_song_size(sender);
     }
 
+    /// Returns the ids of all loaded songs.
+    ///
+    /// Project songs are in list order, followed by songs that are not in the project
+    /// (MML files opened in a song tab).
+    fn song_ids_ordered(&self, pf_songs: &IList<data::Song>) -> Vec<ItemId> {
+        let mut ids: Vec<ItemId> = pf_songs
+            .items_ordered()
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| self.songs.contains_key(id))
+            .collect();
+
+        let mut other: Vec<ItemId> = self
+            .songs
+            .keys()
+            .filter(|id| pf_songs.get(id).is_none())
+            .copied()
+            .collect();
+        other.sort();
+
+        ids.append(&mut other);
+        ids
+    }
+
     fn compile_all_songs(
         &mut self,
         pf_songs: &IList<data::Song>,
         dependencies: &Option<SongDependencies>,
         sender: &Sender,
     ) {
-        for (id, s) in self.songs.iter_mut() {
-            let song_name = pf_songs.get(id).map(|s| &s.name);
+        for id in self.song_ids_ordered(pf_songs) {
+            let song_name = pf_songs.get(&id).map(|s| &s.name);
 
-            s.song_data = Self::compile_song(*id, song_name, &s.file, dependencies, sender);
+            if let Some(s) = self.songs.get_mut(&id) {
+                s.song_data = Self::compile_song(id, song_name, &s.file, dependencies, sender);
+            }
         }
 
         self.output_largest_song_size(sender);
     }
 
-    fn recheck_song_sizes(&mut self, dependencies: &SongDependencies, sender: &Sender) {
+    fn recheck_song_sizes(
+        &mut self,
+        pf_songs: &IList<data::Song>,
+        dependencies: &SongDependencies,
+        sender: &Sender,
+    ) {
         let common_data_size = dependencies.common_data_size();
 
-        for (id, s) in self.songs.iter() {
-            if let Some(song_data) = &s.song_data {
-                match compiler::songs::validate_song_size(song_data, common_data_size) {
+        for id in self.song_ids_ordered(pf_songs) {
+            if let Some(song_data) = self.songs.get(&id).and_then(|s| s.song_data.as_ref()) {
+                match validate_song_size(song_data, common_data_size) {
                     Ok(()) => {}
                     Err(e) => {
-                        sender.send(CompilerOutput::Song(*id, Err(SongError::TooLarge(e))));
+                        sender.send(CompilerOutput::Song(id, Err(e)));
                     }
                 }
             }
         }
     }
 
+    fn set_song_source(
+        &mut self,
+        id: ItemId,
+        source: &SourcePathBuf,
+        pf_songs: &IList<data::Song>,
+        dependencies: &Option<SongDependencies>,
+        sender: &Sender,
+    ) {
+        let path = Some(source.to_path(&self.parent_path));
+        let file_name = source.file_name().to_owned();
+
+        match self.songs.entry(id) {
+            Entry::Occupied(mut o) => {
+                let state = o.get_mut();
+                if state.file.path != path {
+                    state.file.path = path;
+                    state.file.file_name = file_name;
+
+                    let song_name = pf_songs.get(&id).map(|s| &s.name);
+                    state.song_data =
+                        Self::compile_song(id, song_name, &state.file, dependencies, sender);
+
+                    self.output_largest_song_size(sender);
+                }
+            }
+            Entry::Vacant(v) => {
+                // The song is compiled by the next `SongChanged` message
+                v.insert(SongState {
+                    file: TextFile {
+                        contents: String::new(),
+                        file_name,
+                        path,
+                    },
+                    song_data: None,
+                });
+            }
+        }
+    }
+
     fn edit_and_compile_song(
         &mut self,
         id: ItemId,
@@ -1228,22 +1392,27 @@ impl SongCompiler {
             }
         };
 
+        let pf_song = pf_songs.get(&id);
+        let alias = pf_song
+            .and_then(|s| s.alias.as_ref())
+            .map(data::Name::as_str);
+
         let (title, song_data) = match self.songs.get(&id) {
             None => return Err(SpcFileError::NoSong),
             Some(s) => match &s.song_data {
                 None => return Err(SpcFileError::InvalidSong),
                 Some(song_data) => {
-                    let title = song_data.metadata().title.as_deref();
+                    let title = alias.or(song_data.metadata().title.as_deref());
                     (title, song_data)
                 }
             },
         };
 
-        match export_spc_file(&common_audio_data, song_data) {
+        match export_spc_file_with_title(&common_audio_data, song_data, title) {
             Err(e) => Err(SpcFileError::Spc(e)),
             Ok(spc_data) => {
                 let name = title
-                    .or_else(|| pf_songs.get(&id).map(|s| s.name.as_str()))
+                    .or_else(|| pf_song.map(|s| s.name.as_str()))
                     .unwrap_or("Song");
 
                 Ok((name.to_owned(), spc_data))
@@ -1257,20 +1426,17 @@ fn analyse_sample(
     source: SourcePathBuf,
     loop_setting: LoopSetting,
     evaluator: BrrEvaluator,
+    pcm_options: PcmSampleOptions,
 ) -> Result<SampleAnalysis, BrrError> {
-    let brr_sample = Arc::new(encode_or_load_brr_file(
-        &source,
-        cache,
-        &loop_setting,
-        evaluator,
-    )?);
-
-    let wav_sample = match source.extension() {
-        Some(WAV_EXTENSION) => match cache.load_wav_file(&source) {
+    let brr_sample =
+        Arc::new(cache.encode_sample_source(&source, &loop_setting, evaluator, pcm_options)?);
+
+    let wav_sample = match is_pcm_source(&source) {
+        true => match cache.load_wav_file(&source) {
             Ok(w) => Some(w),
             Err(e) => return Err(e.clone()),
         },
-        _ => None,
+        false => None,
     };
 
     Ok(sample_analyser::analyse_sample(brr_sample, wav_sample))
@@ -1486,10 +1652,20 @@ fn bg_thread(
             ToCompiler::SongTabClosed(id) => {
                 songs.song_tab_closed(id, &pf_songs, &song_dependencies, &sender);
             }
+            ToCompiler::SongSourceChanged(id, source) => {
+                songs.set_song_source(id, &source, &pf_songs, &song_dependencies, &sender);
+            }
             ToCompiler::SongChanged(id, mml) => {
                 songs.edit_and_compile_song(id, mml, &pf_songs, &song_dependencies, &sender);
             }
-            ToCompiler::CompileAndPlaySong(id, mml, skip, channels_mask) => {
+            ToCompiler::CompileAndPlaySong(
+                id,
+                mml,
+                skip,
+                channels_mask,
+                interpreter_mask,
+                song_loop,
+            ) => {
                 sender.send_audio(AudioMessage::Pause);
                 songs.edit_and_compile_song(id, mml, &pf_songs, &song_dependencies, &sender);
                 if let Some(song) = songs.get_song_data(&id) {
@@ -1498,6 +1674,8 @@ fn bg_thread(
                         song.clone(),
                         skip,
                         channels_mask,
+                        interpreter_mask,
+                        song_loop,
                     ));
                 }
             }
@@ -1562,9 +1740,14 @@ fn bg_thread(
                 sample_file_cache.remove_path(&source_path);
             }
 
-            ToCompiler::AnalyseSample(source_path, loop_setting, evaluator) => {
-                let r =
-                    analyse_sample(&mut sample_file_cache, source_path, loop_setting, evaluator);
+            ToCompiler::AnalyseSample(source_path, loop_setting, evaluator, pcm_options) => {
+                let r = analyse_sample(
+                    &mut sample_file_cache,
+                    source_path,
+                    loop_setting,
+                    evaluator,
+                    pcm_options,
+                );
                 sender.send(CompilerOutput::SampleAnalysis(r));
             }
         }
@@ -1575,6 +1758,14 @@ fn bg_thread(
         if pending_combine_samples {
             pending_combine_samples = false;
 
+            sender.send(CompilerOutput::InstrumentPitchWarnings(
+                instruments
+                    .items()
+                    .iter()
+                    .flat_map(validate_instrument_pitch_ranges)
+                    .collect(),
+            ));
+
             match build_cad_no_sfx_and_song_dependencies(
                 &instruments,
                 &samples,
@@ -1609,8 +1800,12 @@ fn bg_thread(
             sfx_subroutines =
                 compile_sfx_subroutines(&song_dependencies, &sfx_subroutines_mml, &sender);
 
-            let c = create_sfx_compiler(&song_dependencies, &sfx_subroutines, &sender);
-            sound_effects.recompile_all(c);
+            recompile_all_sfx(
+                &song_dependencies,
+                &sfx_subroutines,
+                &mut sound_effects,
+                &sender,
+            );
 
             match build_common_data_with_sfx_buffer(
                 &song_dependencies,
@@ -1654,7 +1849,7 @@ fn bg_thread(
                     deps.sfx_data_size = sfx_data_size;
 
                     if !pending_compile_all_songs {
-                        songs.recheck_song_sizes(deps, &sender);
+                        songs.recheck_song_sizes(&pf_songs, deps, &sender);
                     }
                 }
             }
@@ -0,0 +1,153 @@
+//! Per-user configuration files
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CONFIG_DIR_NAME: &str = "tad-gui";
+const RECENT_FILES_FILENAME: &str = "recent.json";
+
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// Returns the per-user configuration directory for tad-gui.
+///
+///  * Linux and other Unix: `$XDG_CONFIG_HOME/tad-gui` or `~/.config/tad-gui`
+///  * Windows: `%APPDATA%\tad-gui`
+///  * macOS: `~/Library/Application Support/tad-gui`
+fn config_dir() -> Option<PathBuf> {
+    let dir = if cfg!(target_os = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        match env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(p) if p.is_absolute() => p,
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        }
+    };
+
+    Some(dir.join(CONFIG_DIR_NAME))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Seconds since the UNIX epoch
+    pub timestamp: u64,
+}
+
+/// The most recently opened project files, most recent first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    projects: Vec<RecentFile>,
+}
+
+impl RecentFiles {
+    /// Reads the recent files list.
+    ///
+    /// Returns an empty list if the file does not exist or cannot be read.
+    pub fn load() -> Self {
+        let path = match config_dir() {
+            Some(d) => d.join(RECENT_FILES_FILENAME),
+            None => return Self::default(),
+        };
+
+        match fs::read_to_string(path) {
+            Ok(s) => {
+                let mut rf: Self = serde_json::from_str(&s).unwrap_or_default();
+                rf.projects.truncate(MAX_RECENT_FILES);
+                rf
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let dir = config_dir().ok_or("Cannot find the config directory")?;
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+        let path = dir.join(RECENT_FILES_FILENAME);
+        fs::write(&path, json).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+
+    pub fn projects(&self) -> &[RecentFile] {
+        &self.projects
+    }
+
+    /// Moves `path` to the top of the list
+    pub fn add_project(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        self.projects.retain(|p| p.path != path);
+        self.projects.insert(0, RecentFile { path, timestamp });
+        self.projects.truncate(MAX_RECENT_FILES);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn paths(rf: &RecentFiles) -> Vec<&str> {
+        rf.projects()
+            .iter()
+            .map(|p| p.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_project() {
+        let mut rf = RecentFiles::default();
+
+        rf.add_project(Path::new("/missing/a.terrificaudio"));
+        rf.add_project(Path::new("/missing/b.terrificaudio"));
+        rf.add_project(Path::new("/missing/a.terrificaudio"));
+
+        assert_eq!(
+            paths(&rf),
+            ["/missing/a.terrificaudio", "/missing/b.terrificaudio"]
+        );
+    }
+
+    #[test]
+    fn test_max_recent_files() {
+        let mut rf = RecentFiles::default();
+
+        for i in 0..(MAX_RECENT_FILES + 5) {
+            rf.add_project(Path::new(&format!("/missing/{i}.terrificaudio")));
+        }
+
+        assert_eq!(rf.projects().len(), MAX_RECENT_FILES);
+        assert_eq!(
+            rf.projects()[0].path,
+            Path::new(&format!("/missing/{}.terrificaudio", MAX_RECENT_FILES + 4))
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut rf = RecentFiles::default();
+        rf.add_project(Path::new("/missing/a.terrificaudio"));
+
+        let json = serde_json::to_string(&rf).unwrap();
+        let rf2: RecentFiles = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(paths(&rf2), ["/missing/a.terrificaudio"]);
+        assert_eq!(rf2.projects()[0].timestamp, rf.projects()[0].timestamp);
+    }
+}
@@ -6,18 +6,21 @@
 
 use crate::helpers::*;
 
-use compiler::envelope::{Adsr, Envelope, Gain, GainMode};
+use compiler::envelope::{Adsr, Envelope, EnvelopeSimulator, Gain, GainMode, DSP_SAMPLE_RATE};
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use compiler::errors::ValueError;
 use fltk::button::RadioRoundButton;
-use fltk::enums::Align;
+use fltk::draw;
+use fltk::enums::{Align, Color};
+use fltk::frame::Frame;
 use fltk::group::Group;
 use fltk::menu::Choice;
 use fltk::misc::Spinner;
 use fltk::prelude::*;
+use fltk::widget::Widget;
 
 // Only using the gain modes that output audio on key-on.
 const GAIN_MODE_CHOICES: &str = concat!["Fixed", "|Linear Increase", "|Bent Increase"];
@@ -41,6 +44,7 @@ struct EnvelopeWidgetState {
     adsr_d: Spinner,
     adsr_sl: Spinner,
     adsr_sr: Spinner,
+    adsr_decay_estimate: Frame,
 
     gain_mode: Choice,
     gain: Spinner,
@@ -107,6 +111,13 @@ impl EnvelopeWidget {
         let adsr_sl = spinner(2, 4, 2, "SL", "Sustain Level", 0, Adsr::SUSTAIN_LEVEL_MAX, 2);
         let adsr_sr = spinner(2, 4, 3, "SR", "Sustain Rate", 0, Adsr::SUSTAIN_RATE_MAX, 16);
 
+        let adsr_decay_estimate = {
+            let (x, y, w, h) = pos(3, 1, 0, 1);
+            let mut f = Frame::new(x, y, w, h, None);
+            f.set_align(Align::Left | Align::Inside);
+            f
+        };
+
         let gain_mode = choice(2, 4, 0, 3, "GAIN Mode");
         let gain = spinner(2, 4, 3, "GAIN", "", 0, 127, 127);
 
@@ -121,6 +132,7 @@ impl EnvelopeWidget {
             adsr_d,
             adsr_sl,
             adsr_sr,
+            adsr_decay_estimate,
 
             gain_mode,
             gain,
@@ -143,6 +155,15 @@ impl EnvelopeWidget {
             set_envelope_callback(&mut s.adsr_envelope);
             set_envelope_callback(&mut s.gain_envelope);
 
+            s.adsr_sr.set_callback({
+                let state = state.clone();
+                move |_| {
+                    if let Ok(mut s) = state.try_borrow_mut() {
+                        s.update_adsr_decay_estimate()
+                    }
+                }
+            });
+
             s.gain_mode.add_choice(GAIN_MODE_CHOICES);
             s.gain_mode.set_value(GAIN_MODE_DEFAULT_VALUE);
 
@@ -173,6 +194,7 @@ impl EnvelopeWidgetState {
         self.adsr_d.hide();
         self.adsr_sl.hide();
         self.adsr_sr.hide();
+        self.adsr_decay_estimate.hide();
     }
 
     fn hide_gain(&mut self) {
@@ -186,6 +208,8 @@ impl EnvelopeWidgetState {
             self.adsr_d.show();
             self.adsr_sl.show();
             self.adsr_sr.show();
+            self.adsr_decay_estimate.show();
+            self.update_adsr_decay_estimate();
 
             self.hide_gain();
         } else if self.gain_envelope.is_toggled() {
@@ -200,6 +224,26 @@ impl EnvelopeWidgetState {
         }
     }
 
+    fn update_adsr_decay_estimate(&mut self) {
+        let label = match Adsr::try_new(
+            self.adsr_a.value() as u8,
+            self.adsr_d.value() as u8,
+            self.adsr_sl.value() as u8,
+            self.adsr_sr.value() as u8,
+        ) {
+            Ok(adsr) => {
+                let seconds = adsr.estimated_sustain_decay_seconds(DSP_SAMPLE_RATE.into());
+                if seconds.is_finite() {
+                    format!("Decay to silence: ~{:.1}s", seconds)
+                } else {
+                    "Decay to silence: never".to_owned()
+                }
+            }
+            Err(_) => String::new(),
+        };
+        self.adsr_decay_estimate.set_label(&label);
+    }
+
     fn on_gain_mode_changed(&mut self) {
         let max = read_gain_mode_choice(&self.gain_mode).max_value();
         let old_max = self.gain.maximum() as u32;
@@ -233,3 +277,98 @@ impl EnvelopeWidgetState {
         }
     }
 }
+
+const CANVAS_WIDTH: i32 = 300;
+const CANVAS_HEIGHT: i32 = 60;
+
+const CANVAS_SAMPLES: u32 = DSP_SAMPLE_RATE * 2;
+const CANVAS_KEY_OFF_SAMPLE: u32 = CANVAS_SAMPLES * 3 / 4;
+
+const ENVELOPE_COLOR: Color = Color::DarkGreen;
+const KEY_OFF_COLOR: Color = Color::Inactive;
+
+/// Draws the shape of an ADSR or GAIN envelope.
+///
+/// Shows the first 2 seconds of the envelope, with a key-off at 1.5 seconds.
+#[derive(Clone)]
+pub struct AdsrGainCanvas {
+    widget: Widget,
+    envelope: Rc<RefCell<Option<Envelope>>>,
+}
+
+impl AdsrGainCanvas {
+    pub fn new(form: &mut InputForm) -> Self {
+        let mut widget = form.add_widget_right::<Widget>(CANVAS_WIDTH, CANVAS_HEIGHT);
+
+        let envelope = Rc::new(RefCell::new(None));
+
+        widget.draw({
+            let envelope = envelope.clone();
+            move |w| {
+                if let Ok(e) = envelope.try_borrow() {
+                    Self::draw(w, *e);
+                }
+            }
+        });
+
+        Self { widget, envelope }
+    }
+
+    pub fn set_envelope(&mut self, envelope: Option<Envelope>) {
+        if let Ok(mut e) = self.envelope.try_borrow_mut() {
+            if *e != envelope {
+                *e = envelope;
+                self.widget.redraw();
+            }
+        }
+    }
+
+    fn draw(w: &Widget, envelope: Option<Envelope>) {
+        draw::draw_rect_fill(w.x(), w.y(), w.w(), w.h(), Color::Background2);
+        draw::set_draw_color(Color::Foreground);
+        draw::draw_rect(w.x(), w.y(), w.w(), w.h());
+
+        let x = w.x() + 1;
+        let y = w.y() + 1;
+        let width = w.w() - 2;
+        let height = w.h() - 3;
+
+        let envelope = match envelope {
+            Some(e) => e,
+            None => return,
+        };
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let key_off_x = x
+            + (i64::from(CANVAS_KEY_OFF_SAMPLE) * i64::from(width) / i64::from(CANVAS_SAMPLES))
+                as i32;
+        draw::set_draw_color(KEY_OFF_COLOR);
+        draw::draw_line(key_off_x, y, key_off_x, y + height);
+
+        let to_y =
+            |v: u16| y + height - i32::from(v) * height / i32::from(EnvelopeSimulator::MAX_VALUE);
+
+        let mut sim = EnvelopeSimulator::new(envelope);
+        let mut sample = 0;
+        let mut prev = (x, to_y(0));
+
+        draw::set_draw_color(ENVELOPE_COLOR);
+
+        for px in 0..width {
+            let end = (i64::from(px + 1) * i64::from(CANVAS_SAMPLES) / i64::from(width)) as u32;
+            while sample < end {
+                if sample == CANVAS_KEY_OFF_SAMPLE {
+                    sim.key_off();
+                }
+                sim.process_sample();
+                sample += 1;
+            }
+
+            let p = (x + px, to_y(sim.value()));
+            draw::draw_line(prev.0, prev.1, p.0, p.1);
+            prev = p;
+        }
+    }
+}
@@ -6,6 +6,7 @@
 
 use crate::compiler_thread::{ItemId, ToCompiler};
 use crate::list_editor::ListMessage;
+use crate::project_templates::ProjectTemplate;
 use crate::song_tab::SongTab;
 use crate::tabs::{FileType, TabManager};
 use crate::{GuiMessage, ProjectData, SoundEffectsData};
@@ -13,6 +14,7 @@ use crate::{GuiMessage, ProjectData, SoundEffectsData};
 use compiler::data;
 use compiler::data::{load_text_file_with_limit, Name, ProjectFile, Song, TextFile, MAX_FILE_SIZE};
 use compiler::path::{ParentPathBuf, SourcePathBuf, SourcePathResult};
+use compiler::samples::detect_sample_frequency;
 use compiler::sfx_file::{build_sound_effects_file, load_sound_effects_file, SoundEffectsFile};
 
 extern crate fltk;
@@ -27,11 +29,19 @@ const PROJECT_FILTER: &str = "Project Files\t*.terrificaudio";
 const SOUND_EFFECTS_FILTER: &str = "TXT Files\t*.txt";
 const MML_SONG_FILTER: &str = "MML Files\t*.mml";
 const SPC_FILTER: &str = "SPC Files\t*.spc";
+#[cfg(not(feature = "flac"))]
 const SAMPLE_FILTERS: &str = concat![
     "WAV and BRR samples\t*.{wav,brr}\n",
     "WAV samples\t*.wav\n",
     "BRR samples\t*.brr",
 ];
+#[cfg(feature = "flac")]
+const SAMPLE_FILTERS: &str = concat![
+    "WAV, FLAC and BRR samples\t*.{wav,flac,brr}\n",
+    "WAV samples\t*.wav\n",
+    "FLAC samples\t*.flac\n",
+    "BRR samples\t*.brr",
+];
 
 fn try_show_native_dialog(mut dialog: dialog::NativeFileChooser) -> Option<PathBuf> {
     match dialog.try_show() {
@@ -386,7 +396,28 @@ pub fn open_project_dialog() -> Option<ProjectFile> {
 }
 
 pub fn new_project_dialog() -> Option<ProjectFile> {
-    let path = match save_file_dialog("New Project", PROJECT_FILTER, data::PROJECT_FILE_EXTENSION) {
+    new_project_file_dialog("New Project", data::Project::default(), None)
+}
+
+pub fn new_project_from_template_dialog(template: &ProjectTemplate) -> Option<ProjectFile> {
+    match template.project() {
+        Ok(project) => {
+            new_project_file_dialog("New Project from Template", project, Some(template))
+        }
+        Err(e) => {
+            dialog::message_title("Cannot load project template");
+            dialog::alert_default(&e);
+            None
+        }
+    }
+}
+
+fn new_project_file_dialog(
+    title: &str,
+    project: data::Project,
+    template: Option<&ProjectTemplate>,
+) -> Option<ProjectFile> {
+    let path = match save_file_dialog(title, PROJECT_FILTER, data::PROJECT_FILE_EXTENSION) {
         Some(p) => p,
         None => return None,
     };
@@ -397,7 +428,6 @@ pub fn new_project_dialog() -> Option<ProjectFile> {
         return load_project_file_or_show_error_message(&path);
     }
 
-    let project = data::Project::default();
     let contents = match data::serialize_project(&project) {
         Ok(c) => c,
         Err(e) => {
@@ -407,14 +437,20 @@ pub fn new_project_dialog() -> Option<ProjectFile> {
         }
     };
 
-    match write_to_new_file(&path, &contents.to_vec()) {
-        Ok(()) => load_project_file_or_show_error_message(&path),
-        Err(e) => {
-            dialog::message_title("Cannot save new project");
+    if let Err(e) = write_to_new_file(&path, &contents.to_vec()) {
+        dialog::message_title("Cannot save new project");
+        dialog::alert_default(&e.to_string());
+        return None;
+    }
+
+    if let (Some(t), Some(dir)) = (template, path.parent()) {
+        if let Err(e) = t.write_samples(dir) {
+            dialog::message_title("Cannot save template samples");
             dialog::alert_default(&e.to_string());
-            None
         }
     }
+
+    load_project_file_or_show_error_message(&path)
 }
 
 pub fn load_project_file_or_show_error_message(path: &Path) -> Option<ProjectFile> {
@@ -520,12 +556,14 @@ pub fn add_song_to_pf_dialog(
                         data::Song {
                             name: song_name_from_path(&p.source_path),
                             source: p.source_path,
+                            alias: None,
                         },
                     )));
                 }
                 _ => sender.send(GuiMessage::EditProjectSongs(ListMessage::Add(Song {
                     name: song_name_from_path(&p.source_path),
                     source: p.source_path,
+                    alias: None,
                 }))),
             },
         }
@@ -573,8 +611,32 @@ pub fn open_instrument_sample_dialog(
     };
 
     if let Some(new_source) = open_sample_dialog(compiler_sender, pd, &inst.source) {
+        let freq = match detect_sample_frequency(&new_source, &pd.pf_parent_path) {
+            Some(f) if f != inst.freq => {
+                dialog::message_title("Instrument frequency");
+                let choice = dialog::choice2_default(
+                    &format!(
+                        concat![
+                            "The sample's smpl chunk has a frequency of {} Hz.\n",
+                            "Replace the instrument's frequency ({} Hz)?",
+                        ],
+                        f, inst.freq
+                    ),
+                    "No", // default
+                    "Yes",
+                    "",
+                );
+                match choice {
+                    Some(1) => f,
+                    _ => inst.freq,
+                }
+            }
+            _ => inst.freq,
+        };
+
         let new_inst = data::Instrument {
             source: new_source,
+            freq,
             ..inst.clone()
         };
         sender.send(GuiMessage::EditInstrument(id, new_inst));
@@ -249,6 +249,9 @@ pub struct InputForm {
     row_height: i32,
     left_column_width: i32,
     n_rows: i32,
+
+    // Height of the rows that are taller than `row_height`
+    extra_height: i32,
 }
 
 impl InputForm {
@@ -263,6 +266,7 @@ impl InputForm {
             left_column_width,
             row_height,
             n_rows: 0,
+            extra_height: 0,
         }
     }
 
@@ -272,7 +276,9 @@ impl InputForm {
 
     /// returns (Flex, form height)
     pub fn end(self) -> (Flex, i32) {
-        let form_height = self.row_height * self.n_rows + self.group.pad() * (self.n_rows - 1);
+        let form_height = self.row_height * self.n_rows
+            + self.extra_height
+            + self.group.pad() * (self.n_rows - 1);
 
         self.group.end();
 
@@ -386,6 +392,32 @@ impl InputForm {
         (w1, w2, w3)
     }
 
+    /// Adds a fixed size widget to the right column
+    pub fn add_widget_right<T>(&mut self, width: i32, height: i32) -> T
+    where
+        T: WidgetExt + Default,
+    {
+        let height = height.max(self.row_height);
+
+        let mut r = Flex::default().row();
+        self.group.fixed(&r, height);
+
+        let f = Frame::default();
+        r.fixed(&f, self.left_column_width);
+
+        let w = T::default();
+        r.fixed(&w, width);
+
+        Frame::default();
+
+        r.end();
+
+        self.n_rows += 1;
+        self.extra_height += height - self.row_height;
+
+        w
+    }
+
     pub fn add_two_inputs_right<T, U>(&mut self, text: &str, ch_units: i32) -> (T, U)
     where
         T: WidgetExt + Default,
@@ -4,32 +4,43 @@
 //
 // SPDX-License-Identifier: MIT
 
-use crate::compiler_thread::{InstrumentOutput, ItemId, PlaySampleArgs};
+use crate::compiler_thread::{InstrumentOutput, ItemId, PlaySampleArgs, SongError, SongOutput};
 use crate::envelope_widget::EnvelopeWidget;
 use crate::helpers::*;
 use crate::list_editor::{ListMessage, TableCompilerOutput, TableMapping};
+use crate::presets::{adsr_preset_index, ADSR_PRESETS};
 use crate::sample_widgets::{
     BrrSettingsWidget, SampleEnvelopeWidget, SampleWidgetEditor, SourceFileType, DEFAULT_ENVELOPE,
 };
-use crate::tables::{RowWithStatus, SimpleRow};
+use crate::tables::{draw_truncated_text, RowWithStatus, TableRow};
 use crate::GuiMessage;
 
 use compiler::data::{self, Instrument, LoopSetting};
-use compiler::errors::ValueError;
-use compiler::notes::{Note, Octave, PitchChar, STARTING_OCTAVE};
+use compiler::envelope::Envelope;
+use compiler::errors::{self, BytecodeError, ChannelError, ValueError};
+use compiler::notes::{
+    Note, NoteRange, Octave, PitchChar, N_NOTES, SEMITONES_PER_OCTAVE, STARTING_OCTAVE,
+};
 use compiler::path::SourcePathBuf;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
 use fltk::app;
 use fltk::button::{Button, CheckButton};
+use fltk::draw;
 use fltk::enums::{Align, Color, Event};
 use fltk::group::{Flex, Group};
 use fltk::input::{FloatInput, Input, IntInput};
+use fltk::menu::Choice;
 use fltk::misc::Spinner;
 use fltk::output::Output;
 use fltk::prelude::*;
+use fltk::widget::Widget;
+
+const CUSTOM_ADSR_PRESET_LABEL: &str = "Custom";
 
 fn blank_instrument() -> Instrument {
     Instrument {
@@ -39,18 +50,55 @@ fn blank_instrument() -> Instrument {
         loop_setting: LoopSetting::None,
         evaluator: Default::default(),
         ignore_gaussian_overflow: false,
-        first_octave: STARTING_OCTAVE,
-        last_octave: STARTING_OCTAVE,
+        auto_detect_loop: false,
+        dc_offset_removal: false,
+        normalize: false,
+        note_range: NoteRange {
+            first: STARTING_OCTAVE,
+            last: STARTING_OCTAVE,
+        },
         envelope: DEFAULT_ENVELOPE,
         comment: None,
     }
 }
 
+// Shown in the size column of instruments that have not been compiled yet
+const NOT_COMPILED_SIZE: &str = "...";
+
+pub struct InstrumentRow {
+    name: String,
+    comment: String,
+    size: String,
+}
+
+impl TableRow for InstrumentRow {
+    const N_COLUMNS: i32 = 3;
+    const COLUMN_WIDTHS: &'static [i32] = &[5, 3, 3];
+
+    fn draw_cell(&self, col: i32, x: i32, y: i32, w: i32, h: i32) {
+        match col {
+            0 => draw::draw_text2(&self.name, x, y, w, h, Align::Left),
+            1 => draw_truncated_text(&self.comment, x, y, w, h, Align::Left),
+            2 => draw::draw_text2(&self.size, x, y, w, h, Align::Left),
+            _ => (),
+        }
+    }
+
+    fn value(&self, col: i32) -> Option<&str> {
+        match col {
+            0 => Some(&self.name),
+            1 => Some(&self.comment),
+            2 => Some(&self.size),
+            _ => None,
+        }
+    }
+}
+
 pub struct InstrumentMapping;
 
 impl TableMapping for InstrumentMapping {
     type DataType = data::Instrument;
-    type RowType = RowWithStatus<SimpleRow<1>>;
+    type RowType = RowWithStatus<InstrumentRow>;
 
     const CAN_CLONE: bool = true;
     const CAN_EDIT: bool = false;
@@ -60,7 +108,11 @@ impl TableMapping for InstrumentMapping {
     }
 
     fn headers() -> Vec<String> {
-        vec!["Instruments".to_owned()]
+        vec![
+            "Instruments".to_owned(),
+            "Comment".to_owned(),
+            "Size".to_owned(),
+        ]
     }
 
     fn add_clicked() -> GuiMessage {
@@ -72,11 +124,29 @@ impl TableMapping for InstrumentMapping {
     }
 
     fn new_row(i: &Instrument) -> Self::RowType {
-        RowWithStatus::new_unchecked(SimpleRow::new([i.name.as_str().to_string()]))
+        RowWithStatus::new_unchecked(InstrumentRow {
+            name: i.name.as_str().to_owned(),
+            comment: i.comment.as_deref().unwrap_or("").to_owned(),
+            size: NOT_COMPILED_SIZE.to_owned(),
+        })
     }
 
     fn edit_row(r: &mut Self::RowType, i: &Instrument) -> bool {
-        r.columns.edit_column(0, i.name.as_str())
+        let mut edited = false;
+
+        let write_if_changed = |dest: &mut String, src: &str| {
+            if dest != src {
+                src.clone_into(dest);
+                true
+            } else {
+                false
+            }
+        };
+
+        edited |= write_if_changed(&mut r.columns.name, i.name.as_str());
+        edited |= write_if_changed(&mut r.columns.comment, i.comment.as_deref().unwrap_or(""));
+
+        edited
     }
 
     fn user_changes_selection() -> Option<GuiMessage> {
@@ -88,7 +158,21 @@ impl TableCompilerOutput for InstrumentMapping {
     type CompilerOutputType = InstrumentOutput;
 
     fn set_row_state(r: &mut Self::RowType, co: &Option<InstrumentOutput>) -> bool {
-        r.set_status_optional_result(co)
+        let size = match co {
+            Some(Ok(s)) => format!("{} bytes", s.data_size),
+            Some(Err(_)) => "ERR".to_owned(),
+            None => NOT_COMPILED_SIZE.to_owned(),
+        };
+
+        let size_changed = if r.columns.size != size {
+            r.columns.size = size;
+            true
+        } else {
+            false
+        };
+        let status_changed = r.set_status_optional_result(co);
+
+        size_changed || status_changed
     }
 }
 
@@ -105,10 +189,18 @@ pub struct InstrumentEditor {
     freq: FloatInput,
     brr_settings: BrrSettingsWidget,
     ignore_gaussian_overflow: CheckButton,
+    auto_detect_loop: CheckButton,
+    dc_offset_removal: CheckButton,
+    normalize: CheckButton,
     first_octave: IntInput,
     last_octave: IntInput,
+    note_range: NoteRangeWidget,
+    adsr_preset: Choice,
     envelope: SampleEnvelopeWidget,
     comment: Input,
+
+    // Note ranges of the out of range note errors in each song
+    song_note_range_errors: HashMap<ItemId, Vec<RangeInclusive<Note>>>,
 }
 
 impl InstrumentEditor {
@@ -120,9 +212,15 @@ impl InstrumentEditor {
         let freq = form.add_two_inputs_right::<FloatInput, Button>("Frequency:", 5);
         let brr_settings = BrrSettingsWidget::new(&mut form);
         let ignore_gaussian_overflow = form.add_checkbox_right("Ignore Gaussian overflow");
+        let auto_detect_loop = form.add_checkbox_right("Use wav file loop point");
+        let dc_offset_removal = form.add_checkbox_right("Remove DC offset");
+        let normalize = form.add_checkbox_right("Normalize");
         let first_octave = form.add_input::<IntInput>("First octave:");
         let last_octave = form.add_input::<IntInput>("Last octave:");
-        let envelope = SampleEnvelopeWidget::new(&mut form);
+        let note_range = NoteRangeWidget::new(&mut form, sender.clone());
+        let adsr_preset = form.add_input::<Choice>("ADSR preset:");
+        let mut envelope = SampleEnvelopeWidget::new(&mut form);
+        envelope.add_canvas(&mut form);
         let comment = form.add_input::<Input>("Comment:");
 
         let (group, form_height) = form.end();
@@ -140,10 +238,16 @@ impl InstrumentEditor {
             freq,
             brr_settings,
             ignore_gaussian_overflow,
+            auto_detect_loop,
+            dc_offset_removal,
+            normalize,
             first_octave,
             last_octave,
+            note_range,
+            adsr_preset,
             envelope,
             comment,
+            song_note_range_errors: HashMap::new(),
         }));
 
         {
@@ -170,11 +274,36 @@ impl InstrumentEditor {
             editor.brr_settings.set_editor(out.clone());
             editor.envelope.set_editor(out.clone());
 
+            for (name, _) in ADSR_PRESETS {
+                editor.adsr_preset.add_choice(name);
+            }
+            editor.adsr_preset.add_choice(CUSTOM_ADSR_PRESET_LABEL);
+            editor.adsr_preset.set_callback({
+                let s = out.clone();
+                move |_widget| s.borrow_mut().adsr_preset_selected()
+            });
+
             editor.ignore_gaussian_overflow.set_callback({
                 let s = out.clone();
                 move |_widget| s.borrow_mut().on_finished_editing()
             });
 
+            editor
+                .auto_detect_loop
+                .set_tooltip("Use the loop point in the wav file if the loop setting is none");
+            editor.auto_detect_loop.set_callback({
+                let s = out.clone();
+                move |_widget| s.borrow_mut().on_finished_editing()
+            });
+            editor.dc_offset_removal.set_callback({
+                let s = out.clone();
+                move |_widget| s.borrow_mut().on_finished_editing()
+            });
+            editor.normalize.set_callback({
+                let s = out.clone();
+                move |_widget| s.borrow_mut().on_finished_editing()
+            });
+
             source_button.set_label("...");
             source_button.set_callback({
                 let s = out.clone();
@@ -221,6 +350,27 @@ impl InstrumentEditor {
         }
     }
 
+    fn adsr_preset_selected(&mut self) {
+        let preset = usize::try_from(self.adsr_preset.value())
+            .ok()
+            .and_then(|i| ADSR_PRESETS.get(i).copied());
+
+        if let Some((_, adsr)) = preset {
+            self.envelope.set_value(&Envelope::Adsr(adsr));
+            self.on_finished_editing();
+        }
+    }
+
+    fn update_adsr_preset_choice(&mut self, envelope: &Envelope) {
+        let index = match envelope {
+            Envelope::Adsr(adsr) => adsr_preset_index(adsr),
+            Envelope::Gain(_) => None,
+        };
+        let index = index.unwrap_or(ADSR_PRESETS.len());
+
+        self.adsr_preset.set_value(index.try_into().unwrap_or(-1));
+    }
+
     fn send_edit_message(&self, data: Instrument) {
         if let Some(id) = self.selected_id {
             self.sender.send(GuiMessage::EditInstrument(id, data));
@@ -242,22 +392,38 @@ impl InstrumentEditor {
         }
         read_or_reset!(name);
         read_or_reset!(freq);
-        read_or_reset!(first_octave);
-        read_or_reset!(last_octave);
         read_or_reset!(comment);
+        let first_octave =
+            InputHelper::read_or_reset(&mut self.first_octave, &old.note_range.first);
+        let last_octave = InputHelper::read_or_reset(&mut self.last_octave, &old.note_range.last);
 
         let (loop_setting, evaluator) = self.brr_settings.read_or_reset(&self.data.loop_setting);
         let ignore_gaussian_overflow = self.ignore_gaussian_overflow.value();
+        let auto_detect_loop = self.auto_detect_loop.value();
+        let dc_offset_removal = self.dc_offset_removal.value();
+        let normalize = self.normalize.value();
         let envelope = self.envelope.read_or_reset();
 
+        if let Some(envelope) = &envelope {
+            self.update_adsr_preset_choice(envelope);
+        }
+        if let (Some(first), Some(last)) = (first_octave, last_octave) {
+            self.update_note_range(NoteRange { first, last });
+        }
+
         Some(Instrument {
             name: name?,
             freq: freq?,
             loop_setting: loop_setting?,
             evaluator,
             ignore_gaussian_overflow,
-            first_octave: first_octave?,
-            last_octave: last_octave?,
+            auto_detect_loop,
+            dc_offset_removal,
+            normalize,
+            note_range: NoteRange {
+                first: first_octave?,
+                last: last_octave?,
+            },
             envelope: envelope?,
             comment: comment?,
 
@@ -273,14 +439,55 @@ impl InstrumentEditor {
         self.source.set_value("");
         self.freq.set_value("");
         self.ignore_gaussian_overflow.clear();
+        self.auto_detect_loop.clear();
+        self.dc_offset_removal.clear();
+        self.normalize.clear();
         self.first_octave.set_value("");
         self.last_octave.set_value("");
         self.brr_settings.clear_value();
+        self.adsr_preset.set_value(-1);
         self.envelope.clear_value();
+        self.note_range.clear_value();
 
         self.selected_id = None;
     }
 
+    fn update_note_range(&mut self, note_range: NoteRange) {
+        let range = note_range.to_range();
+
+        let out_of_range = self
+            .song_note_range_errors
+            .values()
+            .flatten()
+            .any(|r| *r == range);
+
+        self.note_range
+            .set_value(self.selected_id, range, out_of_range);
+    }
+
+    /// Used to show an out of range warning if a song plays a note outside the instrument's range.
+    ///
+    /// NOTE: The compiler errors do not contain the instrument, any out-of-range error with the
+    /// same note range as the selected instrument will show a warning.
+    pub fn song_compiled(&mut self, song_id: ItemId, song: &SongOutput) {
+        let ranges = match song {
+            Err(SongError::Song(e)) => out_of_range_note_ranges(e),
+            Ok(_) | Err(SongError::Dependency) | Err(SongError::TooLarge(..)) => Vec::new(),
+        };
+
+        if ranges.is_empty() {
+            if self.song_note_range_errors.remove(&song_id).is_none() {
+                return;
+            }
+        } else {
+            self.song_note_range_errors.insert(song_id, ranges);
+        }
+
+        if self.selected_id.is_some() {
+            self.update_note_range(self.data.note_range);
+        }
+    }
+
     fn set_data_update_widget(&mut self, data: &Instrument) {
         macro_rules! set_widget {
             ($name:ident) => {
@@ -290,21 +497,26 @@ impl InstrumentEditor {
 
         set_widget!(name);
         set_widget!(freq);
-        set_widget!(first_octave);
-        set_widget!(last_octave);
         set_widget!(comment);
+        InputHelper::set_widget_value(&mut self.first_octave, &data.note_range.first);
+        InputHelper::set_widget_value(&mut self.last_octave, &data.note_range.last);
         self.source.set_value(data.source.as_str());
         self.brr_settings
             .set_value(&data.loop_setting, data.evaluator);
         self.ignore_gaussian_overflow
             .set_value(data.ignore_gaussian_overflow);
+        self.auto_detect_loop.set_value(data.auto_detect_loop);
+        self.dc_offset_removal.set_value(data.dc_offset_removal);
+        self.normalize.set_value(data.normalize);
         self.envelope.set_value(&data.envelope);
+        self.update_adsr_preset_choice(&data.envelope);
+
+        self.data = data.clone();
+        self.update_note_range(data.note_range);
 
         self.brr_settings
             .update_loop_type_choice(SourceFileType::from_source(&data.source));
 
-        self.data = data.clone();
-
         self.group.activate();
     }
 
@@ -337,6 +549,238 @@ impl SampleWidgetEditor for InstrumentEditor {
     }
 }
 
+fn out_of_range_note_ranges(e: &errors::SongError) -> Vec<RangeInclusive<Note>> {
+    let mut out = Vec::new();
+
+    if let errors::SongError::MmlError(e) = e {
+        let errors = e
+            .subroutine_errors
+            .iter()
+            .chain(&e.channel_errors)
+            .flat_map(|c| &c.errors);
+
+        for e in errors {
+            if let ChannelError::BytecodeError(BytecodeError::NoteOutOfRange(_, r)) =
+                e.1.error_without_macro()
+            {
+                if !out.contains(r) {
+                    out.push(r.clone());
+                }
+            }
+        }
+    }
+
+    out
+}
+
+const NOTE_RANGE_WIDGET_WIDTH: i32 = 300;
+const NOTE_RANGE_WIDGET_HEIGHT: i32 = 60;
+const NOTE_RANGE_NOTE_LENGTH: u32 = 96;
+
+const N_WHITE_KEYS: i32 = 7 * (N_NOTES / SEMITONES_PER_OCTAVE) as i32;
+
+// Semitones of the white keys in an octave
+const WHITE_KEYS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+// (semitone, index of the white key to the right of the black key)
+const BLACK_KEYS: [(u8, i32); 5] = [(1, 1), (3, 2), (6, 4), (8, 5), (10, 6)];
+
+const IN_RANGE_WHITE_KEY_COLOR: Color = Color::Green;
+const IN_RANGE_BLACK_KEY_COLOR: Color = Color::DarkGreen;
+const OUT_OF_RANGE_WARNING_COLOR: Color = Color::Red;
+
+struct NoteRangeWidgetState {
+    sender: app::Sender<GuiMessage>,
+
+    selected_id: Option<ItemId>,
+    range: Option<RangeInclusive<Note>>,
+    out_of_range_warning: bool,
+}
+
+/// A piano keyboard (covering every note in the audio driver) with the instrument's note range
+/// highlighted.
+///
+/// Clicking on a key inside the instrument's range will play the note.
+pub struct NoteRangeWidget {
+    widget: Widget,
+    state: Rc<RefCell<NoteRangeWidgetState>>,
+}
+
+impl NoteRangeWidget {
+    fn new(form: &mut InputForm, sender: app::Sender<GuiMessage>) -> Self {
+        let mut widget =
+            form.add_widget_right::<Widget>(NOTE_RANGE_WIDGET_WIDTH, NOTE_RANGE_WIDGET_HEIGHT);
+
+        let state = Rc::new(RefCell::new(NoteRangeWidgetState {
+            sender,
+            selected_id: None,
+            range: None,
+            out_of_range_warning: false,
+        }));
+
+        widget.draw({
+            let state = state.clone();
+            move |w| {
+                if let Ok(s) = state.try_borrow() {
+                    s.draw(w);
+                }
+            }
+        });
+
+        widget.handle({
+            let state = state.clone();
+            move |w, ev| match ev {
+                Event::Push => {
+                    if let Ok(s) = state.try_borrow() {
+                        s.on_click(w, app::event_x(), app::event_y());
+                    }
+                    true
+                }
+                _ => false,
+            }
+        });
+
+        Self { widget, state }
+    }
+
+    fn set_value(
+        &mut self,
+        selected_id: Option<ItemId>,
+        range: RangeInclusive<Note>,
+        out_of_range_warning: bool,
+    ) {
+        {
+            let mut s = self.state.borrow_mut();
+            s.selected_id = selected_id;
+            s.range = Some(range);
+            s.out_of_range_warning = out_of_range_warning;
+        }
+        self.widget.redraw();
+    }
+
+    fn clear_value(&mut self) {
+        {
+            let mut s = self.state.borrow_mut();
+            s.selected_id = None;
+            s.range = None;
+            s.out_of_range_warning = false;
+        }
+        self.widget.redraw();
+    }
+}
+
+impl NoteRangeWidgetState {
+    fn white_key_x(w: &Widget, index: i32) -> i32 {
+        w.x() + 1 + index * (w.w() - 2) / N_WHITE_KEYS
+    }
+
+    fn black_key_width(w: &Widget) -> i32 {
+        ((w.w() - 2) / N_WHITE_KEYS * 2 / 3).max(1)
+    }
+
+    fn black_key_height(w: &Widget) -> i32 {
+        (w.h() - 2) * 3 / 5
+    }
+
+    fn in_range(&self, note_id: u8) -> bool {
+        match &self.range {
+            Some(r) => r.start().note_id() <= note_id && note_id <= r.end().note_id(),
+            None => false,
+        }
+    }
+
+    fn draw(&self, w: &Widget) {
+        let bg_color = match self.out_of_range_warning {
+            true => OUT_OF_RANGE_WARNING_COLOR,
+            false => Color::Background,
+        };
+        draw::draw_rect_fill(w.x(), w.y(), w.w(), w.h(), bg_color);
+
+        let y = w.y() + 1;
+        let h = w.h() - 2;
+
+        let black_w = Self::black_key_width(w);
+        let black_h = Self::black_key_height(w);
+
+        for octave in 0..N_NOTES / SEMITONES_PER_OCTAVE {
+            let first_note = octave * SEMITONES_PER_OCTAVE;
+            let first_key = i32::from(octave) * 7;
+
+            for (i, semitone) in (0..).zip(WHITE_KEYS) {
+                let x1 = Self::white_key_x(w, first_key + i);
+                let x2 = Self::white_key_x(w, first_key + i + 1);
+
+                let c = match self.in_range(first_note + semitone) {
+                    true => IN_RANGE_WHITE_KEY_COLOR,
+                    false => Color::White,
+                };
+                draw::draw_rect_fill(x1, y, x2 - x1, h, c);
+
+                draw::set_draw_color(Color::Black);
+                draw::draw_rect(x1, y, x2 - x1 + 1, h);
+            }
+
+            for (semitone, right_key) in BLACK_KEYS {
+                let x = Self::white_key_x(w, first_key + right_key) - black_w / 2;
+
+                let c = match self.in_range(first_note + semitone) {
+                    true => IN_RANGE_BLACK_KEY_COLOR,
+                    false => Color::Black,
+                };
+                draw::draw_rect_fill(x, y, black_w, black_h, c);
+            }
+        }
+    }
+
+    fn note_at(w: &Widget, x: i32, y: i32) -> Option<u8> {
+        if x < w.x() || y < w.y() || x >= w.x() + w.w() || y >= w.y() + w.h() {
+            return None;
+        }
+
+        let white_key = ((x - w.x() - 1) * N_WHITE_KEYS / (w.w() - 2)).clamp(0, N_WHITE_KEYS - 1);
+        let octave = white_key / 7;
+        let first_note = u8::try_from(octave).ok()? * SEMITONES_PER_OCTAVE;
+
+        if y < w.y() + 1 + Self::black_key_height(w) {
+            let black_w = Self::black_key_width(w);
+
+            for (semitone, right_key) in BLACK_KEYS {
+                for o in [octave - 1, octave] {
+                    let key_x = Self::white_key_x(w, o * 7 + right_key) - black_w / 2;
+                    if (0..N_WHITE_KEYS / 7).contains(&o) && x >= key_x && x < key_x + black_w {
+                        return Some(u8::try_from(o).ok()? * SEMITONES_PER_OCTAVE + semitone);
+                    }
+                }
+            }
+        }
+
+        let i = usize::try_from(white_key % 7).ok()?;
+        Some(first_note + WHITE_KEYS[i])
+    }
+
+    fn on_click(&self, w: &Widget, x: i32, y: i32) {
+        let id = match self.selected_id {
+            Some(id) => id,
+            None => return,
+        };
+
+        if let Some(note_id) = Self::note_at(w, x, y) {
+            if self.in_range(note_id) {
+                if let Ok(note) = Note::from_note_id_u32(note_id.into()) {
+                    self.sender.send(GuiMessage::PlayInstrument(
+                        id,
+                        PlaySampleArgs {
+                            note,
+                            note_length: NOTE_RANGE_NOTE_LENGTH,
+                            envelope: None,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
 pub struct TestInstrumentWidget {
     selected_id: Option<ItemId>,
 
@@ -9,6 +9,7 @@ use crate::helpers::ch_units_to_width;
 use crate::names::{
     deduplicate_name_iter, DeduplicatedNameVec, NameGetter, NameSetter, TwoDeduplicatedNameVecs,
 };
+use crate::undo::UndoStack;
 use crate::GuiMessage;
 use crate::{sfx_export_order, tables};
 
@@ -35,6 +36,10 @@ pub enum ListMessage<T> {
     MoveUp(usize),
     MoveDown(usize),
     MoveToBottom(usize),
+    /// Moves an item from one index to another
+    Move(usize, usize),
+    /// Clones an item and inserts the clone at the given index
+    CloneTo(usize, usize),
 
     // Only adds the item if the list does not contain ItemId.
     AddWithItemId(ItemId, T),
@@ -49,17 +54,19 @@ impl<T> ListMessage<T> {
             | Self::AddMultiple(..)
             | Self::AddWithItemId(..)
             | Self::Clone(..)
+            | Self::CloneTo(..)
             | Self::Remove(..) => true,
 
             Self::MoveToTop(..)
             | Self::MoveUp(..)
             | Self::MoveDown(..)
-            | Self::MoveToBottom(..) => false,
+            | Self::MoveToBottom(..)
+            | Self::Move(..) => false,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ListAction<T> {
     None,
     Add(usize, T),
@@ -75,6 +82,17 @@ impl<T> ListAction<T> {
     }
 }
 
+/// A `ListWithCompilerOutput` action stored in an `UndoStack`.
+///
+/// Unlike `ListAction`, `Add` holds the `ItemId` so an undone `Remove` restores the original item.
+#[derive(Debug, Clone)]
+pub enum ListUndoAction<T> {
+    Add(usize, ItemId, T),
+    Remove(usize),
+    Edit(usize, T),
+    Move(usize, usize),
+}
+
 /// Preforms the ListAction on a list
 ///
 /// #Panics
@@ -255,6 +273,11 @@ where
     fn item_edited(&mut self, _id: ItemId, _value: &T) {}
     fn item_removed(&mut self, _id: ItemId) {}
     fn set_compiler_output(&mut self, _index: usize, _id: ItemId, _compiler_output: &Option<O>) {}
+
+    // Lists without an undo stack cannot be undone.
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<ListUndoAction<T>>> {
+        None
+    }
 }
 
 impl<T, O> ListWithCompilerOutput<T, O>
@@ -472,12 +495,141 @@ where
         }
     }
 
+    /// Returns the action that reverses `action`.
+    ///
+    /// MUST be called before `action` is processed.
+    fn inverse_action(&self, action: &ListUndoAction<T>) -> Option<ListUndoAction<T>> {
+        match *action {
+            ListUndoAction::Add(index, ..) => Some(ListUndoAction::Remove(index)),
+            ListUndoAction::Remove(index) => self.remove_inverse(index),
+            ListUndoAction::Edit(index, _) => self.edit_inverse(index),
+            ListUndoAction::Move(from, to) => Some(ListUndoAction::Move(to, from)),
+        }
+    }
+
+    /// Returns the action that reverses `m`.
+    ///
+    /// MUST be called before `m` is processed.
+    fn message_inverse(&self, m: &ListMessage<T>) -> Option<ListUndoAction<T>> {
+        let len = self.list.len();
+
+        match *m {
+            ListMessage::ItemEdited(index, _) => self.edit_inverse(index),
+            ListMessage::Add(_) | ListMessage::AddWithItemId(..) => {
+                Some(ListUndoAction::Remove(len))
+            }
+            // ::TODO add a RemoveMultiple action so AddMultiple can be undone::
+            ListMessage::AddMultiple(_) => None,
+            ListMessage::Clone(index) => Some(ListUndoAction::Remove(index + 1)),
+            ListMessage::CloneTo(_, to) => Some(ListUndoAction::Remove(to)),
+            ListMessage::Remove(index) => self.remove_inverse(index),
+            ListMessage::MoveToTop(index) => Some(ListUndoAction::Move(0, index)),
+            ListMessage::MoveUp(index) => {
+                Some(ListUndoAction::Move(index.saturating_sub(1), index))
+            }
+            ListMessage::MoveDown(index) => Some(ListUndoAction::Move(index + 1, index)),
+            ListMessage::MoveToBottom(index) => {
+                Some(ListUndoAction::Move(len.saturating_sub(1), index))
+            }
+            ListMessage::Move(from, to) => Some(ListUndoAction::Move(to, from)),
+        }
+    }
+
+    fn remove_inverse(&self, index: usize) -> Option<ListUndoAction<T>> {
+        let (id, item) = self.list.get(index)?;
+        Some(ListUndoAction::Add(index, *id, item.clone()))
+    }
+
+    fn edit_inverse(&self, index: usize) -> Option<ListUndoAction<T>> {
+        let (_, item) = self.list.get(index)?;
+        Some(ListUndoAction::Edit(index, item.clone()))
+    }
+
+    /// Adds the processed action to the editor's undo stack.
+    ///
+    /// MUST be called after the action is processed.
+    fn push_undo(
+        &self,
+        inverse: Option<ListUndoAction<T>>,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+    ) {
+        if let Some(undo_stack) = editor.undo_stack() {
+            // The inverse of the inverse is the action that was processed
+            let action = inverse.as_ref().and_then(|i| self.inverse_action(i));
+
+            match (action, inverse) {
+                (Some(action), Some(inverse)) => undo_stack.push(action, inverse),
+                // Clear the stack if the action cannot be undone
+                _ => undo_stack.clear(),
+            }
+        }
+    }
+
+    fn process_undo_action(
+        &mut self,
+        other_list: impl OtherList,
+        action: ListUndoAction<T>,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+    ) -> (bool, Option<ItemChanged<T>>) {
+        match action {
+            ListUndoAction::Add(index, id, item) => {
+                self.add_item(other_list, Some(id), index, item, editor)
+            }
+            ListUndoAction::Remove(index) => self.remove_item(index, editor),
+            ListUndoAction::Edit(index, item) => {
+                self.edit_item_index(other_list, index, item, editor)
+            }
+            ListUndoAction::Move(from, to) => self.move_item(from, to, editor),
+        }
+    }
+
+    fn undo_or_redo_(
+        &mut self,
+        other_list: impl OtherList,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+        pop: fn(&mut UndoStack<ListUndoAction<T>>) -> Option<ListUndoAction<T>>,
+    ) -> (bool, Option<ItemChanged<T>>) {
+        let action = match editor.undo_stack().and_then(pop) {
+            Some(a) => a,
+            None => return (false, None),
+        };
+
+        let out = self.process_undo_action(other_list, action, editor);
+
+        if !out.0 {
+            // The list no longer matches the undo stack
+            if let Some(undo_stack) = editor.undo_stack() {
+                undo_stack.clear();
+            }
+        }
+
+        out
+    }
+
     #[inline]
     fn process_(
         &mut self,
         other_list: impl OtherList,
         m: ListMessage<T>,
         editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+    ) -> (bool, Option<ItemChanged<T>>) {
+        let inverse = self.message_inverse(&m);
+
+        let out = self.process_message_(other_list, m, editor);
+
+        if out.0 {
+            self.push_undo(inverse, editor);
+        }
+
+        out
+    }
+
+    #[inline]
+    fn process_message_(
+        &mut self,
+        other_list: impl OtherList,
+        m: ListMessage<T>,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
     ) -> (bool, Option<ItemChanged<T>>) {
         match m {
             ListMessage::ItemEdited(index, new_value) => {
@@ -500,6 +652,10 @@ where
                 Some(item) => self.add_item(other_list, None, index + 1, item.1.clone(), editor),
                 None => (false, None),
             },
+            ListMessage::CloneTo(index, to) => match self.list.get(index) {
+                Some(item) => self.add_item(other_list, None, to, item.1.clone(), editor),
+                None => (false, None),
+            },
             ListMessage::Remove(index) => self.remove_item(index, editor),
             ListMessage::MoveToTop(index) => self.move_item(index, 0, editor),
             ListMessage::MoveUp(index) => {
@@ -518,6 +674,7 @@ where
                     (false, None)
                 }
             }
+            ListMessage::Move(from, to) => self.move_item(from, to, editor),
         }
     }
 
@@ -530,7 +687,16 @@ where
         editor: &mut impl ListWithCompilerOutputEditor<T, O>,
     ) -> (bool, Option<ItemChanged<T>>) {
         match self.id_to_index(id) {
-            Some(index) => self.edit_item_index(other_list, index, new_value, editor),
+            Some(index) => {
+                let inverse = self.edit_inverse(index);
+
+                let out = self.edit_item_index(other_list, index, new_value, editor);
+
+                if out.0 {
+                    self.push_undo(inverse, editor);
+                }
+                out
+            }
             None => (false, None),
         }
     }
@@ -554,6 +720,22 @@ where
         self.edit_item_((), id, new_value, editor)
     }
 
+    #[must_use]
+    pub fn undo(
+        &mut self,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+    ) -> (bool, Option<ItemChanged<T>>) {
+        self.undo_or_redo_((), editor, UndoStack::undo)
+    }
+
+    #[must_use]
+    pub fn redo(
+        &mut self,
+        editor: &mut impl ListWithCompilerOutputEditor<T, O>,
+    ) -> (bool, Option<ItemChanged<T>>) {
+        self.undo_or_redo_((), editor, UndoStack::redo)
+    }
+
     pub fn max_size(&self) -> usize {
         self.max_size
     }
@@ -714,6 +896,72 @@ where
         self.list2.edit_item_(&self.list1, id, new_value, editor)
     }
 
+    fn undo_or_redo1<Editor>(
+        &mut self,
+        editor: &mut Editor,
+        pop: fn(&mut UndoStack<ListUndoAction<T1>>) -> Option<ListUndoAction<T1>>,
+    ) -> (bool, Option<ItemChanged<T1>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        let out = self.list1.undo_or_redo_(&self.list2, editor, pop);
+
+        // Assumes list1 and list2 have the same max_size
+        ListWithCompilerOutputEditor::<T2, O2>::table_mut(editor)
+            .set_max_size(self.list1.max_size.saturating_sub(self.list1.len()));
+
+        out
+    }
+
+    fn undo_or_redo2<Editor>(
+        &mut self,
+        editor: &mut Editor,
+        pop: fn(&mut UndoStack<ListUndoAction<T2>>) -> Option<ListUndoAction<T2>>,
+    ) -> (bool, Option<ItemChanged<T2>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        let out = self.list2.undo_or_redo_(&self.list1, editor, pop);
+
+        // Assumes list1 and list2 have the same max_size
+        ListWithCompilerOutputEditor::<T1, O1>::table_mut(editor)
+            .set_max_size(self.list2.max_size.saturating_sub(self.list2.len()));
+
+        out
+    }
+
+    #[must_use]
+    pub fn undo1<Editor>(&mut self, editor: &mut Editor) -> (bool, Option<ItemChanged<T1>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        self.undo_or_redo1(editor, UndoStack::undo)
+    }
+
+    #[must_use]
+    pub fn redo1<Editor>(&mut self, editor: &mut Editor) -> (bool, Option<ItemChanged<T1>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        self.undo_or_redo1(editor, UndoStack::redo)
+    }
+
+    #[must_use]
+    pub fn undo2<Editor>(&mut self, editor: &mut Editor) -> (bool, Option<ItemChanged<T2>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        self.undo_or_redo2(editor, UndoStack::undo)
+    }
+
+    #[must_use]
+    pub fn redo2<Editor>(&mut self, editor: &mut Editor) -> (bool, Option<ItemChanged<T2>>)
+    where
+        Editor: ListWithCompilerOutputEditor<T1, O1> + ListWithCompilerOutputEditor<T2, O2>,
+    {
+        self.undo_or_redo2(editor, UndoStack::redo)
+    }
+
     pub fn set_compiler_output1(
         &mut self,
         id: ItemId,
@@ -949,6 +1197,23 @@ where
                 });
             }
 
+            t.set_row_drag_callback({
+                let s = sender.clone();
+                move |from, to, copy| {
+                    if copy {
+                        if T::CAN_CLONE {
+                            s.send(T::to_message(ListMessage::CloneTo(from, to)));
+                        }
+                    } else {
+                        // `to` is the row below the drop line, not the moved item's new index
+                        let to = if to > from { to - 1 } else { to };
+                        if to != from {
+                            s.send(T::to_message(ListMessage::Move(from, to)));
+                        }
+                    }
+                }
+            });
+
             t.set_callback({
                 let s = sender.clone();
                 move |ev, row, col| match T::table_event(ev, row, col) {
@@ -1203,6 +1468,27 @@ where
     pub fn sfx_eo_edited(&mut self, action: &ListAction<Name>) {
         self.list_edited(action);
     }
+
+    // Must only be called by SfxExportOrderEditor
+    pub fn sfx_eo_replace(&mut self, sfx: &[Name]) {
+        let mut t = self.table.borrow_mut();
+
+        t.edit_table(|v| {
+            *v = sfx.iter().map(T::new_row).collect();
+        });
+        t.clear_selected();
+    }
+
+    // Must only be called by SfxExportOrderEditor
+    pub fn enable_sfx_eo_drag_and_drop(
+        &mut self,
+        drag_text: impl Fn(&T::RowType) -> Option<String> + 'static,
+        on_drop: impl Fn(&str) + 'static,
+    ) {
+        let mut t = self.table.borrow_mut();
+        t.set_drag_text_callback(drag_text);
+        t.set_drop_callback(on_drop);
+    }
 }
 
 pub fn tables_for_list_pair<M1, M2>(
@@ -9,7 +9,9 @@
 #![windows_subsystem = "windows"]
 
 mod audio_thread;
+mod channel_meters;
 mod compiler_thread;
+mod config;
 mod envelope_widget;
 mod files;
 mod help;
@@ -21,6 +23,8 @@ mod menu;
 mod mml_editor;
 mod monitor_timer;
 mod names;
+mod presets;
+mod project_templates;
 mod sample_analyser;
 mod sample_editor;
 mod sample_sizes_widget;
@@ -30,6 +34,8 @@ mod sfx_window;
 mod symbols;
 mod tables;
 mod tabs;
+mod undo;
+mod waveform_preview;
 
 mod about_tab;
 mod project_tab;
@@ -39,8 +45,9 @@ mod sound_effects_tab;
 
 use crate::about_tab::AboutTab;
 use crate::compiler_thread::{
-    CompilerOutput, InstrumentOutput, ItemId, SoundEffectOutput, ToCompiler,
+    CompilerOutput, InstrumentOutput, ItemChanged, ItemId, SoundEffectOutput, ToCompiler,
 };
+use crate::config::RecentFiles;
 use crate::files::{
     add_song_to_pf_dialog, load_mml_file, load_pf_sfx_file,
     load_project_file_or_show_error_message, open_mml_file_dialog, open_sfx_file_dialog,
@@ -51,8 +58,8 @@ use crate::helpers::input_height;
 use crate::list_editor::{ListMessage, ListWithCompilerOutput};
 use crate::menu::Menu;
 use crate::names::deduplicate_names;
-use crate::project_tab::ProjectTab;
-use crate::samples_tab::SamplesTab;
+use crate::project_tab::{ProjectTab, ProjectUndoList};
+use crate::samples_tab::{SamplesTab, UndoList};
 use crate::sfx_export_order::SfxId;
 use crate::song_tab::{blank_mml_file, SongTab};
 use crate::sound_effects_tab::{blank_sfx_file, SoundEffectsTab};
@@ -61,7 +68,7 @@ use crate::tabs::{
     Tab, TabManager,
 };
 
-use audio_thread::{AudioMessage, AudioMonitor, MusicChannelsMask};
+use audio_thread::{AudioMessage, AudioMonitor, MusicChannelsMask, SongLoop};
 
 use compiler::data;
 use compiler::data::{DefaultSfxFlags, ProjectFile};
@@ -75,8 +82,8 @@ use compiler::Pan;
 use compiler::time::TickCounter;
 use compiler_thread::{PlaySampleArgs, SampleOutput, SfxToCompiler, ShortSongError};
 use files::{
-    new_project_dialog, open_instrument_sample_dialog, open_project_dialog,
-    open_sample_sample_dialog, song_name_from_path,
+    new_project_dialog, new_project_from_template_dialog, open_instrument_sample_dialog,
+    open_project_dialog, open_sample_sample_dialog, song_name_from_path,
 };
 use fltk::dialog;
 use fltk::prelude::*;
@@ -86,8 +93,9 @@ use licenses_dialog::LicensesDialog;
 use list_editor::{ListPairWithCompilerOutputs, ListWithCompilerOutputEditor};
 use monitor_timer::MonitorTimer;
 use names::deduplicate_two_name_vecs;
+use project_templates::{ProjectTemplateDialog, PROJECT_TEMPLATES};
 use sample_analyser::SampleAnalyserDialog;
-use sfx_export_order::{GuiSfxExportOrder, SfxExportOrderMessage};
+use sfx_export_order::{GuiSfxExportOrder, SfxExportOrderAction, SfxExportOrderMessage};
 use sfx_window::SfxWindow;
 use sound_effects_tab::MAX_SFX_FILE_SOUND_EFFECTS;
 
@@ -121,6 +129,9 @@ pub enum GuiMessage {
     ForceQuit,
     SaveAllAndQuit(Vec<FileType>),
 
+    Undo,
+    Redo,
+
     DefaultSfxFlagChanged(DefaultSfxFlags),
     EditSfxExportOrder(SfxExportOrderMessage),
     EditProjectSongs(ListMessage<data::Song>),
@@ -154,6 +165,7 @@ pub enum GuiMessage {
 
     AddSongToProjectDialog,
     SetProjectSongName(usize, data::Name),
+    SetProjectSongAlias(usize, Option<data::Name>),
 
     ShowSampleSizes,
 
@@ -175,7 +187,15 @@ pub enum GuiMessage {
     SongChanged(ItemId, String),
     RecompileSong(ItemId, String),
 
-    PlaySong(ItemId, String, TickCounter, MusicChannelsMask),
+    // Channels disabled in the optional interpreter mask are not interpreted
+    PlaySong(
+        ItemId,
+        String,
+        TickCounter,
+        MusicChannelsMask,
+        Option<MusicChannelsMask>,
+        Option<SongLoop>,
+    ),
     PlaySongSubroutine(ItemId, String, Option<String>, u8, TickCounter),
     PlaySongForSfxTab(ItemId, TickCounter),
     PlaySoundEffectCommand(SfxId, Pan),
@@ -198,7 +218,10 @@ pub enum GuiMessage {
     ShowLicensesDialog,
 
     NewProject,
+    ShowProjectTemplateDialog,
+    NewProjectFromTemplate(usize),
     OpenProject,
+    OpenRecentProject(PathBuf),
 }
 
 pub type ProjectSongsData =
@@ -391,57 +414,31 @@ impl Project {
             GuiMessage::EditSfxExportOrder(m) => {
                 let a = self
                     .project_tab
-                    .sfx_export_order
-                    .process(m, &mut self.data.sfx_export_order);
-
-                if let Some(a) = a {
-                    let _ = self.compiler_sender.send(ToCompiler::SfxExportOrder(a));
-
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
+                    .process_sfx_export_order(m, &mut self.data.sfx_export_order);
+                self.sfx_export_order_edited(a);
             }
 
             GuiMessage::EditProjectSongs(m) => {
-                let (edited, c) = self.data.project_songs.process(m, &mut self.project_tab);
-
-                if edited {
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
-                if let Some(c) = c {
-                    self.sound_effects_tab.pf_songs_changed();
-
-                    let _ = self.compiler_sender.send(ToCompiler::ProjectSongs(c));
-                }
+                let r = self.data.project_songs.process(m, &mut self.project_tab);
+                self.project_songs_edited(r);
             }
             GuiMessage::SelectProjectSong(index) => {
                 self.project_tab.song_table.set_selected_row(index)
             }
 
             GuiMessage::Instrument(m) => {
-                let (changed, c) = self
+                let r = self
                     .data
                     .instruments_and_samples
                     .process1(m, &mut self.samples_tab);
-
-                if changed {
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
-                if let Some(c) = c {
-                    let _ = self.compiler_sender.send(ToCompiler::Instrument(c));
-                }
+                self.instruments_edited(r);
             }
             GuiMessage::Sample(m) => {
-                let (changed, c) = self
+                let r = self
                     .data
                     .instruments_and_samples
                     .process2(m, &mut self.samples_tab);
-
-                if changed {
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
-                if let Some(c) = c {
-                    let _ = self.compiler_sender.send(ToCompiler::Sample(c));
-                }
+                self.samples_edited(r);
             }
             GuiMessage::UserChangedSelectedInstrument => self
                 .samples_tab
@@ -450,32 +447,23 @@ impl Project {
                 self.samples_tab.selected_item_changed(self.data.samples())
             }
             GuiMessage::EditInstrument(id, inst) => {
-                let (changed, c) =
+                let r =
                     self.data
                         .instruments_and_samples
                         .edit_item1(id, inst, &mut self.samples_tab);
-
-                if changed {
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
-                if let Some(c) = c {
-                    let _ = self.compiler_sender.send(ToCompiler::Instrument(c));
-                }
+                self.instruments_edited(r);
             }
             GuiMessage::EditSample(id, sample) => {
-                let (changed, c) =
+                let r =
                     self.data
                         .instruments_and_samples
                         .edit_item2(id, sample, &mut self.samples_tab);
-
-                if changed {
-                    self.tab_manager.mark_unsaved(FileType::Project);
-                }
-                if let Some(c) = c {
-                    let _ = self.compiler_sender.send(ToCompiler::Sample(c));
-                }
+                self.samples_edited(r);
             }
 
+            GuiMessage::Undo => self.undo(),
+            GuiMessage::Redo => self.redo(),
+
             GuiMessage::SfxSubroutinesChanged(mml) => {
                 if let Some(sfx_data) = &mut self.sfx_data {
                     if sfx_data.subroutines != mml {
@@ -490,31 +478,18 @@ impl Project {
             }
             GuiMessage::EditSoundEffectList(m) => {
                 if let Some(sfx_data) = &mut self.sfx_data {
-                    let (changed, c) = sfx_data
+                    let r = sfx_data
                         .sound_effects
                         .process(m, &mut self.sound_effects_tab);
-
-                    if changed {
-                        self.tab_manager.mark_unsaved(FileType::SoundEffects);
-                    }
-                    if let Some(c) = c {
-                        let _ = self.compiler_sender.send(ToCompiler::SoundEffects(c));
-                    }
+                    self.sound_effects_edited(r);
                 }
             }
             GuiMessage::EditSoundEffect(id, sfx) => {
                 if let Some(sfx_data) = &mut self.sfx_data {
-                    let (changed, c) =
-                        sfx_data
-                            .sound_effects
-                            .edit_item(id, sfx, &mut self.sound_effects_tab);
-
-                    if changed {
-                        self.tab_manager.mark_unsaved(FileType::SoundEffects);
-                    }
-                    if let Some(c) = c {
-                        let _ = self.compiler_sender.send(ToCompiler::SoundEffects(c));
-                    }
+                    let r = sfx_data
+                        .sound_effects
+                        .edit_item(id, sfx, &mut self.sound_effects_tab);
+                    self.sound_effects_edited(r);
                 }
             }
             GuiMessage::UserChangesSelectedSoundEffect => {
@@ -537,13 +512,15 @@ impl Project {
                 // RecompileSong should not mark the song as unsaved
                 let _ = self.compiler_sender.send(ToCompiler::SongChanged(id, mml));
             }
-            GuiMessage::PlaySong(id, mml, skip, channels_mask) => {
+            GuiMessage::PlaySong(id, mml, skip, channels_mask, interpreter_mask, song_loop) => {
                 // RecompileSong should not mark the song as unsaved
                 let _ = self.compiler_sender.send(ToCompiler::CompileAndPlaySong(
                     id,
                     mml,
                     skip,
                     channels_mask,
+                    interpreter_mask,
+                    song_loop,
                 ));
             }
             GuiMessage::PlaySongSubroutine(id, mml, mml_prefix, si, skip) => {
@@ -774,6 +751,15 @@ impl Project {
                         )))
                 }
             }
+            GuiMessage::SetProjectSongAlias(index, alias) => {
+                if let Some(s) = self.data.project_songs.get(index) {
+                    self.sender
+                        .send(GuiMessage::EditProjectSongs(ListMessage::ItemEdited(
+                            index,
+                            data::Song { alias, ..s.clone() },
+                        )))
+                }
+            }
             GuiMessage::NewMmlFile => self.new_blank_song_tab(),
             GuiMessage::OpenMmlFile => self.open_mml_file_dialog(),
             GuiMessage::OpenSongTab(index) => self.open_pf_song_tab(index),
@@ -799,7 +785,10 @@ impl Project {
             GuiMessage::ShowOrHideHelpSyntax => (),
             GuiMessage::ShowLicensesDialog => (),
             GuiMessage::OpenProject => (),
+            GuiMessage::OpenRecentProject(_) => (),
             GuiMessage::NewProject => (),
+            GuiMessage::ShowProjectTemplateDialog => (),
+            GuiMessage::NewProjectFromTemplate(_) => (),
             GuiMessage::SelectedTabChanged => (),
         }
     }
@@ -850,12 +839,19 @@ impl Project {
                     .project_songs
                     .set_compiler_output(id, pf_co, &mut self.project_tab);
 
+                self.samples_tab.song_compiled(id, &co);
+
                 if let Some(song_tab) = self.song_tabs.get_mut(&id) {
                     self.tab_manager.set_tab_label_color(song_tab, co.is_ok());
                     song_tab.set_compiler_output(Some(co));
                 }
             }
 
+            CompilerOutput::SongMetrics(id, m) => {
+                if let Some(song_tab) = self.song_tabs.get_mut(&id) {
+                    song_tab.set_parse_metrics(m);
+                }
+            }
             CompilerOutput::SongPrefix(id, r) => {
                 if let Some(song_tab) = self.song_tabs.get_mut(&id) {
                     song_tab.set_song_prefix_result(r);
@@ -864,12 +860,20 @@ impl Project {
 
             CompilerOutput::CommonAudioData(cad) => {
                 self.project_tab.memory_stats.cad_output_changed(&cad);
+
+                let common_data_size = self.project_tab.memory_stats.common_data_size();
+                for song_tab in self.song_tabs.values_mut() {
+                    song_tab.set_common_data_size(common_data_size);
+                }
                 self.tab_manager
                     .set_tab_label_color(&mut self.samples_tab, cad.is_ok_or_none());
 
                 self.sfx_window.cad_output_changed(&cad);
                 self.samples_tab.set_common_audio_data(cad);
             }
+            CompilerOutput::InstrumentPitchWarnings(w) => {
+                self.samples_tab.set_instrument_pitch_warnings(w);
+            }
 
             CompilerOutput::NumberOfMissingSoundEffects(n_missing) => {
                 self.project_tab.memory_stats.set_n_missing_sfx(n_missing);
@@ -918,6 +922,131 @@ impl Project {
         }
     }
 
+    fn project_songs_edited(&mut self, (edited, c): (bool, Option<ItemChanged<data::Song>>)) {
+        if edited {
+            self.tab_manager.mark_unsaved(FileType::Project);
+        }
+        if let Some(c) = c {
+            self.sound_effects_tab.pf_songs_changed();
+
+            let _ = self.compiler_sender.send(ToCompiler::ProjectSongs(c));
+        }
+    }
+
+    fn instruments_edited(&mut self, (changed, c): (bool, Option<ItemChanged<data::Instrument>>)) {
+        if changed {
+            self.tab_manager.mark_unsaved(FileType::Project);
+        }
+        if let Some(c) = c {
+            let _ = self.compiler_sender.send(ToCompiler::Instrument(c));
+        }
+    }
+
+    fn samples_edited(&mut self, (changed, c): (bool, Option<ItemChanged<data::Sample>>)) {
+        if changed {
+            self.tab_manager.mark_unsaved(FileType::Project);
+        }
+        if let Some(c) = c {
+            let _ = self.compiler_sender.send(ToCompiler::Sample(c));
+        }
+    }
+
+    fn sfx_export_order_edited(&mut self, a: Option<SfxExportOrderAction>) {
+        if let Some(a) = a {
+            let _ = self.compiler_sender.send(ToCompiler::SfxExportOrder(a));
+
+            self.tab_manager.mark_unsaved(FileType::Project);
+        }
+    }
+
+    fn sound_effects_edited(
+        &mut self,
+        (changed, c): (bool, Option<ItemChanged<SoundEffectInput>>),
+    ) {
+        if changed {
+            self.tab_manager.mark_unsaved(FileType::SoundEffects);
+        }
+        if let Some(c) = c {
+            let _ = self.compiler_sender.send(ToCompiler::SoundEffects(c));
+        }
+    }
+
+    fn is_tab_selected(&self, tab: &impl Tab) -> bool {
+        self.tab_manager
+            .selected_widget()
+            .is_some_and(|t| t.is_same(tab.widget()))
+    }
+
+    fn undo(&mut self) {
+        if self.is_tab_selected(&self.project_tab) {
+            match self.project_tab.undo_list() {
+                ProjectUndoList::Songs => {
+                    let r = self.data.project_songs.undo(&mut self.project_tab);
+                    self.project_songs_edited(r);
+                }
+                ProjectUndoList::SfxExportOrder => {
+                    let a = self
+                        .project_tab
+                        .undo_sfx_export_order(&mut self.data.sfx_export_order);
+                    self.sfx_export_order_edited(a);
+                }
+            }
+        } else if self.is_tab_selected(&self.sound_effects_tab) {
+            if let Some(sfx_data) = &mut self.sfx_data {
+                let r = sfx_data.sound_effects.undo(&mut self.sound_effects_tab);
+                self.sound_effects_edited(r);
+            }
+        } else if self.is_tab_selected(&self.samples_tab) {
+            let iands = &mut self.data.instruments_and_samples;
+
+            match self.samples_tab.undo_list() {
+                UndoList::Instruments => {
+                    let r = iands.undo1(&mut self.samples_tab);
+                    self.instruments_edited(r);
+                }
+                UndoList::Samples => {
+                    let r = iands.undo2(&mut self.samples_tab);
+                    self.samples_edited(r);
+                }
+            }
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.is_tab_selected(&self.project_tab) {
+            match self.project_tab.undo_list() {
+                ProjectUndoList::Songs => {
+                    let r = self.data.project_songs.redo(&mut self.project_tab);
+                    self.project_songs_edited(r);
+                }
+                ProjectUndoList::SfxExportOrder => {
+                    let a = self
+                        .project_tab
+                        .redo_sfx_export_order(&mut self.data.sfx_export_order);
+                    self.sfx_export_order_edited(a);
+                }
+            }
+        } else if self.is_tab_selected(&self.sound_effects_tab) {
+            if let Some(sfx_data) = &mut self.sfx_data {
+                let r = sfx_data.sound_effects.redo(&mut self.sound_effects_tab);
+                self.sound_effects_edited(r);
+            }
+        } else if self.is_tab_selected(&self.samples_tab) {
+            let iands = &mut self.data.instruments_and_samples;
+
+            match self.samples_tab.undo_list() {
+                UndoList::Instruments => {
+                    let r = iands.redo1(&mut self.samples_tab);
+                    self.instruments_edited(r);
+                }
+                UndoList::Samples => {
+                    let r = iands.redo2(&mut self.samples_tab);
+                    self.samples_edited(r);
+                }
+            }
+        }
+    }
+
     fn selected_tab_changed(&mut self, window: &mut fltk::window::Window) {
         if self.sfx_tab_selected {
             let _ = self
@@ -1049,6 +1178,9 @@ impl Project {
             self.song_tabs.insert(song_id, song_tab);
 
             // Update song in the compiler thread (in case the file changed)
+            let _ = self
+                .compiler_sender
+                .send(ToCompiler::SongSourceChanged(song_id, source.clone()));
             let _ = self
                 .compiler_sender
                 .send(ToCompiler::SongChanged(song_id, f.contents));
@@ -1084,13 +1216,15 @@ impl Project {
 
     /// Returns a previously closed or new SongTab with the given `song_id` and `mml_text`.
     fn reuse_or_new_song_tab(&mut self, song_id: ItemId, mml_text: &data::TextFile) -> SongTab {
-        match self.closed_song_tabs.pop() {
+        let mut song_tab = match self.closed_song_tabs.pop() {
             Some(mut song_tab) => {
                 song_tab.reuse_tab(song_id, mml_text);
                 song_tab
             }
             None => SongTab::new(song_id, mml_text, self.sender.clone()),
-        }
+        };
+        song_tab.set_common_data_size(self.project_tab.memory_stats.common_data_size());
+        song_tab
     }
 
     // NOTE: Does not test if the song is unsaved before closing
@@ -1147,6 +1281,9 @@ impl Project {
                         SaveResult::None => false,
                         SaveResult::Saved => true,
                         SaveResult::Renamed(source_path) => {
+                            let _ = self
+                                .compiler_sender
+                                .send(ToCompiler::SongSourceChanged(id, source_path.clone()));
                             self.edit_pf_song_source(id, source_path);
                             true
                         }
@@ -1192,6 +1329,7 @@ impl Project {
                             data::Song {
                                 name: song_name_from_path(&source),
                                 source,
+                                alias: None,
                             },
                         )));
 
@@ -1269,6 +1407,9 @@ struct MainWindow {
 
     help_widget: HelpWidget,
     licenses_dialog: LicensesDialog,
+    project_template_dialog: Option<ProjectTemplateDialog>,
+
+    recent_files: RecentFiles,
 
     project: Option<Project>,
 }
@@ -1295,8 +1436,11 @@ impl MainWindow {
         let (audio_thread, audio_sender, audio_monitor) =
             audio_thread::create_audio_thread(sender.clone());
 
+        let recent_files = RecentFiles::load();
+
         let mut menu = Menu::new(sender.clone(), audio_sender.clone());
         menu.deactivate_project_items();
+        menu.set_recent_projects(recent_files.projects());
         col.fixed(menu.menu_bar(), input_height(menu.menu_bar()));
 
         let mut row = fltk::group::Flex::default().row();
@@ -1363,6 +1507,8 @@ impl MainWindow {
             about_tab,
             help_widget: help,
             licenses_dialog: LicensesDialog::new(),
+            project_template_dialog: None,
+            recent_files,
             project: None,
         }
     }
@@ -1371,6 +1517,12 @@ impl MainWindow {
         if self.project.is_some() {
             return;
         }
+
+        self.recent_files.add_project(&pf.path);
+        self.menu.set_recent_projects(self.recent_files.projects());
+        // Failing to save the recent files list is not worth interrupting the user with a dialog
+        let _ = self.recent_files.save();
+
         self.menu.project_loaded();
         self.about_tab.project_loaded();
         self.project = Some(Project::new(
@@ -1433,6 +1585,13 @@ impl MainWindow {
                     }
                 }
             }
+            GuiMessage::OpenRecentProject(path) => {
+                if self.project.is_none() {
+                    if let Some(pf) = load_project_file_or_show_error_message(&path) {
+                        self.load_project(pf);
+                    }
+                }
+            }
             GuiMessage::NewProject => {
                 if self.project.is_none() {
                     if let Some(pf) = new_project_dialog() {
@@ -1440,6 +1599,23 @@ impl MainWindow {
                     }
                 }
             }
+            GuiMessage::ShowProjectTemplateDialog => {
+                if self.project.is_none() {
+                    let sender = &self.sender;
+                    self.project_template_dialog
+                        .get_or_insert_with(|| ProjectTemplateDialog::new(sender.clone()))
+                        .show();
+                }
+            }
+            GuiMessage::NewProjectFromTemplate(index) => {
+                if self.project.is_none() {
+                    if let Some(t) = PROJECT_TEMPLATES.get(index) {
+                        if let Some(pf) = new_project_from_template_dialog(t) {
+                            self.load_project(pf);
+                        }
+                    }
+                }
+            }
             m => {
                 if let Some(p) = &mut self.project {
                     p.process(m);
@@ -5,6 +5,7 @@
 // SPDX-License-Identifier: MIT
 
 use crate::audio_thread::{AudioMessage, StereoFlag};
+use crate::config::RecentFile;
 use crate::tabs::FileType;
 use crate::GuiMessage;
 
@@ -24,6 +25,8 @@ use fltk::prelude::MenuExt;
 // Unfortunately, changing a menu item's label changes its path, so I cannot include the filename
 // in the Save menu item (ie "Save sound_effects.txt").
 
+const NEW_PROJECT_FROM_TEMPLATE: &str = "&File/New Project from Template";
+const OPEN_RECENT: &str = "&File/Open &Recent";
 const NEW_MML_FILE: &str = "&File/New MML File";
 const OPEN_MML_FILE: &str = "&File/Open MML File";
 const SAVE: &str = "&File/&Save";
@@ -45,9 +48,13 @@ const SHOW_ABOUT_TAB: &str = "&Help/&About";
 
 const QUIT: &str = "&File/&Quit";
 
+const UNDO: &str = "&Edit/&Undo";
+const REDO: &str = "&Edit/&Redo";
+
 #[derive(Clone)]
 pub struct Menu {
     menu_bar: fltk::menu::MenuBar,
+    sender: fltk::app::Sender<GuiMessage>,
 }
 
 impl Menu {
@@ -57,6 +64,7 @@ impl Menu {
     ) -> Self {
         let mut menu_bar = fltk::menu::MenuBar::default();
         let mut menu_bar2 = menu_bar.clone();
+        let mut menu_bar3 = menu_bar.clone();
 
         let mut add = |label, shortcut, flags, f: fn() -> GuiMessage| -> menu::MenuItem {
             let index = menu_bar.add(label, shortcut, flags, {
@@ -76,6 +84,21 @@ impl Menu {
             });
         };
 
+        add(
+            NEW_PROJECT_FROM_TEMPLATE,
+            Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            || GuiMessage::ShowProjectTemplateDialog,
+        );
+
+        // Populated by `set_recent_projects()`
+        menu_bar3.add(
+            OPEN_RECENT,
+            Shortcut::None,
+            fltk::menu::MenuFlag::Submenu,
+            |_: &mut fltk::menu::MenuBar| (),
+        );
+
         add(
             NEW_MML_FILE,
             Shortcut::None,
@@ -124,6 +147,20 @@ impl Menu {
             GuiMessage::QuitRequested
         });
 
+        // Text editors and inputs process Ctrl+Z before the menu bar does
+        add(
+            UNDO,
+            Shortcut::Ctrl | 'z',
+            fltk::menu::MenuFlag::Normal,
+            || GuiMessage::Undo,
+        );
+        add(
+            REDO,
+            Shortcut::Ctrl | 'y',
+            fltk::menu::MenuFlag::Normal,
+            || GuiMessage::Redo,
+        );
+
         add(
             AUDIO_SFX_WINDOW,
             Shortcut::from_key(Key::F9),
@@ -169,7 +206,7 @@ impl Menu {
             || GuiMessage::ShowAboutTab,
         );
 
-        Menu { menu_bar }
+        Menu { menu_bar, sender }
     }
 
     pub fn menu_bar(&self) -> &menu::MenuBar {
@@ -209,9 +246,43 @@ impl Menu {
         self.deactivate(CLEAR_CACHE_AND_REBUILD);
         self.deactivate(EXPORT_SPC);
 
+        self.deactivate(UNDO);
+        self.deactivate(REDO);
+
         self.deactivate(AUDIO_SFX_WINDOW);
     }
 
+    /// Replaces the items in the Open Recent submenu.
+    ///
+    /// Projects that no longer exist are deactivated.
+    pub fn set_recent_projects(&mut self, projects: &[RecentFile]) {
+        let index = self.menu_bar.find_index(OPEN_RECENT);
+        if index < 0 {
+            return;
+        }
+        let _ = self.menu_bar.clear_submenu(index);
+
+        for p in projects {
+            let flags = match p.path.is_file() {
+                true => MenuFlag::Normal,
+                false => MenuFlag::Inactive,
+            };
+            let label = format!(
+                "{}/{}",
+                OPEN_RECENT,
+                escape_menu_label(&p.path.to_string_lossy())
+            );
+
+            self.menu_bar.add(&label, Shortcut::None, flags, {
+                let s = self.sender.clone();
+                let path = p.path.clone();
+                move |_: &mut fltk::menu::MenuBar| {
+                    s.send(GuiMessage::OpenRecentProject(path.clone()))
+                }
+            });
+        }
+    }
+
     pub fn is_help_syntax_checked(&self) -> bool {
         self.menu_bar
             .find_item(SHOW_HELP_SYNTAX)
@@ -220,12 +291,18 @@ impl Menu {
     }
 
     pub fn project_loaded(&mut self) {
+        self.deactivate(NEW_PROJECT_FROM_TEMPLATE);
+        self.deactivate(OPEN_RECENT);
+
         self.activate(NEW_MML_FILE);
         self.activate(OPEN_MML_FILE);
 
         self.activate(SAVE_ALL);
         self.activate(CLEAR_CACHE_AND_REBUILD);
 
+        self.activate(UNDO);
+        self.activate(REDO);
+
         self.activate(AUDIO_SFX_WINDOW);
     }
 
@@ -242,3 +319,21 @@ impl Menu {
         self.set_active(EXPORT_SPC, is_song);
     }
 }
+
+/// Escapes the characters in a menu label that `MenuExt::add()` treats as special
+fn escape_menu_label(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '/' | '\\' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '&' => out.push_str("&&"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
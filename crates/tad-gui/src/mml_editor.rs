@@ -540,13 +540,16 @@ impl MmlEditorState {
 
         match errors {
             TextErrorRef::Song(errors) => {
+                // Errors in an included file are highlighted at the `#include` directive
+                let include_map = &errors.include_map;
+
                 for e in &errors.line_errors {
-                    highlight_error(&e.0, Style::Error);
+                    highlight_error(&include_map.to_root_file_pos(&e.0), Style::Error);
                 }
 
                 let mut parse_channel_error = |e: &MmlChannelError| {
                     for e in &e.errors {
-                        highlight_error(&e.0, Style::Error);
+                        highlight_error(&include_map.to_root_file_pos(&e.0), Style::Error);
                     }
                 };
 
@@ -930,6 +933,8 @@ struct MmlColors {
     subroutines: Color,
 
     channel_names: Color,
+    notes: Color,
+    loops: Color,
     invalid: Color,
     mml_bc_asm: Color,
 
@@ -943,17 +948,19 @@ struct MmlColors {
 
 const MML_COLORS: MmlColors = MmlColors {
     normal: Color::Foreground,
-    comments: Color::Blue,
-    metadata: Color::DarkRed,
-    metadata_values: Color::DarkRed,
-    instruments: Color::DarkGreen,
-    // ::TODO slightly different green for instrument hint::
-    instrument_hints: Color::DarkGreen,
+    comments: Color::from_rgb(0x73, 0x73, 0x73), // hsl(0, 0, 45)
+    metadata: Color::from_rgb(0x80, 0x33, 0xcc), // hsl(270, 60, 50)
+    metadata_values: Color::from_rgb(0x80, 0x33, 0xcc), // hsl(270, 60, 50)
+    instruments: Color::from_rgb(0xcc, 0x55, 0x00), // hsl(25, 100, 40)
+    // ::TODO slightly different orange for instrument hint::
+    instrument_hints: Color::from_rgb(0xcc, 0x55, 0x00), // hsl(25, 100, 40)
     mml_bc_asm: Color::DarkGreen,
 
-    subroutines: Color::from_rgb(0xcc, 0x55, 0x00), // hsl(25, 100, 40)
+    subroutines: Color::DarkCyan,
 
-    channel_names: Color::DarkMagenta,
+    channel_names: Color::DarkGreen,
+    notes: Color::Blue,
+    loops: Color::Red,
 
     invalid: Color::Red,
     error_bg: Color::Red,
@@ -1009,6 +1016,11 @@ enum Style {
 
     NoteTracking,
 
+    Note,
+    LoopBracket,
+    SlashCommand,
+    HexNumber,
+
     Unknown,
 }
 
@@ -1058,6 +1070,11 @@ impl Style {
             // MUST edit `NOTE_TRACKER_STR` when this character changes
             b'^' => Style::NoteTracking,
 
+            b'_' => Style::Note,
+            b'`' => Style::LoopBracket,
+            b'a' => Style::SlashCommand,
+            b'b' => Style::HexNumber,
+
             _ => Style::Unknown,
         }
     }
@@ -1121,6 +1138,10 @@ fn highlight_data(mml_colors: &MmlColors, font_size: i32) -> Vec<StyleTableEntry
         bg_bold(mml_colors.error_bg),
         courier_bold(mml_colors.bytecode_error),
         bg_bold(mml_colors.tracker_bg),
+        courier(mml_colors.notes),
+        courier_bold(mml_colors.loops),
+        courier(mml_colors.normal),
+        courier(mml_colors.normal),
         courier(mml_colors.unknown),
     ]
 }
@@ -1183,6 +1204,16 @@ fn next_style_mml(current: Style, c: u8) -> Style {
         Style::Normal => channel_or_subroutine_style(c),
         Style::Unknown => channel_or_subroutine_style(c),
 
+        Style::Note => match c {
+            b'+' | b'-' => Style::Note,
+            _ => channel_or_subroutine_style(c),
+        },
+        Style::LoopBracket => channel_or_subroutine_style(c),
+        Style::HexNumber => match c {
+            c if c.is_ascii_hexdigit() => Style::HexNumber,
+            _ => channel_or_subroutine_style(c),
+        },
+
         Style::Instrument => match c {
             c if c.is_ascii_digit() => Style::InstrumentNumber,
             _ => Style::InstrumentName,
@@ -1228,16 +1259,17 @@ fn next_style_mml(current: Style, c: u8) -> Style {
 
         Style::SlashAsm0 => match c {
             b'a' => Style::SlashAsm1,
-            _ => Style::Normal,
+            _ => slash_command_style(c),
         },
         Style::SlashAsm1 => match c {
             b's' => Style::SlashAsm2,
-            _ => Style::Normal,
+            _ => slash_command_style(c),
         },
         Style::SlashAsm2 => match c {
             b'm' => Style::SlashAsm3,
-            _ => Style::Normal,
+            _ => slash_command_style(c),
         },
+        Style::SlashCommand => slash_command_style(c),
         Style::SlashAsm3 => match c {
             b'{' => Style::BytecodeAsm,
             c if c.is_ascii_whitespace() => Style::SlashAsm3,
@@ -1268,10 +1300,21 @@ fn channel_or_subroutine_style(c: u8) -> Style {
         b'?' => Style::InstrumentHintQuestionMark,
         b'!' => Style::Subroutine,
         b'\\' => Style::SlashAsm0,
+        b'a'..=b'g' => Style::Note,
+        b'[' | b']' | b':' => Style::LoopBracket,
+        b'$' => Style::HexNumber,
         _ => Style::Normal,
     }
 }
 
+/// Slash commands and macro names are not highlighted (they may contain note letters)
+fn slash_command_style(c: u8) -> Style {
+    match c {
+        c if c.is_ascii_whitespace() => Style::Normal,
+        _ => Style::SlashCommand,
+    }
+}
+
 fn next_style_bc(current: Style, c: u8) -> Style {
     match c {
         b'\n' => Style::NewLine,
@@ -0,0 +1,50 @@
+//! Envelope presets
+
+// SPDX-FileCopyrightText: © 2024 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use compiler::envelope::Adsr;
+
+const fn adsr(attack: u8, decay: u8, sustain_level: u8, sustain_rate: u8) -> Adsr {
+    match Adsr::try_new(attack, decay, sustain_level, sustain_rate) {
+        Ok(a) => a,
+        Err(_) => panic!("invalid ADSR preset"),
+    }
+}
+
+pub const ADSR_PRESETS: [(&str, Adsr); 5] = [
+    ("Piano", adsr(12, 1, 1, 16)),
+    ("Strings", adsr(12, 4, 5, 24)),
+    ("Brass", adsr(4, 3, 3, 16)),
+    ("Organ", adsr(12, 7, 7, 31)),
+    ("Percussion", adsr(8, 0, 0, 0)),
+];
+
+pub fn adsr_preset_index(adsr: &Adsr) -> Option<usize> {
+    ADSR_PRESETS.iter().position(|(_, a)| a == adsr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn adsr_preset_serialization() {
+        let expected = [
+            ("Piano", "adsr 12 1 1 16"),
+            ("Strings", "adsr 12 4 5 24"),
+            ("Brass", "adsr 4 3 3 16"),
+            ("Organ", "adsr 12 7 7 31"),
+            ("Percussion", "adsr 8 0 0 0"),
+        ];
+
+        assert_eq!(ADSR_PRESETS.len(), expected.len());
+
+        for ((name, adsr), (e_name, e_str)) in ADSR_PRESETS.iter().zip(expected) {
+            assert_eq!(*name, e_name);
+            assert_eq!(adsr.to_envelope_string(), e_str);
+            assert_eq!(adsr.to_gui_string().parse::<Adsr>().as_ref(), Ok(adsr));
+        }
+    }
+}
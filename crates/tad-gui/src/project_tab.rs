@@ -6,12 +6,15 @@
 
 use crate::compiler_thread::{CadOutput, ShortSongError};
 use crate::list_editor::{
-    ListEditorTable, ListMessage, ListWithCompilerOutputEditor, TableAction, TableCompilerOutput,
-    TableMapping,
+    ListAction, ListEditorTable, ListMessage, ListUndoAction, ListWithCompilerOutputEditor,
+    TableAction, TableCompilerOutput, TableMapping,
+};
+use crate::sfx_export_order::{
+    GuiSfxExportOrder, SfxExportOrderAction, SfxExportOrderEditor, SfxExportOrderMessage,
 };
-use crate::sfx_export_order::SfxExportOrderEditor;
 use crate::tables::{RowWithStatus, TableEvent, TableRow};
 use crate::tabs::{FileType, Tab};
+use crate::undo::UndoStack;
 use crate::GuiMessage;
 use crate::{helpers::*, ProjectData};
 
@@ -26,7 +29,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use fltk::button::CheckButton;
-use fltk::enums::{Align, Color};
+use fltk::enums::{Align, Color, Font};
 use fltk::group::Flex;
 use fltk::output::Output;
 use fltk::prelude::*;
@@ -34,20 +37,28 @@ use fltk::{app, draw};
 
 pub struct SongRow {
     name: String,
+    alias: String,
     filename: String,
     duration: String,
     data_size: String,
 }
 
 impl TableRow for SongRow {
-    const N_COLUMNS: i32 = 4;
+    const N_COLUMNS: i32 = 5;
 
     fn draw_cell(&self, col: i32, x: i32, y: i32, w: i32, h: i32) {
         match col {
             0 => draw::draw_text2(self.name.as_str(), x, y, w, h, Align::Left),
-            1 => draw::draw_text2(&self.filename, x, y, w, h, Align::Left),
-            2 => draw::draw_text2(&self.duration, x, y, w, h, Align::Right),
-            3 => draw::draw_text2(&self.data_size, x, y, w, h, Align::Right),
+            1 => {
+                let font = draw::font();
+                let size = draw::size();
+                draw::set_font(Font::HelveticaItalic, size);
+                draw::draw_text2(&self.alias, x, y, w, h, Align::Left);
+                draw::set_font(font, size);
+            }
+            2 => draw::draw_text2(&self.filename, x, y, w, h, Align::Left),
+            3 => draw::draw_text2(&self.duration, x, y, w, h, Align::Right),
+            4 => draw::draw_text2(&self.data_size, x, y, w, h, Align::Right),
             _ => (),
         }
     }
@@ -55,6 +66,7 @@ impl TableRow for SongRow {
     fn value(&self, col: i32) -> Option<&str> {
         match col {
             0 => Some(&self.name),
+            1 => Some(&self.alias),
             _ => None,
         }
     }
@@ -75,6 +87,7 @@ impl TableMapping for SongMapping {
     fn headers() -> Vec<String> {
         vec![
             "Song Name".to_owned(),
+            "Alias".to_owned(),
             "Filename".to_owned(),
             "Duration".to_owned(),
             "Data size".to_owned(),
@@ -92,6 +105,12 @@ impl TableMapping for SongMapping {
     fn new_row(song: &data::Song) -> Self::RowType {
         RowWithStatus::new_unchecked(SongRow {
             name: song.name.as_str().to_owned(),
+            alias: song
+                .alias
+                .as_ref()
+                .map(Name::as_str)
+                .unwrap_or("")
+                .to_owned(),
             filename: song.source.as_str().to_owned(),
             duration: String::new(),
             data_size: String::new(),
@@ -111,6 +130,10 @@ impl TableMapping for SongMapping {
         };
 
         edited |= write_if_changed(&mut r.columns.name, song.name.as_str());
+        edited |= write_if_changed(
+            &mut r.columns.alias,
+            song.alias.as_ref().map(Name::as_str).unwrap_or(""),
+        );
         edited |= write_if_changed(&mut r.columns.filename, song.source.as_str());
 
         edited
@@ -119,14 +142,14 @@ impl TableMapping for SongMapping {
     fn table_event(event: TableEvent, row: usize, col: i32) -> TableAction {
         match event {
             TableEvent::Enter | TableEvent::EditorRequested | TableEvent::CellClicked => {
-                if col == 0 {
+                if col == 0 || col == 1 {
                     TableAction::OpenEditor
                 } else {
                     TableAction::None
                 }
             }
             TableEvent::DoubleClick => {
-                if col != 0 {
+                if col != 0 && col != 1 {
                     TableAction::Send(GuiMessage::OpenSongTab(row))
                 } else {
                     TableAction::None
@@ -138,6 +161,11 @@ impl TableMapping for SongMapping {
     fn commit_edited_value(index: usize, col: i32, value: String) -> Option<GuiMessage> {
         match col {
             0 => Name::try_new_lossy(value).map(|name| GuiMessage::SetProjectSongName(index, name)),
+            1 => match value.trim().is_empty() {
+                true => Some(GuiMessage::SetProjectSongAlias(index, None)),
+                false => Name::try_new_lossy(value)
+                    .map(|alias| GuiMessage::SetProjectSongAlias(index, Some(alias))),
+            },
             _ => None,
         }
     }
@@ -166,12 +194,21 @@ impl TableCompilerOutput for SongMapping {
     }
 }
 
+/// The list that is undone by the undo/redo commands
+#[derive(Clone, Copy)]
+pub enum ProjectUndoList {
+    Songs,
+    SfxExportOrder,
+}
+
 pub struct ProjectTab {
     group: Flex,
 
-    pub sfx_export_order: SfxExportOrderEditor,
+    sfx_export_order: SfxExportOrderEditor,
+    undo_list: ProjectUndoList,
 
     pub song_table: ListEditorTable<SongMapping>,
+    song_undo_stack: UndoStack<ListUndoAction<data::Song>>,
 
     sound_effects_file: Output,
 
@@ -233,7 +270,9 @@ impl ProjectTab {
         Self {
             group,
             sfx_export_order,
+            undo_list: ProjectUndoList::Songs,
             song_table,
+            song_undo_stack: UndoStack::default(),
             sound_effects_file,
             memory_stats,
         }
@@ -242,6 +281,35 @@ impl ProjectTab {
     pub fn sfx_file_changed(&mut self, source: &SourcePathBuf) {
         self.sound_effects_file.set_value(source.as_str());
     }
+
+    // The most recently edited list is undone
+    pub fn undo_list(&self) -> ProjectUndoList {
+        self.undo_list
+    }
+
+    pub fn process_sfx_export_order(
+        &mut self,
+        m: SfxExportOrderMessage,
+        data: &mut GuiSfxExportOrder,
+    ) -> Option<SfxExportOrderAction> {
+        let a = self.sfx_export_order.process(m, data)?;
+        self.undo_list = ProjectUndoList::SfxExportOrder;
+        Some(a)
+    }
+
+    pub fn undo_sfx_export_order(
+        &mut self,
+        data: &mut GuiSfxExportOrder,
+    ) -> Option<SfxExportOrderAction> {
+        self.sfx_export_order.undo(data)
+    }
+
+    pub fn redo_sfx_export_order(
+        &mut self,
+        data: &mut GuiSfxExportOrder,
+    ) -> Option<SfxExportOrderAction> {
+        self.sfx_export_order.redo(data)
+    }
 }
 
 impl ListWithCompilerOutputEditor<data::Song, Result<Arc<SongData>, ShortSongError>>
@@ -252,6 +320,14 @@ impl ListWithCompilerOutputEditor<data::Song, Result<Arc<SongData>, ShortSongErr
     fn table_mut(&mut self) -> &mut ListEditorTable<Self::TableMapping> {
         &mut self.song_table
     }
+
+    fn list_edited(&mut self, _action: &ListAction<data::Song>) {
+        self.undo_list = ProjectUndoList::Songs;
+    }
+
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<ListUndoAction<data::Song>>> {
+        Some(&mut self.song_undo_stack)
+    }
 }
 
 struct DefaultSfxFlagsWidget {
@@ -384,10 +460,15 @@ impl MemoryStats {
         self.sfx_out.set_text_color(Color::Red);
     }
 
-    fn update_free_space(&mut self) {
+    /// The size of the samples and sound effects in Audio-RAM
+    pub fn common_data_size(&self) -> usize {
         let common_data = self.samples_size + self.sfx_data_size;
         // Add 1 if odd (loader can only transfer a multiple of 2 bytes)
-        let common_data = common_data + (common_data % 2);
+        common_data + (common_data % 2)
+    }
+
+    fn update_free_space(&mut self) {
+        let common_data = self.common_data_size();
 
         let aram_used = Self::DRIVER_SIZE + common_data + self.largest_song_size;
 
@@ -0,0 +1,204 @@
+//! Project templates
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::helpers::{ch_units_to_width, input_height, label};
+use crate::GuiMessage;
+
+use compiler::data;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+extern crate fltk;
+use fltk::app;
+use fltk::button::Button;
+use fltk::frame::Frame;
+use fltk::group::Flex;
+use fltk::menu::Choice;
+use fltk::prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt, WindowExt};
+use fltk::window::Window;
+
+/// A sample file used by a project template (path is relative to the project file)
+pub struct TemplateSample {
+    path: &'static str,
+    data: &'static [u8],
+}
+
+macro_rules! template_sample {
+    ($path:literal) => {
+        TemplateSample {
+            path: $path,
+            data: include_bytes!(concat!("../../../examples/", $path)),
+        }
+    };
+}
+
+pub struct ProjectTemplate {
+    pub name: &'static str,
+    json: &'static str,
+    samples: &'static [TemplateSample],
+}
+
+impl ProjectTemplate {
+    pub fn project(&self) -> Result<data::Project, String> {
+        data::deserialize_project(self.json).map_err(|e| e.to_string())
+    }
+
+    /// Writes the template's samples to `dir`.
+    ///
+    /// Existing files are not overwritten.
+    pub fn write_samples(&self, dir: &Path) -> io::Result<()> {
+        for s in self.samples {
+            let path = dir.join(s.path);
+
+            if !path.try_exists()? {
+                if let Some(p) = path.parent() {
+                    fs::create_dir_all(p)?;
+                }
+                fs::write(&path, s.data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub const PROJECT_TEMPLATES: [ProjectTemplate; 3] = [
+    ProjectTemplate {
+        name: "RPG (strings, piano, choir)",
+        json: include_str!("../templates/rpg.terrificaudio"),
+        samples: &[
+            template_sample!("samples/nesdoug/ViolinC4.brr"),
+            template_sample!("samples/nesdoug/ViolinSpicatoB.brr"),
+            template_sample!("samples/nesdoug/PIANO_C5.brr"),
+            template_sample!("samples/sine.wav"),
+        ],
+    },
+    ProjectTemplate {
+        name: "Platformer (chiptune, percussion)",
+        json: include_str!("../templates/platformer.terrificaudio"),
+        samples: &[
+            template_sample!("samples/square.wav"),
+            template_sample!("samples/triangle.wav"),
+            template_sample!("samples/sawtooth.wav"),
+            template_sample!("samples/nesdoug/Conga.brr"),
+            template_sample!("samples/nesdoug/Cowbell.brr"),
+        ],
+    },
+    ProjectTemplate {
+        name: "Empty",
+        json: include_str!("../templates/empty.terrificaudio"),
+        samples: &[],
+    },
+];
+
+/// A small modal dialog that sends `GuiMessage::NewProjectFromTemplate` when a template is selected
+pub struct ProjectTemplateDialog {
+    window: Window,
+    choice: Choice,
+}
+
+impl ProjectTemplateDialog {
+    pub fn new(sender: app::Sender<GuiMessage>) -> Self {
+        let mut window = Window::default().with_label("New Project from Template");
+        window.set_size(ch_units_to_width(&window, 50), input_height(&window) * 4);
+        window.make_modal(true);
+
+        let margin = ch_units_to_width(&window, 1);
+
+        let mut group = Flex::default_fill().column();
+        group.set_pad(margin);
+        group.set_margin(margin);
+
+        let line_height = input_height(&group);
+
+        let l = label("Template:");
+        group.fixed(&l, line_height);
+
+        let mut choice = Choice::default();
+        group.fixed(&choice, line_height);
+        for t in &PROJECT_TEMPLATES {
+            choice.add_choice(t.name);
+        }
+        choice.set_value(0);
+
+        let mut button_row = Flex::default().row();
+        group.fixed(&button_row, line_height);
+
+        Frame::default();
+
+        let mut cancel_button = Button::default().with_label("Cancel");
+        let mut create_button = Button::default().with_label("Create");
+
+        let button_width = ch_units_to_width(&button_row, 10);
+        button_row.fixed(&cancel_button, button_width);
+        button_row.fixed(&create_button, button_width);
+
+        button_row.end();
+        group.end();
+        window.end();
+
+        cancel_button.set_callback({
+            let mut window = window.clone();
+            move |_| window.hide()
+        });
+
+        create_button.set_callback({
+            let mut window = window.clone();
+            let choice = choice.clone();
+            move |_| {
+                if let Ok(i) = usize::try_from(choice.value()) {
+                    sender.send(GuiMessage::NewProjectFromTemplate(i));
+                }
+                window.hide();
+            }
+        });
+
+        Self { window, choice }
+    }
+
+    pub fn show(&mut self) {
+        self.choice.set_value(0);
+        self.window.show();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use compiler::samples::build_sample_and_instrument_data;
+    use compiler::test_utils::TempDir;
+
+    #[test]
+    fn templates_are_valid_projects() {
+        for (i, t) in PROJECT_TEMPLATES.iter().enumerate() {
+            let dir = TempDir::new(&format!("project-template-{i}"));
+
+            let project = t.project().unwrap();
+            let contents = data::serialize_project(&project).unwrap();
+
+            let path = dir.path().join("project.terrificaudio");
+            fs::write(&path, contents).unwrap();
+            t.write_samples(dir.path()).unwrap();
+
+            let pf = data::load_project_file(&path).unwrap();
+            if pf.contents.instruments.is_empty() && pf.contents.samples.is_empty() {
+                // Nothing to compile (`validate_project_file_names()` rejects an empty project)
+                continue;
+            }
+
+            let pf = data::validate_project_file_names(pf)
+                .unwrap_or_else(|_| panic!("invalid template: {}", t.name));
+
+            assert!(
+                build_sample_and_instrument_data(&pf).is_ok(),
+                "cannot compile samples: {}",
+                t.name
+            );
+        }
+    }
+}
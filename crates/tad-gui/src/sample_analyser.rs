@@ -10,8 +10,9 @@ use crate::helpers::{ch_units_to_width, input_height, label_packed, InputForm, S
 use crate::sample_widgets::{BrrSettingsWidget, SampleWidgetEditor, SourceFileType};
 use crate::{GuiMessage, InstrumentOrSampleId};
 
-use brr::{BrrSample, MonoPcm16WaveFile};
+use brr::{BrrFilter, BrrSample, MonoPcm16WaveFile};
 use compiler::data::{self, BrrEvaluator, LoopSetting};
+use compiler::samples::PcmSampleOptions;
 
 use std::cell::RefCell;
 use std::cmp::{max, min};
@@ -44,6 +45,12 @@ const WAVEFORM_COLOR: Color = Color::from_rgb(0, 0, 255);
 
 const SPECTRUM_COLOR: Color = Color::from_rgb(128, 0, 128);
 const SPECTRUM_INST_FREQ_COLOR: Color = Color::from_rgb(128, 128, 128);
+const SPECTRUM_FILTER_RESPONSE_COLOR: Color = Color::from_rgb(0, 128, 0);
+
+/// Decibel range of the loop filter frequency response curve in the spectrum widget
+/// (BRR filter gain ranges from -11.7dB to +36.1dB)
+const FILTER_RESPONSE_MIN_DB: f64 = -12.0;
+const FILTER_RESPONSE_MAX_DB: f64 = 36.0;
 
 /// Sample rate to decode BRR samples at (S-DSP sample rate)
 const BRR_SAMPLE_RATE: u32 = 32000;
@@ -102,6 +109,7 @@ struct State {
     freq: f64,
     loop_setting: data::LoopSetting,
     evaluator: data::BrrEvaluator,
+    pcm_options: PcmSampleOptions,
 
     analysis: Option<SampleAnalysis>,
     analysis_error: Option<String>,
@@ -252,6 +260,7 @@ impl SampleAnalyserDialog {
             freq: 500.0,
             loop_setting: LoopSetting::None,
             evaluator: BrrEvaluator::Default,
+            pcm_options: PcmSampleOptions::default(),
 
             analysis: None,
             analysis_error: None,
@@ -383,6 +392,7 @@ impl SampleAnalyserDialog {
             inst.freq,
             inst.loop_setting.clone(),
             inst.evaluator,
+            PcmSampleOptions::from_instrument(inst),
         );
     }
 
@@ -393,6 +403,7 @@ impl SampleAnalyserDialog {
             0.0,
             s.loop_setting.clone(),
             s.evaluator,
+            PcmSampleOptions::default(),
         );
     }
 
@@ -479,6 +490,7 @@ impl State {
         freq: f64,
         loop_setting: LoopSetting,
         evaluator: BrrEvaluator,
+        pcm_options: PcmSampleOptions,
     ) {
         self.item_id = Some(id);
 
@@ -486,6 +498,7 @@ impl State {
         self.freq = freq;
         self.loop_setting = loop_setting;
         self.evaluator = evaluator;
+        self.pcm_options = pcm_options;
 
         self.source_out.set_value(self.source.as_str());
         self.freq_widget.set_value(freq);
@@ -520,6 +533,7 @@ impl State {
             self.source.clone(),
             self.loop_setting.clone(),
             self.evaluator,
+            self.pcm_options,
         ));
     }
 
@@ -742,6 +756,41 @@ impl State {
         draw::end_line();
 
         draw::pop_matrix();
+
+        if let Some(filter) = self.loop_setting.loop_filter() {
+            self.draw_spectrum_filter_response(filter, x, y, w, h);
+        }
+    }
+
+    fn draw_spectrum_filter_response(&self, filter: BrrFilter, x: i32, y: i32, w: i32, h: i32) {
+        draw::set_draw_color(SPECTRUM_FILTER_RESPONSE_COLOR);
+        draw::draw_text2(
+            &format!("Loop filter {} response", filter as u8),
+            x + 4,
+            y + 4,
+            w - 8,
+            h - 4,
+            Align::TopRight,
+        );
+
+        let x_scale = self.spectrum_x_scale;
+        let y_scale = f64::from(h) / (FILTER_RESPONSE_MAX_DB - FILTER_RESPONSE_MIN_DB);
+        let bottom = f64::from(y + h);
+
+        draw::begin_line();
+
+        for px in 0..=w {
+            let freq = f64::from(px) / x_scale;
+            let gain = filter.frequency_response(freq, BRR_SAMPLE_RATE.into());
+            let db = 20.0 * gain.log10();
+
+            draw::vertex(
+                f64::from(x + px),
+                bottom - (db - FILTER_RESPONSE_MIN_DB) * y_scale,
+            );
+        }
+
+        draw::end_line();
     }
 
     fn draw_waveform(&self) {
@@ -5,13 +5,14 @@
 // SPDX-License-Identifier: MIT
 
 use crate::compiler_thread::{CadOutput, InstrumentOutput, SampleOutput};
-use crate::helpers::ch_units_to_width;
+use crate::helpers::{ch_units_to_width, input_height, label, label_packed};
 use crate::list_editor::{LaVec, ListAction};
 use crate::InstrumentsAndSamplesData;
 
 use compiler::common_audio_data::CommonAudioData;
 use compiler::data::{Instrument, Name, Sample};
 use compiler::driver_constants::{addresses, COMMON_DATA_HEADER_SIZE};
+use compiler::echo::EchoEdl;
 use compiler::songs::{SongAramSize, BLANK_SONG_ARAM_SIZE};
 use fltk::table::TableContext;
 
@@ -23,12 +24,62 @@ use std::sync::Arc;
 use fltk::draw;
 use fltk::draw::LineStyle;
 use fltk::enums::{Align, Color, Font, FrameType};
+use fltk::frame::Frame;
 use fltk::group::Flex;
+use fltk::misc::Spinner;
 use fltk::prelude::*;
 use fltk::widget::Widget;
 
+/// Previews the size of the echo buffer for a given EDL.
+///
+/// Not saved in the project file (the echo buffer is set by each song).
+struct EchoParameterWidget {
+    edl: Spinner,
+    readout: Frame,
+}
+
+impl EchoParameterWidget {
+    fn new(parent: &mut Flex) -> Self {
+        let mut row = Flex::default().row();
+        parent.fixed(&row, input_height(&row));
+
+        let l = label_packed("Echo EDL preview:");
+        row.fixed(&l, l.width());
+
+        let mut edl = Spinner::default();
+        edl.set_tooltip("Echo delay (EDL) to preview in the memory graph");
+        edl.set_range(EchoEdl::MIN.as_u8().into(), EchoEdl::MAX.as_u8().into());
+        edl.set_step(1.0);
+        edl.set_value(0.0);
+        row.fixed(&edl, ch_units_to_width(&edl, 6));
+
+        let readout = label("");
+
+        row.end();
+
+        let mut out = Self { edl, readout };
+        out.update_readout();
+        out
+    }
+
+    fn value(&self) -> EchoEdl {
+        EchoEdl::try_from(self.edl.value() as u32).unwrap_or(EchoEdl::MIN)
+    }
+
+    fn update_readout(&mut self) {
+        let edl = self.value();
+
+        self.readout.set_label(&format!(
+            "Delay: {} ms — Uses {} bytes of ARAM",
+            edl.to_milliseconds(),
+            edl.buffer_size()
+        ));
+    }
+}
+
 pub struct SampleSizesWidget {
     graph_widget: Widget,
+    echo_widget: EchoParameterWidget,
     table: fltk::table::Table,
 
     font: Font,
@@ -55,19 +106,21 @@ const BRR_SAMPLES_COLOR: Color = Color::Yellow;
 const BRR_SAMPLES_LINE_COLOR: Color = Color::DarkYellow;
 const LARGEST_SONG_COLOR: Color = Color::Red;
 const LARGEST_SONG_ECHO_COLOR: Color = Color::Red;
+const ECHO_PREVIEW_COLOR: Color = Color::DarkRed;
 
 const AUDIO_DRIVER_SIZE: u16 = addresses::COMMON_DATA;
 const CAD_HEADER_END: u16 = addresses::COMMON_DATA + COMMON_DATA_HEADER_SIZE as u16;
 
 const N_COLUMNS: i32 = 2;
 
-const STAT_NAMES: [(&str, Color); 6] = [
+const STAT_NAMES: [(&str, Color); 7] = [
     ("Audio Driver", AUDIO_DRIVER_COLOR),
     ("Largest Song", LARGEST_SONG_COLOR),
     ("Common Audio Data Header", CAD_HEADER_COLOR),
     ("Sound Effects", SFX_COLOR),
     ("Pitch Table", PITCH_TABLE_COLOR),
     ("Instruments and Samples", BRR_SAMPLES_COLOR),
+    ("Echo Buffer Preview", ECHO_PREVIEW_COLOR),
 ];
 const N_STAT_ROWS: usize = STAT_NAMES.len();
 
@@ -77,6 +130,7 @@ const CAD_HEADER_IDX: usize = 2;
 const SFX_IDX: usize = 3;
 const PITCH_TABLE_IDX: usize = 4;
 const INST_SAMPLES_IDX: usize = 5;
+const ECHO_PREVIEW_IDX: usize = 6;
 
 struct GraphData {
     dir_table_range: Range<u16>,
@@ -117,6 +171,8 @@ impl SampleSizesWidget {
         let graph_widget = Widget::default();
         parent.fixed(&graph_widget, graph_height);
 
+        let echo_widget = EchoParameterWidget::new(parent);
+
         let mut table = fltk::table::Table::default();
         table.set_tab_cell_nav(true);
 
@@ -141,6 +197,7 @@ impl SampleSizesWidget {
             font_size: table.label_size(),
 
             graph_widget,
+            echo_widget,
             table,
             graph_data: None,
             largest_song: BLANK_SONG_ARAM_SIZE,
@@ -169,6 +226,16 @@ impl SampleSizesWidget {
             s.update_table_size();
 
             s.stat_sizes[DRIVER_SIZE_IDX] = size_string(AUDIO_DRIVER_SIZE);
+            s.stat_sizes[ECHO_PREVIEW_IDX] = size_string(s.echo_widget.value().buffer_size());
+
+            s.echo_widget.edl.set_callback({
+                let state = state.clone();
+                move |_| {
+                    if let Ok(mut s) = state.try_borrow_mut() {
+                        s.echo_preview_changed();
+                    }
+                }
+            });
 
             s.graph_widget.draw({
                 let state = state.clone();
@@ -199,6 +266,15 @@ impl SampleSizesWidget {
         }
     }
 
+    fn echo_preview_changed(&mut self) {
+        self.echo_widget.update_readout();
+
+        self.stat_sizes[ECHO_PREVIEW_IDX] = size_string(self.echo_widget.value().buffer_size());
+
+        self.graph_widget.redraw();
+        self.table.redraw();
+    }
+
     pub fn cad_changed(&mut self, cad: &CadOutput) {
         match cad {
             CadOutput::None | CadOutput::Err(_) => {
@@ -325,7 +401,7 @@ impl SampleSizesWidget {
     ) {
         if let Some(d) = self.no_cad_instruments.get_mut(index) {
             match compiler_output {
-                Some(Ok(i)) => d.1 = usize_string(i.0),
+                Some(Ok(i)) => d.1 = usize_string(i.data_size),
                 Some(Err(_)) => "ERROR".clone_into(&mut d.1),
                 None => d.1.clear(),
             }
@@ -374,6 +450,10 @@ impl SampleSizesWidget {
                 draw::draw_yxline(addr_x(addr), y1, y2);
             };
 
+            // Echo buffer preview (drawn behind the largest song's echo buffer)
+            let echo_preview_start = 0xffff - self.echo_widget.value().buffer_size() + 1;
+            addr_rect(echo_preview_start, u16::MAX, ECHO_PREVIEW_COLOR);
+
             // Largest song
             let song_start = d.song_start;
             let song_end = song_start.checked_add(self.largest_song.data_size);
@@ -403,6 +483,7 @@ impl SampleSizesWidget {
             addr_line(d.sfx_range.end);
             addr_line(d.pitch_table.end);
             addr_line(d.instruments_samples_range.end);
+            addr_line(echo_preview_start);
 
             if let Some(song_end) = song_end {
                 addr_line(song_end);
@@ -4,16 +4,19 @@
 //
 // SPDX-License-Identifier: MIT
 
+use crate::envelope_widget::AdsrGainCanvas;
 use crate::helpers::{is_input_done_event, InputForm, InputHelper};
 
 use compiler::data::{self, BrrEvaluator, LoopSetting};
-use compiler::envelope::{Adsr, Envelope, Gain};
+use compiler::envelope::{Adsr, Envelope, Gain, DSP_SAMPLE_RATE};
 use compiler::path::SourcePathBuf;
-use compiler::samples::{BRR_EXTENSION, WAV_EXTENSION};
+use compiler::samples::{is_pcm_source, BRR_EXTENSION};
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use fltk::enums::{Align, Event};
+use fltk::frame::Frame;
 use fltk::input::{Input, IntInput};
 use fltk::menu::Choice;
 use fltk::prelude::*;
@@ -27,8 +30,11 @@ pub enum SourceFileType {
 
 impl SourceFileType {
     pub fn from_source(source: &SourcePathBuf) -> Self {
+        if is_pcm_source(source) {
+            return SourceFileType::Wav;
+        }
+
         match source.extension() {
-            Some(WAV_EXTENSION) => SourceFileType::Wav,
             Some(BRR_EXTENSION) => SourceFileType::Brr,
             _ => SourceFileType::Unknown,
         }
@@ -459,19 +465,25 @@ struct SampleEnvelopeWidgetState {
 pub struct SampleEnvelopeWidget {
     choice: Choice,
     argument: Input,
+    estimate: Frame,
+    canvas: Option<AdsrGainCanvas>,
 
     state: Rc<RefCell<SampleEnvelopeWidgetState>>,
 }
 
 impl SampleEnvelopeWidget {
     pub fn new(form: &mut InputForm) -> Self {
-        let (mut choice, argument) = form.add_two_inputs::<Choice, Input>("Envelope:", 12);
+        let (mut choice, argument, mut estimate) =
+            form.add_three_inputs::<Choice, Input, Frame>("Envelope:", 12, 16);
 
         choice.add_choice(EnvelopeChoice::CHOICES);
+        estimate.set_align(Align::Left | Align::Inside);
 
         Self {
             choice,
             argument,
+            estimate,
+            canvas: None,
             state: Rc::new(RefCell::new(SampleEnvelopeWidgetState {
                 prev_adsr: DEFAULT_ADSR.to_gui_string(),
                 prev_gain: DEFAULT_GAIN.to_gui_string(),
@@ -479,22 +491,54 @@ impl SampleEnvelopeWidget {
         }
     }
 
+    /// Adds an envelope visualizer below the envelope input
+    pub fn add_canvas(&mut self, form: &mut InputForm) {
+        self.canvas = Some(AdsrGainCanvas::new(form));
+    }
+
+    fn parse_envelope(choice: &Choice, value: String) -> Option<Envelope> {
+        match EnvelopeChoice::read_widget(choice) {
+            Some(EnvelopeChoice::Adsr) => InputHelper::parse(value).map(Envelope::Adsr),
+            Some(EnvelopeChoice::Gain) => InputHelper::parse(value).map(Envelope::Gain),
+            None => None,
+        }
+    }
+
+    fn update_canvas(&mut self, envelope: Option<Envelope>) {
+        if let Some(c) = &mut self.canvas {
+            c.set_envelope(envelope);
+        }
+    }
+
     pub fn set_editor<E: SampleWidgetEditor + 'static>(&mut self, editor: Rc<RefCell<E>>) {
         self.choice.set_callback({
             let mut argument = self.argument.clone();
+            let mut canvas = self.canvas.clone();
             let state = self.state.clone();
             let editor = editor.clone();
             move |choice| {
                 let mut e = editor.borrow_mut();
                 Self::on_choice_changed(choice, &mut argument, &state.borrow());
+                if let Some(c) = &mut canvas {
+                    c.set_envelope(Self::parse_envelope(choice, argument.value()));
+                }
                 e.on_finished_editing();
             }
         });
 
         self.argument.handle({
-            move |_, ev| {
+            let choice = self.choice.clone();
+            let mut canvas = self.canvas.clone();
+            move |argument, ev| {
                 if is_input_done_event(ev) {
                     editor.borrow_mut().on_finished_editing();
+                } else if ev == Event::KeyUp {
+                    // Update the canvas as the user types
+                    if let Some(c) = &mut canvas {
+                        if let Some(e) = Self::parse_envelope(&choice, argument.value()) {
+                            c.set_envelope(Some(e));
+                        }
+                    }
                 }
                 false
             }
@@ -508,6 +552,8 @@ impl SampleEnvelopeWidget {
             Some(EnvelopeChoice::Adsr) => match InputHelper::parse(value.clone()) {
                 Some(adsr) => {
                     self.state.borrow_mut().prev_adsr = value;
+                    self.estimate.set_label("");
+                    self.update_canvas(Some(Envelope::Adsr(adsr)));
                     Some(Envelope::Adsr(adsr))
                 }
                 None => {
@@ -518,6 +564,8 @@ impl SampleEnvelopeWidget {
             Some(EnvelopeChoice::Gain) => match InputHelper::parse(value.clone()) {
                 Some(gain) => {
                     self.state.borrow_mut().prev_gain = value;
+                    self.update_gain_estimate(gain);
+                    self.update_canvas(Some(Envelope::Gain(gain)));
                     Some(Envelope::Gain(gain))
                 }
                 None => {
@@ -532,19 +580,36 @@ impl SampleEnvelopeWidget {
     pub fn clear_value(&mut self) {
         self.choice.set_value(-1);
         self.argument.set_value("");
+        self.estimate.set_label("");
+        self.update_canvas(None);
+    }
+
+    fn update_gain_estimate(&mut self, gain: Gain) {
+        match gain.estimated_ticks_to_silence(Gain::FIXED_GAIN_MASK) {
+            Some(t) => {
+                let ms = u64::from(t) * 1000 / u64::from(DSP_SAMPLE_RATE);
+                self.estimate
+                    .set_label(&format!("Estimated release: ~{}ms", ms));
+            }
+            None => self.estimate.set_label(""),
+        }
     }
 
     pub fn set_value(&mut self, envelope: &Envelope) {
+        self.update_canvas(Some(*envelope));
+
         match envelope {
             Envelope::Adsr(adsr) => {
                 self.choice.set_value(EnvelopeChoice::Adsr.to_i32());
                 InputHelper::set_widget_value(&mut self.argument, adsr);
+                self.estimate.set_label("");
 
                 self.state.borrow_mut().prev_adsr = self.argument.value();
             }
             Envelope::Gain(gain) => {
                 self.choice.set_value(EnvelopeChoice::Gain.to_i32());
                 InputHelper::set_widget_value(&mut self.argument, gain);
+                self.update_gain_estimate(*gain);
 
                 self.state.borrow_mut().prev_gain = self.argument.value();
             }
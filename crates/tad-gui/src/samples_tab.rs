@@ -5,14 +5,16 @@
 // SPDX-License-Identifier: MIT
 
 use crate::compiler_thread::{
-    CadOutput, CombineSamplesError, InstrumentOutput, ItemId, SampleOutput,
+    CadOutput, CombineSamplesError, InstrumentOutput, ItemId, SampleOutput, SongOutput,
 };
 use crate::list_editor::{
-    tables_for_list_pair, ListAction, ListEditorTable, ListWithCompilerOutput,
-    ListWithCompilerOutputEditor,
+    tables_for_list_pair, LaVec, ListAction, ListEditorTable, ListUndoAction,
+    ListWithCompilerOutput, ListWithCompilerOutputEditor,
 };
 use crate::sample_sizes_widget::SampleSizesWidget;
 use crate::tabs::{FileType, Tab};
+use crate::undo::UndoStack;
+use crate::waveform_preview::WaveformPreview;
 use crate::GuiMessage;
 use crate::{helpers::*, InstrumentsAndSamplesData};
 
@@ -20,6 +22,7 @@ use crate::instrument_editor::{InstrumentEditor, InstrumentMapping, TestInstrume
 use crate::sample_editor::{SampleEditor, SampleMapping, TestSampleWidget};
 
 use compiler::data::{self, Instrument};
+use compiler::errors::InstrumentPitchWarning;
 use compiler::songs::SongAramSize;
 use fltk::button::Button;
 
@@ -33,6 +36,8 @@ use fltk::group::{Flex, Wizard};
 use fltk::prelude::*;
 use fltk::text::{TextBuffer, TextDisplay, WrapMode};
 
+const WARNING_COLOR: Color = Color::DarkYellow;
+
 #[derive(PartialEq)]
 enum SelectedEditor {
     CombinedSamplesResult,
@@ -40,16 +45,30 @@ enum SelectedEditor {
     Sample(ItemId),
 }
 
+/// The list that is undone by the undo/redo commands
+#[derive(Clone, Copy)]
+pub enum UndoList {
+    Instruments,
+    Samples,
+}
+
 pub struct SamplesTab {
     group: Flex,
 
     selected_editor: SelectedEditor,
+    undo_list: UndoList,
+    inst_undo_stack: UndoStack<ListUndoAction<Instrument>>,
+    sample_undo_stack: UndoStack<ListUndoAction<data::Sample>>,
     cad_output: CadOutput,
+    instrument_pitch_warnings: Vec<InstrumentPitchWarning>,
 
     sample_sizes_button: Button,
     inst_table: ListEditorTable<InstrumentMapping>,
     sample_table: ListEditorTable<SampleMapping>,
 
+    instrument_sizes: LaVec<Option<usize>>,
+    instrument_total: Frame,
+
     editor_wizard: Wizard,
 
     sample_sizes_group: Flex,
@@ -58,6 +77,7 @@ pub struct SamplesTab {
     instrument_group: Flex,
     instrument_editor: Rc<RefCell<InstrumentEditor>>,
     test_instrument_widget: Rc<RefCell<TestInstrumentWidget>>,
+    waveform_preview: WaveformPreview,
 
     sample_group: Flex,
     sample_editor: Rc<RefCell<SampleEditor>>,
@@ -100,6 +120,9 @@ impl SamplesTab {
         let (inst_table, sample_table) =
             tables_for_list_pair(&mut sidebar, sender.clone(), instruments_and_samples);
 
+        let instrument_total = label("Instruments total: 0 bytes");
+        sidebar.fixed(&instrument_total, input_height(&instrument_total));
+
         sidebar.end();
 
         let mut main_group = Flex::default().column();
@@ -135,6 +158,8 @@ impl SamplesTab {
             test_instrument_widget
         };
 
+        let waveform_preview = WaveformPreview::new();
+
         instrument_group.end();
 
         let mut sample_group = Flex::default().column().size_of_parent();
@@ -169,8 +194,20 @@ impl SamplesTab {
         Self {
             group,
             selected_editor: SelectedEditor::CombinedSamplesResult,
+            undo_list: UndoList::Instruments,
+            inst_undo_stack: UndoStack::default(),
+            sample_undo_stack: UndoStack::default(),
             cad_output: CadOutput::None,
+            instrument_pitch_warnings: Vec::new(),
             sample_sizes_button,
+            instrument_sizes: LaVec::from_vec(
+                instruments_and_samples
+                    .list1()
+                    .item_iter()
+                    .map(|_| None)
+                    .collect(),
+            ),
+            instrument_total,
             sample_sizes_group,
             sample_sizes_widget,
             inst_table,
@@ -179,6 +216,7 @@ impl SamplesTab {
             instrument_group,
             instrument_editor,
             test_instrument_widget,
+            waveform_preview,
             sample_group,
             sample_editor,
             test_sample_widget,
@@ -222,8 +260,20 @@ impl SamplesTab {
                 }
             }
             CadOutput::NoSfx(..) | CadOutput::SfxBuffer(..) | CadOutput::WithSfx(..) => {
-                self.sample_sizes_button.set_label("All OK");
-                self.sample_sizes_button.set_label_color(Color::Foreground);
+                match self.instrument_pitch_warnings.len() {
+                    0 => {
+                        self.sample_sizes_button.set_label("All OK");
+                        self.sample_sizes_button.set_label_color(Color::Foreground);
+                    }
+                    1 => {
+                        self.sample_sizes_button.set_label("1 warning");
+                        self.sample_sizes_button.set_label_color(WARNING_COLOR);
+                    }
+                    n => {
+                        self.sample_sizes_button.set_label(&format!("{n} warnings"));
+                        self.sample_sizes_button.set_label_color(WARNING_COLOR);
+                    }
+                }
             }
         }
 
@@ -240,10 +290,37 @@ impl SamplesTab {
         }
     }
 
+    pub fn set_instrument_pitch_warnings(&mut self, warnings: Vec<InstrumentPitchWarning>) {
+        self.instrument_pitch_warnings = warnings;
+
+        if matches!(self.selected_editor, SelectedEditor::CombinedSamplesResult) {
+            self.update_sample_sizes_widget_and_console();
+        }
+    }
+
+    fn update_instrument_total(&mut self) {
+        let total: usize = self.instrument_sizes.iter().flatten().sum();
+
+        self.instrument_total
+            .set_label(&format!("Instruments total: {total} bytes"));
+    }
+
+    pub fn song_compiled(&mut self, song_id: ItemId, co: &SongOutput) {
+        self.instrument_editor
+            .borrow_mut()
+            .song_compiled(song_id, co);
+    }
+
     pub fn set_largest_song(&mut self, s: SongAramSize) {
         self.sample_sizes_widget.borrow_mut().set_largest_song(s);
     }
 
+    // The undo list is not changed when the sample sizes widget is shown so a removed item can
+    // be restored.
+    pub fn undo_list(&self) -> UndoList {
+        self.undo_list
+    }
+
     pub fn show_sample_sizes_widget(&mut self) {
         self.inst_table.clear_selected_row();
         self.sample_table.clear_selected_row();
@@ -270,10 +347,26 @@ impl SamplesTab {
             | &CadOutput::NoSfx(..)
             | CadOutput::SfxBuffer(..)
             | CadOutput::WithSfx(..) => {
-                self.console_buffer.set_text("");
+                if self.instrument_pitch_warnings.is_empty() {
+                    self.console_buffer.set_text("");
+                } else {
+                    let mut text = "WARNING:".to_owned();
+                    for w in &self.instrument_pitch_warnings {
+                        text += &format!("\n\t{}", w);
+                    }
+
+                    self.console_buffer.set_text(&text);
+                    self.console.set_text_color(WARNING_COLOR);
+                    self.console.scroll(0, 0);
+                }
             }
             CadOutput::Err(e) => {
-                self.console_buffer.set_text(&e.to_string());
+                let mut text = e.to_string();
+                for w in &self.instrument_pitch_warnings {
+                    text += &format!("\n\t{}", w);
+                }
+
+                self.console_buffer.set_text(&text);
                 self.console.set_text_color(Color::Red);
                 self.console.scroll(0, 0);
             }
@@ -288,11 +381,14 @@ impl SamplesTab {
         match compiler_output {
             None => {
                 self.console_buffer.set_text("");
+                self.waveform_preview.clear();
             }
             Some(Ok(o)) => {
                 self.console_buffer
-                    .set_text(&format!("BRR Sample size: {} bytes", o.0));
+                    .set_text(&format!("BRR Sample size: {} bytes", o.data_size));
                 self.console.set_text_color(Color::Foreground);
+                self.waveform_preview
+                    .set_brr_data(&o.brr_data, o.loop_offset);
             }
             Some(Err(errors)) => {
                 let mut text = "ERROR:".to_owned();
@@ -307,6 +403,7 @@ impl SamplesTab {
                 self.console_buffer.set_text(&text);
                 self.console.set_text_color(Color::Red);
                 self.console.scroll(0, 0);
+                self.waveform_preview.clear();
             }
         }
     }
@@ -354,12 +451,20 @@ impl ListWithCompilerOutputEditor<Instrument, InstrumentOutput> for SamplesTab {
         self.sample_sizes_widget
             .borrow_mut()
             .instrument_edited(action);
+
+        self.instrument_sizes
+            .process_map(action, |_| None, |s, _| *s = None);
+        self.update_instrument_total();
     }
 
     fn item_edited(&mut self, id: ItemId, value: &Instrument) {
         self.instrument_editor.borrow_mut().item_edited(id, value)
     }
 
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<ListUndoAction<Instrument>>> {
+        Some(&mut self.inst_undo_stack)
+    }
+
     fn set_compiler_output(
         &mut self,
         index: usize,
@@ -370,6 +475,14 @@ impl ListWithCompilerOutputEditor<Instrument, InstrumentOutput> for SamplesTab {
             .borrow_mut()
             .instrument_compiled(index, compiler_output);
 
+        if let Some(s) = self.instrument_sizes.get_mut(index) {
+            *s = match compiler_output {
+                Some(Ok(o)) => Some(o.data_size),
+                Some(Err(_)) | None => None,
+            };
+            self.update_instrument_total();
+        }
+
         let sel_id = self.instrument_editor.borrow().selected_id();
         if sel_id == Some(id) {
             self.selected_instrument_output_changed(compiler_output);
@@ -383,6 +496,7 @@ impl ListWithCompilerOutputEditor<Instrument, InstrumentOutput> for SamplesTab {
         match instruments.get_selected_row(&self.inst_table) {
             Some((id, inst, co)) => {
                 self.sample_table.clear_selected_row();
+                self.undo_list = UndoList::Instruments;
 
                 if self.selected_editor != SelectedEditor::Instrument(id) {
                     self.selected_editor = SelectedEditor::Instrument(id);
@@ -421,6 +535,10 @@ impl ListWithCompilerOutputEditor<data::Sample, SampleOutput> for SamplesTab {
         self.test_sample_widget.borrow_mut().item_edited(id, value);
     }
 
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<ListUndoAction<data::Sample>>> {
+        Some(&mut self.sample_undo_stack)
+    }
+
     fn set_compiler_output(
         &mut self,
         index: usize,
@@ -444,6 +562,7 @@ impl ListWithCompilerOutputEditor<data::Sample, SampleOutput> for SamplesTab {
         match samples.get_selected_row(&self.sample_table) {
             Some((id, sample, co)) => {
                 self.inst_table.clear_selected_row();
+                self.undo_list = UndoList::Samples;
 
                 if self.selected_editor != SelectedEditor::Sample(id) {
                     self.selected_editor = SelectedEditor::Sample(id);
@@ -10,7 +10,8 @@ use crate::list_editor::{
     LaVec, ListAction, ListEditorTable, ListMessage, TableAction, TableMapping,
 };
 use crate::names::{deduplicate_item_name, deduplicate_names};
-use crate::tables::{SimpleRow, TableEvent};
+use crate::tables::{SimpleRow, TableEvent, TableRow};
+use crate::undo::UndoStack;
 use crate::GuiMessage;
 
 use compiler::data::Name;
@@ -29,20 +30,54 @@ impl SfxId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SfxExportOrderAction {
     action: ListAction<Name>,
     n_high_priority_sfx: usize,
     low_priority_index: usize,
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum Priority {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Priority {
     High,
     Normal,
     Low,
 }
 
+impl Priority {
+    fn dnd_str(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Normal => "normal",
+            Self::Low => "low",
+        }
+    }
+
+    fn from_dnd_str(s: &str) -> Option<Self> {
+        match s {
+            "high" => Some(Self::High),
+            "normal" => Some(Self::Normal),
+            "low" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
+const DND_TEXT_PREFIX: &str = "tad-sfx-export-order:";
+
+fn sfx_dnd_text(priority: Priority, name: &str) -> String {
+    format!("{DND_TEXT_PREFIX}{}:{name}", priority.dnd_str())
+}
+
+fn parse_sfx_dnd_text(text: &str) -> Option<(Priority, Name)> {
+    let (priority, name) = text.strip_prefix(DND_TEXT_PREFIX)?.split_once(':')?;
+
+    Some((
+        Priority::from_dnd_str(priority)?,
+        Name::try_from(name.to_owned()).ok()?,
+    ))
+}
+
 #[derive(Debug, Clone)]
 pub struct GuiSfxExportOrder {
     export_order: LaVec<Name>,
@@ -122,6 +157,73 @@ impl GuiSfxExportOrder {
         &self.export_order[self.low_priority_index..]
     }
 
+    fn priority_sfx(&self, p: Priority) -> &[Name] {
+        &self.export_order[self.priority_range(p)]
+    }
+
+    fn move_between_priorities_action(
+        &self,
+        index: usize,
+        from: Priority,
+        to: Priority,
+    ) -> Option<SfxExportOrderAction> {
+        let from_range = self.priority_range(from);
+        let to_range = self.priority_range(to);
+
+        if from == to || index >= from_range.len() {
+            return None;
+        }
+
+        let from_index = index + from_range.start;
+
+        let to_index = if from_index < to_range.start {
+            to_range.start - 1
+        } else {
+            to_range.end
+        };
+
+        Some(SfxExportOrderAction {
+            action: ListAction::Move(from_index, to_index),
+            n_high_priority_sfx: if from == Priority::High {
+                self.n_high_priority_sfx - 1
+            } else if to == Priority::High {
+                self.n_high_priority_sfx + 1
+            } else {
+                self.n_high_priority_sfx
+            },
+            low_priority_index: if from == Priority::Low {
+                self.low_priority_index + 1
+            } else if to == Priority::Low {
+                self.low_priority_index - 1
+            } else {
+                self.low_priority_index
+            },
+        })
+    }
+
+    /// Returns the action that reverses `a`.
+    ///
+    /// MUST be called before `a` is processed.
+    fn inverse_action(&self, a: &SfxExportOrderAction) -> Option<SfxExportOrderAction> {
+        let action = match a.action {
+            ListAction::Add(index, _) => ListAction::Remove(index),
+            ListAction::Remove(index) => {
+                ListAction::Add(index, self.export_order.get(index)?.clone())
+            }
+            ListAction::Edit(index, _) => {
+                ListAction::Edit(index, self.export_order.get(index)?.clone())
+            }
+            ListAction::Move(from, to) => ListAction::Move(to, from),
+            ListAction::None | ListAction::AddMultiple(..) => return None,
+        };
+
+        Some(SfxExportOrderAction {
+            action,
+            n_high_priority_sfx: self.n_high_priority_sfx,
+            low_priority_index: self.low_priority_index,
+        })
+    }
+
     fn table_max_sizes(&self) -> (usize, usize, usize) {
         let n_high = self.n_high_priority_sfx;
         let n_normal = self.normal_priority_range().len();
@@ -174,6 +276,8 @@ pub enum SfxExportOrderMessage {
     MoveFromNormalToLow(usize),
     MoveFromLowToHigh(usize),
     MoveFromLowToNormal(usize),
+    /// Sent when a sound effect is dragged and dropped onto a different priority table
+    MoveToOtherPriority(Name, Priority, Priority),
 }
 
 pub trait SfxEoMapping {
@@ -265,10 +369,26 @@ where
     }
 }
 
+fn sfx_drop_callback(to: Priority, sender: &Sender<GuiMessage>) -> impl Fn(&str) + 'static {
+    let sender = sender.clone();
+
+    move |text| {
+        if let Some((from, name)) = parse_sfx_dnd_text(text) {
+            if from != to {
+                sender.send(GuiMessage::EditSfxExportOrder(
+                    SfxExportOrderMessage::MoveToOtherPriority(name, from, to),
+                ));
+            }
+        }
+    }
+}
+
 pub struct SfxExportOrderEditor {
     high_priority: ListEditorTable<HighPrioritySfxEoMapping>,
     normal_priority: ListEditorTable<NormalSfxEoMapping>,
     low_priority: ListEditorTable<LowPrioritySfxEoMapping>,
+
+    undo_stack: UndoStack<SfxExportOrderAction>,
 }
 
 impl SfxExportOrderEditor {
@@ -337,10 +457,27 @@ impl SfxExportOrderEditor {
             |i| GuiMessage::EditSfxExportOrder(SfxExportOrderMessage::MoveFromLowToNormal(i)),
         );
 
+        high_priority.enable_sfx_eo_drag_and_drop(
+            |row| row.value(0).map(|name| sfx_dnd_text(Priority::High, name)),
+            sfx_drop_callback(Priority::High, &sender),
+        );
+        normal_priority.enable_sfx_eo_drag_and_drop(
+            |row| {
+                row.value(0)
+                    .map(|name| sfx_dnd_text(Priority::Normal, name))
+            },
+            sfx_drop_callback(Priority::Normal, &sender),
+        );
+        low_priority.enable_sfx_eo_drag_and_drop(
+            |row| row.value(0).map(|name| sfx_dnd_text(Priority::Low, name)),
+            sfx_drop_callback(Priority::Low, &sender),
+        );
+
         Self {
             high_priority,
             normal_priority,
             low_priority,
+            undo_stack: UndoStack::default(),
         }
     }
 
@@ -421,6 +558,17 @@ impl SfxExportOrderEditor {
                 }
                 _ => None,
             },
+            ListMessage::CloneTo(index, i) => match (slice.get(index), data.can_add_one()) {
+                (Some(name), true) if i <= slice.len() => {
+                    let name = deduplicate_name(name.clone(), None);
+
+                    table.sfx_eo_edited(&ListAction::Add(i, name.clone()));
+                    table.set_selected_row(i);
+
+                    Some((ListAction::Add(i + eo_offset, name), 1))
+                }
+                _ => None,
+            },
 
             ListMessage::Remove(index) => {
                 if index < slice.len() {
@@ -460,6 +608,13 @@ impl SfxExportOrderEditor {
                     None
                 }
             }
+            ListMessage::Move(from, to) => {
+                if from != to && from < slice.len() && to < slice.len() {
+                    Self::process_move(table, range, from, to)
+                } else {
+                    None
+                }
+            }
 
             // Not supported
             ListMessage::AddMultiple(..) => None,
@@ -478,41 +633,21 @@ impl SfxExportOrderEditor {
         M1: SfxEoMapping,
         M2: SfxEoMapping,
     {
+        let action = data.move_between_priorities_action(index, from, to)?;
+
         let from_range = data.priority_range(from);
         let to_range = data.priority_range(to);
 
-        let sfx_list = &data.export_order[from_range.clone()];
-
-        let sfx = sfx_list.get(index)?;
+        let sfx = data.priority_sfx(from).get(index)?;
         from_table.sfx_eo_edited(&ListAction::Remove(index));
 
-        let from_index = index + from_range.start;
-
-        let to_index = if from_index < to_range.start {
+        if index + from_range.start < to_range.start {
             to_table.sfx_eo_edited(&ListAction::Add(0, sfx.clone()));
-            to_range.start - 1
         } else {
             to_table.sfx_eo_edited(&ListAction::Add(to_range.len(), sfx.clone()));
-            to_range.end
-        };
+        }
 
-        Some(SfxExportOrderAction {
-            action: ListAction::Move(from_index, to_index),
-            n_high_priority_sfx: if from == Priority::High {
-                data.n_high_priority_sfx - 1
-            } else if to == Priority::High {
-                data.n_high_priority_sfx + 1
-            } else {
-                data.n_high_priority_sfx
-            },
-            low_priority_index: if from == Priority::Low {
-                data.low_priority_index + 1
-            } else if to == Priority::Low {
-                data.low_priority_index - 1
-            } else {
-                data.low_priority_index
-            },
-        })
+        Some(action)
     }
 
     pub fn process(
@@ -629,15 +764,194 @@ impl SfxExportOrderEditor {
                 Priority::Normal,
                 &mut self.normal_priority,
             )?,
+            SfxExportOrderMessage::MoveToOtherPriority(name, from, to) => {
+                let index = data.priority_sfx(from).iter().position(|n| n == &name)?;
+
+                let m = match (from, to) {
+                    (Priority::High, Priority::Normal) => {
+                        SfxExportOrderMessage::MoveFromHighToNormal(index)
+                    }
+                    (Priority::High, Priority::Low) => {
+                        SfxExportOrderMessage::MoveFromHighToLow(index)
+                    }
+                    (Priority::Normal, Priority::High) => {
+                        SfxExportOrderMessage::MoveFromNormalToHigh(index)
+                    }
+                    (Priority::Normal, Priority::Low) => {
+                        SfxExportOrderMessage::MoveFromNormalToLow(index)
+                    }
+                    (Priority::Low, Priority::High) => {
+                        SfxExportOrderMessage::MoveFromLowToHigh(index)
+                    }
+                    (Priority::Low, Priority::Normal) => {
+                        SfxExportOrderMessage::MoveFromLowToNormal(index)
+                    }
+                    (Priority::High, Priority::High)
+                    | (Priority::Normal, Priority::Normal)
+                    | (Priority::Low, Priority::Low) => return None,
+                };
+                return self.process(m, data);
+            }
         };
 
+        let inverse = data.inverse_action(&a);
+
         data.process(&a);
 
+        match inverse {
+            Some(inverse) => self.undo_stack.push(a.clone(), inverse),
+            None => self.undo_stack.clear(),
+        }
+
+        self.update_max_sizes(data);
+
+        Some(a)
+    }
+
+    pub fn undo(&mut self, data: &mut GuiSfxExportOrder) -> Option<SfxExportOrderAction> {
+        let a = self.undo_stack.undo()?;
+        Some(self.process_undo_action(a, data))
+    }
+
+    pub fn redo(&mut self, data: &mut GuiSfxExportOrder) -> Option<SfxExportOrderAction> {
+        let a = self.undo_stack.redo()?;
+        Some(self.process_undo_action(a, data))
+    }
+
+    fn process_undo_action(
+        &mut self,
+        a: SfxExportOrderAction,
+        data: &mut GuiSfxExportOrder,
+    ) -> SfxExportOrderAction {
+        data.process(&a);
+
+        self.high_priority.sfx_eo_replace(data.high_priority_sfx());
+        self.normal_priority
+            .sfx_eo_replace(data.normal_priority_sfx());
+        self.low_priority.sfx_eo_replace(data.low_priority_sfx());
+        self.update_max_sizes(data);
+
+        a
+    }
+
+    fn update_max_sizes(&mut self, data: &GuiSfxExportOrder) {
         let (max_high, max_normal, max_low) = data.table_max_sizes();
         self.high_priority.set_max_size(max_high);
         self.normal_priority.set_max_size(max_normal);
         self.low_priority.set_max_size(max_low);
+    }
+}
 
-        Some(a)
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(n: &[&str]) -> Vec<Name> {
+        n.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn move_sfx(eo: &mut GuiSfxExportOrder, name: &str, from: Priority, to: Priority) {
+        let index = eo
+            .priority_sfx(from)
+            .iter()
+            .position(|n| n.as_str() == name)
+            .unwrap();
+
+        let a = eo.move_between_priorities_action(index, from, to).unwrap();
+        eo.process(&a);
+    }
+
+    #[test]
+    fn move_normal_to_low_and_back() {
+        let (mut eo, _) = GuiSfxExportOrder::new_lossy(
+            names(&["h"]),
+            names(&["a", "b", "c"]),
+            names(&["x", "y"]),
+        );
+
+        move_sfx(&mut eo, "b", Priority::Normal, Priority::Low);
+        assert_eq!(eo.high_priority_sfx(), names(&["h"]));
+        assert_eq!(eo.normal_priority_sfx(), names(&["a", "c"]));
+        assert_eq!(eo.low_priority_sfx(), names(&["b", "x", "y"]));
+
+        move_sfx(&mut eo, "y", Priority::Low, Priority::Normal);
+        assert_eq!(eo.high_priority_sfx(), names(&["h"]));
+        assert_eq!(eo.normal_priority_sfx(), names(&["a", "c", "y"]));
+        assert_eq!(eo.low_priority_sfx(), names(&["b", "x"]));
+
+        assert_eq!(eo.export_order().len(), 6);
+    }
+
+    #[test]
+    fn inverse_action() {
+        let (mut eo, _) = GuiSfxExportOrder::new_lossy(
+            names(&["h"]),
+            names(&["a", "b", "c"]),
+            names(&["x", "y"]),
+        );
+
+        let actions = [
+            eo.move_between_priorities_action(1, Priority::Normal, Priority::Low)
+                .unwrap(),
+            SfxExportOrderAction {
+                action: ListAction::Remove(0),
+                n_high_priority_sfx: 0,
+                low_priority_index: 2,
+            },
+            SfxExportOrderAction {
+                action: ListAction::Edit(1, "renamed".parse().unwrap()),
+                n_high_priority_sfx: 0,
+                low_priority_index: 2,
+            },
+            SfxExportOrderAction {
+                action: ListAction::Add(2, "new".parse().unwrap()),
+                n_high_priority_sfx: 0,
+                low_priority_index: 3,
+            },
+        ];
+
+        let mut inverses = Vec::new();
+        for a in &actions {
+            inverses.push(eo.inverse_action(a).unwrap());
+            eo.process(a);
+        }
+
+        assert_eq!(eo.high_priority_sfx(), names(&[]));
+        assert_eq!(eo.normal_priority_sfx(), names(&["a", "renamed", "new"]));
+        assert_eq!(eo.low_priority_sfx(), names(&["b", "x", "y"]));
+
+        for a in inverses.iter().rev() {
+            eo.process(a);
+        }
+
+        assert_eq!(eo.high_priority_sfx(), names(&["h"]));
+        assert_eq!(eo.normal_priority_sfx(), names(&["a", "b", "c"]));
+        assert_eq!(eo.low_priority_sfx(), names(&["x", "y"]));
+    }
+
+    #[test]
+    fn move_to_same_priority_is_ignored() {
+        let (eo, _) = GuiSfxExportOrder::new_lossy(names(&[]), names(&["a"]), names(&["x"]));
+
+        assert!(eo
+            .move_between_priorities_action(0, Priority::Normal, Priority::Normal)
+            .is_none());
+        assert!(eo
+            .move_between_priorities_action(1, Priority::Normal, Priority::Low)
+            .is_none());
+    }
+
+    #[test]
+    fn sfx_dnd_text_round_trip() {
+        for p in [Priority::High, Priority::Normal, Priority::Low] {
+            let text = sfx_dnd_text(p, "sfx_name");
+            assert_eq!(
+                parse_sfx_dnd_text(&text),
+                Some((p, "sfx_name".parse().unwrap()))
+            );
+        }
+
+        assert_eq!(parse_sfx_dnd_text("normal:sfx_name"), None);
+        assert_eq!(parse_sfx_dnd_text("tad-sfx-export-order:medium:sfx"), None);
     }
 }
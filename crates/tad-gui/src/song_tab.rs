@@ -4,36 +4,50 @@
 //
 // SPDX-License-Identifier: MIT
 
-use crate::audio_thread::{AudioMonitorData, MusicChannelsMask};
+use crate::audio_thread::{AudioMonitorData, MusicChannelsMask, SongLoop};
+use crate::channel_meters::ChannelMeters;
 use crate::compiler_thread::{ItemId, SongError, SongOutput};
 use crate::helpers::*;
 use crate::mml_editor::{CompiledEditorData, MmlEditor, TextErrorRef, TextFormat};
 use crate::tabs::{FileType, Tab};
 use crate::GuiMessage;
 
+use compiler::bytecode_interpreter::EnvelopeKind;
 use compiler::data::TextFile;
-use compiler::driver_constants::N_MUSIC_CHANNELS;
+use compiler::driver_constants::{
+    addresses, AUDIO_RAM_SIZE, BC_CHANNEL_STACK_SIZE, BC_STACK_BYTES_PER_SUBROUTINE_CALL,
+    N_MUSIC_CHANNELS,
+};
 use compiler::errors::{MmlCompileErrors, MmlPrefixError};
-use compiler::mml::{ChannelId, MmlTickCountTable};
-use compiler::songs::{song_duration_string, SongData};
+use compiler::mml::{
+    ChannelId, MmlTickCountTable, ParseMetrics, SectionTickCounts, SectionTickRanges,
+};
+use compiler::songs::{song_duration_string, SongAramSize, SongData};
 
 use compiler::time::TickCounter;
 use fltk::app;
 use fltk::button::{Button, ToggleButton};
-use fltk::enums::{CallbackReason, CallbackTrigger, Color, Event, Font, Key};
-use fltk::group::{Flex, Pack, PackType};
+use fltk::draw;
+use fltk::enums::{Align, CallbackReason, CallbackTrigger, Color, Event, Font, FrameType, Key};
+use fltk::frame::Frame;
+use fltk::group::{Flex, Group, Pack, PackType, Scroll, ScrollType};
 use fltk::input::{Input, IntInput};
+use fltk::output::Output;
 use fltk::prelude::*;
 use fltk::text::{TextBuffer, TextDisplay, WrapMode};
+use fltk::valuator::{Scrollbar, ScrollbarType};
 use fltk::widget::Widget;
 
 use std::cell::RefCell;
 use std::cmp::min;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 const MAX_START_TICKS: u32 = 2_000_000;
 
+const SECTIONS_PANEL_WIDTH: i32 = 30;
+
 pub fn blank_mml_file() -> TextFile {
     TextFile {
         path: None,
@@ -42,6 +56,63 @@ pub fn blank_mml_file() -> TextFile {
     }
 }
 
+fn channel_instrument_label(channel_index: usize, state: Option<(u8, EnvelopeKind)>) -> String {
+    let channel = char::from(b'A' + channel_index as u8);
+
+    match state {
+        Some((inst, EnvelopeKind::Instrument)) => format!("{}: @@{}", channel, inst),
+        // `*` denotes an envelope override
+        Some((inst, EnvelopeKind::Override(_))) => format!("{}: @@{}*", channel, inst),
+        None => format!("{}: -", channel),
+    }
+}
+
+/// Formats a song position as `m:ss.f`
+fn song_position_string(duration: Option<Duration>) -> String {
+    match duration {
+        Some(d) => {
+            let tenths = d.as_millis() / 100;
+            let minutes = tenths / 600;
+            let seconds = (tenths / 10) % 60;
+
+            format!("{}:{:02}.{}", minutes, seconds, tenths % 10)
+        }
+        None => "-:--.-".to_owned(),
+    }
+}
+
+/// Returns the song size status bar text and true if the song is too large
+fn song_size_string(song_size: &SongAramSize, common_data_size: usize) -> (String, bool) {
+    let data_size = usize::from(song_size.data_size);
+
+    let max_size = (AUDIO_RAM_SIZE - usize::from(addresses::COMMON_DATA))
+        .saturating_sub(common_data_size)
+        .saturating_sub(usize::from(song_size.echo_buffer_size));
+
+    let percent_used = match max_size {
+        0 => 100.0,
+        m => data_size as f64 * 100.0 / m as f64,
+    };
+
+    (
+        format!("Song: {data_size} bytes / {max_size} max ({percent_used:.1}% used)"),
+        data_size > max_size,
+    )
+}
+
+fn subroutine_depth_string(depth: u8) -> String {
+    // Warn if the subroutine calls use more than half of the bytecode stack,
+    // leaving little room on the stack for loops
+    if usize::from(depth) * BC_STACK_BYTES_PER_SUBROUTINE_CALL > BC_CHANNEL_STACK_SIZE / 2 {
+        format!(
+            "Subroutine depth: {}\nWARNING: deeply nested subroutine calls can overflow the bytecode stack\n",
+            depth
+        )
+    } else {
+        format!("Subroutine depth: {}\n", depth)
+    }
+}
+
 pub struct State {
     sender: app::Sender<GuiMessage>,
 
@@ -50,9 +121,13 @@ pub struct State {
     group: Flex,
 
     song_start_ticks: IntInput,
+    loop_button: ToggleButton,
+    loop_end_ticks: IntInput,
 
     prev_channel_mask: MusicChannelsMask,
     channel_buttons: [ToggleButton; N_MUSIC_CHANNELS],
+    channel_instruments: [Frame; N_MUSIC_CHANNELS],
+    channel_meters: ChannelMeters,
 
     sub_prefix_button: ToggleButton,
     sub_prefix_flex: Flex,
@@ -60,9 +135,27 @@ pub struct State {
 
     editor: MmlEditor,
 
+    sections_button: ToggleButton,
+    sections_panel: Flex,
+    sections_scroll: Scroll,
+    sections_pack: Pack,
+    sections: Vec<SectionTickCounts>,
+    section_ranges: SectionTickRanges,
+    section_labels: Vec<Frame>,
+    playing_section: Option<usize>,
+
+    scrubber: Scrollbar,
+    scrubber_position: Output,
+    song_data: Option<Arc<SongData>>,
+
     console: TextDisplay,
     console_buffer: TextBuffer,
 
+    song_size_bar: Frame,
+    song_size: Option<SongAramSize>,
+    common_data_size: usize,
+
+    parse_metrics: Option<ParseMetrics>,
     errors: Option<MmlCompileErrors>,
 }
 
@@ -135,6 +228,15 @@ impl SongTab {
         let mut set_start_ticks = button("set", "Set start ticks to cursor (F9)\nShift+Click (Shift+F9) will also mute the other channels");
         spacer(spacing);
 
+        let loop_button = toggle_button(
+            "@reload",
+            "Loop playback\nPlay (F5) restarts the song at the start ticks when it reaches the loop end",
+        );
+        let mut loop_end_ticks = IntInput::default().with_size(button_size * 2, 0);
+        loop_end_ticks
+            .set_tooltip("Loop end position (in ticks)\nBlank loops at the end of the song");
+        spacer(spacing);
+
         let mut play_button = button(
             "@play",
             "Play song (F5)\nShift+click (Shift+F5) to play song from the beginning",
@@ -150,6 +252,7 @@ impl SongTab {
 
         let sub_prefix_button =
             toggle_button("!", "Temporary MML to run when previewing a subroutine");
+        let sections_button = toggle_button("@menu", "Show song sections");
         spacer(spacing * 2);
 
         let mut enable_all_button = button("All", "Enable all channels (Ctrl `)");
@@ -162,7 +265,7 @@ impl SongTab {
                 .with_size(button_size, button_size)
                 .with_label(&channel.to_string());
             b.set_tooltip(&format!(
-                "Toggle channel {} (Ctrl {})\nShift-click to select only channel {} (Ctrl+Shift {})",
+                "Toggle channel {} (Ctrl {})\nShift-click or Alt-click to select only channel {} (Ctrl+Shift {})",
                 channel,
                 i + 1,
                 channel,
@@ -175,6 +278,21 @@ impl SongTab {
 
         main_toolbar.end();
 
+        let channel_status_row = Flex::default().row();
+        group.fixed(&channel_status_row, input_height(&channel_status_row));
+
+        let channel_instruments = std::array::from_fn(|i| {
+            let mut f = Frame::default();
+            f.set_frame(FrameType::DownBox);
+            f.set_align(Align::Inside | Align::Left);
+            f.set_label(&channel_instrument_label(i, None));
+            f
+        });
+
+        channel_status_row.end();
+
+        let channel_meters = ChannelMeters::new(&mut group);
+
         let mut sub_prefix_flex = Flex::default().row();
         group.fixed(&sub_prefix_flex, input_height(&sub_prefix_flex));
         sub_prefix_flex.hide();
@@ -186,13 +304,59 @@ impl SongTab {
 
         sub_prefix_flex.end();
 
+        let mut editor_row = Flex::default().row();
+
         let mut editor = MmlEditor::new(&mml_file.contents, TextFormat::Mml);
 
         editor.set_text_size(editor.widget().text_size() * 12 / 10);
 
+        let mut sections_panel = Flex::default().column();
+        editor_row.fixed(
+            &sections_panel,
+            ch_units_to_width(&sections_panel, SECTIONS_PANEL_WIDTH),
+        );
+        sections_panel.hide();
+
+        let l = label("Sections:");
+        sections_panel.fixed(&l, input_height(&l));
+
+        let mut sections_scroll = Scroll::default();
+        sections_scroll.set_type(ScrollType::Vertical);
+
+        let sections_pack = Pack::default();
+        sections_pack.end();
+
+        sections_scroll.end();
+        sections_panel.end();
+        editor_row.end();
+
+        let scrubber_row = Flex::default().row();
+        group.fixed(&scrubber_row, input_height(&scrubber_row));
+
+        let mut scrubber = Scrollbar::default().with_type(ScrollbarType::Horizontal);
+        scrubber.set_tooltip("Song position\nDrag to play the song from a different position");
+        scrubber.set_trigger(CallbackTrigger::Release);
+        scrubber.set_range(0.0, 0.0);
+        scrubber.set_step(1.0, 1);
+        scrubber.deactivate();
+
+        let mut scrubber_position = Output::default();
+        scrubber_row.fixed(
+            &scrubber_position,
+            ch_units_to_width(&scrubber_position, 10),
+        );
+        scrubber_position.set_value(&song_position_string(None));
+
+        scrubber_row.end();
+
         let mut console = TextDisplay::default();
         group.fixed(&console, input_height(&console) * 5);
 
+        let mut song_size_bar = Frame::default();
+        group.fixed(&song_size_bar, input_height(&song_size_bar));
+        song_size_bar.set_frame(FrameType::FlatBox);
+        song_size_bar.set_align(Align::Inside | Align::Left);
+
         group.add(editor.status_bar());
         group.fixed(editor.status_bar(), input_height(editor.status_bar()));
 
@@ -210,14 +374,33 @@ impl SongTab {
             song_id,
             group: group.clone(),
             song_start_ticks,
+            loop_button,
+            loop_end_ticks,
             prev_channel_mask: MusicChannelsMask::ALL,
             channel_buttons,
+            channel_instruments,
+            channel_meters,
             sub_prefix_button,
             sub_prefix_flex,
             sub_prefix,
             editor,
+            sections_button,
+            sections_panel,
+            sections_scroll,
+            sections_pack,
+            sections: Vec::new(),
+            section_ranges: SectionTickRanges::default(),
+            section_labels: Vec::new(),
+            playing_section: None,
+            scrubber,
+            scrubber_position,
+            song_data: None,
             console,
             console_buffer,
+            song_size_bar,
+            song_size: None,
+            common_data_size: 0,
+            parse_metrics: None,
             errors: None,
         }));
 
@@ -403,7 +586,7 @@ impl SongTab {
                     let s = state.clone();
                     move |_| {
                         if let Ok(mut s) = s.try_borrow_mut() {
-                            match app::is_event_shift() {
+                            match app::is_event_shift() || app::is_event_alt() {
                                 false => s.channel_button_clicked(),
                                 true => s.set_only_one_channel_index(i),
                             }
@@ -428,8 +611,39 @@ impl SongTab {
                 }
             });
 
+            s.sections_button.set_callback({
+                let s = state.clone();
+                move |_| {
+                    if let Ok(mut s) = s.try_borrow_mut() {
+                        match s.sections_button.value() {
+                            true => s.sections_panel.show(),
+                            false => s.sections_panel.hide(),
+                        }
+                        s.group.layout();
+                    }
+                }
+            });
+
             s.sub_prefix.set_trigger(CallbackTrigger::Changed);
             s.sub_prefix.set_callback(State::on_sub_prefix_changed);
+
+            s.scrubber.set_callback({
+                let s = state.clone();
+                move |_| {
+                    if let Ok(mut s) = s.try_borrow_mut() {
+                        s.scrubber_released();
+                    }
+                }
+            });
+
+            s.scrubber.draw({
+                let s = state.clone();
+                move |w| {
+                    if let Ok(s) = s.try_borrow() {
+                        s.draw_section_marks(w);
+                    }
+                }
+            });
         }
 
         sfx_button.set_callback({
@@ -463,6 +677,9 @@ impl SongTab {
         s.sub_prefix_flex.hide();
         s.sub_prefix.set_value("");
 
+        s.loop_button.set(false);
+        s.loop_end_ticks.set_value("");
+
         s.editor.scroll_to_top();
         s.editor.take_focus();
 
@@ -472,6 +689,9 @@ impl SongTab {
         s.update_channel_buttons(MusicChannelsMask::ALL);
 
         s.group.layout();
+
+        drop(s);
+        Self::populate_sections_panel(&self.state);
     }
 
     pub fn contents(&self) -> String {
@@ -482,6 +702,71 @@ impl SongTab {
         if let Ok(mut s) = self.state.try_borrow_mut() {
             s.set_compiler_output(co);
         }
+        Self::populate_sections_panel(&self.state);
+    }
+
+    pub fn set_common_data_size(&mut self, size: usize) {
+        if let Ok(mut s) = self.state.try_borrow_mut() {
+            s.common_data_size = size;
+            s.update_song_size_bar();
+        }
+    }
+
+    fn populate_sections_panel(state: &Rc<RefCell<State>>) {
+        let mut s = match state.try_borrow_mut() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        let s = &mut *s;
+
+        s.sections_pack.clear();
+        s.section_labels.clear();
+        s.playing_section = None;
+
+        let width =
+            ch_units_to_width(&s.sections_pack, SECTIONS_PANEL_WIDTH) - app::scrollbar_size();
+        let row_height = input_height(&s.sections_pack);
+        let button_width = ch_units_to_width(&s.sections_pack, 6);
+
+        s.sections_pack.begin();
+
+        for section in &s.sections {
+            let mut row = Flex::default().with_size(width, row_height).row();
+
+            let ticks = section.max_ticks().value();
+            let mut l = label(&format!("{} ({})", section.name.replace('@', "@@"), ticks));
+            l.set_tooltip(&format!("{} ticks", ticks));
+            s.section_labels.push(l.clone());
+
+            let mut b = Button::default().with_label("Jump");
+            b.set_tooltip("Jump to section");
+            row.fixed(&b, button_width);
+
+            b.set_callback({
+                let s = state.clone();
+                let line_number = section.line_number;
+                move |_| {
+                    if let Ok(mut s) = s.try_borrow_mut() {
+                        s.editor.move_cursor_to_line_end(line_number);
+                        s.editor.take_focus();
+                    }
+                }
+            });
+
+            row.end();
+        }
+
+        s.sections_pack.end();
+        Group::set_current(None::<&Group>);
+
+        s.sections_scroll.scroll_to(0, 0);
+        s.sections_scroll.redraw();
+    }
+
+    pub fn set_parse_metrics(&mut self, m: ParseMetrics) {
+        if let Ok(mut s) = self.state.try_borrow_mut() {
+            s.parse_metrics = Some(m);
+        }
     }
 
     pub fn set_song_prefix_result(&mut self, r: Result<(), MmlPrefixError>) {
@@ -506,11 +791,19 @@ impl SongTab {
     }
 
     pub fn monitor_timer_elapsed(&mut self, mon: AudioMonitorData) {
-        self.state.borrow_mut().editor.update_note_tracking(mon);
+        let mut s = self.state.borrow_mut();
+        s.update_channel_instruments(&mon.channel_instruments);
+        s.update_scrubber(mon.song_tick_counter);
+        s.channel_meters.set_activity(&mon.channel_activity);
+        s.editor.update_note_tracking(mon);
     }
 
     pub fn clear_note_tracking(&mut self) {
-        self.state.borrow_mut().editor.clear_note_tracking();
+        let mut s = self.state.borrow_mut();
+        s.update_channel_instruments(&[]);
+        s.update_scrubber(None);
+        s.channel_meters.clear();
+        s.editor.clear_note_tracking();
     }
 }
 
@@ -559,14 +852,39 @@ impl State {
 
         self.update_channel_buttons(self.prev_channel_mask);
 
+        let song_loop = match self.loop_button.value() {
+            true => Some(SongLoop {
+                start: TickCounter::new(ticks),
+                end: self.loop_end_ticks(),
+            }),
+            false => None,
+        };
+
         self.sender.send(GuiMessage::PlaySong(
             self.song_id,
             self.editor.text(),
             TickCounter::new(ticks),
             self.prev_channel_mask,
+            None,
+            song_loop,
         ));
     }
 
+    /// Returns None if the loop end is blank (loop at the end of the song)
+    fn loop_end_ticks(&mut self) -> Option<TickCounter> {
+        match self.loop_end_ticks.value().parse() {
+            Ok(t) if t <= MAX_START_TICKS => Some(TickCounter::new(t)),
+            Ok(_) => {
+                self.loop_end_ticks.set_value(&MAX_START_TICKS.to_string());
+                Some(TickCounter::new(MAX_START_TICKS))
+            }
+            Err(_) => {
+                self.loop_end_ticks.set_value("");
+                None
+            }
+        }
+    }
+
     fn play_song_start(&mut self) {
         self.update_channel_buttons(self.prev_channel_mask);
 
@@ -575,6 +893,8 @@ impl State {
             self.editor.text(),
             TickCounter::new(0),
             self.prev_channel_mask,
+            None,
+            None,
         ));
     }
 
@@ -585,9 +905,13 @@ impl State {
     ) {
         match cursor {
             Some((ChannelId::Channel(c), ticks)) => {
-                let channels_mask = match mute_other_channels {
-                    true => MusicChannelsMask::only_one_channel(c),
-                    false => self.prev_channel_mask,
+                // Other channels are not interpreted
+                let (channels_mask, interpreter_mask) = match mute_other_channels {
+                    true => {
+                        let m = MusicChannelsMask::only_one_channel(c);
+                        (m, Some(m))
+                    }
+                    false => (self.prev_channel_mask, None),
                 };
                 self.update_channel_buttons(channels_mask);
 
@@ -596,6 +920,8 @@ impl State {
                     self.editor.text(),
                     ticks,
                     channels_mask,
+                    interpreter_mask,
+                    None,
                 ));
             }
             Some((ChannelId::Subroutine(si), ticks)) => {
@@ -666,6 +992,147 @@ impl State {
         self.channel_button_clicked();
     }
 
+    fn update_channel_instruments(&mut self, state: &[Option<(u8, EnvelopeKind)>]) {
+        for (i, f) in self.channel_instruments.iter_mut().enumerate() {
+            let ci = state.get(i).copied().flatten();
+
+            let label = channel_instrument_label(i, ci);
+            if f.label() != label {
+                f.set_label(&label);
+
+                match ci {
+                    Some((_, EnvelopeKind::Override(e))) => f.set_tooltip(&e.to_envelope_string()),
+                    _ => f.set_tooltip(""),
+                }
+            }
+        }
+    }
+
+    fn update_scrubber(&mut self, tick_counter: Option<TickCounter>) {
+        let (sd, ticks) = match (&self.song_data, tick_counter) {
+            (Some(sd), Some(t)) => (sd, t.value()),
+            _ => {
+                self.update_playing_section(None);
+
+                if self.scrubber.active() {
+                    self.scrubber.deactivate();
+                    self.scrubber.set_value(0.0);
+                    self.scrubber_position
+                        .set_value(&song_position_string(None));
+                }
+                return;
+            }
+        };
+
+        // Do not move the scrubber while the user is dragging it
+        if app::pushed().is_some_and(|w| w.is_same(&self.scrubber)) {
+            return;
+        }
+
+        let max_ticks = sd.max_tick_count().value();
+
+        // Map the tick counter into the loop if the song has looped
+        let ticks = match Self::song_loop_start(sd) {
+            Some(loop_start) if ticks > max_ticks && loop_start < max_ticks => {
+                loop_start + (ticks - loop_start) % (max_ticks - loop_start)
+            }
+            _ => min(ticks, max_ticks),
+        };
+
+        self.update_playing_section(
+            self.section_ranges
+                .position_at_tick(TickCounter::new(ticks)),
+        );
+
+        self.scrubber.activate();
+        self.scrubber.set_value(f64::from(ticks));
+        self.scrubber_position.set_value(&song_position_string(
+            sd.duration_at_tick(TickCounter::new(ticks)),
+        ));
+    }
+
+    /// Highlights the section that is currently playing in the sections panel
+    fn update_playing_section(&mut self, section: Option<usize>) {
+        if section == self.playing_section {
+            return;
+        }
+
+        if let Some(l) = self
+            .playing_section
+            .and_then(|i| self.section_labels.get_mut(i))
+        {
+            l.set_label_font(Font::Helvetica);
+            l.redraw();
+        }
+        if let Some(l) = section.and_then(|i| self.section_labels.get_mut(i)) {
+            l.set_label_font(Font::HelveticaBold);
+            l.redraw();
+        }
+
+        self.playing_section = section;
+    }
+
+    /// Returns the loop point of the longest looping channel
+    fn song_loop_start(sd: &SongData) -> Option<u32> {
+        sd.channels()
+            .iter()
+            .flatten()
+            .filter_map(|c| Some((c.tick_counter, c.loop_point.as_ref()?.tick_counter)))
+            .max_by_key(|(tc, _)| tc.value())
+            .map(|(_, lp)| lp.value())
+    }
+
+    fn scrubber_released(&mut self) {
+        if !self.scrubber.active() {
+            return;
+        }
+
+        let ticks = min(self.scrubber.value().max(0.0) as u32, MAX_START_TICKS);
+
+        self.update_channel_buttons(self.prev_channel_mask);
+
+        self.sender.send(GuiMessage::PlaySong(
+            self.song_id,
+            self.editor.text(),
+            TickCounter::new(ticks),
+            self.prev_channel_mask,
+            None,
+            None,
+        ));
+    }
+
+    fn draw_section_marks(&self, w: &Scrollbar) {
+        let max_ticks = w.maximum();
+        if max_ticks <= 0.0 {
+            return;
+        }
+
+        // Do not draw over the scrollbar arrows
+        let x = w.x() + w.h();
+        let width = f64::from(w.w() - w.h() * 2);
+
+        let y1 = w.y() + 1;
+        let y2 = w.y() + w.h() / 3;
+
+        draw::set_draw_color(Color::Foreground);
+
+        for section in &self.sections {
+            let ticks = f64::from(section.max_ticks().value());
+            let sx = x + (width * ticks / max_ticks) as i32;
+
+            draw::draw_line(sx, y1, sx, y2);
+        }
+    }
+
+    fn update_scrubber_range(&mut self) {
+        let max_ticks = match &self.song_data {
+            Some(sd) => sd.max_tick_count().value(),
+            None => 0,
+        };
+        self.scrubber.set_range(0.0, f64::from(max_ticks));
+        self.scrubber.redraw();
+    }
+
     fn channel_button_clicked(&mut self) {
         let mut channel_mask = 0x00;
         for (i, b) in self.channel_buttons.iter().enumerate() {
@@ -687,47 +1154,110 @@ impl State {
                 self.editor.clear_compiled_data();
 
                 self.console_buffer.set_text("");
+                self.parse_metrics = None;
                 self.errors = None;
+                self.sections.clear();
+                self.section_ranges = SectionTickRanges::default();
+                self.song_data = None;
+                self.song_size = None;
             }
             Some(Ok(sd)) => {
+                let compile_time = match &self.parse_metrics {
+                    Some(m) => format!(
+                        "\nCompiled in {:.1} ms",
+                        m.parse_time_micros as f64 / 1000.0
+                    ),
+                    None => String::new(),
+                };
+                let tick_count_table = MmlTickCountTable::new(&sd);
                 let text = format!(
-                    "MML compiled successfully: {} bytes (+{} echo buffer bytes)\n\nDuration: {}\n{}",
+                    "MML compiled successfully: {} bytes (+{} echo buffer bytes){}\n\nDuration: {}\n{}{}",
                     sd.data().len(),
                     sd.metadata().echo_buffer.buffer_size(),
+                    compile_time,
                     song_duration_string(sd.duration()),
-                    MmlTickCountTable(&sd),
+                    subroutine_depth_string(sd.subroutine_depth()),
+                    tick_count_table,
                 );
                 self.console_buffer.set_text(&text);
                 self.console.set_text_color(Color::Foreground);
                 self.errors = None;
 
+                self.sections = sd.section_tick_count_table().0;
+                self.section_ranges = tick_count_table.into_section_tick_ranges();
+                self.song_size = Some(sd.song_aram_size());
+                self.song_data = Some(sd.clone());
+
                 self.editor.set_compiled_data(CompiledEditorData::Song(sd));
             }
             Some(Err(e)) => {
                 self.editor.clear_compiled_data();
+                self.sections.clear();
+                self.section_ranges = SectionTickRanges::default();
+                self.song_data = None;
 
                 let text = match &e {
                     SongError::Dependency => "dependency error".to_string(),
                     SongError::Song(e) => e.multiline_display().to_string(),
-                    SongError::TooLarge(e) => e.multiline_display().to_string(),
+                    SongError::TooLarge(e, None) => e.multiline_display().to_string(),
+                    SongError::TooLarge(e, Some(overlap)) => {
+                        format!("{}\n  {}", e.multiline_display(), overlap)
+                    }
                 };
 
                 self.console_buffer.set_text(&text);
                 self.console.set_text_color(Color::Red);
 
+                self.song_size = match &e {
+                    SongError::TooLarge(e, _) => Some(SongAramSize {
+                        data_size: e.song_data_size.try_into().unwrap_or(u16::MAX),
+                        echo_buffer_size: e.echo_buffer_size.try_into().unwrap_or(u16::MAX),
+                    }),
+                    SongError::Dependency | SongError::Song(_) => None,
+                };
+
                 self.errors = match e {
                     SongError::Dependency => None,
-                    SongError::Song(compiler::errors::SongError::MmlError(e)) => Some(e),
+                    SongError::Song(compiler::errors::SongError::MmlError(e)) => Some(*e),
                     SongError::Song(_) => None,
                     SongError::TooLarge { .. } => None,
                 };
             }
         }
 
+        self.update_scrubber_range();
+        self.update_song_size_bar();
+
         self.editor
             .highlight_errors(self.errors.as_ref().map(TextErrorRef::Song));
     }
 
+    fn update_song_size_bar(&mut self) {
+        let bar = &mut self.song_size_bar;
+
+        match &self.song_size {
+            Some(song_size) => {
+                let (text, too_large) = song_size_string(song_size, self.common_data_size);
+                bar.set_label(&text);
+                match too_large {
+                    true => {
+                        bar.set_color(Color::Red);
+                        bar.set_label_color(Color::White);
+                    }
+                    false => {
+                        bar.set_color(Color::Background);
+                        bar.set_label_color(Color::Foreground);
+                    }
+                }
+            }
+            None => {
+                bar.set_label("");
+                bar.set_color(Color::Background);
+            }
+        }
+        bar.redraw();
+    }
+
     fn set_song_prefix_result(&mut self, r: Result<(), MmlPrefixError>) {
         match r {
             Ok(()) => {
@@ -8,12 +8,13 @@ use crate::compiler_thread::{
     ItemId, SfxError, SfxSubroutineOutput, SfxSubroutinesError, SoundEffectOutput,
 };
 use crate::list_editor::{
-    ListAction, ListEditorTable, ListMessage, ListWithCompilerOutput, ListWithCompilerOutputEditor,
-    TableCompilerOutput, TableMapping,
+    ListAction, ListEditorTable, ListMessage, ListUndoAction, ListWithCompilerOutput,
+    ListWithCompilerOutputEditor, TableCompilerOutput, TableMapping,
 };
 use crate::mml_editor::{CompiledEditorData, EditorBuffer, MmlEditor, TextErrorRef, TextFormat};
 use crate::tables::{RowWithStatus, SimpleRow};
 use crate::tabs::{FileType, Tab};
+use crate::undo::UndoStack;
 use crate::{helpers::*, ProjectSongsData};
 use crate::{GuiMessage, ProjectData, SoundEffectsData};
 
@@ -179,6 +180,7 @@ pub struct SoundEffectsTab {
 
     sidebar: Flex,
     sfx_table: ListEditorTable<SoundEffectMapping>,
+    undo_stack: UndoStack<ListUndoAction<SoundEffectInput>>,
 
     missing_sfx: bool,
     add_missing_sfx_button: Button,
@@ -537,6 +539,7 @@ impl SoundEffectsTab {
 
             sidebar,
             sfx_table,
+            undo_stack: UndoStack::default(),
             missing_sfx: false,
             add_missing_sfx_button,
 
@@ -562,6 +565,7 @@ impl SoundEffectsTab {
 
         self.disable_editor();
         self.sfx_table.replace(sfx_list);
+        self.undo_stack.clear();
 
         // ::TODO save old buffers::
         self.sfx_buffers.clear();
@@ -755,12 +759,39 @@ impl ListWithCompilerOutputEditor<SoundEffectInput, SoundEffectOutput> for Sound
         self.sfx_buffers.remove(&id);
     }
 
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<ListUndoAction<SoundEffectInput>>> {
+        Some(&mut self.undo_stack)
+    }
+
     fn item_edited(&mut self, id: ItemId, sfx: &SoundEffectInput) {
         let mut s = self.state.borrow_mut();
 
-        if s.selected_id == Some(id) && s.old_name != sfx.name {
-            s.old_name = sfx.name.clone();
-            s.name.set_value(sfx.name.as_str());
+        // An undo or redo can change any part of the sound effect
+        let text = match &sfx.sfx {
+            SoundEffectText::BytecodeAssembly(s) => s,
+            SoundEffectText::Mml(s) => s,
+        };
+
+        if s.selected_id == Some(id) {
+            if s.old_name != sfx.name {
+                s.old_name = sfx.name.clone();
+                s.name.set_value(sfx.name.as_str());
+            }
+            if s.old_flags != sfx.flags {
+                s.old_flags = sfx.flags.clone();
+                s.one_channel_flag.set_value(sfx.flags.one_channel);
+                s.interruptible_flag.set_value(sfx.flags.interruptible);
+            }
+            if s.editor.text() != *text {
+                s.editor.set_text(text);
+            }
+        } else if self
+            .sfx_buffers
+            .get(&id)
+            .is_some_and(|b| b.borrow().text() != *text)
+        {
+            // Recreate the buffer when the sound effect is selected
+            self.sfx_buffers.remove(&id);
         }
     }
 
@@ -20,9 +20,16 @@ const SPACEBAR_KEY: Key = Key::from_char(' ');
 /// Padding to the left/right of each cell.
 const CELL_X_PAD: i32 = 3;
 
+/// Height of the line drawn between rows when dragging a row.
+const DROP_LINE_HEIGHT: i32 = 2;
+
 pub trait TableRow {
     const N_COLUMNS: i32;
 
+    /// Relative width of each column.
+    /// If empty, all columns have the same width.
+    const COLUMN_WIDTHS: &'static [i32] = &[];
+
     fn default_bg_color() -> Color {
         Color::Background2
     }
@@ -61,6 +68,18 @@ where
     ///  * false if the change is selction was caused by `set_selected()` or `clear_selected()`.
     row_selected_callback: Box<dyn Fn(Option<usize>, usize, bool)>,
 
+    /// Drag and drop callbacks (optional)
+    drag_text_callback: Option<Box<dyn Fn(&T) -> Option<String>>>,
+    drop_callback: Option<Box<dyn Fn(&str)>>,
+    drop_highlight: bool,
+
+    /// Row reordering callback (optional)
+    row_drag_callback: Option<Box<dyn Fn(usize, usize, bool)>>,
+    /// The row the user is dragging
+    dragged_row: Option<usize>,
+    /// The position of the drop indicator line (the row index after the line)
+    drop_index: Option<usize>,
+
     edit_widget: Option<fltk::input::Input>,
 
     // The currently selected cell.
@@ -125,6 +144,12 @@ where
             data: Vec::new(),
             callback: Box::from(blank_callback),
             row_selected_callback: Box::from(blank_sel_changed_callback),
+            drag_text_callback: None,
+            drop_callback: None,
+            drop_highlight: false,
+            row_drag_callback: None,
+            dragged_row: None,
+            drop_index: None,
             edit_widget: None,
             sel_row: -1,
             sel_col: 0,
@@ -160,10 +185,21 @@ where
             move |table, _x, _y, w, _h| {
                 // ::TODO adjustable table columns and user adjustable columns::
                 if T::N_COLUMNS > 0 {
-                    let w = (w - table.scrollbar().width() - 3) / T::N_COLUMNS;
-                    let w = max(30, w);
-                    if w != table.col_width(0) {
-                        table.set_col_width_all(w);
+                    let w = w - table.scrollbar().width() - 3;
+
+                    if T::COLUMN_WIDTHS.is_empty() {
+                        let w = max(30, w / T::N_COLUMNS);
+                        if w != table.col_width(0) {
+                            table.set_col_width_all(w);
+                        }
+                    } else {
+                        let total: i32 = T::COLUMN_WIDTHS.iter().sum();
+                        for (col, cw) in (0..).zip(T::COLUMN_WIDTHS) {
+                            let cw = max(30, w * cw / total);
+                            if cw != table.col_width(col) {
+                                table.set_col_width(col, cw);
+                            }
+                        }
                     }
                 }
             }
@@ -261,6 +297,31 @@ where
         self.state.borrow_mut().row_selected_callback = Box::from(f);
     }
 
+    /// Enables dragging the selected row out of the table.
+    ///
+    /// Callback: `f(row) -> Option<String>` returns the drag and drop text for the row.
+    pub fn set_drag_text_callback(&mut self, f: impl Fn(&T) -> Option<String> + 'static) {
+        self.state.borrow_mut().drag_text_callback = Some(Box::from(f));
+    }
+
+    /// Enables dropping text onto the table.
+    ///
+    /// Callback: `f(text)`.
+    /// The column headers are highlighted while a drag and drop is over the table.
+    pub fn set_drop_callback(&mut self, f: impl Fn(&str) + 'static) {
+        self.state.borrow_mut().drop_callback = Some(Box::from(f));
+    }
+
+    /// Enables reordering rows by dragging them inside the table.
+    ///
+    /// Callback: `f(from, to, copy)`.
+    ///  * `from` is the index of the dragged row.
+    ///  * `to` is the index of the row below the drop indicator line (`0..=n_rows`).
+    ///  * `copy` is true if shift was held when the row was dropped.
+    pub fn set_row_drag_callback(&mut self, f: impl Fn(usize, usize, bool) + 'static) {
+        self.state.borrow_mut().row_drag_callback = Some(Box::from(f));
+    }
+
     pub fn clear_selected(&mut self) {
         if let Ok(mut s) = self.state.try_borrow_mut() {
             s.unset_selection(false);
@@ -355,8 +416,15 @@ where
         match ev {
             Event::Push => {
                 if let Ok(mut s) = state.try_borrow_mut() {
+                    s.set_drop_index(None);
+                    s.dragged_row = None;
+
                     if let Some((TableContext::Cell, row, col, _)) = table.cursor2rowcol() {
                         s.set_selection(row, col, true);
+
+                        if s.row_drag_callback.is_some() {
+                            s.dragged_row = usize::try_from(row).ok().filter(|&r| r < s.data.len());
+                        }
                     } else {
                         s.unset_selection(true);
                     }
@@ -365,6 +433,22 @@ where
                 true
             }
             Event::Released => {
+                if let Ok(mut s) = state.try_borrow_mut() {
+                    let dragged_row = s.dragged_row.take();
+                    let drop_index = s.drop_index;
+                    s.set_drop_index(None);
+
+                    // Do not move or copy a row if the user clicked on it
+                    if !app::event_is_click() {
+                        if let (Some(from), Some(to), Some(f)) =
+                            (dragged_row, drop_index, &s.row_drag_callback)
+                        {
+                            f(from, to, app::is_event_shift());
+                            return true;
+                        }
+                    }
+                }
+
                 if app::event_is_click() {
                     // Only process events if a cell was clicked
                     if let Some((TableContext::Cell, row, col, _)) = table.cursor2rowcol() {
@@ -383,12 +467,53 @@ where
             }
             Event::Drag => {
                 if let Ok(mut s) = state.try_borrow_mut() {
-                    if let Some((TableContext::Cell, row, col, _)) = table.cursor2rowcol() {
-                        s.set_selection(row, col, true);
+                    if app::event_inside_widget(&*table) {
+                        if s.dragged_row.is_some() {
+                            let i = s.drop_index_under_cursor(table);
+                            s.set_drop_index(Some(i));
+                        } else if let Some((TableContext::Cell, row, col, _)) =
+                            table.cursor2rowcol()
+                        {
+                            s.set_selection(row, col, true);
+                        }
+                    } else {
+                        s.set_drop_index(None);
+
+                        if let Some(text) = s.drag_text() {
+                            // Start a drag and drop when the cursor is dragged outside the table
+                            s.dragged_row = None;
+                            drop(s);
+                            app::copy2(&text);
+                            app::dnd();
+                        }
                     }
                 }
                 true
             }
+            Event::DndEnter | Event::DndDrag => match state.try_borrow_mut() {
+                Ok(mut s) if s.drop_callback.is_some() => {
+                    s.set_drop_highlight(true);
+                    true
+                }
+                _ => false,
+            },
+            Event::DndLeave | Event::DndRelease => match state.try_borrow_mut() {
+                Ok(mut s) if s.drop_callback.is_some() => {
+                    s.set_drop_highlight(false);
+                    true
+                }
+                _ => false,
+            },
+            Event::Paste => match state.try_borrow_mut() {
+                Ok(s) => match &s.drop_callback {
+                    Some(f) => {
+                        f(&app::event_text());
+                        true
+                    }
+                    None => false,
+                },
+                Err(_) => false,
+            },
             Event::KeyDown => {
                 if let Ok(mut s) = state.try_borrow_mut() {
                     match app::event_key() {
@@ -419,6 +544,45 @@ where
         self.do_callback_at(e, self.sel_row, self.sel_col);
     }
 
+    fn drag_text(&self) -> Option<String> {
+        let row = self.data.get(usize::try_from(self.sel_row).ok()?)?;
+        self.drag_text_callback.as_ref()?(row)
+    }
+
+    /// Returns the drop index for the row under the mouse cursor (`0..=n_rows`)
+    fn drop_index_under_cursor(&self, table: &fltk::table::Table) -> usize {
+        let n_rows = self.data.len();
+
+        match table.cursor2rowcol() {
+            Some((TableContext::Cell, row, col, _)) => {
+                // Drop below the row if the cursor is in the bottom half of the cell
+                let below = match table.find_cell(TableContext::Cell, row, col) {
+                    Some((_, y, _, h)) => app::event_y() >= y + h / 2,
+                    None => false,
+                };
+
+                let row = usize::try_from(row).unwrap_or(0) + usize::from(below);
+                row.min(n_rows)
+            }
+            Some((TableContext::ColHeader, ..)) => 0,
+            _ => n_rows,
+        }
+    }
+
+    fn set_drop_index(&mut self, index: Option<usize>) {
+        if self.drop_index != index {
+            self.drop_index = index;
+            self.table.redraw();
+        }
+    }
+
+    fn set_drop_highlight(&mut self, h: bool) {
+        if self.drop_highlight != h {
+            self.drop_highlight = h;
+            self.table.redraw();
+        }
+    }
+
     fn do_callback_at(&mut self, e: TableEvent, row: i32, col: i32) {
         if let Ok(index) = usize::try_from(row) {
             let open_editor = (self.callback)(e, index, col);
@@ -433,12 +597,17 @@ where
         match ctx {
             TableContext::StartPage => draw::set_font(self.font, self.font_size),
             TableContext::ColHeader => {
+                let (bg_color, fg_color) = match self.drop_highlight {
+                    false => (Color::FrameDefault, Color::Foreground),
+                    true => (Color::Selection, Color::Background2),
+                };
+
                 draw::push_clip(x, y, w, h);
-                draw::draw_box(FrameType::ThinUpBox, x, y, w, h, Color::FrameDefault);
+                draw::draw_box(FrameType::ThinUpBox, x, y, w, h, bg_color);
 
                 if let Ok(col_index) = usize::try_from(col) {
                     if let Some(c) = self.headers.get(col_index) {
-                        draw::set_draw_color(Color::Foreground);
+                        draw::set_draw_color(fg_color);
                         draw::set_font(self.font, self.font_size);
                         draw::draw_text2(c, x, y, w, h, Align::Center);
                     }
@@ -472,6 +641,17 @@ where
 
                         table_row.draw_cell(col, x + CELL_X_PAD, y, w - CELL_X_PAD * 2, h);
                     }
+
+                    // Drop indicator line
+                    if let Some(d) = self.drop_index {
+                        let row = usize::try_from(row).unwrap_or(0);
+                        draw::set_draw_color(Color::Foreground);
+                        if d == row {
+                            draw::draw_rectf(x, y, w, DROP_LINE_HEIGHT);
+                        } else if d == row + 1 {
+                            draw::draw_rectf(x, y + h - DROP_LINE_HEIGHT, w, DROP_LINE_HEIGHT);
+                        }
+                    }
                 } else {
                     draw::set_draw_color(T::default_bg_color());
                     draw::draw_rectf(x, y, w, h);
@@ -664,6 +844,29 @@ where
     }
 }
 
+/// Draws `text` in a cell, truncating it with an ellipsis if it does not fit.
+pub fn draw_truncated_text(text: &str, x: i32, y: i32, w: i32, h: i32, align: Align) {
+    const ELLIPSIS: &str = "\u{2026}";
+
+    if draw::width(text) <= f64::from(w) {
+        draw::draw_text2(text, x, y, w, h, align);
+        return;
+    }
+
+    let max_width = f64::from(w) - draw::width(ELLIPSIS);
+
+    let mut end = 0;
+    for (i, c) in text.char_indices() {
+        let next = i + c.len_utf8();
+        if draw::width(&text[..next]) > max_width {
+            break;
+        }
+        end = next;
+    }
+
+    draw::draw_text2(&format!("{}{ELLIPSIS}", &text[..end]), x, y, w, h, align);
+}
+
 /// A simple TableRow containing a const generic number of `String`s.
 pub struct SimpleRow<const C: usize> {
     pub columns: [String; C],
@@ -782,6 +985,7 @@ where
     TR: TableRow,
 {
     const N_COLUMNS: i32 = TR::N_COLUMNS;
+    const COLUMN_WIDTHS: &'static [i32] = TR::COLUMN_WIDTHS;
 
     #[inline]
     fn draw_cell(&self, col: i32, x: i32, y: i32, w: i32, h: i32) {
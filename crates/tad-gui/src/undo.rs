@@ -0,0 +1,118 @@
+//! Undo Stack
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+const MAX_UNDO_ACTIONS: usize = 100;
+
+struct UndoEntry<A> {
+    action: A,
+    inverse: A,
+}
+
+/// Stores the actions processed by a list and the actions that reverse them.
+pub struct UndoStack<A> {
+    undo: Vec<UndoEntry<A>>,
+    redo: Vec<UndoEntry<A>>,
+}
+
+impl<A> Default for UndoStack<A> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl<A> UndoStack<A>
+where
+    A: Clone,
+{
+    /// Adds an action to the undo stack and clears the redo stack.
+    ///
+    /// `inverse` MUST reverse `action`.
+    pub fn push(&mut self, action: A, inverse: A) {
+        if self.undo.len() >= MAX_UNDO_ACTIONS {
+            self.undo.remove(0);
+        }
+        self.undo.push(UndoEntry { action, inverse });
+        self.redo.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Returns the action that reverses the most recent action
+    pub fn undo(&mut self) -> Option<A> {
+        let e = self.undo.pop()?;
+        let inverse = e.inverse.clone();
+        self.redo.push(e);
+
+        Some(inverse)
+    }
+
+    /// Returns the most recently undone action
+    pub fn redo(&mut self) -> Option<A> {
+        let e = self.redo.pop()?;
+        let action = e.action.clone();
+        self.undo.push(e);
+
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::list_editor::ListAction;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut s = UndoStack::default();
+
+        s.push(ListAction::Add(0, 1), ListAction::Remove(0));
+        s.push(ListAction::Edit(0, 2), ListAction::Edit(0, 1));
+
+        assert_eq!(s.undo(), Some(ListAction::Edit(0, 1)));
+        assert_eq!(s.undo(), Some(ListAction::Remove(0)));
+        assert_eq!(s.undo(), None);
+
+        assert_eq!(s.redo(), Some(ListAction::Add(0, 1)));
+        assert_eq!(s.redo(), Some(ListAction::Edit(0, 2)));
+        assert_eq!(s.redo(), None);
+
+        assert_eq!(s.undo(), Some(ListAction::Edit(0, 1)));
+    }
+
+    #[test]
+    fn test_push_clears_redo() {
+        let mut s = UndoStack::default();
+
+        s.push(ListAction::Add(0, 1), ListAction::Remove(0));
+        assert_eq!(s.undo(), Some(ListAction::Remove(0)));
+
+        s.push(ListAction::Add(0, 5), ListAction::Remove(0));
+        assert_eq!(s.redo(), None);
+        assert_eq!(s.undo(), Some(ListAction::Remove(0)));
+        assert_eq!(s.undo(), None);
+    }
+
+    #[test]
+    fn test_max_undo_actions() {
+        let mut s = UndoStack::default();
+
+        for i in 0..(MAX_UNDO_ACTIONS as u32 + 10) {
+            s.push(ListAction::Edit(0, i + 1), ListAction::Edit(0, i));
+        }
+
+        for _ in 0..MAX_UNDO_ACTIONS {
+            assert!(s.undo().is_some());
+        }
+        assert_eq!(s.undo(), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! BRR waveform preview
+
+// SPDX-FileCopyrightText: © 2025 Marcus Rowe <undisbeliever@gmail.com>
+//
+// SPDX-License-Identifier: MIT
+
+use brr::{decode_brr, BYTES_PER_BRR_BLOCK, SAMPLES_PER_BLOCK};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use fltk::draw;
+use fltk::enums::{Color, FrameType};
+use fltk::prelude::*;
+use fltk::widget::Widget;
+
+const BACKGROUND_COLOR: Color = Color::Background2;
+const WAVEFORM_COLOR: Color = Color::Foreground;
+const ZERO_COLOR: Color = Color::Inactive;
+const LOOP_POINT_COLOR: Color = Color::Inactive;
+
+const FRAME_MARGIN: i32 = 2;
+
+struct DecodedSample {
+    samples: Vec<i16>,
+    loop_point: Option<usize>,
+}
+
+/// Draws the decoded waveform of a compiled BRR sample
+pub struct WaveformPreview {
+    widget: Widget,
+    sample: Rc<RefCell<Option<DecodedSample>>>,
+}
+
+impl WaveformPreview {
+    pub fn new() -> Self {
+        let mut widget = Widget::default();
+        widget.set_tooltip("BRR waveform");
+
+        let sample = Rc::new(RefCell::new(None));
+
+        widget.draw({
+            let sample = sample.clone();
+            move |w| {
+                if let Ok(s) = sample.try_borrow() {
+                    Self::draw(w, s.as_ref());
+                }
+            }
+        });
+
+        Self { widget, sample }
+    }
+
+    pub fn set_brr_data(&mut self, brr_data: &[u8], loop_offset: Option<u16>) {
+        let n_samples = brr_data.len() / BYTES_PER_BRR_BLOCK * SAMPLES_PER_BLOCK;
+
+        *self.sample.borrow_mut() = Some(DecodedSample {
+            samples: decode_brr(brr_data, loop_offset, n_samples),
+            loop_point: loop_offset
+                .map(|lo| usize::from(lo) / BYTES_PER_BRR_BLOCK * SAMPLES_PER_BLOCK),
+        });
+        self.widget.redraw();
+    }
+
+    pub fn clear(&mut self) {
+        *self.sample.borrow_mut() = None;
+        self.widget.redraw();
+    }
+
+    fn draw(w: &Widget, sample: Option<&DecodedSample>) {
+        draw::draw_box(
+            FrameType::DownBox,
+            w.x(),
+            w.y(),
+            w.w(),
+            w.h(),
+            BACKGROUND_COLOR,
+        );
+
+        let (x, y, w, h) = (
+            w.x() + FRAME_MARGIN,
+            w.y() + FRAME_MARGIN,
+            w.w() - FRAME_MARGIN * 2,
+            w.h() - FRAME_MARGIN * 2,
+        );
+
+        if w <= 0 || h <= 0 {
+            return;
+        }
+
+        draw::push_clip(x, y, w, h);
+
+        let center_y = y + h / 2;
+
+        draw::set_draw_color(ZERO_COLOR);
+        draw::draw_line(x, center_y, x + w, center_y);
+
+        if let Some(s) = sample {
+            Self::draw_waveform(s, x, y, w, h);
+        }
+
+        draw::pop_clip();
+    }
+
+    fn draw_waveform(sample: &DecodedSample, x: i32, y: i32, w: i32, h: i32) {
+        let n_samples = sample.samples.len();
+        if n_samples == 0 {
+            return;
+        }
+
+        let to_x = |i: usize| x + (i as i64 * i64::from(w) / n_samples as i64) as i32;
+        let to_y = |s: i16| y + h / 2 - i32::from(s) * (h / 2) / 32768;
+
+        if let Some(lp) = sample.loop_point {
+            if lp < n_samples {
+                let lp_x = to_x(lp);
+                draw::set_draw_color(LOOP_POINT_COLOR);
+                draw::draw_line(lp_x, y, lp_x, y + h);
+            }
+        }
+
+        draw::set_draw_color(WAVEFORM_COLOR);
+
+        if n_samples <= w as usize {
+            for (i, s) in sample.samples.windows(2).enumerate() {
+                draw::draw_line(to_x(i), to_y(s[0]), to_x(i + 1), to_y(s[1]));
+            }
+        } else {
+            // More samples than pixels, draw the range of each column
+            let mut prev = to_y(sample.samples[0]);
+
+            for px in 0..w {
+                let start = px as usize * n_samples / w as usize;
+                let end = ((px as usize + 1) * n_samples / w as usize).max(start + 1);
+                let column = &sample.samples[start..end.min(n_samples)];
+
+                let min = column.iter().copied().min().unwrap_or(0);
+                let max = column.iter().copied().max().unwrap_or(0);
+
+                let (y1, y2) = (to_y(max), to_y(min));
+                draw::draw_line(x + px - 1, prev, x + px, y1);
+                draw::draw_line(x + px, y1, x + px, y2);
+                prev = y2;
+            }
+        }
+    }
+}
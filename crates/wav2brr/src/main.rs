@@ -6,7 +6,10 @@
 
 #![forbid(unsafe_code)]
 
-use brr::{encode_brr, read_mono_pcm_wave_file, BrrFilter, Evaluator};
+use brr::{
+    analyze_brr_quality, encode_brr, find_optimal_loop_point, read_mono_pcm_wave_file, BrrFilter,
+    Evaluator,
+};
 
 use clap::Parser;
 
@@ -57,6 +60,15 @@ struct Args {
     )]
     loop_point: Option<usize>,
 
+    #[arg(
+        long,
+        value_name = "MIN_BLOCKS",
+        help = "Search for the best loop point (minimum loop length in BRR blocks)",
+        conflicts_with = "loop_point",
+        conflicts_with = "dupe_block_hack"
+    )]
+    auto_loop: Option<usize>,
+
     #[arg(
         long,
         value_name = "N",
@@ -93,6 +105,9 @@ struct Args {
 
     #[arg(short = 'i', long, help = "Ignore the gaussian overflow glitch")]
     ignore_gaussian_overflow: bool,
+
+    #[arg(long, help = "Print the signal-to-noise ratio of each BRR block")]
+    quality_report: bool,
 }
 
 const _: () = assert!(matches!(
@@ -100,6 +115,40 @@ const _: () = assert!(matches!(
     Evaluator::SquaredErrorAvoidGaussianOverflow
 ));
 
+// Upper bounds (in dB) of the quality report histogram buckets
+const HISTOGRAM_BUCKETS: [f32; 7] = [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0];
+const HISTOGRAM_WIDTH: usize = 50;
+
+fn print_quality_report(snr: &[f32]) {
+    println!("Block  SNR (dB)");
+    for (i, s) in snr.iter().enumerate() {
+        println!("{:5}  {:8.2}", i, s);
+    }
+    println!();
+
+    let mut counts = [0; HISTOGRAM_BUCKETS.len() + 1];
+    for s in snr {
+        let i = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|b| s < b)
+            .unwrap_or(HISTOGRAM_BUCKETS.len());
+        counts[i] += 1;
+    }
+
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    for (i, &count) in counts.iter().enumerate() {
+        let label = match i {
+            0 => format!("< {}", HISTOGRAM_BUCKETS[0]),
+            i if i == HISTOGRAM_BUCKETS.len() => format!(">= {}", HISTOGRAM_BUCKETS[i - 1]),
+            i => format!("{} .. {}", HISTOGRAM_BUCKETS[i - 1], HISTOGRAM_BUCKETS[i]),
+        };
+        let bar = "#".repeat(count * HISTOGRAM_WIDTH / max_count);
+
+        println!("{:>8} dB {:5} {}", label, count, bar);
+    }
+}
+
 macro_rules! error {
     ($($arg:tt)*) => {{
         eprintln!($($arg)*);
@@ -131,10 +180,21 @@ fn main() {
         }
     };
 
+    let loop_point = match args.auto_loop {
+        Some(min_blocks) => match find_optimal_loop_point(&wav.samples, min_blocks) {
+            Some(lp) => {
+                println!("Loop point: {}", lp);
+                Some(lp)
+            }
+            None => error!("Cannot find a loop point"),
+        },
+        None => args.loop_point,
+    };
+
     let brr = match encode_brr(
         &wav.samples,
         args.evaluator.to_evaluator(),
-        args.loop_point,
+        loop_point,
         args.dupe_block_hack,
         loop_filter,
     ) {
@@ -148,6 +208,10 @@ fn main() {
         }
     }
 
+    if args.quality_report {
+        print_quality_report(&analyze_brr_quality(&wav.samples, &brr));
+    }
+
     let brr = brr.brr_with_loop_header();
 
     match fs::write(&args.output, brr) {